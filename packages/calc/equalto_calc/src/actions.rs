@@ -1,6 +1,9 @@
 use crate::constants::{LAST_COLUMN, LAST_ROW};
+use crate::events::ModelEvent;
 use crate::expressions::parser::stringify::DisplaceData;
+use crate::history::ChangeScope;
 use crate::model::Model;
+use crate::types::ReferenceStyle;
 
 // NOTE: There is a difference with Excel behaviour when deleting cells/rows/columns
 // In Excel if the whole range is deleted then it will substitute for #REF!
@@ -49,15 +52,46 @@ impl Model {
             .cell(source_row, source_column)
             .ok_or("Expected Cell to exist")?;
         let style = source_cell.get_style();
+        if source_cell.get_spill_anchor().is_some() {
+            // Spilled values are not moved, the formula will spill again once evaluated
+            if style != 0 {
+                self.workbook.worksheet_mut(sheet)?.set_cell_style(
+                    target_row,
+                    target_column,
+                    style,
+                );
+            }
+            return self.delete_cell(sheet, source_row, source_column);
+        }
+        let array_formula_area = self
+            .workbook
+            .worksheet(sheet)?
+            .get_array_formula_area(source_row, source_column);
         // FIXME: we need some user_input getter instead of get_text
+        // In A1 style the references keep pointing to the same cells
         let formula_or_value = self
-            .cell_formula(sheet, source_row, source_column)?
+            .cell_formula_in_style(sheet, source_row, source_column, ReferenceStyle::A1)?
             .unwrap_or_else(|| source_cell.get_text(&self.workbook.shared_strings, &self.language));
-        self.set_user_input(sheet, target_row, target_column, formula_or_value);
+        self.set_user_input_in_style(
+            sheet,
+            target_row,
+            target_column,
+            formula_or_value,
+            ReferenceStyle::A1,
+        );
         self.workbook
             .worksheet_mut(sheet)?
             .set_cell_style(target_row, target_column, style);
         self.delete_cell(sheet, source_row, source_column)?;
+        if let Some((last_row, last_column)) = array_formula_area {
+            self.add_array_formula(
+                sheet,
+                target_row,
+                target_column,
+                last_row + target_row - source_row,
+                last_column + target_column - source_column,
+            )?;
+        }
         Ok(())
     }
 
@@ -67,39 +101,47 @@ impl Model {
         column: i32,
         column_count: i32,
     ) -> Result<(), String> {
-        if column_count <= 0 {
-            return Err("Cannot add a negative number of cells :)".to_string());
-        }
-        // check if it is possible:
-        let dimensions = self.workbook.worksheet(sheet)?.dimension();
-        let last_column = dimensions.max_column + column_count;
-        if last_column > LAST_COLUMN {
-            return Err(
-                "Cannot shift cells because that would delete cells at the end of a row"
-                    .to_string(),
-            );
-        }
-        let worksheet = self.workbook.worksheet(sheet)?;
-        let all_rows: Vec<i32> = worksheet.sheet_data.keys().copied().collect();
-        for row in all_rows {
-            let sorted_columns = self.get_columns_for_row(sheet, row, true)?;
-            for col in sorted_columns {
-                if col >= column {
-                    self.move_cell(sheet, row, col, row, col + column_count)?;
-                } else {
-                    // Break because columns are in descending order.
-                    break;
+        self.record_change(ChangeScope::columns(sheet, column), |model| {
+            if column_count <= 0 {
+                return Err("Cannot add a negative number of cells :)".to_string());
+            }
+            // check if it is possible:
+            let dimensions = model.workbook.worksheet(sheet)?.dimension();
+            let last_column = dimensions.max_column + column_count;
+            if last_column > LAST_COLUMN {
+                return Err(
+                    "Cannot shift cells because that would delete cells at the end of a row"
+                        .to_string(),
+                );
+            }
+            let worksheet = model.workbook.worksheet(sheet)?;
+            let all_rows: Vec<i32> = worksheet.sheet_data.keys().copied().collect();
+            for row in all_rows {
+                let sorted_columns = model.get_columns_for_row(sheet, row, true)?;
+                for col in sorted_columns {
+                    if col >= column {
+                        model.move_cell(sheet, row, col, row, col + column_count)?;
+                    } else {
+                        // Break because columns are in descending order.
+                        break;
+                    }
                 }
             }
-        }
 
-        // Update all formulas in the workbook
-        self.displace_cells(&DisplaceData::Column {
+            // Update all formulas in the workbook
+            model.displace_cells(&DisplaceData::Column {
+                sheet,
+                column,
+                delta: column_count,
+            });
+
+            Ok(())
+        })?;
+        self.push_event(ModelEvent::ColumnsInserted {
             sheet,
             column,
-            delta: column_count,
+            count: column_count,
         });
-
         Ok(())
     }
 
@@ -109,143 +151,167 @@ impl Model {
         column: i32,
         column_count: i32,
     ) -> Result<(), String> {
-        if column_count <= 0 {
-            return Err("Please use insert columns instead".to_string());
-        }
+        self.record_change(ChangeScope::columns(sheet, column), |model| {
+            if column_count <= 0 {
+                return Err("Please use insert columns instead".to_string());
+            }
 
-        // Move cells
-        let worksheet = &self.workbook.worksheet(sheet)?;
-        let mut all_rows: Vec<i32> = worksheet.sheet_data.keys().copied().collect();
-        // We do not need to do that, but it is safer to eliminate sources of randomness in the algorithm
-        all_rows.sort_unstable();
+            // Move cells
+            let worksheet = &model.workbook.worksheet(sheet)?;
+            let mut all_rows: Vec<i32> = worksheet.sheet_data.keys().copied().collect();
+            // We do not need to do that, but it is safer to eliminate sources of randomness in the algorithm
+            all_rows.sort_unstable();
 
-        for r in all_rows {
-            let columns: Vec<i32> = self.get_columns_for_row(sheet, r, false)?;
-            for col in columns {
-                if col >= column {
-                    if col >= column + column_count {
-                        self.move_cell(sheet, r, col, r, col - column_count)?;
-                    } else {
-                        self.delete_cell(sheet, r, col)?;
+            for r in all_rows {
+                let columns: Vec<i32> = model.get_columns_for_row(sheet, r, false)?;
+                for col in columns {
+                    if col >= column {
+                        if col >= column + column_count {
+                            model.move_cell(sheet, r, col, r, col - column_count)?;
+                        } else {
+                            model.delete_cell(sheet, r, col)?;
+                        }
                     }
                 }
             }
-        }
-        // Update all formulas in the workbook
+            // Update all formulas in the workbook
+
+            model.displace_cells(&DisplaceData::Column {
+                sheet,
+                column,
+                delta: -column_count,
+            });
 
-        self.displace_cells(&DisplaceData::Column {
+            Ok(())
+        })?;
+        self.push_event(ModelEvent::ColumnsDeleted {
             sheet,
             column,
-            delta: -column_count,
+            count: column_count,
         });
-
         Ok(())
     }
 
     pub fn insert_rows(&mut self, sheet: u32, row: i32, row_count: i32) -> Result<(), String> {
-        if row_count <= 0 {
-            return Err("Cannot add a negative number of cells :)".to_string());
-        }
-        // Check if it is possible:
-        let dimensions = self.workbook.worksheet(sheet)?.dimension();
-        let last_row = dimensions.max_row + row_count;
-        if last_row > LAST_ROW {
-            return Err(
-                "Cannot shift cells because that would delete cells at the end of a column"
-                    .to_string(),
-            );
-        }
+        self.record_change(ChangeScope::rows(sheet, row), |model| {
+            if row_count <= 0 {
+                return Err("Cannot add a negative number of cells :)".to_string());
+            }
+            // Check if it is possible:
+            let dimensions = model.workbook.worksheet(sheet)?.dimension();
+            let last_row = dimensions.max_row + row_count;
+            if last_row > LAST_ROW {
+                return Err(
+                    "Cannot shift cells because that would delete cells at the end of a column"
+                        .to_string(),
+                );
+            }
 
-        // Move cells
-        let worksheet = &self.workbook.worksheet(sheet)?;
-        let mut all_rows: Vec<i32> = worksheet.sheet_data.keys().copied().collect();
-        all_rows.sort_unstable();
-        all_rows.reverse();
-        for r in all_rows {
-            if r >= row {
-                // We do not really need the columns in any order
-                let columns: Vec<i32> = self.get_columns_for_row(sheet, r, false)?;
-                for column in columns {
-                    self.move_cell(sheet, r, column, r + row_count, column)?;
+            // Move cells
+            let worksheet = &model.workbook.worksheet(sheet)?;
+            let mut all_rows: Vec<i32> = worksheet.sheet_data.keys().copied().collect();
+            all_rows.sort_unstable();
+            all_rows.reverse();
+            for r in all_rows {
+                if r >= row {
+                    // We do not really need the columns in any order
+                    let columns: Vec<i32> = model.get_columns_for_row(sheet, r, false)?;
+                    for column in columns {
+                        model.move_cell(sheet, r, column, r + row_count, column)?;
+                    }
+                } else {
+                    // Rows are in descending order
+                    break;
                 }
-            } else {
-                // Rows are in descending order
-                break;
             }
-        }
-        // In the list of rows styles:
-        // * Add all rows above the rows we are inserting unchanged
-        // * Shift the ones below
-        let rows = &self.workbook.worksheets[sheet as usize].rows;
-        let mut new_rows = vec![];
-        for r in rows {
-            if r.r < row {
-                new_rows.push(r.clone());
-            } else if r.r >= row {
-                let mut new_row = r.clone();
-                new_row.r = r.r + row_count;
-                new_rows.push(new_row);
+            // In the list of rows styles:
+            // * Add all rows above the rows we are inserting unchanged
+            // * Shift the ones below
+            let rows = &model.workbook.worksheets[sheet as usize].rows;
+            let mut new_rows = vec![];
+            for r in rows {
+                if r.r < row {
+                    new_rows.push(r.clone());
+                } else if r.r >= row {
+                    let mut new_row = r.clone();
+                    new_row.r = r.r + row_count;
+                    new_rows.push(new_row);
+                }
             }
-        }
-        self.workbook.worksheets[sheet as usize].rows = new_rows;
+            model.workbook.worksheets[sheet as usize].rows = new_rows;
 
-        // Update all formulas in the workbook
-        self.displace_cells(&DisplaceData::Row {
+            // Update all formulas in the workbook
+            model.displace_cells(&DisplaceData::Row {
+                sheet,
+                row,
+                delta: row_count,
+            });
+
+            Ok(())
+        })?;
+        self.push_event(ModelEvent::RowsInserted {
             sheet,
             row,
-            delta: row_count,
+            count: row_count,
         });
-
         Ok(())
     }
 
     pub fn delete_rows(&mut self, sheet: u32, row: i32, row_count: i32) -> Result<(), String> {
-        if row_count <= 0 {
-            return Err("Please use insert rows instead".to_string());
-        }
-        // Move cells
-        let worksheet = &self.workbook.worksheet(sheet)?;
-        let mut all_rows: Vec<i32> = worksheet.sheet_data.keys().copied().collect();
-        all_rows.sort_unstable();
+        self.record_change(ChangeScope::rows(sheet, row), |model| {
+            if row_count <= 0 {
+                return Err("Please use insert rows instead".to_string());
+            }
+            // Move cells
+            let worksheet = &model.workbook.worksheet(sheet)?;
+            let mut all_rows: Vec<i32> = worksheet.sheet_data.keys().copied().collect();
+            all_rows.sort_unstable();
 
-        for r in all_rows {
-            if r >= row {
-                // We do not need ordered, but it is safer to eliminate sources of randomness in the algorithm
-                let columns: Vec<i32> = self.get_columns_for_row(sheet, r, false)?;
-                if r >= row + row_count {
-                    // displace all cells in column
-                    for column in columns {
-                        self.move_cell(sheet, r, column, r - row_count, column)?;
-                    }
-                } else {
-                    // remove all cells in row
-                    // FIXME: We could just remove the entire row in one go
-                    for column in columns {
-                        self.delete_cell(sheet, r, column)?;
+            for r in all_rows {
+                if r >= row {
+                    // We do not need ordered, but it is safer to eliminate sources of randomness in the algorithm
+                    let columns: Vec<i32> = model.get_columns_for_row(sheet, r, false)?;
+                    if r >= row + row_count {
+                        // displace all cells in column
+                        for column in columns {
+                            model.move_cell(sheet, r, column, r - row_count, column)?;
+                        }
+                    } else {
+                        // remove all cells in row
+                        // FIXME: We could just remove the entire row in one go
+                        for column in columns {
+                            model.delete_cell(sheet, r, column)?;
+                        }
                     }
                 }
             }
-        }
-        // In the list of rows styles:
-        // * Add all rows above the rows we are deleting unchanged
-        // * Skip all those we are deleting
-        // * Shift the ones below
-        let rows = &self.workbook.worksheets[sheet as usize].rows;
-        let mut new_rows = vec![];
-        for r in rows {
-            if r.r < row {
-                new_rows.push(r.clone());
-            } else if r.r >= row + row_count {
-                let mut new_row = r.clone();
-                new_row.r = r.r - row_count;
-                new_rows.push(new_row);
+            // In the list of rows styles:
+            // * Add all rows above the rows we are deleting unchanged
+            // * Skip all those we are deleting
+            // * Shift the ones below
+            let rows = &model.workbook.worksheets[sheet as usize].rows;
+            let mut new_rows = vec![];
+            for r in rows {
+                if r.r < row {
+                    new_rows.push(r.clone());
+                } else if r.r >= row + row_count {
+                    let mut new_row = r.clone();
+                    new_row.r = r.r - row_count;
+                    new_rows.push(new_row);
+                }
             }
-        }
-        self.workbook.worksheets[sheet as usize].rows = new_rows;
-        self.displace_cells(&DisplaceData::Row {
+            model.workbook.worksheets[sheet as usize].rows = new_rows;
+            model.displace_cells(&DisplaceData::Row {
+                sheet,
+                row,
+                delta: -row_count,
+            });
+            Ok(())
+        })?;
+        self.push_event(ModelEvent::RowsDeleted {
             sheet,
             row,
-            delta: -row_count,
+            count: row_count,
         });
         Ok(())
     }
@@ -268,24 +334,26 @@ impl Model {
         column: i32,
         delta: i32,
     ) -> Result<(), &'static str> {
-        // Check boundaries
-        let target_column = column + delta;
-        if !(1..=LAST_COLUMN).contains(&target_column) {
-            return Err("Target column out of boundaries");
-        }
-        if !(1..=LAST_COLUMN).contains(&column) {
-            return Err("Initial column out of boundaries");
-        }
+        self.record_change(ChangeScope::formulas(), |model| {
+            // Check boundaries
+            let target_column = column + delta;
+            if !(1..=LAST_COLUMN).contains(&target_column) {
+                return Err("Target column out of boundaries");
+            }
+            if !(1..=LAST_COLUMN).contains(&column) {
+                return Err("Initial column out of boundaries");
+            }
 
-        // TODO: Add the actual displacement of data and styles
+            // TODO: Add the actual displacement of data and styles
 
-        // Update all formulas in the workbook
-        self.displace_cells(&DisplaceData::ColumnMove {
-            sheet,
-            column,
-            delta,
-        });
+            // Update all formulas in the workbook
+            model.displace_cells(&DisplaceData::ColumnMove {
+                sheet,
+                column,
+                delta,
+            });
 
-        Ok(())
+            Ok(())
+        })
     }
 }
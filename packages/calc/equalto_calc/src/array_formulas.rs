@@ -0,0 +1,134 @@
+use crate::{
+    calc_result::{CalcResult, CellReference, Range},
+    constants::{LAST_COLUMN, LAST_ROW},
+    expressions::{token::Error, utils::number_to_column},
+    history::ChangeScope,
+    model::Model,
+};
+
+/// Returns the A1 range of a legacy array formula, like "B1:B3" or "B1" for a single cell
+fn get_array_formula_range(row: i32, column: i32, last_row: i32, last_column: i32) -> String {
+    let first = format!("{}{}", number_to_column(column).unwrap_or_default(), row);
+    if row == last_row && column == last_column {
+        first
+    } else {
+        let last_column = number_to_column(last_column).unwrap_or_default();
+        format!("{first}:{last_column}{last_row}")
+    }
+}
+
+impl Model {
+    /// Returns the area of the legacy array formula in `cell`, if any
+    pub(crate) fn get_array_formula_area(&self, cell: CellReference) -> Option<Range> {
+        let (row, column) = self
+            .workbook
+            .worksheet(cell.sheet)
+            .ok()?
+            .get_array_formula_area(cell.row, cell.column)?;
+        Some(Range {
+            left: cell,
+            right: CellReference {
+                sheet: cell.sheet,
+                row,
+                column,
+            },
+        })
+    }
+
+    /// Adds a legacy array formula over (`row`, `column`) to (`last_row`, `last_column`).
+    /// The formula must already be in the top left cell.
+    pub(crate) fn add_array_formula(
+        &mut self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        last_row: i32,
+        last_column: i32,
+    ) -> Result<(), String> {
+        let range = get_array_formula_range(row, column, last_row, last_column);
+        self.workbook
+            .worksheet_mut(sheet)?
+            .array_formulas
+            .push(range);
+        self.dependency_graph.mark_dirty(sheet, row, column);
+        Ok(())
+    }
+
+    /// Removes the legacy array formula in (`row`, `column`), if any.
+    /// The values it wrote in the rest of its area are removed the next time the model is evaluated.
+    pub(crate) fn remove_array_formula(&mut self, sheet: u32, row: i32, column: i32) {
+        if let Ok(worksheet) = self.workbook.worksheet_mut(sheet) {
+            if let Some((last_row, last_column)) = worksheet.get_array_formula_area(row, column) {
+                let range = get_array_formula_range(row, column, last_row, last_column);
+                worksheet.array_formulas.retain(|r| r != &range);
+            }
+        }
+    }
+
+    /// Fills the area of the legacy array formula in `anchor` with `result`, its value.
+    /// Results smaller than the area are broadcast and the extra elements are #N/A.
+    /// Returns the value of the anchor cell.
+    pub(crate) fn fill_array_formula(
+        &mut self,
+        anchor: CellReference,
+        area: Range,
+        result: CalcResult,
+    ) -> CalcResult {
+        let mut old_cells = self.clear_spill(anchor);
+        let array = match result {
+            CalcResult::Array(array) if !array.is_empty() && !array[0].is_empty() => array,
+            CalcResult::Array(_) => vec![vec![CalcResult::new_error(
+                Error::CALC,
+                anchor,
+                "Empty array".to_string(),
+            )]],
+            value => vec![vec![value]],
+        };
+        self.write_array(anchor, &array, &area, &mut old_cells);
+        self.dependency_graph
+            .set_spill_area((anchor.sheet, anchor.row, anchor.column), Some(area));
+        for (row, column) in old_cells.into_keys() {
+            self.dependency_graph
+                .mark_spilled(anchor.sheet, row, column);
+        }
+        array[0][0].clone()
+    }
+
+    /// Sets a legacy array formula, like the ones entered with Ctrl+Shift+Enter in Excel.
+    /// The formula is evaluated as an array and each cell in the area
+    /// from (`row`, `column`) to (`last_row`, `last_column`) holds an element of the result.
+    /// Any other content in the area is removed.
+    pub fn set_user_array_formula(
+        &mut self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        last_row: i32,
+        last_column: i32,
+        formula: String,
+    ) -> Result<(), String> {
+        self.record_change(
+            ChangeScope::area(sheet, row, column, last_row, last_column),
+            |model| {
+                if !formula.starts_with('=') {
+                    return Err(format!("\"{formula}\" is not a valid formula"));
+                }
+                if row < 1 || column < 1 || last_row > LAST_ROW || last_column > LAST_COLUMN {
+                    return Err("Array formula area out of boundaries".to_string());
+                }
+                if last_row < row || last_column < column {
+                    return Err("Invalid array formula area".to_string());
+                }
+                for r in row..=last_row {
+                    for c in column..=last_column {
+                        if !model.is_empty_cell(sheet, r, c)? {
+                            model.set_cell_empty(sheet, r, c)?;
+                        }
+                    }
+                }
+                model.set_user_input(sheet, row, column, formula);
+                model.add_array_formula(sheet, row, column, last_row, last_column)
+            },
+        )
+    }
+}
@@ -0,0 +1,356 @@
+use crate::{
+    calc_result::{CalcResult, CellReference, Range},
+    constants::{LAST_COLUMN, LAST_ROW},
+    expressions::{
+        parser::Node,
+        token::{Error, OpCompare, OpProduct, OpSum, OpUnary},
+    },
+    functions::util::compare_values,
+    model::Model,
+};
+
+/// A binary operator of the formula language
+#[derive(Clone)]
+pub(crate) enum BinaryOperator {
+    Sum(OpSum),
+    Product(OpProduct),
+    Power,
+    Concatenate,
+    Compare(OpCompare),
+}
+
+/// Number of rows and columns of a non empty array
+fn array_size(array: &[Vec<CalcResult>]) -> (usize, usize) {
+    (array.len(), array.first().map_or(0, |row| row.len()))
+}
+
+/// Returns the element (`row`, `column`) of `array` following Excel's broadcasting rules:
+/// a dimension of size one is repeated and an index out of bounds is an #N/A error.
+pub(crate) fn broadcast_element(
+    array: &[Vec<CalcResult>],
+    row: usize,
+    column: usize,
+    cell: CellReference,
+) -> CalcResult {
+    let (rows, columns) = array_size(array);
+    let row = if rows == 1 { 0 } else { row };
+    let column = if columns == 1 { 0 } else { column };
+    match array.get(row).and_then(|r| r.get(column)) {
+        Some(value) => value.clone(),
+        None => CalcResult::new_error(Error::NA, cell, "Array index out of bounds".to_string()),
+    }
+}
+
+impl Model {
+    /// Returns the values in `range` as an array.
+    /// Full rows and full columns are trimmed to the dimension of the sheet.
+    pub(crate) fn range_to_array(&mut self, range: &Range) -> Vec<Vec<CalcResult>> {
+        let Range { left, right } = range;
+        let mut row2 = right.row;
+        let mut column2 = right.column;
+        if let Ok(dimension) = self.get_sheet_dimension(left.sheet) {
+            if left.row == 1 && right.row == LAST_ROW {
+                row2 = dimension.max_row;
+            }
+            if left.column == 1 && right.column == LAST_COLUMN {
+                column2 = dimension.max_column;
+            }
+        }
+        let mut array = Vec::new();
+        for row in left.row..=row2 {
+            let mut values = Vec::new();
+            for column in left.column..=column2 {
+                values.push(self.evaluate_cell(CellReference {
+                    sheet: left.sheet,
+                    row,
+                    column,
+                }));
+            }
+            array.push(values);
+        }
+        array
+    }
+
+    /// Evaluates `node` in an array context: references evaluate to arrays of values
+    /// and operators are applied element-wise.
+    pub(crate) fn evaluate_node_as_array(
+        &mut self,
+        node: &Node,
+        cell: CellReference,
+    ) -> CalcResult {
+        let (operator, left, right) = match node {
+            Node::OpSumKind { kind, left, right } => {
+                (BinaryOperator::Sum(kind.clone()), left, right)
+            }
+            Node::OpProductKind { kind, left, right } => {
+                (BinaryOperator::Product(kind.clone()), left, right)
+            }
+            Node::OpPowerKind { left, right } => (BinaryOperator::Power, left, right),
+            Node::OpConcatenateKind { left, right } => (BinaryOperator::Concatenate, left, right),
+            Node::CompareKind { kind, left, right } => {
+                (BinaryOperator::Compare(kind.clone()), left, right)
+            }
+            Node::UnaryKind { kind, right } => {
+                let value = self.evaluate_node_as_array(right, cell);
+                let result = self.apply_unary_operator(kind, value, cell);
+                self.record_evaluation_step(node, &result);
+                return result;
+            }
+            _ => {
+                return match self.evaluate_node_in_context(node, cell) {
+                    CalcResult::Range { left, right } => {
+                        if left.sheet != right.sheet {
+                            return CalcResult::new_error(
+                                Error::VALUE,
+                                cell,
+                                "Ranges are in different sheets".to_string(),
+                            );
+                        }
+                        CalcResult::Array(self.range_to_array(&Range { left, right }))
+                    }
+                    result => result,
+                };
+            }
+        };
+        let left = self.evaluate_node_as_array(left, cell);
+        let right = self.evaluate_node_as_array(right, cell);
+        let result = self.apply_binary_operator(&operator, left, right, cell);
+        self.record_evaluation_step(node, &result);
+        result
+    }
+
+    /// Evaluates an operand of an operator.
+    /// Ranges are arrays of values, so the operator is applied element-wise like in Excel 365.
+    pub(crate) fn evaluate_operand(&mut self, node: &Node, cell: CellReference) -> CalcResult {
+        self.evaluate_node_as_array(node, cell)
+    }
+
+    /// Evaluates the formula of `cell` to the value it spills: a range in a single sheet
+    /// is the array of its values.
+    pub(crate) fn evaluate_spilling_formula(
+        &mut self,
+        node: &Node,
+        cell: CellReference,
+    ) -> CalcResult {
+        match self.evaluate_node_in_context(node, cell) {
+            CalcResult::Range { left, right } if left.sheet == right.sheet => {
+                CalcResult::Array(self.range_to_array(&Range { left, right }))
+            }
+            result => result,
+        }
+    }
+
+    /// Evaluates `node` as an array. Single values are returned as a 1x1 array
+    pub(crate) fn get_array(
+        &mut self,
+        node: &Node,
+        cell: CellReference,
+    ) -> Result<Vec<Vec<CalcResult>>, CalcResult> {
+        match self.evaluate_node_as_array(node, cell) {
+            CalcResult::Array(array) => Ok(array),
+            error @ CalcResult::Error { .. } => Err(error),
+            CalcResult::EmptyArg => Err(CalcResult::new_error(
+                Error::VALUE,
+                cell,
+                "Expecting an array".to_string(),
+            )),
+            value => Ok(vec![vec![value]]),
+        }
+    }
+
+    /// Applies `operator` to `left` and `right`.
+    /// If any of the operands is an array the operator is applied element-wise.
+    pub(crate) fn apply_binary_operator(
+        &mut self,
+        operator: &BinaryOperator,
+        left: CalcResult,
+        right: CalcResult,
+        cell: CellReference,
+    ) -> CalcResult {
+        if !matches!(left, CalcResult::Array(_)) && !matches!(right, CalcResult::Array(_)) {
+            return self.apply_scalar_operator(operator, left, right, cell);
+        }
+        let left = match self.as_array(left, cell) {
+            Ok(array) => array,
+            Err(error) => return error,
+        };
+        let right = match self.as_array(right, cell) {
+            Ok(array) => array,
+            Err(error) => return error,
+        };
+        let (left_rows, left_columns) = array_size(&left);
+        let (right_rows, right_columns) = array_size(&right);
+        let rows = left_rows.max(right_rows);
+        let columns = left_columns.max(right_columns);
+        let mut result = Vec::with_capacity(rows);
+        for row in 0..rows {
+            let mut values = Vec::with_capacity(columns);
+            for column in 0..columns {
+                let l = broadcast_element(&left, row, column, cell);
+                let r = broadcast_element(&right, row, column, cell);
+                values.push(self.apply_scalar_operator(operator, l, r, cell));
+            }
+            result.push(values);
+        }
+        CalcResult::Array(result)
+    }
+
+    /// Applies a unary operator, element-wise if `value` is an array
+    pub(crate) fn apply_unary_operator(
+        &mut self,
+        operator: &OpUnary,
+        value: CalcResult,
+        cell: CellReference,
+    ) -> CalcResult {
+        if let CalcResult::Array(array) = value {
+            let mut result = Vec::with_capacity(array.len());
+            for row in array {
+                let mut values = Vec::with_capacity(row.len());
+                for value in row {
+                    values.push(self.apply_unary_operator(operator, value, cell));
+                }
+                result.push(values);
+            }
+            return CalcResult::Array(result);
+        }
+        let value = match self.cast_to_number(value, cell) {
+            Ok(f) => f,
+            Err(s) => return s,
+        };
+        match operator {
+            OpUnary::Minus => CalcResult::Number(-value),
+            OpUnary::Percentage => CalcResult::Number(value / 100.0),
+        }
+    }
+
+    /// Returns an array with the elements of `value_if_true` where `condition` is TRUE
+    /// and the elements of `value_if_false` elsewhere (used in IF)
+    pub(crate) fn choose_elements(
+        &mut self,
+        condition: Vec<Vec<CalcResult>>,
+        value_if_true: CalcResult,
+        value_if_false: CalcResult,
+        cell: CellReference,
+    ) -> CalcResult {
+        let value_if_true = match self.as_array(value_if_true, cell) {
+            Ok(array) => array,
+            Err(error) => return error,
+        };
+        let value_if_false = match self.as_array(value_if_false, cell) {
+            Ok(array) => array,
+            Err(error) => return error,
+        };
+        let sizes = [
+            array_size(&condition),
+            array_size(&value_if_true),
+            array_size(&value_if_false),
+        ];
+        let rows = sizes.iter().map(|size| size.0).max().unwrap_or(0);
+        let columns = sizes.iter().map(|size| size.1).max().unwrap_or(0);
+        let mut result = Vec::with_capacity(rows);
+        for row in 0..rows {
+            let mut values = Vec::with_capacity(columns);
+            for column in 0..columns {
+                let condition = broadcast_element(&condition, row, column, cell);
+                let value = match self.cast_to_bool(condition, cell) {
+                    Ok(true) => broadcast_element(&value_if_true, row, column, cell),
+                    Ok(false) => broadcast_element(&value_if_false, row, column, cell),
+                    Err(error) => error,
+                };
+                values.push(value);
+            }
+            result.push(values);
+        }
+        CalcResult::Array(result)
+    }
+
+    /// Converts an operand of an array operation into an array
+    pub(crate) fn as_array(
+        &mut self,
+        value: CalcResult,
+        cell: CellReference,
+    ) -> Result<Vec<Vec<CalcResult>>, CalcResult> {
+        match value {
+            CalcResult::Array(array) => Ok(array),
+            CalcResult::Range { left, right } => {
+                if left.sheet != right.sheet {
+                    return Err(CalcResult::new_error(
+                        Error::VALUE,
+                        cell,
+                        "Ranges are in different sheets".to_string(),
+                    ));
+                }
+                Ok(self.range_to_array(&Range { left, right }))
+            }
+            value => Ok(vec![vec![value]]),
+        }
+    }
+
+    fn apply_scalar_operator(
+        &mut self,
+        operator: &BinaryOperator,
+        left: CalcResult,
+        right: CalcResult,
+        cell: CellReference,
+    ) -> CalcResult {
+        match operator {
+            BinaryOperator::Sum(_) | BinaryOperator::Product(_) | BinaryOperator::Power => {
+                let l = match self.cast_to_number(left, cell) {
+                    Ok(f) => f,
+                    Err(s) => return s,
+                };
+                let r = match self.cast_to_number(right, cell) {
+                    Ok(f) => f,
+                    Err(s) => return s,
+                };
+                let result = match operator {
+                    BinaryOperator::Sum(OpSum::Add) => l + r,
+                    BinaryOperator::Sum(OpSum::Minus) => l - r,
+                    BinaryOperator::Product(OpProduct::Times) => l * r,
+                    BinaryOperator::Product(OpProduct::Divide) => {
+                        if r == 0.0 {
+                            return CalcResult::new_error(
+                                Error::DIV,
+                                cell,
+                                "Divide by Zero".to_string(),
+                            );
+                        }
+                        l / r
+                    }
+                    // Deal with errors properly
+                    _ => l.powf(r),
+                };
+                CalcResult::Number(result)
+            }
+            BinaryOperator::Concatenate => {
+                let l = match self.cast_to_string(left, cell) {
+                    Ok(f) => f,
+                    Err(s) => return s,
+                };
+                let r = match self.cast_to_string(right, cell) {
+                    Ok(f) => f,
+                    Err(s) => return s,
+                };
+                CalcResult::String(format!("{}{}", l, r))
+            }
+            BinaryOperator::Compare(kind) => {
+                if left.is_error() {
+                    return left;
+                }
+                if right.is_error() {
+                    return right;
+                }
+                let compare = compare_values(&left, &right);
+                let result = match kind {
+                    OpCompare::Equal => compare == 0,
+                    OpCompare::LessThan => compare == -1,
+                    OpCompare::GreaterThan => compare == 1,
+                    OpCompare::LessOrEqualThan => compare < 1,
+                    OpCompare::GreaterOrEqualThan => compare > -1,
+                    OpCompare::NonEqual => compare != 0,
+                };
+                CalcResult::Boolean(result)
+            }
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use std::cmp::Ordering;
 
-use crate::expressions::token::Error;
+use crate::expressions::{parser::Node, token::Error};
 
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 pub struct CellReference {
@@ -9,7 +9,7 @@ pub struct CellReference {
     pub row: i32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Range {
     pub left: CellReference,
     pub right: CellReference,
@@ -29,8 +29,24 @@ pub(crate) enum CalcResult {
         left: CellReference,
         right: CellReference,
     },
+    /// A reference with several ranges, made with the union operator: (A1:A3,C1:C3)
+    Areas(Vec<Range>),
+    /// A list of rows of the same length. Elements are never ranges or arrays
+    Array(Vec<Vec<CalcResult>>),
     EmptyCell,
     EmptyArg,
+    /// A function created with LAMBDA
+    Lambda(Box<Lambda>),
+}
+
+/// A function created with LAMBDA(parameter1, ..., body)
+#[derive(Clone)]
+pub(crate) struct Lambda {
+    /// The names of the parameters, in lower case
+    pub(crate) parameters: Vec<String>,
+    pub(crate) body: Node,
+    /// The names bound by LET when the function was created and their values
+    pub(crate) captured: Vec<(String, CalcResult)>,
 }
 
 impl CalcResult {
@@ -48,6 +64,22 @@ impl CalcResult {
             message: "Wrong number of arguments".to_string(),
         }
     }
+    /// The error of a function created with LAMBDA used as a value
+    pub fn new_lambda_error(origin: CellReference) -> CalcResult {
+        CalcResult::Error {
+            error: Error::CALC,
+            origin,
+            message: "Function used as a value".to_string(),
+        }
+    }
+    /// The error of a reference with several areas used as a value
+    pub fn new_areas_error(origin: CellReference) -> CalcResult {
+        CalcResult::Error {
+            error: Error::VALUE,
+            origin,
+            message: "Reference with several areas used as a value".to_string(),
+        }
+    }
     pub fn is_error(&self) -> bool {
         matches!(self, CalcResult::Error { .. })
     }
@@ -15,7 +15,7 @@ impl Model {
         self.cast_to_number(result, cell)
     }
 
-    fn cast_to_number(
+    pub(crate) fn cast_to_number(
         &mut self,
         result: CalcResult,
         cell: CellReference,
@@ -52,6 +52,20 @@ impl Model {
                     }),
                 }
             }
+            CalcResult::Lambda(_) => Err(CalcResult::new_lambda_error(cell)),
+            CalcResult::Areas(_) => Err(CalcResult::new_areas_error(cell)),
+            CalcResult::Array(array) => match array
+                .into_iter()
+                .next()
+                .and_then(|row| row.into_iter().next())
+            {
+                Some(value) => self.cast_to_number(value, cell),
+                None => Err(CalcResult::new_error(
+                    Error::VALUE,
+                    cell,
+                    "Empty array".to_string(),
+                )),
+            },
         }
     }
 
@@ -112,6 +126,20 @@ impl Model {
                     }),
                 }
             }
+            CalcResult::Lambda(_) => Err(CalcResult::new_lambda_error(cell)),
+            CalcResult::Areas(_) => Err(CalcResult::new_areas_error(cell)),
+            CalcResult::Array(array) => match array
+                .into_iter()
+                .next()
+                .and_then(|row| row.into_iter().next())
+            {
+                Some(value) => self.cast_to_string(value, cell),
+                None => Err(CalcResult::new_error(
+                    Error::VALUE,
+                    cell,
+                    "Empty array".to_string(),
+                )),
+            },
         }
     }
 
@@ -124,7 +152,7 @@ impl Model {
         self.cast_to_bool(result, cell)
     }
 
-    fn cast_to_bool(
+    pub(crate) fn cast_to_bool(
         &mut self,
         result: CalcResult,
         cell: CellReference,
@@ -164,6 +192,20 @@ impl Model {
                     }),
                 }
             }
+            CalcResult::Lambda(_) => Err(CalcResult::new_lambda_error(cell)),
+            CalcResult::Areas(_) => Err(CalcResult::new_areas_error(cell)),
+            CalcResult::Array(array) => match array
+                .into_iter()
+                .next()
+                .and_then(|row| row.into_iter().next())
+            {
+                Some(value) => self.cast_to_bool(value, cell),
+                None => Err(CalcResult::new_error(
+                    Error::VALUE,
+                    cell,
+                    "Empty array".to_string(),
+                )),
+            },
         }
     }
 
@@ -194,6 +236,11 @@ impl Model {
 
                 Ok(Range { left, right: left })
             }
+            Node::TableReferenceKind {
+                table_name,
+                specifier,
+                table_reference,
+            } => self.get_table_range(table_name, specifier, table_reference, cell),
             _ => {
                 let value = self.evaluate_node_in_context(node, cell);
                 if value.is_error() {
@@ -201,6 +248,12 @@ impl Model {
                 }
                 if let CalcResult::Range { left, right } = value {
                     Ok(Range { left, right })
+                } else if let CalcResult::Areas(_) = value {
+                    Err(CalcResult::Error {
+                        error: Error::REF,
+                        origin: cell,
+                        message: "Reference with several areas".to_string(),
+                    })
                 } else {
                     Err(CalcResult::Error {
                         error: Error::VALUE,
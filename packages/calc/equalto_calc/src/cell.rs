@@ -91,6 +91,17 @@ impl Cell {
         self.get_formula().is_some()
     }
 
+    /// Returns the (row, column) of the formula if the value was spilled by a dynamic array formula
+    pub fn get_spill_anchor(&self) -> Option<(i32, i32)> {
+        match self {
+            Cell::SpillBoolean { r, c, .. } => Some((*r, *c)),
+            Cell::SpillNumber { r, c, .. } => Some((*r, *c)),
+            Cell::SpillString { r, c, .. } => Some((*r, *c)),
+            Cell::SpillError { r, c, .. } => Some((*r, *c)),
+            _ => None,
+        }
+    }
+
     pub fn set_style(&mut self, style: i32) {
         match self {
             Cell::EmptyCell { s, .. } => *s = style,
@@ -103,6 +114,10 @@ impl Cell {
             Cell::CellFormulaNumber { s, .. } => *s = style,
             Cell::CellFormulaString { s, .. } => *s = style,
             Cell::CellFormulaError { s, .. } => *s = style,
+            Cell::SpillBoolean { s, .. } => *s = style,
+            Cell::SpillNumber { s, .. } => *s = style,
+            Cell::SpillString { s, .. } => *s = style,
+            Cell::SpillError { s, .. } => *s = style,
         };
     }
 
@@ -118,6 +133,10 @@ impl Cell {
             Cell::CellFormulaNumber { s, .. } => *s,
             Cell::CellFormulaString { s, .. } => *s,
             Cell::CellFormulaError { s, .. } => *s,
+            Cell::SpillBoolean { s, .. } => *s,
+            Cell::SpillNumber { s, .. } => *s,
+            Cell::SpillString { s, .. } => *s,
+            Cell::SpillError { s, .. } => *s,
         }
     }
 
@@ -134,6 +153,10 @@ impl Cell {
             Cell::CellFormulaNumber { .. } => CellType::Number,
             Cell::CellFormulaString { .. } => CellType::Text,
             Cell::CellFormulaError { .. } => CellType::ErrorValue,
+            Cell::SpillBoolean { .. } => CellType::LogicalValue,
+            Cell::SpillNumber { .. } => CellType::Number,
+            Cell::SpillString { .. } => CellType::Text,
+            Cell::SpillError { .. } => CellType::ErrorValue,
         }
     }
 
@@ -171,6 +194,13 @@ impl Cell {
                 let v = ei.to_localized_error_string(language);
                 CellValue::String(v)
             }
+            Cell::SpillBoolean { v, .. } => CellValue::Boolean(*v),
+            Cell::SpillNumber { v, .. } => CellValue::Number(*v),
+            Cell::SpillString { v, .. } => CellValue::String(v.clone()),
+            Cell::SpillError { ei, .. } => {
+                let v = ei.to_localized_error_string(language);
+                CellValue::String(v)
+            }
         }
     }
 
@@ -0,0 +1,210 @@
+use crate::{
+    calc_result::{CalcResult, CellReference},
+    expressions::{token::Error, utils::parse_reference_a1},
+    model::Model,
+    spill::new_spill_cell,
+    types::{Cell, DataTable},
+};
+
+/// A result cell of a data table
+struct DataTableCell {
+    /// The cell holding the result
+    cell: CellReference,
+    /// The formula evaluated to compute the result
+    formula: CellReference,
+    /// The input cells and the cells with the values substituted into them
+    inputs: Vec<(CellReference, CellReference)>,
+}
+
+fn parse_cell(sheet: u32, text: &str) -> Option<CellReference> {
+    let reference = parse_reference_a1(text)?;
+    Some(CellReference {
+        sheet,
+        row: reference.row,
+        column: reference.column,
+    })
+}
+
+/// Returns the result cells of `table`, or None if the table is not valid.
+/// In a two-variable table the formula is on the top left corner and the values for the row
+/// and column input cells (`r1` and `r2`) are on the first row and column.
+/// In a one-variable table the values are on the first column (or the first row if `dtr`)
+/// and the formulas on the row above (or the column to the left).
+fn get_data_table_cells(sheet: u32, table: &DataTable) -> Option<Vec<DataTableCell>> {
+    let (first, last) = table
+        .range
+        .split_once(':')
+        .unwrap_or((&table.range, &table.range));
+    let first = parse_cell(sheet, first)?;
+    let last = parse_cell(sheet, last)?;
+    if first.row < 2 || first.column < 2 || last.row < first.row || last.column < first.column {
+        return None;
+    }
+    let r1 = parse_cell(sheet, &table.r1)?;
+    let r2 = match &table.r2 {
+        Some(r2) => Some(parse_cell(sheet, r2)?),
+        None => None,
+    };
+    let mut cells = Vec::new();
+    for row in first.row..=last.row {
+        for column in first.column..=last.column {
+            let row_value = CellReference {
+                sheet,
+                row: first.row - 1,
+                column,
+            };
+            let column_value = CellReference {
+                sheet,
+                row,
+                column: first.column - 1,
+            };
+            let (formula, inputs) = if table.dt2d {
+                let formula = CellReference {
+                    sheet,
+                    row: first.row - 1,
+                    column: first.column - 1,
+                };
+                (formula, vec![(r1, row_value), (r2?, column_value)])
+            } else if table.dtr {
+                (column_value, vec![(r1, row_value)])
+            } else {
+                (row_value, vec![(r1, column_value)])
+            };
+            cells.push(DataTableCell {
+                cell: CellReference { sheet, row, column },
+                formula,
+                inputs,
+            });
+        }
+    }
+    Some(cells)
+}
+
+impl Model {
+    /// Recomputes the results of all the data tables in the workbook.
+    /// Results that change are flagged so that the cells reading them are recomputed.
+    pub(crate) fn evaluate_data_tables(&mut self) {
+        for sheet in 0..self.workbook.worksheets.len() {
+            let data_tables = self.workbook.worksheets[sheet].data_tables.clone();
+            for table in &data_tables {
+                if let Some(cells) = get_data_table_cells(sheet as u32, table) {
+                    self.evaluate_data_table(&cells);
+                }
+            }
+        }
+    }
+
+    /// Returns the value stored in `cell` after evaluating it
+    fn get_evaluated_value(&mut self, cell: CellReference) -> CalcResult {
+        self.evaluate_cell(cell);
+        match self.workbook.worksheets[cell.sheet as usize].cell(cell.row, cell.column) {
+            Some(c) => self.get_cell_value(c, cell),
+            None => CalcResult::EmptyCell,
+        }
+    }
+
+    fn evaluate_data_table(&mut self, cells: &[DataTableCell]) {
+        let mut inputs: Vec<CellReference> = Vec::new();
+        for (input, _) in cells.iter().flat_map(|cell| cell.inputs.iter()) {
+            if !inputs.contains(input) {
+                inputs.push(*input);
+            }
+        }
+        // The values substituted are read before any input cell is modified
+        let values: Vec<Vec<CalcResult>> = cells
+            .iter()
+            .map(|cell| {
+                cell.inputs
+                    .iter()
+                    .map(|(_, value)| self.get_evaluated_value(*value))
+                    .collect()
+            })
+            .collect();
+        let original_inputs: Vec<Option<Cell>> = inputs
+            .iter()
+            .map(|input| {
+                self.workbook.worksheets[input.sheet as usize]
+                    .cell(input.row, input.column)
+                    .cloned()
+            })
+            .collect();
+        let dependents = self.get_dependent_cells(&inputs);
+
+        let mut results = Vec::with_capacity(cells.len());
+        for (cell, values) in cells.iter().zip(values) {
+            for ((input, _), value) in cell.inputs.iter().zip(values) {
+                // A value with no formula behind it, read as is by the formulas
+                let worksheet = &mut self.workbook.worksheets[input.sheet as usize];
+                let s = worksheet.get_style(input.row, input.column);
+                let input_cell = new_spill_cell(&value, input.row, input.column, s);
+                worksheet.update_cell(input.row, input.column, input_cell);
+            }
+            for dependent in &dependents {
+                self.cells
+                    .remove(&(dependent.sheet, dependent.row, dependent.column));
+            }
+            results.push(self.get_evaluated_value(cell.formula));
+        }
+
+        for (input, original) in inputs.iter().zip(original_inputs) {
+            let worksheet = &mut self.workbook.worksheets[input.sheet as usize];
+            match original {
+                Some(original) => worksheet.update_cell(input.row, input.column, original),
+                None => {
+                    if let Some(row_data) = worksheet.sheet_data.get_mut(&input.row) {
+                        row_data.remove(&input.column);
+                    }
+                }
+            }
+        }
+        for dependent in &dependents {
+            self.cells
+                .remove(&(dependent.sheet, dependent.row, dependent.column));
+        }
+        for dependent in dependents {
+            self.evaluate_cell(dependent);
+        }
+
+        for (cell, result) in cells.iter().zip(results) {
+            self.set_data_table_result(cell.cell, result);
+        }
+    }
+
+    /// Writes `result` in a result cell of a data table, keeping its style
+    fn set_data_table_result(&mut self, cell: CellReference, result: CalcResult) {
+        let CellReference { sheet, row, column } = cell;
+        let worksheet = &mut self.workbook.worksheets[sheet as usize];
+        let s = worksheet.get_style(row, column);
+        let new_cell = match result {
+            CalcResult::Number(v) if v.is_finite() => Cell::NumberCell { v, s },
+            CalcResult::Number(_) => Cell::ErrorCell { ei: Error::NUM, s },
+            CalcResult::Boolean(v) => Cell::BooleanCell { v, s },
+            CalcResult::String(v) => {
+                let shared_strings = &mut self.workbook.shared_strings;
+                let si = match shared_strings.iter().position(|r| r == &v) {
+                    Some(index) => index,
+                    None => {
+                        shared_strings.push(v);
+                        shared_strings.len() - 1
+                    }
+                };
+                Cell::SharedString { si: si as i32, s }
+            }
+            CalcResult::Error { error, .. } => Cell::ErrorCell { ei: error, s },
+            CalcResult::EmptyCell | CalcResult::EmptyArg => Cell::NumberCell { v: 0.0, s },
+            CalcResult::Range { .. }
+            | CalcResult::Array(_)
+            | CalcResult::Lambda(_)
+            | CalcResult::Areas(_) => Cell::ErrorCell {
+                ei: Error::VALUE,
+                s,
+            },
+        };
+        if worksheet.cell(row, column) != Some(&new_cell) {
+            self.touch_cell(sheet, row, column);
+            let worksheet = &mut self.workbook.worksheets[sheet as usize];
+            worksheet.update_cell(row, column, new_cell);
+            self.dependency_graph.mark_spilled(sheet, row, column);
+        }
+    }
+}
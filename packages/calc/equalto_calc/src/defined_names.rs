@@ -0,0 +1,215 @@
+use crate::{
+    expressions::{
+        lexer::LexerMode,
+        parser::{
+            stringify::{rename_defined_name_in_node, to_rc_format, to_string},
+            Node,
+        },
+        types::CellReferenceRC,
+        utils::is_valid_identifier,
+    },
+    history::ChangeScope,
+    model::Model,
+    types::DefinedName,
+};
+
+impl Model {
+    /// Returns the sheet_id of the sheet with index `scope`, None for global names
+    fn get_scope_sheet_id(&self, scope: Option<u32>) -> Result<Option<u32>, String> {
+        match scope {
+            Some(sheet) => Ok(Some(self.workbook.worksheet(sheet)?.sheet_id)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the sheet index of a defined name, None for global names
+    fn get_defined_name_scope(&self, defined_name: &DefinedName) -> Option<u32> {
+        defined_name
+            .sheet_id
+            .and_then(|sheet_id| self.get_sheet_index_by_sheet_id(sheet_id))
+    }
+
+    /// Returns the position of the defined name `name` with `scope` in the workbook
+    fn get_defined_name_index(&self, name: &str, scope: Option<u32>) -> Option<usize> {
+        let name = name.to_lowercase();
+        self.workbook.defined_names.iter().position(|defined_name| {
+            defined_name.name.to_lowercase() == name
+                && self.get_defined_name_scope(defined_name) == scope
+        })
+    }
+
+    /// Checks the name, scope and formula of a defined name.
+    /// Returns the formula without the leading '='
+    fn validate_defined_name(
+        &mut self,
+        name: &str,
+        scope: Option<u32>,
+        formula: &str,
+    ) -> Result<String, String> {
+        if !is_valid_identifier(name) {
+            return Err(format!("Invalid defined name: '{}'", name));
+        }
+        let sheet = scope.unwrap_or(0);
+        let context = Some(CellReferenceRC {
+            sheet: self.workbook.worksheet(sheet)?.get_name(),
+            row: 1,
+            column: 1,
+        });
+        let formula = formula.strip_prefix('=').unwrap_or(formula);
+        self.parser.set_lexer_mode(LexerMode::A1);
+        if let Node::ParseErrorKind { message, .. } = self.parser.parse(formula, &context) {
+            return Err(format!("Invalid formula: {}", message));
+        }
+        Ok(formula.to_string())
+    }
+
+    /// Returns true if `name` in a formula of the sheet `sheet` refers to the name `name` with `scope`.
+    /// Formulas of global names are only affected by global names.
+    fn is_defined_name_visible(&self, name: &str, scope: Option<u32>, sheet: Option<u32>) -> bool {
+        match (scope, sheet) {
+            (Some(scope), Some(sheet)) => scope == sheet,
+            (Some(_), None) => false,
+            (None, Some(sheet)) => self.get_defined_name_index(name, Some(sheet)).is_none(),
+            (None, None) => true,
+        }
+    }
+
+    /// Replaces `name` by `new_name` in all the formulas in which `name` refers to the name with `scope`
+    fn rename_defined_name_in_formulas(&mut self, name: &str, scope: Option<u32>, new_name: &str) {
+        // All internal formulas are R1C1
+        self.parser.set_lexer_mode(LexerMode::R1C1);
+        for sheet in 0..self.workbook.worksheets.len() {
+            if !self.is_defined_name_visible(name, scope, Some(sheet as u32)) {
+                continue;
+            }
+            let worksheet = &self.workbook.worksheets[sheet];
+            let context = Some(CellReferenceRC {
+                sheet: worksheet.get_name(),
+                row: 1,
+                column: 1,
+            });
+            let formulas = worksheet
+                .shared_formulas
+                .iter()
+                .map(|formula| {
+                    let mut node = self.parser.parse(formula, &context);
+                    rename_defined_name_in_node(&mut node, name, new_name);
+                    to_rc_format(&node)
+                })
+                .collect();
+            self.workbook.worksheets[sheet].shared_formulas = formulas;
+        }
+
+        // Defined names are A1
+        self.parser.set_lexer_mode(LexerMode::A1);
+        for index in 0..self.workbook.defined_names.len() {
+            let defined_name = &self.workbook.defined_names[index];
+            let sheet = self.get_defined_name_scope(defined_name);
+            if !self.is_defined_name_visible(name, scope, sheet) {
+                continue;
+            }
+            let context = CellReferenceRC {
+                sheet: self.workbook.worksheets[sheet.unwrap_or(0) as usize].get_name(),
+                row: 1,
+                column: 1,
+            };
+            let mut node = self
+                .parser
+                .parse(&defined_name.formula, &Some(context.clone()));
+            rename_defined_name_in_node(&mut node, name, new_name);
+            self.workbook.defined_names[index].formula = to_string(&node, &context);
+        }
+    }
+
+    /// Returns the defined names in the workbook as (name, scope, formula), except hidden ones.
+    /// The scope is the index of the sheet for local names and None for global names.
+    pub fn get_defined_name_list(&self) -> Vec<(String, Option<u32>, String)> {
+        self.workbook
+            .defined_names
+            .iter()
+            .filter(|defined_name| !defined_name.hidden)
+            .map(|defined_name| {
+                (
+                    defined_name.name.clone(),
+                    self.get_defined_name_scope(defined_name),
+                    defined_name.formula.clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Adds a defined name. The scope is the index of the sheet for local names and
+    /// None for global names.
+    /// Fails if the name is not valid, already exists in that scope or the formula is not valid.
+    pub fn new_defined_name(
+        &mut self,
+        name: &str,
+        scope: Option<u32>,
+        formula: &str,
+    ) -> Result<(), String> {
+        self.record_change(ChangeScope::defined_names(), |model| {
+            let formula = model.validate_defined_name(name, scope, formula)?;
+            if model.get_defined_name_index(name, scope).is_some() {
+                return Err(format!("Defined name already exists: '{}'", name));
+            }
+            let sheet_id = model.get_scope_sheet_id(scope)?;
+            model.workbook.defined_names.push(DefinedName {
+                name: name.to_string(),
+                formula,
+                sheet_id,
+                hidden: false,
+            });
+            model.reset_parsed_structures();
+            Ok(())
+        })
+    }
+
+    /// Changes the name, scope and formula of the defined name `name` with `scope`.
+    /// Formulas using the name are updated if it is renamed.
+    pub fn update_defined_name(
+        &mut self,
+        name: &str,
+        scope: Option<u32>,
+        new_name: &str,
+        new_scope: Option<u32>,
+        new_formula: &str,
+    ) -> Result<(), String> {
+        self.record_change(ChangeScope::defined_names(), |model| {
+            let index = model
+                .get_defined_name_index(name, scope)
+                .ok_or_else(|| format!("Defined name not found: '{}'", name))?;
+            let new_formula = model.validate_defined_name(new_name, new_scope, new_formula)?;
+            if let Some(other) = model.get_defined_name_index(new_name, new_scope) {
+                if other != index {
+                    return Err(format!("Defined name already exists: '{}'", new_name));
+                }
+            }
+            let sheet_id = model.get_scope_sheet_id(new_scope)?;
+            if name != new_name {
+                model.rename_defined_name_in_formulas(name, scope, new_name);
+            }
+            let hidden = model.workbook.defined_names[index].hidden;
+            model.workbook.defined_names[index] = DefinedName {
+                name: new_name.to_string(),
+                formula: new_formula,
+                sheet_id,
+                hidden,
+            };
+            model.reset_parsed_structures();
+            Ok(())
+        })
+    }
+
+    /// Deletes the defined name `name` with `scope`.
+    /// Formulas using it evaluate to #NAME?
+    pub fn delete_defined_name(&mut self, name: &str, scope: Option<u32>) -> Result<(), String> {
+        self.record_change(ChangeScope::defined_names(), |model| {
+            let index = model
+                .get_defined_name_index(name, scope)
+                .ok_or_else(|| format!("Defined name not found: '{}'", name))?;
+            model.workbook.defined_names.remove(index);
+            model.reset_parsed_structures();
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,849 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    calc_result::{CellReference, Range},
+    expressions::parser::Node,
+    model::{Model, ParsedDefinedName},
+    reference_operators::intersect_ranges,
+};
+
+pub(crate) type CellKey = (u32, i32, i32);
+
+/// Graph of precedents/dependents of all the formulas in the workbook.
+///
+/// For every formula cell we keep the cells and ranges it reads from. The reverse direction is
+/// indexed by cell for single cell precedents and by bands of rows and columns for range
+/// precedents, see `RangeIndex`.
+/// Edits only mark cells as dirty. On the next evaluation we recompute the dirty cells,
+/// the volatile cells and all the cells that (transitively) depend on any of those.
+///
+/// Formulas returning arrays write into the cells of their spill area. An anchor depends on
+/// every cell of the area it wants to spill into (they might block it) and the cells whose
+/// spilled value changed during an evaluation are recorded so their dependents can be updated.
+/// The same applies to the result cells of data tables.
+#[derive(Clone, Default)]
+pub(crate) struct DependencyGraph {
+    precedents: HashMap<CellKey, Vec<Range>>,
+    /// The references returned by INDIRECT and OFFSET in the last evaluation of each formula
+    evaluated_precedents: HashMap<CellKey, Vec<Range>>,
+    cell_dependents: HashMap<CellKey, HashSet<CellKey>>,
+    range_dependents: RangeIndex,
+    volatile_cells: HashSet<CellKey>,
+    dirty_cells: HashSet<CellKey>,
+    spill_areas: HashMap<CellKey, Range>,
+    /// The anchors by the area they spill into
+    spill_anchors: RangeIndex,
+    spilled_cells: HashSet<CellKey>,
+    full_evaluation_needed: bool,
+}
+
+fn range_contains(range: &Range, (sheet, row, column): CellKey) -> bool {
+    range.left.sheet == sheet
+        && range.left.row <= row
+        && row <= range.right.row
+        && range.left.column <= column
+        && column <= range.right.column
+}
+
+/// Sheet, log2 of the height and of the width of the bands and the band in each direction
+type BandKey = (u32, u32, u32, i32, i32);
+
+/// Returns the log2 of the size of the smallest bands with room for `extent` cells
+fn band_bits(extent: i32) -> u32 {
+    (extent.max(1) as u32).next_power_of_two().trailing_zeros()
+}
+
+/// Returns the bands `range` is kept in: it touches at most two of them in each direction
+fn band_keys(range: &Range) -> Vec<BandKey> {
+    let row_bits = band_bits(range.right.row - range.left.row + 1);
+    let column_bits = band_bits(range.right.column - range.left.column + 1);
+    let mut keys = Vec::new();
+    for row_band in (range.left.row >> row_bits)..=(range.right.row >> row_bits) {
+        for column_band in (range.left.column >> column_bits)..=(range.right.column >> column_bits)
+        {
+            keys.push((
+                range.left.sheet,
+                row_bits,
+                column_bits,
+                row_band,
+                column_band,
+            ));
+        }
+    }
+    keys
+}
+
+/// Ranges indexed by the cells they contain.
+///
+/// Each range is kept in the bands of rows and columns of the smallest size it fits in, so it is
+/// in at most four of them. The ranges containing a cell are in the band of the cell for each
+/// size in use: finding them doesn't depend on how many other ranges there are.
+#[derive(Clone, Default)]
+struct RangeIndex {
+    bands: HashMap<BandKey, Vec<(CellKey, Range)>>,
+    /// Number of ranges kept in bands of each size (log2 of the height and of the width)
+    sizes: HashMap<(u32, u32), usize>,
+}
+
+impl RangeIndex {
+    /// Adds `range`, which belongs to the formula in `owner`
+    fn insert(&mut self, owner: CellKey, range: &Range) {
+        let keys = band_keys(range);
+        if let Some(&(_, row_bits, column_bits, _, _)) = keys.first() {
+            *self.sizes.entry((row_bits, column_bits)).or_default() += 1;
+        }
+        for key in keys {
+            self.bands
+                .entry(key)
+                .or_default()
+                .push((owner, range.clone()));
+        }
+    }
+
+    /// Removes `range` from the ranges of `owner`
+    fn remove(&mut self, owner: CellKey, range: &Range) {
+        let keys = band_keys(range);
+        if let Some(&(_, row_bits, column_bits, _, _)) = keys.first() {
+            if let Some(count) = self.sizes.get_mut(&(row_bits, column_bits)) {
+                *count -= 1;
+                if *count == 0 {
+                    self.sizes.remove(&(row_bits, column_bits));
+                }
+            }
+        }
+        for key in keys {
+            if let Some(ranges) = self.bands.get_mut(&key) {
+                ranges.retain(|(o, r)| *o != owner || r != range);
+                if ranges.is_empty() {
+                    self.bands.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Returns the owners of the ranges that contain `cell`
+    fn find(&self, cell: CellKey) -> Vec<CellKey> {
+        let (sheet, row, column) = cell;
+        let mut owners = Vec::new();
+        for &(row_bits, column_bits) in self.sizes.keys() {
+            let key = (
+                sheet,
+                row_bits,
+                column_bits,
+                row >> row_bits,
+                column >> column_bits,
+            );
+            if let Some(ranges) = self.bands.get(&key) {
+                for (owner, range) in ranges {
+                    if range_contains(range, cell) {
+                        owners.push(*owner);
+                    }
+                }
+            }
+        }
+        owners
+    }
+}
+
+fn ranges_intersect(a: &Range, b: &Range) -> bool {
+    a.left.sheet == b.left.sheet
+        && a.left.row <= b.right.row
+        && b.left.row <= a.right.row
+        && a.left.column <= b.right.column
+        && b.left.column <= a.right.column
+}
+
+impl DependencyGraph {
+    /// Replaces the precedents of `cell`
+    fn set_precedents(&mut self, cell: CellKey, precedents: Vec<Range>, is_volatile: bool) {
+        self.remove_precedents(cell);
+        for (index, range) in precedents.iter().enumerate() {
+            if range.left == range.right {
+                let key = (range.left.sheet, range.left.row, range.left.column);
+                self.cell_dependents.entry(key).or_default().insert(cell);
+            } else if !precedents[..index].contains(range) {
+                self.range_dependents.insert(cell, range);
+            }
+        }
+        if is_volatile {
+            self.volatile_cells.insert(cell);
+        }
+        self.precedents.insert(cell, precedents);
+    }
+
+    /// Forgets everything `cell` depended on. Its dependents are kept.
+    fn remove_precedents(&mut self, cell: CellKey) {
+        self.volatile_cells.remove(&cell);
+        self.evaluated_precedents.remove(&cell);
+        let precedents = match self.precedents.remove(&cell) {
+            Some(p) => p,
+            None => return,
+        };
+        for (index, range) in precedents.iter().enumerate() {
+            if range.left == range.right {
+                let key = (range.left.sheet, range.left.row, range.left.column);
+                if let Some(dependents) = self.cell_dependents.get_mut(&key) {
+                    dependents.remove(&cell);
+                    if dependents.is_empty() {
+                        self.cell_dependents.remove(&key);
+                    }
+                }
+            } else if !precedents[..index].contains(range) {
+                self.range_dependents.remove(cell, range);
+            }
+        }
+    }
+
+    /// Returns the formula cells that read directly from `cell`.
+    /// Includes the anchors whose spill area contains `cell`.
+    fn direct_dependents(&self, cell: CellKey) -> Vec<CellKey> {
+        let mut dependents = self.value_dependents(cell);
+        for anchor in self.spill_anchors.find(cell) {
+            if anchor != cell {
+                dependents.push(anchor);
+            }
+        }
+        dependents
+    }
+
+    /// Returns the formula cells that read the value of `cell`
+    fn value_dependents(&self, cell: CellKey) -> Vec<CellKey> {
+        let mut dependents = self.range_dependents.find(cell);
+        if let Some(cells) = self.cell_dependents.get(&cell) {
+            dependents.extend(cells.iter().copied());
+        }
+        dependents
+    }
+
+    /// Returns the list of cells to recompute if `roots` have changed, sorted by (sheet, row, column).
+    /// Volatile cells are always included.
+    fn cells_to_evaluate(&self, roots: &[CellKey]) -> Vec<CellKey> {
+        let mut roots = roots.to_vec();
+        roots.extend(self.volatile_cells.iter().copied());
+        self.transitive_dependents(roots)
+    }
+
+    /// Returns `roots` and all the cells that depend on them, sorted by (sheet, row, column)
+    fn transitive_dependents(&self, roots: Vec<CellKey>) -> Vec<CellKey> {
+        let mut visited: HashSet<CellKey> = HashSet::new();
+        let mut stack: Vec<CellKey> = roots;
+        while let Some(cell) = stack.pop() {
+            if visited.insert(cell) {
+                stack.extend(self.direct_dependents(cell));
+            }
+        }
+        let mut cells: Vec<CellKey> = visited.into_iter().collect();
+        cells.sort_unstable();
+        cells
+    }
+
+    /// Returns the groups of formula cells in circular references: the strongly connected
+    /// components of the graph with more than one cell or with a cell that reads itself.
+    /// A group never depends on a group that comes after it.
+    fn circular_groups(&self) -> Vec<Vec<CellKey>> {
+        let mut cells: Vec<CellKey> = self.precedents.keys().copied().collect();
+        cells.sort_unstable();
+        // Tarjan's algorithm with an explicit stack, formulas can be arbitrarily nested
+        let mut next_index = 0;
+        let mut index: HashMap<CellKey, usize> = HashMap::new();
+        let mut low_link: HashMap<CellKey, usize> = HashMap::new();
+        let mut stack: Vec<CellKey> = Vec::new();
+        let mut on_stack: HashSet<CellKey> = HashSet::new();
+        let mut groups = Vec::new();
+        for root in cells {
+            if index.contains_key(&root) {
+                continue;
+            }
+            // cells being visited and the dependents not yet visited of each
+            let mut visiting: Vec<(CellKey, Vec<CellKey>)> = Vec::new();
+            index.insert(root, next_index);
+            low_link.insert(root, next_index);
+            next_index += 1;
+            stack.push(root);
+            on_stack.insert(root);
+            visiting.push((root, self.value_dependents(root)));
+            while let Some((cell, dependents)) = visiting.last_mut() {
+                let cell = *cell;
+                if let Some(dependent) = dependents.pop() {
+                    match index.get(&dependent).copied() {
+                        None => {
+                            index.insert(dependent, next_index);
+                            low_link.insert(dependent, next_index);
+                            next_index += 1;
+                            stack.push(dependent);
+                            on_stack.insert(dependent);
+                            visiting.push((dependent, self.value_dependents(dependent)));
+                        }
+                        Some(dependent_index) if on_stack.contains(&dependent) => {
+                            let low = low_link[&cell].min(dependent_index);
+                            low_link.insert(cell, low);
+                        }
+                        Some(_) => {}
+                    }
+                    continue;
+                }
+                visiting.pop();
+                if let Some((parent, _)) = visiting.last() {
+                    let low = low_link[parent].min(low_link[&cell]);
+                    low_link.insert(*parent, low);
+                }
+                if low_link[&cell] == index[&cell] {
+                    let mut group = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack.remove(&member);
+                        group.push(member);
+                        if member == cell {
+                            break;
+                        }
+                    }
+                    if group.len() > 1 || self.value_dependents(cell).contains(&cell) {
+                        group.sort_unstable();
+                        groups.push(group);
+                    }
+                }
+            }
+        }
+        // Groups are found after the groups that depend on them
+        groups.reverse();
+        groups
+    }
+
+    /// Returns the list of cells to recompute after the spilled values in `cells` have changed,
+    /// sorted by (sheet, row, column). The anchors spilling into `cells` are not included.
+    fn cells_to_evaluate_after_spill(&self, cells: &[CellKey]) -> Vec<CellKey> {
+        let mut visited: HashSet<CellKey> = HashSet::new();
+        let mut stack: Vec<CellKey> = Vec::new();
+        for cell in cells {
+            stack.extend(self.value_dependents(*cell));
+        }
+        while let Some(cell) = stack.pop() {
+            if visited.insert(cell) {
+                stack.extend(self.direct_dependents(cell));
+            }
+        }
+        let mut cells: Vec<CellKey> = visited.into_iter().collect();
+        cells.sort_unstable();
+        cells
+    }
+
+    /// Returns the area `anchor` tried to spill into in the last evaluation, if any
+    pub(crate) fn get_spill_area(&self, anchor: CellKey) -> Option<&Range> {
+        self.spill_areas.get(&anchor)
+    }
+
+    /// Sets (or removes) the area the formula in `anchor` wants to spill into
+    pub(crate) fn set_spill_area(&mut self, anchor: CellKey, area: Option<Range>) {
+        if let Some(old_area) = self.spill_areas.remove(&anchor) {
+            self.spill_anchors.remove(anchor, &old_area);
+        }
+        if let Some(area) = area {
+            self.spill_anchors.insert(anchor, &area);
+            self.spill_areas.insert(anchor, area);
+        }
+    }
+
+    /// Flags a cell whose spilled value has changed during the evaluation
+    pub(crate) fn mark_spilled(&mut self, sheet: u32, row: i32, column: i32) {
+        self.spilled_cells.insert((sheet, row, column));
+    }
+
+    /// Returns the cells and ranges the formula in `cell` reads from, including the references
+    /// returned by INDIRECT and OFFSET in the last evaluation. None if `cell` is not a formula.
+    pub(crate) fn get_precedents(&self, cell: CellKey) -> Option<Vec<Range>> {
+        let mut precedents = self.precedents.get(&cell)?.clone();
+        if let Some(evaluated) = self.evaluated_precedents.get(&cell) {
+            precedents.extend(evaluated.iter().cloned());
+        }
+        Some(precedents)
+    }
+
+    /// Returns the formula cells that read any cell of `area`, including the ones that reached it
+    /// through INDIRECT or OFFSET in the last evaluation. Sorted by (sheet, row, column).
+    pub(crate) fn get_dependents(&self, area: &Range) -> Vec<CellKey> {
+        let mut dependents = Vec::new();
+        for (dependent, ranges) in self.precedents.iter().chain(&self.evaluated_precedents) {
+            if ranges.iter().any(|range| ranges_intersect(range, area)) {
+                dependents.push(*dependent);
+            }
+        }
+        dependents.sort_unstable();
+        dependents.dedup();
+        dependents
+    }
+
+    /// Forgets the references returned by INDIRECT and OFFSET when `cell` was last evaluated
+    pub(crate) fn clear_evaluated_precedents(&mut self, cell: CellKey) {
+        self.evaluated_precedents.remove(&cell);
+    }
+
+    /// Takes note of a reference returned by INDIRECT or OFFSET while evaluating `cell`
+    pub(crate) fn add_evaluated_precedent(&mut self, cell: CellReference, range: Range) {
+        let range = normalize(range.left, range.right);
+        let precedents = self
+            .evaluated_precedents
+            .entry((cell.sheet, cell.row, cell.column))
+            .or_default();
+        if !precedents.contains(&range) {
+            precedents.push(range);
+        }
+    }
+
+    /// Returns true if the formula in `cell` must be recomputed in every evaluation
+    pub(crate) fn is_volatile(&self, cell: CellKey) -> bool {
+        self.volatile_cells.contains(&cell)
+    }
+
+    /// Returns and forgets the cells whose spilled value changed during the evaluation
+    pub(crate) fn take_spilled_cells(&mut self) -> Vec<CellKey> {
+        self.spilled_cells.drain().collect()
+    }
+
+    /// Flags a cell whose content has changed since the last evaluation
+    pub(crate) fn mark_dirty(&mut self, sheet: u32, row: i32, column: i32) {
+        self.dirty_cells.insert((sheet, row, column));
+    }
+
+    /// The next evaluation will recompute every cell in the workbook
+    pub(crate) fn invalidate(&mut self) {
+        self.full_evaluation_needed = true;
+    }
+}
+
+impl Model {
+    /// Collects all the cells and ranges `node` reads from when evaluated in `cell`.
+    /// Returns true if the node has references that can only be known at evaluation time
+    /// (INDIRECT, OFFSET, ...) or uses a volatile function (RAND, NOW, ...).
+    /// `names` are the defined names whose formulas are already being collected.
+    fn collect_precedents(
+        &self,
+        node: &Node,
+        cell: CellReference,
+        precedents: &mut Vec<Range>,
+        names: &mut HashSet<String>,
+    ) -> bool {
+        match node {
+            Node::ReferenceKind { .. } | Node::RangeKind { .. } => {
+                if let Some(range) = resolve_reference(node, cell) {
+                    precedents.push(range);
+                }
+                false
+            }
+            Node::OpRangeKind { left, right } => {
+                match (
+                    resolve_reference(left, cell),
+                    resolve_reference(right, cell),
+                ) {
+                    (Some(left), Some(right)) if left.left.sheet == right.left.sheet => {
+                        precedents.push(normalize(left.left, right.right));
+                        false
+                    }
+                    _ => {
+                        self.collect_precedents(left, cell, precedents, names);
+                        self.collect_precedents(right, cell, precedents, names);
+                        true
+                    }
+                }
+            }
+            Node::OpIntersectKind { left, right } => {
+                match (
+                    resolve_reference(left, cell),
+                    resolve_reference(right, cell),
+                ) {
+                    (Some(left), Some(right)) => {
+                        if let Some(range) = intersect_ranges(&left, &right) {
+                            precedents.push(range);
+                        }
+                        false
+                    }
+                    _ => {
+                        let is_volatile_left =
+                            self.collect_precedents(left, cell, precedents, names);
+                        let is_volatile_right =
+                            self.collect_precedents(right, cell, precedents, names);
+                        is_volatile_left || is_volatile_right
+                    }
+                }
+            }
+            Node::OpUnionKind(items) => {
+                let mut is_volatile = false;
+                for item in items {
+                    is_volatile |= self.collect_precedents(item, cell, precedents, names);
+                }
+                is_volatile
+            }
+            Node::OpConcatenateKind { left, right }
+            | Node::OpSumKind { left, right, .. }
+            | Node::OpProductKind { left, right, .. }
+            | Node::OpPowerKind { left, right }
+            | Node::CompareKind { left, right, .. } => {
+                let is_volatile_left = self.collect_precedents(left, cell, precedents, names);
+                let is_volatile_right = self.collect_precedents(right, cell, precedents, names);
+                is_volatile_left || is_volatile_right
+            }
+            Node::UnaryKind { right, .. } => {
+                self.collect_precedents(right, cell, precedents, names)
+            }
+            Node::FunctionKind { kind, args } => {
+                let mut is_volatile = kind.is_volatile();
+                for arg in args {
+                    is_volatile |= self.collect_precedents(arg, cell, precedents, names);
+                }
+                is_volatile
+            }
+            Node::InvalidFunctionKind { name, args } => {
+                // A call to a named LAMBDA reads whatever its body reads
+                let mut is_volatile = match self.get_parsed_defined_name(name, cell.sheet) {
+                    Some(ParsedDefinedName::Formula(node)) if names.insert(name.to_lowercase()) => {
+                        self.collect_precedents(node, cell, precedents, names)
+                    }
+                    _ => false,
+                };
+                for arg in args {
+                    is_volatile |= self.collect_precedents(arg, cell, precedents, names);
+                }
+                is_volatile
+            }
+            Node::CallKind { function, args } => {
+                let mut is_volatile = self.collect_precedents(function, cell, precedents, names);
+                for arg in args {
+                    is_volatile |= self.collect_precedents(arg, cell, precedents, names);
+                }
+                is_volatile
+            }
+            Node::ArrayKind(rows) => {
+                let mut is_volatile = false;
+                for item in rows.iter().flatten() {
+                    is_volatile |= self.collect_precedents(item, cell, precedents, names);
+                }
+                is_volatile
+            }
+            // The extent of the spill range is only known once the anchor is evaluated,
+            // but it only changes when the anchor is recomputed.
+            Node::SpillRangeKind(anchor) => {
+                if let Some(range) = resolve_reference(anchor, cell) {
+                    precedents.push(range);
+                }
+                false
+            }
+            Node::VariableKind(defined_name) => {
+                match self.get_parsed_defined_name(defined_name, cell.sheet) {
+                    Some(ParsedDefinedName::CellReference(reference)) => {
+                        precedents.push(Range {
+                            left: *reference,
+                            right: *reference,
+                        });
+                    }
+                    Some(ParsedDefinedName::RangeReference(range)) => {
+                        precedents.push(normalize(range.left, range.right));
+                    }
+                    Some(ParsedDefinedName::Formula(node)) => {
+                        if names.insert(defined_name.to_lowercase()) {
+                            return self.collect_precedents(node, cell, precedents, names);
+                        }
+                    }
+                    Some(ParsedDefinedName::InvalidDefinedNameFormula) | None => {}
+                }
+                false
+            }
+            Node::SheetSpanReferenceKind { .. } | Node::SheetSpanRangeKind { .. } => {
+                for reference in self.expand_sheet_span(node) {
+                    if let Some(range) = resolve_reference(&reference, cell) {
+                        precedents.push(range);
+                    }
+                }
+                false
+            }
+            Node::TableReferenceKind {
+                table_name,
+                specifier,
+                table_reference,
+            } => {
+                if let Ok(range) =
+                    self.get_table_range(table_name, specifier, table_reference, cell)
+                {
+                    precedents.push(range);
+                }
+                false
+            }
+            // Other workbooks can change without this one knowing
+            Node::ExternalReferenceKind { .. } | Node::ExternalRangeKind { .. } => true,
+            Node::BooleanKind(_)
+            | Node::NumberKind(_)
+            | Node::StringKind(_)
+            | Node::WrongReferenceKind { .. }
+            | Node::WrongRangeKind { .. }
+            | Node::ErrorKind(_)
+            | Node::ParseErrorKind { .. }
+            | Node::EmptyArgKind => false,
+        }
+    }
+
+    /// Recomputes the precedents of the cell from its current content
+    fn update_cell_dependencies(&mut self, (sheet, row, column): CellKey) {
+        let formula_index = self
+            .workbook
+            .worksheets
+            .get(sheet as usize)
+            .and_then(|worksheet| worksheet.cell(row, column))
+            .and_then(|cell| cell.get_formula());
+        match formula_index {
+            Some(f) => {
+                let mut precedents = Vec::new();
+                let node = &self.parsed_formulas[sheet as usize][f as usize];
+                let is_volatile = self.collect_precedents(
+                    node,
+                    CellReference { sheet, row, column },
+                    &mut precedents,
+                    &mut HashSet::new(),
+                );
+                self.dependency_graph
+                    .set_precedents((sheet, row, column), precedents, is_volatile);
+            }
+            None => self
+                .dependency_graph
+                .remove_precedents((sheet, row, column)),
+        }
+    }
+
+    /// Builds the dependency graph from scratch.
+    /// Needs to be called every time formulas or defined names are (re)parsed.
+    pub(crate) fn build_dependency_graph(&mut self) {
+        self.dependency_graph = DependencyGraph::default();
+        for cell in self.get_all_cells() {
+            self.update_cell_dependencies((cell.index, cell.row, cell.column));
+        }
+        self.dependency_graph.invalidate();
+    }
+
+    /// Brings the graph up to date with the cells edited since the last evaluation.
+    /// Returns the list of cells that need to be recomputed, or None if all cells need to.
+    pub(crate) fn take_cells_to_evaluate(&mut self) -> Option<Vec<CellReference>> {
+        let mut dirty_cells: Vec<CellKey> = self.dependency_graph.dirty_cells.drain().collect();
+        dirty_cells.sort_unstable();
+        for cell in &dirty_cells {
+            self.update_cell_dependencies(*cell);
+            if !self.dependency_graph.precedents.contains_key(cell) {
+                // The cell is not a formula anymore, anything it spilled is gone
+                let (sheet, row, column) = *cell;
+                if (sheet as usize) < self.workbook.worksheets.len() {
+                    self.remove_spill(CellReference { sheet, row, column });
+                } else {
+                    self.dependency_graph.set_spill_area(*cell, None);
+                }
+            }
+        }
+        if self.dependency_graph.full_evaluation_needed {
+            self.dependency_graph.full_evaluation_needed = false;
+            return None;
+        }
+        let cells = self
+            .dependency_graph
+            .cells_to_evaluate(&dirty_cells)
+            .into_iter()
+            .filter(|(sheet, _, _)| (*sheet as usize) < self.workbook.worksheets.len())
+            .map(|(sheet, row, column)| CellReference { sheet, row, column })
+            .collect();
+        Some(cells)
+    }
+
+    /// Returns the formula cells that (transitively) depend on any of `cells`
+    pub(crate) fn get_dependent_cells(&self, cells: &[CellReference]) -> Vec<CellReference> {
+        let roots = cells
+            .iter()
+            .map(|cell| (cell.sheet, cell.row, cell.column))
+            .collect();
+        self.dependency_graph
+            .transitive_dependents(roots)
+            .into_iter()
+            .filter(|key| self.dependency_graph.precedents.contains_key(key))
+            .map(|(sheet, row, column)| CellReference { sheet, row, column })
+            .collect()
+    }
+
+    /// Returns the formulas `cell` reads from according to the dependency graph,
+    /// including the formulas that spilled the values it reads. Empty if `cell` is not a formula.
+    pub(crate) fn get_formula_precedents(&self, cell: CellReference) -> Vec<CellReference> {
+        match self.shared_model().dependency_graph.precedents.get(&(
+            cell.sheet,
+            cell.row,
+            cell.column,
+        )) {
+            Some(ranges) => ranges
+                .iter()
+                .flat_map(|range| self.get_formulas_in_range(range))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the formulas in `range` and the formulas that spilled values into it
+    pub(crate) fn get_formulas_in_range(&self, range: &Range) -> Vec<CellReference> {
+        let mut formulas = Vec::new();
+        let sheet = range.left.sheet;
+        let worksheet = match self.shared_model().workbook.worksheets.get(sheet as usize) {
+            Some(worksheet) => worksheet,
+            None => return formulas,
+        };
+        // Ranges might be whole columns, only the cells with content are visited
+        let row_count = (range.right.row - range.left.row + 1) as usize;
+        let column_count = (range.right.column - range.left.column + 1) as usize;
+        let rows: Vec<i32> = if row_count <= worksheet.sheet_data.len() {
+            (range.left.row..=range.right.row).collect()
+        } else {
+            worksheet
+                .sheet_data
+                .keys()
+                .copied()
+                .filter(|row| range.left.row <= *row && *row <= range.right.row)
+                .collect()
+        };
+        for row in rows {
+            let row_data = match worksheet.sheet_data.get(&row) {
+                Some(row_data) => row_data,
+                None => continue,
+            };
+            let columns: Vec<i32> = if column_count <= row_data.len() {
+                (range.left.column..=range.right.column).collect()
+            } else {
+                row_data
+                    .keys()
+                    .copied()
+                    .filter(|column| range.left.column <= *column && *column <= range.right.column)
+                    .collect()
+            };
+            for column in columns {
+                let precedent = match row_data.get(&column) {
+                    Some(precedent) => precedent,
+                    None => continue,
+                };
+                if precedent.get_formula().is_some() {
+                    formulas.push(CellReference { sheet, row, column });
+                } else if let Some((row, column)) = precedent.get_spill_anchor() {
+                    let anchor_has_formula = worksheet
+                        .cell(row, column)
+                        .and_then(|anchor| anchor.get_formula())
+                        .is_some();
+                    if anchor_has_formula {
+                        formulas.push(CellReference { sheet, row, column });
+                    }
+                }
+            }
+        }
+        formulas
+    }
+
+    /// Returns the groups of formula cells in circular references, sorted so that
+    /// a group never depends on a group that comes after it
+    pub(crate) fn get_circular_groups(&self) -> Vec<Vec<CellReference>> {
+        self.dependency_graph
+            .circular_groups()
+            .into_iter()
+            .map(|group| {
+                group
+                    .into_iter()
+                    .filter(|(sheet, _, _)| (*sheet as usize) < self.workbook.worksheets.len())
+                    .map(|(sheet, row, column)| CellReference { sheet, row, column })
+                    .collect::<Vec<CellReference>>()
+            })
+            .filter(|group| !group.is_empty())
+            .collect()
+    }
+
+    /// Returns the list of cells that need to be recomputed because a spilled value they read
+    /// has changed during the last evaluation. Empty if there are none.
+    pub(crate) fn take_cells_to_evaluate_after_spill(&mut self) -> Vec<CellReference> {
+        let mut spilled_cells: Vec<CellKey> = self.dependency_graph.spilled_cells.drain().collect();
+        spilled_cells.sort_unstable();
+        self.dependency_graph
+            .cells_to_evaluate_after_spill(&spilled_cells)
+            .into_iter()
+            .filter(|(sheet, _, _)| (*sheet as usize) < self.workbook.worksheets.len())
+            .map(|(sheet, row, column)| CellReference { sheet, row, column })
+            .collect()
+    }
+}
+
+/// Returns the range (or single cell) referenced by a ReferenceKind or RangeKind node
+fn resolve_reference(node: &Node, cell: CellReference) -> Option<Range> {
+    match node {
+        Node::ReferenceKind {
+            sheet_index,
+            absolute_row,
+            absolute_column,
+            row,
+            column,
+            ..
+        } => {
+            let reference = CellReference {
+                sheet: *sheet_index,
+                row: if *absolute_row { *row } else { *row + cell.row },
+                column: if *absolute_column {
+                    *column
+                } else {
+                    *column + cell.column
+                },
+            };
+            Some(Range {
+                left: reference,
+                right: reference,
+            })
+        }
+        Node::RangeKind {
+            sheet_index,
+            absolute_row1,
+            absolute_column1,
+            row1,
+            column1,
+            absolute_row2,
+            absolute_column2,
+            row2,
+            column2,
+            ..
+        } => {
+            let left = CellReference {
+                sheet: *sheet_index,
+                row: if *absolute_row1 {
+                    *row1
+                } else {
+                    *row1 + cell.row
+                },
+                column: if *absolute_column1 {
+                    *column1
+                } else {
+                    *column1 + cell.column
+                },
+            };
+            let right = CellReference {
+                sheet: *sheet_index,
+                row: if *absolute_row2 {
+                    *row2
+                } else {
+                    *row2 + cell.row
+                },
+                column: if *absolute_column2 {
+                    *column2
+                } else {
+                    *column2 + cell.column
+                },
+            };
+            Some(normalize(left, right))
+        }
+        _ => None,
+    }
+}
+
+/// Range with the top-left corner in `left` and the bottom-right in `right`
+fn normalize(a: CellReference, b: CellReference) -> Range {
+    Range {
+        left: CellReference {
+            sheet: a.sheet,
+            row: a.row.min(b.row),
+            column: a.column.min(b.column),
+        },
+        right: CellReference {
+            sheet: a.sheet,
+            row: a.row.max(b.row),
+            column: a.column.max(b.column),
+        },
+    }
+}
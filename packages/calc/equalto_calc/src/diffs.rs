@@ -7,9 +7,13 @@ use crate::{
         },
         types::{Area, CellReferenceIndex, CellReferenceRC},
     },
+    history::ChangeScope,
+    locale::Currency,
     model::Model,
+    types::{CalculationSettings, Cell, Col, DefinedName, ReferenceStyle, Row, Table, Worksheet},
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(untagged, deny_unknown_fields)]
@@ -25,6 +29,73 @@ pub struct SetCellValue {
     old_value: CellValue,
 }
 
+/// The parts of a worksheet, other than its name, cells and formulas, that can be changed
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SheetProperties {
+    pub(crate) rows: Vec<Row>,
+    pub(crate) cols: Vec<Col>,
+    pub(crate) array_formulas: Vec<String>,
+    pub(crate) color: Option<String>,
+    pub(crate) frozen_rows: i32,
+    pub(crate) frozen_columns: i32,
+}
+
+/// A change in one part of the workbook, with the values before and after the change.
+/// Undoing a change restores the old value and redoing it sets the new value again.
+#[derive(Debug, Clone)]
+pub(crate) enum Diff {
+    Cell {
+        sheet: u32,
+        row: i32,
+        column: i32,
+        old_value: Option<Box<Cell>>,
+        new_value: Option<Box<Cell>>,
+    },
+    SheetProperties {
+        sheet: u32,
+        old_value: Box<SheetProperties>,
+        new_value: Box<SheetProperties>,
+    },
+    SheetName {
+        sheet: u32,
+        old_value: String,
+        new_value: String,
+    },
+    SharedFormulas {
+        sheet: u32,
+        old_value: Vec<String>,
+        new_value: Vec<String>,
+    },
+    DefinedNames {
+        old_value: Vec<DefinedName>,
+        new_value: Vec<DefinedName>,
+    },
+    Tables {
+        old_value: HashMap<String, Table>,
+        new_value: HashMap<String, Table>,
+    },
+    CalculationSettings {
+        old_value: CalculationSettings,
+        new_value: CalculationSettings,
+    },
+    Currency {
+        old_value: Currency,
+        new_value: Currency,
+    },
+    InsertSheet {
+        sheet: u32,
+        worksheet: Box<Worksheet>,
+    },
+    DeleteSheet {
+        sheet: u32,
+        worksheet: Box<Worksheet>,
+    },
+    MoveSheet {
+        sheet: u32,
+        new_index: u32,
+    },
+}
+
 impl Model {
     pub(crate) fn shift_cell_formula(
         &mut self,
@@ -51,8 +122,14 @@ impl Model {
             let formula = to_string(node, &cell_reference);
             let formula_displaced = to_string_displaced(node, &cell_reference, displace_data);
             if formula != formula_displaced {
-                self.update_cell_with_formula(sheet, row, column, format!("={formula_displaced}"))
-                    .expect("Failed to shift cell formula");
+                self.update_cell_with_formula_in_style(
+                    sheet,
+                    row,
+                    column,
+                    format!("={formula_displaced}"),
+                    ReferenceStyle::A1,
+                )
+                .expect("Failed to shift cell formula");
             }
         }
     }
@@ -62,75 +139,78 @@ impl Model {
         source_area: &Area,
         target: &CellReferenceIndex,
     ) -> Result<Vec<SetCellValue>, String> {
-        let mut diff_list: Vec<SetCellValue> = Vec::new();
-        let target_area = &Area {
-            sheet: target.sheet,
-            row: target.row,
-            column: target.column,
-            width: source_area.width,
-            height: source_area.height,
-        };
-        // Walk over every formula
-        let cells = self.get_all_cells();
-        for cell in cells {
-            if let Some(f) = self
-                .workbook
-                .worksheet(cell.index)
-                .expect("Worksheet must exist")
-                .cell(cell.row, cell.column)
-                .expect("Cell must exist")
-                .get_formula()
-            {
-                let sheet = cell.index;
-                let row = cell.row;
-                let column = cell.column;
-
-                // If cell is in the source or target area, skip
-                if ref_is_in_area(sheet, row, column, source_area)
-                    || ref_is_in_area(sheet, row, column, target_area)
+        self.record_change(ChangeScope::formulas(), |model| {
+            let mut diff_list: Vec<SetCellValue> = Vec::new();
+            let target_area = &Area {
+                sheet: target.sheet,
+                row: target.row,
+                column: target.column,
+                width: source_area.width,
+                height: source_area.height,
+            };
+            // Walk over every formula
+            let cells = model.get_all_cells();
+            for cell in cells {
+                if let Some(f) = model
+                    .workbook
+                    .worksheet(cell.index)
+                    .expect("Worksheet must exist")
+                    .cell(cell.row, cell.column)
+                    .expect("Cell must exist")
+                    .get_formula()
                 {
-                    continue;
-                }
+                    let sheet = cell.index;
+                    let row = cell.row;
+                    let column = cell.column;
+
+                    // If cell is in the source or target area, skip
+                    if ref_is_in_area(sheet, row, column, source_area)
+                        || ref_is_in_area(sheet, row, column, target_area)
+                    {
+                        continue;
+                    }
 
-                // Get the formula
-                // Get a copy of the AST
-                let node = &mut self.parsed_formulas[sheet as usize][f as usize].clone();
-                let cell_reference = CellReferenceRC {
-                    sheet: self.workbook.worksheets[sheet as usize].get_name(),
-                    column: cell.column,
-                    row: cell.row,
-                };
-                let context = CellReferenceIndex { sheet, column, row };
-                let formula = to_string(node, &cell_reference);
-                let target_sheet_name = &self.workbook.worksheets[target.sheet as usize].name;
-                forward_references(
-                    node,
-                    &context,
-                    source_area,
-                    target.sheet,
-                    target_sheet_name,
-                    target.row,
-                    target.column,
-                );
+                    // Get the formula
+                    // Get a copy of the AST
+                    let node = &mut model.parsed_formulas[sheet as usize][f as usize].clone();
+                    let cell_reference = CellReferenceRC {
+                        sheet: model.workbook.worksheets[sheet as usize].get_name(),
+                        column: cell.column,
+                        row: cell.row,
+                    };
+                    let context = CellReferenceIndex { sheet, column, row };
+                    let formula = to_string(node, &cell_reference);
+                    let target_sheet_name = &model.workbook.worksheets[target.sheet as usize].name;
+                    forward_references(
+                        node,
+                        &context,
+                        source_area,
+                        target.sheet,
+                        target_sheet_name,
+                        target.row,
+                        target.column,
+                    );
 
-                // If the string representation of the formula has changed update the cell
-                let updated_formula = to_string(node, &cell_reference);
-                if formula != updated_formula {
-                    self.update_cell_with_formula(
-                        sheet,
-                        row,
-                        column,
-                        format!("={updated_formula}"),
-                    )?;
-                    // Update the diff list
-                    diff_list.push(SetCellValue {
-                        cell: CellReferenceIndex { sheet, column, row },
-                        new_value: CellValue::Value(format!("={}", updated_formula)),
-                        old_value: CellValue::Value(format!("={}", formula)),
-                    });
+                    // If the string representation of the formula has changed update the cell
+                    let updated_formula = to_string(node, &cell_reference);
+                    if formula != updated_formula {
+                        model.update_cell_with_formula_in_style(
+                            sheet,
+                            row,
+                            column,
+                            format!("={updated_formula}"),
+                            ReferenceStyle::A1,
+                        )?;
+                        // Update the diff list
+                        diff_list.push(SetCellValue {
+                            cell: CellReferenceIndex { sheet, column, row },
+                            new_value: CellValue::Value(format!("={}", updated_formula)),
+                            old_value: CellValue::Value(format!("={}", formula)),
+                        });
+                    }
                 }
             }
-        }
-        Ok(diff_list)
+            Ok(diff_list)
+        })
     }
 }
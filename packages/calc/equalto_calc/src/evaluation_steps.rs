@@ -0,0 +1,275 @@
+//! Step by step evaluation of a formula, like Excel's "Evaluate Formula" dialog.
+//!
+//! The formula is evaluated once while the result of every node of its parse tree is recorded.
+//! Nodes that were not needed to compute the result, like the branch of an IF not taken,
+//! have no value. References always show the cells they point to.
+
+use std::{collections::HashMap, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    calc_result::{CalcResult, CellReference, Range},
+    constants::{LAST_COLUMN, LAST_ROW},
+    expressions::{
+        ast::children,
+        parser::{stringify::to_string, Node},
+        types::{Area, CellReferenceIndex, CellReferenceRC},
+    },
+    model::Model,
+};
+
+/// References to larger ranges are reported without their values
+const MAX_REFERENCE_CELLS: i64 = 10_000;
+
+/// The value of a sub-expression of a formula
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum StepValue {
+    Number {
+        value: f64,
+    },
+    Text {
+        value: String,
+    },
+    Boolean {
+        value: bool,
+    },
+    Error {
+        error: String,
+        /// The cell where the error originated
+        origin: CellReferenceIndex,
+        message: String,
+    },
+    /// A cell or range with its values, whole rows and columns trimmed to the used area.
+    /// `values` is empty for ranges with more than 10000 cells.
+    Reference {
+        area: Area,
+        values: Vec<Vec<StepValue>>,
+    },
+    Array {
+        values: Vec<Vec<StepValue>>,
+    },
+    /// A reference with several areas: (A1:A3,C1:C3). Each of them is a `Reference`.
+    Areas {
+        areas: Vec<StepValue>,
+    },
+    /// An empty cell or a missing argument
+    Empty,
+    /// A function created with LAMBDA
+    Lambda,
+}
+
+/// A node of the parse tree of a formula with its value
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EvaluationStep {
+    /// The sub-expression, i.e. `SUM(A1:A3)`
+    pub formula: String,
+    /// None if the sub-expression was not evaluated
+    pub value: Option<StepValue>,
+    /// The operands of an operator or the arguments of a function
+    pub children: Vec<EvaluationStep>,
+}
+
+/// The results recorded while evaluating the steps of a formula
+#[derive(Clone)]
+pub(crate) struct EvaluationSteps {
+    formula: Arc<Node>,
+    /// The result of the nodes of the formula by their position in a walk of its steps
+    results: HashMap<usize, CalcResult>,
+}
+
+/// The sub-expressions of `node` that are shown as steps.
+/// A spilled range, A1#, is a single step like any other reference.
+fn step_children(node: &Node) -> Vec<&Node> {
+    match node {
+        Node::SpillRangeKind(_) => Vec::new(),
+        _ => children(node),
+    }
+}
+
+/// The position of `node` in a walk of the steps of `formula`, parents before their children.
+/// Nodes that are not part of `formula`, like the formula of a defined name, have no position.
+fn find_position(formula: &Node, node: &Node) -> Option<usize> {
+    let mut stack = vec![formula];
+    let mut position = 0;
+    while let Some(current) = stack.pop() {
+        if std::ptr::eq(current, node) {
+            return Some(position);
+        }
+        position += 1;
+        stack.extend(step_children(current).into_iter().rev());
+    }
+    None
+}
+
+impl Model {
+    /// Takes note of the result of `node` if the steps of a formula are being recorded.
+    /// Nodes evaluated more than once, like the body of a LAMBDA, keep their first result.
+    pub(crate) fn record_evaluation_step(&mut self, node: &Node, result: &CalcResult) {
+        if let Some(steps) = &mut self.evaluation_steps {
+            if let Some(position) = find_position(&steps.formula, node) {
+                steps
+                    .results
+                    .entry(position)
+                    .or_insert_with(|| result.clone());
+            }
+        }
+    }
+
+    /// Evaluates the formula in `cell` and returns its parse tree with the value of every
+    /// sub-expression. The workbook is evaluated first. Volatile functions (RAND, NOW, ...)
+    /// are computed again, so they might not match the value of the cell.
+    pub fn evaluate_formula_steps(
+        &mut self,
+        cell: CellReference,
+    ) -> Result<EvaluationStep, String> {
+        let worksheet = self.workbook.worksheet(cell.sheet)?;
+        let sheet_name = worksheet.get_name();
+        let formula_index = worksheet
+            .cell(cell.row, cell.column)
+            .and_then(|c| c.get_formula())
+            .ok_or("The cell does not contain a formula")?;
+        self.evaluate();
+
+        let node =
+            Arc::new(self.parsed_formulas[cell.sheet as usize][formula_index as usize].clone());
+        let array_context = self.get_array_formula_area(cell).is_some();
+        let local_variables = std::mem::take(&mut self.local_variables);
+        self.evaluation_steps = Some(EvaluationSteps {
+            formula: node.clone(),
+            results: HashMap::new(),
+        });
+        let result = if array_context {
+            self.evaluate_node_as_array(&node, cell)
+        } else {
+            self.evaluate_spilling_formula(&node, cell)
+        };
+        let mut steps = self
+            .evaluation_steps
+            .take()
+            .map(|steps| steps.results)
+            .unwrap_or_default();
+        self.local_variables = local_variables;
+        steps.insert(0, result);
+
+        let context = CellReferenceRC {
+            sheet: sheet_name,
+            row: cell.row,
+            column: cell.column,
+        };
+        Ok(self.get_evaluation_step(&node, &mut 0, cell, &context, &steps))
+    }
+
+    /// Builds the step of `node` and its children. `position` is the position of `node` in the
+    /// walk of the steps of the formula and it is advanced past its last descendant.
+    fn get_evaluation_step(
+        &mut self,
+        node: &Node,
+        position: &mut usize,
+        cell: CellReference,
+        context: &CellReferenceRC,
+        steps: &HashMap<usize, CalcResult>,
+    ) -> EvaluationStep {
+        let node_position = *position;
+        *position += 1;
+        let value = match node {
+            // References show the cells they point to, even when a function reads them directly
+            Node::ReferenceKind { .. } | Node::RangeKind { .. } => {
+                let result = self.evaluate_node_with_reference(node, cell);
+                Some(self.get_step_value(&result))
+            }
+            _ => steps
+                .get(&node_position)
+                .map(|result| self.get_step_value(result)),
+        };
+        let children = step_children(node)
+            .into_iter()
+            .map(|child| self.get_evaluation_step(child, position, cell, context, steps))
+            .collect();
+        EvaluationStep {
+            formula: to_string(node, context),
+            value,
+            children,
+        }
+    }
+
+    fn get_step_value(&mut self, result: &CalcResult) -> StepValue {
+        match result {
+            CalcResult::Number(value) => StepValue::Number { value: *value },
+            CalcResult::String(value) => StepValue::Text {
+                value: value.clone(),
+            },
+            CalcResult::Boolean(value) => StepValue::Boolean { value: *value },
+            CalcResult::Error {
+                error,
+                origin,
+                message,
+            } => StepValue::Error {
+                error: error.to_string(),
+                origin: CellReferenceIndex {
+                    sheet: origin.sheet,
+                    row: origin.row,
+                    column: origin.column,
+                },
+                message: message.clone(),
+            },
+            CalcResult::Range { left, right } => {
+                let mut range = Range {
+                    left: *left,
+                    right: *right,
+                };
+                if let Ok(worksheet) = self.workbook.worksheet(left.sheet) {
+                    let dimension = worksheet.dimension();
+                    if left.row == 1 && right.row == LAST_ROW {
+                        range.right.row = dimension.max_row;
+                    }
+                    if left.column == 1 && right.column == LAST_COLUMN {
+                        range.right.column = dimension.max_column;
+                    }
+                }
+                let cells = (range.right.row - range.left.row + 1) as i64
+                    * (range.right.column - range.left.column + 1) as i64;
+                let values = if left.sheet == right.sheet && cells <= MAX_REFERENCE_CELLS {
+                    let array = self.range_to_array(&range);
+                    self.get_array_values(&array)
+                } else {
+                    Vec::new()
+                };
+                StepValue::Reference {
+                    area: Area {
+                        sheet: left.sheet,
+                        row: left.row,
+                        column: left.column,
+                        width: right.column - left.column + 1,
+                        height: right.row - left.row + 1,
+                    },
+                    values,
+                }
+            }
+            CalcResult::Array(array) => StepValue::Array {
+                values: self.get_array_values(array),
+            },
+            CalcResult::EmptyCell | CalcResult::EmptyArg => StepValue::Empty,
+            CalcResult::Lambda(_) => StepValue::Lambda,
+            CalcResult::Areas(ranges) => StepValue::Areas {
+                areas: ranges
+                    .iter()
+                    .map(|range| {
+                        self.get_step_value(&CalcResult::Range {
+                            left: range.left,
+                            right: range.right,
+                        })
+                    })
+                    .collect(),
+            },
+        }
+    }
+
+    fn get_array_values(&mut self, array: &[Vec<CalcResult>]) -> Vec<Vec<StepValue>> {
+        array
+            .iter()
+            .map(|row| row.iter().map(|value| self.get_step_value(value)).collect())
+            .collect()
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{dependencies::CellKey, model::Model, types::Cell};
+
+/// A change in the model, see `Model::take_events`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub enum ModelEvent {
+    /// The content, value or style of a cell changed
+    CellChanged {
+        sheet: u32,
+        row: i32,
+        column: i32,
+    },
+    RowsInserted {
+        sheet: u32,
+        row: i32,
+        count: i32,
+    },
+    RowsDeleted {
+        sheet: u32,
+        row: i32,
+        count: i32,
+    },
+    ColumnsInserted {
+        sheet: u32,
+        column: i32,
+        count: i32,
+    },
+    ColumnsDeleted {
+        sheet: u32,
+        column: i32,
+        count: i32,
+    },
+    /// A sheet was added at index `sheet`, moving the next sheets one position
+    SheetAdded {
+        sheet: u32,
+    },
+    /// The sheet at index `sheet` was deleted, moving the next sheets one position back
+    SheetDeleted {
+        sheet: u32,
+    },
+    /// The sheet at index `sheet` was moved to `new_index`, the sheets in between move one
+    /// position towards `sheet`
+    SheetMoved {
+        sheet: u32,
+        new_index: u32,
+    },
+    SheetRenamed {
+        sheet: u32,
+        old_name: String,
+        new_name: String,
+    },
+}
+
+/// The events recorded since they were last taken
+#[derive(Clone, Default)]
+pub(crate) struct Events {
+    enabled: bool,
+    /// The cells modified since the last evaluation, as they were before being modified
+    old_cells: HashMap<CellKey, Option<Cell>>,
+    queue: Vec<ModelEvent>,
+}
+
+impl Model {
+    /// Starts or stops recording the events returned by `take_events`.
+    /// Events are not recorded by default.
+    pub fn set_events_enabled(&mut self, enabled: bool) {
+        self.events = Events {
+            enabled,
+            ..Default::default()
+        };
+    }
+
+    /// Returns the events since the last call, in the order they happened.
+    /// Each evaluation reports the cells whose content, value or style changed since
+    /// the previous one. Changes in the structure of the workbook, like inserting rows or
+    /// renaming a sheet, are reported as they are made.
+    pub fn take_events(&mut self) -> Vec<ModelEvent> {
+        std::mem::take(&mut self.events.queue)
+    }
+
+    /// Takes note of the cell before it is modified, to report it if it changes
+    pub(crate) fn touch_cell(&mut self, sheet: u32, row: i32, column: i32) {
+        if !self.events.enabled || self.events.old_cells.contains_key(&(sheet, row, column)) {
+            return;
+        }
+        let cell = self
+            .workbook
+            .worksheets
+            .get(sheet as usize)
+            .and_then(|worksheet| worksheet.cell(row, column))
+            .cloned();
+        self.events.old_cells.insert((sheet, row, column), cell);
+    }
+
+    /// Takes note of a cell that was already modified, given its content before the change
+    pub(crate) fn touch_cell_with(&mut self, key: CellKey, old_cell: Option<&Cell>) {
+        if self.events.enabled {
+            self.events
+                .old_cells
+                .entry(key)
+                .or_insert_with(|| old_cell.cloned());
+        }
+    }
+
+    pub(crate) fn push_event(&mut self, event: ModelEvent) {
+        if !self.events.enabled {
+            return;
+        }
+        // Sheets after the one added or deleted change their index
+        match event {
+            ModelEvent::SheetAdded { sheet } => self.shift_touched_sheets(sheet, 1),
+            ModelEvent::SheetDeleted { sheet } => {
+                self.events.old_cells.retain(|key, _| key.0 != sheet);
+                self.shift_touched_sheets(sheet + 1, -1);
+            }
+            _ => {}
+        }
+        self.events.queue.push(event);
+    }
+
+    /// Moves the cells in the sheets from `sheet` on by `delta` sheets
+    fn shift_touched_sheets(&mut self, sheet: u32, delta: i32) {
+        let old_cells = std::mem::take(&mut self.events.old_cells);
+        self.events.old_cells = old_cells
+            .into_iter()
+            .map(|((s, row, column), cell)| {
+                if s >= sheet {
+                    (((s as i32 + delta) as u32, row, column), cell)
+                } else {
+                    ((s, row, column), cell)
+                }
+            })
+            .collect();
+    }
+
+    /// Reports the cells that changed since the last evaluation
+    pub(crate) fn push_cell_events(&mut self) {
+        let old_cells = std::mem::take(&mut self.events.old_cells);
+        let mut changed: Vec<CellKey> = old_cells
+            .into_iter()
+            .filter(|((sheet, row, column), old_cell)| {
+                let cell = self
+                    .workbook
+                    .worksheets
+                    .get(*sheet as usize)
+                    .and_then(|worksheet| worksheet.cell(*row, *column));
+                cell != old_cell.as_ref()
+            })
+            .map(|(key, _)| key)
+            .collect();
+        changed.sort_unstable();
+        for (sheet, row, column) in changed {
+            self.events
+                .queue
+                .push(ModelEvent::CellChanged { sheet, row, column });
+        }
+    }
+}
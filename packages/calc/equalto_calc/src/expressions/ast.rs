@@ -0,0 +1,368 @@
+//! Public API to inspect and transform the parse tree of a formula.
+//!
+//! The tree is made of [`Node`]s. Every node comes with a [`Span`] with its position in the
+//! formula, and the children of a node are always listed in the order given by [`children`],
+//! which is the order they are written in the formula.
+//!
+//! Changes to [`Node`], [`Span`] or the order of the children that are not backwards compatible
+//! bump [`AST_VERSION`].
+//!
+//! ```
+//! use equalto_calc::expressions::ast::{ast_to_formula, collect_references, parse_formula, ParseOptions};
+//!
+//! let options = ParseOptions::default();
+//! let parsed = parse_formula("SUM(A1:A3)*B1", &options);
+//! assert_eq!(collect_references(&parsed.node).len(), 2);
+//! assert_eq!(ast_to_formula(&parsed.node, &options.context()), "SUM(A1:A3)*B1");
+//! ```
+
+use std::collections::HashMap;
+
+use crate::{functions::Function, types::Table};
+
+use super::parser::{stringify::to_string, Parser};
+use super::types::CellReferenceRC;
+
+pub use super::parser::Node;
+
+/// Version of the parse tree, see the module documentation
+pub const AST_VERSION: u32 = 5;
+
+/// Position of a node in the formula, in characters and without the leading `=`.
+/// `start` is inclusive and `end` exclusive. Parenthesis around an expression are not part of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    /// The spans of the children of the node, in the order given by [`children`]
+    pub children: Vec<Span>,
+}
+
+/// A formula parsed with the positions of its nodes
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedFormula {
+    pub node: Node,
+    /// For formulas with errors the span of the whole formula without children
+    pub span: Span,
+}
+
+/// What is needed to parse a formula: the sheets and tables of the workbook and the cell
+/// the formula is in, relative references are stored relative to it.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    pub sheets: Vec<String>,
+    pub tables: HashMap<String, Table>,
+    pub sheet: String,
+    pub row: i32,
+    pub column: i32,
+}
+
+impl Default for ParseOptions {
+    /// A workbook with a single sheet, "Sheet1", and the formula in A1
+    fn default() -> Self {
+        ParseOptions {
+            sheets: vec!["Sheet1".to_string()],
+            tables: HashMap::new(),
+            sheet: "Sheet1".to_string(),
+            row: 1,
+            column: 1,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// The cell the formula is in
+    pub fn context(&self) -> CellReferenceRC {
+        CellReferenceRC {
+            sheet: self.sheet.clone(),
+            row: self.row,
+            column: self.column,
+        }
+    }
+}
+
+/// Parses `formula` (without the leading `=`). Syntax errors are reported as a
+/// [`Node::ParseErrorKind`], never as a failure.
+pub fn parse_formula(formula: &str, options: &ParseOptions) -> ParsedFormula {
+    let mut parser = Parser::new(options.sheets.clone(), options.tables.clone());
+    let (node, span) = parser.parse_with_spans(formula, &Some(options.context()));
+    ParsedFormula { node, span }
+}
+
+/// Returns the formula of `node`, in English and with references relative to `context`.
+/// Parsing the result gives back the same tree.
+pub fn ast_to_formula(node: &Node, context: &CellReferenceRC) -> String {
+    to_string(node, context)
+}
+
+/// Returns the children of `node` in the order they are written in the formula
+pub fn children(node: &Node) -> Vec<&Node> {
+    match node {
+        Node::OpRangeKind { left, right }
+        | Node::OpIntersectKind { left, right }
+        | Node::OpConcatenateKind { left, right }
+        | Node::OpSumKind { left, right, .. }
+        | Node::OpProductKind { left, right, .. }
+        | Node::OpPowerKind { left, right }
+        | Node::CompareKind { left, right, .. } => vec![left, right],
+        Node::UnaryKind { right, .. } => vec![right],
+        Node::SpillRangeKind(reference) => vec![reference],
+        Node::FunctionKind { args, .. } | Node::InvalidFunctionKind { args, .. } => {
+            args.iter().collect()
+        }
+        Node::CallKind { function, args } => {
+            let mut children = vec![function.as_ref()];
+            children.extend(args);
+            children
+        }
+        Node::ArrayKind(rows) => rows.iter().flatten().collect(),
+        Node::OpUnionKind(items) => items.iter().collect(),
+        Node::BooleanKind(_)
+        | Node::NumberKind(_)
+        | Node::StringKind(_)
+        | Node::ReferenceKind { .. }
+        | Node::RangeKind { .. }
+        | Node::WrongReferenceKind { .. }
+        | Node::WrongRangeKind { .. }
+        | Node::ExternalReferenceKind { .. }
+        | Node::ExternalRangeKind { .. }
+        | Node::SheetSpanReferenceKind { .. }
+        | Node::SheetSpanRangeKind { .. }
+        | Node::TableReferenceKind { .. }
+        | Node::VariableKind(_)
+        | Node::ErrorKind(_)
+        | Node::ParseErrorKind { .. }
+        | Node::EmptyArgKind => Vec::new(),
+    }
+}
+
+/// Mutable version of [`children`]
+pub fn children_mut(node: &mut Node) -> Vec<&mut Node> {
+    match node {
+        Node::OpRangeKind { left, right }
+        | Node::OpIntersectKind { left, right }
+        | Node::OpConcatenateKind { left, right }
+        | Node::OpSumKind { left, right, .. }
+        | Node::OpProductKind { left, right, .. }
+        | Node::OpPowerKind { left, right }
+        | Node::CompareKind { left, right, .. } => vec![left, right],
+        Node::UnaryKind { right, .. } => vec![right],
+        Node::SpillRangeKind(reference) => vec![reference],
+        Node::FunctionKind { args, .. } | Node::InvalidFunctionKind { args, .. } => {
+            args.iter_mut().collect()
+        }
+        Node::CallKind { function, args } => {
+            let mut children = vec![function.as_mut()];
+            children.extend(args);
+            children
+        }
+        Node::ArrayKind(rows) => rows.iter_mut().flatten().collect(),
+        Node::OpUnionKind(items) => items.iter_mut().collect(),
+        Node::BooleanKind(_)
+        | Node::NumberKind(_)
+        | Node::StringKind(_)
+        | Node::ReferenceKind { .. }
+        | Node::RangeKind { .. }
+        | Node::WrongReferenceKind { .. }
+        | Node::WrongRangeKind { .. }
+        | Node::ExternalReferenceKind { .. }
+        | Node::ExternalRangeKind { .. }
+        | Node::SheetSpanReferenceKind { .. }
+        | Node::SheetSpanRangeKind { .. }
+        | Node::TableReferenceKind { .. }
+        | Node::VariableKind(_)
+        | Node::ErrorKind(_)
+        | Node::ParseErrorKind { .. }
+        | Node::EmptyArgKind => Vec::new(),
+    }
+}
+
+/// Returns true if `span` has the shape of the tree of `node`
+pub(crate) fn span_matches(node: &Node, span: &Span) -> bool {
+    let children = children(node);
+    children.len() == span.children.len()
+        && children
+            .iter()
+            .zip(&span.children)
+            .all(|(child, child_span)| span_matches(child, child_span))
+}
+
+/// Visits the nodes of a tree in depth first order, see [`walk`]
+pub trait Visitor {
+    /// Called before the children of `node`. Returning false skips them.
+    fn enter(&mut self, _node: &Node) -> bool {
+        true
+    }
+
+    /// Called after the children of `node`, if they were not skipped
+    fn leave(&mut self, _node: &Node) {}
+}
+
+/// Visits the nodes of a tree in depth first order and can change them, see [`walk_mut`]
+pub trait VisitorMut {
+    /// Called before the children of `node`, that can be replaced. Returning false skips them.
+    fn enter(&mut self, _node: &mut Node) -> bool {
+        true
+    }
+
+    /// Called after the children of `node`, if they were not skipped
+    fn leave(&mut self, _node: &mut Node) {}
+}
+
+/// Calls `visitor` for `node` and all its descendants
+pub fn walk<V: Visitor>(node: &Node, visitor: &mut V) {
+    if visitor.enter(node) {
+        for child in children(node) {
+            walk(child, visitor);
+        }
+        visitor.leave(node);
+    }
+}
+
+/// Calls `visitor` for `node` and all its descendants. The children are visited after `enter`,
+/// so they are the ones it leaves.
+pub fn walk_mut<V: VisitorMut>(node: &mut Node, visitor: &mut V) {
+    if visitor.enter(node) {
+        for child in children_mut(node) {
+            walk_mut(child, visitor);
+        }
+        visitor.leave(node);
+    }
+}
+
+struct Collector<'a, F: Fn(&Node) -> bool> {
+    filter: F,
+    nodes: Vec<&'a Node>,
+}
+
+impl<'a, F: Fn(&Node) -> bool> Collector<'a, F> {
+    fn collect(mut self, node: &'a Node) -> Vec<&'a Node> {
+        self.visit(node);
+        self.nodes
+    }
+
+    // The trait can't hand out references that outlive the call, so the walk is done here
+    fn visit(&mut self, node: &'a Node) {
+        if (self.filter)(node) {
+            self.nodes.push(node);
+        }
+        for child in children(node) {
+            self.visit(child);
+        }
+    }
+}
+
+/// Returns the references in `node`, in the order they are written: cells, ranges and
+/// wrong references (`#REF!` after a deletion), references to other workbooks, to spans of
+/// sheets and to tables.
+/// The operands of `A1:B3` are returned separately.
+pub fn collect_references(node: &Node) -> Vec<&Node> {
+    let collector = Collector {
+        filter: |node: &Node| {
+            matches!(
+                node,
+                Node::ReferenceKind { .. }
+                    | Node::RangeKind { .. }
+                    | Node::WrongReferenceKind { .. }
+                    | Node::WrongRangeKind { .. }
+                    | Node::ExternalReferenceKind { .. }
+                    | Node::ExternalRangeKind { .. }
+                    | Node::SheetSpanReferenceKind { .. }
+                    | Node::SheetSpanRangeKind { .. }
+                    | Node::TableReferenceKind { .. }
+            )
+        },
+        nodes: Vec::new(),
+    };
+    collector.collect(node)
+}
+
+/// Returns the function calls in `node`, in the order they are written, including calls to
+/// unknown functions and to LAMBDAs
+pub fn collect_function_calls(node: &Node) -> Vec<&Node> {
+    let collector = Collector {
+        filter: |node: &Node| {
+            matches!(
+                node,
+                Node::FunctionKind { .. }
+                    | Node::InvalidFunctionKind { .. }
+                    | Node::CallKind { .. }
+            )
+        },
+        nodes: Vec::new(),
+    };
+    collector.collect(node)
+}
+
+/// Versions of Excel without dynamic arrays reduce a range to a single cell wherever a value is
+/// expected: the result of the formula and the operands of operators.
+/// The visitor applies its function to the operands.
+struct ImplicitIntersections(fn(&mut Node));
+
+impl VisitorMut for ImplicitIntersections {
+    fn enter(&mut self, node: &mut Node) -> bool {
+        match node {
+            Node::OpSumKind { left, right, .. }
+            | Node::OpProductKind { left, right, .. }
+            | Node::OpPowerKind { left, right }
+            | Node::OpConcatenateKind { left, right }
+            | Node::CompareKind { left, right, .. } => {
+                (self.0)(left);
+                (self.0)(right);
+            }
+            Node::UnaryKind { right, .. } => (self.0)(right),
+            _ => {}
+        }
+        true
+    }
+}
+
+/// Returns true if `node` may evaluate to a range
+fn may_be_range(node: &Node) -> bool {
+    match node {
+        Node::RangeKind { .. }
+        | Node::OpRangeKind { .. }
+        | Node::OpIntersectKind { .. }
+        | Node::TableReferenceKind { .. } => true,
+        Node::FunctionKind { kind, .. } => kind.returns_reference(),
+        _ => false,
+    }
+}
+
+/// Wraps `node` in SINGLE if it may evaluate to a range
+fn add_single(node: &mut Node) {
+    if may_be_range(node) {
+        let range = std::mem::replace(node, Node::EmptyArgKind);
+        *node = Node::FunctionKind {
+            kind: Function::Single,
+            args: vec![range],
+        };
+    }
+}
+
+/// Unwraps `node` if it is a SINGLE that [`add_single`] would add
+fn remove_single(node: &mut Node) {
+    if let Node::FunctionKind {
+        kind: Function::Single,
+        args,
+    } = node
+    {
+        if args.len() == 1 && may_be_range(&args[0]) {
+            *node = args.remove(0);
+        }
+    }
+}
+
+/// Rewrites a formula saved by a version of Excel without dynamic arrays so that it keeps its
+/// meaning: the ranges it reduces to a single cell are wrapped in `SINGLE`, the `@` operator.
+pub fn add_implicit_intersections(node: &mut Node) {
+    walk_mut(node, &mut ImplicitIntersections(add_single));
+    add_single(node);
+}
+
+/// The inverse of [`add_implicit_intersections`]: removes the `SINGLE` around the ranges that
+/// versions of Excel without dynamic arrays reduce to a single cell anyway.
+pub fn remove_implicit_intersections(node: &mut Node) {
+    remove_single(node);
+    walk_mut(node, &mut ImplicitIntersections(remove_single));
+}
@@ -25,6 +25,9 @@
 //! The names of the errors and functions are different in different languages,
 //! but they stay the same in different locales.
 //!
+//! In locales where the comma is the decimal separator the arguments of functions are separated
+//! by `;` and the columns of arrays by `.`: =SUMME(A1; 2,5; {1.2;3.4})
+//!
 //! Note that in EqualTo if you are using a locale different from 'en' or a language different from 'en'
 //! you will still need the 'en' locale and language because formulas are stored in that language and locale
 //!
@@ -106,6 +109,28 @@ impl Lexer {
         self.mode = mode;
     }
 
+    /// Changes the locale (numbers and separators) and the language (names of functions,
+    /// booleans and errors) of the formulas
+    pub fn set_locale(&mut self, locale: &Locale, language: &Language) {
+        self.locale = locale.clone();
+        self.language = language.clone();
+    }
+
+    /// Returns the language of the formulas
+    pub fn get_language(&self) -> &Language {
+        &self.language
+    }
+
+    /// Returns the token that separates the arguments of functions, `,` or `;` depending on
+    /// the locale
+    pub fn argument_separator(&self) -> TokenType {
+        if self.locale.formula_list_separator() == ';' {
+            TokenType::Semicolon
+        } else {
+            TokenType::Comma
+        }
+    }
+
     // FIXME: I don't think we should have `is_a1_mode` and   `get_formula`.
     // The caller already knows those two
 
@@ -126,6 +151,15 @@ impl Lexer {
         self.position as i32
     }
 
+    /// Returns the position where the next token starts, after any whitespace
+    pub fn get_next_token_position(&self) -> usize {
+        let mut position = self.position;
+        while position < self.len && self.chars[position].is_whitespace() {
+            position += 1;
+        }
+        position
+    }
+
     /// Resets the formula
     pub fn set_formula(&mut self, content: &str) {
         self.chars = content.chars().collect();
@@ -179,7 +213,20 @@ impl Lexer {
                     '=' => TokenType::Compare(OpCompare::Equal),
                     '{' => TokenType::LeftBrace,
                     '}' => TokenType::RightBrace,
-                    '[' => TokenType::LeftBracket,
+                    '[' => {
+                        if let Some(reference) = self.consume_external_reference() {
+                            return reference;
+                        }
+                        if self.peek_char() == Some(']') {
+                            return TokenType::LeftBracket;
+                        }
+                        // A structured reference without the table name: [@Price]
+                        self.position -= 1;
+                        match self.consume_structured_reference("") {
+                            Ok(reference) => reference,
+                            Err(error) => TokenType::Illegal(error),
+                        }
+                    }
                     ']' => TokenType::RightBracket,
                     ':' => TokenType::Colon,
                     ';' => TokenType::Semicolon,
@@ -199,6 +246,9 @@ impl Lexer {
                                 Ok(number) => TokenType::Number(number),
                                 Err(error) => TokenType::Illegal(error),
                             }
+                        } else if self.locale.formula_array_column_separator() == '.' {
+                            // The column separator of arrays: {1.2;3.4}
+                            TokenType::Comma
                         } else {
                             // There is no TokenType::PERIOD
                             TokenType::Illegal(self.set_error("Expecting a number", self.position))
@@ -229,7 +279,18 @@ impl Lexer {
                             TokenType::Compare(OpCompare::GreaterThan)
                         }
                     }
-                    '#' => self.consume_error(),
+                    '#' => {
+                        // A '#' at the end of the formula or followed by an operator, a separator
+                        // or a closing bracket is the spilled range operator: A1#
+                        // Otherwise it is an error: #REF!, #N/A, #¡VALOR!, ...
+                        match self.peek_char() {
+                            None => TokenType::Hash,
+                            Some(c) if c.is_whitespace() || "+-*/^&=<>%:,;)}".contains(c) => {
+                                TokenType::Hash
+                            }
+                            Some(_) => self.consume_error(),
+                        }
+                    }
                     '"' => TokenType::String(self.consume_string()),
                     '\'' => self.consume_quoted_sheet_reference(),
                     '0'..='9' => {
@@ -302,6 +363,10 @@ impl Lexer {
                             } else if peek_char == Some('$') {
                                 self.position = position - 1;
                                 return self.consume_range(None);
+                            } else if next_char_is_colon {
+                                if let Some(token) = self.consume_sheet_span(&name) {
+                                    return token;
+                                }
                             }
                             let name_upper = name.to_ascii_uppercase();
                             if name_upper == self.language.booleans.true_value {
@@ -352,17 +417,11 @@ impl Lexer {
                                             return TokenType::Illegal(error);
                                         }
                                     }
-                                } else if utils::is_valid_identifier(&name) {
-                                    if peek_char == Some('[') {
-                                        if let Ok(r) = self.consume_structured_reference(&name) {
-                                            return r;
-                                        }
-                                        return TokenType::Illegal(self.set_error(
-                                            "Invalid structured reference",
-                                            self.position,
-                                        ));
-                                    }
-                                    return TokenType::Ident(name);
+                                } else if utils::is_valid_identifier(&name)
+                                    // Names of functions in other languages might not be ASCII
+                                    || self.language.get_function(&name).is_some()
+                                {
+                                    return self.identifier_or_structured_reference(name);
                                 } else {
                                     return TokenType::Illegal(
                                         self.set_error("Invalid identifier (A1)", self.position),
@@ -378,7 +437,8 @@ impl Lexer {
                                         if pos > self.position {
                                             self.position = pos;
                                             if utils::is_valid_identifier(&name) {
-                                                return TokenType::Ident(name);
+                                                return self
+                                                    .identifier_or_structured_reference(name);
                                             } else {
                                                 self.position = self.len;
                                                 return TokenType::Illegal(
@@ -421,7 +481,7 @@ impl Lexer {
                                         self.position = pos;
 
                                         if utils::is_valid_identifier(&name) {
-                                            return TokenType::Ident(name);
+                                            return self.identifier_or_structured_reference(name);
                                         } else {
                                             return TokenType::Illegal(self.set_error(
                                                 &format!("Invalid identifier (R1C1): {name}"),
@@ -450,6 +510,19 @@ impl Lexer {
         }
     }
 
+    /// Returns the identifier `name` or the structured reference starting with it: Sales[Price]
+    fn identifier_or_structured_reference(&mut self, name: String) -> TokenType {
+        if self.peek_char() == Some('[') {
+            if let Ok(r) = self.consume_structured_reference(&name) {
+                return r;
+            }
+            return TokenType::Illegal(
+                self.set_error("Invalid structured reference", self.position),
+            );
+        }
+        TokenType::Ident(name)
+    }
+
     fn peek_char(&mut self) -> Option<char> {
         let position = self.position;
         if position < self.len {
@@ -736,6 +809,44 @@ impl Lexer {
         self.consume_range(Some(sheet_name))
     }
 
+    fn consume_external_reference(&mut self) -> Option<TokenType> {
+        // This might be a reference to another workbook:
+        // [Rates.xlsx]Sheet1!$B$2
+        // The sheet name keeps the workbook, like in quoted references: '[Rates.xlsx]Sheet 1'!B2
+        let position = self.position;
+        let end = self.chars[position..]
+            .iter()
+            .position(|&c| c == ']')
+            .map(|end| position + end)?;
+        let book: String = self.chars[position..end].iter().collect();
+        if book.is_empty() || book.contains(['[', '\'', '"']) {
+            return None;
+        }
+        self.position = end + 1;
+        let sheet = self.consume_identifier();
+        if sheet.is_empty() || self.peek_char() != Some('!') {
+            self.position = position;
+            return None;
+        }
+        self.position += 1;
+        Some(self.consume_range(Some(format!("[{}]{}", book, sheet))))
+    }
+
+    fn consume_sheet_span(&mut self, first_sheet: &str) -> Option<TokenType> {
+        // This might be a reference to the same cells in a span of sheets:
+        // Jan:Dec!B5
+        // Like in quoted references the sheet name is the whole span: 'Jan 2023:Dec 2023'!B5
+        let position = self.position;
+        self.position += 1;
+        let last_sheet = self.consume_identifier();
+        if last_sheet.is_empty() || self.peek_char() != Some('!') {
+            self.position = position;
+            return None;
+        }
+        self.position += 1;
+        Some(self.consume_range(Some(format!("{}:{}", first_sheet, last_sheet))))
+    }
+
     fn consume_range(&mut self, sheet: Option<String>) -> TokenType {
         let m = if self.mode == LexerMode::A1 {
             self.consume_range_a1()
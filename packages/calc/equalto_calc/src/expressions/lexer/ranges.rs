@@ -205,6 +205,7 @@ impl Lexer {
 
     pub(super) fn consume_range_r1c1(&mut self) -> Result<ParsedRange> {
         // first let's try to parse a cell
+        let position = self.position;
         match self.consume_reference_r1c1() {
             Ok(cell) => {
                 if self.peek_char() == Some(':') {
@@ -226,12 +227,116 @@ impl Lexer {
                     })
                 }
             }
-            Err(s) => Err(s),
+            Err(_) => {
+                // It's either a row range or a column range (or not a range at all)
+                self.position = position;
+                self.consume_rows_or_columns_r1c1()
+            }
+        }
+    }
+
+    // Whole rows and columns in R1C1 style:
+    //
+    //    range  -> row | row ':' row | column | column ':' column
+    //    row    -> 'R' | 'R' number | 'R[' integer ']'
+    //    column -> 'C' | 'C' number | 'C[' integer ']'
+    //
+    // A single row or column is a range: R2 is 2:2 in A1 style.
+    fn consume_rows_or_columns_r1c1(&mut self) -> Result<ParsedRange> {
+        let (kind, left, absolute_left) = self.consume_row_or_column_r1c1()?;
+        let (right, absolute_right) = if self.peek_char() == Some(':') {
+            let position = self.position;
+            self.position += 1;
+            match self.consume_row_or_column_r1c1() {
+                Ok((right_kind, right, absolute_right)) if right_kind == kind => {
+                    (right, absolute_right)
+                }
+                _ => return Err(self.set_error("Expecting reference in range", position)),
+            }
+        } else {
+            (left, absolute_left)
+        };
+        if kind == 'R' {
+            Ok(ParsedRange {
+                left: ParsedReference {
+                    row: left,
+                    absolute_row: absolute_left,
+                    column: 1,
+                    absolute_column: true,
+                },
+                right: Some(ParsedReference {
+                    row: right,
+                    absolute_row: absolute_right,
+                    column: LAST_COLUMN,
+                    absolute_column: true,
+                }),
+            })
+        } else {
+            Ok(ParsedRange {
+                left: ParsedReference {
+                    row: 1,
+                    absolute_row: true,
+                    column: left,
+                    absolute_column: absolute_left,
+                },
+                right: Some(ParsedReference {
+                    row: LAST_ROW,
+                    absolute_row: true,
+                    column: right,
+                    absolute_column: absolute_right,
+                }),
+            })
         }
     }
 
+    // Consumes a row or a column in R1C1 style: R3, R[-1], R, C2, C[4], C
+    // Returns 'R' or 'C', the number and whether it is absolute
+    fn consume_row_or_column_r1c1(&mut self) -> Result<(char, i32, bool)> {
+        let position = self.position;
+        let kind = match self.read_next_char() {
+            Some(c) if c == 'R' || c == 'C' => c,
+            _ => return Err(self.set_error("Expected row or column", position)),
+        };
+        let (number, absolute) = match self.peek_char() {
+            Some('[') => {
+                self.expect_char('[')?;
+                let c = match self.read_next_char() {
+                    Some(s) => s,
+                    None => {
+                        return Err(self.set_error("Expected row or column number", position));
+                    }
+                };
+                let number = match self.consume_integer(c) {
+                    Ok(v) => v,
+                    Err(_) => {
+                        return Err(self.set_error("Expected row or column number", position));
+                    }
+                };
+                self.expect(TokenType::RightBracket)?;
+                (number, false)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                self.expect_char(c)?;
+                match self.consume_integer(c) {
+                    Ok(v) => (v, true),
+                    Err(_) => {
+                        return Err(self.set_error("Expected row or column number", position));
+                    }
+                }
+            }
+            // R is R[0], the row of the formula
+            _ => (0, false),
+        };
+        if let Some(c) = self.peek_char() {
+            if c.is_alphanumeric() || c == '[' {
+                return Err(self.set_error("Expected end of reference", position));
+            }
+        }
+        Ok((kind, number, absolute))
+    }
+
     pub(super) fn consume_reference_r1c1(&mut self) -> Result<ParsedReference> {
-        // R12C3, R[2]C[-2], R3C[6], R[-3]C4, RC1, R[-2]C
+        // R12C3, R[2]C[-2], R3C[6], R[-3]C4, RC1, R[-2]C, RC
         let absolute_column;
         let absolute_row;
         let position = self.position;
@@ -256,7 +361,7 @@ impl Lexer {
                 }
                 self.expect(TokenType::RightBracket)?;
             }
-            Some(c) => {
+            Some(c) if c.is_ascii_digit() => {
                 absolute_row = true;
                 self.expect_char(c)?;
                 match self.consume_integer(c) {
@@ -266,8 +371,10 @@ impl Lexer {
                     }
                 }
             }
-            None => {
-                return Err(self.set_error("Expected row number or '['", position));
+            _ => {
+                // RC1 is R[0]C1
+                absolute_row = false;
+                row = 0;
             }
         }
         self.expect_char('C')?;
@@ -289,7 +396,7 @@ impl Lexer {
                 }
                 self.expect(TokenType::RightBracket)?;
             }
-            Some(c) => {
+            Some(c) if c.is_ascii_digit() => {
                 absolute_column = true;
                 self.expect_char(c)?;
                 match self.consume_integer(c) {
@@ -299,8 +406,10 @@ impl Lexer {
                     }
                 }
             }
-            None => {
-                return Err(self.set_error("Expected column number or '['", position));
+            _ => {
+                // R1C is R1C[0]
+                absolute_column = false;
+                column = 0;
             }
         }
         if let Some(c) = self.peek_char() {
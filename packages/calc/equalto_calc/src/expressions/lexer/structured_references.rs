@@ -1,14 +1,16 @@
 // Grammar:
-// structured references -> table_name "[" arguments "]"
-//  arguments -> table_reference | "["specifier"]"  "," table_reference
+// structured references -> table_name? "[" arguments? "]"
+//  arguments -> specifier | "@" this_row? | column_name | items
+//  this_row -> column_name | "[" column_name "]" (":" "[" column_name "]")?
+//  items -> item ("," item)*
+//  item -> "[" specifier "]" | "[" column_name "]" (":" "[" column_name "]")?
 //  specifier > "#All"      |
 //              "#This Row" |
 //              "#Data"     |
 //              "#Headers"  |
 //              "#Totals"
-// table_reference -> column_reference | range_reference
-// column reference -> column_name | "["column_name"]"
-// range_reference -> column_reference":"column_reference
+//
+// The table name is missing in references from a cell of the table to the table itself: [@Price]
 
 use crate::expressions::token::TokenType;
 use crate::expressions::token::{TableReference, TableSpecifier};
@@ -16,78 +18,77 @@ use crate::expressions::token::{TableReference, TableSpecifier};
 use super::Result;
 use super::{Lexer, LexerError};
 
+/// Returns the specifier of a list of them: [#Headers],[#Data]
+fn combine_specifiers(specifiers: &[TableSpecifier]) -> Option<Option<TableSpecifier>> {
+    use TableSpecifier::*;
+    match specifiers {
+        [] => Some(None),
+        [specifier] => Some(Some(specifier.clone())),
+        [Headers, Data] | [Data, Headers] => Some(Some(HeadersAndData)),
+        [Data, Totals] | [Totals, Data] => Some(Some(DataAndTotals)),
+        _ => None,
+    }
+}
+
 impl Lexer {
-    fn consume_table_specifier(&mut self) -> Result<Option<TableSpecifier>> {
-        if self.peek_char() == Some('#') {
-            // It's a specifier
-            // TODO(TD): There are better ways of doing this :)
-            let rest_of_formula: String = self.chars[self.position..self.len].iter().collect();
-            let specifier = if rest_of_formula.starts_with("#This Row]") {
-                self.position += "#This Row]".bytes().len();
-                TableSpecifier::ThisRow
-            } else if rest_of_formula.starts_with("#All]") {
-                self.position += "#All]".bytes().len();
-                TableSpecifier::All
-            } else if rest_of_formula.starts_with("#Data]") {
-                self.position += "#Data]".bytes().len();
-                TableSpecifier::Data
-            } else if rest_of_formula.starts_with("#Headers]") {
-                self.position += "#Headers]".bytes().len();
-                TableSpecifier::Headers
-            } else if rest_of_formula.starts_with("#Totals]") {
-                self.position += "#Totals]".bytes().len();
-                TableSpecifier::Totals
-            } else {
-                return Err(LexerError {
-                    position: self.position,
-                    message: "Invalid structured reference".to_string(),
-                });
-            };
-            Ok(Some(specifier))
-        } else {
-            Ok(None)
-        }
+    fn structured_reference_error(&mut self) -> LexerError {
+        self.set_error("Invalid structured reference", self.position)
     }
 
-    fn consume_column_reference(&mut self) -> Result<String> {
-        self.consume_whitespace();
-        let end_char = if self.peek_char() == Some('[') {
+    /// Consumes a specifier up to the closing bracket: #This Row
+    fn consume_table_specifier(&mut self) -> Result<TableSpecifier> {
+        let start = self.position;
+        while self.position < self.len && self.chars[self.position] != ']' {
             self.position += 1;
-            ']'
-        } else {
-            ')'
-        };
+        }
+        let name: String = self.chars[start..self.position].iter().collect();
+        match name.to_uppercase().as_str() {
+            "#ALL" => Ok(TableSpecifier::All),
+            "#DATA" => Ok(TableSpecifier::Data),
+            "#HEADERS" => Ok(TableSpecifier::Headers),
+            "#THIS ROW" => Ok(TableSpecifier::ThisRow),
+            "#TOTALS" => Ok(TableSpecifier::Totals),
+            _ => Err(self.set_error("Invalid table specifier", start)),
+        }
+    }
 
-        let mut position = self.position;
-        while position < self.len {
-            let next_char = self.chars[position];
-            if next_char != end_char {
-                position += 1;
-                if next_char == '\'' {
-                    if position == self.len {
-                        return Err(LexerError {
-                            position: self.position,
-                            message: "Invalid column name".to_string(),
-                        });
+    /// Consumes the name of a column up to the closing bracket.
+    /// The characters `[`, `]`, `#`, `@` and `'` are escaped with `'`
+    fn consume_column_name(&mut self) -> Result<String> {
+        let mut name = String::new();
+        while self.position < self.len {
+            match self.chars[self.position] {
+                ']' => break,
+                '\'' => {
+                    self.position += 1;
+                    if self.position == self.len {
+                        return Err(self.set_error("Invalid column name", self.position));
                     }
-                    // skip next char
-                    position += 1
+                    name.push(self.chars[self.position]);
                 }
-            } else {
-                break;
+                c => name.push(c),
             }
+            self.position += 1;
         }
-        let chars: String = self.chars[self.position..position].iter().collect();
-        if end_char == ']' {
-            position += 1;
+        if name.is_empty() {
+            return Err(self.set_error("Invalid column name", self.position));
+        }
+        Ok(name)
+    }
+
+    /// Consumes a column or a range of columns in brackets: [Jan] or [Jan]:[Dec]
+    fn consume_column_range(&mut self) -> Result<TableReference> {
+        self.expect_char('[')?;
+        let left = self.consume_column_name()?;
+        self.expect_char(']')?;
+        if self.peek_char() != Some(':') {
+            return Ok(TableReference::ColumnReference(left));
         }
-        self.position = position;
-        Ok(chars
-            .replace("'[", "[")
-            .replace("']", "]")
-            .replace("'#", "#")
-            .replace("'@", "@")
-            .replace("''", "'"))
+        self.position += 1;
+        self.expect_char('[')?;
+        let right = self.consume_column_name()?;
+        self.expect_char(']')?;
+        Ok(TableReference::RangeReference((left, right)))
     }
 
     // Possibilities:
@@ -98,12 +99,10 @@ impl Lexer {
     //  5. MyTable[[#Totals], [MyColumn]]
     //  6. MyTable[[#This Row], [Jan]:[Dec]]
     //  7. MyTable[]
+    //  8. MyTable[[#Headers], [#Data], [MyColumn]]
+    //  9. MyTable[@MyColumn], MyTable[@[My Column]], MyTable[@[Jan]:[Dec]] or MyTable[@]
     //
-    // Multiple specifiers are not supported yet:
-    //  1. MyTable[[#Data], [#Totals], [MyColumn]]
-    //
-    // In particular note that names of columns are escaped only when they are in the first argument
-    // We use '[' and ']'
+    // '@' is the short form of '#This Row'.
     // When there is only a specifier but not a reference the specifier is not in brackets
     //
     // Invalid:
@@ -111,73 +110,64 @@ impl Lexer {
     //
     // NOTES:
     // * MyTable[[#Totals]] is translated into MyTable[#Totals]
-    // * Excel shows '@' instead of '#This Row':
-    //     MyTable[[#This Row], [Jan]:[Dec]] => MyTable[@[Jan]:[Dec]]
-    //   But this is only a UI thing that we will ignore for now.
     pub(crate) fn consume_structured_reference(&mut self, table_name: &str) -> Result<TokenType> {
-        self.expect(TokenType::LeftBracket)?;
-        let peek_char = self.peek_char();
-        if peek_char == Some(']') {
-            // This is just a reference to the full table
-            self.expect(TokenType::RightBracket)?;
-            return Ok(TokenType::Ident(table_name.to_string()));
-        }
-        if peek_char == Some('#') {
-            // Expecting MyTable[#Totals]
-            if let Some(specifier) = self.consume_table_specifier()? {
-                return Ok(TokenType::StructuredReference {
-                    table_name: table_name.to_string(),
-                    specifier: Some(specifier),
-                    table_reference: None,
-                });
-            } else {
-                return Err(LexerError {
-                    position: self.position,
-                    message: "Invalid structured reference".to_string(),
-                });
+        self.expect_char('[')?;
+        let mut specifiers = Vec::new();
+        let mut table_reference = None;
+        match self.peek_char() {
+            Some(']') => {
+                // This is just a reference to the full table
             }
-        } else if peek_char != Some('[') {
-            // Expecting MyTable[MyColumn]
-            self.position -= 1;
-            let column_name = self.consume_column_reference()?;
-            return Ok(TokenType::StructuredReference {
-                table_name: table_name.to_string(),
-                specifier: None,
-                table_reference: Some(TableReference::ColumnReference(column_name)),
-            });
-        }
-        self.expect(TokenType::LeftBracket)?;
-        let specifier = self.consume_table_specifier()?;
-        if specifier.is_some() {
-            let peek_token = self.peek_token();
-            if peek_token == TokenType::Comma {
-                self.advance_token();
-                self.expect(TokenType::LeftBracket)?;
-            } else if peek_token == TokenType::RightBracket {
-                return Ok(TokenType::StructuredReference {
-                    table_name: table_name.to_string(),
-                    specifier,
-                    table_reference: None,
-                });
+            Some('#') => {
+                // Expecting MyTable[#Totals]
+                specifiers.push(self.consume_table_specifier()?);
+            }
+            Some('@') => {
+                self.position += 1;
+                specifiers.push(TableSpecifier::ThisRow);
+                table_reference = match self.peek_char() {
+                    Some(']') => None,
+                    Some('[') => Some(self.consume_column_range()?),
+                    _ => Some(TableReference::ColumnReference(self.consume_column_name()?)),
+                };
+            }
+            Some('[') => {
+                let separator = self.locale.formula_list_separator();
+                loop {
+                    self.consume_whitespace();
+                    if self.peek_char() == Some('[')
+                        && self.chars.get(self.position + 1) == Some(&'#')
+                    {
+                        // Specifiers come before the columns
+                        if table_reference.is_some() {
+                            return Err(self.structured_reference_error());
+                        }
+                        self.position += 1;
+                        specifiers.push(self.consume_table_specifier()?);
+                        self.expect_char(']')?;
+                    } else {
+                        if table_reference.is_some() {
+                            return Err(self.structured_reference_error());
+                        }
+                        table_reference = Some(self.consume_column_range()?);
+                    }
+                    self.consume_whitespace();
+                    if self.peek_char() != Some(separator) {
+                        break;
+                    }
+                    self.position += 1;
+                }
+            }
+            _ => {
+                // Expecting MyTable[MyColumn]
+                table_reference =
+                    Some(TableReference::ColumnReference(self.consume_column_name()?));
             }
         }
-
-        // Now it's either:
-        // [Column Name]
-        // [Column Name]:[Column Name]
-        self.position -= 1;
-        let column_reference = self.consume_column_reference()?;
-        let table_reference = if self.peek_char() == Some(':') {
-            self.position += 1;
-            let column_reference_right = self.consume_column_reference()?;
-            self.expect(TokenType::RightBracket)?;
-            Some(TableReference::RangeReference((
-                column_reference,
-                column_reference_right,
-            )))
-        } else {
-            self.expect(TokenType::RightBracket)?;
-            Some(TableReference::ColumnReference(column_reference))
+        self.expect_char(']')?;
+        let specifier = match combine_specifiers(&specifiers) {
+            Some(specifier) => specifier,
+            None => return Err(self.set_error("Invalid table specifiers", self.position)),
         };
         Ok(TokenType::StructuredReference {
             table_name: table_name.to_string(),
@@ -6,7 +6,7 @@ use crate::locale::get_locale;
 use crate::expressions::{
     lexer::{Lexer, LexerMode},
     token::TokenType::*,
-    token::{Error, OpSum},
+    token::{Error, OpProduct, OpSum, TableReference},
 };
 
 fn new_lexer(formula: &str, a1_mode: bool) -> Lexer {
@@ -185,6 +185,80 @@ fn test_reference_sheet() {
     assert_eq!(lx.next_token(), EOF);
 }
 
+#[test]
+fn test_reference_external() {
+    let mut lx = new_lexer("[Rates.xlsx]Sheet1!$B$2", true);
+    assert_eq!(
+        lx.next_token(),
+        Reference {
+            sheet: Some("[Rates.xlsx]Sheet1".to_string()),
+            column: 2,
+            row: 2,
+            absolute_column: true,
+            absolute_row: true,
+        }
+    );
+    assert_eq!(lx.next_token(), EOF);
+
+    let mut lx = new_lexer("'C:\\models\\[fx.xlsx]USD'!A1", true);
+    assert_eq!(
+        lx.next_token(),
+        Reference {
+            sheet: Some("C:\\models\\[fx.xlsx]USD".to_string()),
+            column: 1,
+            row: 1,
+            absolute_column: false,
+            absolute_row: false,
+        }
+    );
+    assert_eq!(lx.next_token(), EOF);
+
+    // Not a reference to another workbook but to a column of the table of the cell
+    let mut lx = new_lexer("[Rates.xlsx]+1", true);
+    assert_eq!(
+        lx.next_token(),
+        StructuredReference {
+            table_name: "".to_string(),
+            specifier: None,
+            table_reference: Some(TableReference::ColumnReference("Rates.xlsx".to_string())),
+        }
+    );
+}
+
+#[test]
+fn test_reference_sheet_span() {
+    let mut lx = new_lexer("Jan:Dec!B5", true);
+    assert_eq!(
+        lx.next_token(),
+        Reference {
+            sheet: Some("Jan:Dec".to_string()),
+            column: 2,
+            row: 5,
+            absolute_column: false,
+            absolute_row: false,
+        }
+    );
+    assert_eq!(lx.next_token(), EOF);
+
+    let mut lx = new_lexer("'Jan 2023:Dec 2023'!B5", true);
+    assert_eq!(
+        lx.next_token(),
+        Reference {
+            sheet: Some("Jan 2023:Dec 2023".to_string()),
+            column: 2,
+            row: 5,
+            absolute_column: false,
+            absolute_row: false,
+        }
+    );
+    assert_eq!(lx.next_token(), EOF);
+
+    // JAN is a column
+    let mut lx = new_lexer("JAN:DEC", true);
+    assert!(matches!(lx.next_token(), Range { sheet: None, .. }));
+    assert_eq!(lx.next_token(), EOF);
+}
+
 #[test]
 fn test_reference_sheet_unicode() {
     // Not that also tests the '!'
@@ -410,6 +484,54 @@ fn test_name_r1c1p() {
     assert_eq!(lx.next_token(), EOF);
 }
 
+#[test]
+fn test_reference_r1c1_zero_offsets() {
+    let mut lx = new_lexer("R[-1]C*RC2+RC", false);
+    assert_eq!(
+        lx.next_token(),
+        Reference {
+            sheet: None,
+            column: 0,
+            row: -1,
+            absolute_column: false,
+            absolute_row: false,
+        }
+    );
+    assert_eq!(lx.next_token(), Product(OpProduct::Times));
+    assert_eq!(
+        lx.next_token(),
+        Reference {
+            sheet: None,
+            column: 2,
+            row: 0,
+            absolute_column: true,
+            absolute_row: false,
+        }
+    );
+    assert_eq!(lx.next_token(), Addition(OpSum::Add));
+    assert_eq!(
+        lx.next_token(),
+        Reference {
+            sheet: None,
+            column: 0,
+            row: 0,
+            absolute_column: false,
+            absolute_row: false,
+        }
+    );
+    assert_eq!(lx.next_token(), EOF);
+}
+
+#[test]
+fn test_name_r1c1_like() {
+    let mut lx = new_lexer("RATE(RCX)", false);
+    assert_eq!(lx.next_token(), Ident("RATE".to_string()));
+    assert_eq!(lx.next_token(), LeftParenthesis);
+    assert_eq!(lx.next_token(), Ident("RCX".to_string()));
+    assert_eq!(lx.next_token(), RightParenthesis);
+    assert_eq!(lx.next_token(), EOF);
+}
+
 #[test]
 fn test_name_wrong_ref() {
     let mut lx = new_lexer("Sheet1!2", false);
@@ -24,10 +24,36 @@ fn test_german_locale() {
 
 #[test]
 fn test_german_locale_does_not_parse() {
+    // a period separates the columns of arrays
     let mut lx = new_language_lexer("2.34e-3", "de", "en");
     assert_eq!(lx.next_token(), TokenType::Number(2.0));
-    assert!(matches!(lx.next_token(), TokenType::Illegal { .. }));
+    assert_eq!(lx.next_token(), TokenType::Comma);
+    assert_eq!(lx.next_token(), TokenType::Number(34e-3));
+    assert_eq!(lx.next_token(), TokenType::EOF);
+}
+
+#[test]
+fn test_german_locale_separators() {
+    let mut lx = new_language_lexer("SUMME(1,5; {1.2;3.4})", "de", "de");
+    assert_eq!(lx.argument_separator(), TokenType::Semicolon);
+    assert_eq!(lx.next_token(), TokenType::Ident("SUMME".to_string()));
+    assert_eq!(lx.next_token(), TokenType::LeftParenthesis);
+    assert_eq!(lx.next_token(), TokenType::Number(1.5));
+    assert_eq!(lx.next_token(), TokenType::Semicolon);
+    assert_eq!(lx.next_token(), TokenType::LeftBrace);
+    assert_eq!(lx.next_token(), TokenType::Number(1.0));
+    assert_eq!(lx.next_token(), TokenType::Comma);
+    assert_eq!(lx.next_token(), TokenType::Number(2.0));
+    assert_eq!(lx.next_token(), TokenType::Semicolon);
+    assert_eq!(lx.next_token(), TokenType::Number(3.0));
+    assert_eq!(lx.next_token(), TokenType::Comma);
+    assert_eq!(lx.next_token(), TokenType::Number(4.0));
+    assert_eq!(lx.next_token(), TokenType::RightBrace);
+    assert_eq!(lx.next_token(), TokenType::RightParenthesis);
     assert_eq!(lx.next_token(), TokenType::EOF);
+
+    let lx = new_language_lexer("SUM(1,2)", "en", "en");
+    assert_eq!(lx.argument_separator(), TokenType::Comma);
 }
 
 #[test]
@@ -469,6 +469,58 @@ fn test_range_incomplete_column() {
     assert_eq!(lx.next_token(), EOF);
 }
 
+#[test]
+fn test_range_rows_and_columns_r1c1() {
+    let mut lx = new_lexer("C[-1]");
+    lx.set_lexer_mode(LexerMode::R1C1);
+    assert_eq!(
+        lx.next_token(),
+        Range {
+            sheet: None,
+            left: ParsedReference {
+                column: -1,
+                row: 1,
+                absolute_column: false,
+                absolute_row: true,
+            },
+            right: ParsedReference {
+                column: -1,
+                row: LAST_ROW,
+                absolute_column: false,
+                absolute_row: true,
+            }
+        }
+    );
+    assert_eq!(lx.next_token(), EOF);
+
+    let mut lx = new_lexer("Sheet1!R2:R[3]");
+    lx.set_lexer_mode(LexerMode::R1C1);
+    assert_eq!(
+        lx.next_token(),
+        Range {
+            sheet: Some("Sheet1".to_string()),
+            left: ParsedReference {
+                column: 1,
+                row: 2,
+                absolute_column: true,
+                absolute_row: true,
+            },
+            right: ParsedReference {
+                column: LAST_COLUMN,
+                row: 3,
+                absolute_column: true,
+                absolute_row: false,
+            }
+        }
+    );
+    assert_eq!(lx.next_token(), EOF);
+
+    // A row and a column are not a range
+    let mut lx = new_lexer("R1:C1");
+    lx.set_lexer_mode(LexerMode::R1C1);
+    assert!(matches!(lx.next_token(), Illegal(_)));
+}
+
 #[test]
 fn range_operator() {
     let mut lx = new_lexer("A1:OFFSET(B1,1,2)");
@@ -71,3 +71,102 @@ fn table_totals_no_reference() {
     );
     assert_eq!(lx.next_token(), EOF);
 }
+
+#[test]
+fn table_this_row_short_form() {
+    let mut lx = new_lexer("tbInfo[@Jan]+tbInfo[@[First Month]:[Dec]]");
+    assert_eq!(
+        lx.next_token(),
+        StructuredReference {
+            table_name: "tbInfo".to_string(),
+            specifier: Some(TableSpecifier::ThisRow),
+            table_reference: Some(TableReference::ColumnReference("Jan".to_string()))
+        }
+    );
+    assert!(matches!(lx.next_token(), Addition(_)));
+    assert_eq!(
+        lx.next_token(),
+        StructuredReference {
+            table_name: "tbInfo".to_string(),
+            specifier: Some(TableSpecifier::ThisRow),
+            table_reference: Some(TableReference::RangeReference((
+                "First Month".to_string(),
+                "Dec".to_string()
+            )))
+        }
+    );
+    assert_eq!(lx.next_token(), EOF);
+}
+
+#[test]
+fn table_without_name() {
+    let mut lx = new_lexer("[@Price]*[Qty]");
+    assert_eq!(
+        lx.next_token(),
+        StructuredReference {
+            table_name: "".to_string(),
+            specifier: Some(TableSpecifier::ThisRow),
+            table_reference: Some(TableReference::ColumnReference("Price".to_string()))
+        }
+    );
+    assert!(matches!(lx.next_token(), Product(_)));
+    assert_eq!(
+        lx.next_token(),
+        StructuredReference {
+            table_name: "".to_string(),
+            specifier: None,
+            table_reference: Some(TableReference::ColumnReference("Qty".to_string()))
+        }
+    );
+    assert_eq!(lx.next_token(), EOF);
+}
+
+#[test]
+fn table_several_specifiers() {
+    let mut lx = new_lexer("tbInfo[[#Headers],[#Data],[Jan]]");
+    assert_eq!(
+        lx.next_token(),
+        StructuredReference {
+            table_name: "tbInfo".to_string(),
+            specifier: Some(TableSpecifier::HeadersAndData),
+            table_reference: Some(TableReference::ColumnReference("Jan".to_string()))
+        }
+    );
+    assert_eq!(lx.next_token(), EOF);
+}
+
+#[test]
+fn table_whole_table() {
+    let mut lx = new_lexer("tbInfo[]");
+    assert_eq!(
+        lx.next_token(),
+        StructuredReference {
+            table_name: "tbInfo".to_string(),
+            specifier: None,
+            table_reference: None
+        }
+    );
+    assert_eq!(lx.next_token(), EOF);
+}
+
+#[test]
+fn table_escaped_column() {
+    let mut lx = new_lexer("tbInfo[[Price '[USD']]]");
+    assert_eq!(
+        lx.next_token(),
+        StructuredReference {
+            table_name: "tbInfo".to_string(),
+            specifier: None,
+            table_reference: Some(TableReference::ColumnReference("Price [USD]".to_string()))
+        }
+    );
+    assert_eq!(lx.next_token(), EOF);
+}
+
+#[test]
+fn table_invalid() {
+    let mut lx = new_lexer("tbInfo[[#Totals],[#Headers]]");
+    assert!(matches!(lx.next_token(), Illegal(_)));
+    let mut lx = new_lexer("tbInfo[#Everything]");
+    assert!(matches!(lx.next_token(), Illegal(_)));
+}
@@ -1,4 +1,5 @@
 // public modules
+pub mod ast;
 pub mod lexer;
 pub mod parser;
 pub mod token;
@@ -0,0 +1,188 @@
+//! Layout of long formulas over several lines.
+//!
+//! A node that fits in the remaining width is printed in one line, like
+//! [`to_string`](super::stringify::to_string) does.
+//! Otherwise the arguments of functions and the rows of arrays go in a line each, and chains of
+//! operators are split before each operator:
+//!
+//! ```text
+//! IF(
+//!     A1>10,
+//!     "High",
+//!     IF(
+//!         A1>5,
+//!         "Medium",
+//!         "Low"
+//!     )
+//! )
+//! ```
+//!
+//! The parser ignores whitespace between tokens, so the result parses to the same tree.
+
+use serde::{Deserialize, Serialize};
+
+use super::stringify::{operand_precedence, precedence, to_string_in_style};
+use super::Node;
+use crate::expressions::token::OpUnary;
+use crate::expressions::types::CellReferenceRC;
+use crate::types::ReferenceStyle;
+
+/// How formulas are laid out by [`to_formatted_string`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct FormatOptions {
+    /// Number of spaces of each level of indentation
+    pub indent: usize,
+    /// Lines longer than this are split, when possible
+    pub width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            indent: 4,
+            width: 80,
+        }
+    }
+}
+
+/// Returns the formula of `node` split over several lines, see the module documentation
+pub fn to_formatted_string(
+    node: &Node,
+    context: &CellReferenceRC,
+    options: &FormatOptions,
+    reference_style: ReferenceStyle,
+) -> String {
+    Formatter {
+        context,
+        options,
+        reference_style,
+    }
+    .format(node, 0, 0)
+}
+
+struct Formatter<'a> {
+    context: &'a CellReferenceRC,
+    options: &'a FormatOptions,
+    reference_style: ReferenceStyle,
+}
+
+impl Formatter<'_> {
+    fn flat(&self, node: &Node) -> String {
+        to_string_in_style(node, self.context, self.reference_style)
+    }
+
+    fn new_line(&self, level: usize) -> String {
+        format!("\n{}", " ".repeat(self.options.indent * level))
+    }
+
+    /// Formats `node` starting at `column` in a line indented `level` times
+    fn format(&self, node: &Node, level: usize, column: usize) -> String {
+        let flat = self.flat(node);
+        if column + flat.chars().count() <= self.options.width {
+            return flat;
+        }
+        match node {
+            Node::FunctionKind { kind, args } => {
+                self.format_function(&kind.to_string(), args, level)
+            }
+            Node::InvalidFunctionKind { name, args } => self.format_function(name, args, level),
+            Node::CallKind { function, args } => {
+                let function = self.format(function, level, column);
+                self.format_function(&function, args, level)
+            }
+            Node::OpUnionKind(items) => self.format_function("", items, level),
+            Node::ArrayKind(rows) => {
+                let rows: Vec<String> = rows
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|value| self.flat(value))
+                            .collect::<Vec<String>>()
+                            .join(",")
+                    })
+                    .collect();
+                let inner = self.new_line(level + 1);
+                format!(
+                    "{{{}{}{}}}",
+                    inner,
+                    rows.join(&format!(";{}", inner)),
+                    self.new_line(level)
+                )
+            }
+            Node::CompareKind { left, right, .. }
+            | Node::OpConcatenateKind { left, right }
+            | Node::OpSumKind { left, right, .. }
+            | Node::OpProductKind { left, right, .. }
+            | Node::OpPowerKind { left, right } => {
+                let operator = match node {
+                    Node::CompareKind { kind, .. } => kind.to_string(),
+                    Node::OpConcatenateKind { .. } => "&".to_string(),
+                    Node::OpSumKind { kind, .. } => kind.to_string(),
+                    Node::OpProductKind { kind, .. } => kind.to_string(),
+                    _ => "^".to_string(),
+                };
+                let (left_precedence, right_precedence) = operand_precedence(node);
+                let left = self.format_operand(left, left_precedence, level, column);
+                let column = self.options.indent * (level + 1) + operator.chars().count();
+                let right = self.format_operand(right, right_precedence, level + 1, column);
+                format!("{}{}{}{}", left, self.new_line(level + 1), operator, right)
+            }
+            Node::UnaryKind { kind, right } => {
+                let (operand_precedence, _) = operand_precedence(node);
+                match kind {
+                    OpUnary::Minus => format!(
+                        "-{}",
+                        self.format_operand(right, operand_precedence, level, column + 1)
+                    ),
+                    OpUnary::Percentage => format!(
+                        "{}%",
+                        self.format_operand(right, operand_precedence, level, column)
+                    ),
+                }
+            }
+            _ => flat,
+        }
+    }
+
+    /// Formats an operand, in parenthesis if it binds looser than `min_precedence`
+    fn format_operand(
+        &self,
+        node: &Node,
+        min_precedence: u8,
+        level: usize,
+        column: usize,
+    ) -> String {
+        if precedence(node) < min_precedence {
+            let inner = self.format(node, level + 1, self.options.indent * (level + 1));
+            if inner.contains('\n') {
+                format!(
+                    "({}{}{})",
+                    self.new_line(level + 1),
+                    inner,
+                    self.new_line(level)
+                )
+            } else {
+                format!("({})", inner)
+            }
+        } else {
+            self.format(node, level, column)
+        }
+    }
+
+    fn format_function(&self, name: &str, args: &[Node], level: usize) -> String {
+        let column = self.options.indent * (level + 1);
+        let args: Vec<String> = args
+            .iter()
+            .map(|arg| self.format(arg, level + 1, column))
+            .collect();
+        let inner = self.new_line(level + 1);
+        format!(
+            "{}({}{}{})",
+            name,
+            inner,
+            args.join(&format!(",{}", inner)),
+            self.new_line(level)
+        )
+    }
+}
@@ -11,9 +11,11 @@ concat  => term ('&' term)*
 term    => factor (opFactor factor)*
 factor  => prod (opProd prod)*
 prod    => power ('^' power)*
-power   => (unaryOp)* range '%'*
-range   => primary (':' primary)?
-primary => '(' expr ')'
+power   => (unaryOp)* isect '%'*
+isect   => range (' ' range)*
+range   => spill (':' spill)?
+spill   => primary '#'?
+primary => '(' expr (',' expr)* ')'
         => number
         => function '(' f_args ')'
         => name
@@ -24,26 +26,30 @@ primary => '(' expr ')'
         => error
 
 f_args  => e (',' e)*
+a_args  => a_row (';' a_row)*
+a_row   => e (',' e)*
 </pre>
 */
 
 use std::collections::HashMap;
 
 use crate::functions::Function;
-use crate::language::get_language;
-use crate::locale::get_locale;
+use crate::language::{get_language, Language};
+use crate::locale::{get_locale, Locale};
 use crate::types::Table;
 
+use super::ast::{span_matches, Span};
 use super::lexer;
 use super::token;
 use super::token::OpUnary;
 use super::token::TableReference;
 use super::token::TokenType;
 use super::types::*;
-use super::utils::number_to_column;
+use super::utils::{split_external_name, split_sheet_span};
 
 use token::OpCompare;
 
+pub mod format;
 pub mod move_formula;
 pub mod stringify;
 pub mod walk;
@@ -54,6 +60,10 @@ mod test;
 #[cfg(test)]
 mod test_ranges;
 
+#[cfg(test)]
+mod test_ast;
+#[cfg(test)]
+mod test_format;
 #[cfg(test)]
 mod test_move_formula;
 #[cfg(test)]
@@ -78,15 +88,76 @@ pub(crate) fn parse_range(formula: &str) -> Result<(i32, i32, i32, i32), String>
     }
 }
 
-fn get_table_column_by_name(table_column_name: &str, table: &Table) -> Option<i32> {
+/// Returns the position of the column `table_column_name` in the table.
+/// Like names of tables, names of columns are case insensitive.
+pub(crate) fn get_table_column_by_name(table_column_name: &str, table: &Table) -> Option<i32> {
+    let table_column_name = table_column_name.to_lowercase();
     for (index, table_column) in table.columns.iter().enumerate() {
-        if table_column.name == table_column_name {
+        if table_column.name.to_lowercase() == table_column_name {
             return Some(index as i32);
         }
     }
     None
 }
 
+/// Returns true if the cell is in the area of the table, headers and totals included
+pub(crate) fn table_contains(table: &Table, sheet_name: &str, row: i32, column: i32) -> bool {
+    if table.sheet_name != sheet_name {
+        return false;
+    }
+    match parse_range(&table.reference) {
+        Ok((column1, row1, column2, row2)) => {
+            (row1..=row2).contains(&row) && (column1..=column2).contains(&column)
+        }
+        Err(_) => false,
+    }
+}
+
+/// LET(name1, value1, [name2, value2]*, calculation) needs pairs of names and values
+/// followed by a calculation
+fn check_let_args(args: &[Node]) -> Result<(), String> {
+    if args.len() < 3 || args.len() % 2 != 1 {
+        return Err("Wrong number of arguments for LET".to_string());
+    }
+    for name in args.iter().step_by(2).take(args.len() / 2) {
+        if !matches!(name, Node::VariableKind(_)) {
+            return Err("Invalid name in LET".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// LAMBDA([parameter1, parameter2, ...,] calculation) needs names for the parameters
+fn check_lambda_args(args: &[Node]) -> Result<(), String> {
+    if args.is_empty() {
+        return Err("Wrong number of arguments for LAMBDA".to_string());
+    }
+    for name in &args[..args.len() - 1] {
+        if !matches!(name, Node::VariableKind(_)) {
+            return Err("Invalid parameter in LAMBDA".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if `node` might be a reference, so a space after it is an intersection
+fn is_reference_operand(node: &Node) -> bool {
+    matches!(
+        node,
+        Node::ReferenceKind { .. }
+            | Node::RangeKind { .. }
+            | Node::WrongReferenceKind { .. }
+            | Node::WrongRangeKind { .. }
+            | Node::OpRangeKind { .. }
+            | Node::OpIntersectKind { .. }
+            | Node::OpUnionKind(_)
+            | Node::SpillRangeKind(_)
+            | Node::TableReferenceKind { .. }
+            | Node::VariableKind(_)
+            | Node::FunctionKind { .. }
+    )
+}
+
 pub(crate) struct Reference<'a> {
     sheet_name: &'a Option<String>,
     sheet_index: u32,
@@ -139,10 +210,74 @@ pub enum Node {
         row2: i32,
         column2: i32,
     },
+    /// Reference to a cell of another workbook: [Rates.xlsx]Sheet1!$B$2
+    ExternalReferenceKind {
+        /// The workbook with its path, if any: C:\models\fx.xlsx
+        workbook: String,
+        sheet_name: String,
+        absolute_row: bool,
+        absolute_column: bool,
+        row: i32,
+        column: i32,
+    },
+    ExternalRangeKind {
+        workbook: String,
+        sheet_name: String,
+        absolute_row1: bool,
+        absolute_column1: bool,
+        row1: i32,
+        column1: i32,
+        absolute_row2: bool,
+        absolute_column2: bool,
+        row2: i32,
+        column2: i32,
+    },
+    /// Reference to the same cell in a span of sheets: Jan:Dec!B5.
+    /// The span goes from the first sheet to the last one in the order of the workbook,
+    /// whichever comes first.
+    SheetSpanReferenceKind {
+        first_sheet: String,
+        first_sheet_index: u32,
+        last_sheet: String,
+        last_sheet_index: u32,
+        absolute_row: bool,
+        absolute_column: bool,
+        row: i32,
+        column: i32,
+    },
+    SheetSpanRangeKind {
+        first_sheet: String,
+        first_sheet_index: u32,
+        last_sheet: String,
+        last_sheet_index: u32,
+        absolute_row1: bool,
+        absolute_column1: bool,
+        row1: i32,
+        column1: i32,
+        absolute_row2: bool,
+        absolute_column2: bool,
+        row2: i32,
+        column2: i32,
+    },
     OpRangeKind {
         left: Box<Node>,
         right: Box<Node>,
     },
+    /// Intersection of two references, written with a space between them: B2:D5 C1:C10
+    OpIntersectKind {
+        left: Box<Node>,
+        right: Box<Node>,
+    },
+    /// Union of references, always in parenthesis: (A1:A3,C1:C3)
+    OpUnionKind(Vec<Node>),
+    /// Structured reference to the cells of a table: Sales[[#This Row],[Price]].
+    /// The cells are found when the formula is evaluated, so they follow the table as it grows.
+    /// The name of the table is empty when it is the table of the cell: [@Price].
+    TableReferenceKind {
+        table_name: String,
+        specifier: Option<token::TableSpecifier>,
+        table_reference: Option<TableReference>,
+    },
     OpConcatenateKind {
         left: Box<Node>,
         right: Box<Node>,
@@ -169,7 +304,15 @@ pub enum Node {
         name: String,
         args: Vec<Node>,
     },
-    ArrayKind(Vec<Node>),
+    /// Array literal. A list of rows: {1,2;3,4} => [[1, 2], [3, 4]]
+    ArrayKind(Vec<Vec<Node>>),
+    /// Spilled range operator: A1#. The node is always a (possibly wrong) reference
+    SpillRangeKind(Box<Node>),
+    /// Call to the function returned by an expression: LAMBDA(x, x*2)(A1)
+    CallKind {
+        function: Box<Node>,
+        args: Vec<Node>,
+    },
     VariableKind(String),
     CompareKind {
         kind: OpCompare,
@@ -195,6 +338,8 @@ pub struct Parser {
     worksheets: Vec<String>,
     context: Option<CellReferenceRC>,
     tables: HashMap<String, Table>,
+    /// Spans of the nodes parsed that are not part of another node yet, the last one on top
+    spans: Vec<Span>,
 }
 
 impl Parser {
@@ -210,22 +355,84 @@ impl Parser {
             worksheets,
             context: None,
             tables,
+            spans: Vec::new(),
         }
     }
     pub fn set_lexer_mode(&mut self, mode: lexer::LexerMode) {
         self.lexer.set_lexer_mode(mode)
     }
 
+    /// Parses formulas written in `language` and with the separators of `locale`,
+    /// see [`Lexer`](lexer::Lexer). Formulas are stored with the 'en' locale and language.
+    pub fn set_locale(&mut self, locale: &Locale, language: &Language) {
+        self.lexer.set_locale(locale, language)
+    }
+
     pub fn set_worksheets(&mut self, worksheets: Vec<String>) {
         self.worksheets = worksheets;
     }
 
+    pub fn set_tables(&mut self, tables: HashMap<String, Table>) {
+        self.tables = tables;
+    }
+
     pub fn parse(&mut self, formula: &str, context: &Option<CellReferenceRC>) -> Node {
         self.lexer.set_formula(formula);
         self.context = context.clone();
+        self.spans.clear();
         self.parse_expr()
     }
 
+    /// Parses `formula` and returns the node with the positions in the formula of it and
+    /// its children, see [`Span`]
+    pub fn parse_with_spans(
+        &mut self,
+        formula: &str,
+        context: &Option<CellReferenceRC>,
+    ) -> (Node, Span) {
+        let node = self.parse(formula, context);
+        let span = match self.spans.pop() {
+            // Errors in a part of the formula can leave spans of the nodes around it
+            Some(span)
+                if self.spans.is_empty()
+                    && span_matches(&node, &span)
+                    && !matches!(node, Node::ParseErrorKind { .. }) =>
+            {
+                span
+            }
+            _ => Span {
+                start: 0,
+                end: formula.chars().count(),
+                children: Vec::new(),
+            },
+        };
+        self.spans.clear();
+        (node, span)
+    }
+
+    /// Records the span of a node without children that started at `start`
+    fn push_span(&mut self, start: usize) {
+        self.spans.push(Span {
+            start,
+            end: self.lexer.get_position() as usize,
+            children: Vec::new(),
+        });
+    }
+
+    /// Replaces the spans of the last `count` nodes with the span of the node they are part of,
+    /// that starts at `start` or with the first of them
+    fn join_spans(&mut self, count: usize, start: Option<usize>) {
+        let children = self.spans.split_off(self.spans.len().saturating_sub(count));
+        let start = start
+            .or_else(|| children.first().map(|span| span.start))
+            .unwrap_or_default();
+        self.spans.push(Span {
+            start,
+            end: self.lexer.get_position() as usize,
+            children,
+        });
+    }
+
     fn get_sheet_index_by_name(&self, name: &str) -> Option<u32> {
         let worksheets = &self.worksheets;
         for (i, sheet) in worksheets.iter().enumerate() {
@@ -253,6 +460,7 @@ impl Parser {
                 left: Box::new(t),
                 right: Box::new(p),
             };
+            self.join_spans(2, None);
             next_token = self.lexer.peek_token();
         }
         t
@@ -274,6 +482,7 @@ impl Parser {
                 left: Box::new(t),
                 right: Box::new(p),
             };
+            self.join_spans(2, None);
             next_token = self.lexer.peek_token();
         }
         t
@@ -296,6 +505,7 @@ impl Parser {
                 left: Box::new(t),
                 right: Box::new(p),
             };
+            self.join_spans(2, None);
 
             next_token = self.lexer.peek_token();
         }
@@ -319,6 +529,7 @@ impl Parser {
                 left: Box::new(t),
                 right: Box::new(p),
             };
+            self.join_spans(2, None);
             next_token = self.lexer.peek_token();
         }
         t
@@ -340,12 +551,14 @@ impl Parser {
                 left: Box::new(t),
                 right: Box::new(p),
             };
+            self.join_spans(2, None);
             next_token = self.lexer.peek_token();
         }
         t
     }
 
     fn parse_power(&mut self) -> Node {
+        let start = self.lexer.get_next_token_position();
         let mut next_token = self.lexer.peek_token();
         let mut sign = 1;
         while let TokenType::Addition(op) = next_token {
@@ -356,7 +569,7 @@ impl Parser {
             next_token = self.lexer.peek_token();
         }
 
-        let mut t = self.parse_range();
+        let mut t = self.parse_intersection();
         if let Node::ParseErrorKind { .. } = t {
             return t;
         }
@@ -364,7 +577,8 @@ impl Parser {
             t = Node::UnaryKind {
                 kind: token::OpUnary::Minus,
                 right: Box::new(t),
-            }
+            };
+            self.join_spans(1, Some(start));
         }
         next_token = self.lexer.peek_token();
         while next_token == TokenType::Percent {
@@ -373,23 +587,55 @@ impl Parser {
                 kind: token::OpUnary::Percentage,
                 right: Box::new(t),
             };
+            self.join_spans(1, None);
             next_token = self.lexer.peek_token();
         }
         t
     }
 
+    /// The intersection operator is a space between two references: B2:D5 C1:C10
+    fn parse_intersection(&mut self) -> Node {
+        let mut t = self.parse_range();
+        if let Node::ParseErrorKind { .. } = t {
+            return t;
+        }
+        while self.lexer.get_next_token_position() > self.lexer.get_position() as usize
+            && is_reference_operand(&t)
+            && matches!(
+                self.lexer.peek_token(),
+                TokenType::Reference { .. }
+                    | TokenType::Range { .. }
+                    | TokenType::Ident(_)
+                    | TokenType::StructuredReference { .. }
+                    | TokenType::LeftParenthesis
+            )
+        {
+            let p = self.parse_range();
+            if let Node::ParseErrorKind { .. } = p {
+                return p;
+            }
+            t = Node::OpIntersectKind {
+                left: Box::new(t),
+                right: Box::new(p),
+            };
+            self.join_spans(2, None);
+        }
+        t
+    }
+
     fn parse_range(&mut self) -> Node {
-        let t = self.parse_primary();
+        let t = self.parse_spill();
         if let Node::ParseErrorKind { .. } = t {
             return t;
         }
         let next_token = self.lexer.peek_token();
         if next_token == TokenType::Colon {
             self.lexer.advance_token();
-            let p = self.parse_primary();
+            let p = self.parse_spill();
             if let Node::ParseErrorKind { .. } = p {
                 return p;
             }
+            self.join_spans(2, None);
             return Node::OpRangeKind {
                 left: Box::new(t),
                 right: Box::new(p),
@@ -398,7 +644,41 @@ impl Parser {
         t
     }
 
+    fn parse_spill(&mut self) -> Node {
+        let t = self.parse_primary();
+        if let Node::ParseErrorKind { .. } = t {
+            return t;
+        }
+        if self.lexer.peek_token() == TokenType::Hash {
+            self.lexer.advance_token();
+            return match t {
+                Node::ReferenceKind { .. } | Node::WrongReferenceKind { .. } => {
+                    self.join_spans(1, None);
+                    Node::SpillRangeKind(Box::new(t))
+                }
+                _ => Node::ParseErrorKind {
+                    formula: self.lexer.get_formula(),
+                    position: self.lexer.get_position() as usize,
+                    message: "Expecting a reference before '#'".to_string(),
+                },
+            };
+        }
+        t
+    }
+
     fn parse_primary(&mut self) -> Node {
+        let start = self.lexer.get_next_token_position();
+        let spans = self.spans.len();
+        let node = self.parse_primary_node();
+        if !matches!(node, Node::ParseErrorKind { .. }) && self.spans.len() == spans {
+            // A node made of a single token
+            self.push_span(start);
+        }
+        node
+    }
+
+    fn parse_primary_node(&mut self) -> Node {
+        let start = self.lexer.get_next_token_position();
         let next_token = self.lexer.next_token();
         match next_token {
             TokenType::LeftParenthesis => {
@@ -406,6 +686,16 @@ impl Parser {
                 if let Node::ParseErrorKind { .. } = t {
                     return t;
                 }
+                // A list of references is their union: (A1:A3,C1:C3)
+                let mut items = vec![t];
+                while self.lexer.peek_token() == self.lexer.argument_separator() {
+                    self.lexer.advance_token();
+                    let p = self.parse_expr();
+                    if let Node::ParseErrorKind { .. } = p {
+                        return p;
+                    }
+                    items.push(p);
+                }
 
                 if let Err(err) = self.lexer.expect(TokenType::RightParenthesis) {
                     return Node::ParseErrorKind {
@@ -414,25 +704,37 @@ impl Parser {
                         message: err.message,
                     };
                 }
-                t
+                if items.len() == 1 {
+                    return items.remove(0);
+                }
+                self.join_spans(items.len(), Some(start));
+                Node::OpUnionKind(items)
             }
             TokenType::Number(s) => Node::NumberKind(s),
             TokenType::String(s) => Node::StringKind(s),
             TokenType::LeftBrace => {
-                let t = self.parse_expr();
-                if let Node::ParseErrorKind { .. } = t {
-                    return t;
-                }
-                let mut next_token = self.lexer.peek_token();
-                let mut args: Vec<Node> = vec![t];
-                while next_token == TokenType::Semicolon {
-                    self.lexer.advance_token();
-                    let p = self.parse_expr();
-                    if let Node::ParseErrorKind { .. } = p {
-                        return p;
+                let mut rows: Vec<Vec<Node>> = Vec::new();
+                loop {
+                    let row = match self.parse_array_row() {
+                        Ok(row) => row,
+                        Err(error) => return error,
+                    };
+                    if let Some(first_row) = rows.first() {
+                        if first_row.len() != row.len() {
+                            return Node::ParseErrorKind {
+                                formula: self.lexer.get_formula(),
+                                position: self.lexer.get_position() as usize,
+                                message: "All rows in an array must have the same length"
+                                    .to_string(),
+                            };
+                        }
+                    }
+                    rows.push(row);
+                    if self.lexer.peek_token() == TokenType::Semicolon {
+                        self.lexer.advance_token();
+                    } else {
+                        break;
                     }
-                    next_token = self.lexer.peek_token();
-                    args.push(p);
                 }
                 if let Err(err) = self.lexer.expect(TokenType::RightBrace) {
                     return Node::ParseErrorKind {
@@ -441,7 +743,8 @@ impl Parser {
                         message: err.message,
                     };
                 }
-                Node::ArrayKind(args)
+                self.join_spans(rows.iter().map(|row| row.len()).sum(), Some(start));
+                Node::ArrayKind(rows)
             }
             TokenType::Reference {
                 sheet,
@@ -475,6 +778,51 @@ impl Parser {
                 } else {
                     column - context.column
                 };
+                if let Some((workbook, sheet_name)) = sheet.as_deref().and_then(split_external_name)
+                {
+                    return Node::ExternalReferenceKind {
+                        workbook,
+                        sheet_name,
+                        row,
+                        column,
+                        absolute_row,
+                        absolute_column,
+                    };
+                }
+                if let Some((first_sheet, last_sheet)) = sheet.as_deref().and_then(split_sheet_span)
+                {
+                    match (
+                        self.get_sheet_index_by_name(&first_sheet),
+                        self.get_sheet_index_by_name(&last_sheet),
+                    ) {
+                        (Some(first_sheet_index), Some(last_sheet_index))
+                            if first_sheet_index != last_sheet_index =>
+                        {
+                            return Node::SheetSpanReferenceKind {
+                                first_sheet,
+                                first_sheet_index,
+                                last_sheet,
+                                last_sheet_index,
+                                row,
+                                column,
+                                absolute_row,
+                                absolute_column,
+                            };
+                        }
+                        // Jan:Jan!B5 is Jan!B5
+                        (Some(index), Some(_)) => {
+                            return Node::ReferenceKind {
+                                sheet_name: Some(first_sheet),
+                                sheet_index: index,
+                                row,
+                                column,
+                                absolute_row,
+                                absolute_column,
+                            };
+                        }
+                        _ => {}
+                    }
+                }
                 match sheet_index {
                     Some(index) => Node::ReferenceKind {
                         sheet_name: sheet,
@@ -540,6 +888,62 @@ impl Parser {
                     (column2, column1) = (column1, column2);
                     (absolute_column2, absolute_column1) = (absolute_column1, absolute_column2);
                 }
+                if let Some((workbook, sheet_name)) = sheet.as_deref().and_then(split_external_name)
+                {
+                    return Node::ExternalRangeKind {
+                        workbook,
+                        sheet_name,
+                        row1,
+                        column1,
+                        row2,
+                        column2,
+                        absolute_column1,
+                        absolute_column2,
+                        absolute_row1,
+                        absolute_row2,
+                    };
+                }
+                if let Some((first_sheet, last_sheet)) = sheet.as_deref().and_then(split_sheet_span)
+                {
+                    match (
+                        self.get_sheet_index_by_name(&first_sheet),
+                        self.get_sheet_index_by_name(&last_sheet),
+                    ) {
+                        (Some(first_sheet_index), Some(last_sheet_index))
+                            if first_sheet_index != last_sheet_index =>
+                        {
+                            return Node::SheetSpanRangeKind {
+                                first_sheet,
+                                first_sheet_index,
+                                last_sheet,
+                                last_sheet_index,
+                                row1,
+                                column1,
+                                row2,
+                                column2,
+                                absolute_column1,
+                                absolute_column2,
+                                absolute_row1,
+                                absolute_row2,
+                            };
+                        }
+                        (Some(index), Some(_)) => {
+                            return Node::RangeKind {
+                                sheet_name: Some(first_sheet),
+                                sheet_index: index,
+                                row1,
+                                column1,
+                                row2,
+                                column2,
+                                absolute_column1,
+                                absolute_column2,
+                                absolute_row1,
+                                absolute_row2,
+                            };
+                        }
+                        _ => {}
+                    }
+                }
                 match sheet_index {
                     Some(index) => Node::RangeKind {
                         sheet_name: sheet,
@@ -575,6 +979,7 @@ impl Parser {
                         Ok(s) => s,
                         Err(e) => return e,
                     };
+                    let args_count = args.len();
                     if let Err(err) = self.lexer.expect(TokenType::RightParenthesis) {
                         return Node::ParseErrorKind {
                             formula: self.lexer.get_formula(),
@@ -582,16 +987,66 @@ impl Parser {
                             message: err.message,
                         };
                     }
-                    if let Some(function_kind) = Function::get_function(&name) {
-                        return Node::FunctionKind {
+                    // Spilled ranges are stored as _xlfn.ANCHORARRAY(A1) in xlsx files
+                    if name.eq_ignore_ascii_case("_XLFN.ANCHORARRAY") && args.len() == 1 {
+                        if let Node::ReferenceKind { .. } | Node::WrongReferenceKind { .. } =
+                            args[0]
+                        {
+                            self.join_spans(1, Some(start));
+                            return Node::SpillRangeKind(Box::new(args[0].clone()));
+                        }
+                    }
+                    let function = self.lexer.get_language().get_function(&name);
+                    let mut node = if let Some(function_kind) = function {
+                        let check = match function_kind {
+                            Function::Let => check_let_args(&args),
+                            Function::Lambda => check_lambda_args(&args),
+                            _ => Ok(()),
+                        };
+                        if let Err(message) = check {
+                            return Node::ParseErrorKind {
+                                formula: self.lexer.get_formula(),
+                                position: self.lexer.get_position() as usize,
+                                message,
+                            };
+                        }
+                        Node::FunctionKind {
                             kind: function_kind,
                             args,
-                        };
+                        }
                     } else {
-                        return Node::InvalidFunctionKind { name, args };
+                        Node::InvalidFunctionKind { name, args }
+                    };
+                    self.join_spans(args_count, Some(start));
+                    // The function returned might be called: LAMBDA(x, x*2)(3)
+                    while self.lexer.peek_token() == TokenType::LeftParenthesis {
+                        self.lexer.advance_token();
+                        let args = match self.parse_function_args() {
+                            Ok(s) => s,
+                            Err(e) => return e,
+                        };
+                        if let Err(err) = self.lexer.expect(TokenType::RightParenthesis) {
+                            return Node::ParseErrorKind {
+                                formula: self.lexer.get_formula(),
+                                position: err.position,
+                                message: err.message,
+                            };
+                        }
+                        self.join_spans(args.len() + 1, Some(start));
+                        node = Node::CallKind {
+                            function: Box::new(node),
+                            args,
+                        };
+                    }
+                    return node;
+                }
+                // Names bound by LET are prefixed by `_xlpm.` in xlsx files
+                match name.get(..6) {
+                    Some(prefix) if prefix.eq_ignore_ascii_case("_xlpm.") => {
+                        Node::VariableKind(name[6..].to_string())
                     }
+                    _ => Node::VariableKind(name),
                 }
-                Node::VariableKind(name)
             }
             TokenType::Error(kind) => Node::ErrorKind(kind),
             TokenType::Illegal(error) => Node::ParseErrorKind {
@@ -645,6 +1100,7 @@ impl Parser {
             | TokenType::Comma
             | TokenType::Bang
             | TokenType::And
+            | TokenType::Hash
             | TokenType::Percent => Node::ParseErrorKind {
                 formula: self.lexer.get_formula(),
                 position: 0,
@@ -659,194 +1115,115 @@ impl Parser {
                 table_name,
                 specifier,
                 table_reference,
-            } => {
-                // We will try to convert to a normal reference
-                // table_name[column_name] => cell1:cell2
-                // table_name[[#This Row], [column_name]:[column_name]] => cell1:cell2
-                if let Some(context) = &self.context {
-                    let context_sheet_index = match self.get_sheet_index_by_name(&context.sheet) {
-                        Some(i) => i,
-                        None => {
-                            return Node::ParseErrorKind {
-                                formula: self.lexer.get_formula(),
-                                position: 0,
-                                message: "sheet not found".to_string(),
-                            };
-                        }
-                    };
-                    // table-name => table
-                    let table = self.tables.get(&table_name).unwrap_or_else(|| {
-                        panic!(
-                            "Table not found: '{table_name}' at '{}!{}{}'",
-                            context.sheet,
-                            number_to_column(context.column).expect(""),
-                            context.row
-                        )
-                    });
-                    let table_sheet_index = match self.get_sheet_index_by_name(&table.sheet_name) {
-                        Some(i) => i,
-                        None => {
-                            return Node::ParseErrorKind {
-                                formula: self.lexer.get_formula(),
-                                position: 0,
-                                message: "sheet not found".to_string(),
-                            };
-                        }
-                    };
-
-                    let sheet_name = if table_sheet_index == context_sheet_index {
-                        None
-                    } else {
-                        Some(table.sheet_name.clone())
-                    };
-
-                    // context must be with tables.reference
-                    let (column_start, mut row_start, column_end, mut row_end) =
-                        parse_range(&table.reference).expect("Failed parsing range");
-
-                    let totals_row_count = table.totals_row_count as i32;
-                    let header_row_count = table.header_row_count as i32;
-                    row_end -= totals_row_count;
-
-                    match specifier {
-                        Some(token::TableSpecifier::ThisRow) => {
-                            row_start = context.row;
-                            row_end = context.row;
-                        }
-                        Some(token::TableSpecifier::Totals) => {
-                            if totals_row_count != 0 {
-                                row_start = row_end + 1;
-                                row_end = row_start;
-                            } else {
-                                // Table1[#Totals] is #REF! if Table1 does not have totals
-                                return Node::ErrorKind(token::Error::REF);
-                            }
-                        }
-                        Some(token::TableSpecifier::Headers) => {
-                            row_end = row_start;
-                        }
-                        Some(token::TableSpecifier::Data) => {
-                            row_start += header_row_count;
-                        }
-                        Some(token::TableSpecifier::All) => {
-                            if totals_row_count != 0 {
-                                row_end += 1;
-                            }
-                        }
-                        None => {
-                            // skip the headers
-                            row_start += header_row_count;
-                        }
-                    }
-                    match table_reference {
-                        None => {
-                            return Node::RangeKind {
-                                sheet_name,
-                                sheet_index: table_sheet_index,
-                                absolute_row1: true,
-                                absolute_column1: true,
-                                row1: row_start,
-                                column1: column_start,
-                                absolute_row2: true,
-                                absolute_column2: true,
-                                row2: row_end,
-                                column2: column_end,
-                            };
-                        }
-                        Some(TableReference::ColumnReference(s)) => {
-                            let column_index = match get_table_column_by_name(&s, table) {
-                                Some(s) => s + column_start,
-                                None => {
-                                    return Node::ParseErrorKind {
-                                        formula: self.lexer.get_formula(),
-                                        position: self.lexer.get_position() as usize,
-                                        message: format!(
-                                            "Expecting column: {s} in table {table_name}"
-                                        ),
-                                    };
-                                }
-                            };
-                            if row_start == row_end {
-                                return Node::ReferenceKind {
-                                    sheet_name,
-                                    sheet_index: table_sheet_index,
-                                    absolute_row: true,
-                                    absolute_column: true,
-                                    row: row_start,
-                                    column: column_index,
-                                };
-                            }
-                            return Node::RangeKind {
-                                sheet_name,
-                                sheet_index: table_sheet_index,
-                                absolute_row1: true,
-                                absolute_column1: true,
-                                row1: row_start,
-                                column1: column_index,
-                                absolute_row2: true,
-                                absolute_column2: true,
-                                row2: row_end,
-                                column2: column_index,
-                            };
-                        }
-                        Some(TableReference::RangeReference((left, right))) => {
-                            let left_column_index = match get_table_column_by_name(&left, table) {
-                                Some(f) => f + column_start,
-                                None => {
-                                    return Node::ParseErrorKind {
-                                        formula: self.lexer.get_formula(),
-                                        position: self.lexer.get_position() as usize,
-                                        message: format!(
-                                            "Expecting column: {left} in table {table_name}"
-                                        ),
-                                    };
-                                }
-                            };
+            } => self.parse_structured_reference(&table_name, specifier, table_reference),
+        }
+    }
 
-                            let right_column_index = match get_table_column_by_name(&right, table) {
-                                Some(f) => f + column_start,
-                                None => {
-                                    return Node::ParseErrorKind {
-                                        formula: self.lexer.get_formula(),
-                                        position: self.lexer.get_position() as usize,
-                                        message: format!(
-                                            "Expecting column: {right} in table {table_name}"
-                                        ),
-                                    };
-                                }
-                            };
-                            return Node::RangeKind {
-                                sheet_name,
-                                sheet_index: table_sheet_index,
-                                absolute_row1: true,
-                                absolute_column1: true,
-                                row1: row_start,
-                                column1: left_column_index,
-                                absolute_row2: true,
-                                absolute_column2: true,
-                                row2: row_end,
-                                column2: right_column_index,
-                            };
-                        }
-                    }
-                }
-                Node::ParseErrorKind {
+    /// Finds the table of a structured reference. Without the name of the table it is the table
+    /// of the cell: [@Price], and the name is left out of the node too. Columns not in the table
+    /// are #REF! when evaluated.
+    fn parse_structured_reference(
+        &self,
+        table_name: &str,
+        specifier: Option<token::TableSpecifier>,
+        table_reference: Option<TableReference>,
+    ) -> Node {
+        let context = match &self.context {
+            Some(context) => context,
+            None => {
+                return Node::ParseErrorKind {
                     formula: self.lexer.get_formula(),
                     position: 0,
-                    message: "Structured references not supported in R1C1 mode".to_string(),
-                }
+                    message: "Structured references need the cell of the formula".to_string(),
+                };
+            }
+        };
+        let table = if table_name.is_empty() {
+            self.tables
+                .values()
+                .find(|table| table_contains(table, &context.sheet, context.row, context.column))
+        } else {
+            let table_name = table_name.to_lowercase();
+            self.tables
+                .values()
+                .find(|table| table.name.to_lowercase() == table_name)
+        };
+        let table = match table {
+            Some(table) => table,
+            // Internal formulas are shared by many cells, the table is the one of each cell
+            None if table_name.is_empty() && !self.lexer.is_a1_mode() => {
+                return Node::TableReferenceKind {
+                    table_name: String::new(),
+                    specifier,
+                    table_reference,
+                };
+            }
+            None => {
+                let message = if table_name.is_empty() {
+                    "Structured reference outside of a table".to_string()
+                } else {
+                    format!("Table not found: '{}'", table_name)
+                };
+                return Node::ParseErrorKind {
+                    formula: self.lexer.get_formula(),
+                    position: 0,
+                    message,
+                };
+            }
+        };
+        // Names are written as they are in the table
+        let column_name = |name: String| match get_table_column_by_name(&name, table) {
+            Some(index) => table.columns[index as usize].name.clone(),
+            None => name,
+        };
+        let table_reference = match table_reference {
+            Some(TableReference::ColumnReference(name)) => {
+                Some(TableReference::ColumnReference(column_name(name)))
+            }
+            Some(TableReference::RangeReference((left, right))) => Some(
+                TableReference::RangeReference((column_name(left), column_name(right))),
+            ),
+            None => None,
+        };
+        let table_name = if table_name.is_empty() {
+            String::new()
+        } else {
+            table.name.clone()
+        };
+        Node::TableReferenceKind {
+            table_name,
+            specifier,
+            table_reference,
+        }
+    }
+
+    // Elements of one row of an array literal: 1,2,3
+    fn parse_array_row(&mut self) -> Result<Vec<Node>, Node> {
+        let mut row = Vec::new();
+        loop {
+            let t = self.parse_expr();
+            if let Node::ParseErrorKind { .. } = t {
+                return Err(t);
+            }
+            row.push(t);
+            if self.lexer.peek_token() == TokenType::Comma {
+                self.lexer.advance_token();
+            } else {
+                return Ok(row);
             }
         }
     }
 
     fn parse_function_args(&mut self) -> Result<Vec<Node>, Node> {
+        let separator = self.lexer.argument_separator();
         let mut args: Vec<Node> = Vec::new();
         let mut next_token = self.lexer.peek_token();
         if next_token == TokenType::RightParenthesis {
             return Ok(args);
         }
-        if self.lexer.peek_token() == TokenType::Comma {
+        if self.lexer.peek_token() == separator {
             args.push(Node::EmptyArgKind);
+            self.push_span(self.lexer.get_position() as usize);
         } else {
             let t = self.parse_expr();
             if let Node::ParseErrorKind { .. } = t {
@@ -855,13 +1232,15 @@ impl Parser {
             args.push(t);
         }
         next_token = self.lexer.peek_token();
-        while next_token == TokenType::Comma {
+        while next_token == separator {
             self.lexer.advance_token();
-            if self.lexer.peek_token() == TokenType::Comma {
+            if self.lexer.peek_token() == separator {
                 args.push(Node::EmptyArgKind);
-                next_token = TokenType::Comma;
+                self.push_span(self.lexer.get_position() as usize);
+                next_token = separator.clone();
             } else if self.lexer.peek_token() == TokenType::RightParenthesis {
                 args.push(Node::EmptyArgKind);
+                self.push_span(self.lexer.get_position() as usize);
                 return Ok(args);
             } else {
                 let p = self.parse_expr();
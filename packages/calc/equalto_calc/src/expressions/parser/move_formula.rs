@@ -1,5 +1,5 @@
 use super::{
-    stringify::{stringify_reference, DisplaceData},
+    stringify::{stringify_reference, to_string, DisplaceData},
     Node, Reference,
 };
 use crate::{
@@ -265,6 +265,19 @@ fn to_string_moved(node: &Node, move_context: &MoveContext) -> String {
                 false,
             )
         }
+        ExternalReferenceKind { .. }
+        | ExternalRangeKind { .. }
+        | SheetSpanReferenceKind { .. }
+        | SheetSpanRangeKind { .. }
+        | TableReferenceKind { .. } => {
+            // They are not moved with the area, like in Excel
+            let context = CellReferenceRC {
+                sheet: move_context.source_sheet_name.to_string(),
+                column: move_context.column,
+                row: move_context.row,
+            };
+            to_string(node, &context)
+        }
         WrongRangeKind {
             sheet_name,
             absolute_row1,
@@ -323,6 +336,19 @@ fn to_string_moved(node: &Node, move_context: &MoveContext) -> String {
             to_string_moved(left, move_context),
             to_string_moved(right, move_context),
         ),
+        OpIntersectKind { left, right } => format!(
+            "{} {}",
+            to_string_moved(left, move_context),
+            to_string_moved(right, move_context),
+        ),
+        OpUnionKind(items) => format!(
+            "({})",
+            items
+                .iter()
+                .map(|item| to_string_moved(item, move_context))
+                .collect::<Vec<String>>()
+                .join(",")
+        ),
         OpConcatenateKind { left, right } => format!(
             "{}&{}",
             to_string_moved(left, move_context),
@@ -361,19 +387,22 @@ fn to_string_moved(node: &Node, move_context: &MoveContext) -> String {
             let name = &kind.to_string();
             move_function(name, args, move_context)
         }
-        ArrayKind(args) => {
-            // This code is a placeholder. Arrays are not yet implemented
-            let mut first = true;
-            let mut arguments = "".to_string();
-            for el in args {
-                if !first {
-                    arguments = format!("{},{}", arguments, to_string_moved(el, move_context));
-                } else {
-                    first = false;
-                    arguments = to_string_moved(el, move_context);
-                }
-            }
-            format!("{{{}}}", arguments)
+        ArrayKind(rows) => {
+            let rows: Vec<String> = rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|el| to_string_moved(el, move_context))
+                        .collect::<Vec<String>>()
+                        .join(",")
+                })
+                .collect();
+            format!("{{{}}}", rows.join(";"))
+        }
+        SpillRangeKind(reference) => format!("{}#", to_string_moved(reference, move_context)),
+        CallKind { function, args } => {
+            let function = &to_string_moved(function, move_context);
+            move_function(function, args, move_context)
         }
         VariableKind(value) => value.to_string(),
         CompareKind { kind, left, right } => format!(
@@ -1,6 +1,13 @@
-use super::{super::utils::quote_name, Node, Reference};
+use super::{
+    super::utils::{quote_external_name, quote_name, quote_sheet_span},
+    Node, Reference,
+};
 use crate::constants::{LAST_COLUMN, LAST_ROW};
-use crate::expressions::token::OpUnary;
+use crate::expressions::token::{OpUnary, TableReference, TableSpecifier};
+use crate::functions::Function;
+use crate::language::Language;
+use crate::locale::Locale;
+use crate::types::ReferenceStyle;
 use crate::{expressions::types::CellReferenceRC, number_format::to_excel_precision_str};
 
 pub enum DisplaceData {
@@ -34,8 +41,101 @@ pub enum DisplaceData {
     None,
 }
 
+/// The names, separators and references used to write a formula
+#[derive(Clone, Copy)]
+enum Dialect<'a> {
+    /// English, the way formulas are stored
+    Canonical(ReferenceStyle),
+    /// English with the prefixes used in xlsx files: `_xlfn.LET`
+    Xlsx,
+    /// The language and the separators of the user
+    Localized(&'a Locale, &'a Language, ReferenceStyle),
+}
+
+impl Dialect<'_> {
+    fn list_separator(&self) -> char {
+        match self {
+            Dialect::Localized(locale, _, _) => locale.formula_list_separator(),
+            _ => ',',
+        }
+    }
+
+    fn array_column_separator(&self) -> char {
+        match self {
+            Dialect::Localized(locale, _, _) => locale.formula_array_column_separator(),
+            _ => ',',
+        }
+    }
+
+    fn is_r1c1(&self) -> bool {
+        matches!(
+            self,
+            Dialect::Canonical(ReferenceStyle::R1C1)
+                | Dialect::Localized(_, _, ReferenceStyle::R1C1)
+        )
+    }
+
+    /// Writes `reference` in A1 style, see [`stringify_reference`], or in R1C1 style
+    /// the way users write it: `R[-1]C` instead of `R[-1]C[0]`
+    fn stringify_reference(
+        &self,
+        context: Option<&CellReferenceRC>,
+        displace_data: &DisplaceData,
+        reference: &Reference,
+        full_row: bool,
+        full_column: bool,
+    ) -> String {
+        match self {
+            Dialect::Canonical(ReferenceStyle::R1C1)
+            | Dialect::Localized(_, _, ReferenceStyle::R1C1) => {
+                if let Some(context) = context {
+                    let row = if reference.absolute_row {
+                        reference.row
+                    } else {
+                        reference.row + context.row
+                    };
+                    let column = if reference.absolute_column {
+                        reference.column
+                    } else {
+                        reference.column + context.column
+                    };
+                    if !(1..=LAST_ROW).contains(&row) || !(1..=LAST_COLUMN).contains(&column) {
+                        return "#REF!".to_string();
+                    }
+                }
+                let row = match (reference.absolute_row, reference.row) {
+                    (true, row) => format!("R{}", row),
+                    (false, 0) => "R".to_string(),
+                    (false, row) => format!("R[{}]", row),
+                };
+                let column = match (reference.absolute_column, reference.column) {
+                    (true, column) => format!("C{}", column),
+                    (false, 0) => "C".to_string(),
+                    (false, column) => format!("C[{}]", column),
+                };
+                // Whole columns are C2 or C[-1] and whole rows R3 or R[1]
+                let (row, column) = match (full_row, full_column) {
+                    (true, false) => (String::new(), column),
+                    (false, true) => (row, String::new()),
+                    _ => (row, column),
+                };
+                match reference.sheet_name {
+                    Some(name) => format!("{}!{}{}", quote_name(name), row, column),
+                    None => format!("{}{}", row, column),
+                }
+            }
+            _ => stringify_reference(context, displace_data, reference, full_row, full_column),
+        }
+    }
+}
+
 pub fn to_rc_format(node: &Node) -> String {
-    stringify(node, None, &DisplaceData::None, false)
+    stringify(
+        node,
+        None,
+        &DisplaceData::None,
+        Dialect::Canonical(ReferenceStyle::A1),
+    )
 }
 
 pub fn to_string_displaced(
@@ -43,15 +143,131 @@ pub fn to_string_displaced(
     context: &CellReferenceRC,
     displace_data: &DisplaceData,
 ) -> String {
-    stringify(node, Some(context), displace_data, false)
+    stringify(
+        node,
+        Some(context),
+        displace_data,
+        Dialect::Canonical(ReferenceStyle::A1),
+    )
 }
 
 pub fn to_string(node: &Node, context: &CellReferenceRC) -> String {
-    stringify(node, Some(context), &DisplaceData::None, false)
+    to_string_in_style(node, context, ReferenceStyle::A1)
+}
+
+/// Writes the formula with the references in `reference_style`, relative to `context`
+pub fn to_string_in_style(
+    node: &Node,
+    context: &CellReferenceRC,
+    reference_style: ReferenceStyle,
+) -> String {
+    stringify(
+        node,
+        Some(context),
+        &DisplaceData::None,
+        Dialect::Canonical(reference_style),
+    )
 }
 
 pub fn to_excel_string(node: &Node, context: &CellReferenceRC) -> String {
-    stringify(node, Some(context), &DisplaceData::None, true)
+    stringify(node, Some(context), &DisplaceData::None, Dialect::Xlsx)
+}
+
+/// Writes the formula in `language` and with the separators of `locale`,
+/// the way [`Parser::set_locale`](super::Parser::set_locale) reads it
+pub fn to_localized_string(
+    node: &Node,
+    context: &CellReferenceRC,
+    locale: &Locale,
+    language: &Language,
+    reference_style: ReferenceStyle,
+) -> String {
+    stringify(
+        node,
+        Some(context),
+        &DisplaceData::None,
+        Dialect::Localized(locale, language, reference_style),
+    )
+}
+
+/// Returns true if the name of the column can be written without brackets: Sales[Price].
+/// After '@' spaces need brackets too: Sales[@[Unit Price]].
+fn is_simple_column_name(name: &str, this_row: bool) -> bool {
+    !name.starts_with(char::is_whitespace)
+        && !name
+            .chars()
+            .any(|c| "\t\n\r,:.[]#'@\"{}$^&*+=-<>/".contains(c) || (this_row && c == ' '))
+}
+
+/// Escapes the special characters of the name of a column with `'`
+fn escape_column_name(name: &str) -> String {
+    let mut escaped = String::new();
+    for c in name.chars() {
+        if "[]#'@".contains(c) {
+            escaped.push('\'');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn table_specifier_names(specifier: &TableSpecifier) -> Vec<&'static str> {
+    match specifier {
+        TableSpecifier::All => vec!["#All"],
+        TableSpecifier::Data => vec!["#Data"],
+        TableSpecifier::Headers => vec!["#Headers"],
+        TableSpecifier::ThisRow => vec!["#This Row"],
+        TableSpecifier::Totals => vec!["#Totals"],
+        TableSpecifier::HeadersAndData => vec!["#Headers", "#Data"],
+        TableSpecifier::DataAndTotals => vec!["#Data", "#Totals"],
+    }
+}
+
+/// Writes a structured reference the way Excel shows it:
+/// Sales[Price], Sales[[Unit Price]:[Tax]], Sales[#Totals], Sales[@Price] or Sales[[#Totals],[Price]]
+fn stringify_table_reference(
+    table_name: &str,
+    specifier: &Option<TableSpecifier>,
+    table_reference: &Option<TableReference>,
+    list_separator: char,
+) -> String {
+    let columns = match table_reference {
+        Some(TableReference::ColumnReference(name)) => {
+            Some(format!("[{}]", escape_column_name(name)))
+        }
+        Some(TableReference::RangeReference((left, right))) => Some(format!(
+            "[{}]:[{}]",
+            escape_column_name(left),
+            escape_column_name(right)
+        )),
+        None => None,
+    };
+    let arguments = match (specifier, table_reference) {
+        (None, Some(TableReference::ColumnReference(name)))
+            if is_simple_column_name(name, false) =>
+        {
+            name.to_string()
+        }
+        (Some(TableSpecifier::ThisRow), Some(TableReference::ColumnReference(name)))
+            if is_simple_column_name(name, true) =>
+        {
+            format!("@{}", name)
+        }
+        (Some(TableSpecifier::ThisRow), _) => format!("@{}", columns.unwrap_or_default()),
+        (None, _) => columns.unwrap_or_default(),
+        (Some(specifier), None) if table_specifier_names(specifier).len() == 1 => {
+            table_specifier_names(specifier)[0].to_string()
+        }
+        (Some(specifier), _) => {
+            let mut items: Vec<String> = table_specifier_names(specifier)
+                .iter()
+                .map(|name| format!("[{}]", name))
+                .collect();
+            items.extend(columns);
+            items.join(&list_separator.to_string())
+        }
+    };
+    format!("{}[{}]", table_name, arguments)
 }
 
 /// Converts a local reference to a string applying some displacement if needed.
@@ -235,35 +451,138 @@ fn format_function(
     args: &Vec<Node>,
     context: Option<&CellReferenceRC>,
     displace_data: &DisplaceData,
-    use_original_name: bool,
+    dialect: Dialect,
 ) -> String {
     let mut first = true;
     let mut arguments = "".to_string();
     for el in args {
         if !first {
             arguments = format!(
-                "{},{}",
+                "{}{}{}",
                 arguments,
-                stringify(el, context, displace_data, use_original_name)
+                dialect.list_separator(),
+                stringify(el, context, displace_data, dialect)
             );
         } else {
             first = false;
-            arguments = stringify(el, context, displace_data, use_original_name);
+            arguments = stringify(el, context, displace_data, dialect);
         }
     }
     format!("{}({})", name, arguments)
 }
 
+/// Names bound by LET and parameters of LAMBDA are prefixed by `_xlpm.` in xlsx files
+fn prefix_parameter_names(kind: &Function, args: &[Node]) -> Vec<Node> {
+    let mut args = args.to_vec();
+    let parameters: Vec<&Node> = match kind {
+        Function::Let => args.iter().step_by(2).take(args.len() / 2).collect(),
+        _ => args.iter().take(args.len().saturating_sub(1)).collect(),
+    };
+    let names: Vec<String> = parameters
+        .into_iter()
+        .filter_map(|arg| match arg {
+            Node::VariableKind(name) if !name.to_lowercase().starts_with("_xlpm.") => {
+                Some(name.clone())
+            }
+            _ => None,
+        })
+        .collect();
+    for name in names {
+        let new_name = format!("_xlpm.{}", name);
+        for arg in args.iter_mut() {
+            rename_defined_name_in_node(arg, &name, &new_name);
+        }
+    }
+    args
+}
+
+// Precedence of the operators, from the loosest to the tightest binding one
+const COMPARE: u8 = 1;
+const CONCATENATE: u8 = 2;
+const SUM: u8 = 3;
+const PRODUCT: u8 = 4;
+const POWER: u8 = 5;
+const UNARY: u8 = 6;
+const INTERSECT: u8 = 7;
+const RANGE: u8 = 8;
+const SPILL: u8 = 9;
+const PRIMARY: u8 = 10;
+
+/// Returns the precedence of the operator at the root of `node`
+pub(crate) fn precedence(node: &Node) -> u8 {
+    match node {
+        Node::CompareKind { .. } => COMPARE,
+        Node::OpConcatenateKind { .. } => CONCATENATE,
+        Node::OpSumKind { .. } => SUM,
+        Node::OpProductKind { .. } => PRODUCT,
+        Node::OpPowerKind { .. } => POWER,
+        Node::UnaryKind { .. } => UNARY,
+        Node::OpIntersectKind { .. } => INTERSECT,
+        Node::OpRangeKind { .. } => RANGE,
+        Node::SpillRangeKind(_) => SPILL,
+        _ => PRIMARY,
+    }
+}
+
+/// Returns the minimum precedence the operands of `node` need to be written without
+/// parenthesis, for the left (or only) operand and for the right one.
+/// Binary operators are left associative and a minus sign applies to ranges, so `-2^2` is 4.
+pub(crate) fn operand_precedence(node: &Node) -> (u8, u8) {
+    match node {
+        Node::CompareKind { .. } => (COMPARE, CONCATENATE),
+        Node::OpConcatenateKind { .. } => (CONCATENATE, SUM),
+        Node::OpSumKind { .. } => (SUM, PRODUCT),
+        Node::OpProductKind { .. } => (PRODUCT, POWER),
+        Node::OpPowerKind { .. } => (POWER, UNARY),
+        Node::UnaryKind {
+            kind: OpUnary::Minus,
+            ..
+        } => (INTERSECT, INTERSECT),
+        Node::UnaryKind {
+            kind: OpUnary::Percentage,
+            ..
+        } => (UNARY, UNARY),
+        Node::OpIntersectKind { .. } => (INTERSECT, RANGE),
+        Node::OpRangeKind { .. } => (SPILL, SPILL),
+        _ => (COMPARE, COMPARE),
+    }
+}
+
+/// Stringifies an operand, wrapped in parenthesis if it binds looser than `min_precedence`
+fn stringify_operand(
+    node: &Node,
+    min_precedence: u8,
+    context: Option<&CellReferenceRC>,
+    displace_data: &DisplaceData,
+    dialect: Dialect,
+) -> String {
+    let operand = stringify(node, context, displace_data, dialect);
+    if precedence(node) < min_precedence {
+        format!("({})", operand)
+    } else {
+        operand
+    }
+}
+
 fn stringify(
     node: &Node,
     context: Option<&CellReferenceRC>,
     displace_data: &DisplaceData,
-    use_original_name: bool,
+    dialect: Dialect,
 ) -> String {
     use self::Node::*;
+    let (left_precedence, right_precedence) = operand_precedence(node);
     match node {
-        BooleanKind(value) => format!("{}", value).to_ascii_uppercase(),
-        NumberKind(number) => to_excel_precision_str(*number),
+        BooleanKind(value) => match (dialect, value) {
+            (Dialect::Localized(_, language, _), true) => language.booleans.true_value.clone(),
+            (Dialect::Localized(_, language, _), false) => language.booleans.false_value.clone(),
+            _ => format!("{}", value).to_ascii_uppercase(),
+        },
+        NumberKind(number) => match dialect {
+            Dialect::Localized(locale, _, _) => to_excel_precision_str(*number)
+                .replace('.', &locale.formula_decimal_separator().to_string()),
+            _ => to_excel_precision_str(*number),
+        },
         StringKind(value) => format!("\"{}\"", value),
         WrongReferenceKind {
             sheet_name,
@@ -271,7 +590,7 @@ fn stringify(
             row,
             absolute_row,
             absolute_column,
-        } => stringify_reference(
+        } => dialect.stringify_reference(
             context,
             &DisplaceData::None,
             &Reference {
@@ -292,7 +611,7 @@ fn stringify(
             row,
             absolute_row,
             absolute_column,
-        } => stringify_reference(
+        } => dialect.stringify_reference(
             context,
             displace_data,
             &Reference {
@@ -326,7 +645,7 @@ fn stringify(
                 && *absolute_column2
                 && (*column1 == 1)
                 && (*column2 == LAST_COLUMN);
-            let s1 = stringify_reference(
+            let s1 = dialect.stringify_reference(
                 context,
                 displace_data,
                 &Reference {
@@ -340,7 +659,7 @@ fn stringify(
                 full_row,
                 full_column,
             );
-            let s2 = stringify_reference(
+            let s2 = dialect.stringify_reference(
                 context,
                 displace_data,
                 &Reference {
@@ -354,7 +673,18 @@ fn stringify(
                 full_row,
                 full_column,
             );
-            format!("{}:{}", s1, s2)
+            // In R1C1 style a single row or column is written once: C[-1] is A:A in B1
+            let single_column = full_row
+                && !full_column
+                && column1 == column2
+                && absolute_column1 == absolute_column2;
+            let single_row =
+                full_column && !full_row && row1 == row2 && absolute_row1 == absolute_row2;
+            if dialect.is_r1c1() && (single_column || single_row) {
+                s1
+            } else {
+                format!("{}:{}", s1, s2)
+            }
         }
         WrongRangeKind {
             sheet_name,
@@ -375,7 +705,7 @@ fn stringify(
                 && *absolute_column2
                 && (*column1 == 1)
                 && (*column2 == LAST_COLUMN);
-            let s1 = stringify_reference(
+            let s1 = dialect.stringify_reference(
                 context,
                 &DisplaceData::None,
                 &Reference {
@@ -389,7 +719,7 @@ fn stringify(
                 full_row,
                 full_column,
             );
-            let s2 = stringify_reference(
+            let s2 = dialect.stringify_reference(
                 context,
                 &DisplaceData::None,
                 &Reference {
@@ -405,106 +735,286 @@ fn stringify(
             );
             format!("{}:{}", s1, s2)
         }
+        // References to other workbooks are not displaced by changes to this one
+        ExternalReferenceKind {
+            workbook,
+            sheet_name,
+            absolute_row,
+            absolute_column,
+            row,
+            column,
+        } => {
+            let reference = dialect.stringify_reference(
+                context,
+                &DisplaceData::None,
+                &Reference {
+                    sheet_name: &None,
+                    sheet_index: 0,
+                    row: *row,
+                    column: *column,
+                    absolute_row: *absolute_row,
+                    absolute_column: *absolute_column,
+                },
+                false,
+                false,
+            );
+            format!(
+                "{}!{}",
+                quote_external_name(workbook, sheet_name),
+                reference
+            )
+        }
+        ExternalRangeKind {
+            workbook,
+            sheet_name,
+            absolute_row1,
+            absolute_column1,
+            row1,
+            column1,
+            absolute_row2,
+            absolute_column2,
+            row2,
+            column2,
+        } => {
+            let full_row = *absolute_row1 && *absolute_row2 && (*row1 == 1) && (*row2 == LAST_ROW);
+            let full_column = *absolute_column1
+                && *absolute_column2
+                && (*column1 == 1)
+                && (*column2 == LAST_COLUMN);
+            let s1 = dialect.stringify_reference(
+                context,
+                &DisplaceData::None,
+                &Reference {
+                    sheet_name: &None,
+                    sheet_index: 0,
+                    row: *row1,
+                    column: *column1,
+                    absolute_row: *absolute_row1,
+                    absolute_column: *absolute_column1,
+                },
+                full_row,
+                full_column,
+            );
+            let s2 = dialect.stringify_reference(
+                context,
+                &DisplaceData::None,
+                &Reference {
+                    sheet_name: &None,
+                    sheet_index: 0,
+                    row: *row2,
+                    column: *column2,
+                    absolute_row: *absolute_row2,
+                    absolute_column: *absolute_column2,
+                },
+                full_row,
+                full_column,
+            );
+            format!(
+                "{}!{}:{}",
+                quote_external_name(workbook, sheet_name),
+                s1,
+                s2
+            )
+        }
+        // Like references to other workbooks, spans of sheets are not displaced by changes to
+        // the rows or columns of one of the sheets
+        SheetSpanReferenceKind {
+            first_sheet,
+            last_sheet,
+            absolute_row,
+            absolute_column,
+            row,
+            column,
+            ..
+        } => {
+            let reference = dialect.stringify_reference(
+                context,
+                &DisplaceData::None,
+                &Reference {
+                    sheet_name: &None,
+                    sheet_index: 0,
+                    row: *row,
+                    column: *column,
+                    absolute_row: *absolute_row,
+                    absolute_column: *absolute_column,
+                },
+                false,
+                false,
+            );
+            format!(
+                "{}!{}",
+                quote_sheet_span(first_sheet, last_sheet),
+                reference
+            )
+        }
+        SheetSpanRangeKind {
+            first_sheet,
+            last_sheet,
+            absolute_row1,
+            absolute_column1,
+            row1,
+            column1,
+            absolute_row2,
+            absolute_column2,
+            row2,
+            column2,
+            ..
+        } => {
+            let full_row = *absolute_row1 && *absolute_row2 && (*row1 == 1) && (*row2 == LAST_ROW);
+            let full_column = *absolute_column1
+                && *absolute_column2
+                && (*column1 == 1)
+                && (*column2 == LAST_COLUMN);
+            let s1 = dialect.stringify_reference(
+                context,
+                &DisplaceData::None,
+                &Reference {
+                    sheet_name: &None,
+                    sheet_index: 0,
+                    row: *row1,
+                    column: *column1,
+                    absolute_row: *absolute_row1,
+                    absolute_column: *absolute_column1,
+                },
+                full_row,
+                full_column,
+            );
+            let s2 = dialect.stringify_reference(
+                context,
+                &DisplaceData::None,
+                &Reference {
+                    sheet_name: &None,
+                    sheet_index: 0,
+                    row: *row2,
+                    column: *column2,
+                    absolute_row: *absolute_row2,
+                    absolute_column: *absolute_column2,
+                },
+                full_row,
+                full_column,
+            );
+            format!(
+                "{}!{}:{}",
+                quote_sheet_span(first_sheet, last_sheet),
+                s1,
+                s2
+            )
+        }
+        TableReferenceKind {
+            table_name,
+            specifier,
+            table_reference,
+        } => stringify_table_reference(
+            table_name,
+            specifier,
+            table_reference,
+            dialect.list_separator(),
+        ),
         OpRangeKind { left, right } => format!(
             "{}:{}",
-            stringify(left, context, displace_data, use_original_name),
-            stringify(right, context, displace_data, use_original_name)
+            stringify_operand(left, left_precedence, context, displace_data, dialect),
+            stringify_operand(right, right_precedence, context, displace_data, dialect)
+        ),
+        OpIntersectKind { left, right } => format!(
+            "{} {}",
+            stringify_operand(left, left_precedence, context, displace_data, dialect),
+            stringify_operand(right, right_precedence, context, displace_data, dialect)
+        ),
+        OpUnionKind(items) => format!(
+            "({})",
+            items
+                .iter()
+                .map(|item| stringify(item, context, displace_data, dialect))
+                .collect::<Vec<String>>()
+                .join(&dialect.list_separator().to_string())
         ),
         OpConcatenateKind { left, right } => format!(
             "{}&{}",
-            stringify(left, context, displace_data, use_original_name),
-            stringify(right, context, displace_data, use_original_name)
+            stringify_operand(left, left_precedence, context, displace_data, dialect),
+            stringify_operand(right, right_precedence, context, displace_data, dialect)
         ),
         CompareKind { kind, left, right } => format!(
             "{}{}{}",
-            stringify(left, context, displace_data, use_original_name),
+            stringify_operand(left, left_precedence, context, displace_data, dialect),
             kind,
-            stringify(right, context, displace_data, use_original_name)
+            stringify_operand(right, right_precedence, context, displace_data, dialect)
         ),
         OpSumKind { kind, left, right } => format!(
             "{}{}{}",
-            stringify(left, context, displace_data, use_original_name),
+            stringify_operand(left, left_precedence, context, displace_data, dialect),
             kind,
-            stringify(right, context, displace_data, use_original_name)
+            stringify_operand(right, right_precedence, context, displace_data, dialect)
+        ),
+        OpProductKind { kind, left, right } => format!(
+            "{}{}{}",
+            stringify_operand(left, left_precedence, context, displace_data, dialect),
+            kind,
+            stringify_operand(right, right_precedence, context, displace_data, dialect)
         ),
-        OpProductKind { kind, left, right } => {
-            let x = match **left {
-                OpSumKind { .. } => format!(
-                    "({})",
-                    stringify(left, context, displace_data, use_original_name)
-                ),
-                CompareKind { .. } => format!(
-                    "({})",
-                    stringify(left, context, displace_data, use_original_name)
-                ),
-                _ => stringify(left, context, displace_data, use_original_name),
-            };
-            let y = match **right {
-                OpSumKind { .. } => format!(
-                    "({})",
-                    stringify(right, context, displace_data, use_original_name)
-                ),
-                CompareKind { .. } => format!(
-                    "({})",
-                    stringify(right, context, displace_data, use_original_name)
-                ),
-                OpProductKind { .. } => format!(
-                    "({})",
-                    stringify(right, context, displace_data, use_original_name)
-                ),
-                _ => stringify(right, context, displace_data, use_original_name),
-            };
-            format!("{}{}{}", x, kind, y)
-        }
         OpPowerKind { left, right } => format!(
             "{}^{}",
-            stringify(left, context, displace_data, use_original_name),
-            stringify(right, context, displace_data, use_original_name)
+            stringify_operand(left, left_precedence, context, displace_data, dialect),
+            stringify_operand(right, right_precedence, context, displace_data, dialect)
         ),
         InvalidFunctionKind { name, args } => {
-            format_function(name, args, context, displace_data, use_original_name)
+            format_function(name, args, context, displace_data, dialect)
         }
         FunctionKind { kind, args } => {
-            let name = if use_original_name {
-                kind.to_xlsx_string()
-            } else {
-                kind.to_string()
+            let name = match dialect {
+                Dialect::Canonical(_) => kind.to_string(),
+                Dialect::Xlsx => kind.to_xlsx_string(),
+                Dialect::Localized(_, language, _) => language.get_function_name(kind),
             };
-            format_function(&name, args, context, displace_data, use_original_name)
-        }
-        ArrayKind(args) => {
-            let mut first = true;
-            let mut arguments = "".to_string();
-            for el in args {
-                if !first {
-                    arguments = format!(
-                        "{},{}",
-                        arguments,
-                        stringify(el, context, displace_data, use_original_name)
-                    );
-                } else {
-                    first = false;
-                    arguments = stringify(el, context, displace_data, use_original_name);
-                }
+            if matches!(dialect, Dialect::Xlsx) && matches!(kind, Function::Let | Function::Lambda)
+            {
+                let args = prefix_parameter_names(kind, args);
+                return format_function(&name, &args, context, displace_data, dialect);
+            }
+            format_function(&name, args, context, displace_data, dialect)
+        }
+        ArrayKind(rows) => {
+            let rows: Vec<String> = rows
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|el| stringify(el, context, displace_data, dialect))
+                        .collect::<Vec<String>>()
+                        .join(&dialect.array_column_separator().to_string())
+                })
+                .collect();
+            format!("{{{}}}", rows.join(";"))
+        }
+        CallKind { function, args } => {
+            let function = stringify(function, context, displace_data, dialect);
+            format_function(&function, args, context, displace_data, dialect)
+        }
+        SpillRangeKind(reference) => {
+            let reference = stringify(reference, context, displace_data, dialect);
+            if matches!(dialect, Dialect::Xlsx) {
+                format!("_xlfn.ANCHORARRAY({})", reference)
+            } else {
+                format!("{}#", reference)
             }
-            format!("{{{}}}", arguments)
         }
         VariableKind(value) => value.to_string(),
         UnaryKind { kind, right } => match kind {
             OpUnary::Minus => {
                 format!(
                     "-{}",
-                    stringify(right, context, displace_data, use_original_name)
+                    stringify_operand(right, right_precedence, context, displace_data, dialect)
                 )
             }
             OpUnary::Percentage => {
                 format!(
                     "{}%",
-                    stringify(right, context, displace_data, use_original_name)
+                    stringify_operand(right, right_precedence, context, displace_data, dialect)
                 )
             }
         },
-        ErrorKind(kind) => format!("{}", kind),
+        ErrorKind(kind) => match dialect {
+            Dialect::Localized(_, language, _) => kind.to_localized_error_string(language),
+            _ => format!("{}", kind),
+        },
         ParseErrorKind {
             formula,
             position: _,
@@ -535,6 +1045,27 @@ pub(crate) fn rename_sheet_in_node(node: &mut Node, sheet_index: u32, new_name:
                 *sheet_name = Some(new_name.to_owned());
             }
         }
+        Node::SheetSpanReferenceKind {
+            first_sheet,
+            first_sheet_index,
+            last_sheet,
+            last_sheet_index,
+            ..
+        }
+        | Node::SheetSpanRangeKind {
+            first_sheet,
+            first_sheet_index,
+            last_sheet,
+            last_sheet_index,
+            ..
+        } => {
+            if *first_sheet_index == sheet_index {
+                *first_sheet = new_name.to_owned();
+            }
+            if *last_sheet_index == sheet_index {
+                *last_sheet = new_name.to_owned();
+            }
+        }
         Node::WrongReferenceKind { sheet_name, .. } => {
             if let Some(name) = sheet_name {
                 if name.to_uppercase() == new_name.to_uppercase() {
@@ -549,10 +1080,15 @@ pub(crate) fn rename_sheet_in_node(node: &mut Node, sheet_index: u32, new_name:
         }
 
         // Go next level
-        Node::OpRangeKind { left, right } => {
+        Node::OpRangeKind { left, right } | Node::OpIntersectKind { left, right } => {
             rename_sheet_in_node(left, sheet_index, new_name);
             rename_sheet_in_node(right, sheet_index, new_name);
         }
+        Node::OpUnionKind(items) => {
+            for item in items {
+                rename_sheet_in_node(item, sheet_index, new_name);
+            }
+        }
         Node::OpConcatenateKind { left, right } => {
             rename_sheet_in_node(left, sheet_index, new_name);
             rename_sheet_in_node(right, sheet_index, new_name);
@@ -598,15 +1134,109 @@ pub(crate) fn rename_sheet_in_node(node: &mut Node, sheet_index: u32, new_name:
         Node::UnaryKind { kind: _, right } => {
             rename_sheet_in_node(right, sheet_index, new_name);
         }
+        Node::ArrayKind(rows) => {
+            for el in rows.iter_mut().flatten() {
+                rename_sheet_in_node(el, sheet_index, new_name);
+            }
+        }
+        Node::SpillRangeKind(reference) => {
+            rename_sheet_in_node(reference, sheet_index, new_name);
+        }
+        Node::CallKind { function, args } => {
+            rename_sheet_in_node(function, sheet_index, new_name);
+            for arg in args {
+                rename_sheet_in_node(arg, sheet_index, new_name);
+            }
+        }
 
         // Do nothing
+        Node::ExternalReferenceKind { .. } => {}
+        Node::ExternalRangeKind { .. } => {}
+        Node::TableReferenceKind { .. } => {}
         Node::BooleanKind(_) => {}
         Node::NumberKind(_) => {}
         Node::StringKind(_) => {}
         Node::ErrorKind(_) => {}
         Node::ParseErrorKind { .. } => {}
-        Node::ArrayKind(_) => {}
         Node::VariableKind(_) => {}
         Node::EmptyArgKind => {}
     }
 }
+
+/// Replaces the uses of the name `name` (a defined name or a LET name) in `node` by `new_name`
+pub(crate) fn rename_defined_name_in_node(node: &mut Node, name: &str, new_name: &str) {
+    match node {
+        // Rename
+        Node::VariableKind(variable) => {
+            if variable.to_lowercase() == name.to_lowercase() {
+                *variable = new_name.to_owned();
+            }
+        }
+
+        // Go next level
+        Node::OpRangeKind { left, right }
+        | Node::OpIntersectKind { left, right }
+        | Node::OpConcatenateKind { left, right }
+        | Node::OpSumKind { left, right, .. }
+        | Node::OpProductKind { left, right, .. }
+        | Node::OpPowerKind { left, right }
+        | Node::CompareKind { left, right, .. } => {
+            rename_defined_name_in_node(left, name, new_name);
+            rename_defined_name_in_node(right, name, new_name);
+        }
+        Node::OpUnionKind(items) => {
+            for item in items {
+                rename_defined_name_in_node(item, name, new_name);
+            }
+        }
+        Node::InvalidFunctionKind {
+            name: function,
+            args,
+        } => {
+            // A call to a named LAMBDA
+            if function.to_lowercase() == name.to_lowercase() {
+                *function = new_name.to_owned();
+            }
+            for arg in args {
+                rename_defined_name_in_node(arg, name, new_name);
+            }
+        }
+        Node::FunctionKind { args, .. } => {
+            for arg in args {
+                rename_defined_name_in_node(arg, name, new_name);
+            }
+        }
+        Node::CallKind { function, args } => {
+            rename_defined_name_in_node(function, name, new_name);
+            for arg in args {
+                rename_defined_name_in_node(arg, name, new_name);
+            }
+        }
+        Node::UnaryKind { right, .. } => {
+            rename_defined_name_in_node(right, name, new_name);
+        }
+        Node::ArrayKind(rows) => {
+            for el in rows.iter_mut().flatten() {
+                rename_defined_name_in_node(el, name, new_name);
+            }
+        }
+
+        // Do nothing
+        Node::BooleanKind(_)
+        | Node::NumberKind(_)
+        | Node::StringKind(_)
+        | Node::ReferenceKind { .. }
+        | Node::RangeKind { .. }
+        | Node::WrongReferenceKind { .. }
+        | Node::WrongRangeKind { .. }
+        | Node::ExternalReferenceKind { .. }
+        | Node::ExternalRangeKind { .. }
+        | Node::SheetSpanReferenceKind { .. }
+        | Node::SheetSpanRangeKind { .. }
+        | Node::TableReferenceKind { .. }
+        | Node::SpillRangeKind(_)
+        | Node::ErrorKind(_)
+        | Node::ParseErrorKind { .. }
+        | Node::EmptyArgKind => {}
+    }
+}
@@ -7,7 +7,7 @@ use super::super::types::CellReferenceRC;
 use super::Parser;
 use super::{
     super::parser::{
-        stringify::{to_rc_format, to_string},
+        stringify::{to_excel_string, to_rc_format, to_string},
         Node,
     },
     stringify::to_string_displaced,
@@ -495,3 +495,146 @@ fn test_to_string_displaced_too_high() {
     let t = to_string_displaced(&node, context, &displace_data);
     assert_eq!(t, "#REF!".to_string());
 }
+
+#[test]
+fn test_parser_arrays() {
+    let worksheets = vec!["Sheet1".to_string()];
+    let mut parser = Parser::new(worksheets, HashMap::new());
+
+    // Reference cell is Sheet1!A1
+    let cell_reference = CellReferenceRC {
+        sheet: "Sheet1".to_string(),
+        row: 1,
+        column: 1,
+    };
+    let t = parser.parse("SUM({1,2;3,-4})", &Some(cell_reference.clone()));
+    assert_eq!(to_rc_format(&t), "SUM({1,2;3,-4})");
+    assert_eq!(to_string(&t, &cell_reference), "SUM({1,2;3,-4})");
+    match &t {
+        Node::FunctionKind { args, .. } => match &args[0] {
+            Node::ArrayKind(rows) => {
+                assert_eq!(rows.len(), 2);
+                assert_eq!(rows[0].len(), 2);
+            }
+            _ => panic!("Expected an array"),
+        },
+        _ => panic!("Expected a function"),
+    }
+
+    // All rows must have the same length
+    let t = parser.parse("{1,2;3}", &Some(cell_reference));
+    assert!(matches!(t, Node::ParseErrorKind { .. }));
+}
+
+#[test]
+fn test_parser_spill_range() {
+    let worksheets = vec!["Sheet1".to_string(), "Sheet2".to_string()];
+    let mut parser = Parser::new(worksheets, HashMap::new());
+
+    // Reference cell is Sheet1!A1
+    let cell_reference = CellReferenceRC {
+        sheet: "Sheet1".to_string(),
+        row: 1,
+        column: 1,
+    };
+    let t = parser.parse("SUM(B2#)+Sheet2!$C$3#", &Some(cell_reference.clone()));
+    assert_eq!(to_rc_format(&t), "SUM(R[1]C[1]#)+Sheet2!R3C3#");
+    assert_eq!(to_string(&t, &cell_reference), "SUM(B2#)+Sheet2!$C$3#");
+    assert_eq!(
+        to_excel_string(&t, &cell_reference),
+        "SUM(_xlfn.ANCHORARRAY(B2))+_xlfn.ANCHORARRAY(Sheet2!$C$3)"
+    );
+
+    // Excel stores spill ranges as a function
+    let t = parser.parse("SUM(_xlfn.ANCHORARRAY(B2))", &Some(cell_reference.clone()));
+    assert_eq!(to_string(&t, &cell_reference), "SUM(B2#)");
+
+    // Errors are still errors
+    let t = parser.parse("#REF!+1", &Some(cell_reference.clone()));
+    assert_eq!(to_string(&t, &cell_reference), "#REF!+1");
+
+    let t = parser.parse("1#", &Some(cell_reference));
+    assert!(matches!(t, Node::ParseErrorKind { .. }));
+}
+
+#[test]
+fn test_parser_let() {
+    let worksheets = vec!["Sheet1".to_string()];
+    let mut parser = Parser::new(worksheets, HashMap::new());
+
+    // Reference cell is Sheet1!B2
+    let cell_reference = CellReferenceRC {
+        sheet: "Sheet1".to_string(),
+        row: 2,
+        column: 2,
+    };
+    let t = parser.parse(
+        "LET(x,A1*2,y,LET(x,3,x+1),x+y+Rate)",
+        &Some(cell_reference.clone()),
+    );
+    assert_eq!(
+        to_rc_format(&t),
+        "LET(x,R[-1]C[-1]*2,y,LET(x,3,x+1),x+y+Rate)"
+    );
+    assert_eq!(
+        to_string(&t, &cell_reference),
+        "LET(x,A1*2,y,LET(x,3,x+1),x+y+Rate)"
+    );
+    assert_eq!(
+        to_excel_string(&t, &cell_reference),
+        "_xlfn.LET(_xlpm.x,A1*2,_xlpm.y,_xlfn.LET(_xlpm.x,3,_xlpm.x+1),_xlpm.x+_xlpm.y+Rate)"
+    );
+
+    // Excel prefixes LET names with _xlpm.
+    let t = parser.parse(
+        "_xlfn.LET(_xlpm.x,A1*2,_xlpm.x+1)",
+        &Some(cell_reference.clone()),
+    );
+    assert_eq!(to_string(&t, &cell_reference), "LET(x,A1*2,x+1)");
+
+    // Names and values come in pairs before the calculation
+    let t = parser.parse("LET(x,1)", &Some(cell_reference.clone()));
+    assert!(matches!(t, Node::ParseErrorKind { .. }));
+    let t = parser.parse("LET(x,1,y,2)", &Some(cell_reference.clone()));
+    assert!(matches!(t, Node::ParseErrorKind { .. }));
+    let t = parser.parse("LET(A1,1,A1+1)", &Some(cell_reference));
+    assert!(matches!(t, Node::ParseErrorKind { .. }));
+}
+
+#[test]
+fn test_parser_lambda() {
+    let worksheets = vec!["Sheet1".to_string()];
+    let mut parser = Parser::new(worksheets, HashMap::new());
+
+    // Reference cell is Sheet1!B2
+    let cell_reference = CellReferenceRC {
+        sheet: "Sheet1".to_string(),
+        row: 2,
+        column: 2,
+    };
+    let t = parser.parse("LAMBDA(x,y,x*y+A1)(3,A2)", &Some(cell_reference.clone()));
+    assert!(matches!(t, Node::CallKind { .. }));
+    assert_eq!(to_rc_format(&t), "LAMBDA(x,y,x*y+R[-1]C[-1])(3,R[0]C[-1])");
+    assert_eq!(to_string(&t, &cell_reference), "LAMBDA(x,y,x*y+A1)(3,A2)");
+    assert_eq!(
+        to_excel_string(&t, &cell_reference),
+        "_xlfn.LAMBDA(_xlpm.x,_xlpm.y,_xlpm.x*_xlpm.y+A1)(3,A2)"
+    );
+
+    // Excel prefixes LAMBDA parameters with _xlpm.
+    let t = parser.parse(
+        "_xlfn.LAMBDA(_xlpm.x,_xlpm.x+1)",
+        &Some(cell_reference.clone()),
+    );
+    assert_eq!(to_string(&t, &cell_reference), "LAMBDA(x,x+1)");
+
+    // Calls to named functions
+    let t = parser.parse("Double(A1)", &Some(cell_reference.clone()));
+    assert_eq!(to_string(&t, &cell_reference), "Double(A1)");
+
+    // Parameters are names
+    let t = parser.parse("LAMBDA()", &Some(cell_reference.clone()));
+    assert!(matches!(t, Node::ParseErrorKind { .. }));
+    let t = parser.parse("LAMBDA(A1,A1+1)", &Some(cell_reference));
+    assert!(matches!(t, Node::ParseErrorKind { .. }));
+}
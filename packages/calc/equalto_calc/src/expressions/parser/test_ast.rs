@@ -0,0 +1,244 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::expressions::ast::{
+    add_implicit_intersections, ast_to_formula, children, collect_function_calls,
+    collect_references, parse_formula, remove_implicit_intersections, walk, walk_mut, Node,
+    ParseOptions, Span, Visitor, VisitorMut,
+};
+
+fn options() -> ParseOptions {
+    ParseOptions {
+        sheets: vec!["Sheet1".to_string(), "Second Sheet".to_string()],
+        sheet: "Sheet1".to_string(),
+        row: 3,
+        column: 2,
+        ..Default::default()
+    }
+}
+
+/// Returns the text of every node of the tree in depth first order
+fn span_texts(formula: &str, span: &Span) -> Vec<String> {
+    let chars: Vec<char> = formula.chars().collect();
+    let mut texts = vec![chars[span.start..span.end].iter().collect()];
+    for child in &span.children {
+        texts.extend(span_texts(formula, child));
+    }
+    texts
+}
+
+#[test]
+fn test_round_trip() {
+    let options = options();
+    let formulas = [
+        "SUM(A1:A3)*B$1",
+        "-A1^2%+'Second Sheet'!$C$4",
+        "IF(A1>=3,\"yes\",\"no\")&TEXT(B2,\"0.00\")",
+        "{1,2;3,4}",
+        "LAMBDA(x,y,x*y)(2,3)",
+        "LET(x,5,x+1)",
+        "A1#",
+        "SUM(A1:B2,,C1)",
+        "INDEX(A1:C3,2,3):D5",
+        "NOSUCHFUNCTION(1)",
+        "#N/A",
+        "(1+2)*3",
+        "(1+2)^2",
+        "1-(2-3)",
+        "(1&2)+3",
+        "1=(2=3)",
+        "-(-A1)",
+        "-(A1%)",
+        "-2^-(1+1)",
+        "A1:B2%",
+        "[Rates.xlsx]Sheet1!$B$2*2",
+        "SUM('C:\\models\\[fx.xlsx]USD'!A1:B3)",
+        "SUM('Sheet1:Second Sheet'!B5,'Second Sheet:Sheet1'!$A$1:$B$2)",
+        "SUM((A1:A3,C1:C3),B2:D5 C1:C10)",
+        "-A1:B2 B1:C2",
+        "(A1 B1):C1",
+        "INDEX((A1:B2,D1:E2),1,1,2)",
+    ];
+    for formula in formulas {
+        let parsed = parse_formula(formula, &options);
+        let printed = ast_to_formula(&parsed.node, &options.context());
+        assert_eq!(parse_formula(&printed, &options).node, parsed.node);
+        assert_eq!(printed, formula);
+    }
+}
+
+#[test]
+fn test_spans() {
+    let options = options();
+    let formula = "SUM(A1:A3, 2) * -B1";
+    let parsed = parse_formula(formula, &options);
+    assert_eq!(
+        span_texts(formula, &parsed.span),
+        vec![
+            "SUM(A1:A3, 2) * -B1",
+            "SUM(A1:A3, 2)",
+            "A1:A3",
+            "2",
+            "-B1",
+            "B1"
+        ]
+    );
+
+    let formula = "{1,2;3,4}&A1#";
+    let parsed = parse_formula(formula, &options);
+    assert_eq!(
+        span_texts(formula, &parsed.span),
+        vec![
+            "{1,2;3,4}&A1#",
+            "{1,2;3,4}",
+            "1",
+            "2",
+            "3",
+            "4",
+            "A1#",
+            "A1"
+        ]
+    );
+
+    let formula = "IF(A1,,3)%";
+    let parsed = parse_formula(formula, &options);
+    assert_eq!(
+        span_texts(formula, &parsed.span),
+        vec!["IF(A1,,3)%", "IF(A1,,3)", "A1", "", "3"]
+    );
+
+    let formula = "LAMBDA(x,x*2)(4)";
+    let parsed = parse_formula(formula, &options);
+    assert_eq!(
+        span_texts(formula, &parsed.span),
+        vec![
+            "LAMBDA(x,x*2)(4)",
+            "LAMBDA(x,x*2)",
+            "x",
+            "x*2",
+            "x",
+            "2",
+            "4"
+        ]
+    );
+}
+
+#[test]
+fn test_spans_parse_error() {
+    let formula = "SUM(A1,";
+    let parsed = parse_formula(formula, &options());
+    assert!(matches!(parsed.node, Node::ParseErrorKind { .. }));
+    assert_eq!(
+        parsed.span,
+        Span {
+            start: 0,
+            end: 7,
+            children: vec![]
+        }
+    );
+}
+
+#[test]
+fn test_collect() {
+    let options = options();
+    let parsed = parse_formula("SUM(A1:A3)+MAX(B1,'Second Sheet'!C1)*LEN(\"A1\")", &options);
+    let references: Vec<String> = collect_references(&parsed.node)
+        .into_iter()
+        .map(|node| ast_to_formula(node, &options.context()))
+        .collect();
+    assert_eq!(references, vec!["A1:A3", "B1", "'Second Sheet'!C1"]);
+
+    let functions: Vec<String> = collect_function_calls(&parsed.node)
+        .into_iter()
+        .map(|node| match node {
+            Node::FunctionKind { kind, .. } => kind.to_string(),
+            _ => panic!("Unexpected node"),
+        })
+        .collect();
+    assert_eq!(functions, vec!["SUM", "MAX", "LEN"]);
+}
+
+#[test]
+fn test_visitor() {
+    struct Depth {
+        current: usize,
+        max: usize,
+        skipped: usize,
+    }
+
+    impl Visitor for Depth {
+        fn enter(&mut self, node: &Node) -> bool {
+            if matches!(node, Node::FunctionKind { .. }) {
+                self.skipped += children(node).len();
+                return false;
+            }
+            self.current += 1;
+            self.max = self.max.max(self.current);
+            true
+        }
+
+        fn leave(&mut self, _node: &Node) {
+            self.current -= 1;
+        }
+    }
+
+    let parsed = parse_formula("1+2*(3-SUM(4,5))", &options());
+    let mut visitor = Depth {
+        current: 0,
+        max: 0,
+        skipped: 0,
+    };
+    walk(&parsed.node, &mut visitor);
+    assert_eq!(visitor.current, 0);
+    assert_eq!(visitor.max, 4);
+    assert_eq!(visitor.skipped, 2);
+}
+
+#[test]
+fn test_visitor_mut() {
+    struct Double;
+
+    impl VisitorMut for Double {
+        fn enter(&mut self, node: &mut Node) -> bool {
+            if let Node::NumberKind(value) = node {
+                *value *= 2.0;
+            }
+            true
+        }
+    }
+
+    let options = options();
+    let mut parsed = parse_formula("SUM(1,A1,{2,3})+4", &options);
+    walk_mut(&mut parsed.node, &mut Double);
+    assert_eq!(
+        ast_to_formula(&parsed.node, &options.context()),
+        "SUM(2,A1,{4,6})+8"
+    );
+}
+
+#[test]
+fn test_implicit_intersections() {
+    let options = options();
+    let formulas = [
+        ("A1:A3", "SINGLE(A1:A3)"),
+        ("A1:A3*2+SUM(B1:B3)", "SINGLE(A1:A3)*2+SUM(B1:B3)"),
+        ("-OFFSET(A1,0,0,3,1)&B1", "-SINGLE(OFFSET(A1,0,0,3,1))&B1"),
+        ("SUM(A1:A3*B1)", "SUM(SINGLE(A1:A3)*B1)"),
+        ("A1+1", "A1+1"),
+        ("SINGLE(A1:A3)", "SINGLE(A1:A3)"),
+    ];
+    for (formula, expected) in formulas {
+        let mut parsed = parse_formula(formula, &options);
+        add_implicit_intersections(&mut parsed.node);
+        assert_eq!(ast_to_formula(&parsed.node, &options.context()), expected);
+
+        // Reading the formula as older versions of Excel write it gives the same formula
+        remove_implicit_intersections(&mut parsed.node);
+        let legacy = ast_to_formula(&parsed.node, &options.context());
+        if formula != "SINGLE(A1:A3)" {
+            assert_eq!(legacy, formula);
+        }
+        let mut parsed = parse_formula(&legacy, &options);
+        add_implicit_intersections(&mut parsed.node);
+        assert_eq!(ast_to_formula(&parsed.node, &options.context()), expected);
+    }
+}
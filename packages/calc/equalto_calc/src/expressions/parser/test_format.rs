@@ -0,0 +1,89 @@
+#![allow(clippy::unwrap_used)]
+
+use std::collections::HashMap;
+
+use crate::expressions::types::CellReferenceRC;
+use crate::types::ReferenceStyle;
+
+use super::format::{to_formatted_string, FormatOptions};
+use super::stringify::to_string;
+use super::Parser;
+
+fn format(formula: &str, indent: usize, width: usize) -> String {
+    let mut parser = Parser::new(vec!["Sheet1".to_string()], HashMap::new());
+    let context = CellReferenceRC {
+        sheet: "Sheet1".to_string(),
+        row: 1,
+        column: 1,
+    };
+    let node = parser.parse(formula, &Some(context.clone()));
+    let formatted = to_formatted_string(
+        &node,
+        &context,
+        &FormatOptions { indent, width },
+        ReferenceStyle::A1,
+    );
+    // The result parses to the same formula
+    let reparsed = parser.parse(&formatted, &Some(context.clone()));
+    assert_eq!(reparsed, node);
+    assert_eq!(to_string(&reparsed, &context), to_string(&node, &context));
+    formatted
+}
+
+#[test]
+fn test_short_formulas() {
+    assert_eq!(format("SUM(A1:A10)*2", 4, 80), "SUM(A1:A10)*2");
+    assert_eq!(format("IF( A1 > 2 , 1 , 0 )", 4, 80), "IF(A1>2,1,0)");
+}
+
+#[test]
+fn test_nested_functions() {
+    assert_eq!(
+        format("IF(A1>10,\"High\",IF(A1>5,\"Medium\",\"Low\"))", 4, 30),
+        "IF(\n    A1>10,\n    \"High\",\n    IF(A1>5,\"Medium\",\"Low\")\n)"
+    );
+    assert_eq!(
+        format("IF(A1>10,\"High\",IF(A1>5,\"Medium\",\"Low\"))", 2, 20),
+        "IF(\n  A1>10,\n  \"High\",\n  IF(\n    A1>5,\n    \"Medium\",\n    \"Low\"\n  )\n)"
+    );
+    assert_eq!(
+        format(
+            "LET(total,SUM(A1:A10),count,COUNT(A1:A10),total/count)",
+            4,
+            40
+        ),
+        "LET(\n    total,\n    SUM(A1:A10),\n    count,\n    COUNT(A1:A10),\n    total/count\n)"
+    );
+    assert_eq!(
+        format("XLOOKUP(A1,B1:B10,C1:C10,,0)", 2, 20),
+        "XLOOKUP(\n  A1,\n  B1:B10,\n  C1:C10,\n  ,\n  0\n)"
+    );
+}
+
+#[test]
+fn test_operators() {
+    assert_eq!(
+        format("SUM(A1:A10)+SUM(B1:B10)-SUM(C1:C10)", 4, 20),
+        "SUM(A1:A10)\n    +SUM(B1:B10)\n    -SUM(C1:C10)"
+    );
+    assert_eq!(
+        format("(SUM(A1:A10)+SUM(B1:B10))*2", 4, 20),
+        "(\n    SUM(A1:A10)\n        +SUM(B1:B10)\n)\n    *2"
+    );
+    assert_eq!(
+        format("-(AVERAGE(A1:A10)+1)", 4, 10),
+        "-(\n    AVERAGE(\n        A1:A10\n    )\n        +1\n)"
+    );
+}
+
+#[test]
+fn test_arrays_and_lambdas() {
+    assert_eq!(
+        format("SUM({1,2,3;4,5,6})", 2, 10),
+        "SUM(\n  {\n    1,2,3;\n    4,5,6\n  }\n)"
+    );
+    assert_eq!(
+        format("LAMBDA(x,y,x*y+1)(10,20)", 2, 15),
+        "LAMBDA(\n  x,\n  y,\n  x*y+1\n)(\n  10,\n  20\n)"
+    );
+}
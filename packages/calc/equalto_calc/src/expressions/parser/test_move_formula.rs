@@ -480,3 +480,45 @@ fn test_move_formula_another_sheet() {
         "Sheet1!AB31*SUM(Sheet1!JJ3:JJ4)+SUM(Sheet2!C2:F6)*SUM(M12:P16)"
     );
 }
+
+#[test]
+fn test_move_formula_let() {
+    // top left corner C2
+    let row = 2;
+    let column = 3;
+    let context = &CellReferenceRC {
+        sheet: "Sheet1".to_string(),
+        row,
+        column,
+    };
+    let worksheets = vec!["Sheet1".to_string()];
+    let mut parser = Parser::new(worksheets, HashMap::new());
+
+    // Area is C2:F6
+    let area = &Area {
+        sheet: 0,
+        row,
+        column,
+        width: 4,
+        height: 5,
+    };
+
+    // References in the area move, the LET names are unchanged
+    let node = parser.parse(
+        "LET(x,D3,total,SUM(x,AB31),total*x)",
+        &Some(context.clone()),
+    );
+    let t = move_formula(
+        &node,
+        &MoveContext {
+            source_sheet_name: "Sheet1",
+            row,
+            column,
+            area,
+            target_sheet_name: "Sheet1",
+            row_delta: 10,
+            column_delta: 10,
+        },
+    );
+    assert_eq!(t, "LET(x,N13,total,SUM(x,AB31),total*x)");
+}
@@ -7,7 +7,7 @@ use crate::expressions::utils::{number_to_column, parse_reference_a1};
 use crate::types::{Table, TableColumn, TableStyleInfo};
 
 use super::super::types::CellReferenceRC;
-use super::Parser;
+use super::{Node, Parser};
 
 fn create_test_table(
     table_name: &str,
@@ -73,7 +73,10 @@ fn simple_table() {
 
     let formula = "SUM(tblIncome[[#This Row],[Jan]:[Dec]])";
     let t = parser.parse(formula, &Some(cell_reference.clone()));
-    assert_eq!(to_string(&t, &cell_reference), "SUM($A$2:$E$2)");
+    assert_eq!(
+        to_string(&t, &cell_reference),
+        "SUM(tblIncome[@[Jan]:[Dec]])"
+    );
 
     // Cell A3
     let cell_reference = CellReferenceRC {
@@ -83,7 +86,10 @@ fn simple_table() {
     };
     let formula = "SUBTOTAL(109, tblIncome[Jan])";
     let t = parser.parse(formula, &Some(cell_reference.clone()));
-    assert_eq!(to_string(&t, &cell_reference), "SUBTOTAL(109,$A$2:$A$3)");
+    assert_eq!(
+        to_string(&t, &cell_reference),
+        "SUBTOTAL(109,tblIncome[Jan])"
+    );
 
     // Cell A3 in 'Second Sheet'
     let cell_reference = CellReferenceRC {
@@ -95,6 +101,54 @@ fn simple_table() {
     let t = parser.parse(formula, &Some(cell_reference.clone()));
     assert_eq!(
         to_string(&t, &cell_reference),
-        "SUBTOTAL(109,'Sheet One'!$A$2:$A$3)"
+        "SUBTOTAL(109,tblIncome[Jan])"
     );
 }
+
+#[test]
+fn structured_references() {
+    let worksheets = vec!["Sheet One".to_string(), "Second Sheet".to_string()];
+    let column_names = ["Item", "Unit Price", "Qty"];
+    let tables = create_test_table("Sales", &column_names, "B2", 4);
+    let mut parser = Parser::new(worksheets, tables);
+    // 'Sheet One'!E3, in the table
+    let cell_reference = CellReferenceRC {
+        sheet: "Sheet One".to_string(),
+        row: 3,
+        column: 5,
+    };
+
+    let cases = [
+        ("Sales[@Qty]*2", "Sales[@Qty]*2"),
+        ("[@Qty]*[@[Unit Price]]", "[@Qty]*[@[Unit Price]]"),
+        ("[@qty]", "[@Qty]"),
+        ("sales[[#This Row],[qty]]", "Sales[@Qty]"),
+        ("Sales[[#This Row], [Item]:[Qty]]", "Sales[@[Item]:[Qty]]"),
+        ("SUM(Sales[[Unit Price]])", "SUM(Sales[Unit Price])"),
+        ("Sales[#Totals]", "Sales[#Totals]"),
+        ("Sales[[#Totals],[Qty]]", "Sales[[#Totals],[Qty]]"),
+        (
+            "COUNTA(Sales[[#Headers],[#Data],[Qty]])",
+            "COUNTA(Sales[[#Headers],[#Data],[Qty]])",
+        ),
+        ("ROWS(Sales[])", "ROWS(Sales[])"),
+        ("Sales[@]", "Sales[@]"),
+    ];
+    for (formula, expected) in cases {
+        let t = parser.parse(formula, &Some(cell_reference.clone()));
+        assert_eq!(to_string(&t, &cell_reference), expected, "{}", formula);
+    }
+
+    // 'Second Sheet'!A1, outside of the table
+    let cell_reference = CellReferenceRC {
+        sheet: "Second Sheet".to_string(),
+        row: 1,
+        column: 1,
+    };
+    let t = parser.parse("[@Qty]", &Some(cell_reference.clone()));
+    assert!(matches!(t, Node::ParseErrorKind { .. }));
+    let t = parser.parse("Costs[Qty]", &Some(cell_reference.clone()));
+    assert!(matches!(t, Node::ParseErrorKind { .. }));
+    let t = parser.parse("Sales[[Qty],[Item]]", &Some(cell_reference));
+    assert!(matches!(t, Node::ParseErrorKind { .. }));
+}
@@ -91,7 +91,7 @@ pub(crate) fn forward_references(
             }
         }
         // Recurse
-        Node::OpRangeKind { left, right } => {
+        Node::OpRangeKind { left, right } | Node::OpIntersectKind { left, right } => {
             forward_references(
                 left,
                 context,
@@ -260,8 +260,65 @@ pub(crate) fn forward_references(
                 target_column,
             );
         }
-        // TODO: Not implemented
-        Node::ArrayKind(_) => {}
+        Node::OpUnionKind(items) => {
+            for item in items {
+                forward_references(
+                    item,
+                    context,
+                    source_area,
+                    target_sheet,
+                    target_sheet_name,
+                    target_row,
+                    target_column,
+                );
+            }
+        }
+        Node::ArrayKind(rows) => {
+            for el in rows.iter_mut().flatten() {
+                forward_references(
+                    el,
+                    context,
+                    source_area,
+                    target_sheet,
+                    target_sheet_name,
+                    target_row,
+                    target_column,
+                );
+            }
+        }
+        Node::SpillRangeKind(reference) => {
+            forward_references(
+                reference,
+                context,
+                source_area,
+                target_sheet,
+                target_sheet_name,
+                target_row,
+                target_column,
+            );
+        }
+        Node::CallKind { function, args } => {
+            forward_references(
+                function,
+                context,
+                source_area,
+                target_sheet,
+                target_sheet_name,
+                target_row,
+                target_column,
+            );
+            for arg in args {
+                forward_references(
+                    arg,
+                    context,
+                    source_area,
+                    target_sheet,
+                    target_sheet_name,
+                    target_row,
+                    target_column,
+                );
+            }
+        }
         // Do nothing. Note: we could do a blanket _ => {}
         Node::VariableKind(_) => {}
         Node::ErrorKind(_) => {}
@@ -272,5 +329,10 @@ pub(crate) fn forward_references(
         Node::StringKind(_) => {}
         Node::WrongReferenceKind { .. } => {}
         Node::WrongRangeKind { .. } => {}
+        Node::ExternalReferenceKind { .. } => {}
+        Node::ExternalRangeKind { .. } => {}
+        Node::SheetSpanReferenceKind { .. } => {}
+        Node::SheetSpanRangeKind { .. } => {}
+        Node::TableReferenceKind { .. } => {}
     }
 }
@@ -207,6 +207,10 @@ pub enum TableSpecifier {
     Headers,
     ThisRow,
     Totals,
+    /// [#Headers],[#Data]
+    HeadersAndData,
+    /// [#Data],[#Totals]
+    DataAndTotals,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -240,6 +244,7 @@ pub enum TokenType {
     Bang,               // !
     Percent,            // %
     And,                // &
+    Hash,               // #
     Reference {
         sheet: Option<String>,
         row: i32,
@@ -286,6 +291,7 @@ impl fmt::Display for TokenType {
             Bang => write!(fmt, "!"),
             Percent => write!(fmt, "%"),
             And => write!(fmt, "&"),
+            Hash => write!(fmt, "#"),
             Reference {
                 sheet,
                 row,
@@ -380,6 +386,7 @@ pub fn index(token: &TokenType) -> u32 {
         Bang => 24,
         Percent => 30,
         And => 31,
+        Hash => 32,
         Reference { .. } => 34,
         Range { .. } => 35,
         Compare(..) => 37,
@@ -279,3 +279,55 @@ pub fn quote_name(name: &str) -> String {
     };
     name.to_string()
 }
+
+/// Splits the sheets of a reference to a span of sheets into the first and the last one:
+/// `Jan:Dec` is (`Jan`, `Dec`). Returns None for single sheets, their names can't have colons.
+pub fn split_sheet_span(name: &str) -> Option<(String, String)> {
+    let (first, last) = name.split_once(':')?;
+    if first.is_empty() || last.is_empty() || last.contains(':') {
+        return None;
+    }
+    Some((first.to_string(), last.to_string()))
+}
+
+/// Writes a span of sheets the way it goes before the `!` of a reference:
+/// `Jan:Dec` or `'Jan 2023:Dec 2023'`
+pub fn quote_sheet_span(first: &str, last: &str) -> String {
+    let name = format!("{}:{}", first, last);
+    if !name_needs_quoting(first) && !name_needs_quoting(last) {
+        name
+    } else {
+        format!("'{}'", name.replace('\'', "''"))
+    }
+}
+
+/// Splits the sheet name of a reference to another workbook into the workbook, with its path,
+/// and the sheet: `C:\models\[fx.xlsx]USD` is (`C:\models\fx.xlsx`, `USD`).
+/// Returns None for sheets of this workbook, their names can't have brackets.
+pub fn split_external_name(name: &str) -> Option<(String, String)> {
+    let start = name.find('[')?;
+    let end = start + name[start..].find(']')?;
+    let book = &name[start + 1..end];
+    if book.is_empty() || end + 1 == name.len() {
+        return None;
+    }
+    Some((
+        format!("{}{}", &name[..start], book),
+        name[end + 1..].to_string(),
+    ))
+}
+
+/// Writes the sheet of another workbook the way it goes before the `!` of a reference:
+/// `[Rates.xlsx]Sheet1` or `'C:\models\[fx.xlsx]USD'`
+pub fn quote_external_name(workbook: &str, sheet_name: &str) -> String {
+    let (path, book) = match workbook.rfind(['\\', '/']) {
+        Some(index) => workbook.split_at(index + 1),
+        None => ("", workbook),
+    };
+    let name = format!("{}[{}]{}", path, book, sheet_name);
+    if path.is_empty() && !name_needs_quoting(book) && !name_needs_quoting(sheet_name) {
+        name
+    } else {
+        format!("'{}'", name.replace('\'', "''"))
+    }
+}
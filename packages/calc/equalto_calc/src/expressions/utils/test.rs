@@ -212,3 +212,48 @@ fn test_names() {
     assert!(!is_valid_identifier("truñe"));
     assert!(!is_valid_identifier("tr&ue"));
 }
+
+#[test]
+fn test_external_names() {
+    assert_eq!(
+        split_external_name("[Rates.xlsx]Sheet1"),
+        Some(("Rates.xlsx".to_string(), "Sheet1".to_string()))
+    );
+    assert_eq!(
+        split_external_name("C:\\models\\[fx.xlsx]USD"),
+        Some(("C:\\models\\fx.xlsx".to_string(), "USD".to_string()))
+    );
+    assert_eq!(split_external_name("Sheet1"), None);
+    assert_eq!(split_external_name("[]Sheet1"), None);
+    assert_eq!(split_external_name("[Rates.xlsx]"), None);
+
+    assert_eq!(
+        quote_external_name("Rates.xlsx", "Sheet1"),
+        "[Rates.xlsx]Sheet1"
+    );
+    assert_eq!(
+        quote_external_name("Rates.xlsx", "Second Sheet"),
+        "'[Rates.xlsx]Second Sheet'"
+    );
+    assert_eq!(
+        quote_external_name("C:\\models\\fx.xlsx", "USD"),
+        "'C:\\models\\[fx.xlsx]USD'"
+    );
+}
+
+#[test]
+fn test_sheet_spans() {
+    assert_eq!(
+        split_sheet_span("Jan:Dec"),
+        Some(("Jan".to_string(), "Dec".to_string()))
+    );
+    assert_eq!(split_sheet_span("Jan"), None);
+    assert_eq!(split_sheet_span(":Dec"), None);
+    assert_eq!(split_sheet_span("Jan:"), None);
+
+    assert_eq!(quote_sheet_span("Jan", "Dec"), "Jan:Dec");
+    assert_eq!(
+        quote_sheet_span("Jan 2023", "Dec 2023"),
+        "'Jan 2023:Dec 2023'"
+    );
+}
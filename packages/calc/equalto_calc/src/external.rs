@@ -0,0 +1,212 @@
+//! Values of references to other workbooks: `=[Rates.xlsx]Sheet1!$B$2`.
+//!
+//! The host tells the model where the values come from with
+//! [`Model::set_external_resolver`](crate::model::Model::set_external_resolver): other loaded
+//! models, a snapshot of values ([`ExternalSnapshot`]) or anything implementing
+//! [`ExternalResolver`]. Cells the resolver doesn't know are read from the values cached in the
+//! workbook, if any.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{
+    calc_result::{CalcResult, CellReference},
+    expressions::token::Error,
+    model::Model,
+    types::{Cell, ExternalLink, ExternalValue},
+};
+
+/// Gives the values of the cells of other workbooks
+pub trait ExternalResolver: Send + Sync {
+    /// Returns the value of a cell of `sheet` in `workbook`, written as in the formula
+    /// (`C:\models\fx.xlsx` or `Rates.xlsx`), or None if it is not known
+    fn cell_value(
+        &self,
+        workbook: &str,
+        sheet: &str,
+        row: i32,
+        column: i32,
+    ) -> Option<ExternalValue>;
+}
+
+/// Returns the workbook without its path
+pub fn file_name(workbook: &str) -> &str {
+    match workbook.rfind(['\\', '/']) {
+        Some(index) => &workbook[index + 1..],
+        None => workbook,
+    }
+}
+
+/// Returns true if `workbook` names `other`, maybe with a different path or none at all
+pub(crate) fn is_same_workbook(workbook: &str, other: &str) -> bool {
+    file_name(workbook).to_lowercase() == file_name(other).to_lowercase()
+}
+
+/// Returns the value of a cell the way other workbooks see it
+fn cell_external_value(cell: &Cell, shared_strings: &[String]) -> ExternalValue {
+    match cell {
+        Cell::EmptyCell { .. } => ExternalValue::Empty,
+        Cell::BooleanCell { v, .. }
+        | Cell::CellFormulaBoolean { v, .. }
+        | Cell::SpillBoolean { v, .. } => ExternalValue::Boolean(*v),
+        Cell::NumberCell { v, .. }
+        | Cell::CellFormulaNumber { v, .. }
+        | Cell::SpillNumber { v, .. } => ExternalValue::Number(*v),
+        Cell::CellFormulaString { v, .. } | Cell::SpillString { v, .. } => {
+            ExternalValue::String(v.clone())
+        }
+        Cell::SharedString { si, .. } => match shared_strings.get(*si as usize) {
+            Some(value) => ExternalValue::String(value.clone()),
+            None => ExternalValue::Empty,
+        },
+        Cell::ErrorCell { ei, .. }
+        | Cell::CellFormulaError { ei, .. }
+        | Cell::SpillError { ei, .. } => ExternalValue::Error(ei.clone()),
+        // Not evaluated yet
+        Cell::CellFormula { .. } => ExternalValue::Error(Error::ERROR),
+    }
+}
+
+/// Other loaded models by workbook name: `Rates.xlsx`
+impl ExternalResolver for HashMap<String, Model> {
+    fn cell_value(
+        &self,
+        workbook: &str,
+        sheet: &str,
+        row: i32,
+        column: i32,
+    ) -> Option<ExternalValue> {
+        let model = match self.get(workbook) {
+            Some(model) => model,
+            None => self
+                .iter()
+                .find(|(name, _)| is_same_workbook(name, workbook))
+                .map(|(_, model)| model)?,
+        };
+        model.get_external_value(sheet, row, column)
+    }
+}
+
+/// The values of the cells of the sheets of a workbook by the name of the sheet in lower case
+type SheetValues = HashMap<String, HashMap<(i32, i32), ExternalValue>>;
+
+/// A snapshot of values, like the ones cached in xlsx files
+#[derive(Clone, Default)]
+pub struct ExternalSnapshot {
+    /// The workbooks as written in formulas with the values of their sheets
+    workbooks: Vec<(String, SheetValues)>,
+}
+
+impl ExternalSnapshot {
+    /// Indexes the cached values of `links` by sheet, row and column
+    pub fn new(links: &[ExternalLink]) -> ExternalSnapshot {
+        let workbooks = links
+            .iter()
+            .map(|link| {
+                let sheets = link
+                    .sheets
+                    .iter()
+                    .map(|sheet| {
+                        let cells = sheet
+                            .cells
+                            .iter()
+                            .map(|cell| ((cell.row, cell.column), cell.value.clone()))
+                            .collect();
+                        (sheet.name.to_lowercase(), cells)
+                    })
+                    .collect();
+                (link.workbook.clone(), sheets)
+            })
+            .collect();
+        ExternalSnapshot { workbooks }
+    }
+}
+
+impl ExternalResolver for ExternalSnapshot {
+    fn cell_value(
+        &self,
+        workbook: &str,
+        sheet: &str,
+        row: i32,
+        column: i32,
+    ) -> Option<ExternalValue> {
+        let (_, sheets) = self
+            .workbooks
+            .iter()
+            .find(|(name, _)| name == workbook)
+            .or_else(|| {
+                self.workbooks
+                    .iter()
+                    .find(|(name, _)| is_same_workbook(name, workbook))
+            })?;
+        let cells = sheets.get(&sheet.to_lowercase())?;
+        Some(
+            cells
+                .get(&(row, column))
+                .cloned()
+                .unwrap_or(ExternalValue::Empty),
+        )
+    }
+}
+
+impl Model {
+    /// Returns the value of a cell the way other workbooks see it,
+    /// or None if there is no sheet with that name
+    pub fn get_external_value(&self, sheet: &str, row: i32, column: i32) -> Option<ExternalValue> {
+        let worksheet = self
+            .workbook
+            .worksheets
+            .iter()
+            .find(|worksheet| worksheet.name.to_lowercase() == sheet.to_lowercase())?;
+        Some(match worksheet.cell(row, column) {
+            Some(cell) => cell_external_value(cell, &self.workbook.shared_strings),
+            None => ExternalValue::Empty,
+        })
+    }
+
+    /// Sets where the values of references to other workbooks come from.
+    /// Cells the resolver doesn't know take the values cached in the workbook.
+    pub fn set_external_resolver(&mut self, resolver: Arc<dyn ExternalResolver>) {
+        self.external_resolver = Some(resolver);
+    }
+
+    /// Sets the values of other workbooks cached in the workbook
+    pub fn set_external_links(&mut self, external_links: Vec<ExternalLink>) {
+        self.cached_external_values = ExternalSnapshot::new(&external_links);
+        self.workbook.external_links = external_links;
+    }
+
+    /// Returns the value of a cell of another workbook for a formula in `cell`
+    pub(crate) fn evaluate_external_cell(
+        &self,
+        workbook: &str,
+        sheet: &str,
+        row: i32,
+        column: i32,
+        cell: CellReference,
+    ) -> CalcResult {
+        let value = self
+            .external_resolver
+            .as_ref()
+            .and_then(|resolver| resolver.cell_value(workbook, sheet, row, column))
+            .or_else(|| {
+                self.shared_model()
+                    .cached_external_values
+                    .cell_value(workbook, sheet, row, column)
+            });
+        match value {
+            Some(ExternalValue::Empty) => CalcResult::EmptyCell,
+            Some(ExternalValue::Number(value)) => CalcResult::Number(value),
+            Some(ExternalValue::String(value)) => CalcResult::String(value),
+            Some(ExternalValue::Boolean(value)) => CalcResult::Boolean(value),
+            Some(ExternalValue::Error(error)) => {
+                CalcResult::new_error(error, cell, format!("Error in [{}]{}", workbook, sheet))
+            }
+            None => CalcResult::new_error(
+                Error::REF,
+                cell,
+                format!("Unknown external workbook: [{}]{}", workbook, sheet),
+            ),
+        }
+    }
+}
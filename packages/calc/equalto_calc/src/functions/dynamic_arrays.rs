@@ -0,0 +1,360 @@
+use std::cmp::Ordering;
+
+use crate::{
+    calc_result::{CalcResult, CellReference, Range},
+    expressions::{parser::Node, token::Error},
+    implicit_intersection::implicit_intersection,
+    model::Model,
+};
+
+use super::util::values_are_equal;
+
+type Array = Vec<Vec<CalcResult>>;
+
+fn transpose(array: Array) -> Array {
+    let columns = array.first().map_or(0, |row| row.len());
+    let mut result: Array = (0..columns)
+        .map(|_| Vec::with_capacity(array.len()))
+        .collect();
+    for row in array {
+        for (column, value) in row.into_iter().enumerate() {
+            result[column].push(value);
+        }
+    }
+    result
+}
+
+fn empty_array_error(cell: CellReference) -> CalcResult {
+    CalcResult::new_error(Error::CALC, cell, "Empty array".to_string())
+}
+
+/// Order used by SORT and SORTBY: numbers, text, logical values, errors and empty values
+fn compare_for_sort(left: &CalcResult, right: &CalcResult) -> Ordering {
+    fn rank(value: &CalcResult) -> u8 {
+        match value {
+            CalcResult::Number(_) => 0,
+            CalcResult::String(_) => 1,
+            CalcResult::Boolean(_) => 2,
+            CalcResult::Error { .. } => 3,
+            _ => 4,
+        }
+    }
+    match (left, right) {
+        (CalcResult::Number(value1), CalcResult::Number(value2)) => value1.total_cmp(value2),
+        (CalcResult::String(value1), CalcResult::String(value2)) => {
+            value1.to_uppercase().cmp(&value2.to_uppercase())
+        }
+        (CalcResult::Boolean(value1), CalcResult::Boolean(value2)) => value1.cmp(value2),
+        _ => rank(left).cmp(&rank(right)),
+    }
+}
+
+/// The sort order of SORT and SORTBY: 1 for ascending and -1 for descending
+fn is_descending(order: f64) -> Option<bool> {
+    if order == 1.0 {
+        Some(false)
+    } else if order == -1.0 {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+fn rows_are_equal(row1: &[CalcResult], row2: &[CalcResult]) -> bool {
+    row1.iter()
+        .zip(row2.iter())
+        .all(|(value1, value2)| values_are_equal(value1, value2))
+}
+
+impl Model {
+    /// Returns the value of an optional numeric argument or `default` if it is missing
+    pub(crate) fn get_optional_number(
+        &mut self,
+        args: &[Node],
+        index: usize,
+        default: f64,
+        cell: CellReference,
+    ) -> Result<f64, CalcResult> {
+        match args.get(index) {
+            None | Some(Node::EmptyArgKind) => Ok(default),
+            Some(arg) => self.get_number(arg, cell),
+        }
+    }
+
+    /// Returns the value of an optional boolean argument, FALSE if it is missing
+    fn get_optional_boolean(
+        &mut self,
+        args: &[Node],
+        index: usize,
+        cell: CellReference,
+    ) -> Result<bool, CalcResult> {
+        match args.get(index) {
+            None | Some(Node::EmptyArgKind) => Ok(false),
+            Some(arg) => self.get_boolean(arg, cell),
+        }
+    }
+
+    /// Returns the values of `array` as a list of booleans (used in FILTER)
+    fn get_include_list(
+        &mut self,
+        array: Array,
+        cell: CellReference,
+    ) -> Result<Vec<bool>, CalcResult> {
+        let mut result = Vec::new();
+        for value in array.into_iter().flatten() {
+            let include = match value {
+                CalcResult::String(_) => {
+                    return Err(CalcResult::new_error(
+                        Error::VALUE,
+                        cell,
+                        "Expecting a boolean".to_string(),
+                    ))
+                }
+                value => self.cast_to_bool(value, cell)?,
+            };
+            result.push(include);
+        }
+        Ok(result)
+    }
+
+    /// SORT(array, [sort_index], [sort_order], [by_col])
+    pub(crate) fn fn_sort(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        if args.is_empty() || args.len() > 4 {
+            return CalcResult::new_args_number_error(cell);
+        }
+        let array = match self.get_array(&args[0], cell) {
+            Ok(array) => array,
+            Err(error) => return error,
+        };
+        let sort_index = match self.get_optional_number(args, 1, 1.0, cell) {
+            Ok(f) => f.trunc() as i64,
+            Err(error) => return error,
+        };
+        let descending = match self
+            .get_optional_number(args, 2, 1.0, cell)
+            .map(is_descending)
+        {
+            Ok(Some(descending)) => descending,
+            Ok(None) => {
+                return CalcResult::new_error(
+                    Error::VALUE,
+                    cell,
+                    "Sort order must be 1 or -1".to_string(),
+                )
+            }
+            Err(error) => return error,
+        };
+        let by_column = match self.get_optional_boolean(args, 3, cell) {
+            Ok(b) => b,
+            Err(error) => return error,
+        };
+        let mut array = if by_column { transpose(array) } else { array };
+        let width = array.first().map_or(0, |row| row.len()) as i64;
+        if sort_index < 1 || sort_index > width {
+            return CalcResult::new_error(Error::VALUE, cell, "Invalid sort index".to_string());
+        }
+        let index = (sort_index - 1) as usize;
+        array.sort_by(|row1, row2| {
+            let ordering = compare_for_sort(&row1[index], &row2[index]);
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        let array = if by_column { transpose(array) } else { array };
+        CalcResult::Array(array)
+    }
+
+    /// SORTBY(array, by_array1, [sort_order1], [by_array2, sort_order2],...)
+    pub(crate) fn fn_sortby(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        if args.len() < 2 {
+            return CalcResult::new_args_number_error(cell);
+        }
+        let array = match self.get_array(&args[0], cell) {
+            Ok(array) => array,
+            Err(error) => return error,
+        };
+        let rows = array.len();
+        let columns = array.first().map_or(0, |row| row.len());
+        // Every key is a list of values and a sort order
+        let mut keys: Vec<(Vec<CalcResult>, bool)> = Vec::new();
+        let mut by_column = None;
+        for pair in args[1..].chunks(2) {
+            let by_array = match self.get_array(&pair[0], cell) {
+                Ok(array) => array,
+                Err(error) => return error,
+            };
+            let descending = match self
+                .get_optional_number(pair, 1, 1.0, cell)
+                .map(is_descending)
+            {
+                Ok(Some(descending)) => descending,
+                Ok(None) => {
+                    return CalcResult::new_error(
+                        Error::VALUE,
+                        cell,
+                        "Sort order must be 1 or -1".to_string(),
+                    )
+                }
+                Err(error) => return error,
+            };
+            let by_rows = by_array.len();
+            let by_columns = by_array.first().map_or(0, |row| row.len());
+            let is_column = if by_columns == 1 && by_rows == rows {
+                false
+            } else if by_rows == 1 && by_columns == columns {
+                true
+            } else {
+                return CalcResult::new_error(
+                    Error::VALUE,
+                    cell,
+                    "Arrays must be of the same size".to_string(),
+                );
+            };
+            if *by_column.get_or_insert(is_column) != is_column {
+                return CalcResult::new_error(
+                    Error::VALUE,
+                    cell,
+                    "Arrays must all sort in the same direction".to_string(),
+                );
+            }
+            keys.push((by_array.into_iter().flatten().collect(), descending));
+        }
+        let by_column = by_column.unwrap_or(false);
+        let array = if by_column { transpose(array) } else { array };
+        let mut indices: Vec<usize> = (0..array.len()).collect();
+        indices.sort_by(|&i, &j| {
+            for (values, descending) in &keys {
+                let ordering = compare_for_sort(&values[i], &values[j]);
+                if ordering != Ordering::Equal {
+                    return if *descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    };
+                }
+            }
+            Ordering::Equal
+        });
+        let sorted: Array = indices.iter().map(|&i| array[i].clone()).collect();
+        let array = if by_column { transpose(sorted) } else { sorted };
+        CalcResult::Array(array)
+    }
+
+    /// FILTER(array, include, [if_empty])
+    pub(crate) fn fn_filter(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        if args.len() < 2 || args.len() > 3 {
+            return CalcResult::new_args_number_error(cell);
+        }
+        let array = match self.get_array(&args[0], cell) {
+            Ok(array) => array,
+            Err(error) => return error,
+        };
+        let include = match self.get_array(&args[1], cell) {
+            Ok(array) => array,
+            Err(error) => return error,
+        };
+        let rows = array.len();
+        let columns = array.first().map_or(0, |row| row.len());
+        let include_rows = include.len();
+        let include_columns = include.first().map_or(0, |row| row.len());
+        let by_column = if include_columns == 1 && include_rows == rows {
+            false
+        } else if include_rows == 1 && include_columns == columns {
+            true
+        } else {
+            return CalcResult::new_error(
+                Error::VALUE,
+                cell,
+                "Arrays must be of the same size".to_string(),
+            );
+        };
+        let include = match self.get_include_list(include, cell) {
+            Ok(include) => include,
+            Err(error) => return error,
+        };
+        let array = if by_column { transpose(array) } else { array };
+        let filtered: Array = array
+            .into_iter()
+            .zip(include)
+            .filter_map(|(row, include)| if include { Some(row) } else { None })
+            .collect();
+        if filtered.is_empty() {
+            return match args.get(2) {
+                Some(if_empty) => self.evaluate_node_as_array(if_empty, cell),
+                None => empty_array_error(cell),
+            };
+        }
+        let array = if by_column {
+            transpose(filtered)
+        } else {
+            filtered
+        };
+        CalcResult::Array(array)
+    }
+
+    /// UNIQUE(array, [by_col], [exactly_once])
+    pub(crate) fn fn_unique(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        if args.is_empty() || args.len() > 3 {
+            return CalcResult::new_args_number_error(cell);
+        }
+        let array = match self.get_array(&args[0], cell) {
+            Ok(array) => array,
+            Err(error) => return error,
+        };
+        let by_column = match self.get_optional_boolean(args, 1, cell) {
+            Ok(b) => b,
+            Err(error) => return error,
+        };
+        let exactly_once = match self.get_optional_boolean(args, 2, cell) {
+            Ok(b) => b,
+            Err(error) => return error,
+        };
+        let array = if by_column { transpose(array) } else { array };
+        // distinct rows in order of appearance and the number of times they appear
+        let mut distinct: Vec<(Vec<CalcResult>, usize)> = Vec::new();
+        for row in array {
+            match distinct
+                .iter_mut()
+                .find(|(other, _)| rows_are_equal(other, &row))
+            {
+                Some((_, count)) => *count += 1,
+                None => distinct.push((row, 1)),
+            }
+        }
+        let unique: Array = distinct
+            .into_iter()
+            .filter(|(_, count)| !exactly_once || *count == 1)
+            .map(|(row, _)| row)
+            .collect();
+        if unique.is_empty() {
+            return empty_array_error(cell);
+        }
+        let array = if by_column { transpose(unique) } else { unique };
+        CalcResult::Array(array)
+    }
+
+    /// SINGLE(value), the implicit intersection operator `@`: a range is reduced to the cell in
+    /// the row or column of the formula and an array to its first element
+    pub(crate) fn fn_single(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        if args.len() != 1 {
+            return CalcResult::new_args_number_error(cell);
+        }
+        match self.evaluate_node_in_context(&args[0], cell) {
+            CalcResult::Range { left, right } => {
+                match implicit_intersection(&cell, &Range { left, right }) {
+                    Some(reference) => self.evaluate_cell(reference),
+                    None => {
+                        CalcResult::new_error(Error::VALUE, cell, "Invalid reference".to_string())
+                    }
+                }
+            }
+            CalcResult::Array(array) => match array.first().and_then(|row| row.first()) {
+                Some(value) => value.clone(),
+                None => empty_array_error(cell),
+            },
+            value => value,
+        }
+    }
+}
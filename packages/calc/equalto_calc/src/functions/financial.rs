@@ -218,18 +218,14 @@ impl Model {
                 let mut column2 = right.column;
                 if row1 == 1 && row2 == LAST_ROW {
                     row2 = self
-                        .workbook
-                        .worksheet(left.sheet)
+                        .get_sheet_dimension(left.sheet)
                         .expect("Sheet expected during evaluation.")
-                        .dimension()
                         .max_row;
                 }
                 if column1 == 1 && column2 == LAST_COLUMN {
                     column2 = self
-                        .workbook
-                        .worksheet(left.sheet)
+                        .get_sheet_dimension(left.sheet)
                         .expect("Sheet expected during evaluation.")
-                        .dimension()
                         .max_column;
                 }
                 for row in row1..row2 + 1 {
@@ -281,18 +277,14 @@ impl Model {
                 let mut column2 = right.column;
                 if row1 == 1 && row2 == LAST_ROW {
                     row2 = self
-                        .workbook
-                        .worksheet(left.sheet)
+                        .get_sheet_dimension(left.sheet)
                         .expect("Sheet expected during evaluation.")
-                        .dimension()
                         .max_row;
                 }
                 if column1 == 1 && column2 == LAST_COLUMN {
                     column2 = self
-                        .workbook
-                        .worksheet(left.sheet)
+                        .get_sheet_dimension(left.sheet)
                         .expect("Sheet expected during evaluation.")
-                        .dimension()
                         .max_column;
                 }
                 for row in row1..row2 + 1 {
@@ -357,18 +349,14 @@ impl Model {
                 let mut column2 = right.column;
                 if row1 == 1 && row2 == LAST_ROW {
                     row2 = self
-                        .workbook
-                        .worksheet(left.sheet)
+                        .get_sheet_dimension(left.sheet)
                         .expect("Sheet expected during evaluation.")
-                        .dimension()
                         .max_row;
                 }
                 if column1 == 1 && column2 == LAST_COLUMN {
                     column2 = self
-                        .workbook
-                        .worksheet(left.sheet)
+                        .get_sheet_dimension(left.sheet)
                         .expect("Sheet expected during evaluation.")
-                        .dimension()
                         .max_column;
                 }
                 for row in row1..row2 + 1 {
@@ -863,18 +851,14 @@ impl Model {
                     let mut column2 = right.column;
                     if row1 == 1 && row2 == LAST_ROW {
                         row2 = self
-                            .workbook
-                            .worksheet(left.sheet)
+                            .get_sheet_dimension(left.sheet)
                             .expect("Sheet expected during evaluation.")
-                            .dimension()
                             .max_row;
                     }
                     if column1 == 1 && column2 == LAST_COLUMN {
                         column2 = self
-                            .workbook
-                            .worksheet(left.sheet)
+                            .get_sheet_dimension(left.sheet)
                             .expect("Sheet expected during evaluation.")
-                            .dimension()
                             .max_column;
                     }
                     for row in row1..row2 + 1 {
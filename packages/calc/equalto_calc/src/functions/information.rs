@@ -114,9 +114,11 @@ impl Model {
             return CalcResult::new_args_number_error(cell);
         }
         match &args[0] {
-            Node::ReferenceKind { .. } | Node::RangeKind { .. } | Node::OpRangeKind { .. } => {
-                CalcResult::Boolean(true)
-            }
+            Node::ReferenceKind { .. }
+            | Node::RangeKind { .. }
+            | Node::OpRangeKind { .. }
+            | Node::OpUnionKind(_)
+            | Node::OpIntersectKind { .. } => CalcResult::Boolean(true),
             Node::FunctionKind { kind, args: _ } => CalcResult::Boolean(kind.returns_reference()),
             _ => CalcResult::Boolean(false),
         }
@@ -228,8 +230,9 @@ impl Model {
             CalcResult::String(_) => CalcResult::Number(2.0),
             CalcResult::Number(_) => CalcResult::Number(1.0),
             CalcResult::Boolean(_) => CalcResult::Number(4.0),
-            CalcResult::Error { .. } => CalcResult::Number(16.0),
-            CalcResult::Range { .. } => CalcResult::Number(64.0),
+            CalcResult::Error { .. } | CalcResult::Areas(_) => CalcResult::Number(16.0),
+            CalcResult::Range { .. } | CalcResult::Array(_) => CalcResult::Number(64.0),
+            CalcResult::Lambda(_) => CalcResult::Number(128.0),
             CalcResult::EmptyCell => CalcResult::Number(1.0),
             CalcResult::EmptyArg => {
                 // This cannot happen
@@ -250,8 +253,7 @@ impl Model {
         let arg = &args[0];
         if let Node::VariableKind(name) = arg {
             // Let's see if it is a defined name
-            if let Some(defined_name) = self.parsed_defined_names.get(&(None, name.to_lowercase()))
-            {
+            if let Some(defined_name) = self.get_parsed_defined_name(name, cell.sheet) {
                 match defined_name {
                     ParsedDefinedName::CellReference(reference) => {
                         return CalcResult::Number(reference.sheet as f64 + 1.0)
@@ -259,7 +261,12 @@ impl Model {
                     ParsedDefinedName::RangeReference(range) => {
                         return CalcResult::Number(range.left.sheet as f64 + 1.0)
                     }
-                    ParsedDefinedName::InvalidDefinedNameFormula => {
+                    ParsedDefinedName::Formula(
+                        Node::ReferenceKind { sheet_index, .. }
+                        | Node::RangeKind { sheet_index, .. },
+                    ) => return CalcResult::Number(*sheet_index as f64 + 1.0),
+                    ParsedDefinedName::Formula(_)
+                    | ParsedDefinedName::InvalidDefinedNameFormula => {
                         return CalcResult::Error {
                             error: Error::NA,
                             origin: cell,
@@ -279,6 +286,14 @@ impl Model {
                 }
             }
         }
+        if let Node::TableReferenceKind { table_name, .. } = arg {
+            if let Some(sheet_index) = self
+                .get_table(table_name, cell)
+                .and_then(|table| self.get_sheet_index_by_name(&table.sheet_name))
+            {
+                return CalcResult::Number(sheet_index as f64 + 1.0);
+            }
+        }
         // Now it should be the name of a sheet
         let sheet_name = match self.get_string(arg, cell) {
             Ok(s) => s,
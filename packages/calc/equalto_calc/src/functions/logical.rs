@@ -1,5 +1,5 @@
 use crate::{
-    calc_result::{CalcResult, CellReference},
+    calc_result::{CalcResult, CellReference, Lambda},
     expressions::parser::Node,
     expressions::token::Error,
     model::Model,
@@ -7,10 +7,23 @@ use crate::{
 
 use super::util::compare_values;
 
+/// Maximum number of nested LAMBDA calls. Deeper recursion evaluates to #NUM!
+const MAX_LAMBDA_DEPTH: usize = 200;
+
 impl Model {
     pub(crate) fn fn_if(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
         if args.len() == 2 || args.len() == 3 {
-            let cond_result = self.get_boolean(&args[0], cell);
+            let cond_result = match self.evaluate_operand(&args[0], cell) {
+                CalcResult::Array(condition) => {
+                    let value_if_true = self.evaluate_operand(&args[1], cell);
+                    let value_if_false = match args.get(2) {
+                        Some(arg) => self.evaluate_operand(arg, cell),
+                        None => CalcResult::Boolean(false),
+                    };
+                    return self.choose_elements(condition, value_if_true, value_if_false, cell);
+                }
+                value => self.cast_to_bool(value, cell),
+            };
             let cond = match cond_result {
                 Ok(f) => f,
                 Err(s) => {
@@ -116,14 +129,32 @@ impl Model {
                                     true_count += 1;
                                 }
                                 error @ CalcResult::Error { .. } => return error,
-                                CalcResult::Range { .. } => {}
+                                CalcResult::Range { .. }
+                                | CalcResult::Array(_)
+                                | CalcResult::Lambda(_)
+                                | CalcResult::Areas(_) => {}
                                 CalcResult::EmptyCell | CalcResult::EmptyArg => {}
                             }
                         }
                     }
                 }
+                CalcResult::Array(array) => {
+                    for value in array.iter().flatten() {
+                        match value {
+                            CalcResult::Boolean(false) => return CalcResult::Boolean(false),
+                            CalcResult::Number(value) if *value == 0.0 => {
+                                return CalcResult::Boolean(false)
+                            }
+                            CalcResult::Boolean(_) | CalcResult::Number(_) => true_count += 1,
+                            error @ CalcResult::Error { .. } => return error.clone(),
+                            _ => {}
+                        }
+                    }
+                }
                 error @ CalcResult::Error { .. } => return error,
                 CalcResult::EmptyCell | CalcResult::EmptyArg => {}
+                CalcResult::Lambda(_) => return CalcResult::new_lambda_error(cell),
+                CalcResult::Areas(_) => return CalcResult::new_areas_error(cell),
             };
         }
         if true_count == 0 {
@@ -176,14 +207,31 @@ impl Model {
                                     return CalcResult::Boolean(true);
                                 }
                                 error @ CalcResult::Error { .. } => return error,
-                                CalcResult::Range { .. } => {}
+                                CalcResult::Range { .. }
+                                | CalcResult::Array(_)
+                                | CalcResult::Lambda(_)
+                                | CalcResult::Areas(_) => {}
                                 CalcResult::EmptyCell | CalcResult::EmptyArg => {}
                             }
                         }
                     }
                 }
+                CalcResult::Array(array) => {
+                    for value in array.iter().flatten() {
+                        match value {
+                            CalcResult::Boolean(value) => result = *value || result,
+                            CalcResult::Number(value) if *value != 0.0 => {
+                                return CalcResult::Boolean(true)
+                            }
+                            error @ CalcResult::Error { .. } => return error.clone(),
+                            _ => {}
+                        }
+                    }
+                }
                 error @ CalcResult::Error { .. } => return error,
                 CalcResult::EmptyCell | CalcResult::EmptyArg => {}
+                CalcResult::Lambda(_) => return CalcResult::new_lambda_error(cell),
+                CalcResult::Areas(_) => return CalcResult::new_areas_error(cell),
             };
         }
         CalcResult::Boolean(result)
@@ -245,6 +293,22 @@ impl Model {
                         }
                     }
                 }
+                CalcResult::Array(array) => {
+                    for value in array.iter().flatten() {
+                        match value {
+                            CalcResult::Boolean(true) => true_count += 1,
+                            CalcResult::Boolean(false) => false_count += 1,
+                            CalcResult::Number(value) => {
+                                if *value != 0.0 {
+                                    true_count += 1;
+                                } else {
+                                    false_count += 1;
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
                 _ => {}
             };
         }
@@ -318,4 +382,107 @@ impl Model {
             message: "Did not find a match".to_string(),
         }
     }
+
+    /// =LET(name1, value1, [name2, value2]*, calculation)
+    /// Each value is evaluated once and can be used by the following values and the calculation
+    pub(crate) fn fn_let(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        let args_count = args.len();
+        if args_count < 3 || args_count % 2 != 1 {
+            return CalcResult::new_args_number_error(cell);
+        }
+        let scope = self.local_variables.len();
+        for pair in args[..args_count - 1].chunks(2) {
+            let name = match &pair[0] {
+                Node::VariableKind(name) => name.to_lowercase(),
+                _ => {
+                    self.local_variables.truncate(scope);
+                    return CalcResult::new_error(Error::VALUE, cell, "Invalid name".to_string());
+                }
+            };
+            let value = self.evaluate_node_in_context(&pair[1], cell);
+            self.local_variables.push((name, value));
+        }
+        let result = self.evaluate_node_in_context(&args[args_count - 1], cell);
+        self.local_variables.truncate(scope);
+        result
+    }
+
+    /// =LAMBDA([parameter1, parameter2, ...,] calculation)
+    /// Returns a function that can be called or bound to a name.
+    /// The names bound by LET at this point are captured by the function.
+    pub(crate) fn fn_lambda(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        let (body, parameters) = match args.split_last() {
+            Some(split) => split,
+            None => return CalcResult::new_args_number_error(cell),
+        };
+        let mut names: Vec<String> = Vec::with_capacity(parameters.len());
+        for parameter in parameters {
+            let name = match parameter {
+                Node::VariableKind(name) => name.to_lowercase(),
+                _ => return CalcResult::new_error(Error::VALUE, cell, "Invalid name".to_string()),
+            };
+            if names.contains(&name) {
+                return CalcResult::new_error(
+                    Error::VALUE,
+                    cell,
+                    format!("Duplicate parameter: {}", name),
+                );
+            }
+            names.push(name);
+        }
+        CalcResult::Lambda(Box::new(Lambda {
+            parameters: names,
+            body: body.clone(),
+            captured: self.local_variables.clone(),
+        }))
+    }
+
+    /// Calls `lambda` with `args`, evaluated in the scope of the caller
+    pub(crate) fn call_lambda(
+        &mut self,
+        lambda: &Lambda,
+        args: &[Node],
+        cell: CellReference,
+    ) -> CalcResult {
+        if args.len() != lambda.parameters.len() {
+            return CalcResult::new_error(
+                Error::VALUE,
+                cell,
+                "Wrong number of arguments".to_string(),
+            );
+        }
+        if self.lambda_depth >= MAX_LAMBDA_DEPTH {
+            return CalcResult::new_error(
+                Error::NUM,
+                cell,
+                "Too many nested LAMBDA calls".to_string(),
+            );
+        }
+        let mut variables = lambda.captured.clone();
+        for (name, arg) in lambda.parameters.iter().zip(args) {
+            let value = self.evaluate_node_in_context(arg, cell);
+            variables.push((name.clone(), value));
+        }
+        let local_variables = std::mem::replace(&mut self.local_variables, variables);
+        self.lambda_depth += 1;
+        let result = self.evaluate_node_in_context(&lambda.body, cell);
+        self.lambda_depth -= 1;
+        self.local_variables = local_variables;
+        result
+    }
+
+    /// Calls the function `value` with `args`.
+    /// Errors are propagated and any other value is not a function.
+    pub(crate) fn call_function_value(
+        &mut self,
+        value: CalcResult,
+        args: &[Node],
+        cell: CellReference,
+    ) -> CalcResult {
+        match value {
+            CalcResult::Lambda(lambda) => self.call_lambda(&lambda, args, cell),
+            error @ CalcResult::Error { .. } => error,
+            _ => CalcResult::new_error(Error::VALUE, cell, "Not a function".to_string()),
+        }
+    }
 }
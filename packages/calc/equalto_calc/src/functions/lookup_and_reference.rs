@@ -1,6 +1,6 @@
 use crate::constants::{LAST_COLUMN, LAST_ROW};
 use crate::{
-    calc_result::{CalcResult, CellReference},
+    calc_result::{CalcResult, CellReference, Range},
     expressions::parser::Node,
     expressions::token::Error,
     model::Model,
@@ -10,10 +10,12 @@ use crate::{
 use super::util::{compare_values, from_wildcard_to_regex, result_matches_regex, values_are_equal};
 
 impl Model {
+    // INDEX(reference, row_num, [column_num], [area_num])
+    // area_num picks one of the areas of a reference with several: INDEX((A1:B2,D1:E2),1,1,2)
     pub(crate) fn fn_index(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
         let row_num;
         let col_num;
-        if args.len() == 3 {
+        if args.len() == 3 || args.len() == 4 {
             row_num = match self.get_number(&args[1], cell) {
                 Ok(f) => f,
                 Err(s) => {
@@ -58,7 +60,46 @@ impl Model {
         } else {
             return CalcResult::new_args_number_error(cell);
         }
-        match self.evaluate_node_in_context(&args[0], cell) {
+        let area_num = if args.len() == 4 {
+            match self.get_number(&args[3], cell) {
+                Ok(f) => f,
+                Err(s) => return s,
+            }
+        } else {
+            1.0
+        };
+        if area_num < 1.0 {
+            return CalcResult::Error {
+                error: Error::VALUE,
+                origin: cell,
+                message: "Argument must be >= 1".to_string(),
+            };
+        }
+        let reference = match self.evaluate_node_in_context(&args[0], cell) {
+            CalcResult::Areas(areas) => match areas.get(area_num as usize - 1) {
+                Some(area) => CalcResult::Range {
+                    left: area.left,
+                    right: area.right,
+                },
+                None => {
+                    return CalcResult::Error {
+                        error: Error::REF,
+                        origin: cell,
+                        message: "Wrong area number".to_string(),
+                    }
+                }
+            },
+            error @ CalcResult::Error { .. } => return error,
+            _ if area_num as usize != 1 => {
+                return CalcResult::Error {
+                    error: Error::REF,
+                    origin: cell,
+                    message: "Wrong area number".to_string(),
+                }
+            }
+            result => result,
+        };
+        match reference {
             CalcResult::Range { left, right } => {
                 let row;
                 let column;
@@ -616,6 +657,18 @@ impl Model {
         }
     }
 
+    // AREAS(reference)
+    // Returns the number of areas in reference: AREAS((A1:B2,D1)) is 2
+    pub(crate) fn fn_areas(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        if args.len() != 1 {
+            return CalcResult::new_args_number_error(cell);
+        }
+        match self.get_areas(&args[0], cell) {
+            Ok(areas) => CalcResult::Number(areas.len() as f64),
+            Err(error) => error,
+        }
+    }
+
     // ROWS(range)
     // Returns the number of rows in range
     pub(crate) fn fn_rows(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
@@ -715,13 +768,13 @@ impl Model {
                     }
                 };
 
-                match parsed_reference {
-                    ParsedReference::CellReference(reference) => CalcResult::Range {
-                        left: reference,
-                        right: reference,
-                    },
-                    ParsedReference::Range(left, right) => CalcResult::Range { left, right },
-                }
+                let (left, right) = match parsed_reference {
+                    ParsedReference::CellReference(reference) => (reference, reference),
+                    ParsedReference::Range(left, right) => (left, right),
+                };
+                self.dependency_graph
+                    .add_evaluated_precedent(cell, Range { left, right });
+                CalcResult::Range { left, right }
             }
             Err(v) => v,
         }
@@ -838,6 +891,8 @@ impl Model {
             row: row_end,
             column: column_end,
         };
+        self.dependency_graph
+            .add_evaluated_precedent(cell, Range { left, right });
         CalcResult::Range { left, right }
     }
 }
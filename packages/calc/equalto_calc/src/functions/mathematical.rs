@@ -50,6 +50,15 @@ impl Model {
                         }
                     }
                 }
+                CalcResult::Array(array) => {
+                    for value in array.iter().flatten() {
+                        match value {
+                            CalcResult::Number(value) => result = value.min(result),
+                            error @ CalcResult::Error { .. } => return error.clone(),
+                            _ => {}
+                        }
+                    }
+                }
                 error @ CalcResult::Error { .. } => return error,
                 _ => {
                     // We ignore booleans and strings
@@ -93,6 +102,15 @@ impl Model {
                         }
                     }
                 }
+                CalcResult::Array(array) => {
+                    for value in array.iter().flatten() {
+                        match value {
+                            CalcResult::Number(value) => result = value.max(result),
+                            error @ CalcResult::Error { .. } => return error.clone(),
+                            _ => {}
+                        }
+                    }
+                }
                 error @ CalcResult::Error { .. } => return error,
                 _ => {
                     // We ignore booleans and strings
@@ -131,18 +149,14 @@ impl Model {
                     let mut column2 = right.column;
                     if row1 == 1 && row2 == LAST_ROW {
                         row2 = self
-                            .workbook
-                            .worksheet(left.sheet)
+                            .get_sheet_dimension(left.sheet)
                             .expect("Sheet expected during evaluation.")
-                            .dimension()
                             .max_row;
                     }
                     if column1 == 1 && column2 == LAST_COLUMN {
                         column2 = self
-                            .workbook
-                            .worksheet(left.sheet)
+                            .get_sheet_dimension(left.sheet)
                             .expect("Sheet expected during evaluation.")
-                            .dimension()
                             .max_column;
                     }
                     for row in row1..row2 + 1 {
@@ -163,6 +177,15 @@ impl Model {
                         }
                     }
                 }
+                CalcResult::Array(array) => {
+                    for value in array.iter().flatten() {
+                        match value {
+                            CalcResult::Number(value) => result += value,
+                            error @ CalcResult::Error { .. } => return error.clone(),
+                            _ => {}
+                        }
+                    }
+                }
                 error @ CalcResult::Error { .. } => return error,
                 _ => {
                     // We ignore booleans and strings
@@ -198,18 +221,14 @@ impl Model {
                     let mut column2 = right.column;
                     if row1 == 1 && row2 == LAST_ROW {
                         row2 = self
-                            .workbook
-                            .worksheet(left.sheet)
+                            .get_sheet_dimension(left.sheet)
                             .expect("Sheet expected during evaluation.")
-                            .dimension()
                             .max_row;
                     }
                     if column1 == 1 && column2 == LAST_COLUMN {
                         column2 = self
-                            .workbook
-                            .worksheet(left.sheet)
+                            .get_sheet_dimension(left.sheet)
                             .expect("Sheet expected during evaluation.")
-                            .dimension()
                             .max_column;
                     }
                     for row in row1..row2 + 1 {
@@ -231,6 +250,18 @@ impl Model {
                         }
                     }
                 }
+                CalcResult::Array(array) => {
+                    for value in array.iter().flatten() {
+                        match value {
+                            CalcResult::Number(value) => {
+                                seen_value = true;
+                                result *= *value;
+                            }
+                            error @ CalcResult::Error { .. } => return error.clone(),
+                            _ => {}
+                        }
+                    }
+                }
                 error @ CalcResult::Error { .. } => return error,
                 _ => {
                     // We ignore booleans and strings
@@ -668,4 +699,122 @@ impl Model {
         }
         CalcResult::Number((x + random() * (y - x)).floor())
     }
+
+    /// Returns the size of an array given in the arguments `rows` and `columns` of SEQUENCE or RANDARRAY
+    fn get_array_size(
+        &mut self,
+        args: &[Node],
+        default_rows: f64,
+        cell: CellReference,
+    ) -> Result<(usize, usize), CalcResult> {
+        let rows = self
+            .get_optional_number(args, 0, default_rows, cell)?
+            .trunc();
+        let columns = self.get_optional_number(args, 1, 1.0, cell)?.trunc();
+        if rows < 0.0 || columns < 0.0 {
+            return Err(CalcResult::new_error(
+                Error::VALUE,
+                cell,
+                "Dimensions must be positive".to_string(),
+            ));
+        }
+        if rows == 0.0 || columns == 0.0 {
+            return Err(CalcResult::new_error(
+                Error::CALC,
+                cell,
+                "Empty array".to_string(),
+            ));
+        }
+        if rows > LAST_ROW as f64 || columns > LAST_COLUMN as f64 {
+            return Err(CalcResult::new_error(
+                Error::VALUE,
+                cell,
+                "Array is too large".to_string(),
+            ));
+        }
+        Ok((rows as usize, columns as usize))
+    }
+
+    /// SEQUENCE(rows, [columns], [start], [step])
+    pub(crate) fn fn_sequence(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        if args.is_empty() || args.len() > 4 {
+            return CalcResult::new_args_number_error(cell);
+        }
+        let (rows, columns) = match self.get_array_size(args, 1.0, cell) {
+            Ok(size) => size,
+            Err(s) => return s,
+        };
+        let start = match self.get_optional_number(args, 2, 1.0, cell) {
+            Ok(f) => f,
+            Err(s) => return s,
+        };
+        let step = match self.get_optional_number(args, 3, 1.0, cell) {
+            Ok(f) => f,
+            Err(s) => return s,
+        };
+        let array = (0..rows)
+            .map(|row| {
+                (0..columns)
+                    .map(|column| {
+                        CalcResult::Number(start + step * (row * columns + column) as f64)
+                    })
+                    .collect()
+            })
+            .collect();
+        CalcResult::Array(array)
+    }
+
+    /// RANDARRAY([rows], [columns], [min], [max], [whole_number])
+    pub(crate) fn fn_randarray(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        if args.len() > 5 {
+            return CalcResult::new_args_number_error(cell);
+        }
+        let (rows, columns) = match self.get_array_size(args, 1.0, cell) {
+            Ok(size) => size,
+            Err(s) => return s,
+        };
+        let min = match self.get_optional_number(args, 2, 0.0, cell) {
+            Ok(f) => f,
+            Err(s) => return s,
+        };
+        let max = match self.get_optional_number(args, 3, 1.0, cell) {
+            Ok(f) => f,
+            Err(s) => return s,
+        };
+        let whole_number = match args.get(4) {
+            None | Some(Node::EmptyArgKind) => false,
+            Some(arg) => match self.get_boolean(arg, cell) {
+                Ok(b) => b,
+                Err(s) => return s,
+            },
+        };
+        if min > max {
+            return CalcResult::new_error(
+                Error::VALUE,
+                cell,
+                "Minimum is larger than maximum".to_string(),
+            );
+        }
+        if whole_number && (min.fract() != 0.0 || max.fract() != 0.0) {
+            return CalcResult::new_error(
+                Error::VALUE,
+                cell,
+                "Expecting whole numbers".to_string(),
+            );
+        }
+        let array = (0..rows)
+            .map(|_| {
+                (0..columns)
+                    .map(|_| {
+                        if whole_number {
+                            CalcResult::Number((min + random() * (max - min + 1.0)).floor())
+                        } else {
+                            CalcResult::Number(min + random() * (max - min))
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        CalcResult::Array(array)
+    }
 }
@@ -4,10 +4,13 @@ use crate::{
     calc_result::{CalcResult, CellReference},
     expressions::{parser::Node, token::Error},
     model::Model,
+    reference_operators::accepts_unions,
+    sheet_spans::accepts_sheet_spans,
 };
 
 pub(crate) mod binary_search;
 mod date_and_time;
+mod dynamic_arrays;
 mod engineering;
 mod financial;
 mod financial_util;
@@ -32,6 +35,8 @@ pub enum Function {
     Iferror,
     Ifna,
     Ifs,
+    Lambda,
+    Let,
     Not,
     Or,
     Switch,
@@ -42,6 +47,7 @@ pub enum Function {
     Abs,
     Acos,
     Acosh,
+    Areas,
     Asin,
     Asinh,
     Atan,
@@ -58,10 +64,12 @@ pub enum Function {
     Power,
     Product,
     Rand,
+    Randarray,
     Randbetween,
     Round,
     Rounddown,
     Roundup,
+    Sequence,
     Sin,
     Sinh,
     Sqrt,
@@ -102,6 +110,13 @@ pub enum Function {
     Vlookup,
     Xlookup,
 
+    // Dynamic arrays
+    Filter,
+    Single,
+    Sort,
+    Sortby,
+    Unique,
+
     // Text
     Concat,
     Concatenate,
@@ -135,8 +150,18 @@ pub enum Function {
     Countblank,
     Countif,
     Countifs,
+    Large,
+    Maxa,
     Maxifs,
+    Median,
+    Mina,
     Minifs,
+    Rank,
+    Small,
+    Stdev,
+    Stdevp,
+    Var,
+    Varp,
 
     // Date and time
     Date,
@@ -251,6 +276,8 @@ impl Function {
             Function::Concat => "_xlfn.CONCAT".to_string(),
             Function::Ifna => "_xlfn.IFNA".to_string(),
             Function::Ifs => "_xlfn.IFS".to_string(),
+            Function::Lambda => "_xlfn.LAMBDA".to_string(),
+            Function::Let => "_xlfn.LET".to_string(),
             Function::Maxifs => "_xlfn.MAXIFS".to_string(),
             Function::Minifs => "_xlfn.MINIFS".to_string(),
             Function::Switch => "_xlfn.SWITCH".to_string(),
@@ -278,6 +305,13 @@ impl Function {
             Function::Valuetotext => "_xlfn.VALUETOTEXT".to_string(),
             Function::Isformula => "_xlfn.ISFORMULA".to_string(),
             Function::Sheet => "_xlfn.SHEET".to_string(),
+            Function::Filter => "_xlfn._xlws.FILTER".to_string(),
+            Function::Single => "_xlfn.SINGLE".to_string(),
+            Function::Sort => "_xlfn._xlws.SORT".to_string(),
+            Function::Sortby => "_xlfn.SORTBY".to_string(),
+            Function::Unique => "_xlfn.UNIQUE".to_string(),
+            Function::Sequence => "_xlfn.SEQUENCE".to_string(),
+            Function::Randarray => "_xlfn.RANDARRAY".to_string(),
             _ => self.to_string(),
         }
     }
@@ -285,6 +319,21 @@ impl Function {
     pub(crate) fn returns_reference(&self) -> bool {
         matches!(self, Function::Indirect | Function::Offset)
     }
+
+    /// Volatile functions need to be recomputed on every evaluation.
+    /// Functions returning references are included because their precedents are unknown until evaluated.
+    pub(crate) fn is_volatile(&self) -> bool {
+        matches!(
+            self,
+            Function::Rand
+                | Function::Randarray
+                | Function::Randbetween
+                | Function::Now
+                | Function::Today
+                | Function::Indirect
+                | Function::Offset
+        )
+    }
     /// Gets the function from the name.
     /// Note that in Excel some (modern) functions are prefixed by `_xlfn.`
     pub fn get_function(name: &str) -> Option<Function> {
@@ -295,6 +344,8 @@ impl Function {
             "IFERROR" => Some(Function::Iferror),
             "IFNA" | "_XLFN.IFNA" => Some(Function::Ifna),
             "IFS" | "_XLFN.IFS" => Some(Function::Ifs),
+            "LAMBDA" | "_XLFN.LAMBDA" => Some(Function::Lambda),
+            "LET" | "_XLFN.LET" => Some(Function::Let),
             "NOT" => Some(Function::Not),
             "OR" => Some(Function::Or),
             "SWITCH" | "_XLFN.SWITCH" => Some(Function::Switch),
@@ -328,15 +379,18 @@ impl Function {
             "MIN" => Some(Function::Min),
             "PRODUCT" => Some(Function::Product),
             "RAND" => Some(Function::Rand),
+            "RANDARRAY" | "_XLFN.RANDARRAY" => Some(Function::Randarray),
             "RANDBETWEEN" => Some(Function::Randbetween),
             "ROUND" => Some(Function::Round),
             "ROUNDDOWN" => Some(Function::Rounddown),
             "ROUNDUP" => Some(Function::Roundup),
+            "SEQUENCE" | "_XLFN.SEQUENCE" => Some(Function::Sequence),
             "SUM" => Some(Function::Sum),
             "SUMIF" => Some(Function::Sumif),
             "SUMIFS" => Some(Function::Sumifs),
 
             // Lookup and Reference
+            "AREAS" => Some(Function::Areas),
             "CHOOSE" => Some(Function::Choose),
             "COLUMN" => Some(Function::Column),
             "COLUMNS" => Some(Function::Columns),
@@ -351,6 +405,12 @@ impl Function {
             "VLOOKUP" => Some(Function::Vlookup),
             "XLOOKUP" | "_XLFN.XLOOKUP" => Some(Function::Xlookup),
 
+            "FILTER" | "_XLFN._XLWS.FILTER" => Some(Function::Filter),
+            "SINGLE" | "_XLFN.SINGLE" => Some(Function::Single),
+            "SORT" | "_XLFN._XLWS.SORT" => Some(Function::Sort),
+            "SORTBY" | "_XLFN.SORTBY" => Some(Function::Sortby),
+            "UNIQUE" | "_XLFN.UNIQUE" => Some(Function::Unique),
+
             "CONCATENATE" => Some(Function::Concatenate),
             "EXACT" => Some(Function::Exact),
             "VALUE" => Some(Function::Value),
@@ -400,8 +460,18 @@ impl Function {
             "COUNTBLANK" => Some(Function::Countblank),
             "COUNTIF" => Some(Function::Countif),
             "COUNTIFS" => Some(Function::Countifs),
+            "LARGE" => Some(Function::Large),
+            "MAXA" => Some(Function::Maxa),
             "MAXIFS" | "_XLFN.MAXIFS" => Some(Function::Maxifs),
+            "MEDIAN" => Some(Function::Median),
+            "MINA" => Some(Function::Mina),
             "MINIFS" | "_XLFN.MINIFS" => Some(Function::Minifs),
+            "RANK" => Some(Function::Rank),
+            "SMALL" => Some(Function::Small),
+            "STDEV" => Some(Function::Stdev),
+            "STDEVP" => Some(Function::Stdevp),
+            "VAR" => Some(Function::Var),
+            "VARP" => Some(Function::Varp),
             // Date and Time
             "YEAR" => Some(Function::Year),
             "DAY" => Some(Function::Day),
@@ -516,6 +586,8 @@ impl fmt::Display for Function {
             Function::Iferror => write!(f, "IFERROR"),
             Function::Ifna => write!(f, "IFNA"),
             Function::Ifs => write!(f, "IFS"),
+            Function::Lambda => write!(f, "LAMBDA"),
+            Function::Let => write!(f, "LET"),
             Function::Not => write!(f, "NOT"),
             Function::Or => write!(f, "OR"),
             Function::Switch => write!(f, "SWITCH"),
@@ -543,13 +615,16 @@ impl fmt::Display for Function {
             Function::Min => write!(f, "MIN"),
             Function::Product => write!(f, "PRODUCT"),
             Function::Rand => write!(f, "RAND"),
+            Function::Randarray => write!(f, "RANDARRAY"),
             Function::Randbetween => write!(f, "RANDBETWEEN"),
             Function::Round => write!(f, "ROUND"),
             Function::Rounddown => write!(f, "ROUNDDOWN"),
             Function::Roundup => write!(f, "ROUNDUP"),
+            Function::Sequence => write!(f, "SEQUENCE"),
             Function::Sum => write!(f, "SUM"),
             Function::Sumif => write!(f, "SUMIF"),
             Function::Sumifs => write!(f, "SUMIFS"),
+            Function::Areas => write!(f, "AREAS"),
             Function::Choose => write!(f, "CHOOSE"),
             Function::Column => write!(f, "COLUMN"),
             Function::Columns => write!(f, "COLUMNS"),
@@ -563,6 +638,11 @@ impl fmt::Display for Function {
             Function::Rows => write!(f, "ROWS"),
             Function::Vlookup => write!(f, "VLOOKUP"),
             Function::Xlookup => write!(f, "XLOOKUP"),
+            Function::Filter => write!(f, "FILTER"),
+            Function::Single => write!(f, "SINGLE"),
+            Function::Sort => write!(f, "SORT"),
+            Function::Sortby => write!(f, "SORTBY"),
+            Function::Unique => write!(f, "UNIQUE"),
             Function::Concatenate => write!(f, "CONCATENATE"),
             Function::Exact => write!(f, "EXACT"),
             Function::Value => write!(f, "VALUE"),
@@ -605,8 +685,18 @@ impl fmt::Display for Function {
             Function::Countblank => write!(f, "COUNTBLANK"),
             Function::Countif => write!(f, "COUNTIF"),
             Function::Countifs => write!(f, "COUNTIFS"),
+            Function::Large => write!(f, "LARGE"),
+            Function::Maxa => write!(f, "MAXA"),
             Function::Maxifs => write!(f, "MAXIFS"),
+            Function::Median => write!(f, "MEDIAN"),
+            Function::Mina => write!(f, "MINA"),
             Function::Minifs => write!(f, "MINIFS"),
+            Function::Rank => write!(f, "RANK"),
+            Function::Small => write!(f, "SMALL"),
+            Function::Stdev => write!(f, "STDEV"),
+            Function::Stdevp => write!(f, "STDEVP"),
+            Function::Var => write!(f, "VAR"),
+            Function::Varp => write!(f, "VARP"),
             Function::Year => write!(f, "YEAR"),
             Function::Day => write!(f, "DAY"),
             Function::Month => write!(f, "MONTH"),
@@ -715,6 +805,17 @@ impl Model {
         args: &[Node],
         cell: CellReference,
     ) -> CalcResult {
+        if accepts_sheet_spans(kind) {
+            if let Some(args) = self.expand_sheet_spans(args, cell.sheet) {
+                return self.evaluate_function(kind, &args, cell);
+            }
+        }
+        if accepts_unions(kind) {
+            // The areas of a union are separate arguments: SUM((A1:A3,C1:C3))
+            if let Some(args) = self.expand_areas(args, cell) {
+                return self.evaluate_function(kind, &args, cell);
+            }
+        }
         match kind {
             // Logical
             Function::And => self.fn_and(args, cell),
@@ -723,6 +824,8 @@ impl Model {
             Function::Iferror => self.fn_iferror(args, cell),
             Function::Ifna => self.fn_ifna(args, cell),
             Function::Ifs => self.fn_ifs(args, cell),
+            Function::Lambda => self.fn_lambda(args, cell),
+            Function::Let => self.fn_let(args, cell),
             Function::Not => self.fn_not(args, cell),
             Function::Or => self.fn_or(args, cell),
             Function::Switch => self.fn_switch(args, cell),
@@ -757,15 +860,18 @@ impl Model {
             Function::Min => self.fn_min(args, cell),
             Function::Product => self.fn_product(args, cell),
             Function::Rand => self.fn_rand(args, cell),
+            Function::Randarray => self.fn_randarray(args, cell),
             Function::Randbetween => self.fn_randbetween(args, cell),
             Function::Round => self.fn_round(args, cell),
             Function::Rounddown => self.fn_rounddown(args, cell),
             Function::Roundup => self.fn_roundup(args, cell),
+            Function::Sequence => self.fn_sequence(args, cell),
             Function::Sum => self.fn_sum(args, cell),
             Function::Sumif => self.fn_sumif(args, cell),
             Function::Sumifs => self.fn_sumifs(args, cell),
 
             // Lookup and Reference
+            Function::Areas => self.fn_areas(args, cell),
             Function::Choose => self.fn_choose(args, cell),
             Function::Column => self.fn_column(args, cell),
             Function::Columns => self.fn_columns(args, cell),
@@ -779,6 +885,12 @@ impl Model {
             Function::Rows => self.fn_rows(args, cell),
             Function::Vlookup => self.fn_vlookup(args, cell),
             Function::Xlookup => self.fn_xlookup(args, cell),
+            // Dynamic arrays
+            Function::Filter => self.fn_filter(args, cell),
+            Function::Single => self.fn_single(args, cell),
+            Function::Sort => self.fn_sort(args, cell),
+            Function::Sortby => self.fn_sortby(args, cell),
+            Function::Unique => self.fn_unique(args, cell),
             // Text
             Function::Concatenate => self.fn_concatenate(args, cell),
             Function::Exact => self.fn_exact(args, cell),
@@ -823,8 +935,18 @@ impl Model {
             Function::Countblank => self.fn_countblank(args, cell),
             Function::Countif => self.fn_countif(args, cell),
             Function::Countifs => self.fn_countifs(args, cell),
+            Function::Large => self.fn_large(args, cell),
+            Function::Maxa => self.fn_maxa(args, cell),
             Function::Maxifs => self.fn_maxifs(args, cell),
+            Function::Median => self.fn_median(args, cell),
+            Function::Mina => self.fn_mina(args, cell),
             Function::Minifs => self.fn_minifs(args, cell),
+            Function::Rank => self.fn_rank(args, cell),
+            Function::Small => self.fn_small(args, cell),
+            Function::Stdev => self.fn_stdev(args, cell),
+            Function::Stdevp => self.fn_stdevp(args, cell),
+            Function::Var => self.fn_var(args, cell),
+            Function::Varp => self.fn_varp(args, cell),
             // Date and Time
             Function::Year => self.fn_year(args, cell),
             Function::Day => self.fn_day(args, cell),
@@ -60,6 +60,18 @@ impl Model {
                         }
                     }
                 }
+                CalcResult::Array(array) => {
+                    for value in array.iter().flatten() {
+                        match value {
+                            CalcResult::Number(value) => {
+                                count += 1.0;
+                                sum += value;
+                            }
+                            error @ CalcResult::Error { .. } => return error.clone(),
+                            _ => {}
+                        }
+                    }
+                }
                 error @ CalcResult::Error { .. } => return error,
                 CalcResult::String(s) => {
                     if let Node::ReferenceKind { .. } = arg {
@@ -125,7 +137,10 @@ impl Model {
                                     count += 1.0;
                                 }
                                 error @ CalcResult::Error { .. } => return error,
-                                CalcResult::Range { .. } => {
+                                CalcResult::Range { .. }
+                                | CalcResult::Array(_)
+                                | CalcResult::Lambda(_)
+                                | CalcResult::Areas(_) => {
                                     return CalcResult::new_error(
                                         Error::ERROR,
                                         cell,
@@ -156,6 +171,25 @@ impl Model {
                         };
                     }
                 }
+                CalcResult::Array(array) => {
+                    for value in array.iter().flatten() {
+                        match value {
+                            CalcResult::Number(value) => {
+                                count += 1.0;
+                                sum += value;
+                            }
+                            CalcResult::String(_) => count += 1.0,
+                            CalcResult::Boolean(b) => {
+                                if *b {
+                                    sum += 1.0;
+                                }
+                                count += 1.0;
+                            }
+                            error @ CalcResult::Error { .. } => return error.clone(),
+                            _ => {}
+                        }
+                    }
+                }
                 CalcResult::Boolean(b) => {
                     count += 1.0;
                     if b {
@@ -164,6 +198,8 @@ impl Model {
                 }
                 error @ CalcResult::Error { .. } => return error,
                 CalcResult::EmptyCell | CalcResult::EmptyArg => {}
+                CalcResult::Lambda(_) => return CalcResult::new_lambda_error(cell),
+                CalcResult::Areas(_) => return CalcResult::new_areas_error(cell),
             };
         }
         if count == 0.0 {
@@ -216,6 +252,13 @@ impl Model {
                         }
                     }
                 }
+                CalcResult::Array(array) => {
+                    for value in array.iter().flatten() {
+                        if let CalcResult::Number(_) = value {
+                            result += 1.0;
+                        }
+                    }
+                }
                 _ => {
                     // Ignore everything else
                 }
@@ -255,6 +298,16 @@ impl Model {
                         }
                     }
                 }
+                CalcResult::Array(array) => {
+                    for value in array.iter().flatten() {
+                        match value {
+                            CalcResult::EmptyCell | CalcResult::EmptyArg => {}
+                            _ => {
+                                result += 1.0;
+                            }
+                        }
+                    }
+                }
                 _ => {
                     result += 1.0;
                 }
@@ -381,10 +434,8 @@ impl Model {
         let right_column = first_range.right.column;
 
         let dimension = self
-            .workbook
-            .worksheet(first_range.left.sheet)
-            .expect("Sheet expected during evaluation.")
-            .dimension();
+            .get_sheet_dimension(first_range.left.sheet)
+            .expect("Sheet expected during evaluation.");
         let max_row = dimension.max_row;
         let max_column = dimension.max_column;
 
@@ -526,18 +577,14 @@ impl Model {
 
         if left_row == 1 && right_row == LAST_ROW {
             right_row = self
-                .workbook
-                .worksheet(sum_range.left.sheet)
+                .get_sheet_dimension(sum_range.left.sheet)
                 .expect("Sheet expected during evaluation.")
-                .dimension()
                 .max_row;
         }
         if left_column == 1 && right_column == LAST_COLUMN {
             right_column = self
-                .workbook
-                .worksheet(sum_range.left.sheet)
+                .get_sheet_dimension(sum_range.left.sheet)
                 .expect("Sheet expected during evaluation.")
-                .dimension()
                 .max_column;
         }
 
@@ -621,4 +668,262 @@ impl Model {
         }
         CalcResult::Number(max)
     }
+
+    /// Returns the numbers in `node` for a statistical function. Text and booleans in references
+    /// and arrays are ignored, or counted as 0 and 1 if `all_values` is true (MAXA, MINA).
+    /// Numbers written as text in the arguments are numbers.
+    fn collect_numbers(
+        &mut self,
+        node: &Node,
+        cell: CellReference,
+        all_values: bool,
+        numbers: &mut Vec<f64>,
+    ) -> Result<(), CalcResult> {
+        let ranges = match self.evaluate_node_in_context(node, cell) {
+            CalcResult::Number(value) => {
+                numbers.push(value);
+                return Ok(());
+            }
+            CalcResult::Boolean(b) => {
+                if all_values || !matches!(node, Node::ReferenceKind { .. }) {
+                    numbers.push(if b { 1.0 } else { 0.0 });
+                }
+                return Ok(());
+            }
+            CalcResult::String(s) => {
+                if matches!(node, Node::ReferenceKind { .. }) {
+                    if all_values {
+                        numbers.push(0.0);
+                    }
+                } else {
+                    numbers.push(self.cast_to_number(CalcResult::String(s), cell)?);
+                }
+                return Ok(());
+            }
+            CalcResult::Range { left, right } => vec![Range { left, right }],
+            CalcResult::Areas(ranges) => ranges,
+            CalcResult::Array(array) => {
+                for value in array.iter().flatten() {
+                    push_number(value, all_values, numbers)?;
+                }
+                return Ok(());
+            }
+            error @ CalcResult::Error { .. } => return Err(error),
+            CalcResult::Lambda(_) => return Err(CalcResult::new_lambda_error(cell)),
+            CalcResult::EmptyCell | CalcResult::EmptyArg => return Ok(()),
+        };
+        for range in ranges {
+            if range.left.sheet != range.right.sheet {
+                return Err(CalcResult::new_error(
+                    Error::VALUE,
+                    cell,
+                    "Ranges are in different sheets".to_string(),
+                ));
+            }
+            for value in self.range_to_array(&range).iter().flatten() {
+                push_number(value, all_values, numbers)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the numbers in all the arguments, see `collect_numbers`
+    fn get_numbers(
+        &mut self,
+        args: &[Node],
+        cell: CellReference,
+        all_values: bool,
+    ) -> Result<Vec<f64>, CalcResult> {
+        let mut numbers = Vec::new();
+        for arg in args {
+            self.collect_numbers(arg, cell, all_values, &mut numbers)?;
+        }
+        Ok(numbers)
+    }
+
+    fn fn_extreme(
+        &mut self,
+        args: &[Node],
+        cell: CellReference,
+        fold: fn(f64, f64) -> f64,
+    ) -> CalcResult {
+        if args.is_empty() {
+            return CalcResult::new_args_number_error(cell);
+        }
+        match self.get_numbers(args, cell, true) {
+            Ok(numbers) => match numbers.into_iter().reduce(fold) {
+                Some(value) => CalcResult::Number(value),
+                None => CalcResult::Number(0.0),
+            },
+            Err(error) => error,
+        }
+    }
+
+    pub(crate) fn fn_maxa(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        self.fn_extreme(args, cell, f64::max)
+    }
+
+    pub(crate) fn fn_mina(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        self.fn_extreme(args, cell, f64::min)
+    }
+
+    pub(crate) fn fn_median(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        if args.is_empty() {
+            return CalcResult::new_args_number_error(cell);
+        }
+        let mut numbers = match self.get_numbers(args, cell, false) {
+            Ok(numbers) => numbers,
+            Err(error) => return error,
+        };
+        if numbers.is_empty() {
+            return CalcResult::new_error(Error::NUM, cell, "No numbers".to_string());
+        }
+        numbers.sort_by(|a, b| a.total_cmp(b));
+        let middle = numbers.len() / 2;
+        if numbers.len() % 2 == 0 {
+            CalcResult::Number((numbers[middle - 1] + numbers[middle]) / 2.0)
+        } else {
+            CalcResult::Number(numbers[middle])
+        }
+    }
+
+    /// The variance of the numbers in `args`, of a sample or of the whole population
+    fn variance(
+        &mut self,
+        args: &[Node],
+        cell: CellReference,
+        sample: bool,
+    ) -> Result<f64, CalcResult> {
+        if args.is_empty() {
+            return Err(CalcResult::new_args_number_error(cell));
+        }
+        let numbers = self.get_numbers(args, cell, false)?;
+        let count = numbers.len() as f64;
+        let degrees = if sample { count - 1.0 } else { count };
+        if degrees < 1.0 {
+            return Err(CalcResult::new_error(
+                Error::DIV,
+                cell,
+                "Division by Zero".to_string(),
+            ));
+        }
+        let mean = numbers.iter().sum::<f64>() / count;
+        let squares: f64 = numbers.iter().map(|x| (x - mean) * (x - mean)).sum();
+        Ok(squares / degrees)
+    }
+
+    pub(crate) fn fn_var(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        match self.variance(args, cell, true) {
+            Ok(value) => CalcResult::Number(value),
+            Err(error) => error,
+        }
+    }
+
+    pub(crate) fn fn_varp(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        match self.variance(args, cell, false) {
+            Ok(value) => CalcResult::Number(value),
+            Err(error) => error,
+        }
+    }
+
+    pub(crate) fn fn_stdev(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        match self.variance(args, cell, true) {
+            Ok(value) => CalcResult::Number(value.sqrt()),
+            Err(error) => error,
+        }
+    }
+
+    pub(crate) fn fn_stdevp(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        match self.variance(args, cell, false) {
+            Ok(value) => CalcResult::Number(value.sqrt()),
+            Err(error) => error,
+        }
+    }
+
+    /// LARGE(array, k) and SMALL(array, k): the k-th largest or smallest number
+    fn fn_kth(&mut self, args: &[Node], cell: CellReference, largest: bool) -> CalcResult {
+        if args.len() != 2 {
+            return CalcResult::new_args_number_error(cell);
+        }
+        let mut numbers = Vec::new();
+        if let Err(error) = self.collect_numbers(&args[0], cell, false, &mut numbers) {
+            return error;
+        }
+        let k = match self.get_number(&args[1], cell) {
+            Ok(k) => k.ceil(),
+            Err(error) => return error,
+        };
+        if k < 1.0 || k > numbers.len() as f64 {
+            return CalcResult::new_error(Error::NUM, cell, "Invalid position".to_string());
+        }
+        if largest {
+            numbers.sort_by(|a, b| b.total_cmp(a));
+        } else {
+            numbers.sort_by(|a, b| a.total_cmp(b));
+        }
+        CalcResult::Number(numbers[k as usize - 1])
+    }
+
+    pub(crate) fn fn_large(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        self.fn_kth(args, cell, true)
+    }
+
+    pub(crate) fn fn_small(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        self.fn_kth(args, cell, false)
+    }
+
+    /// RANK(number, ref, [order]): the position of `number` in the numbers of `ref`,
+    /// in descending order unless `order` is not zero
+    pub(crate) fn fn_rank(&mut self, args: &[Node], cell: CellReference) -> CalcResult {
+        if args.len() < 2 || args.len() > 3 {
+            return CalcResult::new_args_number_error(cell);
+        }
+        let number = match self.get_number(&args[0], cell) {
+            Ok(number) => number,
+            Err(error) => return error,
+        };
+        let reference = self.evaluate_node_with_reference(&args[1], cell);
+        if !matches!(reference, CalcResult::Range { .. } | CalcResult::Areas(_)) {
+            return CalcResult::new_error(
+                Error::VALUE,
+                cell,
+                "Argument must be a reference".to_string(),
+            );
+        }
+        let mut numbers = Vec::new();
+        if let Err(error) = self.collect_numbers(&args[1], cell, false, &mut numbers) {
+            return error;
+        }
+        let ascending = match args.get(2) {
+            Some(order) => match self.get_number(order, cell) {
+                Ok(order) => order != 0.0,
+                Err(error) => return error,
+            },
+            None => false,
+        };
+        if !numbers.contains(&number) {
+            return CalcResult::new_error(Error::NA, cell, "Number not found".to_string());
+        }
+        let before = numbers
+            .iter()
+            .filter(|&&x| if ascending { x < number } else { x > number })
+            .count();
+        CalcResult::Number(before as f64 + 1.0)
+    }
+}
+
+/// Adds `value`, from a reference or an array, to `numbers`, see `Model::collect_numbers`
+fn push_number(
+    value: &CalcResult,
+    all_values: bool,
+    numbers: &mut Vec<f64>,
+) -> Result<(), CalcResult> {
+    match value {
+        CalcResult::Number(value) => numbers.push(*value),
+        CalcResult::Boolean(b) if all_values => numbers.push(if *b { 1.0 } else { 0.0 }),
+        CalcResult::String(_) if all_values => numbers.push(0.0),
+        error @ CalcResult::Error { .. } => return Err(error.clone()),
+        _ => {}
+    }
+    Ok(())
 }
@@ -74,23 +74,14 @@ impl Model {
 
     // FIXME(TD): This is too much
     fn cell_is_subtotal(&self, sheet_index: u32, row: i32, column: i32) -> bool {
-        let row_data = match self.workbook.worksheets[sheet_index as usize]
-            .sheet_data
-            .get(&row)
-        {
-            Some(r) => r,
-            None => return false,
-        };
-        let cell = match row_data.get(&column) {
+        let cell = match self.get_cell(sheet_index, row, column) {
             Some(c) => c,
-            None => {
-                return false;
-            }
+            None => return false,
         };
 
         match cell.get_formula() {
             Some(f) => {
-                let node = &self.parsed_formulas[sheet_index as usize][f as usize];
+                let node = &self.shared_model().parsed_formulas[sheet_index as usize][f as usize];
                 matches!(
                     node,
                     Node::FunctionKind {
@@ -171,7 +162,18 @@ impl Model {
                                 }
                             }
                         }
+                        CalcResult::Array(array) => {
+                            for value in array.iter().flatten() {
+                                match value {
+                                    CalcResult::Number(value) => result.push(*value),
+                                    error @ CalcResult::Error { .. } => return Err(error.clone()),
+                                    _ => {}
+                                }
+                            }
+                        }
                         CalcResult::EmptyCell | CalcResult::EmptyArg => result.push(0.0),
+                        CalcResult::Lambda(_) => return Err(CalcResult::new_lambda_error(cell)),
+                        CalcResult::Areas(_) => return Err(CalcResult::new_areas_error(cell)),
                     }
                 }
             }
@@ -405,10 +407,19 @@ impl Model {
                                 }
                             }
                         }
+                        CalcResult::Array(array) => {
+                            for value in array.iter().flatten() {
+                                if !matches!(value, CalcResult::EmptyCell | CalcResult::EmptyArg) {
+                                    counta += 1;
+                                }
+                            }
+                        }
                         CalcResult::String(_)
                         | CalcResult::Number(_)
                         | CalcResult::Boolean(_)
                         | CalcResult::Error { .. } => counta += 1,
+                        CalcResult::Lambda(_) => return CalcResult::new_lambda_error(cell),
+                        CalcResult::Areas(_) => return CalcResult::new_areas_error(cell),
                     }
                 }
             }
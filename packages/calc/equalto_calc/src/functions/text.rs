@@ -97,11 +97,33 @@ impl Model {
                                 }
                                 error @ CalcResult::Error { .. } => return error,
                                 CalcResult::EmptyCell | CalcResult::EmptyArg => {}
-                                CalcResult::Range { .. } => {}
+                                CalcResult::Range { .. }
+                                | CalcResult::Array(_)
+                                | CalcResult::Lambda(_)
+                                | CalcResult::Areas(_) => {}
                             }
                         }
                     }
                 }
+                CalcResult::Array(array) => {
+                    for value in array.iter().flatten() {
+                        match value {
+                            CalcResult::String(value) => result = format!("{}{}", result, value),
+                            CalcResult::Number(value) => result = format!("{}{}", result, value),
+                            CalcResult::Boolean(value) => {
+                                if *value {
+                                    result = format!("{}TRUE", result);
+                                } else {
+                                    result = format!("{}FALSE", result);
+                                }
+                            }
+                            error @ CalcResult::Error { .. } => return error.clone(),
+                            _ => {}
+                        }
+                    }
+                }
+                CalcResult::Lambda(_) => return CalcResult::new_lambda_error(cell),
+                CalcResult::Areas(_) => return CalcResult::new_areas_error(cell),
             };
         }
         CalcResult::String(result)
@@ -117,7 +139,10 @@ impl Model {
                     return CalcResult::Boolean(b);
                 }
                 error @ CalcResult::Error { .. } => return error,
-                CalcResult::Range { .. } => {
+                CalcResult::Range { .. }
+                | CalcResult::Array(_)
+                | CalcResult::Lambda(_)
+                | CalcResult::Areas(_) => {
                     // Implicit Intersection not implemented
                     return CalcResult::Error {
                         error: Error::NIMPL,
@@ -272,7 +297,10 @@ impl Model {
                     }
                 }
                 error @ CalcResult::Error { .. } => return error,
-                CalcResult::Range { .. } => {
+                CalcResult::Range { .. }
+                | CalcResult::Array(_)
+                | CalcResult::Lambda(_)
+                | CalcResult::Areas(_) => {
                     // Implicit Intersection not implemented
                     return CalcResult::Error {
                         error: Error::NIMPL,
@@ -300,7 +328,10 @@ impl Model {
                     }
                 }
                 error @ CalcResult::Error { .. } => return error,
-                CalcResult::Range { .. } => {
+                CalcResult::Range { .. }
+                | CalcResult::Array(_)
+                | CalcResult::Lambda(_)
+                | CalcResult::Areas(_) => {
                     // Implicit Intersection not implemented
                     return CalcResult::Error {
                         error: Error::NIMPL,
@@ -328,7 +359,10 @@ impl Model {
                     }
                 }
                 error @ CalcResult::Error { .. } => return error,
-                CalcResult::Range { .. } => {
+                CalcResult::Range { .. }
+                | CalcResult::Array(_)
+                | CalcResult::Lambda(_)
+                | CalcResult::Areas(_) => {
                     // Implicit Intersection not implemented
                     return CalcResult::Error {
                         error: Error::NIMPL,
@@ -356,7 +390,10 @@ impl Model {
                     }
                 }
                 error @ CalcResult::Error { .. } => return error,
-                CalcResult::Range { .. } => {
+                CalcResult::Range { .. }
+                | CalcResult::Array(_)
+                | CalcResult::Lambda(_)
+                | CalcResult::Areas(_) => {
                     // Implicit Intersection not implemented
                     return CalcResult::Error {
                         error: Error::NIMPL,
@@ -386,7 +423,10 @@ impl Model {
                 }
             }
             error @ CalcResult::Error { .. } => return error,
-            CalcResult::Range { .. } => {
+            CalcResult::Range { .. }
+            | CalcResult::Array(_)
+            | CalcResult::Lambda(_)
+            | CalcResult::Areas(_) => {
                 // Implicit Intersection not implemented
                 return CalcResult::Error {
                     error: Error::NIMPL,
@@ -416,7 +456,10 @@ impl Model {
                     };
                 }
                 error @ CalcResult::Error { .. } => return error,
-                CalcResult::Range { .. } => {
+                CalcResult::Range { .. }
+                | CalcResult::Array(_)
+                | CalcResult::Lambda(_)
+                | CalcResult::Areas(_) => {
                     // Implicit Intersection not implemented
                     return CalcResult::Error {
                         error: Error::NIMPL,
@@ -454,7 +497,10 @@ impl Model {
                 }
             }
             error @ CalcResult::Error { .. } => return error,
-            CalcResult::Range { .. } => {
+            CalcResult::Range { .. }
+            | CalcResult::Array(_)
+            | CalcResult::Lambda(_)
+            | CalcResult::Areas(_) => {
                 // Implicit Intersection not implemented
                 return CalcResult::Error {
                     error: Error::NIMPL,
@@ -484,7 +530,10 @@ impl Model {
                     };
                 }
                 error @ CalcResult::Error { .. } => return error,
-                CalcResult::Range { .. } => {
+                CalcResult::Range { .. }
+                | CalcResult::Array(_)
+                | CalcResult::Lambda(_)
+                | CalcResult::Areas(_) => {
                     // Implicit Intersection not implemented
                     return CalcResult::Error {
                         error: Error::NIMPL,
@@ -522,7 +571,10 @@ impl Model {
                 }
             }
             error @ CalcResult::Error { .. } => return error,
-            CalcResult::Range { .. } => {
+            CalcResult::Range { .. }
+            | CalcResult::Array(_)
+            | CalcResult::Lambda(_)
+            | CalcResult::Areas(_) => {
                 // Implicit Intersection not implemented
                 return CalcResult::Error {
                     error: Error::NIMPL,
@@ -544,7 +596,10 @@ impl Model {
                 v.floor() as usize
             }
             error @ CalcResult::Error { .. } => return error,
-            CalcResult::Range { .. } => {
+            CalcResult::Range { .. }
+            | CalcResult::Array(_)
+            | CalcResult::Lambda(_)
+            | CalcResult::Areas(_) => {
                 // Implicit Intersection not implemented
                 return CalcResult::Error {
                     error: Error::NIMPL,
@@ -586,7 +641,10 @@ impl Model {
                 }
             }
             error @ CalcResult::Error { .. } => return error,
-            CalcResult::Range { .. } => {
+            CalcResult::Range { .. }
+            | CalcResult::Array(_)
+            | CalcResult::Lambda(_)
+            | CalcResult::Areas(_) => {
                 // Implicit Intersection not implemented
                 return CalcResult::Error {
                     error: Error::NIMPL,
@@ -890,18 +948,14 @@ impl Model {
                     let mut column2 = right.column;
                     if row1 == 1 && row2 == LAST_ROW {
                         row2 = self
-                            .workbook
-                            .worksheet(left.sheet)
+                            .get_sheet_dimension(left.sheet)
                             .expect("Sheet expected during evaluation.")
-                            .dimension()
                             .max_row;
                     }
                     if column1 == 1 && column2 == LAST_COLUMN {
                         column2 = self
-                            .workbook
-                            .worksheet(left.sheet)
+                            .get_sheet_dimension(left.sheet)
                             .expect("Sheet expected during evaluation.")
-                            .dimension()
                             .max_column;
                     }
                     for row in row1..row2 + 1 {
@@ -928,7 +982,11 @@ impl Model {
                                     }
                                 }
                                 error @ CalcResult::Error { .. } => return error,
-                                CalcResult::EmptyArg | CalcResult::Range { .. } => {}
+                                CalcResult::EmptyArg
+                                | CalcResult::Range { .. }
+                                | CalcResult::Array(_)
+                                | CalcResult::Lambda(_)
+                                | CalcResult::Areas(_) => {}
                             }
                         }
                     }
@@ -947,7 +1005,27 @@ impl Model {
                         values.push("".to_string())
                     }
                 }
+                CalcResult::Array(array) => {
+                    for value in array.iter().flatten() {
+                        match value {
+                            CalcResult::Number(value) => values.push(format!("{value}")),
+                            CalcResult::String(value) => values.push(value.clone()),
+                            CalcResult::Boolean(value) => {
+                                if *value {
+                                    values.push("TRUE".to_string())
+                                } else {
+                                    values.push("FALSE".to_string())
+                                }
+                            }
+                            CalcResult::EmptyCell if !ignore_empty => values.push("".to_string()),
+                            error @ CalcResult::Error { .. } => return error.clone(),
+                            _ => {}
+                        }
+                    }
+                }
                 CalcResult::EmptyArg => {}
+                CalcResult::Lambda(_) => return CalcResult::new_lambda_error(cell),
+                CalcResult::Areas(_) => return CalcResult::new_areas_error(cell),
             };
         }
         let result = values.join(&delimiter);
@@ -1062,7 +1140,10 @@ impl Model {
                 message: "Invalid number".to_string(),
             },
             error @ CalcResult::Error { .. } => error,
-            CalcResult::Range { .. } => {
+            CalcResult::Range { .. }
+            | CalcResult::Array(_)
+            | CalcResult::Lambda(_)
+            | CalcResult::Areas(_) => {
                 // TODO Implicit Intersection
                 CalcResult::Error {
                     error: Error::VALUE,
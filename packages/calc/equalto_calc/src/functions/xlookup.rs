@@ -254,18 +254,14 @@ impl Model {
 
                         if row1 == 1 && row2 == LAST_ROW {
                             row2 = self
-                                .workbook
-                                .worksheet(left.sheet)
+                                .get_sheet_dimension(left.sheet)
                                 .expect("Sheet expected during evaluation.")
-                                .dimension()
                                 .max_row;
                         }
                         if column1 == 1 && column2 == LAST_COLUMN {
                             column2 = self
-                                .workbook
-                                .worksheet(left.sheet)
+                                .get_sheet_dimension(left.sheet)
                                 .expect("Sheet expected during evaluation.")
-                                .dimension()
                                 .max_column;
                         }
                         let left = CellReference {
@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{calc_result::CellReference, model::Model, types::Cell};
+
+/// Options of `Model::goal_seek`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct GoalSeekOptions {
+    /// Maximum number of values tried in the changing cell
+    pub max_iterations: usize,
+    /// The goal is reached when the target cell is this close to it
+    pub tolerance: f64,
+    /// Keeps the solution in the changing cell, if one is found
+    pub apply: bool,
+}
+
+impl Default for GoalSeekOptions {
+    fn default() -> Self {
+        // The defaults of Excel
+        GoalSeekOptions {
+            max_iterations: 100,
+            tolerance: 0.001,
+            apply: true,
+        }
+    }
+}
+
+/// The outcome of `Model::goal_seek`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GoalSeekResult {
+    /// The value of the changing cell that brings the target cell closest to the goal
+    pub solution: f64,
+    /// The value of the target cell with that solution
+    pub target_value: f64,
+    /// Number of values tried in the changing cell
+    pub iterations: usize,
+    /// True if the target cell reached the goal
+    pub converged: bool,
+}
+
+/// A value of the changing cell and how far the target cell is from the goal with it
+#[derive(Clone, Copy)]
+struct Point {
+    x: f64,
+    error: f64,
+}
+
+struct GoalSeek {
+    target: CellReference,
+    goal: f64,
+    changing: CellReference,
+    /// Style of the changing cell
+    style: i32,
+    max_iterations: usize,
+    tolerance: f64,
+    iterations: usize,
+    /// The point closest to the goal so far
+    best: Option<Point>,
+}
+
+impl GoalSeek {
+    fn is_exhausted(&self) -> bool {
+        self.iterations >= self.max_iterations
+    }
+
+    fn is_solution(&self, point: &Point) -> bool {
+        point.error.abs() <= self.tolerance
+    }
+
+    /// Sets `x` in the changing cell and evaluates the target cell.
+    /// Returns None if the target is not a number or there are no iterations left.
+    fn try_value(&mut self, model: &mut Model, x: f64) -> Option<Point> {
+        if self.is_exhausted() || !x.is_finite() {
+            return None;
+        }
+        self.iterations += 1;
+        let CellReference { sheet, row, column } = self.changing;
+        model.workbook.worksheets[sheet as usize].set_cell_with_number(row, column, x, self.style);
+        model.dependency_graph.mark_dirty(sheet, row, column);
+        model.evaluate();
+        let CellReference { sheet, row, column } = self.target;
+        let value = match model.workbook.worksheets[sheet as usize].cell(row, column) {
+            Some(Cell::CellFormulaNumber { v, .. }) => *v,
+            _ => return None,
+        };
+        let point = Point {
+            x,
+            error: value - self.goal,
+        };
+        if self
+            .best
+            .map_or(true, |best| point.error.abs() < best.error.abs())
+        {
+            self.best = Some(point);
+        }
+        Some(point)
+    }
+
+    /// Tries `x`, moving halfway back to `from` while the target cell is not a number
+    fn try_towards(&mut self, model: &mut Model, from: f64, mut x: f64) -> Option<Point> {
+        while !self.is_exhausted() {
+            if let Some(point) = self.try_value(model, x) {
+                return Some(point);
+            }
+            x = (from + x) / 2.0;
+        }
+        None
+    }
+
+    /// Looks for a solution starting from `start`.
+    /// Secant steps are taken until the solution is bracketed, then the bracket is narrowed with
+    /// the Illinois variant of regula falsi, which always converges for continuous functions.
+    fn solve(&mut self, model: &mut Model, start: f64) {
+        let mut a = match self.try_value(model, start) {
+            Some(point) => point,
+            None => return,
+        };
+        if self.is_solution(&a) {
+            return;
+        }
+        let first_step = if start == 0.0 {
+            0.01
+        } else {
+            start.abs() * 0.01
+        };
+        let mut b = match self.try_towards(model, a.x, a.x + first_step) {
+            Some(point) => point,
+            None => return,
+        };
+        while a.error.signum() == b.error.signum() {
+            if self.is_solution(&b) {
+                return;
+            }
+            let slope = (b.error - a.error) / (b.x - a.x);
+            let x = if slope != 0.0 && slope.is_finite() {
+                // Flat regions could send the secant step too far
+                let limit = 100.0 * (b.x - a.x).abs().max(b.x.abs());
+                b.x + (-b.error / slope).clamp(-limit, limit)
+            } else {
+                b.x + 2.0 * (b.x - a.x)
+            };
+            let c = match self.try_towards(model, b.x, x) {
+                Some(point) => point,
+                None => return,
+            };
+            a = b;
+            b = c;
+        }
+
+        let (mut low, mut high) = (a, b);
+        let mut last_side = 0;
+        while !self.is_exhausted() {
+            let x = (low.x * high.error - high.x * low.error) / (high.error - low.error);
+            let c = match self.try_value(model, x) {
+                Some(point) => point,
+                None => match self.try_value(model, (low.x + high.x) / 2.0) {
+                    Some(point) => point,
+                    None => return,
+                },
+            };
+            if self.is_solution(&c) {
+                return;
+            }
+            if c.error.signum() == high.error.signum() {
+                high = c;
+                if last_side == -1 {
+                    low.error /= 2.0;
+                }
+                last_side = -1;
+            } else {
+                low = c;
+                if last_side == 1 {
+                    high.error /= 2.0;
+                }
+                last_side = 1;
+            }
+            if (high.x - low.x).abs() <= f64::EPSILON * high.x.abs().max(1.0) {
+                // A discontinuity, there is no solution
+                return;
+            }
+        }
+    }
+}
+
+impl Model {
+    /// Finds the value of `changing` that makes the formula in `target` evaluate to `goal`,
+    /// starting from the current value of `changing`, like Goal Seek in Excel.
+    /// With `options.apply` the changing cell keeps the solution if the goal was reached,
+    /// otherwise the model is left unchanged.
+    /// Fails if the target cell has no formula, the changing cell has one or the target cell
+    /// never evaluates to a number.
+    pub fn goal_seek(
+        &mut self,
+        target: CellReference,
+        goal: f64,
+        changing: CellReference,
+        options: &GoalSeekOptions,
+    ) -> Result<GoalSeekResult, String> {
+        let target_cell = self
+            .workbook
+            .worksheet(target.sheet)?
+            .cell(target.row, target.column);
+        if target_cell.and_then(|cell| cell.get_formula()).is_none() {
+            return Err("The target cell must contain a formula".to_string());
+        }
+        let changing_cell = self
+            .workbook
+            .worksheet(changing.sheet)?
+            .cell(changing.row, changing.column)
+            .cloned();
+        let start = match &changing_cell {
+            Some(cell) if cell.get_formula().is_some() => {
+                return Err("The changing cell must contain a value".to_string())
+            }
+            Some(Cell::NumberCell { v, .. }) => *v,
+            _ => 0.0,
+        };
+        if !goal.is_finite() {
+            return Err("The goal must be a number".to_string());
+        }
+        if options.max_iterations == 0 {
+            return Err("The maximum number of iterations must be at least 1".to_string());
+        }
+
+        // Brings the model up to date, the values tried are not reported as events
+        self.evaluate();
+        let events = std::mem::take(&mut self.events);
+        let mut goal_seek = GoalSeek {
+            target,
+            goal,
+            changing,
+            style: self.get_cell_style_index(changing.sheet, changing.row, changing.column),
+            max_iterations: options.max_iterations,
+            tolerance: options.tolerance,
+            iterations: 0,
+            best: None,
+        };
+        goal_seek.solve(self, start);
+
+        let CellReference { sheet, row, column } = changing;
+        let worksheet = &mut self.workbook.worksheets[sheet as usize];
+        match changing_cell {
+            Some(cell) => worksheet.update_cell(row, column, cell),
+            None => {
+                if let Some(row_data) = worksheet.sheet_data.get_mut(&row) {
+                    row_data.remove(&column);
+                }
+            }
+        }
+        self.dependency_graph.mark_dirty(sheet, row, column);
+        self.evaluate();
+        self.events = events;
+
+        let best = goal_seek
+            .best
+            .ok_or_else(|| "The target cell does not evaluate to a number".to_string())?;
+        let converged = goal_seek.is_solution(&best);
+        if options.apply && converged {
+            self.update_cell_with_number(sheet, row, column, best.x);
+            self.evaluate();
+        }
+        Ok(GoalSeekResult {
+            solution: best.x,
+            target_value: best.error + goal,
+            iterations: goal_seek.iterations,
+            converged,
+        })
+    }
+}
@@ -0,0 +1,763 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::RangeInclusive,
+};
+
+use crate::{
+    calc_result::CellReference,
+    constants::{LAST_COLUMN, LAST_ROW},
+    dependencies::CellKey,
+    diffs::{Diff, SheetProperties},
+    events::ModelEvent,
+    locale::Currency,
+    model::Model,
+    types::{CalculationSettings, Cell, DefinedName, Table, Worksheet},
+};
+
+/// The changes to the model that can be undone and redone.
+/// Each entry of the stacks is a transaction: a list of diffs undone and redone together.
+#[derive(Clone, Default)]
+pub(crate) struct History {
+    undo_stack: Vec<Vec<Diff>>,
+    redo_stack: Vec<Vec<Diff>>,
+    /// The diffs recorded since `begin_transaction` was called
+    transaction: Option<Vec<Diff>>,
+    /// Number of changes being applied. Changes made while applying another change are part of
+    /// it and are not recorded on their own, neither are those made while undoing or redoing.
+    depth: usize,
+    /// The cells changed by the changes made while applying one that rewrites formulas, with
+    /// their values before. Only the formulas that change are recorded that way.
+    rewritten_cells: Option<BTreeMap<CellKey, Option<Cell>>>,
+}
+
+impl History {
+    fn push(&mut self, diffs: Vec<Diff>) {
+        if self.depth > 0 || diffs.is_empty() {
+            return;
+        }
+        self.redo_stack.clear();
+        match &mut self.transaction {
+            Some(transaction) => transaction.extend(diffs),
+            None => self.undo_stack.push(diffs),
+        }
+    }
+
+    /// The cells changed by the last change that can be undone
+    #[cfg(test)]
+    pub(crate) fn last_changed_cells(&self) -> Vec<CellKey> {
+        self.undo_stack
+            .last()
+            .map(|diffs| {
+                diffs
+                    .iter()
+                    .filter_map(|diff| match diff {
+                        Diff::Cell {
+                            sheet, row, column, ..
+                        } => Some((*sheet, *row, *column)),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn end_transaction(&mut self) {
+        if let Some(diffs) = self.transaction.take() {
+            if !diffs.is_empty() {
+                self.undo_stack.push(diffs);
+            }
+        }
+    }
+}
+
+/// The parts of the workbook a change might modify
+#[derive(Default)]
+pub(crate) struct ChangeScope {
+    /// The cells of a sheet in some rows and columns
+    areas: Vec<(u32, RangeInclusive<i32>, RangeInclusive<i32>)>,
+    /// The cells with a formula anywhere in the workbook, only those rewritten by the change
+    formulas: bool,
+    /// The name and properties of these sheets
+    sheets: Vec<u32>,
+    /// The formulas of all the sheets
+    shared_formulas: bool,
+    defined_names: bool,
+    calculation_settings: bool,
+    /// The currency of the locale
+    currency: bool,
+    /// The names, areas and columns of the tables
+    tables: bool,
+}
+
+impl ChangeScope {
+    /// A change in a cell. It might remove a legacy array formula from the sheet or add a row
+    /// to a table.
+    pub(crate) fn cell(sheet: u32, row: i32, column: i32) -> ChangeScope {
+        ChangeScope {
+            tables: true,
+            ..ChangeScope::area(sheet, row, column, row, column)
+        }
+    }
+
+    /// A change in the cells from (`row`, `column`) to (`last_row`, `last_column`)
+    pub(crate) fn area(
+        sheet: u32,
+        row: i32,
+        column: i32,
+        last_row: i32,
+        last_column: i32,
+    ) -> ChangeScope {
+        ChangeScope {
+            areas: vec![(sheet, row..=last_row, column..=last_column)],
+            sheets: vec![sheet],
+            ..Default::default()
+        }
+    }
+
+    /// A change in some cells, anywhere in the workbook
+    pub(crate) fn cells(cells: &[CellReference]) -> ChangeScope {
+        let mut scope = ChangeScope::default();
+        for cell in cells {
+            scope
+                .areas
+                .push((cell.sheet, cell.row..=cell.row, cell.column..=cell.column));
+            if !scope.sheets.contains(&cell.sheet) {
+                scope.sheets.push(cell.sheet);
+            }
+        }
+        scope
+    }
+
+    /// A change in the name, the rows and columns, the color or the frozen panes of a sheet
+    pub(crate) fn sheet(sheet: u32) -> ChangeScope {
+        ChangeScope {
+            sheets: vec![sheet],
+            ..Default::default()
+        }
+    }
+
+    /// Inserting or deleting rows from `row` on: the cells below move and formulas are displaced
+    pub(crate) fn rows(sheet: u32, row: i32) -> ChangeScope {
+        ChangeScope {
+            areas: vec![(sheet, row..=LAST_ROW, 1..=LAST_COLUMN)],
+            formulas: true,
+            sheets: vec![sheet],
+            ..Default::default()
+        }
+    }
+
+    /// Inserting or deleting columns from `column` on
+    pub(crate) fn columns(sheet: u32, column: i32) -> ChangeScope {
+        ChangeScope {
+            areas: vec![(sheet, 1..=LAST_ROW, column..=LAST_COLUMN)],
+            formulas: true,
+            sheets: vec![sheet],
+            ..Default::default()
+        }
+    }
+
+    /// A change in the formulas of any cell
+    pub(crate) fn formulas() -> ChangeScope {
+        ChangeScope {
+            formulas: true,
+            ..Default::default()
+        }
+    }
+
+    /// Renaming a sheet changes the formulas referring to it and the tables in it
+    pub(crate) fn sheet_name(sheet: u32) -> ChangeScope {
+        ChangeScope {
+            sheets: vec![sheet],
+            shared_formulas: true,
+            tables: true,
+            ..Default::default()
+        }
+    }
+
+    /// Renaming a table or one of its columns changes the formulas using it
+    pub(crate) fn tables() -> ChangeScope {
+        ChangeScope {
+            shared_formulas: true,
+            defined_names: true,
+            tables: true,
+            ..Default::default()
+        }
+    }
+
+    /// Renaming a column of a table changes its header cell and the formulas in the table too
+    pub(crate) fn table_area(
+        sheet: u32,
+        row: i32,
+        column: i32,
+        last_row: i32,
+        last_column: i32,
+    ) -> ChangeScope {
+        ChangeScope {
+            areas: vec![(sheet, row..=last_row, column..=last_column)],
+            ..ChangeScope::tables()
+        }
+    }
+
+    /// Renaming a defined name changes the formulas using it
+    pub(crate) fn defined_names() -> ChangeScope {
+        ChangeScope {
+            shared_formulas: true,
+            defined_names: true,
+            ..Default::default()
+        }
+    }
+
+    /// Deleting a sheet changes the formulas referring to spans of sheets ending in it
+    pub(crate) fn sheet_spans() -> ChangeScope {
+        ChangeScope {
+            shared_formulas: true,
+            defined_names: true,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn calculation_settings() -> ChangeScope {
+        ChangeScope {
+            calculation_settings: true,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn currency() -> ChangeScope {
+        ChangeScope {
+            currency: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// The values of the parts of the workbook in a ChangeScope
+struct Snapshot {
+    cells: BTreeMap<CellKey, Cell>,
+    sheets: Vec<(u32, String, SheetProperties)>,
+    shared_formulas: Vec<Vec<String>>,
+    defined_names: Vec<DefinedName>,
+    calculation_settings: Option<CalculationSettings>,
+    currency: Option<Currency>,
+    tables: Option<HashMap<String, Table>>,
+}
+
+/// Returns the diffs between the values of the same parts of the workbook before and after a change
+fn get_diffs(before: Snapshot, mut after: Snapshot) -> Vec<Diff> {
+    let mut diffs = Vec::new();
+    for ((sheet, old_name, old_properties), (_, new_name, new_properties)) in
+        before.sheets.into_iter().zip(after.sheets)
+    {
+        if old_name != new_name {
+            diffs.push(Diff::SheetName {
+                sheet,
+                old_value: old_name,
+                new_value: new_name,
+            });
+        }
+        if old_properties != new_properties {
+            diffs.push(Diff::SheetProperties {
+                sheet,
+                old_value: Box::new(old_properties),
+                new_value: Box::new(new_properties),
+            });
+        }
+    }
+    for (sheet, (old_value, new_value)) in before
+        .shared_formulas
+        .into_iter()
+        .zip(after.shared_formulas)
+        .enumerate()
+    {
+        if old_value != new_value {
+            diffs.push(Diff::SharedFormulas {
+                sheet: sheet as u32,
+                old_value,
+                new_value,
+            });
+        }
+    }
+    if before.defined_names != after.defined_names {
+        diffs.push(Diff::DefinedNames {
+            old_value: before.defined_names,
+            new_value: after.defined_names,
+        });
+    }
+    if let (Some(old_value), Some(new_value)) = (before.tables, after.tables) {
+        if old_value != new_value {
+            diffs.push(Diff::Tables {
+                old_value,
+                new_value,
+            });
+        }
+    }
+    if let (Some(old_value), Some(new_value)) =
+        (before.calculation_settings, after.calculation_settings)
+    {
+        if old_value != new_value {
+            diffs.push(Diff::CalculationSettings {
+                old_value,
+                new_value,
+            });
+        }
+    }
+    if let (Some(old_value), Some(new_value)) = (before.currency, after.currency) {
+        if old_value != new_value {
+            diffs.push(Diff::Currency {
+                old_value,
+                new_value,
+            });
+        }
+    }
+    for ((sheet, row, column), old_value) in before.cells {
+        let new_value = after.cells.remove(&(sheet, row, column));
+        if new_value.as_ref() != Some(&old_value) {
+            diffs.push(Diff::Cell {
+                sheet,
+                row,
+                column,
+                old_value: Some(Box::new(old_value)),
+                new_value: new_value.map(Box::new),
+            });
+        }
+    }
+    for ((sheet, row, column), new_value) in after.cells {
+        diffs.push(Diff::Cell {
+            sheet,
+            row,
+            column,
+            old_value: None,
+            new_value: Some(Box::new(new_value)),
+        });
+    }
+    diffs
+}
+
+/// Inserts in `cells` the cells of `worksheet` in `rows` and `columns`
+fn collect_cells(
+    cells: &mut BTreeMap<CellKey, Cell>,
+    sheet: u32,
+    worksheet: &Worksheet,
+    rows: &RangeInclusive<i32>,
+    columns: &RangeInclusive<i32>,
+) {
+    let sheet_data = &worksheet.sheet_data;
+    // Small areas are looked up, large ones are filtered
+    let row_data: Vec<_> = if ((rows.end() - rows.start()) as usize) < sheet_data.len() {
+        rows.clone()
+            .filter_map(|row| sheet_data.get(&row).map(|data| (row, data)))
+            .collect()
+    } else {
+        sheet_data
+            .iter()
+            .filter(|(row, _)| rows.contains(row))
+            .map(|(row, data)| (*row, data))
+            .collect()
+    };
+    for (row, data) in row_data {
+        if ((columns.end() - columns.start()) as usize) < data.len() {
+            for column in columns.clone() {
+                if let Some(cell) = data.get(&column) {
+                    cells.insert((sheet, row, column), cell.clone());
+                }
+            }
+        } else {
+            for (column, cell) in data {
+                if columns.contains(column) {
+                    cells.insert((sheet, row, *column), cell.clone());
+                }
+            }
+        }
+    }
+}
+
+fn get_sheet_properties(worksheet: &Worksheet) -> SheetProperties {
+    SheetProperties {
+        rows: worksheet.rows.clone(),
+        cols: worksheet.cols.clone(),
+        array_formulas: worksheet.array_formulas.clone(),
+        color: worksheet.color.clone(),
+        frozen_rows: worksheet.frozen_rows,
+        frozen_columns: worksheet.frozen_columns,
+    }
+}
+
+fn set_sheet_properties(worksheet: &mut Worksheet, properties: &SheetProperties) {
+    worksheet.rows = properties.rows.clone();
+    worksheet.cols = properties.cols.clone();
+    worksheet.array_formulas = properties.array_formulas.clone();
+    worksheet.color = properties.color.clone();
+    worksheet.frozen_rows = properties.frozen_rows;
+    worksheet.frozen_columns = properties.frozen_columns;
+}
+
+impl Model {
+    fn take_snapshot(&self, scope: &ChangeScope) -> Snapshot {
+        let worksheets = &self.workbook.worksheets;
+        let mut cells = BTreeMap::new();
+        for (sheet, rows, columns) in &scope.areas {
+            if let Some(worksheet) = worksheets.get(*sheet as usize) {
+                collect_cells(&mut cells, *sheet, worksheet, rows, columns);
+            }
+        }
+        let sheets = scope
+            .sheets
+            .iter()
+            .filter_map(|sheet| {
+                let worksheet = worksheets.get(*sheet as usize)?;
+                Some((
+                    *sheet,
+                    worksheet.name.clone(),
+                    get_sheet_properties(worksheet),
+                ))
+            })
+            .collect();
+        let shared_formulas = if scope.shared_formulas {
+            worksheets
+                .iter()
+                .map(|worksheet| worksheet.shared_formulas.clone())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let defined_names = if scope.defined_names {
+            self.workbook.defined_names.clone()
+        } else {
+            Vec::new()
+        };
+        let calculation_settings = if scope.calculation_settings {
+            Some(self.workbook.settings.calculation.clone())
+        } else {
+            None
+        };
+        let currency = if scope.currency {
+            Some(self.locale.currency.clone())
+        } else {
+            None
+        };
+        let tables = if scope.tables {
+            Some(self.workbook.tables.clone())
+        } else {
+            None
+        };
+        Snapshot {
+            cells,
+            sheets,
+            shared_formulas,
+            defined_names,
+            calculation_settings,
+            currency,
+            tables,
+        }
+    }
+
+    /// Applies `change` and records the parts of the workbook in `scope` that it modified,
+    /// so that it can be undone
+    pub(crate) fn record_change<T>(
+        &mut self,
+        scope: ChangeScope,
+        change: impl FnOnce(&mut Model) -> T,
+    ) -> T {
+        if self.history.depth > 0 {
+            if self.history.rewritten_cells.is_some() {
+                self.remember_cells(&scope);
+            }
+            return change(self);
+        }
+        let mut before = self.take_snapshot(&scope);
+        if scope.formulas {
+            self.history.rewritten_cells = Some(BTreeMap::new());
+        }
+        self.history.depth += 1;
+        let result = change(self);
+        self.history.depth -= 1;
+        let mut after = self.take_snapshot(&scope);
+        for (key, old_value) in self.history.rewritten_cells.take().unwrap_or_default() {
+            // The snapshot before the change has the original values of the cells in its areas
+            if let Some(old_value) = old_value {
+                before.cells.entry(key).or_insert(old_value);
+            }
+            let (sheet, row, column) = key;
+            if let Some(cell) = self
+                .workbook
+                .worksheets
+                .get(sheet as usize)
+                .and_then(|worksheet| worksheet.cell(row, column))
+            {
+                after.cells.entry(key).or_insert_with(|| cell.clone());
+            }
+        }
+        let diffs = get_diffs(before, after);
+        for diff in &diffs {
+            if let Diff::Cell {
+                sheet,
+                row,
+                column,
+                old_value,
+                ..
+            } = diff
+            {
+                self.touch_cell_with((*sheet, *row, *column), old_value.as_deref());
+            }
+        }
+        self.history.push(diffs);
+        result
+    }
+
+    /// Keeps the values of the cells in `scope` before the first change made to them.
+    /// A cell that didn't exist is only noted for changes to a single cell, like rewriting a
+    /// formula, larger areas are not walked.
+    fn remember_cells(&mut self, scope: &ChangeScope) {
+        let mut cells = BTreeMap::new();
+        for (sheet, rows, columns) in &scope.areas {
+            if let Some(worksheet) = self.workbook.worksheets.get(*sheet as usize) {
+                collect_cells(&mut cells, *sheet, worksheet, rows, columns);
+            }
+        }
+        let mut values: BTreeMap<CellKey, Option<Cell>> = cells
+            .into_iter()
+            .map(|(key, cell)| (key, Some(cell)))
+            .collect();
+        for (sheet, rows, columns) in &scope.areas {
+            if rows.start() == rows.end() && columns.start() == columns.end() {
+                values
+                    .entry((*sheet, *rows.start(), *columns.start()))
+                    .or_insert(None);
+            }
+        }
+        if let Some(rewritten_cells) = &mut self.history.rewritten_cells {
+            for (key, value) in values {
+                rewritten_cells.entry(key).or_insert(value);
+            }
+        }
+    }
+
+    /// Records a change that was already applied
+    pub(crate) fn record_diff(&mut self, diff: Diff) {
+        self.history.push(vec![diff]);
+    }
+
+    /// Applies `change`, made of several recorded changes, so that they are undone together
+    pub(crate) fn record_changes<T>(&mut self, change: impl FnOnce(&mut Model) -> T) -> T {
+        if self.history.transaction.is_some() {
+            return change(self);
+        }
+        self.history.transaction = Some(Vec::new());
+        let result = change(self);
+        self.history.end_transaction();
+        result
+    }
+
+    /// Sets the old values of `diff` if `undo` or the new values otherwise.
+    /// Returns true if the formulas need to be parsed again.
+    fn apply_diff(&mut self, diff: &mut Diff, undo: bool) -> Result<bool, String> {
+        let is_deletion = matches!(diff, Diff::DeleteSheet { .. });
+        match diff {
+            Diff::Cell {
+                sheet,
+                row,
+                column,
+                old_value,
+                new_value,
+            } => {
+                let value = if undo { old_value } else { new_value };
+                self.touch_cell(*sheet, *row, *column);
+                let worksheet = self.workbook.worksheet_mut(*sheet)?;
+                match value {
+                    Some(cell) => worksheet.update_cell(*row, *column, *cell.clone()),
+                    None => {
+                        if let Some(row_data) = worksheet.sheet_data.get_mut(row) {
+                            row_data.remove(column);
+                        }
+                    }
+                }
+                self.dependency_graph.mark_dirty(*sheet, *row, *column);
+                Ok(false)
+            }
+            Diff::SheetProperties {
+                sheet,
+                old_value,
+                new_value,
+            } => {
+                let value = if undo { old_value } else { new_value };
+                set_sheet_properties(self.workbook.worksheet_mut(*sheet)?, value);
+                // The legacy array formulas might be different
+                self.dependency_graph.invalidate();
+                Ok(false)
+            }
+            Diff::SheetName {
+                sheet,
+                old_value,
+                new_value,
+            } => {
+                let (old_name, new_name) = if undo {
+                    (new_value, old_value)
+                } else {
+                    (old_value, new_value)
+                };
+                self.workbook.worksheet_mut(*sheet)?.set_name(new_name);
+                self.push_event(ModelEvent::SheetRenamed {
+                    sheet: *sheet,
+                    old_name: old_name.clone(),
+                    new_name: new_name.clone(),
+                });
+                Ok(true)
+            }
+            Diff::SharedFormulas {
+                sheet,
+                old_value,
+                new_value,
+            } => {
+                let value = if undo { old_value } else { new_value };
+                // Formulas added after the change are kept, cells might still use them
+                let shared_formulas = &mut self.workbook.worksheet_mut(*sheet)?.shared_formulas;
+                for (index, formula) in value.iter().enumerate() {
+                    match shared_formulas.get_mut(index) {
+                        Some(shared_formula) => *shared_formula = formula.clone(),
+                        None => shared_formulas.push(formula.clone()),
+                    }
+                }
+                Ok(true)
+            }
+            Diff::DefinedNames {
+                old_value,
+                new_value,
+            } => {
+                let value = if undo { old_value } else { new_value };
+                self.workbook.defined_names = value.clone();
+                Ok(true)
+            }
+            Diff::Tables {
+                old_value,
+                new_value,
+            } => {
+                let value = if undo { old_value } else { new_value };
+                self.workbook.tables = value.clone();
+                Ok(true)
+            }
+            Diff::CalculationSettings {
+                old_value,
+                new_value,
+            } => {
+                let value = if undo { old_value } else { new_value };
+                self.workbook.settings.calculation = value.clone();
+                self.dependency_graph.invalidate();
+                Ok(false)
+            }
+            Diff::Currency {
+                old_value,
+                new_value,
+            } => {
+                let value = if undo { old_value } else { new_value };
+                self.locale.currency = value.clone();
+                self.dependency_graph.invalidate();
+                Ok(false)
+            }
+            Diff::InsertSheet { sheet, worksheet } | Diff::DeleteSheet { sheet, worksheet } => {
+                let insert = undo == is_deletion;
+                let index = *sheet as usize;
+                if insert {
+                    if index > self.workbook.worksheets.len() {
+                        return Err("Sheet index out of range".to_string());
+                    }
+                    self.workbook
+                        .worksheets
+                        .insert(index, worksheet.as_ref().clone());
+                    self.push_event(ModelEvent::SheetAdded { sheet: *sheet });
+                } else {
+                    if index >= self.workbook.worksheets.len() {
+                        return Err("Sheet index out of range".to_string());
+                    }
+                    // Keep the sheet as it is now, with all the formulas added to it
+                    **worksheet = self.workbook.worksheets.remove(index);
+                    self.push_event(ModelEvent::SheetDeleted { sheet: *sheet });
+                }
+                Ok(true)
+            }
+            Diff::MoveSheet { sheet, new_index } => {
+                let (from, to) = if undo {
+                    (*new_index, *sheet)
+                } else {
+                    (*sheet, *new_index)
+                };
+                self.move_worksheet(from, to)?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Undoes or redoes a list of diffs. Undo goes from the last diff to the first.
+    fn apply_diffs(&mut self, diffs: &mut [Diff], undo: bool) -> Result<(), String> {
+        let order: Vec<usize> = if undo {
+            (0..diffs.len()).rev().collect()
+        } else {
+            (0..diffs.len()).collect()
+        };
+        self.history.depth += 1;
+        let mut reset = false;
+        let mut result = Ok(());
+        for index in order {
+            match self.apply_diff(&mut diffs[index], undo) {
+                Ok(needs_reset) => reset |= needs_reset,
+                Err(message) => {
+                    // The workbook might be left half way, parse everything again
+                    reset = true;
+                    result = Err(message);
+                    break;
+                }
+            }
+        }
+        if reset {
+            self.reset_parsed_structures();
+        }
+        self.history.depth -= 1;
+        result
+    }
+
+    /// Returns true if there are changes to undo
+    pub fn can_undo(&self) -> bool {
+        !self.history.undo_stack.is_empty()
+            || matches!(&self.history.transaction, Some(diffs) if !diffs.is_empty())
+    }
+
+    /// Returns true if there are changes to redo
+    pub fn can_redo(&self) -> bool {
+        !self.history.redo_stack.is_empty()
+    }
+
+    /// Undoes the last change to the model, or the last transaction.
+    /// An open transaction is ended first. Does nothing if there is nothing to undo.
+    /// As with any other change, the model needs to be evaluated afterwards.
+    pub fn undo(&mut self) -> Result<(), String> {
+        self.history.end_transaction();
+        if let Some(mut diffs) = self.history.undo_stack.pop() {
+            let result = self.apply_diffs(&mut diffs, true);
+            self.history.redo_stack.push(diffs);
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Redoes the last change that was undone
+    pub fn redo(&mut self) -> Result<(), String> {
+        self.history.end_transaction();
+        if let Some(mut diffs) = self.history.redo_stack.pop() {
+            let result = self.apply_diffs(&mut diffs, false);
+            self.history.undo_stack.push(diffs);
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Groups all the changes until `end_transaction` is called, so they are undone together
+    pub fn begin_transaction(&mut self) {
+        if self.history.transaction.is_none() {
+            self.history.transaction = Some(Vec::new());
+        }
+    }
+
+    /// Ends the transaction started with `begin_transaction`
+    pub fn end_transaction(&mut self) {
+        self.history.end_transaction();
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{
+    calc_result::{CalcResult, CellReference},
+    expressions::token::Error,
+    history::ChangeScope,
+    model::Model,
+    types::Cell,
+};
+
+impl Model {
+    /// Returns the value of a formula that is read while being evaluated (a circular reference)
+    /// when iterative calculation is enabled: its value in the previous iteration.
+    /// Formulas that were never computed are zero.
+    pub(crate) fn get_previous_iteration_value(&self, cell_reference: CellReference) -> CalcResult {
+        let CellReference { sheet, row, column } = cell_reference;
+        match self.get_cell(sheet, row, column) {
+            None
+            | Some(Cell::CellFormula { .. })
+            | Some(Cell::CellFormulaError {
+                ei: Error::CIRC, ..
+            }) => CalcResult::Number(0.0),
+            Some(cell) => self.get_cell_value(cell, cell_reference),
+        }
+    }
+
+    /// Recomputes the formulas in circular references until their values converge.
+    /// Only the groups of formulas with a cell in `evaluated` are recomputed.
+    /// Does nothing unless iterative calculation is enabled.
+    pub(crate) fn evaluate_circular_references(&mut self, evaluated: &[CellReference]) {
+        let settings = self.workbook.settings.calculation.clone();
+        if !settings.iterative {
+            return;
+        }
+        for group in self.get_circular_groups() {
+            if !group.iter().any(|cell| evaluated.contains(cell)) {
+                continue;
+            }
+            // The first evaluation of the group counts as the first iteration
+            for _ in 1..settings.max_iterations {
+                let previous: Vec<Option<Cell>> = group
+                    .iter()
+                    .map(|cell| self.get_cell_content(*cell))
+                    .collect();
+                for cell in &group {
+                    self.cells.remove(&(cell.sheet, cell.row, cell.column));
+                }
+                for cell in &group {
+                    self.evaluate_cell(*cell);
+                }
+                let mut converged = true;
+                for (cell, previous) in group.iter().zip(previous) {
+                    let current = self.get_cell_content(*cell);
+                    converged &= match (previous, current) {
+                        (
+                            Some(Cell::CellFormulaNumber { v: previous, .. }),
+                            Some(Cell::CellFormulaNumber { v: current, .. }),
+                        ) => (current - previous).abs() <= settings.max_change,
+                        (previous, current) => previous == current,
+                    };
+                }
+                if converged {
+                    break;
+                }
+            }
+            // Formulas depending on the group were computed with the values of the first iteration
+            let dependents: Vec<CellReference> = self
+                .get_dependent_cells(&group)
+                .into_iter()
+                .filter(|cell| !group.contains(cell))
+                .collect();
+            for cell in &dependents {
+                self.cells.remove(&(cell.sheet, cell.row, cell.column));
+            }
+            for cell in dependents {
+                self.evaluate_cell(cell);
+            }
+        }
+    }
+
+    fn get_cell_content(&self, cell: CellReference) -> Option<Cell> {
+        self.get_cell(cell.sheet, cell.row, cell.column).cloned()
+    }
+
+    /// Enables or disables the iterative calculation of circular references.
+    /// Formulas in a cycle are recomputed up to `max_iterations` times or until
+    /// no value changes by more than `max_change`.
+    pub fn set_iterative_calculation(
+        &mut self,
+        iterative: bool,
+        max_iterations: i32,
+        max_change: f64,
+    ) -> Result<(), String> {
+        self.record_change(ChangeScope::calculation_settings(), |model| {
+            if max_iterations < 1 {
+                return Err("The maximum number of iterations must be at least 1".to_string());
+            }
+            if !max_change.is_finite() || max_change < 0.0 {
+                return Err("The maximum change must be a non negative number".to_string());
+            }
+            let calculation = &mut model.workbook.settings.calculation;
+            calculation.iterative = iterative;
+            calculation.max_iterations = max_iterations;
+            calculation.max_change = max_change;
+            model.dependency_graph.invalidate();
+            Ok(())
+        })
+    }
+}
@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 
 use std::collections::HashMap;
 
+use crate::functions::Function;
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Booleans {
     #[serde(rename = "true")]
@@ -32,6 +34,36 @@ pub struct Errors {
 pub struct Language {
     pub booleans: Booleans,
     pub errors: Errors,
+    /// Names of the functions in this language by their English name.
+    /// Functions not listed here have the English name.
+    #[serde(default)]
+    pub functions: HashMap<String, String>,
+}
+
+impl Language {
+    /// Returns the name of `function` in this language
+    pub fn get_function_name(&self, function: &Function) -> String {
+        let name = function.to_string();
+        match self.functions.get(&name) {
+            Some(local_name) => local_name.to_string(),
+            None => name,
+        }
+    }
+
+    /// Returns the function called `name` in this language. The English names of the
+    /// functions with a translation are not recognized.
+    pub fn get_function(&self, name: &str) -> Option<Function> {
+        let name_upper = name.to_uppercase();
+        if let Some((english_name, _)) = self
+            .functions
+            .iter()
+            .find(|(_, local_name)| **local_name == name_upper)
+        {
+            return Function::get_function(english_name);
+        }
+        Function::get_function(name)
+            .filter(|function| !self.functions.contains_key(&function.to_string()))
+    }
 }
 
 static LANGUAGES: Lazy<HashMap<String, Language>> = Lazy::new(|| {
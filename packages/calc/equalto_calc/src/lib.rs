@@ -1,25 +1,44 @@
 #![deny(clippy::unwrap_used)]
 pub mod calc_result;
 pub mod cell;
+pub mod evaluation_steps;
+pub mod events;
 pub mod expressions;
+pub mod external;
 pub mod formatter;
+pub mod goal_seek;
 pub mod language;
 pub mod locale;
 pub mod model;
 pub mod new_empty;
 pub mod number_format;
+pub mod solver;
+pub mod trace;
 pub mod types;
 pub mod worksheet;
 
 mod functions;
 
 mod actions;
+mod array_formulas;
+mod arrays;
 mod cast;
 mod constants;
+mod data_tables;
+mod defined_names;
+mod dependencies;
+mod reference_operators;
+mod sheet_spans;
+mod spill;
 mod styles;
+mod tables;
 
 mod diffs;
+mod history;
 mod implicit_intersection;
+mod iterative_calculation;
+#[cfg(not(target_arch = "wasm32"))]
+mod parallel;
 
 mod units;
 mod utils;
@@ -10,7 +10,7 @@ pub struct Locale {
     pub currency: Currency,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct Currency {
     pub iso: String,
     pub symbol: String,
@@ -53,6 +53,33 @@ pub struct NumbersSymbols {
     pub time_separator: String,
 }
 
+impl Locale {
+    /// The decimal separator of the numbers in formulas
+    pub fn formula_decimal_separator(&self) -> char {
+        self.numbers.symbols.decimal.chars().next().unwrap_or('.')
+    }
+
+    /// The separator of the arguments of functions in formulas: a comma, or the list symbol
+    /// of the locale (`;`) if the comma is the decimal separator
+    pub fn formula_list_separator(&self) -> char {
+        if self.formula_decimal_separator() == ',' {
+            self.numbers.symbols.list.chars().next().unwrap_or(';')
+        } else {
+            ','
+        }
+    }
+
+    /// The separator of the columns of arrays in formulas: a comma, or a period if the comma
+    /// is the decimal separator. Rows are always separated by `;`.
+    pub fn formula_array_column_separator(&self) -> char {
+        if self.formula_decimal_separator() == ',' {
+            '.'
+        } else {
+            ','
+        }
+    }
+}
+
 // See: https://cldr.unicode.org/translation/number-currency-formats/number-and-currency-patterns
 #[derive(Serialize, Deserialize, Clone)]
 pub struct CurrencyFormats {
@@ -1,15 +1,21 @@
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use std::vec::Vec;
 
 use crate::{
+    arrays::BinaryOperator,
     calc_result::{CalcResult, CellReference, Range},
     cell::CellValue,
     constants,
-    expressions::token::{Error, OpCompare, OpProduct, OpSum, OpUnary},
+    dependencies::{CellKey, DependencyGraph},
+    evaluation_steps::EvaluationSteps,
+    events::Events,
+    expressions::token::Error,
     expressions::{
+        lexer::LexerMode,
         parser::move_formula::{move_formula, MoveContext},
         token::get_error_by_name,
         types::*,
@@ -17,21 +23,24 @@ use crate::{
     },
     expressions::{
         parser::{
-            stringify::{to_rc_format, to_string},
+            format::{to_formatted_string, FormatOptions},
+            stringify::{to_localized_string, to_rc_format, to_string, to_string_in_style},
             Node, Parser,
         },
         utils::is_valid_column_number,
     },
+    external::{ExternalResolver, ExternalSnapshot},
     formatter::{
         format::{format_number, parse_formatted_number},
         lexer::is_likely_date_number_format,
     },
-    functions::util::compare_values,
+    history::{ChangeScope, History},
     implicit_intersection::implicit_intersection,
     language::{get_language, Language},
-    locale::{get_locale, Currency, Locale},
+    locale::{get_locale, get_locale_fix, Currency, Locale},
     types::*,
     utils as common,
+    worksheet::WorksheetDimension,
 };
 
 pub use chrono_tz::Tz;
@@ -74,16 +83,17 @@ pub enum CellState {
 pub enum ParsedDefinedName {
     CellReference(CellReference),
     RangeReference(Range),
+    /// Constants, formulas and references that are not absolute.
+    /// Relative references are relative to A1 and are shifted to the cell using the name.
+    Formula(Node),
     InvalidDefinedNameFormula,
-    // TODO: Support constants in defined names
-    // TODO: Support formulas in defined names
-    // TODO: Support tables in defined names
 }
 
 /// A model includes:
 ///     * A Workbook: An internal representation of and Excel workbook
 ///     * Parsed Formulas: All the formulas in the workbook are parsed here (runtime only)
 ///     * A list of cells with its status (evaluating, evaluated, not evaluated)
+///     * A dependency graph of the formulas, used to recompute only what changed (runtime only)
 #[derive(Clone)]
 pub struct Model {
     pub workbook: Workbook,
@@ -91,6 +101,29 @@ pub struct Model {
     pub parsed_defined_names: HashMap<(Option<u32>, String), ParsedDefinedName>,
     pub parser: Parser,
     pub cells: HashMap<(u32, i32, i32), CellState>,
+    pub(crate) dependency_graph: DependencyGraph,
+    /// Names bound by LET in the formula being evaluated and their values, innermost last
+    pub(crate) local_variables: Vec<(String, CalcResult)>,
+    /// Number of LAMBDA calls being evaluated
+    pub(crate) lambda_depth: usize,
+    /// The result of every node evaluated while recording the steps of a formula
+    pub(crate) evaluation_steps: Option<EvaluationSteps>,
+    /// Number of threads used to evaluate independent groups of formulas, 1 by default
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) evaluation_threads: usize,
+    /// Changes that can be undone and redone
+    pub(crate) history: History,
+    /// Changes reported to the user of the model
+    pub(crate) events: Events,
+    /// Where the values of references to other workbooks come from
+    pub(crate) external_resolver: Option<Arc<dyn ExternalResolver>>,
+    /// The values cached in `workbook.external_links`, indexed
+    pub(crate) cached_external_values: ExternalSnapshot,
+    /// On the threads of a parallel evaluation, the model all of them read from.
+    /// `workbook` then only holds the cells written by the thread, see `parallel.rs`.
+    pub(crate) shared: Option<Arc<Model>>,
+    /// The cells of `shared` removed by the thread of a parallel evaluation
+    pub(crate) removed_cells: HashSet<CellKey>,
     pub locale: Locale,
     pub language: Language,
     pub tz: Tz,
@@ -190,6 +223,14 @@ impl Model {
                     },
                 }
             }
+            Node::TableReferenceKind {
+                table_name,
+                specifier,
+                table_reference,
+            } => match self.get_table_range(table_name, specifier, table_reference, cell) {
+                Ok(Range { left, right }) => CalcResult::Range { left, right },
+                Err(error) => error,
+            },
             _ => self.evaluate_node_in_context(node, cell),
         }
     }
@@ -232,33 +273,86 @@ impl Model {
         }
     }
 
+    fn evaluate_binary_operator(
+        &mut self,
+        operator: BinaryOperator,
+        left: &Node,
+        right: &Node,
+        cell: CellReference,
+    ) -> CalcResult {
+        let l = self.evaluate_operand(left, cell);
+        if l.is_error() {
+            return l;
+        }
+        let r = self.evaluate_operand(right, cell);
+        self.apply_binary_operator(&operator, l, r, cell)
+    }
+
+    /// Returns the defined name `name` as seen from a formula in `sheet`:
+    /// the local name of the sheet if there is one, otherwise the global name
+    pub(crate) fn get_parsed_defined_name(
+        &self,
+        name: &str,
+        sheet: u32,
+    ) -> Option<&ParsedDefinedName> {
+        let name = name.to_lowercase();
+        let parsed_defined_names = &self.shared_model().parsed_defined_names;
+        parsed_defined_names
+            .get(&(Some(sheet), name.clone()))
+            .or_else(|| parsed_defined_names.get(&(None, name)))
+    }
+
+    /// Evaluates the name `name`: a name bound by LET or LAMBDA, or a defined name
+    fn evaluate_variable(&mut self, defined_name: &str, cell: CellReference) -> CalcResult {
+        let name = defined_name.to_lowercase();
+        if let Some((_, value)) = self.local_variables.iter().rev().find(|(n, _)| *n == name) {
+            return value.clone();
+        }
+        match self.get_parsed_defined_name(defined_name, cell.sheet) {
+            Some(ParsedDefinedName::CellReference(reference)) => {
+                let reference = *reference;
+                self.evaluate_cell(reference)
+            }
+            Some(ParsedDefinedName::RangeReference(range)) => CalcResult::Range {
+                left: range.left,
+                right: range.right,
+            },
+            Some(ParsedDefinedName::Formula(node)) => {
+                let node = node.clone();
+                // Names bound by LET are not visible in the defined name
+                let local_variables = std::mem::take(&mut self.local_variables);
+                let result = self.evaluate_node_in_context(&node, cell);
+                self.local_variables = local_variables;
+                result
+            }
+            Some(ParsedDefinedName::InvalidDefinedNameFormula) => CalcResult::new_error(
+                Error::NAME,
+                cell,
+                format!("Defined name \"{}\" is not valid.", defined_name),
+            ),
+            None => CalcResult::new_error(
+                Error::NAME,
+                cell,
+                format!("Defined name \"{}\" not found.", defined_name),
+            ),
+        }
+    }
+
     pub(crate) fn evaluate_node_in_context(
         &mut self,
         node: &Node,
         cell: CellReference,
     ) -> CalcResult {
+        let result = self.evaluate_node(node, cell);
+        self.record_evaluation_step(node, &result);
+        result
+    }
+
+    fn evaluate_node(&mut self, node: &Node, cell: CellReference) -> CalcResult {
         use Node::*;
         match node {
             OpSumKind { kind, left, right } => {
-                // In the future once the feature try trait stabilizes we could use the '?' operator for this :)
-                // See: https://play.rust-lang.org/?version=nightly&mode=debug&edition=2018&gist=236044e8321a1450988e6ffe5a27dab5
-                let l = match self.get_number(left, cell) {
-                    Ok(f) => f,
-                    Err(s) => {
-                        return s;
-                    }
-                };
-                let r = match self.get_number(right, cell) {
-                    Ok(f) => f,
-                    Err(s) => {
-                        return s;
-                    }
-                };
-                let result = match kind {
-                    OpSum::Add => l + r,
-                    OpSum::Minus => l - r,
-                };
-                CalcResult::Number(result)
+                self.evaluate_binary_operator(BinaryOperator::Sum(kind.clone()), left, right, cell)
             }
             NumberKind(value) => CalcResult::Number(*value),
             StringKind(value) => CalcResult::String(value.replace(r#""""#, r#"""#)),
@@ -288,7 +382,83 @@ impl Model {
             WrongReferenceKind { .. } => {
                 CalcResult::new_error(Error::REF, cell, "Wrong reference".to_string())
             }
+            ExternalReferenceKind {
+                workbook,
+                sheet_name,
+                absolute_row,
+                absolute_column,
+                row,
+                column,
+            } => {
+                let row = if *absolute_row { *row } else { *row + cell.row };
+                let column = if *absolute_column {
+                    *column
+                } else {
+                    *column + cell.column
+                };
+                self.evaluate_external_cell(workbook, sheet_name, row, column, cell)
+            }
+            ExternalRangeKind {
+                workbook,
+                sheet_name,
+                absolute_row1,
+                absolute_column1,
+                row1,
+                column1,
+                absolute_row2,
+                absolute_column2,
+                row2,
+                column2,
+            } => {
+                // Ranges of other workbooks are arrays of their values
+                let row1 = if *absolute_row1 {
+                    *row1
+                } else {
+                    *row1 + cell.row
+                };
+                let row2 = if *absolute_row2 {
+                    *row2
+                } else {
+                    *row2 + cell.row
+                };
+                let column1 = if *absolute_column1 {
+                    *column1
+                } else {
+                    *column1 + cell.column
+                };
+                let column2 = if *absolute_column2 {
+                    *column2
+                } else {
+                    *column2 + cell.column
+                };
+                CalcResult::Array(
+                    (row1..=row2)
+                        .map(|row| {
+                            (column1..=column2)
+                                .map(|column| {
+                                    self.evaluate_external_cell(
+                                        workbook, sheet_name, row, column, cell,
+                                    )
+                                })
+                                .collect()
+                        })
+                        .collect(),
+                )
+            }
+            // Only the functions that aggregate values read spans of sheets: SUM(Jan:Dec!B5)
+            SheetSpanReferenceKind { .. } | SheetSpanRangeKind { .. } => CalcResult::new_error(
+                Error::VALUE,
+                cell,
+                "References to several sheets are only valid in aggregate functions".to_string(),
+            ),
             OpRangeKind { left, right } => self.get_range(left, right, cell),
+            OpIntersectKind { left, right } => self.get_intersection(left, right, cell),
+            OpUnionKind(items) => self.get_union(items, cell),
+            TableReferenceKind {
+                table_name,
+                specifier,
+                table_reference,
+            } => self.evaluate_table_reference(table_name, specifier, table_reference, cell),
             WrongRangeKind { .. } => {
                 CalcResult::new_error(Error::REF, cell, "Wrong range".to_string())
             }
@@ -332,171 +502,66 @@ impl Model {
                 },
             },
             OpConcatenateKind { left, right } => {
-                let l = match self.get_string(left, cell) {
-                    Ok(f) => f,
-                    Err(s) => {
-                        return s;
-                    }
-                };
-                let r = match self.get_string(right, cell) {
-                    Ok(f) => f,
-                    Err(s) => {
-                        return s;
-                    }
-                };
-                let result = format!("{}{}", l, r);
-                CalcResult::String(result)
-            }
-            OpProductKind { kind, left, right } => {
-                let l = match self.get_number(left, cell) {
-                    Ok(f) => f,
-                    Err(s) => {
-                        return s;
-                    }
-                };
-                let r = match self.get_number(right, cell) {
-                    Ok(f) => f,
-                    Err(s) => {
-                        return s;
-                    }
-                };
-                let result = match kind {
-                    OpProduct::Times => l * r,
-                    OpProduct::Divide => {
-                        if r == 0.0 {
-                            return CalcResult::new_error(
-                                Error::DIV,
-                                cell,
-                                "Divide by Zero".to_string(),
-                            );
-                        }
-                        l / r
-                    }
-                };
-                CalcResult::Number(result)
+                self.evaluate_binary_operator(BinaryOperator::Concatenate, left, right, cell)
             }
+            OpProductKind { kind, left, right } => self.evaluate_binary_operator(
+                BinaryOperator::Product(kind.clone()),
+                left,
+                right,
+                cell,
+            ),
             OpPowerKind { left, right } => {
-                let l = match self.get_number(left, cell) {
-                    Ok(f) => f,
-                    Err(s) => {
-                        return s;
-                    }
-                };
-                let r = match self.get_number(right, cell) {
-                    Ok(f) => f,
-                    Err(s) => {
-                        return s;
-                    }
-                };
-                // Deal with errors properly
-                CalcResult::Number(l.powf(r))
+                self.evaluate_binary_operator(BinaryOperator::Power, left, right, cell)
             }
             FunctionKind { kind, args } => self.evaluate_function(kind, args, cell),
-            InvalidFunctionKind { name, args: _ } => {
+            InvalidFunctionKind { name, args } => {
+                // Names bound by LET or defined names might hold a LAMBDA
+                let lower_name = name.to_lowercase();
+                if self.local_variables.iter().any(|(n, _)| *n == lower_name)
+                    || self.get_parsed_defined_name(name, cell.sheet).is_some()
+                {
+                    let value = self.evaluate_variable(name, cell);
+                    return self.call_function_value(value, args, cell);
+                }
                 CalcResult::new_error(Error::ERROR, cell, format!("Invalid function: {}", name))
             }
-            ArrayKind(_) => {
-                // TODO: NOT IMPLEMENTED
-                CalcResult::new_error(Error::NIMPL, cell, "Arrays not implemented".to_string())
+            CallKind { function, args } => {
+                let value = self.evaluate_node_in_context(function, cell);
+                self.call_function_value(value, args, cell)
             }
-            VariableKind(defined_name) => {
-                let parsed_defined_name = self
-                    .parsed_defined_names
-                    .get(&(Some(cell.sheet), defined_name.to_lowercase())) // try getting local defined name
-                    .or_else(|| {
-                        self.parsed_defined_names
-                            .get(&(None, defined_name.to_lowercase()))
-                    }); // fallback to global
-
-                if let Some(parsed_defined_name) = parsed_defined_name {
-                    match parsed_defined_name {
-                        ParsedDefinedName::CellReference(reference) => {
-                            self.evaluate_cell(*reference)
-                        }
-                        ParsedDefinedName::RangeReference(range) => CalcResult::Range {
-                            left: range.left,
-                            right: range.right,
-                        },
-                        ParsedDefinedName::InvalidDefinedNameFormula => CalcResult::new_error(
-                            Error::NIMPL,
-                            cell,
-                            format!("Defined name \"{}\" is not a reference.", defined_name),
-                        ),
-                    }
-                } else {
-                    CalcResult::new_error(
-                        Error::NAME,
-                        cell,
-                        format!("Defined name \"{}\" not found.", defined_name),
-                    )
-                }
-            }
-            CompareKind { kind, left, right } => {
-                let l = self.evaluate_node_in_context(left, cell);
-                if l.is_error() {
-                    return l;
-                }
-                let r = self.evaluate_node_in_context(right, cell);
-                if r.is_error() {
-                    return r;
-                }
-                let compare = compare_values(&l, &r);
-                match kind {
-                    OpCompare::Equal => {
-                        if compare == 0 {
-                            CalcResult::Boolean(true)
-                        } else {
-                            CalcResult::Boolean(false)
-                        }
-                    }
-                    OpCompare::LessThan => {
-                        if compare == -1 {
-                            CalcResult::Boolean(true)
-                        } else {
-                            CalcResult::Boolean(false)
-                        }
-                    }
-                    OpCompare::GreaterThan => {
-                        if compare == 1 {
-                            CalcResult::Boolean(true)
-                        } else {
-                            CalcResult::Boolean(false)
-                        }
-                    }
-                    OpCompare::LessOrEqualThan => {
-                        if compare < 1 {
-                            CalcResult::Boolean(true)
-                        } else {
-                            CalcResult::Boolean(false)
-                        }
-                    }
-                    OpCompare::GreaterOrEqualThan => {
-                        if compare > -1 {
-                            CalcResult::Boolean(true)
-                        } else {
-                            CalcResult::Boolean(false)
-                        }
-                    }
-                    OpCompare::NonEqual => {
-                        if compare != 0 {
-                            CalcResult::Boolean(true)
-                        } else {
-                            CalcResult::Boolean(false)
+            ArrayKind(rows) => {
+                let mut array = Vec::with_capacity(rows.len());
+                for row in rows {
+                    let mut values = Vec::with_capacity(row.len());
+                    for item in row {
+                        match self.evaluate_node_in_context(item, cell) {
+                            CalcResult::Range { .. }
+                            | CalcResult::Array(_)
+                            | CalcResult::Lambda(_) => {
+                                return CalcResult::new_error(
+                                    Error::VALUE,
+                                    cell,
+                                    "Arrays can only contain values".to_string(),
+                                );
+                            }
+                            value => values.push(value),
                         }
                     }
+                    array.push(values);
                 }
+                CalcResult::Array(array)
             }
+            SpillRangeKind(anchor) => self.get_spill_range(anchor, cell),
+            VariableKind(defined_name) => self.evaluate_variable(defined_name, cell),
+            CompareKind { kind, left, right } => self.evaluate_binary_operator(
+                BinaryOperator::Compare(kind.clone()),
+                left,
+                right,
+                cell,
+            ),
             UnaryKind { kind, right } => {
-                let r = match self.get_number(right, cell) {
-                    Ok(f) => f,
-                    Err(s) => {
-                        return s;
-                    }
-                };
-                match kind {
-                    OpUnary::Minus => CalcResult::Number(-r),
-                    OpUnary::Percentage => CalcResult::Number(r / 100.0),
-                }
+                let value = self.evaluate_operand(right, cell);
+                self.apply_unary_operator(kind, value, cell)
             }
             ErrorKind(kind) => CalcResult::new_error(kind.clone(), cell, "".to_string()),
             ParseErrorKind {
@@ -522,13 +587,15 @@ impl Model {
         Ok(format!("{}!{}{}", sheet.name, column, cell_reference.row))
     }
     /// Sets `result` in the cell given by `sheet` sheet index, row and column
-    /// Note that will panic if the cell does not exist
     /// It will do nothing if the cell does not have a formula
     fn set_cell_value(&mut self, cell_reference: CellReference, result: &CalcResult) {
         let CellReference { sheet, column, row } = cell_reference;
-        let cell = &self.workbook.worksheets[sheet as usize].sheet_data[&row][&column];
-        let s = cell.get_style();
-        if let Some(f) = cell.get_formula() {
+        self.touch_cell(sheet, row, column);
+        let (s, formula) = match self.get_cell(sheet, row, column) {
+            Some(cell) => (cell.get_style(), cell.get_formula()),
+            None => return,
+        };
+        if let Some(f) = formula {
             match result {
                 CalcResult::Number(value) => {
                     // safety belt
@@ -543,32 +610,32 @@ impl Model {
                             },
                         );
                     }
-                    *self.workbook.worksheets[sheet as usize]
-                        .sheet_data
-                        .get_mut(&row)
-                        .expect("expected a row")
-                        .get_mut(&column)
-                        .expect("expected a column") = Cell::CellFormulaNumber { f, s, v: *value };
+                    self.write_cell(
+                        sheet,
+                        row,
+                        column,
+                        Cell::CellFormulaNumber { f, s, v: *value },
+                    );
                 }
                 CalcResult::String(value) => {
-                    *self.workbook.worksheets[sheet as usize]
-                        .sheet_data
-                        .get_mut(&row)
-                        .expect("expected a row")
-                        .get_mut(&column)
-                        .expect("expected a column") = Cell::CellFormulaString {
-                        f,
-                        s,
-                        v: value.clone(),
-                    };
+                    self.write_cell(
+                        sheet,
+                        row,
+                        column,
+                        Cell::CellFormulaString {
+                            f,
+                            s,
+                            v: value.clone(),
+                        },
+                    );
                 }
                 CalcResult::Boolean(value) => {
-                    *self.workbook.worksheets[sheet as usize]
-                        .sheet_data
-                        .get_mut(&row)
-                        .expect("expected a row")
-                        .get_mut(&column)
-                        .expect("expected a column") = Cell::CellFormulaBoolean { f, s, v: *value };
+                    self.write_cell(
+                        sheet,
+                        row,
+                        column,
+                        Cell::CellFormulaBoolean { f, s, v: *value },
+                    );
                 }
                 CalcResult::Error {
                     error,
@@ -579,18 +646,18 @@ impl Model {
                         Ok(s) => s,
                         Err(_) => "".to_string(),
                     };
-                    *self.workbook.worksheets[sheet as usize]
-                        .sheet_data
-                        .get_mut(&row)
-                        .expect("expected a row")
-                        .get_mut(&column)
-                        .expect("expected a column") = Cell::CellFormulaError {
-                        f,
-                        s,
-                        o,
-                        m: message.to_string(),
-                        ei: error.clone(),
-                    };
+                    self.write_cell(
+                        sheet,
+                        row,
+                        column,
+                        Cell::CellFormulaError {
+                            f,
+                            s,
+                            o,
+                            m: message.to_string(),
+                            ei: error.clone(),
+                        },
+                    );
                 }
                 CalcResult::Range { left, right } => {
                     let range = Range {
@@ -606,45 +673,102 @@ impl Model {
                             Ok(s) => s,
                             Err(_) => "".to_string(),
                         };
-                        *self.workbook.worksheets[sheet as usize]
-                            .sheet_data
-                            .get_mut(&row)
-                            .expect("expected a row")
-                            .get_mut(&column)
-                            .expect("expected a column") = Cell::CellFormulaError {
-                            f,
-                            s,
-                            o,
-                            m: "Invalid reference".to_string(),
-                            ei: Error::VALUE,
-                        };
+                        self.write_cell(
+                            sheet,
+                            row,
+                            column,
+                            Cell::CellFormulaError {
+                                f,
+                                s,
+                                o,
+                                m: "Invalid reference".to_string(),
+                                ei: Error::VALUE,
+                            },
+                        );
                     }
                 }
                 CalcResult::EmptyCell | CalcResult::EmptyArg => {
-                    *self.workbook.worksheets[sheet as usize]
-                        .sheet_data
-                        .get_mut(&row)
-                        .expect("expected a row")
-                        .get_mut(&column)
-                        .expect("expected a column") = Cell::CellFormulaNumber { f, s, v: 0.0 };
+                    self.write_cell(sheet, row, column, Cell::CellFormulaNumber { f, s, v: 0.0 });
+                }
+                CalcResult::Lambda(_) => {
+                    let error = CalcResult::new_lambda_error(cell_reference);
+                    self.set_cell_value(cell_reference, &error);
+                }
+                CalcResult::Areas(_) => {
+                    let error = CalcResult::new_areas_error(cell_reference);
+                    self.set_cell_value(cell_reference, &error);
+                }
+                CalcResult::Array(array) => {
+                    // Arrays are spilled before reaching this point, only the top left value is kept
+                    let value = match array.first().and_then(|row| row.first()) {
+                        Some(value) => value.clone(),
+                        None => CalcResult::new_error(
+                            Error::CALC,
+                            cell_reference,
+                            "Empty array".to_string(),
+                        ),
+                    };
+                    self.set_cell_value(cell_reference, &value);
                 }
             }
         }
     }
 
     pub fn set_sheet_color(&mut self, sheet: u32, color: &str) -> Result<(), String> {
-        let mut worksheet = self.workbook.worksheet_mut(sheet)?;
-        if color.is_empty() {
-            worksheet.color = None;
-            return Ok(());
-        } else if common::is_valid_hex_color(color) {
-            worksheet.color = Some(color.to_string());
-            return Ok(());
-        }
-        Err(format!("Invalid color: {}", color))
+        self.record_change(ChangeScope::sheet(sheet), |model| {
+            let worksheet = model.workbook.worksheet_mut(sheet)?;
+            if color.is_empty() {
+                worksheet.color = None;
+                return Ok(());
+            } else if common::is_valid_hex_color(color) {
+                worksheet.color = Some(color.to_string());
+                return Ok(());
+            }
+            Err(format!("Invalid color: {}", color))
+        })
+    }
+
+    /// Sets the width of a column of the sheet
+    pub fn set_column_width(&mut self, sheet: u32, column: i32, width: f64) -> Result<(), String> {
+        self.record_change(ChangeScope::sheet(sheet), |model| {
+            model
+                .workbook
+                .worksheet_mut(sheet)?
+                .set_column_width(column, width)
+        })
+    }
+
+    /// Sets the height of a row of the sheet
+    pub fn set_row_height(&mut self, sheet: u32, row: i32, height: f64) -> Result<(), String> {
+        self.record_change(ChangeScope::sheet(sheet), |model| {
+            model
+                .workbook
+                .worksheet_mut(sheet)?
+                .set_row_height(row, height)
+        })
     }
 
-    fn get_cell_value(&self, cell: &Cell, cell_reference: CellReference) -> CalcResult {
+    /// Sets the number of rows frozen at the top of the sheet
+    pub fn set_frozen_rows(&mut self, sheet: u32, frozen_rows: i32) -> Result<(), String> {
+        self.record_change(ChangeScope::sheet(sheet), |model| {
+            model
+                .workbook
+                .worksheet_mut(sheet)?
+                .set_frozen_rows(frozen_rows)
+        })
+    }
+
+    /// Sets the number of columns frozen at the left of the sheet
+    pub fn set_frozen_columns(&mut self, sheet: u32, frozen_columns: i32) -> Result<(), String> {
+        self.record_change(ChangeScope::sheet(sheet), |model| {
+            model
+                .workbook
+                .worksheet_mut(sheet)?
+                .set_frozen_columns(frozen_columns)
+        })
+    }
+
+    pub(crate) fn get_cell_value(&self, cell: &Cell, cell_reference: CellReference) -> CalcResult {
         use Cell::*;
         match cell {
             EmptyCell { .. } => CalcResult::EmptyCell,
@@ -655,7 +779,12 @@ impl Model {
                 CalcResult::new_error(ei.clone(), cell_reference, message)
             }
             SharedString { si, .. } => {
-                if let Some(s) = self.workbook.shared_strings.get(*si as usize) {
+                if let Some(s) = self
+                    .shared_model()
+                    .workbook
+                    .shared_strings
+                    .get(*si as usize)
+                {
                     CalcResult::String(s.clone())
                 } else {
                     let message = "Invalid shared string".to_string();
@@ -667,9 +796,13 @@ impl Model {
                 origin: cell_reference,
                 message: "Unevaluated formula".to_string(),
             },
-            CellFormulaBoolean { v, .. } => CalcResult::Boolean(*v),
-            CellFormulaNumber { v, .. } => CalcResult::Number(*v),
-            CellFormulaString { v, .. } => CalcResult::String(v.clone()),
+            CellFormulaBoolean { v, .. } | SpillBoolean { v, .. } => CalcResult::Boolean(*v),
+            CellFormulaNumber { v, .. } | SpillNumber { v, .. } => CalcResult::Number(*v),
+            SpillError { ei, .. } => {
+                let message = ei.to_localized_error_string(&self.language);
+                CalcResult::new_error(ei.clone(), cell_reference, message)
+            }
+            CellFormulaString { v, .. } | SpillString { v, .. } => CalcResult::String(v.clone()),
             CellFormulaError { ei, o, m, .. } => {
                 if let Some(cell_reference) = self.parse_reference(o) {
                     CalcResult::new_error(ei.clone(), cell_reference, m.clone())
@@ -691,19 +824,130 @@ impl Model {
         worksheet.is_empty_cell(row, column)
     }
 
-    pub(crate) fn evaluate_cell(&mut self, cell_reference: CellReference) -> CalcResult {
-        let row_data = match self.workbook.worksheets[cell_reference.sheet as usize]
+    /// Returns the model holding the cells, the parsed formulas and the dependency graph:
+    /// `self`, except on the threads of a parallel evaluation
+    pub(crate) fn shared_model(&self) -> &Model {
+        self.shared.as_deref().unwrap_or(self)
+    }
+
+    /// Returns the content of a cell as seen by the evaluation
+    pub(crate) fn get_cell(&self, sheet: u32, row: i32, column: i32) -> Option<&Cell> {
+        let cell = self
+            .workbook
+            .worksheets
+            .get(sheet as usize)?
+            .cell(row, column);
+        match &self.shared {
+            Some(shared)
+                if cell.is_none() && !self.removed_cells.contains(&(sheet, row, column)) =>
+            {
+                shared.get_cell(sheet, row, column)
+            }
+            _ => cell,
+        }
+    }
+
+    /// Sets the content of a cell from the evaluation
+    pub(crate) fn write_cell(&mut self, sheet: u32, row: i32, column: i32, cell: Cell) {
+        self.removed_cells.remove(&(sheet, row, column));
+        self.workbook.worksheets[sheet as usize].update_cell(row, column, cell);
+    }
+
+    /// Removes a cell from the evaluation and returns what it held
+    pub(crate) fn take_cell(&mut self, sheet: u32, row: i32, column: i32) -> Option<Cell> {
+        let cell = self.workbook.worksheets[sheet as usize]
             .sheet_data
-            .get(&cell_reference.row)
-        {
-            Some(r) => r,
-            None => return CalcResult::EmptyCell,
+            .get_mut(&row)
+            .and_then(|row_data| row_data.remove(&column));
+        let key = (sheet, row, column);
+        match &self.shared {
+            Some(shared) if !self.removed_cells.contains(&key) => {
+                let cell = cell.or_else(|| shared.get_cell(sheet, row, column).cloned());
+                self.removed_cells.insert(key);
+                cell
+            }
+            _ => cell,
+        }
+    }
+
+    /// Returns the dimension of the sheet as seen by the evaluation, see `Worksheet::dimension`
+    pub(crate) fn get_sheet_dimension(&self, sheet: u32) -> Result<WorksheetDimension, String> {
+        let worksheet = self.workbook.worksheet(sheet)?;
+        let shared = match &self.shared {
+            Some(shared) => shared,
+            None => return Ok(worksheet.dimension()),
         };
-        let cell = match row_data.get(&cell_reference.column) {
-            Some(c) => c,
-            None => {
-                return CalcResult::EmptyCell;
+        let dimension = shared.get_sheet_dimension(sheet)?;
+        if worksheet.sheet_data.is_empty() {
+            return Ok(dimension);
+        }
+        let written = worksheet.dimension();
+        Ok(WorksheetDimension {
+            min_row: dimension.min_row.min(written.min_row),
+            max_row: dimension.max_row.max(written.max_row),
+            min_column: dimension.min_column.min(written.min_column),
+            max_column: dimension.max_column.max(written.max_column),
+        })
+    }
+
+    /// Returns whether the formula in the cell is being evaluated or has been evaluated
+    fn get_cell_state(&self, key: &CellKey) -> Option<&CellState> {
+        self.cells
+            .get(key)
+            .or_else(|| self.shared.as_ref()?.cells.get(key))
+    }
+
+    /// Evaluates the formulas `cell` (transitively) depends on, the most distant ones first.
+    /// Uses an explicit stack so that long chains of formulas cannot overflow the native stack:
+    /// once they are computed, evaluating `cell` only needs to read their values.
+    fn evaluate_precedents(&mut self, cell: CellReference) {
+        let root = (cell.sheet, cell.row, cell.column);
+        if self.get_cell_state(&root).is_some() {
+            return;
+        }
+        // Each cell is pushed twice: to expand its precedents and to evaluate it afterwards
+        let mut stack: Vec<(CellReference, bool)> = self
+            .get_formula_precedents(cell)
+            .into_iter()
+            .map(|precedent| (precedent, false))
+            .collect();
+        if stack.is_empty() {
+            return;
+        }
+        let mut visited = HashSet::from([root]);
+        while let Some((cell, expanded)) = stack.pop() {
+            let key = (cell.sheet, cell.row, cell.column);
+            if expanded {
+                self.evaluate_formula(cell);
+                continue;
+            }
+            if self.get_cell_state(&key).is_some() || !visited.insert(key) {
+                continue;
             }
+            stack.push((cell, true));
+            for precedent in self.get_formula_precedents(cell) {
+                let key = (precedent.sheet, precedent.row, precedent.column);
+                if self.get_cell_state(&key).is_none() && !visited.contains(&key) {
+                    stack.push((precedent, false));
+                }
+            }
+        }
+    }
+
+    pub(crate) fn evaluate_cell(&mut self, cell_reference: CellReference) -> CalcResult {
+        self.evaluate_precedents(cell_reference);
+        self.evaluate_formula(cell_reference)
+    }
+
+    /// Evaluates the cell, computing its formula if it has not been computed yet
+    fn evaluate_formula(&mut self, cell_reference: CellReference) -> CalcResult {
+        let cell = match self.get_cell(
+            cell_reference.sheet,
+            cell_reference.row,
+            cell_reference.column,
+        ) {
+            Some(c) => c,
+            None => return CalcResult::EmptyCell,
         };
 
         match cell.get_formula() {
@@ -713,8 +957,11 @@ impl Model {
                     cell_reference.row,
                     cell_reference.column,
                 );
-                match self.cells.get(&key) {
+                match self.get_cell_state(&key) {
                     Some(CellState::Evaluating) => {
+                        if self.workbook.settings.calculation.iterative {
+                            return self.get_previous_iteration_value(cell_reference);
+                        }
                         return CalcResult::new_error(
                             Error::CIRC,
                             cell_reference,
@@ -727,16 +974,61 @@ impl Model {
                     _ => {
                         // mark cell as being evaluated
                         self.cells.insert(key, CellState::Evaluating);
+                        self.dependency_graph.clear_evaluated_precedents(key);
                     }
                 }
-                let node = &self.parsed_formulas[cell_reference.sheet as usize][f as usize].clone();
-                let result = self.evaluate_node_in_context(node, cell_reference);
+                let node = &self.shared_model().parsed_formulas[cell_reference.sheet as usize]
+                    [f as usize]
+                    .clone();
+                let area = self.get_array_formula_area(cell_reference);
+                let local_variables = std::mem::take(&mut self.local_variables);
+                let result = match area {
+                    Some(area) => {
+                        let result = self.evaluate_node_as_array(node, cell_reference);
+                        self.fill_array_formula(cell_reference, area, result)
+                    }
+                    None => {
+                        let result = self.evaluate_spilling_formula(node, cell_reference);
+                        self.spill_result(cell_reference, result)
+                    }
+                };
+                self.local_variables = local_variables;
+                let result = match result {
+                    CalcResult::Lambda(_) => CalcResult::new_lambda_error(cell_reference),
+                    result => result,
+                };
                 self.set_cell_value(cell_reference, &result);
                 // mark cell as evaluated
                 self.cells.insert(key, CellState::Evaluated);
                 result
             }
-            None => self.get_cell_value(cell, cell_reference),
+            None => match cell.get_spill_anchor() {
+                Some((row, column)) => {
+                    let anchor = CellReference {
+                        sheet: cell_reference.sheet,
+                        row,
+                        column,
+                    };
+                    let anchor_has_formula = self
+                        .get_cell(anchor.sheet, row, column)
+                        .and_then(|c| c.get_formula())
+                        .is_some();
+                    match self.get_cell_state(&(anchor.sheet, row, column)).cloned() {
+                        Some(CellState::Evaluating) => CalcResult::new_error(
+                            Error::CIRC,
+                            cell_reference,
+                            "Circular reference detected".to_string(),
+                        ),
+                        None if anchor_has_formula => {
+                            // The spilled value might be stale, the formula needs to be evaluated first
+                            self.evaluate_cell(anchor);
+                            self.evaluate_cell(cell_reference)
+                        }
+                        _ => self.get_cell_value(cell, cell_reference),
+                    }
+                }
+                None => self.get_cell_value(cell, cell_reference),
+            },
         }
     }
 
@@ -787,12 +1079,25 @@ impl Model {
         // FIXME: Add support for display languages
         let language = get_language("en").expect("").clone();
 
+        let cached_external_values = ExternalSnapshot::new(&workbook.external_links);
         let mut model = Model {
             workbook,
             parsed_formulas,
             parsed_defined_names: HashMap::new(),
             parser,
             cells,
+            dependency_graph: DependencyGraph::default(),
+            local_variables: Vec::new(),
+            lambda_depth: 0,
+            evaluation_steps: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            evaluation_threads: 1,
+            history: History::default(),
+            events: Events::default(),
+            external_resolver: None,
+            cached_external_values,
+            shared: None,
+            removed_cells: HashSet::new(),
             language,
             locale,
             tz,
@@ -800,6 +1105,7 @@ impl Model {
 
         model.parse_formulas();
         model.parse_defined_names();
+        model.build_dependency_graph();
 
         Ok(model)
     }
@@ -833,10 +1139,7 @@ impl Model {
                 }
             }
         }
-        let sheet = match self.get_sheet_index_by_name(&sheet_name) {
-            Some(s) => s,
-            None => return None,
-        };
+        let sheet = self.get_sheet_index_by_name(&sheet_name)?;
         let row = match row.parse::<i32>() {
             Ok(r) => r,
             Err(_) => return None,
@@ -969,80 +1272,228 @@ impl Model {
         Ok(value.to_string())
     }
 
+    /// Lays out `formula` (starting with `=`) as if it was in cell [sheet, row, column].
+    /// Long formulas are split over several lines as given by `options`, if None the formula
+    /// is printed in one line without any whitespace. The result is valid input for the cell.
+    /// References are read and written in the reference style of the workbook.
+    pub fn format_formula(
+        &self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        formula: &str,
+        options: Option<&FormatOptions>,
+    ) -> Result<String, String> {
+        let (node, cell_reference) = self.parse_user_formula(sheet, row, column, formula, None)?;
+        let reference_style = self.get_reference_style();
+        let formula = match options {
+            Some(options) => to_formatted_string(&node, &cell_reference, options, reference_style),
+            None => to_string_in_style(&node, &cell_reference, reference_style),
+        };
+        Ok(format!("={}", formula))
+    }
+
+    /// Translates `formula` (starting with `=`) as if it was in cell [sheet, row, column] to the
+    /// language `language_id` and the separators of the locale `locale_id`, i.e. `=SUM(A1,0.5)`
+    /// to `=SUMME(A1;0,5)` for 'de'.
+    pub fn localize_formula(
+        &self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        formula: &str,
+        locale_id: &str,
+        language_id: &str,
+    ) -> Result<String, String> {
+        let locale =
+            get_locale_fix(locale_id).map_err(|_| format!("Invalid locale: {}", locale_id))?;
+        let language = get_language(language_id)?;
+        let (node, cell_reference) = self.parse_user_formula(sheet, row, column, formula, None)?;
+        Ok(format!(
+            "={}",
+            to_localized_string(
+                &node,
+                &cell_reference,
+                locale,
+                language,
+                self.get_reference_style()
+            )
+        ))
+    }
+
+    /// Translates `formula` (starting with `=`) written in the language `language_id` and with
+    /// the separators of the locale `locale_id` to English, the way formulas are stored.
+    /// This is the inverse of [`Model::localize_formula`].
+    pub fn delocalize_formula(
+        &self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        formula: &str,
+        locale_id: &str,
+        language_id: &str,
+    ) -> Result<String, String> {
+        let locale =
+            get_locale_fix(locale_id).map_err(|_| format!("Invalid locale: {}", locale_id))?;
+        let language = get_language(language_id)?;
+        let (node, cell_reference) =
+            self.parse_user_formula(sheet, row, column, formula, Some((locale, language)))?;
+        Ok(format!(
+            "={}",
+            to_string_in_style(&node, &cell_reference, self.get_reference_style())
+        ))
+    }
+
+    /// Parses `formula` (starting with `=`) in cell [sheet, row, column], in English or in the
+    /// given locale and language, and with the reference style of the workbook
+    fn parse_user_formula(
+        &self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        formula: &str,
+        localization: Option<(&Locale, &Language)>,
+    ) -> Result<(Node, CellReferenceRC), String> {
+        let sheet_name = self.workbook.worksheet(sheet)?.get_name();
+        let formula = formula
+            .strip_prefix('=')
+            .ok_or_else(|| format!("Not a formula: '{}'", formula))?;
+        let cell_reference = CellReferenceRC {
+            sheet: sheet_name,
+            row,
+            column,
+        };
+        let mut parser = self.parser.clone();
+        if let Some((locale, language)) = localization {
+            parser.set_locale(locale, language);
+        }
+        if self.get_reference_style() == ReferenceStyle::R1C1 {
+            parser.set_lexer_mode(LexerMode::R1C1);
+        }
+        let node = parser.parse(formula, &Some(cell_reference.clone()));
+        if let Node::ParseErrorKind { message, .. } = node {
+            return Err(message);
+        }
+        Ok((node, cell_reference))
+    }
+
+    /// Returns the formula of the cell, if any, in the reference style of the workbook
     pub fn cell_formula(
         &self,
         sheet: u32,
         row: i32,
         column: i32,
+    ) -> Result<Option<String>, String> {
+        self.cell_formula_in_style(sheet, row, column, self.get_reference_style())
+    }
+
+    pub(crate) fn cell_formula_in_style(
+        &self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        reference_style: ReferenceStyle,
     ) -> Result<Option<String>, String> {
         let worksheet = self.workbook.worksheet(sheet)?;
-        Ok(worksheet.cell(row, column).and_then(|cell| {
+        Ok(self.get_cell(sheet, row, column).and_then(|cell| {
             cell.get_formula().map(|formula_index| {
-                let formula = &self.parsed_formulas[sheet as usize][formula_index as usize];
+                let formula =
+                    &self.shared_model().parsed_formulas[sheet as usize][formula_index as usize];
                 let cell_ref = CellReferenceRC {
                     sheet: worksheet.get_name(),
                     row,
                     column,
                 };
-                format!("={}", to_string(formula, &cell_ref))
+                format!(
+                    "={}",
+                    to_string_in_style(formula, &cell_ref, reference_style)
+                )
             })
         }))
     }
 
+    /// Returns how users write and read references in formulas
+    pub fn get_reference_style(&self) -> ReferenceStyle {
+        self.workbook.settings.calculation.reference_style
+    }
+
+    /// Changes how users write and read references in formulas, i.e. `=R[-1]C*2` instead of
+    /// `=A1*2` in cell A2. Formulas are the same, only the way they are entered and shown changes.
+    pub fn set_reference_style(&mut self, reference_style: ReferenceStyle) {
+        self.record_change(ChangeScope::calculation_settings(), |model| {
+            model.workbook.settings.calculation.reference_style = reference_style;
+        })
+    }
+
     /// Updates the value of a cell with some text
     /// It does not change the style unless needs to add "quoting"
     pub fn update_cell_with_text(&mut self, sheet: u32, row: i32, column: i32, value: &str) {
-        let style_index = self.get_cell_style_index(sheet, row, column);
-        let new_style_index;
-        if common::value_needs_quoting(value, &self.language) {
-            new_style_index = self
-                .workbook
-                .styles
-                .get_style_with_quote_prefix(style_index);
-        } else if self.workbook.styles.style_is_quote_prefix(style_index) {
-            new_style_index = self
-                .workbook
-                .styles
-                .get_style_without_quote_prefix(style_index);
-        } else {
-            new_style_index = style_index;
-        }
-        self.set_cell_with_string(sheet, row, column, value, new_style_index);
+        self.record_change(ChangeScope::cell(sheet, row, column), |model| {
+            model.dependency_graph.mark_dirty(sheet, row, column);
+            model.remove_array_formula(sheet, row, column);
+            let style_index = model.get_cell_style_index(sheet, row, column);
+            let new_style_index;
+            if common::value_needs_quoting(value, &model.language) {
+                new_style_index = model
+                    .workbook
+                    .styles
+                    .get_style_with_quote_prefix(style_index);
+            } else if model.workbook.styles.style_is_quote_prefix(style_index) {
+                new_style_index = model
+                    .workbook
+                    .styles
+                    .get_style_without_quote_prefix(style_index);
+            } else {
+                new_style_index = style_index;
+            }
+            model.set_cell_with_string(sheet, row, column, value, new_style_index);
+        })
     }
 
     /// Updates the value of a cell with a boolean value
     /// It does not change the style
     pub fn update_cell_with_bool(&mut self, sheet: u32, row: i32, column: i32, value: bool) {
-        let style_index = self.get_cell_style_index(sheet, row, column);
-        let new_style_index = if self.workbook.styles.style_is_quote_prefix(style_index) {
-            self.workbook
-                .styles
-                .get_style_without_quote_prefix(style_index)
-        } else {
-            style_index
-        };
-        let worksheet = &mut self.workbook.worksheets[sheet as usize];
-        worksheet.set_cell_with_boolean(row, column, value, new_style_index);
+        self.record_change(ChangeScope::cell(sheet, row, column), |model| {
+            model.dependency_graph.mark_dirty(sheet, row, column);
+            model.remove_array_formula(sheet, row, column);
+            let style_index = model.get_cell_style_index(sheet, row, column);
+            let new_style_index = if model.workbook.styles.style_is_quote_prefix(style_index) {
+                model
+                    .workbook
+                    .styles
+                    .get_style_without_quote_prefix(style_index)
+            } else {
+                style_index
+            };
+            let worksheet = &mut model.workbook.worksheets[sheet as usize];
+            worksheet.set_cell_with_boolean(row, column, value, new_style_index);
+        })
     }
 
     /// Updates the value of a cell with a number
     /// It does not change the style
     pub fn update_cell_with_number(&mut self, sheet: u32, row: i32, column: i32, value: f64) {
-        let style_index = self.get_cell_style_index(sheet, row, column);
-        let new_style_index = if self.workbook.styles.style_is_quote_prefix(style_index) {
-            self.workbook
-                .styles
-                .get_style_without_quote_prefix(style_index)
-        } else {
-            style_index
-        };
-        let worksheet = &mut self.workbook.worksheets[sheet as usize];
-        worksheet.set_cell_with_number(row, column, value, new_style_index);
+        self.record_change(ChangeScope::cell(sheet, row, column), |model| {
+            model.dependency_graph.mark_dirty(sheet, row, column);
+            model.remove_array_formula(sheet, row, column);
+            let style_index = model.get_cell_style_index(sheet, row, column);
+            let new_style_index = if model.workbook.styles.style_is_quote_prefix(style_index) {
+                model
+                    .workbook
+                    .styles
+                    .get_style_without_quote_prefix(style_index)
+            } else {
+                style_index
+            };
+            let worksheet = &mut model.workbook.worksheets[sheet as usize];
+            worksheet.set_cell_with_number(row, column, value, new_style_index);
+        })
     }
 
     /// Updates the formula of given cell
     /// It does not change the style unless needs to add "quoting"
-    /// Expects the formula to start with "="
+    /// A legacy array formula remains an array formula over the same area
+    /// Expects the formula to start with "=" and to be in the reference style of the workbook
     pub fn update_cell_with_formula(
         &mut self,
         sheet: u32,
@@ -1050,18 +1501,40 @@ impl Model {
         column: i32,
         formula: String,
     ) -> Result<(), String> {
-        let mut style_index = self.get_cell_style_index(sheet, row, column);
-        if self.workbook.styles.style_is_quote_prefix(style_index) {
-            style_index = self
-                .workbook
-                .styles
-                .get_style_without_quote_prefix(style_index);
-        }
-        let formula = formula
-            .strip_prefix('=')
-            .ok_or_else(|| format!("\"{formula}\" is not a valid formula"))?;
-        self.set_cell_with_formula(sheet, row, column, formula, style_index)?;
-        Ok(())
+        let reference_style = self.get_reference_style();
+        self.update_cell_with_formula_in_style(sheet, row, column, formula, reference_style)
+    }
+
+    pub(crate) fn update_cell_with_formula_in_style(
+        &mut self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        formula: String,
+        reference_style: ReferenceStyle,
+    ) -> Result<(), String> {
+        self.record_change(ChangeScope::cell(sheet, row, column), |model| {
+            let mut style_index = model.get_cell_style_index(sheet, row, column);
+            if model.workbook.styles.style_is_quote_prefix(style_index) {
+                style_index = model
+                    .workbook
+                    .styles
+                    .get_style_without_quote_prefix(style_index);
+            }
+            let formula = formula
+                .strip_prefix('=')
+                .ok_or_else(|| format!("\"{formula}\" is not a valid formula"))?;
+            model.dependency_graph.mark_dirty(sheet, row, column);
+            model.set_cell_with_formula(
+                sheet,
+                row,
+                column,
+                formula,
+                style_index,
+                reference_style,
+            )?;
+            Ok(())
+        })
     }
 
     /// Sets a cell parametrized by (`sheet`, `row`, `column`) with `value`
@@ -1069,88 +1542,122 @@ impl Model {
     /// If you enter a currency `$100` it will set as a number and update the style
     /// Note that for currencies/percentage there is only one possible style
     /// The value is always a string, so we need to try to cast it into numbers/booleans/errors
+    /// Formulas are in the reference style of the workbook
     pub fn set_user_input(&mut self, sheet: u32, row: i32, column: i32, value: String) {
-        // If value starts with "'" then we force the style to be quote_prefix
-        let style_index = self.get_cell_style_index(sheet, row, column);
-        if let Some(new_value) = value.strip_prefix('\'') {
-            // First check if it needs quoting
-            let new_style = if common::value_needs_quoting(new_value, &self.language) {
-                self.workbook
-                    .styles
-                    .get_style_with_quote_prefix(style_index)
-            } else {
-                style_index
-            };
-            self.set_cell_with_string(sheet, row, column, new_value, new_style);
-        } else {
-            let mut new_style_index = style_index;
-            if self.workbook.styles.style_is_quote_prefix(style_index) {
-                new_style_index = self
-                    .workbook
-                    .styles
-                    .get_style_without_quote_prefix(style_index);
+        let reference_style = self.get_reference_style();
+        self.set_user_input_in_style(sheet, row, column, value, reference_style)
+    }
+
+    pub(crate) fn set_user_input_in_style(
+        &mut self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        value: String,
+        reference_style: ReferenceStyle,
+    ) {
+        if self.set_table_header(sheet, row, column, &value) {
+            return;
+        }
+        self.record_change(ChangeScope::cell(sheet, row, column), |model| {
+            model.dependency_graph.mark_dirty(sheet, row, column);
+            model.remove_array_formula(sheet, row, column);
+            if !value.is_empty() {
+                model.grow_tables(sheet, row, column);
             }
-            if let Some(formula) = value.strip_prefix('=') {
-                let formula_index = self
-                    .set_cell_with_formula(sheet, row, column, formula, new_style_index)
-                    .expect("could not set the cell formula");
-                // Update the style if needed
-                let cell = CellReference { sheet, row, column };
-                let parsed_formula = &self.parsed_formulas[sheet as usize][formula_index as usize];
-                if let Some(units) = self.compute_node_units(parsed_formula, &cell) {
-                    let new_style_index = self
+            // If value starts with "'" then we force the style to be quote_prefix
+            let style_index = model.get_cell_style_index(sheet, row, column);
+            if let Some(new_value) = value.strip_prefix('\'') {
+                // First check if it needs quoting
+                let new_style = if common::value_needs_quoting(new_value, &model.language) {
+                    model
                         .workbook
                         .styles
-                        .get_style_with_format(new_style_index, &units.get_num_fmt());
-                    let style = self.workbook.styles.get_style(new_style_index);
-                    self.set_cell_style(sheet, row, column, &style)
-                        .expect("Failed setting the style");
-                }
+                        .get_style_with_quote_prefix(style_index)
+                } else {
+                    style_index
+                };
+                model.set_cell_with_string(sheet, row, column, new_value, new_style);
             } else {
-                let worksheets = &mut self.workbook.worksheets;
-                let worksheet = &mut worksheets[sheet as usize];
-
-                // The list of currencies is '$', '€' and the local currency
-                let mut currencies = vec!["$", "€"];
-                let currency = &self.locale.currency.symbol;
-                if !currencies.iter().any(|e| e == currency) {
-                    currencies.push(currency);
+                let mut new_style_index = style_index;
+                if model.workbook.styles.style_is_quote_prefix(style_index) {
+                    new_style_index = model
+                        .workbook
+                        .styles
+                        .get_style_without_quote_prefix(style_index);
                 }
-                //  We try to parse as number
-                if let Ok((v, number_format)) = parse_formatted_number(&value, &currencies) {
-                    if let Some(num_fmt) = number_format {
-                        // Should not apply the format in the following cases:
-                        // - we assign a date to already date-formatted cell
-                        let should_apply_format = !(is_likely_date_number_format(
-                            &self.workbook.styles.get_style(new_style_index).num_fmt,
-                        ) && is_likely_date_number_format(&num_fmt));
-                        if should_apply_format {
-                            new_style_index = self
-                                .workbook
-                                .styles
-                                .get_style_with_format(new_style_index, &num_fmt);
+                if let Some(formula) = value.strip_prefix('=') {
+                    let formula_index = model
+                        .set_cell_with_formula(
+                            sheet,
+                            row,
+                            column,
+                            formula,
+                            new_style_index,
+                            reference_style,
+                        )
+                        .expect("could not set the cell formula");
+                    // Update the style if needed
+                    let cell = CellReference { sheet, row, column };
+                    let parsed_formula =
+                        &model.parsed_formulas[sheet as usize][formula_index as usize];
+                    if let Some(units) = model.compute_node_units(parsed_formula, &cell) {
+                        let new_style_index = model
+                            .workbook
+                            .styles
+                            .get_style_with_format(new_style_index, &units.get_num_fmt());
+                        let style = model.workbook.styles.get_style(new_style_index);
+                        model
+                            .set_cell_style(sheet, row, column, &style)
+                            .expect("Failed setting the style");
+                    }
+                } else {
+                    let worksheets = &mut model.workbook.worksheets;
+                    let worksheet = &mut worksheets[sheet as usize];
+
+                    // The list of currencies is '$', '€' and the local currency
+                    let mut currencies = vec!["$", "€"];
+                    let currency = &model.locale.currency.symbol;
+                    if !currencies.iter().any(|e| e == currency) {
+                        currencies.push(currency);
+                    }
+                    //  We try to parse as number
+                    if let Ok((v, number_format)) = parse_formatted_number(&value, &currencies) {
+                        if let Some(num_fmt) = number_format {
+                            // Should not apply the format in the following cases:
+                            // - we assign a date to already date-formatted cell
+                            let should_apply_format =
+                                !(is_likely_date_number_format(
+                                    &model.workbook.styles.get_style(new_style_index).num_fmt,
+                                ) && is_likely_date_number_format(&num_fmt));
+                            if should_apply_format {
+                                new_style_index = model
+                                    .workbook
+                                    .styles
+                                    .get_style_with_format(new_style_index, &num_fmt);
+                            }
                         }
+                        worksheet.set_cell_with_number(row, column, v, new_style_index);
+                        return;
                     }
-                    worksheet.set_cell_with_number(row, column, v, new_style_index);
-                    return;
-                }
-                // We try to parse as boolean
-                if let Ok(v) = value.to_lowercase().parse::<bool>() {
-                    worksheet.set_cell_with_boolean(row, column, v, new_style_index);
-                    return;
-                }
-                // Check is it is error value
-                let upper = value.to_uppercase();
-                match get_error_by_name(&upper, &self.language) {
-                    Some(error) => {
-                        worksheet.set_cell_with_error(row, column, error, new_style_index);
+                    // We try to parse as boolean
+                    if let Ok(v) = value.to_lowercase().parse::<bool>() {
+                        worksheet.set_cell_with_boolean(row, column, v, new_style_index);
+                        return;
                     }
-                    None => {
-                        self.set_cell_with_string(sheet, row, column, &value, new_style_index);
+                    // Check is it is error value
+                    let upper = value.to_uppercase();
+                    match get_error_by_name(&upper, &model.language) {
+                        Some(error) => {
+                            worksheet.set_cell_with_error(row, column, error, new_style_index);
+                        }
+                        None => {
+                            model.set_cell_with_string(sheet, row, column, &value, new_style_index);
+                        }
                     }
                 }
             }
-        }
+        })
     }
 
     fn set_cell_with_formula(
@@ -1160,6 +1667,7 @@ impl Model {
         column: i32,
         formula: &str,
         style: i32,
+        reference_style: ReferenceStyle,
     ) -> Result<i32, String> {
         let worksheet = self.workbook.worksheet_mut(sheet)?;
         let cell_reference = CellReferenceRC {
@@ -1168,6 +1676,9 @@ impl Model {
             column,
         };
         let shared_formulas = &mut worksheet.shared_formulas;
+        if reference_style == ReferenceStyle::R1C1 {
+            self.parser.set_lexer_mode(LexerMode::R1C1);
+        }
         let mut parsed_formula = self.parser.parse(formula, &Some(cell_reference.clone()));
         // If the formula fails to parse try adding a parenthesis
         // SUM(A1:A3  => SUM(A1:A3)
@@ -1180,6 +1691,7 @@ impl Model {
                 _ => parsed_formula = new_parsed_formula,
             }
         }
+        self.parser.set_lexer_mode(LexerMode::A1);
 
         let s = to_rc_format(&parsed_formula);
         let mut formula_index: i32 = -1;
@@ -1195,7 +1707,14 @@ impl Model {
         Ok(formula_index)
     }
 
-    fn set_cell_with_string(&mut self, sheet: u32, row: i32, column: i32, value: &str, style: i32) {
+    pub(crate) fn set_cell_with_string(
+        &mut self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        value: &str,
+        style: i32,
+    ) {
         // Interestingly, `self.workbook.worksheet()` cannot be used because it would create two
         // mutable borrows of worksheet. However, I suspect that lexical lifetimes silently help
         // here, so there is no issue with inlined call.
@@ -1289,38 +1808,67 @@ impl Model {
         cells
     }
 
-    /// Evaluates the model with a top-down recursive algorithm
+    /// Evaluates the model. The precedents of every formula are computed before the formula.
+    /// Only the cells edited since the last evaluation, the volatile cells and their dependents are
+    /// recomputed. The first evaluation after (re)parsing the formulas recomputes all cells.
     pub fn evaluate(&mut self) {
-        // clear all computation artifacts
-        self.cells.clear();
-
-        let cells = self.get_all_cells();
+        let cells = match self.take_cells_to_evaluate() {
+            Some(cells) => {
+                for cell in &cells {
+                    self.cells.remove(&(cell.sheet, cell.row, cell.column));
+                }
+                cells
+            }
+            None => {
+                // clear all computation artifacts
+                self.cells.clear();
+                self.get_all_cells()
+                    .iter()
+                    .map(|cell| CellReference {
+                        sheet: cell.index,
+                        row: cell.row,
+                        column: cell.column,
+                    })
+                    .collect()
+            }
+        };
 
-        for cell in cells {
-            self.evaluate_cell(CellReference {
-                sheet: cell.index,
-                row: cell.row,
-                column: cell.column,
-            });
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.evaluation_threads > 1 {
+            self.evaluate_in_parallel(&cells);
+        }
+        for cell in &cells {
+            self.evaluate_cell(*cell);
         }
+        self.evaluate_circular_references(&cells);
+        self.evaluate_spilled_dependents();
+        self.evaluate_data_tables();
+        self.evaluate_spilled_dependents();
+        self.push_cell_events();
     }
 
-    /// Evaluates the model with a top-down recursive algorithm
+    /// Evaluates the model, see `evaluate`.
     /// Returns a list of errors instead of using #N/IMPL!, #CIRC! or #ERROR! values.
     pub fn evaluate_with_error_check(&mut self) -> Result<(), Vec<String>> {
+        // this is always a full evaluation, but the dependency graph needs to be kept in sync
+        self.take_cells_to_evaluate();
         // clear all computation artifacts
         self.cells.clear();
 
-        let cells = self.get_all_cells();
-
-        let mut errors = Vec::new();
-
-        for cell in cells {
-            let calc_result = self.evaluate_cell(CellReference {
+        let cells: Vec<CellReference> = self
+            .get_all_cells()
+            .iter()
+            .map(|cell| CellReference {
                 sheet: cell.index,
                 row: cell.row,
                 column: cell.column,
-            });
+            })
+            .collect();
+
+        let mut errors = Vec::new();
+
+        for cell in &cells {
+            let calc_result = self.evaluate_cell(*cell);
             if let CalcResult::Error {
                 error: Error::CIRC | Error::NIMPL | Error::ERROR,
                 origin,
@@ -1340,6 +1888,11 @@ impl Model {
                 }
             }
         }
+        self.evaluate_circular_references(&cells);
+        self.evaluate_spilled_dependents();
+        self.evaluate_data_tables();
+        self.evaluate_spilled_dependents();
+        self.push_cell_events();
 
         if !errors.is_empty() {
             return Err(errors);
@@ -1350,32 +1903,36 @@ impl Model {
 
     /// Sets cell to empty. Can be used to delete value without affecting style.
     pub fn set_cell_empty(&mut self, sheet: u32, row: i32, column: i32) -> Result<(), String> {
-        let worksheet = self.workbook.worksheet_mut(sheet)?;
-        worksheet.set_cell_empty(row, column);
-        Ok(())
+        self.record_change(ChangeScope::cell(sheet, row, column), |model| {
+            let worksheet = model.workbook.worksheet_mut(sheet)?;
+            worksheet.set_cell_empty(row, column);
+            model.dependency_graph.mark_dirty(sheet, row, column);
+            model.remove_array_formula(sheet, row, column);
+            Ok(())
+        })
     }
 
     /// Deletes a cell by removing it from worksheet data.
     pub fn delete_cell(&mut self, sheet: u32, row: i32, column: i32) -> Result<(), String> {
-        let worksheet = self.workbook.worksheet_mut(sheet)?;
+        self.record_change(ChangeScope::cell(sheet, row, column), |model| {
+            let worksheet = model.workbook.worksheet_mut(sheet)?;
 
-        let sheet_data = &mut worksheet.sheet_data;
-        if let Some(row_data) = sheet_data.get_mut(&row) {
-            row_data.remove(&column);
-        }
+            let sheet_data = &mut worksheet.sheet_data;
+            if let Some(row_data) = sheet_data.get_mut(&row) {
+                row_data.remove(&column);
+            }
+            model.dependency_graph.mark_dirty(sheet, row, column);
+            model.remove_array_formula(sheet, row, column);
 
-        Ok(())
+            Ok(())
+        })
     }
 
     // FIXME: expect
     pub fn get_cell_style_index(&self, sheet: u32, row: i32, column: i32) -> i32 {
         // First check the cell, then row, the column
-        let cell = self
-            .workbook
-            .worksheet(sheet)
-            .expect("Invalid sheet")
-            .cell(row, column);
-        match cell {
+        self.workbook.worksheet(sheet).expect("Invalid sheet");
+        match self.get_cell(sheet, row, column) {
             Some(cell) => cell.get_style(),
             None => {
                 let rows = &self.workbook.worksheets[sheet as usize].rows;
@@ -1459,10 +2016,13 @@ impl Model {
         } else {
             return Err("Unsupported currency");
         };
-        self.locale.currency = Currency {
+        let currency = Currency {
             symbol: symbol.to_string(),
             iso: iso.to_string(),
         };
+        self.record_change(ChangeScope::currency(), |model| {
+            model.locale.currency = currency;
+        });
         Ok(())
     }
 }
@@ -1,20 +1,25 @@
 use chrono::NaiveDateTime;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::{
-    calc_result::Range,
+    calc_result::{CellReference, Range},
+    dependencies::DependencyGraph,
+    diffs::Diff,
+    events::{Events, ModelEvent},
     expressions::{
         lexer::LexerMode,
         parser::stringify::{rename_sheet_in_node, to_rc_format},
-        parser::Parser,
+        parser::{Node, Parser},
+        token::Error,
         types::CellReferenceRC,
     },
+    external::ExternalSnapshot,
+    history::{ChangeScope, History},
     language::get_language,
     locale::get_locale,
     model::{get_milliseconds_since_epoch, Model, ParsedDefinedName},
     types::{Metadata, SheetState, Workbook, WorkbookSettings, Worksheet},
-    utils::ParsedReference,
 };
 
 pub use chrono_tz::Tz;
@@ -28,7 +33,149 @@ pub const EQUALTO_USER: &str = "EqualTo User";
 /// \ , / , * , ? , : , [ , ].
 fn is_valid_sheet_name(name: &str) -> bool {
     let invalid = ['\\', '/', '*', '?', ':', '[', ']'];
-    return !name.is_empty() && name.chars().count() <= 31 && !name.contains(&invalid[..]);
+    !name.is_empty() && name.chars().count() <= 31 && !name.contains(&invalid[..])
+}
+
+/// Absolute references to a cell or a range are resolved, anything else is kept as a formula
+fn get_parsed_defined_name(node: Node) -> ParsedDefinedName {
+    match node {
+        Node::ReferenceKind {
+            sheet_index,
+            absolute_row: true,
+            absolute_column: true,
+            row,
+            column,
+            ..
+        } => ParsedDefinedName::CellReference(CellReference {
+            sheet: sheet_index,
+            row,
+            column,
+        }),
+        Node::RangeKind {
+            sheet_index,
+            absolute_row1: true,
+            absolute_column1: true,
+            row1,
+            column1,
+            absolute_row2: true,
+            absolute_column2: true,
+            row2,
+            column2,
+            ..
+        } => ParsedDefinedName::RangeReference(Range {
+            left: CellReference {
+                sheet: sheet_index,
+                row: row1,
+                column: column1,
+            },
+            right: CellReference {
+                sheet: sheet_index,
+                row: row2,
+                column: column2,
+            },
+        }),
+        Node::ParseErrorKind { .. } => ParsedDefinedName::InvalidDefinedNameFormula,
+        node => ParsedDefinedName::Formula(node),
+    }
+}
+
+/// Collects the names used in `node`
+fn collect_variables(node: &Node, names: &mut Vec<String>) {
+    match node {
+        Node::VariableKind(name) => names.push(name.to_lowercase()),
+        Node::OpRangeKind { left, right }
+        | Node::OpIntersectKind { left, right }
+        | Node::OpConcatenateKind { left, right }
+        | Node::OpSumKind { left, right, .. }
+        | Node::OpProductKind { left, right, .. }
+        | Node::OpPowerKind { left, right }
+        | Node::CompareKind { left, right, .. } => {
+            collect_variables(left, names);
+            collect_variables(right, names);
+        }
+        Node::UnaryKind { right, .. } => collect_variables(right, names),
+        Node::SpillRangeKind(node) => collect_variables(node, names),
+        Node::CallKind { function, args } => {
+            collect_variables(function, names);
+            for arg in args {
+                collect_variables(arg, names);
+            }
+        }
+        Node::FunctionKind { args, .. } | Node::InvalidFunctionKind { args, .. } => {
+            for arg in args {
+                collect_variables(arg, names);
+            }
+        }
+        Node::OpUnionKind(items) => {
+            for item in items {
+                collect_variables(item, names);
+            }
+        }
+        Node::ArrayKind(rows) => {
+            for item in rows.iter().flatten() {
+                collect_variables(item, names);
+            }
+        }
+        Node::BooleanKind(_)
+        | Node::NumberKind(_)
+        | Node::StringKind(_)
+        | Node::ReferenceKind { .. }
+        | Node::RangeKind { .. }
+        | Node::WrongReferenceKind { .. }
+        | Node::WrongRangeKind { .. }
+        | Node::ExternalReferenceKind { .. }
+        | Node::ExternalRangeKind { .. }
+        | Node::SheetSpanReferenceKind { .. }
+        | Node::SheetSpanRangeKind { .. }
+        | Node::TableReferenceKind { .. }
+        | Node::ErrorKind(_)
+        | Node::ParseErrorKind { .. }
+        | Node::EmptyArgKind => {}
+    }
+}
+
+/// Returns true if the formula of the defined name `key` uses, directly or through other names,
+/// the name itself. Names in a formula are looked up as local names of the sheet and then as global.
+fn refers_to_itself(
+    defined_names: &HashMap<(Option<u32>, String), ParsedDefinedName>,
+    key: &(Option<u32>, String),
+) -> bool {
+    let mut visited = HashSet::new();
+    let mut stack = vec![key.clone()];
+    while let Some(current) = stack.pop() {
+        let node = match defined_names.get(&current) {
+            Some(ParsedDefinedName::Formula(node)) => node,
+            _ => continue,
+        };
+        let mut names = Vec::new();
+        collect_variables(node, &mut names);
+        for name in names {
+            // A global name could be used from any sheet
+            let candidates = match key.0 {
+                Some(sheet) if defined_names.contains_key(&(Some(sheet), name.clone())) => {
+                    vec![(Some(sheet), name)]
+                }
+                Some(_) => vec![(None, name)],
+                None => defined_names
+                    .keys()
+                    .filter(|(_, other)| other == &name)
+                    .cloned()
+                    .collect(),
+            };
+            for candidate in candidates {
+                if !defined_names.contains_key(&candidate) {
+                    continue;
+                }
+                if &candidate == key {
+                    return true;
+                }
+                if visited.insert(candidate.clone()) {
+                    stack.push(candidate);
+                }
+            }
+        }
+    }
+    false
 }
 
 impl Model {
@@ -40,6 +187,8 @@ impl Model {
             comments: vec![],
             dimension: "A1".to_string(),
             merge_cells: vec![],
+            array_formulas: vec![],
+            data_tables: vec![],
             name: name.to_string(),
             shared_formulas: vec![],
             sheet_data: Default::default(),
@@ -81,27 +230,9 @@ impl Model {
     }
 
     pub(crate) fn parse_defined_names(&mut self) {
+        self.parser.set_lexer_mode(LexerMode::A1);
         let mut parsed_defined_names = HashMap::new();
         for defined_name in &self.workbook.defined_names {
-            let parsed_defined_name_formula = if let Ok(reference) =
-                ParsedReference::parse_reference_formula(
-                    None,
-                    &defined_name.formula,
-                    &self.locale,
-                    |name| self.get_sheet_index_by_name(name),
-                ) {
-                match reference {
-                    ParsedReference::CellReference(cell_reference) => {
-                        ParsedDefinedName::CellReference(cell_reference)
-                    }
-                    ParsedReference::Range(left, right) => {
-                        ParsedDefinedName::RangeReference(Range { left, right })
-                    }
-                }
-            } else {
-                ParsedDefinedName::InvalidDefinedNameFormula
-            };
-
             let local_sheet_index = if let Some(sheet_id) = defined_name.sheet_id {
                 if let Some(sheet_index) = self.get_sheet_index_by_sheet_id(sheet_id) {
                     Some(sheet_index)
@@ -113,9 +244,31 @@ impl Model {
                 None
             };
 
+            // References without a sheet are in the sheet of a local name (or the first sheet).
+            // Relative references are relative to A1.
+            let context = Some(CellReferenceRC {
+                sheet: self.workbook.worksheets[local_sheet_index.unwrap_or(0) as usize].get_name(),
+                row: 1,
+                column: 1,
+            });
+            let node = self.parser.parse(&defined_name.formula, &context);
+
             parsed_defined_names.insert(
                 (local_sheet_index, defined_name.name.to_lowercase()),
-                parsed_defined_name_formula,
+                get_parsed_defined_name(node),
+            );
+        }
+
+        // A name that refers to itself would be evaluated forever
+        let circular_names: Vec<(Option<u32>, String)> = parsed_defined_names
+            .keys()
+            .filter(|key| refers_to_itself(&parsed_defined_names, key))
+            .cloned()
+            .collect();
+        for key in circular_names {
+            parsed_defined_names.insert(
+                key,
+                ParsedDefinedName::Formula(Node::ErrorKind(Error::CIRC)),
             );
         }
 
@@ -123,13 +276,15 @@ impl Model {
     }
 
     // Reparses all formulas and defined names
-    fn reset_parsed_structures(&mut self) {
+    pub(crate) fn reset_parsed_structures(&mut self) {
         self.parser
             .set_worksheets(self.workbook.get_worksheet_names());
+        self.parser.set_tables(self.workbook.tables.clone());
         self.parsed_formulas = vec![];
         self.parse_formulas();
         self.parsed_defined_names = HashMap::new();
         self.parse_defined_names();
+        self.build_dependency_graph();
         self.evaluate();
     }
 
@@ -154,7 +309,14 @@ impl Model {
         // Now we need a sheet_id
         let sheet_id = self.get_new_sheet_id();
         let worksheet = Model::new_empty_worksheet(&sheet_name, sheet_id);
+        self.record_diff(Diff::InsertSheet {
+            sheet: self.workbook.worksheets.len() as u32,
+            worksheet: Box::new(worksheet.clone()),
+        });
         self.workbook.worksheets.push(worksheet);
+        self.push_event(ModelEvent::SheetAdded {
+            sheet: self.workbook.worksheets.len() as u32 - 1,
+        });
         self.reset_parsed_structures();
     }
 
@@ -187,9 +349,14 @@ impl Model {
         if sheet_index as usize > self.workbook.worksheets.len() {
             return Err("Sheet index out of range".to_string());
         }
+        self.record_diff(Diff::InsertSheet {
+            sheet: sheet_index,
+            worksheet: Box::new(worksheet.clone()),
+        });
         self.workbook
             .worksheets
             .insert(sheet_index as usize, worksheet);
+        self.push_event(ModelEvent::SheetAdded { sheet: sheet_index });
         self.reset_parsed_structures();
         Ok(())
     }
@@ -222,6 +389,23 @@ impl Model {
         sheet_index: u32,
         new_name: &str,
     ) -> Result<(), String> {
+        let old_name = self
+            .workbook
+            .worksheet(sheet_index)
+            .map(|worksheet| worksheet.get_name())
+            .unwrap_or_default();
+        self.record_change(ChangeScope::sheet_name(sheet_index), |model| {
+            model.rename_sheet_in_workbook(sheet_index, new_name)
+        })?;
+        self.push_event(ModelEvent::SheetRenamed {
+            sheet: sheet_index,
+            old_name,
+            new_name: new_name.to_string(),
+        });
+        Ok(())
+    }
+
+    fn rename_sheet_in_workbook(&mut self, sheet_index: u32, new_name: &str) -> Result<(), String> {
         if !is_valid_sheet_name(new_name) {
             return Err(format!("Invalid name for a sheet: '{}'", new_name));
         }
@@ -254,9 +438,15 @@ impl Model {
         }
         // Se the mode back to A1
         self.parser.set_lexer_mode(LexerMode::A1);
-        // Update the name of the worksheet
+        // Update the name of the worksheet and of the sheet of its tables
         let worksheets = &mut self.workbook.worksheets;
+        let old_name = worksheets[sheet_index as usize].get_name();
         worksheets[sheet_index as usize].set_name(new_name);
+        for table in self.workbook.tables.values_mut() {
+            if table.sheet_name == old_name {
+                table.sheet_name = new_name.to_string();
+            }
+        }
         self.reset_parsed_structures();
         Ok(())
     }
@@ -270,14 +460,58 @@ impl Model {
         if sheet_count == 1 {
             return Err("Cannot delete only sheet".to_string());
         };
-        if sheet_index > sheet_count {
+        if sheet_index >= sheet_count {
             return Err("Sheet index too large".to_string());
         }
-        self.workbook.worksheets.remove(sheet_index as usize);
+        self.record_changes(|model| {
+            // Spans of sheets ending in the sheet now end in the next one inside them
+            model.record_change(ChangeScope::sheet_spans(), |model| {
+                model.delete_sheet_in_spans(sheet_index)
+            });
+            let worksheet = model.workbook.worksheets.remove(sheet_index as usize);
+            model.record_diff(Diff::DeleteSheet {
+                sheet: sheet_index,
+                worksheet: Box::new(worksheet),
+            });
+        });
+        self.push_event(ModelEvent::SheetDeleted { sheet: sheet_index });
+        self.reset_parsed_structures();
+        Ok(())
+    }
+
+    /// Moves the sheet at `sheet_index` to `new_index`. Fails if either index is too large.
+    /// References to spans of sheets keep their first and last sheets, like in Excel:
+    /// moving a sheet out of a span leaves it out of the sum.
+    pub fn move_sheet(&mut self, sheet_index: u32, new_index: u32) -> Result<(), String> {
+        self.move_worksheet(sheet_index, new_index)?;
+        self.record_diff(Diff::MoveSheet {
+            sheet: sheet_index,
+            new_index,
+        });
         self.reset_parsed_structures();
         Ok(())
     }
 
+    pub(crate) fn move_worksheet(
+        &mut self,
+        sheet_index: u32,
+        new_index: u32,
+    ) -> Result<(), String> {
+        let sheet_count = self.workbook.worksheets.len() as u32;
+        if sheet_index >= sheet_count || new_index >= sheet_count {
+            return Err("Sheet index out of range".to_string());
+        }
+        let worksheet = self.workbook.worksheets.remove(sheet_index as usize);
+        self.workbook
+            .worksheets
+            .insert(new_index as usize, worksheet);
+        self.push_event(ModelEvent::SheetMoved {
+            sheet: sheet_index,
+            new_index,
+        });
+        Ok(())
+    }
+
     /// Deletes a sheet by name. Fails if:
     ///   * The sheet does not exists
     ///   * It is the last sheet
@@ -340,6 +574,7 @@ impl Model {
             settings: WorkbookSettings {
                 tz: timezone.to_string(),
                 locale: locale_id.to_string(),
+                calculation: Default::default(),
             },
             metadata: Metadata {
                 application: APPLICATION.to_string(),
@@ -350,6 +585,7 @@ impl Model {
                 last_modified: now,
             },
             tables: HashMap::new(),
+            external_links: Vec::new(),
         };
         let parsed_formulas = Vec::new();
         let worksheets = &workbook.worksheets;
@@ -366,11 +602,24 @@ impl Model {
             parsed_defined_names: HashMap::new(),
             parser,
             cells,
+            dependency_graph: DependencyGraph::default(),
+            local_variables: Vec::new(),
+            lambda_depth: 0,
+            evaluation_steps: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            evaluation_threads: 1,
+            history: History::default(),
+            events: Events::default(),
+            external_resolver: None,
+            cached_external_values: ExternalSnapshot::default(),
+            shared: None,
+            removed_cells: HashSet::new(),
             locale,
             language,
             tz,
         };
         model.parse_formulas();
+        model.build_dependency_graph();
         Ok(model)
     }
 }
@@ -0,0 +1,233 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    thread,
+};
+
+use crate::{
+    calc_result::{CellReference, Range},
+    dependencies::{CellKey, DependencyGraph},
+    events::Events,
+    external::ExternalSnapshot,
+    history::History,
+    model::{CellState, Model},
+    types::{Cell, SheetData},
+};
+
+/// What a thread computed for a set of independent groups of formulas
+struct EvaluationResult {
+    /// The new content of the formulas and of the cells they spilled into
+    cells: Vec<(CellKey, Option<Cell>)>,
+    /// The areas the formulas spill into
+    spill_areas: Vec<(CellKey, Option<Range>)>,
+    /// The cells whose spilled value changed
+    spilled_cells: Vec<CellKey>,
+}
+
+fn find_root(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+impl Model {
+    /// Sets the number of threads used by `evaluate`.
+    /// With more than one thread, groups of formulas that do not depend on each other are
+    /// evaluated concurrently. Results are the same as with a single thread.
+    /// Volatile formulas (RAND, NOW, INDIRECT, ...) and the formulas connected to them are always
+    /// evaluated on the calling thread, in sheet, row and column order.
+    pub fn set_evaluation_threads(&mut self, threads: usize) {
+        self.evaluation_threads = threads.max(1);
+    }
+
+    /// Splits `cells` into groups of formulas that do not read from each other.
+    /// The cells of each group keep the order they had in `cells`.
+    fn get_independent_groups(&self, cells: &[CellReference]) -> Vec<Vec<CellReference>> {
+        let index: HashMap<CellKey, usize> = cells
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| ((cell.sheet, cell.row, cell.column), i))
+            .collect();
+        let mut parents: Vec<usize> = (0..cells.len()).collect();
+        for (i, cell) in cells.iter().enumerate() {
+            for precedent in self.get_formula_precedents(*cell) {
+                if let Some(&j) = index.get(&(precedent.sheet, precedent.row, precedent.column)) {
+                    let root_i = find_root(&mut parents, i);
+                    let root_j = find_root(&mut parents, j);
+                    parents[root_i.max(root_j)] = root_i.min(root_j);
+                }
+            }
+        }
+        let mut group_index: HashMap<usize, usize> = HashMap::new();
+        let mut groups: Vec<Vec<CellReference>> = Vec::new();
+        for (i, cell) in cells.iter().enumerate() {
+            let root = find_root(&mut parents, i);
+            let group = *group_index.entry(root).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[group].push(*cell);
+        }
+        groups
+    }
+
+    /// Returns a model for a thread of a parallel evaluation. It has no cells, parsed formulas,
+    /// evaluation state or dependency graph of its own: it reads them from the shared model and
+    /// keeps what it writes to itself.
+    fn new_worker(&mut self) -> Model {
+        // Everything but the cells and the shared strings is small enough to be copied
+        let sheet_data: Vec<SheetData> = self
+            .workbook
+            .worksheets
+            .iter_mut()
+            .map(|worksheet| std::mem::take(&mut worksheet.sheet_data))
+            .collect();
+        let shared_strings = std::mem::take(&mut self.workbook.shared_strings);
+        let workbook = self.workbook.clone();
+        for (worksheet, sheet_data) in self.workbook.worksheets.iter_mut().zip(sheet_data) {
+            worksheet.sheet_data = sheet_data;
+        }
+        self.workbook.shared_strings = shared_strings;
+        Model {
+            workbook,
+            parsed_formulas: Vec::new(),
+            parsed_defined_names: HashMap::new(),
+            parser: self.parser.clone(),
+            cells: HashMap::new(),
+            dependency_graph: DependencyGraph::default(),
+            local_variables: Vec::new(),
+            lambda_depth: 0,
+            evaluation_steps: None,
+            evaluation_threads: 1,
+            history: History::default(),
+            events: Events::default(),
+            external_resolver: self.external_resolver.clone(),
+            // Read from the shared model
+            cached_external_values: ExternalSnapshot::default(),
+            shared: None,
+            removed_cells: HashSet::new(),
+            locale: self.locale.clone(),
+            language: self.language.clone(),
+            tz: self.tz,
+        }
+    }
+
+    /// Evaluates `cells` on a thread of a parallel evaluation and returns what changed
+    fn evaluate_group(mut self, cells: &[CellReference]) -> EvaluationResult {
+        for cell in cells {
+            self.evaluate_cell(*cell);
+        }
+        let spilled_cells = self.dependency_graph.take_spilled_cells();
+        let keys = cells.iter().map(|cell| (cell.sheet, cell.row, cell.column));
+        let changed_cells = keys
+            .clone()
+            .chain(spilled_cells.iter().copied())
+            .map(|(sheet, row, column)| {
+                let cell = self.get_cell(sheet, row, column).cloned();
+                ((sheet, row, column), cell)
+            })
+            .collect();
+        let spill_areas = keys
+            .map(|key| (key, self.dependency_graph.get_spill_area(key).cloned()))
+            .collect();
+        EvaluationResult {
+            cells: changed_cells,
+            spill_areas,
+            spilled_cells,
+        }
+    }
+
+    /// Evaluates the groups of formulas in `cells` that do not depend on each other concurrently.
+    /// Cells that could not be evaluated this way are left for the sequential evaluation.
+    pub(crate) fn evaluate_in_parallel(&mut self, cells: &[CellReference]) {
+        let mut groups: Vec<Vec<CellReference>> = self
+            .get_independent_groups(cells)
+            .into_iter()
+            .filter(|group| {
+                !group.iter().any(|cell| {
+                    self.dependency_graph
+                        .is_volatile((cell.sheet, cell.row, cell.column))
+                })
+            })
+            .collect();
+        let threads = self.evaluation_threads.min(groups.len());
+        if threads < 2 {
+            return;
+        }
+        // The largest groups first, each one to the thread with less work
+        groups.sort_by_key(|group| Reverse(group.len()));
+        let mut work: Vec<Vec<CellReference>> = vec![Vec::new(); threads];
+        for group in groups {
+            if let Some(cells) = work.iter_mut().min_by_key(|cells| cells.len()) {
+                cells.extend(group);
+            }
+        }
+
+        // The threads read from the model, which can't change until all of them are done
+        let worker = self.new_worker();
+        let shared = Arc::new(std::mem::replace(self, worker.clone()));
+        let results = thread::scope(|scope| {
+            let mut handles = Vec::with_capacity(work.len());
+            for cells in &work {
+                let mut worker = worker.clone();
+                // Each thread knows the spill areas of its own formulas
+                for cell in cells {
+                    let key = (cell.sheet, cell.row, cell.column);
+                    let area = shared.dependency_graph.get_spill_area(key).cloned();
+                    worker.dependency_graph.set_spill_area(key, area);
+                }
+                worker.shared = Some(Arc::clone(&shared));
+                handles.push(scope.spawn(move || worker.evaluate_group(cells)));
+            }
+            handles
+                .into_iter()
+                .map(|handle| handle.join())
+                .collect::<Result<Vec<EvaluationResult>, _>>()
+        });
+        *self = Arc::try_unwrap(shared).unwrap_or_else(|shared| (*shared).clone());
+        let results = match results {
+            Ok(results) => results,
+            Err(_) => return,
+        };
+
+        // Two formulas spilling into the same cell depend on the order they are evaluated in
+        let mut spilled_cells = HashSet::new();
+        for result in &results {
+            for cell in &result.spilled_cells {
+                if !spilled_cells.insert(*cell) {
+                    return;
+                }
+            }
+        }
+
+        for result in results {
+            for ((sheet, row, column), cell) in result.cells {
+                self.touch_cell(sheet, row, column);
+                let worksheet = &mut self.workbook.worksheets[sheet as usize];
+                match cell {
+                    Some(cell) => worksheet.update_cell(row, column, cell),
+                    None => {
+                        if let Some(row_data) = worksheet.sheet_data.get_mut(&row) {
+                            row_data.remove(&column);
+                        }
+                    }
+                }
+            }
+            for (anchor, area) in result.spill_areas {
+                self.dependency_graph.set_spill_area(anchor, area);
+            }
+            for (sheet, row, column) in result.spilled_cells {
+                self.dependency_graph.mark_spilled(sheet, row, column);
+            }
+        }
+        for cells in work {
+            for cell in cells {
+                self.cells
+                    .insert((cell.sheet, cell.row, cell.column), CellState::Evaluated);
+            }
+        }
+    }
+}
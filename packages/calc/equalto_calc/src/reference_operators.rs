@@ -0,0 +1,243 @@
+//! The reference operators besides the range operator (`:`).
+//!
+//! A space between two references is their intersection: `=B2:D5 C1:C10` is `C2:C5`, and an
+//! empty intersection is `#NULL!`. A list of references in parenthesis is their union:
+//! `=SUM((A1:A3,C1:C3))`. Unions and intersections of unions are references with several areas,
+//! all of them in the same sheet.
+
+use crate::{
+    calc_result::{CalcResult, CellReference, Range},
+    expressions::{parser::Node, token::Error},
+    functions::Function,
+    model::{Model, ParsedDefinedName},
+};
+
+/// Returns true if the function takes any number of references and a union can be passed as its
+/// areas in separate arguments. Functions with a fixed number of arguments, like LARGE or RANK,
+/// read the areas of a union themselves.
+pub(crate) fn accepts_unions(kind: &Function) -> bool {
+    matches!(
+        kind,
+        Function::And
+            | Function::Average
+            | Function::Averagea
+            | Function::Concat
+            | Function::Count
+            | Function::Counta
+            | Function::Max
+            | Function::Maxa
+            | Function::Median
+            | Function::Min
+            | Function::Mina
+            | Function::Or
+            | Function::Product
+            | Function::Stdev
+            | Function::Stdevp
+            | Function::Subtotal
+            | Function::Sum
+            | Function::Var
+            | Function::Varp
+            | Function::Xor
+    )
+}
+
+/// Returns the cells in both `a` and `b`, if any
+pub(crate) fn intersect_ranges(a: &Range, b: &Range) -> Option<Range> {
+    if a.left.sheet != b.left.sheet {
+        return None;
+    }
+    let left = CellReference {
+        sheet: a.left.sheet,
+        row: a.left.row.max(b.left.row),
+        column: a.left.column.max(b.left.column),
+    };
+    let right = CellReference {
+        sheet: a.left.sheet,
+        row: a.right.row.min(b.right.row),
+        column: a.right.column.min(b.right.column),
+    };
+    if left.row > right.row || left.column > right.column {
+        return None;
+    }
+    Some(Range { left, right })
+}
+
+/// Returns the reference with the areas `ranges`, a single range if there is only one
+fn areas_to_result(mut ranges: Vec<Range>) -> CalcResult {
+    if ranges.len() == 1 {
+        let range = ranges.remove(0);
+        return CalcResult::Range {
+            left: range.left,
+            right: range.right,
+        };
+    }
+    CalcResult::Areas(ranges)
+}
+
+/// Returns an absolute reference to `range`
+fn range_to_node(range: &Range, sheet_name: &str) -> Node {
+    Node::RangeKind {
+        sheet_name: Some(sheet_name.to_string()),
+        sheet_index: range.left.sheet,
+        absolute_row1: true,
+        absolute_column1: true,
+        row1: range.left.row,
+        column1: range.left.column,
+        absolute_row2: true,
+        absolute_column2: true,
+        row2: range.right.row,
+        column2: range.right.column,
+    }
+}
+
+impl Model {
+    /// Evaluates `node` as a reference and returns its areas
+    pub(crate) fn get_areas(
+        &mut self,
+        node: &Node,
+        cell: CellReference,
+    ) -> Result<Vec<Range>, CalcResult> {
+        if let Node::VariableKind(name) = node {
+            // A name for a single cell evaluates to its value
+            let lower_name = name.to_lowercase();
+            if !self.local_variables.iter().any(|(n, _)| *n == lower_name) {
+                if let Some(ParsedDefinedName::CellReference(reference)) =
+                    self.get_parsed_defined_name(name, cell.sheet)
+                {
+                    return Ok(vec![Range {
+                        left: *reference,
+                        right: *reference,
+                    }]);
+                }
+            }
+        }
+        match self.evaluate_node_with_reference(node, cell) {
+            CalcResult::Range { left, right } => Ok(vec![Range { left, right }]),
+            CalcResult::Areas(ranges) => Ok(ranges),
+            error @ CalcResult::Error { .. } => Err(error),
+            _ => Err(CalcResult::new_error(
+                Error::VALUE,
+                cell,
+                "Expecting a reference".to_string(),
+            )),
+        }
+    }
+
+    /// Evaluates the intersection of two references: B2:D5 C1:C10
+    pub(crate) fn get_intersection(
+        &mut self,
+        left: &Node,
+        right: &Node,
+        cell: CellReference,
+    ) -> CalcResult {
+        let left = match self.get_areas(left, cell) {
+            Ok(ranges) => ranges,
+            Err(error) => return error,
+        };
+        let right = match self.get_areas(right, cell) {
+            Ok(ranges) => ranges,
+            Err(error) => return error,
+        };
+        if left[0].left.sheet != right[0].left.sheet {
+            return CalcResult::new_error(
+                Error::VALUE,
+                cell,
+                "Ranges are in different sheets".to_string(),
+            );
+        }
+        let mut ranges = Vec::new();
+        for a in &left {
+            for b in &right {
+                if let Some(range) = intersect_ranges(a, b) {
+                    ranges.push(range);
+                }
+            }
+        }
+        if ranges.is_empty() {
+            return CalcResult::new_error(Error::NULL, cell, "Empty intersection".to_string());
+        }
+        areas_to_result(ranges)
+    }
+
+    /// Evaluates the union of references: (A1:A3,C1:C3)
+    pub(crate) fn get_union(&mut self, items: &[Node], cell: CellReference) -> CalcResult {
+        let mut ranges = Vec::new();
+        for item in items {
+            match self.get_areas(item, cell) {
+                Ok(areas) => ranges.extend(areas),
+                Err(error) => return error,
+            }
+        }
+        if ranges
+            .iter()
+            .any(|range| range.left.sheet != ranges[0].left.sheet)
+        {
+            return CalcResult::new_error(
+                Error::VALUE,
+                cell,
+                "Ranges are in different sheets".to_string(),
+            );
+        }
+        areas_to_result(ranges)
+    }
+
+    /// Returns true if `node` is a union or an intersection, directly or through a name
+    fn is_reference_operation(&self, node: &Node, sheet: u32) -> bool {
+        match node {
+            Node::OpUnionKind(_) | Node::OpIntersectKind { .. } => true,
+            Node::VariableKind(name) => {
+                let name = name.to_lowercase();
+                if let Some((_, value)) =
+                    self.local_variables.iter().rev().find(|(n, _)| *n == name)
+                {
+                    return matches!(value, CalcResult::Areas(_));
+                }
+                matches!(
+                    self.get_parsed_defined_name(&name, sheet),
+                    Some(ParsedDefinedName::Formula(
+                        Node::OpUnionKind(_) | Node::OpIntersectKind { .. }
+                    ))
+                )
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the arguments of a function with the references with several areas replaced
+    /// by each of their areas, or None if there are none
+    pub(crate) fn expand_areas(&mut self, args: &[Node], cell: CellReference) -> Option<Vec<Node>> {
+        if !args
+            .iter()
+            .any(|arg| self.is_reference_operation(arg, cell.sheet))
+        {
+            return None;
+        }
+        let mut expanded = Vec::new();
+        for arg in args {
+            if !self.is_reference_operation(arg, cell.sheet) {
+                expanded.push(arg.clone());
+                continue;
+            }
+            let ranges = match self.evaluate_node_in_context(arg, cell) {
+                CalcResult::Areas(ranges) => ranges,
+                CalcResult::Range { left, right } => vec![Range { left, right }],
+                CalcResult::Error { error, .. } => {
+                    expanded.push(Node::ErrorKind(error));
+                    continue;
+                }
+                _ => {
+                    expanded.push(Node::ErrorKind(Error::VALUE));
+                    continue;
+                }
+            };
+            for range in &ranges {
+                let sheet_name = match self.workbook.worksheets.get(range.left.sheet as usize) {
+                    Some(worksheet) => worksheet.get_name(),
+                    None => continue,
+                };
+                expanded.push(range_to_node(range, &sheet_name));
+            }
+        }
+        Some(expanded)
+    }
+}
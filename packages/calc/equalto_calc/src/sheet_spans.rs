@@ -0,0 +1,274 @@
+//! References to the same cells in a span of sheets: `=SUM(Jan:Dec!B5)`.
+//!
+//! A span goes from its first sheet to its last one in the order of the workbook, so sheets
+//! inserted or moved between them are part of it. Deleting the first or the last sheet makes the
+//! span end in the next sheet inside it, like in Excel.
+
+use crate::{
+    expressions::{
+        ast::{walk_mut, VisitorMut},
+        lexer::LexerMode,
+        parser::{
+            stringify::{to_rc_format, to_string},
+            Node,
+        },
+        types::CellReferenceRC,
+    },
+    functions::Function,
+    model::{Model, ParsedDefinedName},
+};
+
+/// Returns true if the function accepts references to spans of sheets, those that do in Excel
+pub(crate) fn accepts_sheet_spans(kind: &Function) -> bool {
+    matches!(
+        kind,
+        Function::Average
+            | Function::Averagea
+            | Function::Count
+            | Function::Counta
+            | Function::Max
+            | Function::Maxa
+            | Function::Min
+            | Function::Mina
+            | Function::Product
+            | Function::Stdev
+            | Function::Stdevp
+            | Function::Sum
+            | Function::Var
+            | Function::Varp
+    )
+}
+
+/// Returns the reference to the cells of the span `node` in one of its sheets
+fn get_sheet_reference(node: &Node, sheet_name: &str, sheet_index: u32) -> Option<Node> {
+    match node {
+        Node::SheetSpanReferenceKind {
+            absolute_row,
+            absolute_column,
+            row,
+            column,
+            ..
+        } => Some(Node::ReferenceKind {
+            sheet_name: Some(sheet_name.to_string()),
+            sheet_index,
+            absolute_row: *absolute_row,
+            absolute_column: *absolute_column,
+            row: *row,
+            column: *column,
+        }),
+        Node::SheetSpanRangeKind {
+            absolute_row1,
+            absolute_column1,
+            row1,
+            column1,
+            absolute_row2,
+            absolute_column2,
+            row2,
+            column2,
+            ..
+        } => Some(Node::RangeKind {
+            sheet_name: Some(sheet_name.to_string()),
+            sheet_index,
+            absolute_row1: *absolute_row1,
+            absolute_column1: *absolute_column1,
+            row1: *row1,
+            column1: *column1,
+            absolute_row2: *absolute_row2,
+            absolute_column2: *absolute_column2,
+            row2: *row2,
+            column2: *column2,
+        }),
+        _ => None,
+    }
+}
+
+/// Moves the first or last sheet of the spans when that sheet is deleted
+struct DeletedSheet<'a> {
+    sheet_index: u32,
+    worksheet_names: &'a [String],
+    changed: bool,
+}
+
+impl VisitorMut for DeletedSheet<'_> {
+    fn enter(&mut self, node: &mut Node) -> bool {
+        let (first_sheet_index, last_sheet_index) = match node {
+            Node::SheetSpanReferenceKind {
+                first_sheet_index,
+                last_sheet_index,
+                ..
+            }
+            | Node::SheetSpanRangeKind {
+                first_sheet_index,
+                last_sheet_index,
+                ..
+            } => (*first_sheet_index, *last_sheet_index),
+            _ => return true,
+        };
+        if self.sheet_index != first_sheet_index && self.sheet_index != last_sheet_index {
+            return true;
+        }
+        // The end of the span moves one sheet towards the other end
+        let step_inside =
+            |index: u32, other: u32| if index < other { index + 1 } else { index - 1 };
+        let new_first_index = if first_sheet_index == self.sheet_index {
+            step_inside(first_sheet_index, last_sheet_index)
+        } else {
+            first_sheet_index
+        };
+        let new_last_index = if last_sheet_index == self.sheet_index {
+            step_inside(last_sheet_index, first_sheet_index)
+        } else {
+            last_sheet_index
+        };
+        let first_name = &self.worksheet_names[new_first_index as usize];
+        let last_name = &self.worksheet_names[new_last_index as usize];
+        self.changed = true;
+        if new_first_index == new_last_index {
+            // Only one sheet is left: Jan:Feb!B5 is Feb!B5 once Jan is deleted
+            if let Some(reference) = get_sheet_reference(node, first_name, new_first_index) {
+                *node = reference;
+            }
+            return true;
+        }
+        if let Node::SheetSpanReferenceKind {
+            first_sheet,
+            first_sheet_index,
+            last_sheet,
+            last_sheet_index,
+            ..
+        }
+        | Node::SheetSpanRangeKind {
+            first_sheet,
+            first_sheet_index,
+            last_sheet,
+            last_sheet_index,
+            ..
+        } = node
+        {
+            *first_sheet = first_name.clone();
+            *first_sheet_index = new_first_index;
+            *last_sheet = last_name.clone();
+            *last_sheet_index = new_last_index;
+        }
+        true
+    }
+}
+
+impl Model {
+    /// Returns the references to each sheet of a span: Jan:Mar!B5 is Jan!B5, Feb!B5 and Mar!B5
+    pub(crate) fn expand_sheet_span(&self, node: &Node) -> Vec<Node> {
+        let (first_sheet_index, last_sheet_index) = match node {
+            Node::SheetSpanReferenceKind {
+                first_sheet_index,
+                last_sheet_index,
+                ..
+            }
+            | Node::SheetSpanRangeKind {
+                first_sheet_index,
+                last_sheet_index,
+                ..
+            } => (*first_sheet_index, *last_sheet_index),
+            _ => return vec![node.clone()],
+        };
+        let sheets =
+            first_sheet_index.min(last_sheet_index)..=first_sheet_index.max(last_sheet_index);
+        sheets
+            .filter_map(|sheet| {
+                let worksheet = self.workbook.worksheets.get(sheet as usize)?;
+                get_sheet_reference(node, &worksheet.name, sheet)
+            })
+            .collect()
+    }
+
+    /// Returns the span of sheets `node` refers to, directly or through a defined name
+    fn get_sheet_span<'a>(&'a self, node: &'a Node, sheet: u32) -> Option<&'a Node> {
+        let node = match node {
+            Node::VariableKind(name) => {
+                let name = name.to_lowercase();
+                if self.local_variables.iter().any(|(n, _)| *n == name) {
+                    return None;
+                }
+                match self.get_parsed_defined_name(&name, sheet)? {
+                    ParsedDefinedName::Formula(node) => node,
+                    _ => return None,
+                }
+            }
+            _ => node,
+        };
+        match node {
+            Node::SheetSpanReferenceKind { .. } | Node::SheetSpanRangeKind { .. } => Some(node),
+            _ => None,
+        }
+    }
+
+    /// Returns the arguments of a function with the spans of sheets replaced by the references
+    /// to each of their sheets, or None if there are no spans
+    pub(crate) fn expand_sheet_spans(&self, args: &[Node], sheet: u32) -> Option<Vec<Node>> {
+        if !args
+            .iter()
+            .any(|arg| self.get_sheet_span(arg, sheet).is_some())
+        {
+            return None;
+        }
+        Some(
+            args.iter()
+                .flat_map(|arg| match self.get_sheet_span(arg, sheet) {
+                    Some(span) => self.expand_sheet_span(span),
+                    None => vec![arg.clone()],
+                })
+                .collect(),
+        )
+    }
+
+    /// Updates the spans of sheets in formulas and defined names before the sheet is deleted
+    pub(crate) fn delete_sheet_in_spans(&mut self, sheet_index: u32) {
+        let worksheet_names = self.workbook.get_worksheet_names();
+        let mut visitor = DeletedSheet {
+            sheet_index,
+            worksheet_names: &worksheet_names,
+            changed: false,
+        };
+
+        // All internal formulas are R1C1
+        self.parser.set_lexer_mode(LexerMode::R1C1);
+        for worksheet in &mut self.workbook.worksheets {
+            let context = Some(CellReferenceRC {
+                sheet: worksheet.get_name(),
+                row: 1,
+                column: 1,
+            });
+            for formula in &mut worksheet.shared_formulas {
+                if !formula.contains(':') {
+                    continue;
+                }
+                let mut node = self.parser.parse(formula, &context);
+                visitor.changed = false;
+                walk_mut(&mut node, &mut visitor);
+                if visitor.changed {
+                    *formula = to_rc_format(&node);
+                }
+            }
+        }
+
+        // Defined names are A1
+        self.parser.set_lexer_mode(LexerMode::A1);
+        for defined_name in &mut self.workbook.defined_names {
+            if !defined_name.formula.contains(':') {
+                continue;
+            }
+            let context = CellReferenceRC {
+                sheet: worksheet_names[0].clone(),
+                row: 1,
+                column: 1,
+            };
+            let mut node = self
+                .parser
+                .parse(&defined_name.formula, &Some(context.clone()));
+            visitor.changed = false;
+            walk_mut(&mut node, &mut visitor);
+            if visitor.changed {
+                defined_name.formula = to_string(&node, &context);
+            }
+        }
+    }
+}
@@ -0,0 +1,639 @@
+//! An optimisation solver over the cells of a model, like the Solver add-in of Excel.
+//!
+//! Linear models are solved with the simplex method and branch and bound, nonlinear ones with
+//! the Nelder-Mead method, adding the constraints to the objective as penalties.
+
+mod nelder_mead;
+mod simplex;
+mod storage;
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    calc_result::CellReference, history::ChangeScope, model::Model, types::Cell,
+    utils::ParsedReference,
+};
+
+use simplex::{LinearConstraint, LinearOutcome, LinearProgram, PivotBudget, Relation};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SolverGoal {
+    Maximize,
+    Minimize,
+    /// Make the objective cell equal to the value
+    Value(f64),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintOperator {
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Integer,
+    Binary,
+}
+
+/// A constraint on the cells of a range, like `Sheet1!$A$1:$A$3 <= 10`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SolverConstraint {
+    /// A cell or a range
+    pub cells: String,
+    pub operator: ConstraintOperator,
+    /// A number, a cell or a range of the same size as `cells`.
+    /// Integer and binary constraints do not have a value.
+    #[serde(default)]
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SolverMethod {
+    /// For models where the objective and the constraints are linear in the variables
+    Linear,
+    #[default]
+    Nonlinear,
+}
+
+/// What to optimise and how.
+/// References without a sheet name refer to the sheet the solver belongs to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct SolverDefinition {
+    /// The cell to optimise
+    pub objective: String,
+    pub goal: SolverGoal,
+    /// The cells the solver changes, as cells or ranges
+    pub variables: Vec<String>,
+    pub constraints: Vec<SolverConstraint>,
+    pub method: SolverMethod,
+    /// Variables without a lower bound in the constraints can't be negative
+    pub non_negative: bool,
+    /// Maximum number of simplex pivots or, for nonlinear models, of trial solutions
+    pub max_iterations: usize,
+    /// How much the constraints can be violated
+    pub precision: f64,
+}
+
+impl Default for SolverDefinition {
+    fn default() -> Self {
+        SolverDefinition {
+            objective: "".to_string(),
+            goal: SolverGoal::Maximize,
+            variables: vec![],
+            constraints: vec![],
+            method: SolverMethod::default(),
+            non_negative: true,
+            max_iterations: 1000,
+            precision: 1e-6,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolverStatus {
+    /// The constraints are satisfied and the objective can't be improved.
+    /// For nonlinear models the solution is a local optimum.
+    Optimal,
+    /// The constraints are satisfied but the iterations ran out before proving optimality
+    Feasible,
+    /// There is no solution satisfying the constraints
+    Infeasible,
+    /// The objective can be improved without limit
+    Unbounded,
+    /// The iterations ran out before satisfying the constraints
+    IterationLimit,
+}
+
+/// The outcome of `Model::solve`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SolverResult {
+    pub status: SolverStatus,
+    /// The value of the objective cell with the solution
+    pub objective_value: f64,
+    /// The values of the variable cells, in the order of the definition
+    pub values: Vec<f64>,
+    pub iterations: usize,
+}
+
+/// The value a constraint compares the cells to
+enum Bound {
+    Number(f64),
+    Cell(CellReference),
+}
+
+/// `cell` `relation` `bound`
+struct Row {
+    cell: CellReference,
+    relation: Relation,
+    bound: Bound,
+}
+
+/// A solver definition with all the references resolved
+struct Problem {
+    objective: CellReference,
+    goal: SolverGoal,
+    variables: Vec<CellReference>,
+    /// Style of each variable cell
+    styles: Vec<i32>,
+    rows: Vec<Row>,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+    integer: Vec<bool>,
+    precision: f64,
+}
+
+/// The value of the objective and of each row, as the difference of both sides
+struct Evaluation {
+    objective: f64,
+    rows: Vec<f64>,
+}
+
+impl Problem {
+    /// Moves `values` inside the bounds and rounds the integer variables
+    fn project(&self, values: &[f64]) -> Vec<f64> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, &x)| {
+                // The bounds may cross if the constraints are contradictory, where `clamp` panics
+                #[allow(clippy::manual_clamp)]
+                let x = x.max(self.lower[index]).min(self.upper[index]);
+                if self.integer[index] {
+                    x.round()
+                } else {
+                    x
+                }
+            })
+            .collect()
+    }
+
+    /// How much the evaluation violates each constraint, including reaching the goal value
+    fn violations(&self, evaluation: &Evaluation) -> Vec<f64> {
+        let mut violations: Vec<f64> = self
+            .rows
+            .iter()
+            .zip(&evaluation.rows)
+            .map(|(row, &difference)| match row.relation {
+                Relation::LessOrEqual => difference.max(0.0),
+                Relation::Equal => difference.abs(),
+                Relation::GreaterOrEqual => (-difference).max(0.0),
+            })
+            .collect();
+        if let SolverGoal::Value(value) = self.goal {
+            violations.push((evaluation.objective - value).abs());
+        }
+        violations
+    }
+
+    fn is_feasible(&self, evaluation: &Evaluation) -> bool {
+        self.violations(evaluation)
+            .iter()
+            .all(|&violation| violation <= self.precision)
+    }
+
+    /// Sets the values of the variables, evaluates the model and reads the objective and the rows.
+    /// Returns None if any of them is not a number.
+    fn evaluate(&self, model: &mut Model, values: &[f64]) -> Option<Evaluation> {
+        for ((cell, style), &value) in self.variables.iter().zip(&self.styles).zip(values) {
+            model.workbook.worksheets[cell.sheet as usize].set_cell_with_number(
+                cell.row,
+                cell.column,
+                value,
+                *style,
+            );
+            model
+                .dependency_graph
+                .mark_dirty(cell.sheet, cell.row, cell.column);
+        }
+        model.evaluate();
+        let objective = get_number(model, self.objective)?;
+        let mut rows = Vec::with_capacity(self.rows.len());
+        for row in &self.rows {
+            let bound = match row.bound {
+                Bound::Number(value) => value,
+                Bound::Cell(cell) => get_number(model, cell)?,
+            };
+            rows.push(get_number(model, row.cell)? - bound);
+        }
+        Some(Evaluation { objective, rows })
+    }
+
+    /// Solves a model that is linear in the variables.
+    /// The coefficients are found changing one variable at a time from `start`.
+    fn solve_linear(
+        &self,
+        model: &mut Model,
+        start: &[f64],
+        max_iterations: usize,
+    ) -> Result<(LinearOutcome, usize), String> {
+        let not_evaluable = || "The model can't be evaluated at the starting values".to_string();
+        let base = self.evaluate(model, start).ok_or_else(not_evaluable)?;
+        let mut objective = Vec::with_capacity(start.len());
+        let mut coefficients = vec![Vec::with_capacity(start.len()); self.rows.len()];
+        for index in 0..start.len() {
+            let mut values = start.to_vec();
+            values[index] += 1.0;
+            let evaluation = self.evaluate(model, &values).ok_or_else(not_evaluable)?;
+            objective.push(evaluation.objective - base.objective);
+            for ((row, value), base_value) in coefficients
+                .iter_mut()
+                .zip(&evaluation.rows)
+                .zip(&base.rows)
+            {
+                row.push(value - base_value);
+            }
+        }
+
+        // The model is linear if a point changing all the variables is where it is expected
+        let steps: Vec<f64> = (0..start.len())
+            .map(|index| 1.5 + 0.25 * index as f64)
+            .collect();
+        let values: Vec<f64> = start.iter().zip(&steps).map(|(x, s)| x + s).collect();
+        let evaluation = self.evaluate(model, &values).ok_or_else(not_evaluable)?;
+        let is_expected = |base: f64, coefficients: &[f64], actual: f64| {
+            let expected = base + dot(coefficients, &steps);
+            let scale = coefficients
+                .iter()
+                .zip(&steps)
+                .fold(1.0_f64.max(base.abs()), |scale, (c, s)| {
+                    scale.max((c * s).abs())
+                });
+            (expected - actual).abs() <= 1e-7 * scale
+        };
+        let is_linear = is_expected(base.objective, &objective, evaluation.objective)
+            && coefficients
+                .iter()
+                .enumerate()
+                .all(|(index, row)| is_expected(base.rows[index], row, evaluation.rows[index]));
+        if !is_linear {
+            return Err("The model is not linear, use the nonlinear method".to_string());
+        }
+
+        // Each row is base + coefficients·(x - start) `relation` 0
+        let mut constraints: Vec<LinearConstraint> = self
+            .rows
+            .iter()
+            .zip(coefficients)
+            .zip(&base.rows)
+            .map(|((row, coefficients), base)| LinearConstraint {
+                value: dot(&coefficients, start) - base,
+                coefficients,
+                relation: row.relation,
+            })
+            .collect();
+        let objective = match self.goal {
+            SolverGoal::Maximize => objective.iter().map(|c| -c).collect(),
+            SolverGoal::Minimize => objective,
+            SolverGoal::Value(value) => {
+                constraints.push(LinearConstraint {
+                    value: value - base.objective + dot(&objective, start),
+                    coefficients: objective,
+                    relation: Relation::Equal,
+                });
+                vec![0.0; start.len()]
+            }
+        };
+        let program = LinearProgram {
+            objective,
+            constraints,
+            lower: self.lower.clone(),
+            upper: self.upper.clone(),
+        };
+        let mut budget = PivotBudget {
+            used: 0,
+            max: max_iterations,
+        };
+        let outcome = simplex::solve_linear_program(&program, &self.integer, &mut budget);
+        Ok((outcome, budget.used))
+    }
+
+    /// Solves a nonlinear model minimizing the objective plus the squared violations of the
+    /// constraints, with larger weights until they are satisfied.
+    fn solve_nonlinear(
+        &self,
+        model: &mut Model,
+        start: &[f64],
+        max_iterations: usize,
+    ) -> (SolverStatus, Vec<f64>, usize) {
+        let mut values = start.to_vec();
+        let mut iterations = 0;
+        let mut weight = 1.0;
+        loop {
+            let minimum = nelder_mead::minimize(
+                |point| {
+                    let point = self.project(point);
+                    let evaluation = match self.evaluate(model, &point) {
+                        Some(evaluation) => evaluation,
+                        None => return f64::INFINITY,
+                    };
+                    let objective = match self.goal {
+                        SolverGoal::Maximize => -evaluation.objective,
+                        SolverGoal::Minimize => evaluation.objective,
+                        SolverGoal::Value(_) => 0.0,
+                    };
+                    let penalty: f64 = self
+                        .violations(&evaluation)
+                        .iter()
+                        .map(|violation| violation * violation)
+                        .sum();
+                    objective + weight * penalty
+                },
+                &values,
+                max_iterations - iterations,
+                self.precision,
+            );
+            iterations += minimum.evaluations;
+            values = self.project(&minimum.point);
+            let feasible = self
+                .evaluate(model, &values)
+                .map_or(false, |evaluation| self.is_feasible(&evaluation));
+            let exhausted = iterations >= max_iterations;
+            if feasible {
+                let status = if minimum.converged {
+                    SolverStatus::Optimal
+                } else {
+                    SolverStatus::Feasible
+                };
+                return (status, values, iterations);
+            }
+            if exhausted {
+                return (SolverStatus::IterationLimit, values, iterations);
+            }
+            if weight >= 1e12 {
+                return (SolverStatus::Infeasible, values, iterations);
+            }
+            weight *= 100.0;
+        }
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// The number in a cell. Empty cells are zero.
+fn get_number(model: &Model, cell: CellReference) -> Option<f64> {
+    match model.workbook.worksheets[cell.sheet as usize].cell(cell.row, cell.column) {
+        None => Some(0.0),
+        Some(Cell::NumberCell { v, .. }) | Some(Cell::CellFormulaNumber { v, .. }) => Some(*v),
+        _ => None,
+    }
+}
+
+impl Model {
+    /// Parses a cell or a range like `Sheet1!$A$1:$B$2` and returns its cells, row by row
+    fn get_solver_cells(&self, sheet: u32, reference: &str) -> Result<Vec<CellReference>, String> {
+        let reference = reference.trim();
+        let reference = reference.strip_prefix('=').unwrap_or(reference);
+        let parsed = ParsedReference::parse_reference_formula(
+            Some(sheet),
+            reference,
+            &self.locale,
+            |name| self.get_sheet_index_by_name(name),
+        )?;
+        Ok(match parsed {
+            ParsedReference::CellReference(cell) => vec![cell],
+            ParsedReference::Range(left, right) => {
+                let mut cells = Vec::new();
+                for row in left.row..=right.row {
+                    for column in left.column..=right.column {
+                        cells.push(CellReference {
+                            sheet: left.sheet,
+                            row,
+                            column,
+                        });
+                    }
+                }
+                cells
+            }
+        })
+    }
+
+    /// Resolves the references of a solver definition for the sheet `sheet`
+    fn get_solver_problem(
+        &self,
+        sheet: u32,
+        definition: &SolverDefinition,
+    ) -> Result<Problem, String> {
+        let objective = match self.get_solver_cells(sheet, &definition.objective)?[..] {
+            [cell] => cell,
+            _ => return Err("The objective must be a single cell".to_string()),
+        };
+        let mut variables: Vec<CellReference> = Vec::new();
+        let mut seen = HashSet::new();
+        for reference in &definition.variables {
+            for cell in self.get_solver_cells(sheet, reference)? {
+                if seen.insert((cell.sheet, cell.row, cell.column)) {
+                    variables.push(cell);
+                }
+            }
+        }
+        if variables.is_empty() {
+            return Err("There are no variable cells".to_string());
+        }
+        for cell in &variables {
+            let content = self.workbook.worksheets[cell.sheet as usize].cell(cell.row, cell.column);
+            if content.map_or(false, |content| content.get_formula().is_some()) {
+                return Err("The variable cells can't contain formulas".to_string());
+            }
+        }
+        let count = variables.len();
+        let variable_index = |cell: &CellReference| variables.iter().position(|v| v == cell);
+
+        let mut lower = vec![f64::NEG_INFINITY; count];
+        let mut upper = vec![f64::INFINITY; count];
+        let mut integer = vec![false; count];
+        let mut rows = Vec::new();
+        for constraint in &definition.constraints {
+            let cells = self.get_solver_cells(sheet, &constraint.cells)?;
+            let relation = match constraint.operator {
+                ConstraintOperator::LessOrEqual => Relation::LessOrEqual,
+                ConstraintOperator::Equal => Relation::Equal,
+                ConstraintOperator::GreaterOrEqual => Relation::GreaterOrEqual,
+                ConstraintOperator::Integer | ConstraintOperator::Binary => {
+                    for cell in &cells {
+                        let index = variable_index(cell).ok_or_else(|| {
+                            "Integer and binary constraints must refer to variable cells"
+                                .to_string()
+                        })?;
+                        integer[index] = true;
+                        if constraint.operator == ConstraintOperator::Binary {
+                            lower[index] = lower[index].max(0.0);
+                            upper[index] = upper[index].min(1.0);
+                        }
+                    }
+                    continue;
+                }
+            };
+            let value = constraint.value.trim();
+            let value = value.strip_prefix('=').unwrap_or(value);
+            let bounds: Vec<Bound> = match value.parse::<f64>() {
+                Ok(number) => cells.iter().map(|_| Bound::Number(number)).collect(),
+                Err(_) => {
+                    let invalid = || {
+                        format!(
+                            "The value of the constraint on {} must be a number, a cell or a range of the same size",
+                            constraint.cells
+                        )
+                    };
+                    let bound_cells = self.get_solver_cells(sheet, value).map_err(|_| invalid())?;
+                    match bound_cells[..] {
+                        [cell] => cells.iter().map(|_| Bound::Cell(cell)).collect(),
+                        _ if bound_cells.len() == cells.len() => {
+                            bound_cells.into_iter().map(Bound::Cell).collect()
+                        }
+                        _ => return Err(invalid()),
+                    }
+                }
+            };
+            for (cell, bound) in cells.into_iter().zip(bounds) {
+                // Constant bounds on variables are applied directly
+                if let (Some(index), Bound::Number(value)) = (variable_index(&cell), &bound) {
+                    if relation != Relation::LessOrEqual {
+                        lower[index] = lower[index].max(*value);
+                    }
+                    if relation != Relation::GreaterOrEqual {
+                        upper[index] = upper[index].min(*value);
+                    }
+                    continue;
+                }
+                rows.push(Row {
+                    cell,
+                    relation,
+                    bound,
+                });
+            }
+        }
+        if definition.non_negative {
+            for low in lower.iter_mut() {
+                if *low == f64::NEG_INFINITY {
+                    *low = 0.0;
+                }
+            }
+        }
+        let styles = variables
+            .iter()
+            .map(|cell| self.get_cell_style_index(cell.sheet, cell.row, cell.column))
+            .collect();
+        Ok(Problem {
+            objective,
+            goal: definition.goal.clone(),
+            variables,
+            styles,
+            rows,
+            lower,
+            upper,
+            integer,
+            precision: definition.precision,
+        })
+    }
+
+    /// Looks for the values of the variable cells that optimise the objective cell while
+    /// satisfying the constraints. References without a sheet name refer to the sheet `sheet`.
+    /// With `apply` the variable cells keep the solution if one satisfying the constraints is
+    /// found, otherwise the model is left unchanged.
+    pub fn solve(
+        &mut self,
+        sheet: u32,
+        definition: &SolverDefinition,
+        apply: bool,
+    ) -> Result<SolverResult, String> {
+        self.workbook.worksheet(sheet)?;
+        let problem = self.get_solver_problem(sheet, definition)?;
+        let original_cells: Vec<Option<Cell>> = problem
+            .variables
+            .iter()
+            .map(|cell| {
+                self.workbook.worksheets[cell.sheet as usize]
+                    .cell(cell.row, cell.column)
+                    .cloned()
+            })
+            .collect();
+        let start: Vec<f64> = problem.project(
+            &original_cells
+                .iter()
+                .map(|cell| match cell {
+                    Some(Cell::NumberCell { v, .. }) => *v,
+                    _ => 0.0,
+                })
+                .collect::<Vec<f64>>(),
+        );
+
+        // Brings the model up to date, the values tried are not reported as events
+        self.evaluate();
+        let events = std::mem::take(&mut self.events);
+        let solution = if problem
+            .lower
+            .iter()
+            .zip(&problem.upper)
+            .any(|(low, high)| low > high)
+        {
+            Ok((SolverStatus::Infeasible, start.clone(), 0))
+        } else {
+            match definition.method {
+                SolverMethod::Linear => problem
+                    .solve_linear(self, &start, definition.max_iterations)
+                    .map(|(outcome, iterations)| match outcome {
+                        LinearOutcome::Optimal(values) => {
+                            (SolverStatus::Optimal, values, iterations)
+                        }
+                        LinearOutcome::Feasible(values) => {
+                            (SolverStatus::Feasible, values, iterations)
+                        }
+                        LinearOutcome::Infeasible => {
+                            (SolverStatus::Infeasible, start.clone(), iterations)
+                        }
+                        LinearOutcome::Unbounded => {
+                            (SolverStatus::Unbounded, start.clone(), iterations)
+                        }
+                        LinearOutcome::PivotLimit => {
+                            (SolverStatus::IterationLimit, start.clone(), iterations)
+                        }
+                    }),
+                SolverMethod::Nonlinear => {
+                    Ok(problem.solve_nonlinear(self, &start, definition.max_iterations))
+                }
+            }
+        };
+        let objective_value = match &solution {
+            Ok((_, values, _)) => problem
+                .evaluate(self, values)
+                .map_or(f64::NAN, |evaluation| evaluation.objective),
+            Err(_) => f64::NAN,
+        };
+
+        for (cell, original) in problem.variables.iter().zip(original_cells) {
+            let worksheet = &mut self.workbook.worksheets[cell.sheet as usize];
+            match original {
+                Some(original) => worksheet.update_cell(cell.row, cell.column, original),
+                None => {
+                    if let Some(row_data) = worksheet.sheet_data.get_mut(&cell.row) {
+                        row_data.remove(&cell.column);
+                    }
+                }
+            }
+            self.dependency_graph
+                .mark_dirty(cell.sheet, cell.row, cell.column);
+        }
+        self.evaluate();
+        self.events = events;
+
+        let (status, values, iterations) = solution?;
+        let found = matches!(status, SolverStatus::Optimal | SolverStatus::Feasible);
+        if apply && found {
+            self.record_change(ChangeScope::cells(&problem.variables), |model| {
+                for (cell, &value) in problem.variables.iter().zip(&values) {
+                    model.update_cell_with_number(cell.sheet, cell.row, cell.column, value);
+                }
+            });
+            self.evaluate();
+        }
+        Ok(SolverResult {
+            status,
+            objective_value,
+            values,
+            iterations,
+        })
+    }
+}
@@ -0,0 +1,137 @@
+//! The Nelder-Mead method, which minimizes a function without using its derivatives
+
+/// The result of `minimize`
+pub(crate) struct Minimum {
+    pub(crate) point: Vec<f64>,
+    pub(crate) evaluations: usize,
+    /// True if the simplex collapsed before using all the evaluations
+    pub(crate) converged: bool,
+}
+
+/// Minimizes `function` starting from `start`, evaluating it at most `max_evaluations` times.
+/// Points where the function can't be evaluated should return infinity.
+/// Stops when the values at the vertices of the simplex and the vertices themselves are
+/// within `tolerance`.
+pub(crate) fn minimize<F: FnMut(&[f64]) -> f64>(
+    mut function: F,
+    start: &[f64],
+    max_evaluations: usize,
+    tolerance: f64,
+) -> Minimum {
+    let dimension = start.len();
+    let mut evaluations = 0;
+    let mut evaluate = |point: &[f64], evaluations: &mut usize| -> f64 {
+        *evaluations += 1;
+        let value = function(point);
+        if value.is_nan() {
+            f64::INFINITY
+        } else {
+            value
+        }
+    };
+
+    let mut vertices: Vec<(Vec<f64>, f64)> = Vec::with_capacity(dimension + 1);
+    let value = evaluate(start, &mut evaluations);
+    vertices.push((start.to_vec(), value));
+    for index in 0..dimension {
+        if evaluations >= max_evaluations {
+            break;
+        }
+        let mut point = start.to_vec();
+        point[index] += if point[index] == 0.0 {
+            0.1
+        } else {
+            0.05 * point[index].abs()
+        };
+        let value = evaluate(&point, &mut evaluations);
+        vertices.push((point, value));
+    }
+
+    let mut converged = false;
+    while vertices.len() == dimension + 1 && evaluations < max_evaluations {
+        vertices.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let best = &vertices[0];
+        let worst = &vertices[dimension];
+        let size = vertices[1..].iter().fold(0.0_f64, |size, (point, _)| {
+            point
+                .iter()
+                .zip(&best.0)
+                .fold(size, |size, (x, y)| size.max((x - y).abs()))
+        });
+        if (worst.1 - best.1).abs() <= tolerance && size <= tolerance {
+            converged = true;
+            break;
+        }
+
+        // Centroid of all the vertices but the worst
+        let mut centroid = vec![0.0; dimension];
+        for (point, _) in &vertices[..dimension] {
+            for (c, x) in centroid.iter_mut().zip(point) {
+                *c += x / dimension as f64;
+            }
+        }
+        let along = |factor: f64| -> Vec<f64> {
+            centroid
+                .iter()
+                .zip(&vertices[dimension].0)
+                .map(|(c, w)| c + factor * (c - w))
+                .collect()
+        };
+
+        let reflected = along(1.0);
+        let reflected_value = evaluate(&reflected, &mut evaluations);
+        if reflected_value < vertices[0].1 {
+            let expanded = along(2.0);
+            let expanded_value = evaluate(&expanded, &mut evaluations);
+            vertices[dimension] = if expanded_value < reflected_value {
+                (expanded, expanded_value)
+            } else {
+                (reflected, reflected_value)
+            };
+            continue;
+        }
+        if reflected_value < vertices[dimension - 1].1 {
+            vertices[dimension] = (reflected, reflected_value);
+            continue;
+        }
+        if reflected_value < vertices[dimension].1 {
+            let outside = along(0.5);
+            let value = evaluate(&outside, &mut evaluations);
+            if value <= reflected_value {
+                vertices[dimension] = (outside, value);
+                continue;
+            }
+        } else {
+            let inside = along(-0.5);
+            let value = evaluate(&inside, &mut evaluations);
+            if value < vertices[dimension].1 {
+                vertices[dimension] = (inside, value);
+                continue;
+            }
+        }
+        // Shrink towards the best vertex
+        let best = vertices[0].0.clone();
+        for vertex in vertices.iter_mut().skip(1) {
+            if evaluations >= max_evaluations {
+                break;
+            }
+            let point: Vec<f64> = best
+                .iter()
+                .zip(&vertex.0)
+                .map(|(b, x)| b + 0.5 * (x - b))
+                .collect();
+            let value = evaluate(&point, &mut evaluations);
+            *vertex = (point, value);
+        }
+    }
+
+    let point = vertices
+        .into_iter()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or_else(|| start.to_vec(), |(point, _)| point);
+    Minimum {
+        point,
+        evaluations,
+        converged,
+    }
+}
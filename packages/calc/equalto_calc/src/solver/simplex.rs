@@ -0,0 +1,404 @@
+//! Linear programs solved with the two-phase simplex method, and branch and bound for the ones
+//! with integer variables.
+
+const EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Relation {
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+}
+
+/// `coefficients`·x `relation` `value`
+#[derive(Clone, Debug)]
+pub(crate) struct LinearConstraint {
+    pub(crate) coefficients: Vec<f64>,
+    pub(crate) relation: Relation,
+    pub(crate) value: f64,
+}
+
+/// Minimize `objective`·x subject to the constraints and lower[j] <= x[j] <= upper[j].
+/// Bounds may be infinite.
+#[derive(Clone, Debug)]
+pub(crate) struct LinearProgram {
+    pub(crate) objective: Vec<f64>,
+    pub(crate) constraints: Vec<LinearConstraint>,
+    pub(crate) lower: Vec<f64>,
+    pub(crate) upper: Vec<f64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum LinearOutcome {
+    Optimal(Vec<f64>),
+    /// The pivot limit was reached after finding this solution, which might not be optimal
+    Feasible(Vec<f64>),
+    Infeasible,
+    Unbounded,
+    /// The pivot limit was reached before finding a solution
+    PivotLimit,
+}
+
+/// Counts the pivots of all the simplex runs of a problem
+pub(crate) struct PivotBudget {
+    pub(crate) used: usize,
+    pub(crate) max: usize,
+}
+
+/// A simplex tableau. Each row ends with its right hand side.
+struct Tableau {
+    rows: Vec<Vec<f64>>,
+    /// The column of the basic variable of each row
+    basis: Vec<usize>,
+    columns: usize,
+}
+
+impl Tableau {
+    fn pivot(&mut self, row: usize, column: usize) {
+        let value = self.rows[row][column];
+        for x in self.rows[row].iter_mut() {
+            *x /= value;
+        }
+        let pivot_row = self.rows[row].clone();
+        for (index, other) in self.rows.iter_mut().enumerate() {
+            let factor = other[column];
+            if index == row || factor == 0.0 {
+                continue;
+            }
+            for (x, p) in other.iter_mut().zip(&pivot_row) {
+                *x -= factor * p;
+            }
+        }
+        self.basis[row] = column;
+    }
+
+    /// Minimizes `cost`·z entering only the allowed columns, with Bland's rule to avoid cycles.
+    fn minimize(
+        &mut self,
+        cost: &[f64],
+        allowed: &[bool],
+        budget: &mut PivotBudget,
+    ) -> Result<(), LinearOutcome> {
+        loop {
+            let entering = (0..self.columns).find(|&column| {
+                if !allowed[column] {
+                    return false;
+                }
+                let reduced_cost = self
+                    .rows
+                    .iter()
+                    .zip(&self.basis)
+                    .fold(cost[column], |acc, (row, &basic)| {
+                        acc - cost[basic] * row[column]
+                    });
+                reduced_cost < -EPSILON
+            });
+            let column = match entering {
+                Some(column) => column,
+                None => return Ok(()),
+            };
+            let mut leaving: Option<(usize, f64)> = None;
+            for (index, row) in self.rows.iter().enumerate() {
+                if row[column] <= EPSILON {
+                    continue;
+                }
+                let ratio = row[self.columns] / row[column];
+                let better = match leaving {
+                    None => true,
+                    Some((other, best)) => {
+                        ratio < best - EPSILON
+                            || (ratio <= best + EPSILON && self.basis[index] < self.basis[other])
+                    }
+                };
+                if better {
+                    leaving = Some((index, ratio));
+                }
+            }
+            let row = match leaving {
+                Some((row, _)) => row,
+                None => return Err(LinearOutcome::Unbounded),
+            };
+            if budget.used >= budget.max {
+                return Err(LinearOutcome::PivotLimit);
+            }
+            budget.used += 1;
+            self.pivot(row, column);
+        }
+    }
+}
+
+/// Minimizes `cost`·y subject to the rows and y >= 0
+fn simplex(
+    rows: Vec<LinearConstraint>,
+    cost: &[f64],
+    budget: &mut PivotBudget,
+) -> Result<Vec<f64>, LinearOutcome> {
+    let variables = cost.len();
+    // Right hand sides must be non negative
+    let rows: Vec<LinearConstraint> = rows
+        .into_iter()
+        .map(|row| {
+            if row.value >= 0.0 {
+                return row;
+            }
+            LinearConstraint {
+                coefficients: row.coefficients.iter().map(|x| -x).collect(),
+                relation: match row.relation {
+                    Relation::LessOrEqual => Relation::GreaterOrEqual,
+                    Relation::Equal => Relation::Equal,
+                    Relation::GreaterOrEqual => Relation::LessOrEqual,
+                },
+                value: -row.value,
+            }
+        })
+        .collect();
+    let slacks = rows
+        .iter()
+        .filter(|row| row.relation != Relation::Equal)
+        .count();
+    let artificials = rows
+        .iter()
+        .filter(|row| row.relation != Relation::LessOrEqual)
+        .count();
+    let columns = variables + slacks + artificials;
+    let mut tableau = Tableau {
+        rows: Vec::new(),
+        basis: Vec::new(),
+        columns,
+    };
+    let mut slack = variables;
+    let mut artificial = variables + slacks;
+    for row in rows {
+        let mut values = row.coefficients;
+        values.resize(columns + 1, 0.0);
+        values[columns] = row.value;
+        match row.relation {
+            Relation::LessOrEqual => {
+                values[slack] = 1.0;
+                tableau.basis.push(slack);
+                slack += 1;
+            }
+            Relation::GreaterOrEqual => {
+                values[slack] = -1.0;
+                slack += 1;
+                values[artificial] = 1.0;
+                tableau.basis.push(artificial);
+                artificial += 1;
+            }
+            Relation::Equal => {
+                values[artificial] = 1.0;
+                tableau.basis.push(artificial);
+                artificial += 1;
+            }
+        }
+        tableau.rows.push(values);
+    }
+    let is_artificial = |column: usize| column >= variables + slacks;
+
+    // Phase 1: find a feasible solution
+    if artificials > 0 {
+        let phase_one_cost: Vec<f64> = (0..columns)
+            .map(|column| if is_artificial(column) { 1.0 } else { 0.0 })
+            .collect();
+        let allowed = vec![true; columns];
+        match tableau.minimize(&phase_one_cost, &allowed, budget) {
+            Ok(()) => {}
+            Err(LinearOutcome::Unbounded) => return Err(LinearOutcome::Infeasible),
+            Err(outcome) => return Err(outcome),
+        }
+        let infeasibility: f64 = tableau
+            .rows
+            .iter()
+            .zip(&tableau.basis)
+            .filter(|(_, &basic)| is_artificial(basic))
+            .map(|(row, _)| row[columns])
+            .sum();
+        if infeasibility > 1e-7 {
+            return Err(LinearOutcome::Infeasible);
+        }
+        // Artificial variables left in the basis are zero, they are replaced when possible
+        for index in 0..tableau.rows.len() {
+            if !is_artificial(tableau.basis[index]) {
+                continue;
+            }
+            if let Some(column) =
+                (0..variables + slacks).find(|&column| tableau.rows[index][column].abs() > EPSILON)
+            {
+                tableau.pivot(index, column);
+            }
+        }
+    }
+
+    // Phase 2: the optimal solution
+    let mut phase_two_cost = cost.to_vec();
+    phase_two_cost.resize(columns, 0.0);
+    let allowed: Vec<bool> = (0..columns).map(|column| !is_artificial(column)).collect();
+    tableau.minimize(&phase_two_cost, &allowed, budget)?;
+
+    let mut solution = vec![0.0; variables];
+    for (row, &basic) in tableau.rows.iter().zip(&tableau.basis) {
+        if basic < variables {
+            solution[basic] = row[columns];
+        }
+    }
+    Ok(solution)
+}
+
+/// How a variable of the linear program is written in terms of the simplex variables y >= 0
+enum Substitution {
+    /// x = lower + y
+    Lower(f64, usize),
+    /// x = upper - y
+    Upper(f64, usize),
+    /// x = y1 - y2
+    Free(usize, usize),
+}
+
+/// Solves the linear program with the given bounds
+fn solve_relaxation(
+    program: &LinearProgram,
+    lower: &[f64],
+    upper: &[f64],
+    budget: &mut PivotBudget,
+) -> LinearOutcome {
+    let mut substitutions = Vec::new();
+    let mut columns = 0;
+    for (&low, &high) in lower.iter().zip(upper) {
+        if low > high + EPSILON {
+            return LinearOutcome::Infeasible;
+        }
+        let substitution = if low.is_finite() {
+            Substitution::Lower(low, columns)
+        } else if high.is_finite() {
+            Substitution::Upper(high, columns)
+        } else {
+            columns += 1;
+            Substitution::Free(columns - 1, columns)
+        };
+        columns += 1;
+        substitutions.push(substitution);
+    }
+
+    // Writes a·x as b·y + offset
+    let substitute = |coefficients: &[f64]| -> (Vec<f64>, f64) {
+        let mut result = vec![0.0; columns];
+        let mut offset = 0.0;
+        for (&a, substitution) in coefficients.iter().zip(&substitutions) {
+            match *substitution {
+                Substitution::Lower(low, y) => {
+                    result[y] += a;
+                    offset += a * low;
+                }
+                Substitution::Upper(high, y) => {
+                    result[y] -= a;
+                    offset += a * high;
+                }
+                Substitution::Free(y1, y2) => {
+                    result[y1] += a;
+                    result[y2] -= a;
+                }
+            }
+        }
+        (result, offset)
+    };
+
+    let mut rows = Vec::new();
+    for constraint in &program.constraints {
+        let (coefficients, offset) = substitute(&constraint.coefficients);
+        rows.push(LinearConstraint {
+            coefficients,
+            relation: constraint.relation,
+            value: constraint.value - offset,
+        });
+    }
+    for (index, substitution) in substitutions.iter().enumerate() {
+        if let Substitution::Lower(low, y) = *substitution {
+            if upper[index].is_finite() {
+                let mut coefficients = vec![0.0; columns];
+                coefficients[y] = 1.0;
+                rows.push(LinearConstraint {
+                    coefficients,
+                    relation: Relation::LessOrEqual,
+                    value: upper[index] - low,
+                });
+            }
+        }
+    }
+    let (cost, _) = substitute(&program.objective);
+
+    match simplex(rows, &cost, budget) {
+        Ok(y) => LinearOutcome::Optimal(
+            substitutions
+                .iter()
+                .map(|substitution| match *substitution {
+                    Substitution::Lower(low, index) => low + y[index],
+                    Substitution::Upper(high, index) => high - y[index],
+                    Substitution::Free(y1, y2) => y[y1] - y[y2],
+                })
+                .collect(),
+        ),
+        Err(outcome) => outcome,
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Solves the linear program with the variables marked in `integer` restricted to integers,
+/// using depth first branch and bound
+pub(crate) fn solve_linear_program(
+    program: &LinearProgram,
+    integer: &[bool],
+    budget: &mut PivotBudget,
+) -> LinearOutcome {
+    let mut best: Option<(f64, Vec<f64>)> = None;
+    let mut nodes = vec![(program.lower.clone(), program.upper.clone())];
+    while let Some((lower, upper)) = nodes.pop() {
+        let solution = match solve_relaxation(program, &lower, &upper, budget) {
+            LinearOutcome::Optimal(solution) => solution,
+            LinearOutcome::Infeasible => continue,
+            LinearOutcome::PivotLimit => {
+                return match best {
+                    Some((_, solution)) => LinearOutcome::Feasible(solution),
+                    None => LinearOutcome::PivotLimit,
+                }
+            }
+            outcome => return outcome,
+        };
+        let value = dot(&program.objective, &solution);
+        if best
+            .as_ref()
+            .map_or(false, |(best_value, _)| value >= best_value - EPSILON)
+        {
+            continue;
+        }
+        let fractional = solution
+            .iter()
+            .zip(integer)
+            .position(|(x, &is_integer)| is_integer && (x - x.round()).abs() > 1e-6);
+        match fractional {
+            Some(index) => {
+                let x = solution[index];
+                let mut down = upper.clone();
+                down[index] = x.floor();
+                let mut up = lower.clone();
+                up[index] = x.ceil();
+                nodes.push((up, upper));
+                nodes.push((lower, down));
+            }
+            None => {
+                let solution = solution
+                    .iter()
+                    .zip(integer)
+                    .map(|(x, &is_integer)| if is_integer { x.round() } else { *x })
+                    .collect();
+                best = Some((value, solution));
+            }
+        }
+    }
+    match best {
+        Some((_, solution)) => LinearOutcome::Optimal(solution),
+        None => LinearOutcome::Infeasible,
+    }
+}
@@ -0,0 +1,230 @@
+//! Solver definitions are stored in hidden names local to a sheet, the same way Excel does:
+//!
+//! * `solver_opt`: the objective cell
+//! * `solver_typ`: 1 to maximize, 2 to minimize and 3 to reach the value in `solver_val`
+//! * `solver_adj`: the variable cells, ranges separated by commas
+//! * `solver_num`: the number of constraints, each one in `solver_lhs{i}`, `solver_rel{i}` and
+//!   `solver_rhs{i}`. The relations are 1 (<=), 2 (=), 3 (>=), 4 (integer) and 5 (binary)
+//! * `solver_eng`: 1 for nonlinear models and 2 for linear ones
+//! * `solver_neg`: 1 if variables without a lower bound can't be negative, 2 otherwise
+//! * `solver_itr` and `solver_pre`: maximum iterations and precision
+
+use crate::{
+    expressions::utils::{number_to_column, quote_name},
+    history::ChangeScope,
+    model::Model,
+    types::DefinedName,
+    utils::ParsedReference,
+};
+
+use super::{ConstraintOperator, SolverConstraint, SolverDefinition, SolverGoal, SolverMethod};
+
+const PREFIX: &str = "solver_";
+
+fn is_solver_name(name: &str) -> bool {
+    name.to_lowercase().starts_with(PREFIX)
+}
+
+impl Model {
+    /// Writes a cell or a range as an absolute reference with the sheet name
+    fn format_solver_reference(&self, sheet: u32, reference: &str) -> Result<String, String> {
+        let reference = reference.trim();
+        let reference = reference.strip_prefix('=').unwrap_or(reference);
+        let parsed = ParsedReference::parse_reference_formula(
+            Some(sheet),
+            reference,
+            &self.locale,
+            |name| self.get_sheet_index_by_name(name),
+        )?;
+        let (left, right) = match parsed {
+            ParsedReference::CellReference(cell) => (cell, None),
+            ParsedReference::Range(left, right) => (left, Some(right)),
+        };
+        let name = quote_name(&self.workbook.worksheet(left.sheet)?.get_name());
+        let format_cell = |row: i32, column: i32| -> Result<String, String> {
+            let column = number_to_column(column).ok_or("Invalid column")?;
+            Ok(format!("${column}${row}"))
+        };
+        let mut result = format!("{name}!{}", format_cell(left.row, left.column)?);
+        if let Some(right) = right {
+            result.push(':');
+            result.push_str(&format_cell(right.row, right.column)?);
+        }
+        Ok(result)
+    }
+
+    /// Returns the formula of the hidden solver name `name` of the sheet `sheet_id`
+    fn get_solver_name(&self, sheet_id: u32, name: &str) -> Option<String> {
+        let name = format!("{PREFIX}{name}");
+        self.workbook
+            .defined_names
+            .iter()
+            .find(|defined_name| {
+                defined_name.sheet_id == Some(sheet_id) && defined_name.name.to_lowercase() == name
+            })
+            .map(|defined_name| {
+                let formula = defined_name.formula.trim();
+                let formula = formula.strip_prefix('=').unwrap_or(formula);
+                formula.trim_matches('"').to_string()
+            })
+    }
+
+    /// Returns the solver definition stored in the sheet `sheet`, if any
+    pub fn get_solver(&self, sheet: u32) -> Result<Option<SolverDefinition>, String> {
+        let sheet_id = self.workbook.worksheet(sheet)?.sheet_id;
+        let get = |name: &str| self.get_solver_name(sheet_id, name);
+        let get_number = |name: &str| get(name).and_then(|value| value.parse::<f64>().ok());
+        let objective = match get("opt") {
+            Some(objective) => objective,
+            None => return Ok(None),
+        };
+        let goal = match get_number("typ").unwrap_or(1.0) as i32 {
+            2 => SolverGoal::Minimize,
+            3 => SolverGoal::Value(get_number("val").unwrap_or(0.0)),
+            _ => SolverGoal::Maximize,
+        };
+        let variables = get("adj")
+            .map(|adj| {
+                adj.split(',')
+                    .map(|range| range.trim().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut constraints = Vec::new();
+        for index in 1..=get_number("num").unwrap_or(0.0) as usize {
+            let cells = match get(&format!("lhs{index}")) {
+                Some(cells) => cells,
+                None => continue,
+            };
+            let operator = match get_number(&format!("rel{index}")).unwrap_or(0.0) as i32 {
+                1 => ConstraintOperator::LessOrEqual,
+                2 => ConstraintOperator::Equal,
+                3 => ConstraintOperator::GreaterOrEqual,
+                4 => ConstraintOperator::Integer,
+                5 => ConstraintOperator::Binary,
+                // "dif" (all different) constraints are not supported
+                _ => continue,
+            };
+            let value = match operator {
+                ConstraintOperator::Integer | ConstraintOperator::Binary => "".to_string(),
+                _ => get(&format!("rhs{index}")).unwrap_or_default(),
+            };
+            constraints.push(SolverConstraint {
+                cells,
+                operator,
+                value,
+            });
+        }
+        let defaults = SolverDefinition::default();
+        Ok(Some(SolverDefinition {
+            objective,
+            goal,
+            variables,
+            constraints,
+            method: if get_number("eng") == Some(2.0) {
+                SolverMethod::Linear
+            } else {
+                SolverMethod::Nonlinear
+            },
+            non_negative: get_number("neg").map_or(defaults.non_negative, |neg| neg == 1.0),
+            max_iterations: get_number("itr").map_or(defaults.max_iterations, |itr| itr as usize),
+            precision: get_number("pre").unwrap_or(defaults.precision),
+        }))
+    }
+
+    /// Stores `definition` in the sheet `sheet`, replacing the previous one.
+    /// Fails if any of its references is not valid.
+    pub fn set_solver(&mut self, sheet: u32, definition: &SolverDefinition) -> Result<(), String> {
+        self.get_solver_problem(sheet, definition)?;
+        let mut names: Vec<(String, String)> = Vec::new();
+        let mut add =
+            |name: &str, formula: String| names.push((format!("{PREFIX}{name}"), formula));
+        add(
+            "opt",
+            self.format_solver_reference(sheet, &definition.objective)?,
+        );
+        let (typ, val) = match definition.goal {
+            SolverGoal::Maximize => (1, 0.0),
+            SolverGoal::Minimize => (2, 0.0),
+            SolverGoal::Value(value) => (3, value),
+        };
+        add("typ", typ.to_string());
+        add("val", val.to_string());
+        let variables = definition
+            .variables
+            .iter()
+            .map(|range| self.format_solver_reference(sheet, range))
+            .collect::<Result<Vec<String>, String>>()?;
+        add("adj", variables.join(","));
+        add("num", definition.constraints.len().to_string());
+        for (index, constraint) in definition.constraints.iter().enumerate() {
+            let index = index + 1;
+            add(
+                &format!("lhs{index}"),
+                self.format_solver_reference(sheet, &constraint.cells)?,
+            );
+            let (relation, value) = match constraint.operator {
+                ConstraintOperator::LessOrEqual => (1, None),
+                ConstraintOperator::Equal => (2, None),
+                ConstraintOperator::GreaterOrEqual => (3, None),
+                ConstraintOperator::Integer => (4, Some("\"integer\"".to_string())),
+                ConstraintOperator::Binary => (5, Some("\"binary\"".to_string())),
+            };
+            let value = match value {
+                Some(value) => value,
+                None => {
+                    let value = constraint.value.trim();
+                    match value.parse::<f64>() {
+                        Ok(number) => number.to_string(),
+                        Err(_) => self.format_solver_reference(sheet, value)?,
+                    }
+                }
+            };
+            add(&format!("rel{index}"), relation.to_string());
+            add(&format!("rhs{index}"), value);
+        }
+        let engine = match definition.method {
+            SolverMethod::Nonlinear => 1,
+            SolverMethod::Linear => 2,
+        };
+        add("eng", engine.to_string());
+        add(
+            "neg",
+            if definition.non_negative { "1" } else { "2" }.to_string(),
+        );
+        add("itr", definition.max_iterations.to_string());
+        add("pre", definition.precision.to_string());
+        add("ver", "3".to_string());
+
+        let sheet_id = self.workbook.worksheet(sheet)?.sheet_id;
+        self.record_change(ChangeScope::defined_names(), |model| {
+            model.remove_solver_names(sheet_id);
+            for (name, formula) in names {
+                model.workbook.defined_names.push(DefinedName {
+                    name,
+                    formula,
+                    sheet_id: Some(sheet_id),
+                    hidden: true,
+                });
+            }
+            model.reset_parsed_structures();
+        });
+        Ok(())
+    }
+
+    /// Removes the solver definition stored in the sheet `sheet`
+    pub fn delete_solver(&mut self, sheet: u32) -> Result<(), String> {
+        let sheet_id = self.workbook.worksheet(sheet)?.sheet_id;
+        self.record_change(ChangeScope::defined_names(), |model| {
+            model.remove_solver_names(sheet_id);
+            model.reset_parsed_structures();
+        });
+        Ok(())
+    }
+
+    fn remove_solver_names(&mut self, sheet_id: u32) {
+        self.workbook.defined_names.retain(|defined_name| {
+            defined_name.sheet_id != Some(sheet_id) || !is_solver_name(&defined_name.name)
+        });
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+
+use crate::{
+    arrays::broadcast_element,
+    calc_result::{CalcResult, CellReference, Range},
+    constants::{LAST_COLUMN, LAST_ROW},
+    expressions::{parser::Node, token::Error},
+    model::Model,
+    types::Cell,
+};
+
+/// Maximum number of times the dependents of spilled values are recomputed in one evaluation.
+/// Formulas whose spill area depends on their own spilled values might otherwise never settle.
+const MAX_SPILL_PASSES: usize = 100;
+
+/// Returns the cell holding `value` spilled from the formula at (`row`, `column`)
+pub(crate) fn new_spill_cell(value: &CalcResult, row: i32, column: i32, s: i32) -> Cell {
+    let (r, c) = (row, column);
+    match value {
+        CalcResult::Number(v) => {
+            if v.is_nan() || v.is_infinite() {
+                Cell::SpillError {
+                    ei: Error::NUM,
+                    r,
+                    c,
+                    s,
+                }
+            } else {
+                Cell::SpillNumber { v: *v, r, c, s }
+            }
+        }
+        CalcResult::String(v) => Cell::SpillString {
+            v: v.clone(),
+            r,
+            c,
+            s,
+        },
+        CalcResult::Boolean(v) => Cell::SpillBoolean { v: *v, r, c, s },
+        CalcResult::Error { error, .. } => Cell::SpillError {
+            ei: error.clone(),
+            r,
+            c,
+            s,
+        },
+        CalcResult::EmptyCell | CalcResult::EmptyArg => Cell::SpillNumber { v: 0.0, r, c, s },
+        CalcResult::Range { .. }
+        | CalcResult::Array(_)
+        | CalcResult::Lambda(_)
+        | CalcResult::Areas(_) => Cell::SpillError {
+            ei: Error::VALUE,
+            r,
+            c,
+            s,
+        },
+    }
+}
+
+impl Model {
+    /// Returns true if the cell holds a value spilled from `anchor`
+    fn is_spilled_from(&self, anchor: CellReference, row: i32, column: i32) -> bool {
+        self.get_cell(anchor.sheet, row, column)
+            .and_then(|cell| cell.get_spill_anchor())
+            == Some((anchor.row, anchor.column))
+    }
+
+    /// Returns the area covered by the formula in `anchor` and the values it spilled
+    pub(crate) fn get_spill_extent(&self, anchor: CellReference) -> Range {
+        let mut row = anchor.row;
+        while self.is_spilled_from(anchor, row + 1, anchor.column) {
+            row += 1;
+        }
+        let mut column = anchor.column;
+        while self.is_spilled_from(anchor, anchor.row, column + 1) {
+            column += 1;
+        }
+        Range {
+            left: anchor,
+            right: CellReference {
+                sheet: anchor.sheet,
+                row,
+                column,
+            },
+        }
+    }
+
+    /// Removes all the values spilled by the formula in `anchor`.
+    /// Returns the cells that were removed, indexed by (row, column)
+    pub(crate) fn clear_spill(&mut self, anchor: CellReference) -> HashMap<(i32, i32), Cell> {
+        let mut areas = vec![self.get_spill_extent(anchor)];
+        if let Some(area) =
+            self.dependency_graph
+                .get_spill_area((anchor.sheet, anchor.row, anchor.column))
+        {
+            areas.push(area.clone());
+        }
+        let mut cleared = HashMap::new();
+        for area in areas {
+            for row in area.left.row..=area.right.row {
+                for column in area.left.column..=area.right.column {
+                    if (row == anchor.row && column == anchor.column)
+                        || cleared.contains_key(&(row, column))
+                        || !self.is_spilled_from(anchor, row, column)
+                    {
+                        continue;
+                    }
+                    self.touch_cell(anchor.sheet, row, column);
+                    if let Some(cell) = self.take_cell(anchor.sheet, row, column) {
+                        let s = cell.get_style();
+                        if s != 0 {
+                            self.write_cell(anchor.sheet, row, column, Cell::EmptyCell { s });
+                        }
+                        cleared.insert((row, column), cell);
+                    }
+                }
+            }
+        }
+        cleared
+    }
+
+    /// Removes all the values spilled by the formula that was in `anchor`
+    pub(crate) fn remove_spill(&mut self, anchor: CellReference) {
+        for (row, column) in self.clear_spill(anchor).into_keys() {
+            self.dependency_graph
+                .mark_spilled(anchor.sheet, row, column);
+        }
+        self.dependency_graph
+            .set_spill_area((anchor.sheet, anchor.row, anchor.column), None);
+    }
+
+    /// Spills `result`, the value of the formula in `anchor`, into the neighbouring cells.
+    /// Returns the value of the anchor cell: the top left element of the array,
+    /// or #SPILL! if the area is not empty or does not fit in the sheet.
+    pub(crate) fn spill_result(&mut self, anchor: CellReference, result: CalcResult) -> CalcResult {
+        let key = (anchor.sheet, anchor.row, anchor.column);
+        let mut old_cells = self.clear_spill(anchor);
+        let (value, area) = match result {
+            CalcResult::Array(array) => self.write_spill(anchor, array, &mut old_cells),
+            result => (result, None),
+        };
+        self.dependency_graph.set_spill_area(key, area);
+        for (row, column) in old_cells.into_keys() {
+            self.dependency_graph
+                .mark_spilled(anchor.sheet, row, column);
+        }
+        value
+    }
+
+    /// Writes `array` into the spill area of `anchor`.
+    /// Returns the value of the anchor and the area it wanted to spill into.
+    /// Cells written with the same value are taken out of `old_cells`.
+    fn write_spill(
+        &mut self,
+        anchor: CellReference,
+        array: Vec<Vec<CalcResult>>,
+        old_cells: &mut HashMap<(i32, i32), Cell>,
+    ) -> (CalcResult, Option<Range>) {
+        let rows = array.len() as i32;
+        let columns = array.first().map_or(0, |row| row.len()) as i32;
+        if rows == 0 || columns == 0 {
+            let error = CalcResult::new_error(Error::CALC, anchor, "Empty array".to_string());
+            return (error, None);
+        }
+        if rows == 1 && columns == 1 {
+            return (array[0][0].clone(), None);
+        }
+        let last_row = anchor.row + rows - 1;
+        let last_column = anchor.column + columns - 1;
+        if last_row > LAST_ROW || last_column > LAST_COLUMN {
+            let error = CalcResult::new_error(
+                Error::SPILL,
+                anchor,
+                "Spill range extends beyond the edge of the sheet".to_string(),
+            );
+            return (error, None);
+        }
+        let area = Range {
+            left: anchor,
+            right: CellReference {
+                sheet: anchor.sheet,
+                row: last_row,
+                column: last_column,
+            },
+        };
+        for row in anchor.row..=last_row {
+            for column in anchor.column..=last_column {
+                if row == anchor.row && column == anchor.column {
+                    continue;
+                }
+                if !matches!(
+                    self.get_cell(anchor.sheet, row, column),
+                    None | Some(Cell::EmptyCell { .. })
+                ) {
+                    let error = CalcResult::new_error(
+                        Error::SPILL,
+                        anchor,
+                        "Spill range isn't blank".to_string(),
+                    );
+                    return (error, Some(area));
+                }
+            }
+        }
+        self.write_array(anchor, &array, &area, old_cells);
+        (array[0][0].clone(), Some(area))
+    }
+
+    /// Writes the elements of `array` in the empty cells of `area`, except for the anchor.
+    /// Arrays smaller than the area are broadcast.
+    /// Cells written with the same value are taken out of `old_cells`.
+    pub(crate) fn write_array(
+        &mut self,
+        anchor: CellReference,
+        array: &[Vec<CalcResult>],
+        area: &Range,
+        old_cells: &mut HashMap<(i32, i32), Cell>,
+    ) {
+        for row in area.left.row..=area.right.row {
+            for column in area.left.column..=area.right.column {
+                if (row == anchor.row && column == anchor.column)
+                    || !matches!(
+                        self.get_cell(anchor.sheet, row, column),
+                        None | Some(Cell::EmptyCell { .. })
+                    )
+                {
+                    continue;
+                }
+                self.touch_cell(anchor.sheet, row, column);
+                let value = broadcast_element(
+                    array,
+                    (row - anchor.row) as usize,
+                    (column - anchor.column) as usize,
+                    anchor,
+                );
+                let s = self.get_cell_style_index(anchor.sheet, row, column);
+                let cell = new_spill_cell(&value, anchor.row, anchor.column, s);
+                if old_cells.get(&(row, column)) == Some(&cell) {
+                    old_cells.remove(&(row, column));
+                } else {
+                    self.dependency_graph
+                        .mark_spilled(anchor.sheet, row, column);
+                }
+                self.write_cell(anchor.sheet, row, column, cell);
+            }
+        }
+    }
+
+    /// Evaluates a spill reference like `A1#`: the range of values spilled by the formula in A1
+    pub(crate) fn get_spill_range(&mut self, node: &Node, cell: CellReference) -> CalcResult {
+        let anchor = match node {
+            Node::ReferenceKind {
+                sheet_index,
+                absolute_row,
+                absolute_column,
+                row,
+                column,
+                ..
+            } => CellReference {
+                sheet: *sheet_index,
+                row: if *absolute_row { *row } else { *row + cell.row },
+                column: if *absolute_column {
+                    *column
+                } else {
+                    *column + cell.column
+                },
+            },
+            _ => return CalcResult::new_error(Error::REF, cell, "Wrong reference".to_string()),
+        };
+        let value = self.evaluate_cell(anchor);
+        let has_formula = self
+            .get_cell(anchor.sheet, anchor.row, anchor.column)
+            .and_then(|c| c.get_formula())
+            .is_some();
+        if !has_formula {
+            return CalcResult::new_error(
+                Error::REF,
+                cell,
+                "Cell does not contain a formula".to_string(),
+            );
+        }
+        match value {
+            CalcResult::Error {
+                error: Error::SPILL,
+                ..
+            } => {
+                return CalcResult::new_error(
+                    Error::REF,
+                    cell,
+                    "Formula could not spill".to_string(),
+                )
+            }
+            error @ CalcResult::Error { .. } => return error,
+            _ => {}
+        }
+        let Range { left, right } = self.get_spill_extent(anchor);
+        CalcResult::Range { left, right }
+    }
+
+    /// Recomputes the cells that read values spilled during the evaluation
+    pub(crate) fn evaluate_spilled_dependents(&mut self) {
+        for _ in 0..MAX_SPILL_PASSES {
+            let cells = self.take_cells_to_evaluate_after_spill();
+            if cells.is_empty() {
+                return;
+            }
+            for cell in &cells {
+                self.cells.remove(&(cell.sheet, cell.row, cell.column));
+            }
+            for cell in cells {
+                self.evaluate_cell(cell);
+            }
+        }
+        self.take_cells_to_evaluate_after_spill();
+    }
+}
@@ -0,0 +1,644 @@
+//! Structured references to the cells of tables: `=SUM(Sales[Price])` or `=Sales[@Price]*2`.
+//!
+//! Formulas keep the names of the table and of its columns, the cells are only found when the
+//! formula is evaluated. That way formulas follow the table when it grows and renaming the table
+//! or one of its columns rewrites the formulas using them.
+
+use std::collections::HashMap;
+
+use crate::{
+    calc_result::{CalcResult, CellReference, Range},
+    expressions::{
+        ast::{walk_mut, VisitorMut},
+        lexer::LexerMode,
+        parser::{
+            get_table_column_by_name, parse_range,
+            stringify::{to_rc_format, to_string},
+            table_contains, Node,
+        },
+        token::{Error, TableReference, TableSpecifier},
+        types::CellReferenceRC,
+        utils::{is_valid_identifier, number_to_column},
+    },
+    history::ChangeScope,
+    model::Model,
+    types::Table,
+};
+
+/// The cells of a table a structured reference points to
+struct TableArea {
+    row1: i32,
+    column1: i32,
+    row2: i32,
+    column2: i32,
+}
+
+/// Returns the cells of `table` for `specifier` and `table_reference`.
+/// `row` is the row of the formula, the one of [#This Row].
+fn get_table_area(
+    table: &Table,
+    specifier: &Option<TableSpecifier>,
+    table_reference: &Option<TableReference>,
+    row: i32,
+) -> Result<TableArea, (Error, String)> {
+    let (column1, row1, column2, row2) =
+        parse_range(&table.reference).map_err(|message| (Error::REF, message))?;
+    let first_data_row = row1 + table.header_row_count as i32;
+    let last_data_row = row2 - table.totals_row_count as i32;
+    let (row1, row2) = match specifier {
+        None | Some(TableSpecifier::Data) => (first_data_row, last_data_row),
+        Some(TableSpecifier::All) => (row1, row2),
+        Some(TableSpecifier::Headers) => {
+            if table.header_row_count == 0 {
+                return Err((Error::REF, "The table has no headers".to_string()));
+            }
+            (row1, first_data_row - 1)
+        }
+        Some(TableSpecifier::Totals) => {
+            if table.totals_row_count == 0 {
+                return Err((Error::REF, "The table has no totals".to_string()));
+            }
+            (last_data_row + 1, row2)
+        }
+        Some(TableSpecifier::HeadersAndData) => (row1, last_data_row),
+        Some(TableSpecifier::DataAndTotals) => (first_data_row, row2),
+        Some(TableSpecifier::ThisRow) => {
+            if row < first_data_row || row > last_data_row {
+                return Err((Error::VALUE, "The row is not in the table".to_string()));
+            }
+            (row, row)
+        }
+    };
+    let column_index = |name: &str| match get_table_column_by_name(name, table) {
+        Some(index) => Ok(column1 + index),
+        None => Err((Error::REF, format!("Column not found: '{}'", name))),
+    };
+    let (column1, column2) = match table_reference {
+        None => (column1, column2),
+        Some(TableReference::ColumnReference(name)) => {
+            let column = column_index(name)?;
+            (column, column)
+        }
+        Some(TableReference::RangeReference((left, right))) => {
+            let left = column_index(left)?;
+            let right = column_index(right)?;
+            (left.min(right), left.max(right))
+        }
+    };
+    Ok(TableArea {
+        row1,
+        column1,
+        row2,
+        column2,
+    })
+}
+
+/// Renames a table or one of its columns in structured references
+struct TableRenamer<'a> {
+    table_name: &'a str,
+    new_table_name: &'a str,
+    /// The old and new names of the column, if a column is renamed
+    column: Option<(&'a str, &'a str)>,
+    changed: bool,
+}
+
+impl VisitorMut for TableRenamer<'_> {
+    fn enter(&mut self, node: &mut Node) -> bool {
+        if let Node::TableReferenceKind {
+            table_name,
+            table_reference,
+            ..
+        } = node
+        {
+            if table_name.to_lowercase() != self.table_name.to_lowercase() {
+                return false;
+            }
+            if *table_name != self.new_table_name {
+                *table_name = self.new_table_name.to_string();
+                self.changed = true;
+            }
+            if let Some((name, new_name)) = self.column {
+                let name = name.to_lowercase();
+                let mut rename = |column: &mut String| {
+                    if column.to_lowercase() == name {
+                        *column = new_name.to_string();
+                        self.changed = true;
+                    }
+                };
+                match table_reference {
+                    Some(TableReference::ColumnReference(column)) => rename(column),
+                    Some(TableReference::RangeReference((left, right))) => {
+                        rename(left);
+                        rename(right);
+                    }
+                    None => {}
+                }
+            }
+            return false;
+        }
+        true
+    }
+}
+
+/// Replaces the structured references by the cells they point to
+struct TableReferenceReplacer<'a> {
+    model: &'a Model,
+    /// The table of the references without a table name
+    table_name: Option<&'a str>,
+}
+
+impl VisitorMut for TableReferenceReplacer<'_> {
+    fn enter(&mut self, node: &mut Node) -> bool {
+        if let Node::TableReferenceKind {
+            table_name,
+            specifier,
+            table_reference,
+        } = node
+        {
+            let table_name = match self.table_name {
+                Some(name) if table_name.is_empty() => name,
+                _ => table_name,
+            };
+            *node = self
+                .model
+                .table_reference_to_node(table_name, specifier, table_reference)
+                .unwrap_or(Node::ErrorKind(Error::REF));
+            return false;
+        }
+        true
+    }
+}
+
+impl Model {
+    /// Returns the name of the table called `name`, ignoring case
+    fn get_table_name(&self, name: &str) -> Result<String, String> {
+        let lower_name = name.to_lowercase();
+        self.workbook
+            .tables
+            .keys()
+            .find(|table_name| table_name.to_lowercase() == lower_name)
+            .cloned()
+            .ok_or_else(|| format!("Table not found: '{}'", name))
+    }
+
+    /// Returns the table of a structured reference in `cell`, the table of the cell if the
+    /// reference has no table name
+    pub(crate) fn get_table(&self, table_name: &str, cell: CellReference) -> Option<&Table> {
+        if !table_name.is_empty() {
+            return self.workbook.tables.get(table_name);
+        }
+        let sheet_name = self
+            .workbook
+            .worksheets
+            .get(cell.sheet as usize)?
+            .get_name();
+        self.workbook
+            .tables
+            .values()
+            .find(|table| table_contains(table, &sheet_name, cell.row, cell.column))
+    }
+
+    /// Returns the cells a structured reference in `cell` points to
+    pub(crate) fn get_table_range(
+        &self,
+        table_name: &str,
+        specifier: &Option<TableSpecifier>,
+        table_reference: &Option<TableReference>,
+        cell: CellReference,
+    ) -> Result<Range, CalcResult> {
+        let table = match self.get_table(table_name, cell) {
+            Some(table) => table,
+            None if table_name.is_empty() => {
+                return Err(CalcResult::new_error(
+                    Error::REF,
+                    cell,
+                    "Structured reference outside of a table".to_string(),
+                ))
+            }
+            None => {
+                return Err(CalcResult::new_error(
+                    Error::REF,
+                    cell,
+                    format!("Table not found: '{}'", table_name),
+                ))
+            }
+        };
+        let sheet = match self.get_sheet_index_by_name(&table.sheet_name) {
+            Some(sheet) => sheet,
+            None => {
+                return Err(CalcResult::new_error(
+                    Error::REF,
+                    cell,
+                    format!("Sheet not found: '{}'", table.sheet_name),
+                ))
+            }
+        };
+        let area = get_table_area(table, specifier, table_reference, cell.row)
+            .map_err(|(error, message)| CalcResult::new_error(error, cell, message))?;
+        Ok(Range {
+            left: CellReference {
+                sheet,
+                row: area.row1,
+                column: area.column1,
+            },
+            right: CellReference {
+                sheet,
+                row: area.row2,
+                column: area.column2,
+            },
+        })
+    }
+
+    /// Evaluates a structured reference. A reference to a single cell evaluates to its value.
+    pub(crate) fn evaluate_table_reference(
+        &mut self,
+        table_name: &str,
+        specifier: &Option<TableSpecifier>,
+        table_reference: &Option<TableReference>,
+        cell: CellReference,
+    ) -> CalcResult {
+        match self.get_table_range(table_name, specifier, table_reference, cell) {
+            Ok(Range { left, right }) if left == right => self.evaluate_cell(left),
+            Ok(Range { left, right }) => CalcResult::Range { left, right },
+            Err(error) => error,
+        }
+    }
+
+    /// Returns the reference to the cells of a structured reference.
+    /// [#This Row] is a reference relative to the row of the formula.
+    fn table_reference_to_node(
+        &self,
+        table_name: &str,
+        specifier: &Option<TableSpecifier>,
+        table_reference: &Option<TableReference>,
+    ) -> Option<Node> {
+        let table = self.workbook.tables.get(table_name)?;
+        let sheet_index = self.get_sheet_index_by_name(&table.sheet_name)?;
+        let this_row = specifier == &Some(TableSpecifier::ThisRow);
+        // Any row of the data will do for [#This Row]
+        let (_, row, _, _) = parse_range(&table.reference).ok()?;
+        let row = row + table.header_row_count as i32;
+        let area = get_table_area(table, specifier, table_reference, row).ok()?;
+        let (row1, row2) = if this_row {
+            (0, 0)
+        } else {
+            (area.row1, area.row2)
+        };
+        let sheet_name = Some(table.sheet_name.clone());
+        if area.row1 == area.row2 && area.column1 == area.column2 {
+            return Some(Node::ReferenceKind {
+                sheet_name,
+                sheet_index,
+                absolute_row: !this_row,
+                absolute_column: true,
+                row: row1,
+                column: area.column1,
+            });
+        }
+        Some(Node::RangeKind {
+            sheet_name,
+            sheet_index,
+            absolute_row1: !this_row,
+            absolute_column1: true,
+            row1,
+            column1: area.column1,
+            absolute_row2: !this_row,
+            absolute_column2: true,
+            row2,
+            column2: area.column2,
+        })
+    }
+
+    /// Returns `node` with the structured references replaced by references to their cells,
+    /// for files that don't have the tables
+    pub fn replace_table_references(&self, node: &Node) -> Node {
+        let mut node = node.clone();
+        walk_mut(
+            &mut node,
+            &mut TableReferenceReplacer {
+                model: self,
+                table_name: None,
+            },
+        );
+        node
+    }
+
+    /// Returns the formulas of `sheet` with the structured references replaced by references to
+    /// their cells. The references without a table name are to the table of the first cell with
+    /// the formula.
+    pub fn replace_table_references_in_sheet(&self, sheet: u32) -> Vec<Node> {
+        let mut cells = Vec::new();
+        if let Some(worksheet) = self.workbook.worksheets.get(sheet as usize) {
+            for (row, columns) in &worksheet.sheet_data {
+                for (column, cell) in columns {
+                    if let Some(index) = cell.get_formula() {
+                        cells.push((*row, *column, index));
+                    }
+                }
+            }
+        }
+        cells.sort_unstable();
+        let mut tables = HashMap::new();
+        for (row, column, index) in cells {
+            if tables.contains_key(&index) {
+                continue;
+            }
+            if let Some(table) = self.get_table("", CellReference { sheet, row, column }) {
+                tables.insert(index, table.name.as_str());
+            }
+        }
+        self.parsed_formulas[sheet as usize]
+            .iter()
+            .enumerate()
+            .map(|(index, node)| {
+                let mut node = node.clone();
+                walk_mut(
+                    &mut node,
+                    &mut TableReferenceReplacer {
+                        model: self,
+                        table_name: tables.get(&(index as i32)).copied(),
+                    },
+                );
+                node
+            })
+            .collect()
+    }
+
+    /// Rewrites the structured references of all formulas and defined names with `visitor`
+    fn rename_in_table_references(&mut self, visitor: &mut TableRenamer) {
+        // All internal formulas are R1C1
+        self.parser.set_lexer_mode(LexerMode::R1C1);
+        for worksheet in &mut self.workbook.worksheets {
+            let context = Some(CellReferenceRC {
+                sheet: worksheet.get_name(),
+                row: 1,
+                column: 1,
+            });
+            for formula in &mut worksheet.shared_formulas {
+                if !formula.contains('[') {
+                    continue;
+                }
+                let mut node = self.parser.parse(formula, &context);
+                visitor.changed = false;
+                walk_mut(&mut node, visitor);
+                if visitor.changed {
+                    *formula = to_rc_format(&node);
+                }
+            }
+        }
+
+        // Defined names are A1
+        self.parser.set_lexer_mode(LexerMode::A1);
+        let context = CellReferenceRC {
+            sheet: self.workbook.worksheets[0].get_name(),
+            row: 1,
+            column: 1,
+        };
+        for defined_name in &mut self.workbook.defined_names {
+            if !defined_name.formula.contains('[') {
+                continue;
+            }
+            let mut node = self
+                .parser
+                .parse(&defined_name.formula, &Some(context.clone()));
+            visitor.changed = false;
+            walk_mut(&mut node, visitor);
+            if visitor.changed {
+                defined_name.formula = to_string(&node, &context);
+            }
+        }
+    }
+
+    /// Renames a column in the references without a table name of the formulas in `table`.
+    /// The same formula can be in other tables, so the cells of the table get a new formula.
+    fn rename_in_unqualified_references(&mut self, table: &Table, name: &str, new_name: &str) {
+        let sheet = match self.get_sheet_index_by_name(&table.sheet_name) {
+            Some(sheet) => sheet as usize,
+            None => return,
+        };
+        let (column1, row1, column2, row2) = match parse_range(&table.reference) {
+            Ok(range) => range,
+            Err(_) => return,
+        };
+        let worksheet = &mut self.workbook.worksheets[sheet];
+        let mut cells = Vec::new();
+        for (row, columns) in &worksheet.sheet_data {
+            if !(row1..=row2).contains(row) {
+                continue;
+            }
+            for (column, cell) in columns {
+                if let (true, Some(index)) =
+                    ((column1..=column2).contains(column), cell.get_formula())
+                {
+                    cells.push((*row, *column, index, cell.get_style()));
+                }
+            }
+        }
+        self.parser.set_lexer_mode(LexerMode::R1C1);
+        let context = Some(CellReferenceRC {
+            sheet: worksheet.get_name(),
+            row: 1,
+            column: 1,
+        });
+        // The new index of each formula that changes
+        let mut new_indexes = HashMap::new();
+        for (row, column, index, style) in cells {
+            let new_index = *new_indexes.entry(index).or_insert_with(|| {
+                let formula = &worksheet.shared_formulas[index as usize];
+                if !formula.contains('[') {
+                    return None;
+                }
+                let mut node = self.parser.parse(formula, &context);
+                let mut renamer = TableRenamer {
+                    table_name: "",
+                    new_table_name: "",
+                    column: Some((name, new_name)),
+                    changed: false,
+                };
+                walk_mut(&mut node, &mut renamer);
+                if !renamer.changed {
+                    return None;
+                }
+                let formula = to_rc_format(&node);
+                let shared_formulas = &mut worksheet.shared_formulas;
+                match shared_formulas.iter().position(|other| other == &formula) {
+                    Some(position) => Some(position as i32),
+                    None => {
+                        shared_formulas.push(formula);
+                        Some(shared_formulas.len() as i32 - 1)
+                    }
+                }
+            });
+            if let Some(new_index) = new_index {
+                worksheet.set_cell_with_formula(row, column, new_index, style);
+            }
+        }
+        self.parser.set_lexer_mode(LexerMode::A1);
+    }
+
+    /// Renames the table `name` and the references to it in formulas and defined names
+    pub fn rename_table(&mut self, name: &str, new_name: &str) -> Result<(), String> {
+        self.record_change(ChangeScope::tables(), |model| {
+            let name = model.get_table_name(name)?;
+            if !is_valid_identifier(new_name) {
+                return Err(format!("Invalid table name: '{}'", new_name));
+            }
+            if let Ok(other) = model.get_table_name(new_name) {
+                if other != name {
+                    return Err(format!("Table already exists: '{}'", new_name));
+                }
+            }
+            let lower_name = new_name.to_lowercase();
+            if model
+                .workbook
+                .defined_names
+                .iter()
+                .any(|defined_name| defined_name.name.to_lowercase() == lower_name)
+            {
+                return Err(format!("Defined name already exists: '{}'", new_name));
+            }
+            model.rename_in_table_references(&mut TableRenamer {
+                table_name: &name,
+                new_table_name: new_name,
+                column: None,
+                changed: false,
+            });
+            if let Some(mut table) = model.workbook.tables.remove(&name) {
+                table.name = new_name.to_string();
+                table.display_name = new_name.to_string();
+                model.workbook.tables.insert(new_name.to_string(), table);
+            }
+            model.reset_parsed_structures();
+            Ok(())
+        })
+    }
+
+    /// Renames the column `name` of the table `table_name`, its header and the references to it
+    /// in formulas and defined names
+    pub fn rename_table_column(
+        &mut self,
+        table_name: &str,
+        name: &str,
+        new_name: &str,
+    ) -> Result<(), String> {
+        let table_name = self.get_table_name(table_name)?;
+        let table = &self.workbook.tables[&table_name];
+        let index = get_table_column_by_name(name, table)
+            .ok_or_else(|| format!("Column not found: '{}'", name))?;
+        if new_name.trim().is_empty() {
+            return Err(format!("Invalid column name: '{}'", new_name));
+        }
+        if let Some(other) = get_table_column_by_name(new_name, table) {
+            if other != index {
+                return Err(format!("Column already exists: '{}'", new_name));
+            }
+        }
+        let sheet = self
+            .get_sheet_index_by_name(&table.sheet_name)
+            .ok_or_else(|| format!("Sheet not found: '{}'", table.sheet_name))?;
+        let (column1, row1, column2, row2) = parse_range(&table.reference)?;
+        let header_row_count = table.header_row_count;
+        let column = column1 + index;
+        let scope = ChangeScope::table_area(sheet, row1, column1, row2, column2);
+        self.record_change(scope, |model| {
+            let table = model.workbook.tables[&table_name].clone();
+            let old_name = table.columns[index as usize].name.clone();
+            model.rename_in_unqualified_references(&table, &old_name, new_name);
+            model.rename_in_table_references(&mut TableRenamer {
+                table_name: &table_name,
+                new_table_name: &table_name,
+                column: Some((&old_name, new_name)),
+                changed: false,
+            });
+            if let Some(table) = model.workbook.tables.get_mut(&table_name) {
+                table.columns[index as usize].name = new_name.to_string();
+            }
+            if header_row_count > 0 {
+                let style = model.get_cell_style_index(sheet, row1, column);
+                model.set_cell_with_string(sheet, row1, column, new_name, style);
+            }
+            model.reset_parsed_structures();
+            Ok(())
+        })
+    }
+
+    /// Typing in the header of a table column renames the column, like `rename_table_column`.
+    /// A name already in the table gets a number: Qty2. Returns false if the cell is not a header
+    /// or `value` is not a name.
+    pub(crate) fn set_table_header(
+        &mut self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        value: &str,
+    ) -> bool {
+        let name = value.strip_prefix('\'').unwrap_or(value);
+        if name.trim().is_empty() || value.starts_with('=') {
+            return false;
+        }
+        let sheet_name = match self.workbook.worksheets.get(sheet as usize) {
+            Some(worksheet) => worksheet.get_name(),
+            None => return false,
+        };
+        let header = self.workbook.tables.values().find_map(|table| {
+            let (column1, row1, _, _) = parse_range(&table.reference).ok()?;
+            if table.header_row_count == 0
+                || row != row1
+                || !table_contains(table, &sheet_name, row, column)
+            {
+                return None;
+            }
+            let index = column - column1;
+            let mut new_name = name.to_string();
+            let mut suffix = 1;
+            while let Some(other) = get_table_column_by_name(&new_name, table) {
+                if other == index {
+                    break;
+                }
+                suffix += 1;
+                new_name = format!("{}{}", name, suffix);
+            }
+            let old_name = table.columns.get(index as usize)?.name.clone();
+            Some((table.name.clone(), old_name, new_name))
+        });
+        match header {
+            Some((table_name, old_name, new_name)) => self
+                .rename_table_column(&table_name, &old_name, &new_name)
+                .is_ok(),
+            None => false,
+        }
+    }
+
+    /// Adds the row below a table to it when a value is entered in that row, under one of the
+    /// columns. Tables with a totals row don't grow.
+    pub(crate) fn grow_tables(&mut self, sheet: u32, row: i32, column: i32) {
+        let sheet_name = match self.workbook.worksheets.get(sheet as usize) {
+            Some(worksheet) => worksheet.get_name(),
+            None => return,
+        };
+        let mut changed = false;
+        for table in self.workbook.tables.values_mut() {
+            if table.sheet_name != sheet_name || table.totals_row_count > 0 {
+                continue;
+            }
+            let (column1, row1, column2, row2) = match parse_range(&table.reference) {
+                Ok(range) => range,
+                Err(_) => continue,
+            };
+            if row != row2 + 1 || column < column1 || column > column2 {
+                continue;
+            }
+            if let (Some(left), Some(right)) =
+                (number_to_column(column1), number_to_column(column2))
+            {
+                table.reference = format!("{}{}:{}{}", left, row1, right, row);
+                changed = true;
+            }
+        }
+        if changed {
+            self.parser.set_tables(self.workbook.tables.clone());
+            // The structured references now point to more cells
+            self.build_dependency_graph();
+        }
+    }
+}
@@ -1,13 +1,20 @@
 mod test_actions;
+mod test_array_formulas;
 mod test_binary_search;
 mod test_cell;
 mod test_circular_references;
 mod test_column_width;
 mod test_criteria;
 mod test_currency;
+mod test_data_tables;
 mod test_date_and_time;
+mod test_defined_names;
+mod test_dynamic_arrays;
 mod test_error_propagation;
 mod test_evaluate_with_error_check;
+mod test_evaluation_steps;
+mod test_events;
+mod test_external_references;
 mod test_fn_average;
 mod test_fn_averageifs;
 mod test_fn_choose;
@@ -16,17 +23,27 @@ mod test_fn_count;
 mod test_fn_exact;
 mod test_fn_financial;
 mod test_fn_if;
+mod test_fn_lambda;
+mod test_fn_large;
+mod test_fn_let;
 mod test_fn_maxifs;
 mod test_fn_minifs;
 mod test_fn_product;
 mod test_fn_rept;
+mod test_fn_stdev;
 mod test_fn_sum;
 mod test_fn_sumifs;
 mod test_fn_textbefore;
 mod test_fn_textjoin;
+mod test_format_formula;
 mod test_forward_references;
 mod test_frozen_rows_columns;
 mod test_general;
+mod test_goal_seek;
+mod test_incremental_evaluation;
+mod test_iterative_calculation;
+mod test_localized_formulas;
+mod test_long_chains;
 mod test_math;
 mod test_metadata;
 mod test_model_delete_cell;
@@ -34,17 +51,25 @@ mod test_model_is_empty_cell;
 mod test_model_set_cell_empty;
 mod test_move_formula;
 mod test_quote_prefix;
+mod test_reference_operators;
+mod test_reference_style;
 mod test_set_user_input;
 mod test_sheet_markup;
+mod test_sheet_spans;
 mod test_sheets;
+mod test_solver;
 mod test_styles;
+mod test_tables;
+mod test_trace;
 mod test_trigonometric;
+mod test_undo_redo;
 mod test_worksheet;
 pub(crate) mod util;
 
 mod engineering;
 mod test_fn_offset;
 mod test_number_format;
+mod test_parallel_evaluation;
 
 mod test_escape_quotes;
 mod test_fn_type;
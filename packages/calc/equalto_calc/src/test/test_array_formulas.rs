@@ -0,0 +1,138 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::model::Model;
+use crate::test::util::new_empty_model;
+
+#[test]
+fn test_array_formula_fills_its_area() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model._set("A3", "3");
+    model
+        .set_user_array_formula(0, 1, 2, 3, 2, "=A1:A3*2".to_string())
+        .unwrap();
+    model._set("C1", "=SUM(B1:B3)");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "2");
+    assert_eq!(model._get_text("B2"), "4");
+    assert_eq!(model._get_text("B3"), "6");
+    assert_eq!(model._get_text("C1"), "12");
+    assert_eq!(model._get_formula("B1"), "=A1:A3*2");
+    assert!(!model._has_formula("B2"));
+
+    model._set("A2", "10");
+    model.evaluate();
+    assert_eq!(model._get_text("B2"), "20");
+    assert_eq!(model._get_text("C1"), "28");
+}
+
+#[test]
+fn test_single_cell_array_formula() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model._set("A3", "3");
+    model
+        .set_user_array_formula(0, 1, 2, 1, 2, "=SUM(IF(A1:A3>1,A1:A3))".to_string())
+        .unwrap();
+    model
+        .set_user_array_formula(0, 2, 2, 2, 2, "=SUM(A1:A3*A1:A3)".to_string())
+        .unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "5");
+    assert_eq!(model._get_text("B2"), "14");
+}
+
+#[test]
+fn test_array_formula_broadcast() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model
+        .set_user_array_formula(0, 1, 2, 3, 3, "=A1:A2*10".to_string())
+        .unwrap();
+    model
+        .set_user_array_formula(0, 1, 4, 2, 5, "=1+1".to_string())
+        .unwrap();
+    model.evaluate();
+    // Single columns are repeated, missing rows are #N/A
+    assert_eq!(model._get_text("B1"), "10");
+    assert_eq!(model._get_text("C2"), "20");
+    assert_eq!(model._get_text("B3"), "#N/A");
+    assert_eq!(model._get_text("C3"), "#N/A");
+    // Single values fill the whole area
+    assert_eq!(model._get_text("D1"), "2");
+    assert_eq!(model._get_text("E2"), "2");
+}
+
+#[test]
+fn test_overwrite_array_formula() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model
+        .set_user_array_formula(0, 1, 2, 2, 2, "=A1:A2+1".to_string())
+        .unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("B2"), "3");
+
+    model._set("B1", "=A1:A2+1");
+    model.evaluate();
+    assert!(model.workbook.worksheets[0].array_formulas.is_empty());
+    // The formula spills now
+    assert_eq!(model._get_text("B1"), "2");
+    assert_eq!(model._get_text("B2"), "3");
+}
+
+#[test]
+fn test_array_formula_insert_rows() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model
+        .set_user_array_formula(0, 1, 2, 2, 2, "=A1:A2*3".to_string())
+        .unwrap();
+    model.evaluate();
+
+    model.insert_rows(0, 1, 2).unwrap();
+    model.evaluate();
+    assert_eq!(
+        model.workbook.worksheets[0].array_formulas,
+        vec!["B3:B4".to_string()]
+    );
+    assert_eq!(model._get_formula("B3"), "=A3:A4*3");
+    assert_eq!(model._get_text("B3"), "3");
+    assert_eq!(model._get_text("B4"), "6");
+    assert_eq!(model._get_text("B2"), "");
+}
+
+#[test]
+fn test_array_formula_json() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model
+        .set_user_array_formula(0, 1, 2, 2, 2, "=A1:A2*3".to_string())
+        .unwrap();
+    model.evaluate();
+
+    let mut model = Model::from_json(&model.to_json_str()).unwrap();
+    model._set("A2", "5");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "3");
+    assert_eq!(model._get_text("B2"), "15");
+}
+
+#[test]
+fn test_array_formula_errors() {
+    let mut model = new_empty_model();
+    assert_eq!(
+        model.set_user_array_formula(0, 1, 1, 2, 2, "A1".to_string()),
+        Err("\"A1\" is not a valid formula".to_string())
+    );
+    assert_eq!(
+        model.set_user_array_formula(0, 2, 2, 1, 1, "=1".to_string()),
+        Err("Invalid array formula area".to_string())
+    );
+}
@@ -0,0 +1,102 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::test::util::new_empty_model;
+use crate::types::DataTable;
+
+fn data_table(range: &str, r1: &str, r2: Option<&str>, dt2d: bool, dtr: bool) -> DataTable {
+    DataTable {
+        range: range.to_string(),
+        r1: r1.to_string(),
+        r2: r2.map(|r| r.to_string()),
+        dt2d,
+        dtr,
+    }
+}
+
+#[test]
+fn test_column_input_data_table() {
+    let mut model = new_empty_model();
+    model._set("A1", "2");
+    model._set("B1", "=A1*10");
+    // Values for A1 on the first column, the formula on the row above the results
+    model._set("C1", "=B1+1");
+    model._set("B2", "1");
+    model._set("B3", "5");
+    model._set("D2", "=SUM(C2:C3)");
+    model.workbook.worksheets[0]
+        .data_tables
+        .push(data_table("C2:C3", "A1", None, false, false));
+    model.evaluate();
+    assert_eq!(model._get_text("C2"), "11");
+    assert_eq!(model._get_text("C3"), "51");
+    assert_eq!(model._get_text("D2"), "62");
+    // The input cell and its dependents keep their values
+    assert_eq!(model._get_text("A1"), "2");
+    assert_eq!(model._get_text("B1"), "20");
+    assert_eq!(model._get_text("C1"), "21");
+
+    model._set("B3", "7");
+    model.evaluate();
+    assert_eq!(model._get_text("C3"), "71");
+    assert_eq!(model._get_text("D2"), "82");
+}
+
+#[test]
+fn test_row_input_data_table() {
+    let mut model = new_empty_model();
+    model._set("A5", "3");
+    model._set("B1", "1");
+    model._set("C1", "2");
+    model._set("D1", "3");
+    model._set("A2", "=A5*A5");
+    model._set("A3", "=IF(A5>1,\"big\",\"small\")");
+    model.workbook.worksheets[0]
+        .data_tables
+        .push(data_table("B2:D3", "A5", None, false, true));
+    model.evaluate();
+    assert_eq!(model._get_text("B2"), "1");
+    assert_eq!(model._get_text("C2"), "4");
+    assert_eq!(model._get_text("D2"), "9");
+    assert_eq!(model._get_text("B3"), "small");
+    assert_eq!(model._get_text("C3"), "big");
+    assert_eq!(model._get_text("A2"), "9");
+}
+
+#[test]
+fn test_two_variable_data_table() {
+    let mut model = new_empty_model();
+    model._set("F1", "1");
+    model._set("F2", "1");
+    model._set("A1", "=F1*F2");
+    model._set("B1", "2");
+    model._set("C1", "3");
+    model._set("A2", "4");
+    model._set("A3", "5");
+    model.workbook.worksheets[0].data_tables.push(data_table(
+        "B2:C3",
+        "F1",
+        Some("F2"),
+        true,
+        false,
+    ));
+    model.evaluate();
+    assert_eq!(model._get_text("B2"), "8");
+    assert_eq!(model._get_text("C2"), "12");
+    assert_eq!(model._get_text("B3"), "10");
+    assert_eq!(model._get_text("C3"), "15");
+    assert_eq!(model._get_text("A1"), "1");
+}
+
+#[test]
+fn test_empty_input_cell() {
+    let mut model = new_empty_model();
+    model._set("B1", "=A1+1");
+    model._set("A2", "10");
+    model.workbook.worksheets[0]
+        .data_tables
+        .push(data_table("B2", "A1", None, false, false));
+    model.evaluate();
+    assert_eq!(model._get_text("B2"), "11");
+    assert_eq!(model._get_text("A1"), "");
+    assert_eq!(model._get_text("B1"), "1");
+}
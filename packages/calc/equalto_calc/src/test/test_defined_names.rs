@@ -0,0 +1,204 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::model::Model;
+use crate::test::util::new_empty_model;
+use crate::types::DefinedName;
+
+fn add_defined_name(model: &mut Model, name: &str, formula: &str, sheet_id: Option<u32>) {
+    model.workbook.defined_names.push(DefinedName {
+        name: name.to_string(),
+        formula: formula.to_string(),
+        sheet_id,
+        hidden: false,
+    });
+    model.parse_defined_names();
+    model.build_dependency_graph();
+}
+
+#[test]
+fn test_constants() {
+    let mut model = new_empty_model();
+    add_defined_name(&mut model, "TaxRate", "0.21", None);
+    add_defined_name(&mut model, "Greeting", "\"Hello\"", None);
+    model._set("A1", "=100*TaxRate");
+    model._set("A2", "=Greeting&\" world\"");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "21");
+    assert_eq!(model._get_text("A2"), "Hello world");
+}
+
+#[test]
+fn test_formulas() {
+    let mut model = new_empty_model();
+    add_defined_name(&mut model, "Total", "SUM(Sheet1!$A$1:$A$3)*1.1", None);
+    add_defined_name(&mut model, "Doubled", "Total*2", None);
+    model._set("A1", "10");
+    model._set("A2", "20");
+    model._set("A3", "70");
+    model._set("B1", "=Total");
+    model._set("B2", "=Doubled");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "110");
+    assert_eq!(model._get_text("B2"), "220");
+
+    // Cells using the name are recomputed when its precedents change
+    model._set("A3", "170");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "220");
+    assert_eq!(model._get_text("B2"), "440");
+}
+
+#[test]
+fn test_relative_references() {
+    let mut model = new_empty_model();
+    // Relative to A1: the cell to the right of the cell using the name
+    add_defined_name(&mut model, "NextCell", "Sheet1!B1", None);
+    add_defined_name(&mut model, "RowTotal", "SUM(Sheet1!$B1:$C1)", None);
+    model._set("B3", "5");
+    model._set("C3", "7");
+    model._set("A3", "=NextCell*2");
+    model._set("D3", "=RowTotal");
+    model._set("D4", "=RowTotal");
+    model.evaluate();
+    assert_eq!(model._get_text("A3"), "10");
+    assert_eq!(model._get_text("D3"), "12");
+    assert_eq!(model._get_text("D4"), "0");
+
+    model._set("C3", "8");
+    model.evaluate();
+    assert_eq!(model._get_text("D3"), "13");
+}
+
+#[test]
+fn test_local_names() {
+    let mut model = new_empty_model();
+    model.new_sheet();
+    add_defined_name(&mut model, "Rate", "0.5", None);
+    add_defined_name(&mut model, "Rate", "0.25", Some(2));
+    model._set("A1", "=Rate*100");
+    model._set("Sheet2!A1", "=Rate*100");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "50");
+    assert_eq!(model._get_text("Sheet2!A1"), "25");
+}
+
+#[test]
+fn test_invalid_and_circular_names() {
+    let mut model = new_empty_model();
+    add_defined_name(&mut model, "Broken", "SUM(1,", None);
+    add_defined_name(&mut model, "Ping", "Pong+1", None);
+    add_defined_name(&mut model, "Pong", "Ping+1", None);
+    model._set("A1", "=Broken");
+    model._set("A2", "=Ping");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "#NAME?");
+    assert_eq!(model._get_text("A2"), "#CIRC!");
+}
+
+#[test]
+fn test_new_defined_name() {
+    let mut model = new_empty_model();
+    model._set("A1", "10");
+    model._set("B1", "=Price*2");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "#NAME?");
+
+    model
+        .new_defined_name("Price", None, "=Sheet1!$A$1")
+        .unwrap();
+    assert_eq!(model._get_text("B1"), "20");
+    assert_eq!(
+        model.get_defined_name_list(),
+        vec![("Price".to_string(), None, "Sheet1!$A$1".to_string())]
+    );
+
+    assert!(model.new_defined_name("price", None, "3").is_err());
+    assert!(model.new_defined_name("A1", None, "3").is_err());
+    assert!(model.new_defined_name("Tax", None, "SUM(1,").is_err());
+    assert!(model.new_defined_name("Tax", Some(5), "3").is_err());
+
+    // A local name with the same name takes precedence in its sheet
+    model.new_sheet();
+    model._set("Sheet2!B1", "=Price*2");
+    model.new_defined_name("Price", Some(1), "7").unwrap();
+    assert_eq!(model._get_text("Sheet2!B1"), "14");
+    assert_eq!(model._get_text("B1"), "20");
+}
+
+#[test]
+fn test_update_defined_name() {
+    let mut model = new_empty_model();
+    model.new_sheet();
+    model.new_defined_name("Rate", None, "0.5").unwrap();
+    model.new_defined_name("Total", None, "Rate*100").unwrap();
+    model._set("A1", "=Rate*10");
+    model._set("A2", "=SUM(Rate,Total)");
+    model._set("Sheet2!A1", "=Rate");
+    model.evaluate();
+    assert_eq!(model._get_text("A2"), "50.5");
+
+    // Renaming updates the formulas using the name
+    model
+        .update_defined_name("Rate", None, "Interest", None, "0.25")
+        .unwrap();
+    assert_eq!(model._get_formula("A1"), "=Interest*10");
+    assert_eq!(model._get_formula("A2"), "=SUM(Interest,Total)");
+    assert_eq!(model._get_formula("Sheet2!A1"), "=Interest");
+    assert_eq!(model._get_text("A1"), "2.5");
+    assert_eq!(model._get_text("A2"), "25.25");
+    assert_eq!(
+        model.get_defined_name_list()[1],
+        ("Total".to_string(), None, "Interest*100".to_string())
+    );
+
+    // Changing the scope
+    model
+        .update_defined_name("Interest", None, "Interest", Some(0), "0.25")
+        .unwrap();
+    assert_eq!(model._get_text("A1"), "2.5");
+    assert_eq!(model._get_text("Sheet2!A1"), "#NAME?");
+
+    assert!(model
+        .update_defined_name("Interest", None, "Interest", None, "1")
+        .is_err());
+    assert!(model
+        .update_defined_name("Interest", Some(0), "Total", None, "1")
+        .is_err());
+    assert!(model
+        .update_defined_name("Interest", Some(0), "Interest", Some(0), "=(")
+        .is_err());
+}
+
+#[test]
+fn test_rename_local_defined_name() {
+    let mut model = new_empty_model();
+    model.new_sheet();
+    model.new_defined_name("Rate", None, "1").unwrap();
+    model.new_defined_name("Rate", Some(1), "2").unwrap();
+    model._set("A1", "=Rate");
+    model._set("Sheet2!A1", "=Rate");
+    model.evaluate();
+    assert_eq!(model._get_text("Sheet2!A1"), "2");
+
+    model
+        .update_defined_name("Rate", Some(1), "LocalRate", Some(1), "2")
+        .unwrap();
+    assert_eq!(model._get_formula("A1"), "=Rate");
+    assert_eq!(model._get_formula("Sheet2!A1"), "=LocalRate");
+    assert_eq!(model._get_text("A1"), "1");
+    assert_eq!(model._get_text("Sheet2!A1"), "2");
+}
+
+#[test]
+fn test_delete_defined_name() {
+    let mut model = new_empty_model();
+    model.new_defined_name("Rate", None, "0.5").unwrap();
+    model._set("A1", "=Rate*10");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "5");
+
+    model.delete_defined_name("Rate", None).unwrap();
+    assert_eq!(model._get_text("A1"), "#NAME?");
+    assert!(model.get_defined_name_list().is_empty());
+    assert!(model.delete_defined_name("Rate", None).is_err());
+}
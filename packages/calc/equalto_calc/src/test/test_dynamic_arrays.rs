@@ -0,0 +1,462 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::test::util::new_empty_model;
+use crate::types::Cell;
+
+#[test]
+fn test_array_literal_spills() {
+    let mut model = new_empty_model();
+    model._set("A1", "={1,2,3;4,5,6}");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), "1");
+    assert_eq!(model._get_text("B1"), "2");
+    assert_eq!(model._get_text("C1"), "3");
+    assert_eq!(model._get_text("A2"), "4");
+    assert_eq!(model._get_text("C2"), "6");
+    assert!(model.is_empty_cell(0, 3, 1).unwrap());
+    assert!(model.is_empty_cell(0, 1, 4).unwrap());
+
+    // Spilled cells have no formula
+    assert_eq!(model._get_formula("A1"), "={1,2,3;4,5,6}");
+    assert!(!model._has_formula("B2"));
+    assert_eq!(
+        model._get_cell("B2"),
+        &Cell::SpillNumber {
+            v: 5.0,
+            r: 1,
+            c: 1,
+            s: 0
+        }
+    );
+}
+
+#[test]
+fn test_array_literal_parse_error() {
+    let mut model = new_empty_model();
+    model._set("A1", "={1,2;3}");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "#ERROR!");
+}
+
+#[test]
+fn test_operators_on_arrays() {
+    let mut model = new_empty_model();
+    model._set("A1", "={1,2,3}*2");
+    model._set("A2", "={1;2}+{10,20}");
+    model._set("A4", "={1,2,3}+{1,2}");
+    model._set("A5", "=-{1,2}");
+    model._set("A6", "={1,2}&\"x\"");
+    model._set("A7", "={1,2,3}>1");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), "2");
+    assert_eq!(model._get_text("C1"), "6");
+
+    // A column and a row are broadcast into a matrix
+    assert_eq!(model._get_text("A2"), "11");
+    assert_eq!(model._get_text("B2"), "21");
+    assert_eq!(model._get_text("A3"), "12");
+    assert_eq!(model._get_text("B3"), "22");
+
+    // Missing elements are #N/A
+    assert_eq!(model._get_text("B4"), "4");
+    assert_eq!(model._get_text("C4"), "#N/A");
+
+    assert_eq!(model._get_text("B5"), "-2");
+    assert_eq!(model._get_text("B6"), "2x");
+    assert_eq!(model._get_text("A7"), "FALSE");
+    assert_eq!(model._get_text("C7"), "TRUE");
+}
+
+#[test]
+fn test_aggregate_functions_on_arrays() {
+    let mut model = new_empty_model();
+    model._set("A1", "=SUM({1,2;3,4})");
+    model._set("A2", "=SUM({1,2,3}*{4,5,6})");
+    model._set("A3", "=COUNT({1,\"a\",TRUE,2})");
+    model._set("A4", "=MAX(SEQUENCE(5))");
+    model._set("A5", "=AVERAGE({1,2,3,6})");
+    model._set("A6", "=AND({TRUE,1,FALSE})");
+    model._set("A7", "=CONCAT({\"a\",\"b\";\"c\",\"d\"})");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), "10");
+    assert_eq!(model._get_text("A2"), "32");
+    assert_eq!(model._get_text("A3"), "2");
+    assert_eq!(model._get_text("A4"), "5");
+    assert_eq!(model._get_text("A5"), "3");
+    assert_eq!(model._get_text("A6"), "FALSE");
+    assert_eq!(model._get_text("A7"), "abcd");
+}
+
+#[test]
+fn test_spill_is_blocked() {
+    let mut model = new_empty_model();
+    model._set("A1", "=SEQUENCE(3)");
+    model._set("A3", "blocker");
+    model._set("B1", "=A1");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "#SPILL!");
+    assert_eq!(model._get_text("A2"), "");
+    assert_eq!(model._get_text("B1"), "#SPILL!");
+
+    // Removing the blocker lets the formula spill
+    model.set_cell_empty(0, 3, 1).unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "1");
+    assert_eq!(model._get_text("A3"), "3");
+    assert_eq!(model._get_text("B1"), "1");
+
+    // Writing into the spill area blocks it again
+    model._set("A2", "7");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "#SPILL!");
+    assert_eq!(model._get_text("A2"), "7");
+    assert_eq!(model._get_text("A3"), "");
+}
+
+#[test]
+fn test_spill_beyond_the_sheet() {
+    let mut model = new_empty_model();
+    model._set("A1048575", "=SEQUENCE(3)");
+    model.evaluate();
+    assert_eq!(model._get_text("A1048575"), "#SPILL!");
+}
+
+#[test]
+fn test_spill_shrinks_and_grows() {
+    let mut model = new_empty_model();
+    model._set("A1", "3");
+    model._set("B1", "=SEQUENCE(A1)");
+    model._set("C1", "=SUM(B1:B10)");
+    model._set("D1", "=B4");
+    model.evaluate();
+    assert_eq!(model._get_text("B3"), "3");
+    assert_eq!(model._get_text("C1"), "6");
+    assert_eq!(model._get_text("D1"), "0");
+
+    model._set("A1", "2");
+    model.evaluate();
+    assert_eq!(model._get_text("B2"), "2");
+    assert!(model.is_empty_cell(0, 3, 2).unwrap());
+    assert_eq!(model._get_text("C1"), "3");
+
+    model._set("A1", "5");
+    model.evaluate();
+    assert_eq!(model._get_text("B5"), "5");
+    assert_eq!(model._get_text("C1"), "15");
+    assert_eq!(model._get_text("D1"), "4");
+
+    // Replacing the formula with a value removes the spilled values
+    model._set("B1", "1");
+    model.evaluate();
+    assert!(model.is_empty_cell(0, 2, 2).unwrap());
+    assert_eq!(model._get_text("C1"), "1");
+    assert_eq!(model._get_text("D1"), "0");
+}
+
+#[test]
+fn test_reading_spilled_cells_before_the_formula() {
+    let mut model = new_empty_model();
+    // A1 is evaluated before the formula in B2
+    model._set("A1", "=C3*10");
+    model._set("B2", "={1,2;3,4}");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "40");
+
+    model._set("B2", "={5,6;7,8}");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "80");
+}
+
+#[test]
+fn test_spill_range_operator() {
+    let mut model = new_empty_model();
+    model._set("A1", "=SEQUENCE(4)");
+    model._set("B1", "=SUM(A1#)");
+    model._set("C1", "=ROWS(A1#)");
+    model._set("D1", "=A1#*2");
+    model._set("E1", "=SUM(F1#)");
+    model._set("F1", "5");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "10");
+    assert_eq!(model._get_text("C1"), "4");
+    assert_eq!(model._get_text("E1"), "#REF!");
+    assert_eq!(model._get_formula("B1"), "=SUM(A1#)");
+
+    model._set("A1", "=SEQUENCE(2)");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "3");
+    assert_eq!(model._get_text("C1"), "2");
+
+    // A blocked spill range is a #REF!
+    model._set("A2", "x");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "#REF!");
+}
+
+#[test]
+fn test_ranges_in_operators() {
+    let mut model = new_empty_model();
+    model._set("E1", "1");
+    model._set("E2", "2");
+    model._set("A5", "=E1:E2*10");
+    model._set("B5", "=SUM(E1:E2*10)");
+    model._set("C5", "=-E1:E2");
+    model.evaluate();
+    assert_eq!(model._get_text("A5"), "10");
+    assert_eq!(model._get_text("A6"), "20");
+    assert_eq!(model._get_text("B5"), "30");
+    assert_eq!(model._get_text("B6"), "");
+    assert_eq!(model._get_text("C6"), "-2");
+}
+
+#[test]
+fn test_single() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model._set("A3", "3");
+    model._set("B2", "=SINGLE(A1:A3)*10");
+    model._set("C2", "=SINGLE(SEQUENCE(3)+5)");
+    model._set("D5", "=SINGLE(A1:A3)");
+    model.evaluate();
+    assert_eq!(model._get_text("B2"), "20");
+    assert_eq!(model._get_text("B3"), "");
+    assert_eq!(model._get_text("C2"), "6");
+    assert_eq!(model._get_text("D5"), "#VALUE!");
+}
+
+#[test]
+fn test_bare_range_spills() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "Two");
+    model._set("C1", "=A1:A3");
+    model._set("D1", "=A1:A1");
+    model.evaluate();
+    assert_eq!(model._get_text("C1"), "1");
+    assert_eq!(model._get_text("C2"), "Two");
+    assert_eq!(model._get_text("C3"), "0");
+    assert_eq!(model._get_text("D1"), "1");
+    assert_eq!(model._get_text("D2"), "");
+
+    model._set("C3", "blocked");
+    model.evaluate();
+    assert_eq!(model._get_text("C1"), "#SPILL!");
+}
+
+#[test]
+fn test_spill_range_operator_in_arrays() {
+    let mut model = new_empty_model();
+    model._set("A1", "=SEQUENCE(3)");
+    model._set("B1", "=A1#*2");
+    model._set("C1", "=-A1#");
+    model._set("D2", "=A1:A3*2");
+    model.evaluate();
+    // Operators on spill ranges spill
+    assert_eq!(model._get_text("B1"), "2");
+    assert_eq!(model._get_text("B3"), "6");
+    assert_eq!(model._get_text("C3"), "-3");
+    // So do operators on plain ranges
+    assert_eq!(model._get_text("D2"), "2");
+    assert_eq!(model._get_text("D4"), "6");
+
+    model._set("B1", "=SORT(A1#*2,1,-1)");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "6");
+    assert_eq!(model._get_text("B3"), "2");
+}
+
+#[test]
+fn test_fn_sequence() {
+    let mut model = new_empty_model();
+    model._set("A1", "=SEQUENCE(2,3)");
+    model._set("A3", "=SEQUENCE(2,,10,-5)");
+    model._set("A5", "=SEQUENCE(0)");
+    model._set("A6", "=SEQUENCE(-1)");
+    model._set("A7", "=SEQUENCE()");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), "1");
+    assert_eq!(model._get_text("C1"), "3");
+    assert_eq!(model._get_text("A2"), "4");
+    assert_eq!(model._get_text("C2"), "6");
+    assert_eq!(model._get_text("A3"), "10");
+    assert_eq!(model._get_text("A4"), "5");
+    assert_eq!(model._get_text("A5"), "#CALC!");
+    assert_eq!(model._get_text("A6"), "#VALUE!");
+    assert_eq!(model._get_text("A7"), *"#ERROR!");
+}
+
+#[test]
+fn test_fn_randarray() {
+    let mut model = new_empty_model();
+    model._set("A1", "=RANDARRAY(3,2,5,10,TRUE)");
+    model._set("D1", "=RANDARRAY()");
+    model._set("E1", "=RANDARRAY(2,2,3,1)");
+    model.evaluate();
+
+    for row in 1..=3 {
+        for column in 1..=2 {
+            let value: f64 = model._get_text_at(0, row, column).parse().unwrap();
+            assert!((5.0..=10.0).contains(&value));
+            assert_eq!(value.fract(), 0.0);
+        }
+    }
+    let value: f64 = model._get_text("D1").parse().unwrap();
+    assert!((0.0..1.0).contains(&value));
+    assert!(model.is_empty_cell(0, 2, 4).unwrap());
+    assert_eq!(model._get_text("E1"), "#VALUE!");
+}
+
+#[test]
+fn test_fn_sort() {
+    let mut model = new_empty_model();
+    model._set("A1", "3");
+    model._set("A2", "b");
+    model._set("A3", "1");
+    model._set("A4", "TRUE");
+    model._set("A5", "a");
+    model._set("B1", "=SORT(A1:A5)");
+    model._set("C1", "=SORT(A1:A5,,-1)");
+    model._set("D1", "=SORT({3,1,2;30,10,20},1,1,TRUE)");
+    model._set("D3", "=SORT({1,\"z\";2,\"y\";3,\"x\"},2)");
+    model._set("D6", "=SORT(A1:A5,2)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("B1"), "1");
+    assert_eq!(model._get_text("B2"), "3");
+    assert_eq!(model._get_text("B3"), "a");
+    assert_eq!(model._get_text("B4"), "b");
+    assert_eq!(model._get_text("B5"), "TRUE");
+    assert_eq!(model._get_text("C1"), "TRUE");
+    assert_eq!(model._get_text("C5"), "1");
+    assert_eq!(model._get_text("D1"), "1");
+    assert_eq!(model._get_text("E1"), "2");
+    assert_eq!(model._get_text("F2"), "30");
+    assert_eq!(model._get_text("D3"), "3");
+    assert_eq!(model._get_text("E5"), "z");
+    assert_eq!(model._get_text("D6"), "#VALUE!");
+}
+
+#[test]
+fn test_fn_sortby() {
+    let mut model = new_empty_model();
+    model._set("A1", "Tom");
+    model._set("A2", "Fred");
+    model._set("A3", "Amy");
+    model._set("B1", "2");
+    model._set("B2", "1");
+    model._set("B3", "2");
+    model._set("C1", "=SORTBY(A1:A3,B1:B3,1,A1:A3,-1)");
+    model._set("D1", "=SORTBY(A1:A3,B1:B2)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("C1"), "Fred");
+    assert_eq!(model._get_text("C2"), "Tom");
+    assert_eq!(model._get_text("C3"), "Amy");
+    assert_eq!(model._get_text("D1"), "#VALUE!");
+}
+
+#[test]
+fn test_fn_filter() {
+    let mut model = new_empty_model();
+    model._set("A1", "apples");
+    model._set("A2", "pears");
+    model._set("A3", "plums");
+    model._set("B1", "10");
+    model._set("B2", "2");
+    model._set("B3", "30");
+    model._set("C1", "=FILTER(A1:B3,B1:B3>5)");
+    model._set("E1", "=FILTER(A1:A3,B1:B3>100)");
+    model._set("E2", "=FILTER(A1:A3,B1:B3>100,\"none\")");
+    model._set("E3", "=FILTER({1,2,3},{TRUE,FALSE,TRUE})");
+    model._set("E4", "=FILTER(A1:A3,B1:B2>5)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("C1"), "apples");
+    assert_eq!(model._get_text("D1"), "10");
+    assert_eq!(model._get_text("C2"), "plums");
+    assert_eq!(model._get_text("D2"), "30");
+    assert!(model.is_empty_cell(0, 3, 3).unwrap());
+    assert_eq!(model._get_text("E1"), "#CALC!");
+    assert_eq!(model._get_text("E2"), "none");
+    assert_eq!(model._get_text("E3"), "1");
+    assert_eq!(model._get_text("F3"), "3");
+    assert_eq!(model._get_text("E4"), "#VALUE!");
+
+    // Updating the source updates the filtered values
+    model._set("B2", "20");
+    model.evaluate();
+    assert_eq!(model._get_text("C2"), "pears");
+    assert_eq!(model._get_text("C3"), "plums");
+}
+
+#[test]
+fn test_fn_unique() {
+    let mut model = new_empty_model();
+    model._set("A1", "=UNIQUE({1;2;1;\"a\";\"A\"})");
+    model._set("B1", "=UNIQUE({1;2;1},FALSE,TRUE)");
+    model._set("C1", "=UNIQUE({1,1,2},TRUE)");
+    model._set("C2", "=UNIQUE({1;1},,TRUE)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), "1");
+    assert_eq!(model._get_text("A2"), "2");
+    assert_eq!(model._get_text("A3"), "a");
+    assert!(model.is_empty_cell(0, 4, 1).unwrap());
+    assert_eq!(model._get_text("B1"), "2");
+    assert!(model.is_empty_cell(0, 2, 2).unwrap());
+    assert_eq!(model._get_text("C1"), "1");
+    assert_eq!(model._get_text("D1"), "2");
+    assert_eq!(model._get_text("C2"), "#CALC!");
+}
+
+#[test]
+fn test_xlsx_function_names() {
+    let mut model = new_empty_model();
+    model._set("A1", "=_xlfn._xlws.SORT({2;1})");
+    model._set("B1", "=_xlfn.SEQUENCE(2)");
+    model.evaluate();
+    assert_eq!(model._get_formula("A1"), "=SORT({2;1})");
+    assert_eq!(model._get_text("A1"), "1");
+    assert_eq!(model._get_text("B2"), "2");
+}
+
+#[test]
+fn test_insert_rows_in_spill_area() {
+    let mut model = new_empty_model();
+    model._set("A1", "=SEQUENCE(3)");
+    model._set("B1", "=SUM(A1:A10)");
+    model.evaluate();
+
+    model.insert_rows(0, 2, 1).unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "1");
+    assert_eq!(model._get_text("A2"), "2");
+    assert_eq!(model._get_text("A3"), "3");
+    assert!(model.is_empty_cell(0, 4, 1).unwrap());
+    assert_eq!(model._get_text("B1"), "6");
+
+    model.insert_rows(0, 1, 1).unwrap();
+    model.evaluate();
+    assert!(model.is_empty_cell(0, 1, 1).unwrap());
+    assert_eq!(model._get_formula("A2"), "=SEQUENCE(3)");
+    assert_eq!(model._get_text("A4"), "3");
+    assert_eq!(model._get_text("B2"), "6");
+}
+
+#[test]
+fn test_json_round_trip() {
+    let mut model = new_empty_model();
+    model._set("A1", "={\"a\",TRUE;1,#N/A}");
+    model.evaluate();
+    let mut model = crate::model::Model::from_json(&model.to_json_str()).unwrap();
+    assert_eq!(model._get_text("B1"), "TRUE");
+    assert_eq!(model._get_text("B2"), "#N/A");
+    model.evaluate();
+    assert_eq!(model._get_text("A2"), "1");
+    assert_eq!(model._get_text("B2"), "#N/A");
+}
@@ -17,11 +17,11 @@ fn test_circ() {
 #[test]
 fn test_nimpl() {
     let mut model = new_empty_model();
-    model._set("B2", "={{1}}");
+    model._set("B2", "=TEXT(A1:A3, \"0\")");
     assert_eq!(
         model.evaluate_with_error_check(),
         Err(vec![
-            "Sheet1!B2 ('={{1}}'): Arrays not implemented".to_string()
+            "Sheet1!B2 ('=TEXT(A1:A3,\"0\")'): Implicit Intersection not implemented".to_string()
         ]),
     )
 }
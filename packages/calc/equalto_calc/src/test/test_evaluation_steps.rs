@@ -0,0 +1,161 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::calc_result::CellReference;
+use crate::evaluation_steps::{EvaluationStep, StepValue};
+use crate::expressions::types::{Area, CellReferenceIndex};
+use crate::test::util::{cell, new_empty_model};
+
+fn number(value: f64) -> Option<StepValue> {
+    Some(StepValue::Number { value })
+}
+
+#[test]
+fn test_operators_and_functions() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model._set("B1", "=SUM(A1:A2)*2+A1");
+
+    let steps = model.evaluate_formula_steps(cell(1, 2)).unwrap();
+    assert_eq!(steps.formula, "SUM(A1:A2)*2+A1");
+    assert_eq!(steps.value, number(7.0));
+    assert_eq!(steps.children.len(), 2);
+
+    let product = &steps.children[0];
+    assert_eq!(product.formula, "SUM(A1:A2)*2");
+    assert_eq!(product.value, number(6.0));
+
+    let sum = &product.children[0];
+    assert_eq!(sum.formula, "SUM(A1:A2)");
+    assert_eq!(sum.value, number(3.0));
+    assert_eq!(
+        sum.children,
+        vec![EvaluationStep {
+            formula: "A1:A2".to_string(),
+            value: Some(StepValue::Reference {
+                area: Area {
+                    sheet: 0,
+                    row: 1,
+                    column: 1,
+                    width: 1,
+                    height: 2,
+                },
+                values: vec![
+                    vec![StepValue::Number { value: 1.0 }],
+                    vec![StepValue::Number { value: 2.0 }],
+                ],
+            }),
+            children: vec![],
+        }]
+    );
+
+    let reference = &steps.children[1];
+    assert_eq!(reference.formula, "A1");
+    assert!(matches!(
+        reference.value,
+        Some(StepValue::Reference { ref values, .. }) if values == &vec![vec![StepValue::Number { value: 1.0 }]]
+    ));
+}
+
+#[test]
+fn test_branches_not_taken() {
+    let mut model = new_empty_model();
+    model._set("A1", "5");
+    model._set("B1", "=IF(A1>3,\"big\",1/0)");
+
+    let steps = model.evaluate_formula_steps(cell(1, 2)).unwrap();
+    assert_eq!(
+        steps.value,
+        Some(StepValue::Text {
+            value: "big".to_string()
+        })
+    );
+    let condition = &steps.children[0];
+    assert_eq!(condition.formula, "A1>3");
+    assert_eq!(condition.value, Some(StepValue::Boolean { value: true }));
+    assert_eq!(steps.children[2].formula, "1/0");
+    assert_eq!(steps.children[2].value, None);
+}
+
+#[test]
+fn test_error_origin() {
+    let mut model = new_empty_model();
+    model._set("A1", "hello");
+    model._set("A2", "=A1*2");
+    model._set("B1", "=A2+1");
+
+    let steps = model.evaluate_formula_steps(cell(1, 2)).unwrap();
+    match steps.value {
+        Some(StepValue::Error { error, origin, .. }) => {
+            assert_eq!(error, "#VALUE!");
+            assert_eq!(
+                origin,
+                CellReferenceIndex {
+                    sheet: 0,
+                    row: 2,
+                    column: 1
+                }
+            );
+        }
+        value => panic!("Expected an error, found {value:?}"),
+    }
+}
+
+#[test]
+fn test_let_and_arrays() {
+    let mut model = new_empty_model();
+    model._set("A1", "=LET(x,{1,2},x*10)");
+
+    let steps = model.evaluate_formula_steps(cell(1, 1)).unwrap();
+    let expected = Some(StepValue::Array {
+        values: vec![vec![
+            StepValue::Number { value: 10.0 },
+            StepValue::Number { value: 20.0 },
+        ]],
+    });
+    assert_eq!(steps.value, expected);
+    // The body is evaluated with the names bound by LET
+    assert_eq!(steps.children[2].formula, "x*10");
+    assert_eq!(steps.children[2].value, expected);
+    assert_eq!(steps.children[1].children.len(), 2);
+}
+
+#[test]
+fn test_not_a_formula() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    assert_eq!(
+        model.evaluate_formula_steps(cell(1, 1)),
+        Err("The cell does not contain a formula".to_string())
+    );
+    assert!(model
+        .evaluate_formula_steps(CellReference {
+            sheet: 3,
+            row: 1,
+            column: 1
+        })
+        .is_err());
+}
+
+#[test]
+fn test_spilled_ranges_and_defined_names() {
+    let mut model = new_empty_model();
+    model.new_defined_name("Two", None, "=1+1").unwrap();
+    model._set("A1", "=SEQUENCE(3)");
+    model._set("C1", "=COUNT(A1#)+Two*3");
+
+    let steps = model.evaluate_formula_steps(cell(1, 3)).unwrap();
+    assert_eq!(steps.value, number(9.0));
+    let count = &steps.children[0];
+    assert_eq!(count.formula, "COUNT(A1#)");
+    assert_eq!(count.value, number(3.0));
+    // A spilled range is a single step
+    assert_eq!(count.children[0].formula, "A1#");
+    assert!(count.children[0].children.is_empty());
+    // The steps that come after it keep their values
+    let product = &steps.children[1];
+    assert_eq!(product.value, number(6.0));
+    assert_eq!(product.children[0].formula, "Two");
+    assert_eq!(product.children[0].value, number(2.0));
+    assert_eq!(product.children[1].value, number(3.0));
+}
@@ -0,0 +1,148 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::events::ModelEvent;
+use crate::test::util::new_empty_model;
+
+fn cell_changed(row: i32, column: i32) -> ModelEvent {
+    ModelEvent::CellChanged {
+        sheet: 0,
+        row,
+        column,
+    }
+}
+
+#[test]
+fn test_disabled_by_default() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model.evaluate();
+    assert!(model.take_events().is_empty());
+}
+
+#[test]
+fn test_changed_cells() {
+    let mut model = new_empty_model();
+    model.set_events_enabled(true);
+    model._set("A1", "1");
+    model._set("A2", "=A1*2");
+    model._set("A3", "=5");
+    model.evaluate();
+    assert_eq!(
+        model.take_events(),
+        vec![cell_changed(1, 1), cell_changed(2, 1), cell_changed(3, 1)]
+    );
+
+    // A3 does not change
+    model._set("A1", "4");
+    model.evaluate();
+    assert_eq!(
+        model.take_events(),
+        vec![cell_changed(1, 1), cell_changed(2, 1)]
+    );
+
+    // Same content
+    model._set("A1", "4");
+    model.evaluate();
+    assert!(model.take_events().is_empty());
+
+    // Back to the original value before evaluating
+    model._set("A1", "5");
+    model._set("A1", "4");
+    model.evaluate();
+    assert!(model.take_events().is_empty());
+}
+
+#[test]
+fn test_spilled_cells() {
+    let mut model = new_empty_model();
+    model._set("A1", "=SEQUENCE(3)");
+    model.evaluate();
+    model.set_events_enabled(true);
+    model._set("A1", "=SEQUENCE(2)");
+    model.evaluate();
+    assert_eq!(
+        model.take_events(),
+        vec![cell_changed(1, 1), cell_changed(3, 1)]
+    );
+}
+
+#[test]
+fn test_styles() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model.evaluate();
+    model.set_events_enabled(true);
+    let mut style = model.get_style_for_cell(0, 1, 1);
+    style.font.b = true;
+    model.set_cell_style(0, 1, 1, &style).unwrap();
+    model.evaluate();
+    assert_eq!(model.take_events(), vec![cell_changed(1, 1)]);
+}
+
+#[test]
+fn test_structural_changes() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("B1", "=A1");
+    model.evaluate();
+    model.set_events_enabled(true);
+
+    model.insert_rows(0, 1, 1).unwrap();
+    model.evaluate();
+    assert_eq!(
+        model.take_events(),
+        vec![
+            ModelEvent::RowsInserted {
+                sheet: 0,
+                row: 1,
+                count: 1
+            },
+            cell_changed(1, 1),
+            cell_changed(1, 2),
+            cell_changed(2, 1),
+            cell_changed(2, 2),
+        ]
+    );
+
+    model.new_sheet();
+    model.rename_sheet("Sheet2", "Data").unwrap();
+    model.delete_sheet(1).unwrap();
+    assert_eq!(
+        model.take_events(),
+        vec![
+            ModelEvent::SheetAdded { sheet: 1 },
+            ModelEvent::SheetRenamed {
+                sheet: 1,
+                old_name: "Sheet2".to_string(),
+                new_name: "Data".to_string()
+            },
+            ModelEvent::SheetDeleted { sheet: 1 },
+        ]
+    );
+}
+
+#[test]
+fn test_undo() {
+    let mut model = new_empty_model();
+    model.set_events_enabled(true);
+    model._set("A1", "1");
+    model.evaluate();
+    model.take_events();
+
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(model.take_events(), vec![cell_changed(1, 1)]);
+}
+
+#[test]
+fn test_serialization() {
+    let event = ModelEvent::RowsDeleted {
+        sheet: 0,
+        row: 3,
+        count: 2,
+    };
+    assert_eq!(
+        serde_json::to_string(&event).unwrap(),
+        r#"{"type":"RowsDeleted","sheet":0,"row":3,"count":2}"#
+    );
+}
@@ -0,0 +1,132 @@
+#![allow(clippy::unwrap_used)]
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::external::ExternalSnapshot;
+use crate::test::util::new_empty_model;
+use crate::types::{ExternalCell, ExternalLink, ExternalSheet, ExternalValue, ReferenceStyle};
+
+fn rates_snapshot() -> Vec<ExternalLink> {
+    vec![ExternalLink {
+        workbook: "Rates.xlsx".to_string(),
+        sheets: vec![ExternalSheet {
+            name: "Sheet1".to_string(),
+            cells: vec![
+                ExternalCell {
+                    row: 2,
+                    column: 2,
+                    value: ExternalValue::Number(1.5),
+                },
+                ExternalCell {
+                    row: 3,
+                    column: 2,
+                    value: ExternalValue::Number(2.5),
+                },
+            ],
+        }],
+    }]
+}
+
+#[test]
+fn test_external_formulas() {
+    let mut model = new_empty_model();
+    model._set("A1", "=[Rates.xlsx]Sheet1!$B$2*2");
+    model._set("A2", "='C:\\models\\[fx.xlsx]USD'!A1");
+    model._set("A3", "=SUM([Rates.xlsx]Sheet1!B2:B3)");
+    model._set("A4", "='[Rates.xlsx]Second Sheet'!B$1");
+    model.evaluate();
+
+    assert_eq!(model._get_formula("A1"), "=[Rates.xlsx]Sheet1!$B$2*2");
+    assert_eq!(model._get_formula("A2"), "='C:\\models\\[fx.xlsx]USD'!A1");
+    assert_eq!(model._get_formula("A3"), "=SUM([Rates.xlsx]Sheet1!B2:B3)");
+    assert_eq!(model._get_formula("A4"), "='[Rates.xlsx]Second Sheet'!B$1");
+
+    // Nothing is known about the other workbooks
+    assert_eq!(model._get_text("A1"), "#REF!");
+    assert_eq!(model._get_text("A2"), "#REF!");
+
+    model.set_reference_style(ReferenceStyle::R1C1);
+    assert_eq!(model._get_formula("A1"), "=[Rates.xlsx]Sheet1!R2C2*2");
+    assert_eq!(
+        model._get_formula("A3"),
+        "=SUM([Rates.xlsx]Sheet1!R[-1]C[1]:RC[1])"
+    );
+}
+
+#[test]
+fn test_cached_values() {
+    let mut model = new_empty_model();
+    model.set_external_links(rates_snapshot());
+    model._set("A1", "=[Rates.xlsx]Sheet1!$B$2*2");
+    model._set("A2", "=SUM('C:\\rates\\[Rates.xlsx]Sheet1'!B2:B3)");
+    model._set("A3", "=[Rates.xlsx]Sheet1!C2");
+    model._set("A4", "=[Rates.xlsx]Other!C2");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), "3");
+    assert_eq!(model._get_text("A2"), "4");
+    assert_eq!(model._get_text("A3"), "0");
+    assert_eq!(model._get_text("A4"), "#REF!");
+}
+
+#[test]
+fn test_model_resolver() {
+    let mut rates = new_empty_model();
+    rates._set("B2", "=1+1");
+    rates._set("B3", "hello");
+    rates.evaluate();
+
+    let mut model = new_empty_model();
+    model.set_external_links(rates_snapshot());
+    model._set("A1", "=[Rates.xlsx]Sheet1!$B$2*2");
+    model._set("A2", "=[Rates.xlsx]Sheet1!B3");
+    model._set("A3", "=[Other.xlsx]Sheet1!B3");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "3");
+
+    let mut models = HashMap::new();
+    models.insert("Rates.xlsx".to_string(), rates);
+    model.set_external_resolver(Arc::new(models));
+    model.evaluate();
+
+    // The loaded workbook takes precedence over the cached values
+    assert_eq!(model._get_text("A1"), "4");
+    assert_eq!(model._get_text("A2"), "hello");
+    assert_eq!(model._get_text("A3"), "#REF!");
+}
+
+#[test]
+fn test_external_references_are_not_displaced() {
+    let mut model = new_empty_model();
+    model._set("A3", "=[Rates.xlsx]Sheet1!B2+B2");
+    model.insert_rows(0, 1, 1).unwrap();
+    model.insert_columns(0, 1, 1).unwrap();
+    assert_eq!(model._get_formula("B4"), "=[Rates.xlsx]Sheet1!B2+C3");
+}
+
+#[test]
+fn test_snapshot_resolver() {
+    let mut model = new_empty_model();
+    model._set("A1", "=[Rates.xlsx]Sheet1!$B$2*2");
+    model._set("A2", "=SUM([Rates.xlsx]Sheet1!B2:B4)");
+    model._set("A3", "=[Rates.xlsx]sheet1!B3");
+    model.set_external_resolver(Arc::new(ExternalSnapshot::new(&rates_snapshot())));
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "3");
+    assert_eq!(model._get_text("A2"), "4");
+    assert_eq!(model._get_text("A3"), "2.5");
+}
+
+#[test]
+fn test_cached_values_in_parallel() {
+    let mut model = new_empty_model();
+    model.add_sheet("Sheet2").unwrap();
+    model.set_external_links(rates_snapshot());
+    model._set("Sheet1!A1", "=[Rates.xlsx]Sheet1!$B$2*2");
+    model._set("Sheet2!A1", "=[Rates.xlsx]Sheet1!$B$3*2");
+    model.set_evaluation_threads(2);
+    model.evaluate();
+    assert_eq!(model._get_text("Sheet1!A1"), "3");
+    assert_eq!(model._get_text("Sheet2!A1"), "5");
+}
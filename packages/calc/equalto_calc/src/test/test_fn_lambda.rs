@@ -0,0 +1,149 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::test::util::new_empty_model;
+
+#[test]
+fn fn_lambda_inline() {
+    let mut model = new_empty_model();
+    model._set("A1", "3");
+    model._set("B1", "=LAMBDA(x, x*2)(A1)");
+    model._set("B2", "=LAMBDA(x, y, x^2+y)(A1, 1)");
+    model._set("B3", "=LAMBDA(42)()");
+    model._set("B4", "=LAMBDA(x, LAMBDA(y, x+y))(1)(2)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("B1"), *"6");
+    assert_eq!(model._get_text("B2"), *"10");
+    assert_eq!(model._get_text("B3"), *"42");
+    assert_eq!(model._get_text("B4"), *"3");
+    assert_eq!(model._get_formula("B1"), *"=LAMBDA(x,x*2)(A1)");
+
+    // Cells calling a LAMBDA are recomputed when the values change
+    model._set("A1", "5");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), *"10");
+}
+
+#[test]
+fn fn_lambda_errors() {
+    let mut model = new_empty_model();
+    // A function is not a value
+    model._set("A1", "=LAMBDA(x, x*2)");
+    model._set("A2", "=A1+1");
+    model._set("A3", "=LAMBDA(x, x)+1");
+    // Wrong number of arguments
+    model._set("A4", "=LAMBDA(x, x*2)(1, 2)");
+    model._set("A5", "=LAMBDA(x, x*2)()");
+    // Parameters must be names
+    model._set("A6", "=LAMBDA(1, 2)");
+    model._set("A7", "=LAMBDA()");
+    model._set("A8", "=LAMBDA(x, x, x)(1, 2)");
+    // Only functions can be called
+    model._set("A9", "=LET(f, 2, f(1))");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), *"#CALC!");
+    assert_eq!(model._get_text("A2"), *"#CALC!");
+    assert_eq!(model._get_text("A3"), *"#CALC!");
+    assert_eq!(model._get_text("A4"), *"#VALUE!");
+    assert_eq!(model._get_text("A5"), *"#VALUE!");
+    assert_eq!(model._get_text("A6"), *"#ERROR!");
+    assert_eq!(model._get_text("A7"), *"#ERROR!");
+    assert_eq!(model._get_text("A8"), *"#VALUE!");
+    assert_eq!(model._get_text("A9"), *"#VALUE!");
+}
+
+#[test]
+fn fn_lambda_defined_names() {
+    let mut model = new_empty_model();
+    model
+        .new_defined_name("Double", None, "=LAMBDA(x, x*2)")
+        .unwrap();
+    model
+        .new_defined_name("Hypotenuse", None, "=LAMBDA(a, b, SQRT(a^2+b^2))")
+        .unwrap();
+    model._set("A1", "4");
+    model._set("B1", "=Double(A1)");
+    model._set("B2", "=Hypotenuse(3, A1)");
+    model._set("B3", "=Double(Double(A1))");
+    model._set("B4", "=Double");
+    model._set("B5", "=Unknown(A1)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("B1"), *"8");
+    assert_eq!(model._get_text("B2"), *"5");
+    assert_eq!(model._get_text("B3"), *"16");
+    assert_eq!(model._get_text("B4"), *"#CALC!");
+    assert_eq!(model._get_text("B5"), *"#ERROR!");
+
+    model._set("A1", "12");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), *"24");
+    assert_eq!(model._get_text("B2"), *"12.369316877");
+
+    // Cells calling the function are updated when it is renamed or changed
+    model
+        .update_defined_name("Double", None, "Twice", None, "=LAMBDA(x, x*3)")
+        .unwrap();
+    assert_eq!(model._get_formula("B1"), *"=Twice(A1)");
+    assert_eq!(model._get_text("B1"), *"36");
+}
+
+#[test]
+fn fn_lambda_body_references() {
+    let mut model = new_empty_model();
+    model
+        .new_defined_name("AddRate", None, "=LAMBDA(x, x+Sheet1!$C$1)")
+        .unwrap();
+    model._set("C1", "1");
+    model._set("A1", "=AddRate(10)");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), *"11");
+
+    // The cell reads the cells the body of the function reads
+    model._set("C1", "5");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), *"15");
+}
+
+#[test]
+fn fn_lambda_recursion() {
+    let mut model = new_empty_model();
+    model
+        .new_defined_name("Fact", None, "=LAMBDA(n, IF(n<=1, 1, n*Fact(n-1)))")
+        .unwrap();
+    model
+        .new_defined_name("Forever", None, "=LAMBDA(n, Forever(n+1))")
+        .unwrap();
+    model._set("A1", "=Fact(5)");
+    model._set("A2", "=Fact(20)");
+    model._set("A3", "=Forever(1)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), *"120");
+    assert_eq!(model._get_text("A2"), *"2.4329E+18");
+    assert_eq!(model._get_text("A3"), *"#NUM!");
+}
+
+#[test]
+fn fn_lambda_let() {
+    let mut model = new_empty_model();
+    model._set("A1", "=LET(f, LAMBDA(x, x+1), f(1)+f(2))");
+    // Functions see the names bound when they were created
+    model._set(
+        "A2",
+        "=LET(a, 10, add, LAMBDA(x, x+a), LET(a, 100, add(1)))",
+    );
+    // but not the names of the caller
+    model._set("A3", "=LET(add, LAMBDA(x, x+b), LET(b, 1, add(1)))");
+    // Parameters shadow the names
+    model._set("A4", "=LET(x, 5, LAMBDA(x, x*2)(1)+x)");
+    model._set("A5", "=TYPE(LAMBDA(x, x))");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), *"5");
+    assert_eq!(model._get_text("A2"), *"11");
+    assert_eq!(model._get_text("A3"), *"#NAME?");
+    assert_eq!(model._get_text("A4"), *"7");
+    assert_eq!(model._get_text("A5"), *"128");
+}
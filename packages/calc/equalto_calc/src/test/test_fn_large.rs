@@ -0,0 +1,48 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::test::util::new_empty_model;
+
+#[test]
+fn test_fn_large_arguments() {
+    let mut model = new_empty_model();
+    model._set("A1", "=LARGE(B1:B3)");
+    model._set("A2", "=SMALL()");
+    model._set("A3", "=RANK(1)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), *"#ERROR!");
+    assert_eq!(model._get_text("A2"), *"#ERROR!");
+    assert_eq!(model._get_text("A3"), *"#ERROR!");
+}
+
+#[test]
+fn test_fn_large_minimal() {
+    let mut model = new_empty_model();
+    model._set("B1", "4");
+    model._set("B2", "1");
+    model._set("B3", "'7");
+    model._set("B4", "9");
+    // B5 is empty
+    model._set("B6", "4");
+    model._set("A1", "=LARGE(B1:B6,1)");
+    model._set("A2", "=LARGE(B1:B6,2.1)");
+    model._set("A3", "=SMALL(B1:B6,1)");
+    model._set("A4", "=SMALL(B1:B6,5)");
+    model._set("A5", "=LARGE({1,2,3},0)");
+    model._set("A6", "=RANK(B1,B1:B6)");
+    model._set("A7", "=RANK(B1,B1:B6,1)");
+    model._set("A8", "=RANK(3,B1:B6)");
+    model._set("A9", "=RANK(1,{1,2})");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), *"9");
+    assert_eq!(model._get_text("A2"), *"4");
+    assert_eq!(model._get_text("A3"), *"1");
+    assert_eq!(model._get_text("A4"), *"#NUM!");
+    assert_eq!(model._get_text("A5"), *"#NUM!");
+    // Ties share the best position
+    assert_eq!(model._get_text("A6"), *"2");
+    assert_eq!(model._get_text("A7"), *"2");
+    assert_eq!(model._get_text("A8"), *"#N/A");
+    assert_eq!(model._get_text("A9"), *"#VALUE!");
+}
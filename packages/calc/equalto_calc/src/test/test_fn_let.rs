@@ -0,0 +1,87 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::test::util::new_empty_model;
+
+#[test]
+fn fn_let_arguments() {
+    let mut model = new_empty_model();
+    model._set("A1", "=LET(x, 1)");
+    model._set("A2", "=LET(x, 1, y, 2)");
+    model._set("A3", "=LET(1, 1, 2)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), *"#ERROR!");
+    assert_eq!(model._get_text("A2"), *"#ERROR!");
+    assert_eq!(model._get_text("A3"), *"#ERROR!");
+}
+
+#[test]
+fn fn_let_basic() {
+    let mut model = new_empty_model();
+    model._set("A1", "3");
+    model._set("A2", "4");
+    model._set("B1", "=LET(x, A1, y, A2, SQRT(x*x+y*y))");
+    model._set("B2", "=LET(x, A1, y, x*10, x+y)");
+    model._set("B3", "=LET(total, SUM(A1:A2), Total/2)");
+    model._set("B4", "=LET(data, A1:A2, MAX(data)+COUNT(data))");
+    model.evaluate();
+
+    assert_eq!(model._get_text("B1"), *"5");
+    assert_eq!(model._get_text("B2"), *"33");
+    assert_eq!(model._get_text("B3"), *"3.5");
+    assert_eq!(model._get_text("B4"), *"6");
+    assert_eq!(model._get_formula("B1"), *"=LET(x,A1,y,A2,SQRT(x*x+y*y))");
+
+    // Cells using LET are recomputed when the values change
+    model._set("A1", "6");
+    model._set("A2", "8");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), *"10");
+}
+
+#[test]
+fn fn_let_scopes() {
+    let mut model = new_empty_model();
+    model.new_defined_name("x", None, "100").unwrap();
+    // Inner names shadow outer names and defined names
+    model._set("A1", "=LET(x, 1, LET(x, 2, x)+x)");
+    // The names are only visible inside the LET
+    model._set("A2", "=LET(x, 1, x)+x");
+    // Defined names do not see the names of the calling formula
+    model.new_defined_name("Double", None, "x*2").unwrap();
+    model._set("A3", "=LET(x, 1, Double)");
+    // Unknown names
+    model._set("A4", "=LET(x, 1, y)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), *"3");
+    assert_eq!(model._get_text("A2"), *"101");
+    assert_eq!(model._get_text("A3"), *"200");
+    assert_eq!(model._get_text("A4"), *"#NAME?");
+}
+
+#[test]
+fn fn_let_single_evaluation() {
+    let mut model = new_empty_model();
+    // A random value evaluated once is equal to itself
+    model._set("A1", "=LET(rnd, RAND(), rnd=rnd)");
+    // Errors are only returned if the name is used
+    model._set("A2", "=LET(e, 1/0, 5)");
+    model._set("A3", "=LET(e, 1/0, e+5)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), *"TRUE");
+    assert_eq!(model._get_text("A2"), *"5");
+    assert_eq!(model._get_text("A3"), *"#DIV/0!");
+}
+
+#[test]
+fn fn_let_arrays() {
+    let mut model = new_empty_model();
+    model._set("A1", "=LET(s, SEQUENCE(3), s*s)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), *"1");
+    assert_eq!(model._get_text("A2"), *"4");
+    assert_eq!(model._get_text("A3"), *"9");
+}
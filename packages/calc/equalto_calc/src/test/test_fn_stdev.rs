@@ -0,0 +1,71 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::test::util::new_empty_model;
+
+#[test]
+fn test_fn_stdev_arguments() {
+    let mut model = new_empty_model();
+    model._set("A1", "=STDEV()");
+    model._set("A2", "=VARP()");
+    model._set("A3", "=MEDIAN()");
+    model._set("A4", "=MAXA()");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), *"#ERROR!");
+    assert_eq!(model._get_text("A2"), *"#ERROR!");
+    assert_eq!(model._get_text("A3"), *"#ERROR!");
+    assert_eq!(model._get_text("A4"), *"#ERROR!");
+}
+
+#[test]
+fn test_fn_stdev_minimal() {
+    let mut model = new_empty_model();
+    model._set("B1", "2");
+    model._set("B2", "4");
+    model._set("B3", "4");
+    model._set("B4", "'100");
+    // B5 is empty
+    model._set("B6", "true");
+    model._set("B7", "6");
+    model._set("A1", "=VAR(B1:B7)");
+    model._set("A2", "=VARP(B1:B7)");
+    model._set("A3", "=STDEV(B1:B7)");
+    model._set("A4", "=STDEVP(B1:B7)");
+    model._set("A5", "=STDEV(B1)");
+    model._set("A6", "=VAR(B1:B7,\"4\",TRUE)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), *"2.666666667");
+    assert_eq!(model._get_text("A2"), *"2");
+    assert_eq!(model._get_text("A3"), *"1.632993162");
+    assert_eq!(model._get_text("A4"), *"1.414213562");
+    assert_eq!(model._get_text("A5"), *"#DIV/0!");
+    // Values in the arguments are numbers
+    assert_eq!(model._get_text("A6"), *"3.1");
+}
+
+#[test]
+fn test_fn_median_minimal() {
+    let mut model = new_empty_model();
+    model._set("B1", "5");
+    model._set("B2", "1");
+    model._set("B3", "hello");
+    model._set("B4", "true");
+    model._set("B5", "3");
+    model._set("A1", "=MEDIAN(B1:B5)");
+    model._set("A2", "=MEDIAN(B1:B5,10)");
+    model._set("A3", "=MEDIAN(B3:B4)");
+    model._set("A4", "=MAXA(B3:B4)");
+    model._set("A5", "=MINA(B1:B5)");
+    model._set("A6", "=MAXA(B1:B5)");
+    model._set("A7", "=MAXA(C1:C5)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), *"3");
+    assert_eq!(model._get_text("A2"), *"4");
+    assert_eq!(model._get_text("A3"), *"#NUM!");
+    assert_eq!(model._get_text("A4"), *"1");
+    assert_eq!(model._get_text("A5"), *"0");
+    assert_eq!(model._get_text("A6"), *"5");
+    assert_eq!(model._get_text("A7"), *"0");
+}
@@ -0,0 +1,41 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::expressions::parser::format::FormatOptions;
+use crate::test::util::new_empty_model;
+
+#[test]
+fn test_format_formula() {
+    let mut model = new_empty_model();
+    let options = FormatOptions {
+        indent: 2,
+        width: 20,
+    };
+    let formula = "=IF(A1>10,\"High\",IF(A1>5,\"Medium\",\"Low\"))";
+    let formatted = model
+        .format_formula(0, 3, 2, formula, Some(&options))
+        .unwrap();
+    assert_eq!(
+        formatted,
+        "=IF(\n  A1>10,\n  \"High\",\n  IF(\n    A1>5,\n    \"Medium\",\n    \"Low\"\n  )\n)"
+    );
+
+    // The cell keeps the formula
+    model._set("B3", &formatted);
+    model._set("A1", "7");
+    model.evaluate();
+    assert_eq!(model._get_text("B3"), "Medium");
+
+    // Minified
+    assert_eq!(
+        model.format_formula(0, 3, 2, &formatted, None).unwrap(),
+        formula
+    );
+}
+
+#[test]
+fn test_format_formula_errors() {
+    let model = new_empty_model();
+    assert!(model.format_formula(0, 1, 1, "SUM(A1)", None).is_err());
+    assert!(model.format_formula(0, 1, 1, "=SUM(A1", None).is_err());
+    assert!(model.format_formula(3, 1, 1, "=SUM(A1)", None).is_err());
+}
@@ -0,0 +1,154 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::cell::CellValue;
+use crate::goal_seek::GoalSeekOptions;
+use crate::model::Model;
+use crate::test::util::{cell, new_empty_model};
+
+fn get_number(model: &Model, cell: &str) -> f64 {
+    match model.get_cell_value_by_ref(cell).unwrap() {
+        CellValue::Number(value) => value,
+        value => panic!("Expected a number, found {value:?}"),
+    }
+}
+
+#[test]
+fn test_linear() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("B1", "=3*A1+4");
+    model.evaluate();
+    let result = model
+        .goal_seek(cell(1, 2), 19.0, cell(1, 1), &GoalSeekOptions::default())
+        .unwrap();
+    assert!(result.converged);
+    assert!((result.solution - 5.0).abs() < 1e-3);
+    assert!((result.target_value - 19.0).abs() <= 0.001);
+    assert!(result.iterations <= 3);
+    assert_eq!(get_number(&model, "Sheet1!A1"), result.solution);
+    assert_eq!(get_number(&model, "Sheet1!B1"), result.target_value);
+    assert!(model.can_undo());
+}
+
+#[test]
+fn test_nonlinear() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("B1", "=A1^2");
+    model.evaluate();
+    let options = GoalSeekOptions {
+        tolerance: 1e-9,
+        ..Default::default()
+    };
+    let result = model
+        .goal_seek(cell(1, 2), 2.0, cell(1, 1), &options)
+        .unwrap();
+    assert!(result.converged);
+    assert!((result.solution - 2.0_f64.sqrt()).abs() < 1e-8);
+}
+
+#[test]
+fn test_loan_payment() {
+    let mut model = new_empty_model();
+    // The rate that gives a payment of 200 for a loan of 10000 in 60 months
+    model._set("A1", "0.01");
+    model._set("B1", "=-PMT(A1,60,10000)");
+    model.evaluate();
+    let options = GoalSeekOptions {
+        tolerance: 1e-7,
+        ..Default::default()
+    };
+    let result = model
+        .goal_seek(cell(1, 2), 200.0, cell(1, 1), &options)
+        .unwrap();
+    assert!(result.converged);
+    model._set("C1", "=RATE(60,-200,10000)");
+    model.evaluate();
+    assert!((get_number(&model, "Sheet1!C1") - result.solution).abs() < 1e-9);
+}
+
+#[test]
+fn test_do_not_apply() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("B1", "=A1*2");
+    model.evaluate();
+    model.set_events_enabled(true);
+    let options = GoalSeekOptions {
+        apply: false,
+        ..Default::default()
+    };
+    let result = model
+        .goal_seek(cell(1, 2), 10.0, cell(1, 1), &options)
+        .unwrap();
+    assert!(result.converged);
+    assert!((result.solution - 5.0).abs() < 1e-3);
+    assert_eq!(model._get_text("A1"), "1");
+    assert_eq!(model._get_text("B1"), "2");
+    model.evaluate();
+    assert!(model.take_events().is_empty());
+}
+
+#[test]
+fn test_empty_changing_cell() {
+    let mut model = new_empty_model();
+    model._set("B1", "=A1+10");
+    model.evaluate();
+    let result = model
+        .goal_seek(cell(1, 2), 12.0, cell(1, 1), &GoalSeekOptions::default())
+        .unwrap();
+    assert!(result.converged);
+    assert_eq!(model._get_text("A1"), "2");
+}
+
+#[test]
+fn test_no_solution() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("B1", "=A1^2+1");
+    model.evaluate();
+    let options = GoalSeekOptions {
+        max_iterations: 30,
+        ..Default::default()
+    };
+    let result = model
+        .goal_seek(cell(1, 2), 0.0, cell(1, 1), &options)
+        .unwrap();
+    assert!(!result.converged);
+    assert_eq!(result.iterations, 30);
+    // The model is unchanged
+    assert_eq!(model._get_text("A1"), "1");
+    assert_eq!(model._get_text("B1"), "2");
+}
+
+#[test]
+fn test_errors() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "=A1");
+    model._set("B1", "5");
+    model._set("B2", "=\"text\"&A1");
+    model.evaluate();
+    let options = GoalSeekOptions::default();
+    assert_eq!(
+        model.goal_seek(cell(1, 2), 1.0, cell(1, 1), &options),
+        Err("The target cell must contain a formula".to_string())
+    );
+    assert_eq!(
+        model.goal_seek(cell(2, 1), 1.0, cell(2, 1), &options),
+        Err("The changing cell must contain a value".to_string())
+    );
+    assert_eq!(
+        model.goal_seek(cell(2, 2), 1.0, cell(1, 1), &options),
+        Err("The target cell does not evaluate to a number".to_string())
+    );
+    let options = GoalSeekOptions {
+        max_iterations: 0,
+        ..Default::default()
+    };
+    assert_eq!(
+        model.goal_seek(cell(2, 1), 1.0, cell(1, 1), &options),
+        Err("The maximum number of iterations must be at least 1".to_string())
+    );
+    assert_eq!(model._get_text("A1"), "1");
+}
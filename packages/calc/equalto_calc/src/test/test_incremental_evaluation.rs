@@ -0,0 +1,285 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::model::Model;
+use crate::test::util::new_empty_model;
+use crate::types::{Cell, DefinedName};
+
+// Overwrites the cached value of a formula cell without touching the formula.
+// If the cell is not recomputed by the next evaluation the stale value is kept.
+fn set_cached_value(model: &mut Model, row: i32, column: i32, v: f64) {
+    let worksheet = model.workbook.worksheet_mut(0).unwrap();
+    let cell = worksheet
+        .sheet_data
+        .get_mut(&row)
+        .unwrap()
+        .get_mut(&column)
+        .unwrap();
+    if let Cell::CellFormulaNumber { f, s, .. } = *cell {
+        *cell = Cell::CellFormulaNumber { f, v, s };
+    } else {
+        panic!("Expected a numeric formula cell");
+    }
+}
+
+#[test]
+fn test_chain_is_updated() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "=A1*2");
+    model._set("A3", "=A2+1");
+    model.evaluate();
+    assert_eq!(model._get_text("A3"), "3");
+
+    model._set("A1", "5");
+    model.evaluate();
+    assert_eq!(model._get_text("A2"), "10");
+    assert_eq!(model._get_text("A3"), "11");
+}
+
+#[test]
+fn test_unrelated_cells_are_not_recomputed() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "=A1+1");
+    model._set("B1", "1");
+    model._set("B2", "=B1+1");
+    model.evaluate();
+
+    // A2 (row 2, column 1) does not depend on B1
+    set_cached_value(&mut model, 2, 1, 100.0);
+    model._set("B1", "7");
+    model.evaluate();
+    assert_eq!(model._get_text("A2"), "100");
+    assert_eq!(model._get_text("B2"), "8");
+
+    // But it is recomputed when A1 changes
+    model._set("A1", "3");
+    model.evaluate();
+    assert_eq!(model._get_text("A2"), "4");
+}
+
+#[test]
+fn test_range_dependents() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model._set("B1", "=SUM(A1:A10)");
+    model._set("C1", "=SUM(A:A)");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "3");
+    assert_eq!(model._get_text("C1"), "3");
+
+    // A new cell inside the range
+    model._set("A7", "10");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "13");
+    assert_eq!(model._get_text("C1"), "13");
+
+    // A new cell only in the full column range
+    model._set("A20", "100");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "13");
+    assert_eq!(model._get_text("C1"), "113");
+}
+
+#[test]
+fn test_ranges_across_bands() {
+    let mut model = new_empty_model();
+    // Ranges of different sizes that straddle the boundaries of the bands they are kept in
+    model._set("Z1", "=SUM(C7:C9)");
+    model._set("Z2", "=SUM(B3:E17)");
+    model._set("Z3", "=SUM(A60:H70)");
+    model._set("Z4", "=SUM(C:D)");
+    model._set("Z5", "=SUM(8:8)");
+    model._set("Z6", "=SUM(C8:C9)+SUM(C8:C9)");
+    model.evaluate();
+
+    model._set("C8", "1");
+    model.evaluate();
+    assert_eq!(model._get_text("Z1"), "1");
+    assert_eq!(model._get_text("Z2"), "1");
+    assert_eq!(model._get_text("Z3"), "0");
+    assert_eq!(model._get_text("Z4"), "1");
+    assert_eq!(model._get_text("Z5"), "1");
+    assert_eq!(model._get_text("Z6"), "2");
+
+    model._set("H64", "5");
+    model.evaluate();
+    assert_eq!(model._get_text("Z2"), "1");
+    assert_eq!(model._get_text("Z3"), "5");
+    assert_eq!(model._get_text("Z4"), "1");
+
+    // Z6 does not read C8 anymore, Z1 now reads a range in another band
+    model._set("Z6", "=SUM(C9)");
+    model._set("Z1", "=SUM(D1000:D1100)");
+    model.evaluate();
+    set_cached_value(&mut model, 6, 26, 0.0);
+    model._set("C8", "2");
+    model._set("D1064", "7");
+    model.evaluate();
+    assert_eq!(model._get_text("Z1"), "7");
+    assert_eq!(model._get_text("Z4"), "9");
+    assert_eq!(model._get_text("Z6"), "0");
+}
+
+#[test]
+fn test_formula_precedents_change() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("B1", "2");
+    model._set("C1", "=A1");
+    model.evaluate();
+    assert_eq!(model._get_text("C1"), "1");
+
+    model._set("C1", "=B1*10");
+    model.evaluate();
+    assert_eq!(model._get_text("C1"), "20");
+
+    // C1 does not depend on A1 anymore
+    set_cached_value(&mut model, 1, 3, 0.0);
+    model._set("A1", "3");
+    model.evaluate();
+    assert_eq!(model._get_text("C1"), "0");
+
+    model._set("B1", "3");
+    model.evaluate();
+    assert_eq!(model._get_text("C1"), "30");
+}
+
+#[test]
+fn test_delete_and_empty_cells() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model._set("B1", "=A1+A2");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "3");
+
+    model.delete_cell(0, 1, 1).unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "2");
+
+    model.set_cell_empty(0, 2, 1).unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "0");
+}
+
+#[test]
+fn test_other_sheets_and_defined_names() {
+    let mut model = new_empty_model();
+    model.new_sheet();
+    model.workbook.defined_names.push(DefinedName {
+        name: "myName".to_string(),
+        formula: "Sheet2!$A$1".to_string(),
+        sheet_id: None,
+        hidden: false,
+    });
+    model.parse_defined_names();
+    model.build_dependency_graph();
+    model._set("Sheet2!A1", "2");
+    model._set("A1", "=Sheet2!A1*3");
+    model._set("A2", "=myName+1");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "6");
+    assert_eq!(model._get_text("A2"), "3");
+
+    model._set("Sheet2!A1", "4");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "12");
+    assert_eq!(model._get_text("A2"), "5");
+}
+
+#[test]
+fn test_volatile_and_dynamic_references() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "A1");
+    model._set("B1", "=INDIRECT(A2)");
+    model._set("B2", "=SUM(OFFSET(A1,0,0,3,1))");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "1");
+    assert_eq!(model._get_text("B2"), "1");
+
+    // Neither INDIRECT nor OFFSET reference A1 or A3 statically
+    model._set("A1", "5");
+    model._set("A3", "2");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "5");
+    assert_eq!(model._get_text("B2"), "7");
+}
+
+#[test]
+fn test_circular_references() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("B1", "=A1+1");
+    model._set("C1", "=B1+1");
+    model.evaluate();
+    assert_eq!(model._get_text("C1"), "3");
+
+    model._set("A1", "=C1");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "#CIRC!");
+    assert_eq!(model._get_text("B1"), "#CIRC!");
+    assert_eq!(model._get_text("C1"), "#CIRC!");
+
+    model._set("A1", "10");
+    model.evaluate();
+    assert_eq!(model._get_text("B1"), "11");
+    assert_eq!(model._get_text("C1"), "12");
+}
+
+#[test]
+fn test_insert_and_delete_rows() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model._set("A3", "=SUM(A1:A2)");
+    model._set("B1", "=A2*2");
+    model.evaluate();
+    assert_eq!(model._get_text("A3"), "3");
+
+    model.insert_rows(0, 2, 1).unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("A4"), "=SUM(A1:A3)");
+    assert_eq!(model._get_text("A4"), "3");
+    assert_eq!(model._get_formula("B1"), "=A3*2");
+
+    model._set("A2", "10");
+    model._set("A3", "5");
+    model.evaluate();
+    assert_eq!(model._get_text("A4"), "16");
+    assert_eq!(model._get_text("B1"), "10");
+
+    model.delete_rows(0, 2, 1).unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("A3"), "=SUM(A1:A2)");
+    assert_eq!(model._get_text("A3"), "6");
+    assert_eq!(model._get_formula("B1"), "=A2*2");
+
+    model._set("A1", "4");
+    model._set("A2", "1");
+    model.evaluate();
+    assert_eq!(model._get_text("A3"), "5");
+    assert_eq!(model._get_text("B1"), "2");
+}
+
+#[test]
+fn test_sheet_operations() {
+    let mut model = new_empty_model();
+    model.new_sheet();
+    model._set("Sheet2!A1", "3");
+    model._set("A1", "=Sheet2!A1");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "3");
+
+    model.insert_sheet("First", 0, None).unwrap();
+    model._set("Sheet2!A1", "4");
+    model.evaluate();
+    assert_eq!(model._get_text("Sheet1!A1"), "4");
+
+    model.rename_sheet("Sheet2", "Other").unwrap();
+    model._set("Other!A1", "5");
+    model.evaluate();
+    assert_eq!(model._get_text("Sheet1!A1"), "5");
+}
@@ -0,0 +1,69 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::model::Model;
+use crate::test::util::new_empty_model;
+
+#[test]
+fn test_circular_reference_without_iterations() {
+    let mut model = new_empty_model();
+    model._set("A1", "=100+B1");
+    model._set("B1", "=A1*0.1");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "#CIRC!");
+}
+
+#[test]
+fn test_converging_circular_reference() {
+    let mut model = new_empty_model();
+    model.set_iterative_calculation(true, 100, 0.001).unwrap();
+    model._set("A1", "=100+B1");
+    model._set("B1", "=A1*0.1");
+    model._set("C1", "=ROUND(A1,2)");
+    model.evaluate();
+    assert_eq!(model._get_text("C1"), "111.11");
+    assert!(model.evaluate_with_error_check().is_ok());
+    assert_eq!(model._get_text("C1"), "111.11");
+
+    model._set("A1", "=200+B1");
+    model.evaluate();
+    assert_eq!(model._get_text("C1"), "222.22");
+}
+
+#[test]
+fn test_maximum_iterations() {
+    let mut model = new_empty_model();
+    model.set_iterative_calculation(true, 10, 0.001).unwrap();
+    model._set("A1", "=A1+1");
+    model._set("B1", "=A1*2");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "10");
+    assert_eq!(model._get_text("B1"), "20");
+
+    // Every evaluation starts from the previous values, as in Excel
+    model._set("C1", "=A1");
+    model.evaluate();
+    assert_eq!(model._get_text("C1"), "10");
+    model._set("A2", "3");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "10");
+}
+
+#[test]
+fn test_iterative_calculation_settings() {
+    let mut model = new_empty_model();
+    assert_eq!(
+        model.set_iterative_calculation(true, 0, 0.001),
+        Err("The maximum number of iterations must be at least 1".to_string())
+    );
+    assert_eq!(
+        model.set_iterative_calculation(true, 10, -1.0),
+        Err("The maximum change must be a non negative number".to_string())
+    );
+    model.set_iterative_calculation(true, 20, 0.01).unwrap();
+
+    let model = Model::from_json(&model.to_json_str()).unwrap();
+    let calculation = &model.workbook.settings.calculation;
+    assert!(calculation.iterative);
+    assert_eq!(calculation.max_iterations, 20);
+    assert_eq!(calculation.max_change, 0.01);
+}
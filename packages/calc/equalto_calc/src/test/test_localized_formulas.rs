@@ -0,0 +1,152 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::functions::Function;
+use crate::language::get_language;
+use crate::test::util::new_empty_model;
+
+#[test]
+fn test_function_names() {
+    let german = get_language("de").unwrap();
+    assert_eq!(german.get_function("summe"), Some(Function::Sum));
+    assert_eq!(german.get_function("LÄNGE"), Some(Function::Len));
+    assert_eq!(
+        german.get_function("Bereich.Verschieben"),
+        Some(Function::Offset)
+    );
+    assert_eq!(german.get_function_name(&Function::Vlookup), "SVERWEIS");
+    // Functions without a translation keep the English name
+    assert_eq!(german.get_function("LAMBDA"), Some(Function::Lambda));
+    assert_eq!(german.get_function_name(&Function::Lambda), "LAMBDA");
+    // The English names of translated functions are not recognized
+    assert_eq!(german.get_function("SUM"), None);
+
+    let french = get_language("fr").unwrap();
+    assert_eq!(french.get_function("TRIM"), Some(Function::Mirr));
+    assert_eq!(french.get_function("SUPPRESPACE"), Some(Function::Trim));
+
+    let english = get_language("en").unwrap();
+    assert_eq!(english.get_function("SUM"), Some(Function::Sum));
+    assert_eq!(english.get_function("SUMME"), None);
+}
+
+#[test]
+fn test_localize_formula() {
+    let model = new_empty_model();
+    let localize = |formula: &str, locale: &str, language: &str| {
+        model
+            .localize_formula(0, 1, 1, formula, locale, language)
+            .unwrap()
+    };
+    let formula = "=IF(SUM(B1:B3,0.5)>2,TRUE,#N/A)";
+    assert_eq!(
+        localize(formula, "de", "de"),
+        "=WENN(SUMME(B1:B3;0,5)>2;WAHR;#NV)"
+    );
+    assert_eq!(
+        localize(formula, "es", "es"),
+        "=SI(SUMA(B1:B3;0,5)>2;VERDADERO;#N/A)"
+    );
+    assert_eq!(
+        localize(formula, "fr", "fr"),
+        "=SI(SOMME(B1:B3;0,5)>2;VRAI;#N/A)"
+    );
+    // Only the names change with the 'en' locale
+    assert_eq!(
+        localize(formula, "en", "de"),
+        "=WENN(SUMME(B1:B3,0.5)>2,WAHR,#NV)"
+    );
+    assert_eq!(localize(formula, "en", "en"), formula);
+
+    assert_eq!(
+        localize("=SUM({1.5,2;3,4})", "de", "de"),
+        "=SUMME({1,5.2;3.4})"
+    );
+    // Functions without a translation keep the English name
+    assert_eq!(
+        localize("=LAMBDA(x,x+1)(2)", "de", "de"),
+        "=LAMBDA(x;x+1)(2)"
+    );
+}
+
+#[test]
+fn test_delocalize_formula() {
+    let model = new_empty_model();
+    let delocalize = |formula: &str, locale: &str, language: &str| {
+        model
+            .delocalize_formula(0, 1, 1, formula, locale, language)
+            .unwrap()
+    };
+    assert_eq!(
+        delocalize("=summe(B1:B3; 0,5; {1.2;3.4})", "de", "de"),
+        "=SUM(B1:B3,0.5,{1,2;3,4})"
+    );
+    assert_eq!(
+        delocalize("=SI(A1>0,5;\"sí\";AÑO(HOY()))", "es", "es"),
+        "=IF(A1>0.5,\"sí\",YEAR(TODAY()))"
+    );
+    assert_eq!(
+        delocalize("=SIERREUR(RECHERCHEV(A1;B1:C3;2;FAUX);\"\")", "fr", "fr"),
+        "=IFERROR(VLOOKUP(A1,B1:C3,2,FALSE),\"\")"
+    );
+    assert!(model
+        .delocalize_formula(0, 1, 1, "=SUMME(A1,A2)", "de", "de")
+        .is_err());
+    assert!(model
+        .delocalize_formula(0, 1, 1, "SUMME(A1)", "de", "de")
+        .is_err());
+    assert!(model
+        .delocalize_formula(0, 1, 1, "=SUMME(A1)", "xx", "de")
+        .is_err());
+    assert!(model
+        .delocalize_formula(0, 1, 1, "=SUMME(A1)", "de", "xx")
+        .is_err());
+}
+
+#[test]
+fn test_localized_round_trip() {
+    let mut model = new_empty_model();
+    model._set("A1", "2");
+    model._set("A2", "3");
+    let formulas = [
+        "=SUMIFS(A1:A2,A1:A2,\">1\")*1.5",
+        "=TEXTJOIN(\"-\",TRUE,A1:A2)",
+        "=IFERROR(1/0,ROUND(PI(),2))",
+        "=XLOOKUP(3,A1:A2,A1:A2,,0)",
+    ];
+    for formula in formulas {
+        for (locale, language) in [("de", "de"), ("es", "es"), ("fr", "fr"), ("en", "es")] {
+            let localized = model
+                .localize_formula(0, 3, 1, formula, locale, language)
+                .unwrap();
+            let delocalized = model
+                .delocalize_formula(0, 3, 1, &localized, locale, language)
+                .unwrap();
+            assert_eq!(delocalized, formula);
+        }
+    }
+
+    // Formulas are stored in English
+    let formula = model
+        .delocalize_formula(0, 3, 1, "=SUMME(A1:A2)*0,5", "de", "de")
+        .unwrap();
+    model._set("A3", &formula);
+    model.evaluate();
+    assert_eq!(model._get_formula("A3"), "=SUM(A1:A2)*0.5");
+    assert_eq!(model._get_text("A3"), "2.5");
+}
+
+#[test]
+fn test_locales_are_generated() {
+    // locales.json is generated from the list of locales, a locale added by hand would be lost
+    let mut generated: Vec<String> = serde_json::from_str::<
+        std::collections::HashMap<String, serde_json::Value>,
+    >(include_str!("../locale/locales.json"))
+    .unwrap()
+    .into_keys()
+    .collect();
+    let mut listed: Vec<String> =
+        serde_json::from_str(include_str!("../../../generate_locale/locales_list.json")).unwrap();
+    generated.sort();
+    listed.sort();
+    assert_eq!(generated, listed);
+}
@@ -0,0 +1,32 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::test::util::new_empty_model;
+
+const CHAIN_LENGTH: i32 = 20_000;
+
+// Cells are evaluated top to bottom, each formula depends on the one below it
+#[test]
+fn test_long_chain_of_formulas() {
+    let mut model = new_empty_model();
+    model.set_user_input(0, CHAIN_LENGTH, 1, "1".to_string());
+    for row in 1..CHAIN_LENGTH {
+        model.set_user_input(0, row, 1, format!("=A{}+1", row + 1));
+    }
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), CHAIN_LENGTH.to_string());
+
+    model._set(&format!("A{CHAIN_LENGTH}"), "10");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), (CHAIN_LENGTH + 9).to_string());
+}
+
+#[test]
+fn test_long_chain_of_ranges() {
+    let mut model = new_empty_model();
+    model.set_user_input(0, CHAIN_LENGTH, 1, "1".to_string());
+    for row in 1..CHAIN_LENGTH {
+        model.set_user_input(0, row, 1, format!("=SUM(A{}:A{})", row + 1, row + 1));
+    }
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "1");
+}
@@ -0,0 +1,154 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::model::Model;
+use crate::test::util::new_empty_model;
+
+// Three sheets that do not reference each other and a fourth one that reads two of them
+fn new_model_with_independent_sheets() -> Model {
+    let mut model = new_empty_model();
+    model.add_sheet("Sheet2").unwrap();
+    model.add_sheet("Sheet3").unwrap();
+    model.add_sheet("Summary").unwrap();
+    for sheet in ["Sheet1", "Sheet2", "Sheet3"] {
+        model._set(&format!("{sheet}!A1"), "1");
+        for row in 2..=50 {
+            model._set(
+                &format!("{sheet}!A{row}"),
+                &format!("=A{}*2+ROW()", row - 1),
+            );
+        }
+        model._set(&format!("{sheet}!B1"), "=SUM(A1:A10)");
+        model._set(&format!("{sheet}!C1"), "=SEQUENCE(3)*B1");
+        model._set(&format!("{sheet}!D1"), "=SUM(C1#)");
+        model._set(&format!("{sheet}!E1"), "=1/0");
+    }
+    model._set("Sheet2!F1", "=F1+1");
+    model._set("Summary!A1", "=Sheet1!D1+Sheet3!D1");
+    model._set("Summary!A2", "=Sheet3!C3");
+    model
+}
+
+#[test]
+fn test_same_results_as_sequential_evaluation() {
+    let mut model = new_model_with_independent_sheets();
+    model.evaluate();
+
+    let mut parallel_model = new_model_with_independent_sheets();
+    parallel_model.set_evaluation_threads(4);
+    parallel_model.evaluate();
+    assert_eq!(parallel_model.workbook, model.workbook);
+    assert_eq!(
+        parallel_model._get_text("Sheet2!C3"),
+        model._get_text("Sheet2!C3")
+    );
+    assert_eq!(parallel_model._get_text("Sheet2!F1"), "#CIRC!");
+
+    model._set("Sheet2!A1", "5");
+    model._set("Sheet3!A1", "7");
+    model.evaluate();
+    parallel_model._set("Sheet2!A1", "5");
+    parallel_model._set("Sheet3!A1", "7");
+    parallel_model.evaluate();
+    assert_eq!(parallel_model.workbook, model.workbook);
+}
+
+#[test]
+fn test_volatile_formulas() {
+    let mut model = new_model_with_independent_sheets();
+    model.set_evaluation_threads(4);
+    model._set("Sheet1!G1", "=RAND()");
+    model._set("Sheet1!G2", "=G1*2");
+    model._set("Sheet3!G1", "=INDIRECT(\"A2\")");
+    model.evaluate();
+    let random = model._get_text("Sheet1!G1").parse::<f64>().unwrap();
+    let double = model._get_text("Sheet1!G2").parse::<f64>().unwrap();
+    // Values are displayed rounded to nine decimals
+    assert!((double - 2.0 * random).abs() < 1e-8);
+    assert_eq!(model._get_text("Sheet3!G1"), model._get_text("Sheet3!A2"));
+}
+
+// Each sheet spills an array whose size depends on a value, reads strings and whole columns
+fn new_model_with_spills() -> Model {
+    let mut model = new_empty_model();
+    model.add_sheet("Sheet2").unwrap();
+    model.add_sheet("Sheet3").unwrap();
+    model
+        .new_defined_name("Factor", None, "=Sheet1!$H$1")
+        .unwrap();
+    model._set("Sheet1!H1", "10");
+    for sheet in ["Sheet1", "Sheet2", "Sheet3"] {
+        model._set(&format!("{sheet}!A1"), "4");
+        model._set(&format!("{sheet}!B1"), "=SEQUENCE(A1)*Factor");
+        model._set(&format!("{sheet}!C1"), "=SUM(B:B)");
+        model._set(&format!("{sheet}!D1"), "Total");
+        model._set(&format!("{sheet}!E1"), "=D1&\": \"&C1");
+    }
+    model
+}
+
+#[test]
+fn test_spills_strings_and_names() {
+    let mut model = new_model_with_spills();
+    model.evaluate();
+
+    let mut parallel_model = new_model_with_spills();
+    parallel_model.set_evaluation_threads(3);
+    parallel_model.evaluate();
+    assert_eq!(parallel_model.workbook, model.workbook);
+    assert_eq!(parallel_model._get_text("Sheet2!E1"), "Total: 100");
+
+    // The spilled arrays shrink: the cells they no longer cover are removed
+    for sheet in ["Sheet1", "Sheet2", "Sheet3"] {
+        model._set(&format!("{sheet}!A1"), "2");
+        parallel_model._set(&format!("{sheet}!A1"), "2");
+    }
+    model.evaluate();
+    parallel_model.evaluate();
+    assert_eq!(parallel_model.workbook, model.workbook);
+    assert_eq!(parallel_model._get_text("Sheet3!B4"), "");
+    assert_eq!(parallel_model._get_text("Sheet3!E1"), "Total: 30");
+
+    // The history of changes is kept
+    parallel_model.undo().unwrap();
+    parallel_model.evaluate();
+    assert_eq!(parallel_model._get_text("Sheet3!E1"), "Total: 100");
+}
+
+// Each sheet reads global and local defined names
+fn new_model_with_defined_names() -> Model {
+    let mut model = new_empty_model();
+    model.add_sheet("Sheet2").unwrap();
+    model.add_sheet("Sheet3").unwrap();
+    model
+        .new_defined_name("Rate", None, "=Sheet2!$A$1")
+        .unwrap();
+    model
+        .new_defined_name("Values", None, "=Sheet3!$A$1:$A$3")
+        .unwrap();
+    model.new_defined_name("Rate", Some(2), "=0.5").unwrap();
+    for sheet in ["Sheet1", "Sheet2", "Sheet3"] {
+        model._set(&format!("{sheet}!A1"), &format!("{}", sheet.len()));
+        model._set(&format!("{sheet}!A2"), "=A1*2");
+        model._set(&format!("{sheet}!A3"), "=A2+1");
+        model._set(&format!("{sheet}!B1"), "=SHEET(Rate)");
+        model._set(&format!("{sheet}!B2"), "=SHEET(Values)");
+        model._set(&format!("{sheet}!B3"), "=Rate*SUM(Values)");
+        model._set(&format!("{sheet}!B4"), "=ISREF(Values)");
+    }
+    model
+}
+
+#[test]
+fn test_defined_names() {
+    let mut model = new_model_with_defined_names();
+    model.evaluate();
+
+    let mut parallel_model = new_model_with_defined_names();
+    parallel_model.set_evaluation_threads(4);
+    parallel_model.evaluate();
+    assert_eq!(parallel_model.workbook, model.workbook);
+    assert_eq!(parallel_model._get_text("Sheet1!B1"), "2");
+    assert_eq!(parallel_model._get_text("Sheet1!B2"), "3");
+    assert_eq!(parallel_model._get_text("Sheet1!B3"), "186");
+    assert_eq!(parallel_model._get_text("Sheet3!B3"), "15.5");
+}
@@ -0,0 +1,187 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::model::Model;
+use crate::test::util::new_empty_model;
+
+/// The numbers 1 to 25 in A1:E5, by rows
+fn grid_model() -> Model {
+    let mut model = new_empty_model();
+    for row in 1..=5 {
+        for (index, column) in ["A", "B", "C", "D", "E"].iter().enumerate() {
+            let value = (row - 1) * 5 + index as i32 + 1;
+            model._set(&format!("{}{}", column, row), &value.to_string());
+        }
+    }
+    model
+}
+
+#[test]
+fn test_intersection() {
+    let mut model = grid_model();
+    model._set("G1", "=SUM(B2:D5 C1:C10)");
+    model._set("G2", "=B2:D2 C1:C10");
+    model._set("G3", "=A1:B2 D4:E5");
+    model._set("G4", "=SUM(A1:B2  B2:C3)");
+    model._set("G5", "=SUM(A1:E5 B:B 3:3)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("G1"), "62");
+    // A single cell intersection is its value
+    assert_eq!(model._get_text("G2"), "8");
+    assert_eq!(model._get_text("G3"), "#NULL!");
+    assert_eq!(model._get_text("G4"), "7");
+    assert_eq!(model._get_text("G5"), "12");
+
+    assert_eq!(model._get_formula("G1"), "=SUM(B2:D5 C1:C10)");
+    assert_eq!(model._get_formula("G4"), "=SUM(A1:B2 B2:C3)");
+
+    // The formula depends on the cells in the intersection
+    model._set("C3", "100");
+    model.evaluate();
+    assert_eq!(model._get_text("G1"), "149");
+}
+
+#[test]
+fn test_union() {
+    let mut model = grid_model();
+    model.new_sheet();
+    model._set("G1", "=SUM((A1:A3,C1:C3))");
+    model._set("G2", "=COUNT((A1,B1:B2),E5)");
+    model._set("G3", "=MAX((A1:A2,E5))");
+    model._set("G4", "=(A1,B1)");
+    model._set("G5", "=SUM((A1,Sheet2!A1))");
+    model._set("G6", "=SUM((A1:C3,B2:D4) D1:D5)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("G1"), "42");
+    assert_eq!(model._get_text("G2"), "4");
+    assert_eq!(model._get_text("G3"), "25");
+    assert_eq!(model._get_text("G4"), "#VALUE!");
+    assert_eq!(model._get_text("G5"), "#VALUE!");
+    // Only the second area meets column D
+    assert_eq!(model._get_text("G6"), "42");
+
+    assert_eq!(model._get_formula("G1"), "=SUM((A1:A3,C1:C3))");
+    assert_eq!(model._get_formula("G2"), "=COUNT((A1,B1:B2),E5)");
+
+    model._set("C2", "0");
+    model.evaluate();
+    assert_eq!(model._get_text("G1"), "34");
+}
+
+#[test]
+fn test_union_in_functions() {
+    let mut model = new_empty_model();
+    for (row, (a, c)) in [(1, 10), (2, 20), (3, 30)].iter().enumerate() {
+        model._set(&format!("A{}", row + 1), &a.to_string());
+        model._set(&format!("C{}", row + 1), &c.to_string());
+    }
+    model._set("E1", "=SUBTOTAL(9,(A1:A3,C1:C3))");
+    model._set("E2", "=LARGE((A1:A3,C1:C3),2)");
+    model._set("E3", "=SMALL((A1:A3,C1:C3),2)");
+    model._set("E4", "=MEDIAN((A1:A3,C1:C3))");
+    model._set("E5", "=ROUND(STDEV((A1:A3,C1:C3)),2)");
+    model._set("E6", "=RANK(C1,(A1:A3,C1:C3))");
+    model._set("E7", "=MAXA((A1:A3,C1:C3))");
+    model._set("E8", "=ISREF((A1,B1))");
+    model._set("E9", "=ISREF(A1:B2 B1:B3)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("E1"), "66");
+    assert_eq!(model._get_text("E2"), "20");
+    assert_eq!(model._get_text("E3"), "2");
+    assert_eq!(model._get_text("E4"), "6.5");
+    assert_eq!(model._get_text("E5"), "11.73");
+    assert_eq!(model._get_text("E6"), "3");
+    assert_eq!(model._get_text("E7"), "30");
+    assert_eq!(model._get_text("E8"), "TRUE");
+    assert_eq!(model._get_text("E9"), "TRUE");
+}
+
+#[test]
+fn test_defined_names() {
+    let mut model = grid_model();
+    model
+        .new_defined_name(
+            "Corners",
+            None,
+            "=(Sheet1!$A$1,Sheet1!$E$1,Sheet1!$A$5,Sheet1!$E$5)",
+        )
+        .unwrap();
+    model
+        .new_defined_name("Middle", None, "=Sheet1!$C$1:$C$5")
+        .unwrap();
+    model
+        .new_defined_name("Center", None, "=Sheet1!$A$3:$E$3")
+        .unwrap();
+    model._set("G1", "=SUM(Corners)");
+    model._set("G2", "=AREAS(Corners)");
+    model._set("G3", "=Middle Center");
+    model._set("G4", "=LET(x, (A1,B1), SUM(x))");
+    model.evaluate();
+
+    assert_eq!(model._get_text("G1"), "52");
+    assert_eq!(model._get_text("G2"), "4");
+    assert_eq!(model._get_text("G3"), "13");
+    assert_eq!(model._get_text("G4"), "3");
+}
+
+#[test]
+fn test_reference_functions() {
+    let mut model = grid_model();
+    model._set("G1", "=AREAS((A1:B2,D1:E2,C5))");
+    model._set("G2", "=AREAS(A1:B2)");
+    model._set("G3", "=AREAS(B2)");
+    model._set("G4", "=AREAS(1)");
+    model._set("G5", "=INDEX((A1:B2,D1:E2),2,1,2)");
+    model._set("G6", "=INDEX((A1:B2,D1:E2),1,2)");
+    model._set("G7", "=INDEX((A1:B2,D1:E2),1,1,3)");
+    model._set("G8", "=INDEX(A1:B2,1,1,2)");
+    model._set("G9", "=ROWS((A1:B2,D1:E2))");
+    model._set("G10", "=ROWS((A1:B4))");
+    model._set("G11", "=COLUMNS(A1:E5 B2:C2)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("G1"), "3");
+    assert_eq!(model._get_text("G2"), "1");
+    assert_eq!(model._get_text("G3"), "1");
+    assert_eq!(model._get_text("G4"), "#VALUE!");
+    assert_eq!(model._get_text("G5"), "9");
+    assert_eq!(model._get_text("G6"), "2");
+    assert_eq!(model._get_text("G7"), "#REF!");
+    assert_eq!(model._get_text("G8"), "#REF!");
+    assert_eq!(model._get_text("G9"), "#REF!");
+    assert_eq!(model._get_text("G10"), "4");
+    assert_eq!(model._get_text("G11"), "2");
+}
+
+#[test]
+fn test_not_references() {
+    let mut model = grid_model();
+    // A space between values is not an operator
+    model._set("G1", "=1 + 2");
+    model._set("G2", "=SUM(A1 , B1 )");
+    model._set("G3", "=(1,2)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("G1"), "3");
+    assert_eq!(model._get_text("G2"), "3");
+    assert_eq!(model._get_text("G3"), "#VALUE!");
+}
+
+#[test]
+fn test_localized() {
+    let model = new_empty_model();
+    assert_eq!(
+        model
+            .localize_formula(0, 1, 1, "=SUM((A1:A3,C1:C3) B2:D2)", "de", "de")
+            .unwrap(),
+        "=SUMME((A1:A3;C1:C3) B2:D2)"
+    );
+    assert_eq!(
+        model
+            .delocalize_formula(0, 1, 1, "=BEREICHE((A1;B2))", "de", "de")
+            .unwrap(),
+        "=AREAS((A1,B2))"
+    );
+}
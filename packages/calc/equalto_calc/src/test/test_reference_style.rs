@@ -0,0 +1,119 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::test::util::new_empty_model;
+use crate::types::ReferenceStyle;
+
+#[test]
+fn test_default_reference_style() {
+    let mut model = new_empty_model();
+    assert_eq!(model.get_reference_style(), ReferenceStyle::A1);
+    model._set("A2", "=A1*2");
+    assert_eq!(model._get_formula("A2"), "=A1*2");
+}
+
+#[test]
+fn test_r1c1_input_and_display() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model._set("B1", "=A1*2");
+    model.set_reference_style(ReferenceStyle::R1C1);
+    assert_eq!(model._get_formula("B1"), "=RC[-1]*2");
+
+    model._set("A3", "=R[-1]C*2");
+    model._set("A4", "=SUM(R1C1:R3C1)");
+    model._set("A5", "=R1C*Sheet1!R[-4]C[1]+R[-5]C");
+    model._set("B2", "=RC");
+    model.evaluate();
+
+    assert_eq!(model._get_formula("A3"), "=R[-1]C*2");
+    assert_eq!(model._get_formula("A4"), "=SUM(R1C1:R3C1)");
+    assert_eq!(model._get_formula("A5"), "=R1C*Sheet1!R[-4]C[1]+#REF!");
+    assert_eq!(model._get_text("A3"), "4");
+    assert_eq!(model._get_text("A4"), "7");
+    assert_eq!(model._get_text("B2"), "#CIRC!");
+
+    model.set_reference_style(ReferenceStyle::A1);
+    assert_eq!(model._get_formula("A3"), "=A2*2");
+    assert_eq!(model._get_formula("A4"), "=SUM($A$1:$A$3)");
+    assert_eq!(model._get_formula("B2"), "=B2");
+}
+
+#[test]
+fn test_rows_and_columns_in_r1c1_style() {
+    let mut model = new_empty_model();
+    model._set("B4", "=SUM(A:A)");
+    model._set("B5", "=SUM(1:2)");
+    model.set_reference_style(ReferenceStyle::R1C1);
+    assert_eq!(model._get_formula("B4"), "=SUM(C[-1])");
+    assert_eq!(model._get_formula("B5"), "=SUM(R[-4]:R[-3])");
+
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model._set("B1", "10");
+    model.set_reference_style(ReferenceStyle::R1C1);
+    model._set("E10", "=SUM(C[-3])");
+    model._set("E11", "=SUM(R1)");
+    model._set("E12", "=SUM(C1)");
+    model._set("E13", "=SUM(R1:R2)");
+    model._set("E14", "=SUM(C1:C2)");
+    model._set("E15", "=SUM(Sheet1!R[-14])");
+    model.evaluate();
+
+    assert_eq!(model._get_formula("E10"), "=SUM(C[-3])");
+    assert_eq!(model._get_formula("E11"), "=SUM(R1)");
+    assert_eq!(model._get_formula("E12"), "=SUM(C1)");
+    assert_eq!(model._get_formula("E13"), "=SUM(R1:R2)");
+    assert_eq!(model._get_formula("E14"), "=SUM(C1:C2)");
+    assert_eq!(model._get_formula("E15"), "=SUM(Sheet1!R[-14])");
+    assert_eq!(model._get_text("E10"), "10");
+    assert_eq!(model._get_text("E11"), "11");
+    assert_eq!(model._get_text("E12"), "3");
+    assert_eq!(model._get_text("E13"), "13");
+    assert_eq!(model._get_text("E14"), "13");
+    assert_eq!(model._get_text("E15"), "11");
+
+    model.set_reference_style(ReferenceStyle::A1);
+    assert_eq!(model._get_formula("E10"), "=SUM(B:B)");
+    assert_eq!(model._get_formula("E11"), "=SUM($1:$1)");
+    assert_eq!(model._get_formula("E12"), "=SUM($A:$A)");
+    assert_eq!(model._get_formula("E13"), "=SUM($1:$2)");
+}
+
+#[test]
+fn test_a1_references_in_r1c1_style() {
+    let mut model = new_empty_model();
+    model.set_reference_style(ReferenceStyle::R1C1);
+    assert!(model
+        .update_cell_with_formula(0, 1, 1, "=B1".to_string())
+        .is_ok());
+    // In R1C1 style B1 is neither a reference nor a name
+    assert_eq!(model._get_formula("A1"), "=B1");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "#ERROR!");
+}
+
+#[test]
+fn test_reference_style_undo() {
+    let mut model = new_empty_model();
+    model.set_reference_style(ReferenceStyle::R1C1);
+    assert_eq!(model.get_reference_style(), ReferenceStyle::R1C1);
+    model.undo().unwrap();
+    assert_eq!(model.get_reference_style(), ReferenceStyle::A1);
+    model.redo().unwrap();
+    assert_eq!(model.get_reference_style(), ReferenceStyle::R1C1);
+}
+
+#[test]
+fn test_insert_columns_in_r1c1_style() {
+    let mut model = new_empty_model();
+    model.set_reference_style(ReferenceStyle::R1C1);
+    model._set("A1", "5");
+    model._set("C1", "=RC[-2]+1");
+    model.insert_columns(0, 2, 1).unwrap();
+    model.evaluate();
+    // The formula still reads A1
+    assert_eq!(model._get_formula("D1"), "=RC[-3]+1");
+    assert_eq!(model._get_text("D1"), "6");
+}
@@ -0,0 +1,205 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::model::Model;
+use crate::test::util::new_empty_model;
+use crate::types::ReferenceStyle;
+
+/// A summary sheet followed by a sheet for each month with the sales in B5
+fn monthly_model() -> Model {
+    let mut model = new_empty_model();
+    for (month, sales) in [("Jan", "1"), ("Feb", "2"), ("Mar", "3"), ("Apr", "4")] {
+        model.add_sheet(month).unwrap();
+        model._set(&format!("{}!B5", month), sales);
+    }
+    model
+}
+
+#[test]
+fn test_aggregate_functions() {
+    let mut model = monthly_model();
+    model._set("Jan!B4", "10");
+    model._set("Mar!B4", "text");
+    model._set("A1", "=SUM(Jan:Mar!B5)");
+    model._set("A2", "=AVERAGE(Jan:Mar!B5)");
+    model._set("A3", "=COUNT(Jan:Apr!B4:B5)");
+    model._set("A4", "=COUNTA(Jan:Apr!B4:B5)");
+    model._set("A5", "=MAX(Jan:Mar!B5, 0)");
+    model._set("A6", "=MIN(Feb:Apr!$B$5)");
+    model._set("A7", "=PRODUCT(Jan:Apr!B5)");
+    model._set("A8", "=AVERAGEA(Jan:Mar!B4)");
+    model._set("A9", "=MAXA(Jan:Apr!B5)");
+    model._set("A10", "=MINA(Jan:Mar!B4)");
+    model._set("A11", "=VAR(Jan:Apr!B5)");
+    model._set("A12", "=STDEVP(Jan:Apr!B5)");
+    model._set("A13", "=MEDIAN(Jan:Apr!B5)");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), "6");
+    assert_eq!(model._get_text("A2"), "2");
+    assert_eq!(model._get_text("A3"), "5");
+    assert_eq!(model._get_text("A4"), "6");
+    assert_eq!(model._get_text("A5"), "3");
+    assert_eq!(model._get_text("A6"), "2");
+    assert_eq!(model._get_text("A7"), "24");
+    assert_eq!(model._get_text("A8"), "5");
+    assert_eq!(model._get_text("A9"), "4");
+    assert_eq!(model._get_text("A10"), "0");
+    assert_eq!(model._get_text("A11"), "1.666666667");
+    assert_eq!(model._get_text("A12"), "1.118033989");
+    // Like in Excel MEDIAN doesn't take several sheets
+    assert_eq!(model._get_text("A13"), "#VALUE!");
+
+    assert_eq!(model._get_formula("A1"), "=SUM(Jan:Mar!B5)");
+    assert_eq!(model._get_formula("A6"), "=MIN(Feb:Apr!$B$5)");
+
+    // Changes in any sheet of the span are picked up
+    model._set("Feb!B5", "20");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "24");
+}
+
+#[test]
+fn test_formulas() {
+    let mut model = monthly_model();
+    model.add_sheet("Jan 2024").unwrap();
+    model.add_sheet("Dec 2024").unwrap();
+    model._set("Jan 2024!B5", "5");
+    model._set("A1", "=SUM('Jan 2024:Dec 2024'!B5)");
+    // The sheets can be written in any order
+    model._set("A2", "=SUM(Mar:Jan!B5)");
+    model._set("A3", "=SUM(Jan:Jan!B5)");
+    model._set("A4", "=SUM(Jan:Nov!B5)");
+    model._set("A5", "=Jan:Mar!B5");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), "5");
+    assert_eq!(model._get_formula("A1"), "=SUM('Jan 2024:Dec 2024'!B5)");
+    assert_eq!(model._get_text("A2"), "6");
+    assert_eq!(model._get_formula("A2"), "=SUM(Mar:Jan!B5)");
+    assert_eq!(model._get_text("A3"), "1");
+    assert_eq!(model._get_formula("A3"), "=SUM(Jan!B5)");
+    assert_eq!(model._get_text("A4"), "#REF!");
+    assert_eq!(model._get_formula("A4"), "=SUM(Jan:Nov!B5)");
+    // Only aggregate functions take several sheets
+    assert_eq!(model._get_text("A5"), "#VALUE!");
+
+    model.set_reference_style(ReferenceStyle::R1C1);
+    assert_eq!(model._get_formula("A2"), "=SUM(Mar:Jan!R[3]C[1])");
+}
+
+#[test]
+fn test_rename_sheet() {
+    let mut model = monthly_model();
+    model._set("A1", "=SUM(Jan:Mar!B5)");
+    model.rename_sheet("Jan", "January").unwrap();
+    model.rename_sheet("Mar", "March").unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("A1"), "=SUM(January:March!B5)");
+    assert_eq!(model._get_text("A1"), "6");
+}
+
+#[test]
+fn test_insert_sheet() {
+    let mut model = monthly_model();
+    model._set("A1", "=SUM(Jan:Mar!B5)");
+
+    // Between the first and the last sheet
+    model.insert_sheet("Bonus", 3, None).unwrap();
+    model._set("Bonus!B5", "100");
+    // Before the first sheet
+    model.insert_sheet("Budget", 1, None).unwrap();
+    model._set("Budget!B5", "1000");
+    model.evaluate();
+
+    assert_eq!(model._get_text("A1"), "106");
+    assert_eq!(model._get_formula("A1"), "=SUM(Jan:Mar!B5)");
+}
+
+#[test]
+fn test_move_sheet() {
+    let mut model = monthly_model();
+    model._set("A1", "=SUM(Jan:Mar!B5)");
+    model.evaluate();
+
+    // Feb leaves the span
+    model.move_sheet(2, 4).unwrap();
+    model.evaluate();
+    assert_eq!(
+        model.workbook.get_worksheet_names(),
+        ["Sheet1", "Jan", "Mar", "Apr", "Feb"]
+    );
+    assert_eq!(model._get_text("A1"), "4");
+
+    // The span grows when its last sheet moves to the end
+    model.move_sheet(2, 4).unwrap();
+    model.evaluate();
+    assert_eq!(
+        model.workbook.get_worksheet_names(),
+        ["Sheet1", "Jan", "Apr", "Feb", "Mar"]
+    );
+    assert_eq!(model._get_text("A1"), "10");
+    assert_eq!(model._get_formula("A1"), "=SUM(Jan:Mar!B5)");
+
+    // The first sheet after the last one
+    model.move_sheet(1, 4).unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "4");
+
+    model.undo().unwrap();
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(
+        model.workbook.get_worksheet_names(),
+        ["Sheet1", "Jan", "Mar", "Apr", "Feb"]
+    );
+    assert_eq!(model._get_text("A1"), "4");
+
+    assert!(model.move_sheet(1, 5).is_err());
+}
+
+#[test]
+fn test_delete_sheet() {
+    let mut model = monthly_model();
+    model._set("A1", "=SUM(Jan:Apr!B5)");
+    model._set("A2", "=SUM(Jan:Feb!B5)");
+    model._set("Apr!A1", "=SUM(Jan:Mar!B4:B5)");
+    model
+        .new_defined_name("Sales", None, "=Jan:Mar!$B$5")
+        .unwrap();
+    model._set("A3", "=SUM(Sales)");
+    model.evaluate();
+    assert_eq!(model._get_text("A3"), "6");
+
+    // The span starts in the next sheet
+    model.delete_sheet_by_name("Jan").unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("A1"), "=SUM(Feb:Apr!B5)");
+    assert_eq!(model._get_text("A1"), "9");
+    assert_eq!(model._get_formula("A2"), "=SUM(Feb!B5)");
+    assert_eq!(model._get_text("A2"), "2");
+    assert_eq!(model._get_formula("Apr!A1"), "=SUM(Feb:Mar!B4:B5)");
+    assert_eq!(model._get_text("A3"), "5");
+
+    // The span ends in the previous sheet
+    model.delete_sheet_by_name("Apr").unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("A1"), "=SUM(Feb:Mar!B5)");
+    assert_eq!(model._get_text("A1"), "5");
+
+    // Undoing the deletion brings back the span
+    model.undo().unwrap();
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(
+        model.workbook.get_worksheet_names(),
+        ["Sheet1", "Jan", "Feb", "Mar", "Apr"]
+    );
+    assert_eq!(model._get_formula("A1"), "=SUM(Jan:Apr!B5)");
+    assert_eq!(model._get_text("A1"), "10");
+    assert_eq!(model._get_formula("A2"), "=SUM(Jan:Feb!B5)");
+    assert_eq!(model._get_text("A3"), "6");
+
+    model.redo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("A1"), "=SUM(Feb:Apr!B5)");
+}
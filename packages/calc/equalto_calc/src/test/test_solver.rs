@@ -0,0 +1,358 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::model::Model;
+use crate::solver::{
+    ConstraintOperator, SolverConstraint, SolverDefinition, SolverGoal, SolverMethod, SolverStatus,
+};
+use crate::test::util::new_empty_model;
+
+fn constraint(cells: &str, operator: ConstraintOperator, value: &str) -> SolverConstraint {
+    SolverConstraint {
+        cells: cells.to_string(),
+        operator,
+        value: value.to_string(),
+    }
+}
+
+/// Maximize 3x + 5y with x <= 4, 2y <= 12 and 3x + 2y <= 18
+fn linear_model() -> (Model, SolverDefinition) {
+    let mut model = new_empty_model();
+    model._set("A1", "0");
+    model._set("A2", "0");
+    model._set("B1", "=3*A1+5*A2");
+    model._set("C1", "=2*A2");
+    model._set("C2", "=3*A1+2*A2");
+    model._set("D1", "12");
+    model.evaluate();
+    let definition = SolverDefinition {
+        objective: "Sheet1!$B$1".to_string(),
+        goal: SolverGoal::Maximize,
+        variables: vec!["Sheet1!$A$1:$A$2".to_string()],
+        constraints: vec![
+            constraint("Sheet1!$A$1", ConstraintOperator::LessOrEqual, "4"),
+            constraint(
+                "Sheet1!$C$1",
+                ConstraintOperator::LessOrEqual,
+                "Sheet1!$D$1",
+            ),
+            constraint("Sheet1!$C$2", ConstraintOperator::LessOrEqual, "18"),
+        ],
+        method: SolverMethod::Linear,
+        ..Default::default()
+    };
+    (model, definition)
+}
+
+#[test]
+fn test_linear() {
+    let (mut model, definition) = linear_model();
+    let result = model.solve(0, &definition, true).unwrap();
+    assert_eq!(result.status, SolverStatus::Optimal);
+    assert!((result.objective_value - 36.0).abs() < 1e-9);
+    assert!((result.values[0] - 2.0).abs() < 1e-9);
+    assert!((result.values[1] - 6.0).abs() < 1e-9);
+    assert_eq!(model._get_text("B1"), "36");
+
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "0");
+    assert_eq!(model._get_text("A2"), "0");
+}
+
+#[test]
+fn test_do_not_apply() {
+    let (mut model, definition) = linear_model();
+    let result = model.solve(0, &definition, false).unwrap();
+    assert_eq!(result.status, SolverStatus::Optimal);
+    assert_eq!(model._get_text("A1"), "0");
+    assert_eq!(model._get_text("B1"), "0");
+}
+
+#[test]
+fn test_minimize_without_sheet_names() {
+    // Minimize 2x + 3y with x + y >= 10 and x >= 3
+    let mut model = new_empty_model();
+    model._set("B1", "=2*A1+3*A2");
+    model._set("C1", "=A1+A2");
+    let definition = SolverDefinition {
+        objective: "B1".to_string(),
+        goal: SolverGoal::Minimize,
+        variables: vec!["A1".to_string(), "A2".to_string()],
+        constraints: vec![
+            constraint("C1", ConstraintOperator::GreaterOrEqual, "10"),
+            constraint("A2", ConstraintOperator::GreaterOrEqual, "3"),
+        ],
+        method: SolverMethod::Linear,
+        ..Default::default()
+    };
+    let result = model.solve(0, &definition, true).unwrap();
+    assert_eq!(result.status, SolverStatus::Optimal);
+    assert_eq!(model._get_text("A1"), "7");
+    assert_eq!(model._get_text("A2"), "3");
+    assert_eq!(model._get_text("B1"), "23");
+}
+
+#[test]
+fn test_binary_knapsack() {
+    // Values 5, 4 and 3 with weights 2, 3 and 1 and a capacity of 5
+    let mut model = new_empty_model();
+    model._set("B1", "5");
+    model._set("B2", "4");
+    model._set("B3", "3");
+    model._set("C1", "2");
+    model._set("C2", "3");
+    model._set("C3", "1");
+    model._set("D1", "=A1*B1+A2*B2+A3*B3");
+    model._set("D2", "=A1*C1+A2*C2+A3*C3");
+    let definition = SolverDefinition {
+        objective: "D1".to_string(),
+        goal: SolverGoal::Maximize,
+        variables: vec!["A1:A3".to_string()],
+        constraints: vec![
+            constraint("D2", ConstraintOperator::LessOrEqual, "5"),
+            constraint("A1:A3", ConstraintOperator::Binary, ""),
+        ],
+        method: SolverMethod::Linear,
+        ..Default::default()
+    };
+    let result = model.solve(0, &definition, true).unwrap();
+    assert_eq!(result.status, SolverStatus::Optimal);
+    assert_eq!(result.values, vec![1.0, 1.0, 0.0]);
+    assert_eq!(model._get_text("D1"), "9");
+}
+
+#[test]
+fn test_integer() {
+    // Maximize x + y with 2x + 2y <= 7 and 2x - 2y <= 1
+    let mut model = new_empty_model();
+    model._set("B1", "=A1+A2");
+    model._set("C1", "=2*A1+2*A2");
+    model._set("C2", "=2*A1-2*A2");
+    let definition = SolverDefinition {
+        objective: "B1".to_string(),
+        goal: SolverGoal::Maximize,
+        variables: vec!["A1:A2".to_string()],
+        constraints: vec![
+            constraint("C1", ConstraintOperator::LessOrEqual, "7"),
+            constraint("C2", ConstraintOperator::LessOrEqual, "1"),
+            constraint("A1:A2", ConstraintOperator::Integer, ""),
+        ],
+        method: SolverMethod::Linear,
+        ..Default::default()
+    };
+    let result = model.solve(0, &definition, true).unwrap();
+    assert_eq!(result.status, SolverStatus::Optimal);
+    assert_eq!(result.objective_value, 3.0);
+    assert!(result.values.iter().all(|x| x.fract() == 0.0));
+}
+
+#[test]
+fn test_target_value() {
+    let mut model = new_empty_model();
+    model._set("B1", "=2*A1+A2");
+    let definition = SolverDefinition {
+        objective: "B1".to_string(),
+        goal: SolverGoal::Value(10.0),
+        variables: vec!["A1:A2".to_string()],
+        constraints: vec![constraint("A1", ConstraintOperator::Equal, "3")],
+        method: SolverMethod::Linear,
+        ..Default::default()
+    };
+    let result = model.solve(0, &definition, true).unwrap();
+    assert_eq!(result.status, SolverStatus::Optimal);
+    assert_eq!(result.values, vec![3.0, 4.0]);
+}
+
+#[test]
+fn test_infeasible_and_unbounded() {
+    let mut model = new_empty_model();
+    model._set("B1", "=A1+A2");
+    let mut definition = SolverDefinition {
+        objective: "B1".to_string(),
+        goal: SolverGoal::Maximize,
+        variables: vec!["A1:A2".to_string()],
+        constraints: vec![],
+        method: SolverMethod::Linear,
+        ..Default::default()
+    };
+    let result = model.solve(0, &definition, true).unwrap();
+    assert_eq!(result.status, SolverStatus::Unbounded);
+
+    definition.constraints = vec![
+        constraint("B1", ConstraintOperator::LessOrEqual, "5"),
+        constraint("B1", ConstraintOperator::GreaterOrEqual, "6"),
+    ];
+    let result = model.solve(0, &definition, true).unwrap();
+    assert_eq!(result.status, SolverStatus::Infeasible);
+
+    definition.constraints = vec![
+        constraint("A1", ConstraintOperator::LessOrEqual, "5"),
+        constraint("A1", ConstraintOperator::GreaterOrEqual, "6"),
+    ];
+    let result = model.solve(0, &definition, true).unwrap();
+    assert_eq!(result.status, SolverStatus::Infeasible);
+    assert!(model.is_empty_cell(0, 1, 1).unwrap());
+}
+
+#[test]
+fn test_not_linear() {
+    let mut model = new_empty_model();
+    model._set("B1", "=A1^2");
+    let definition = SolverDefinition {
+        objective: "B1".to_string(),
+        goal: SolverGoal::Minimize,
+        variables: vec!["A1".to_string()],
+        method: SolverMethod::Linear,
+        ..Default::default()
+    };
+    assert_eq!(
+        model.solve(0, &definition, true),
+        Err("The model is not linear, use the nonlinear method".to_string())
+    );
+    assert!(model.is_empty_cell(0, 1, 1).unwrap());
+}
+
+#[test]
+fn test_nonlinear() {
+    // Minimize (x - 3)^2 + (y + 1)^2
+    let mut model = new_empty_model();
+    model._set("B1", "=(A1-3)^2+(A2+1)^2");
+    let definition = SolverDefinition {
+        objective: "B1".to_string(),
+        goal: SolverGoal::Minimize,
+        variables: vec!["A1:A2".to_string()],
+        non_negative: false,
+        ..Default::default()
+    };
+    let result = model.solve(0, &definition, false).unwrap();
+    assert_eq!(result.status, SolverStatus::Optimal);
+    assert!((result.values[0] - 3.0).abs() < 1e-3);
+    assert!((result.values[1] + 1.0).abs() < 1e-3);
+
+    // With the default non negative variables
+    let definition = SolverDefinition {
+        non_negative: true,
+        ..definition
+    };
+    let result = model.solve(0, &definition, false).unwrap();
+    assert_eq!(result.status, SolverStatus::Optimal);
+    assert!((result.values[0] - 3.0).abs() < 1e-3);
+    assert_eq!(result.values[1], 0.0);
+}
+
+#[test]
+fn test_nonlinear_constraints() {
+    // Minimize x^2 + y^2 with x + y >= 2
+    let mut model = new_empty_model();
+    model._set("A1", "3");
+    model._set("A2", "4");
+    model._set("B1", "=A1^2+A2^2");
+    model._set("C1", "=A1+A2");
+    let definition = SolverDefinition {
+        objective: "B1".to_string(),
+        goal: SolverGoal::Minimize,
+        variables: vec!["A1:A2".to_string()],
+        constraints: vec![constraint("C1", ConstraintOperator::GreaterOrEqual, "2")],
+        max_iterations: 5000,
+        ..Default::default()
+    };
+    let result = model.solve(0, &definition, true).unwrap();
+    assert!(matches!(
+        result.status,
+        SolverStatus::Optimal | SolverStatus::Feasible
+    ));
+    assert!((result.objective_value - 2.0).abs() < 1e-3);
+    assert!((result.values[0] - 1.0).abs() < 1e-2);
+    assert!((result.values[1] - 1.0).abs() < 1e-2);
+}
+
+#[test]
+fn test_errors() {
+    let mut model = new_empty_model();
+    model._set("A2", "=A1");
+    model._set("B1", "=A1");
+    let definition = SolverDefinition {
+        objective: "B1:B2".to_string(),
+        variables: vec!["A1".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(
+        model.solve(0, &definition, true),
+        Err("The objective must be a single cell".to_string())
+    );
+    let definition = SolverDefinition {
+        objective: "B1".to_string(),
+        variables: vec!["A1:A2".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(
+        model.solve(0, &definition, true),
+        Err("The variable cells can't contain formulas".to_string())
+    );
+    let definition = SolverDefinition {
+        objective: "B1".to_string(),
+        variables: vec!["A1".to_string()],
+        constraints: vec![constraint("B1", ConstraintOperator::Integer, "")],
+        ..Default::default()
+    };
+    assert_eq!(
+        model.solve(0, &definition, true),
+        Err("Integer and binary constraints must refer to variable cells".to_string())
+    );
+    let definition = SolverDefinition {
+        objective: "B1".to_string(),
+        variables: vec!["A1".to_string()],
+        constraints: vec![constraint("B1", ConstraintOperator::LessOrEqual, "C1:C3")],
+        ..Default::default()
+    };
+    assert!(model.solve(0, &definition, true).is_err());
+}
+
+#[test]
+fn test_storage() {
+    let (mut model, mut definition) = linear_model();
+    assert_eq!(model.get_solver(0).unwrap(), None);
+    definition.constraints.push(constraint(
+        "Sheet1!$A$1:$A$2",
+        ConstraintOperator::Integer,
+        "",
+    ));
+    model.set_solver(0, &definition).unwrap();
+    assert_eq!(model.get_solver(0).unwrap(), Some(definition.clone()));
+    // The names are hidden
+    assert!(model.get_defined_name_list().is_empty());
+
+    // References are stored with the sheet name
+    let other = SolverDefinition {
+        objective: "B1".to_string(),
+        goal: SolverGoal::Value(2.5),
+        variables: vec!["A1".to_string(), "A2".to_string()],
+        constraints: vec![],
+        method: SolverMethod::Nonlinear,
+        non_negative: false,
+        max_iterations: 200,
+        precision: 1e-4,
+    };
+    model.set_solver(0, &other).unwrap();
+    let stored = model.get_solver(0).unwrap().unwrap();
+    assert_eq!(stored.objective, "Sheet1!$B$1");
+    assert_eq!(stored.variables, vec!["Sheet1!$A$1", "Sheet1!$A$2"]);
+    assert_eq!(stored.goal, SolverGoal::Value(2.5));
+    assert!(!stored.non_negative);
+    assert_eq!(stored.max_iterations, 200);
+    assert_eq!(stored.precision, 1e-4);
+
+    model.undo().unwrap();
+    assert_eq!(model.get_solver(0).unwrap(), Some(definition.clone()));
+    model.delete_solver(0).unwrap();
+    assert_eq!(model.get_solver(0).unwrap(), None);
+
+    let stored = model.set_solver(
+        0,
+        &SolverDefinition {
+            objective: "Sheet7!A1".to_string(),
+            ..definition
+        },
+    );
+    assert!(stored.is_err());
+}
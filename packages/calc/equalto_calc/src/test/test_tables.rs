@@ -0,0 +1,304 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::model::Model;
+use crate::test::util::new_empty_model;
+use crate::types::{Table, TableColumn, TableStyleInfo};
+
+/// The table Sales in B2:E5 with a header row and three rows of data.
+/// The column Total is empty.
+fn sales_model() -> Model {
+    let mut model = new_empty_model();
+    let rows = [
+        ["Item", "Price", "Qty", "Total"],
+        ["Pens", "2", "10", ""],
+        ["Paper", "5", "3", ""],
+        ["Ink", "20", "1", ""],
+    ];
+    for (index, values) in rows.iter().enumerate() {
+        let row = index + 2;
+        for (column, value) in ["B", "C", "D", "E"].iter().zip(values) {
+            model._set(&format!("{}{}", column, row), value);
+        }
+    }
+    let columns = rows[0]
+        .iter()
+        .enumerate()
+        .map(|(id, name)| TableColumn {
+            id: id as u32 + 1,
+            name: name.to_string(),
+            ..Default::default()
+        })
+        .collect();
+    model.workbook.tables.insert(
+        "Sales".to_string(),
+        Table {
+            name: "Sales".to_string(),
+            display_name: "Sales".to_string(),
+            sheet_name: "Sheet1".to_string(),
+            reference: "B2:E5".to_string(),
+            totals_row_count: 0,
+            header_row_count: 1,
+            header_row_dxf_id: None,
+            data_dxf_id: None,
+            totals_row_dxf_id: None,
+            columns,
+            style_info: TableStyleInfo::default(),
+            has_filters: false,
+        },
+    );
+    model.reset_parsed_structures();
+    model
+}
+
+#[test]
+fn test_evaluate() {
+    let mut model = sales_model();
+    model._set("E3", "=[@Price]*[@Qty]");
+    model._set("E4", "=Sales[[#This Row],[Price]]*Sales[@Qty]");
+    model._set("G1", "=SUM(Sales[Qty])");
+    model._set("G2", "=AVERAGE(Sales[Price])");
+    model._set("G3", "=COUNTA(Sales[#Headers])");
+    model._set("G4", "=ROWS(Sales[])");
+    model._set("G5", "=ROWS(Sales[#All])");
+    model._set("G6", "=SUM(Sales[@[Price]:[Qty]])");
+    model._set("G7", "=Sales[#Totals]");
+    model._set("G8", "=SUM(Sales[Tax])");
+    model._set("G9", "=COUNTA(Sales[[#Headers],[#Data],[Item]])");
+    model.evaluate();
+
+    assert_eq!(model._get_text("E3"), "20");
+    assert_eq!(model._get_text("E4"), "15");
+    assert_eq!(model._get_text("G1"), "14");
+    assert_eq!(model._get_text("G2"), "9");
+    assert_eq!(model._get_text("G3"), "4");
+    assert_eq!(model._get_text("G4"), "3");
+    assert_eq!(model._get_text("G5"), "4");
+    // Row 6 is not in the table
+    assert_eq!(model._get_text("G6"), "#VALUE!");
+    assert_eq!(model._get_text("G7"), "#REF!");
+    assert_eq!(model._get_text("G8"), "#REF!");
+    assert_eq!(model._get_text("G9"), "4");
+
+    // The name of the table is left out as it was entered
+    assert_eq!(model._get_formula("E3"), "=[@Price]*[@Qty]");
+    assert_eq!(model._get_formula("E4"), "=Sales[@Price]*Sales[@Qty]");
+    assert_eq!(model._get_formula("G6"), "=SUM(Sales[@[Price]:[Qty]])");
+
+    // The formulas depend on the cells of the table
+    model._set("D3", "100");
+    model.evaluate();
+    assert_eq!(model._get_text("E3"), "200");
+    assert_eq!(model._get_text("G1"), "104");
+}
+
+#[test]
+fn test_table_grows() {
+    let mut model = sales_model();
+    model._set("G1", "=SUM(Sales[Qty])");
+    model._set("G2", "=ROWS(Sales[])");
+    model.evaluate();
+    assert_eq!(model._get_text("G1"), "14");
+
+    model._set("B6", "Clips");
+    model._set("D6", "6");
+    model._set("E6", "=[@Qty]*2");
+    model.evaluate();
+    assert_eq!(model.workbook.tables["Sales"].reference, "B2:E6");
+    assert_eq!(model._get_text("G1"), "20");
+    assert_eq!(model._get_text("G2"), "4");
+    assert_eq!(model._get_text("E6"), "12");
+
+    // Values further down or to the side don't change the table
+    model._set("B8", "Tape");
+    model._set("F7", "1");
+    model.evaluate();
+    assert_eq!(model.workbook.tables["Sales"].reference, "B2:E6");
+
+    model.undo().unwrap();
+    model.undo().unwrap();
+    model.undo().unwrap();
+    model.undo().unwrap();
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(model.workbook.tables["Sales"].reference, "B2:E5");
+    assert_eq!(model._get_text("G1"), "14");
+    assert_eq!(model._get_text("G2"), "3");
+}
+
+#[test]
+fn test_rename_table() {
+    let mut model = sales_model();
+    model._set("G1", "=SUM(Sales[Qty])");
+    model._set("E3", "=[@Price]*2");
+    model
+        .new_defined_name("Quantities", None, "=Sales[Qty]")
+        .unwrap();
+    model._set("G2", "=SUM(Quantities)");
+    model.evaluate();
+
+    model.rename_table("sales", "Orders").unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("G1"), "=SUM(Orders[Qty])");
+    assert_eq!(model._get_formula("E3"), "=[@Price]*2");
+    assert_eq!(
+        model.get_defined_name_list()[0].2,
+        "Orders[Qty]".to_string()
+    );
+    assert_eq!(model._get_text("G1"), "14");
+    assert_eq!(model._get_text("G2"), "14");
+    assert_eq!(model._get_text("E3"), "4");
+    assert!(model.workbook.tables.contains_key("Orders"));
+
+    // New formulas use the new name
+    model._set("G3", "=ROWS(Orders[])");
+    model._set("G4", "=ROWS(Sales[])");
+    model.evaluate();
+    assert_eq!(model._get_text("G3"), "3");
+    assert_eq!(model._get_text("G4"), "#ERROR!");
+
+    model.undo().unwrap();
+    model.undo().unwrap();
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("G1"), "=SUM(Sales[Qty])");
+    assert_eq!(model._get_formula("E3"), "=[@Price]*2");
+    assert!(model.workbook.tables.contains_key("Sales"));
+    assert_eq!(model._get_text("G1"), "14");
+
+    model.redo().unwrap();
+    assert_eq!(model._get_formula("G1"), "=SUM(Orders[Qty])");
+}
+
+#[test]
+fn test_rename_table_errors() {
+    let mut model = sales_model();
+    model.new_defined_name("Total", None, "=1").unwrap();
+    assert_eq!(
+        model.rename_table("Costs", "Orders"),
+        Err("Table not found: 'Costs'".to_string())
+    );
+    assert_eq!(
+        model.rename_table("Sales", "A1"),
+        Err("Invalid table name: 'A1'".to_string())
+    );
+    assert_eq!(
+        model.rename_table("Sales", "total"),
+        Err("Defined name already exists: 'total'".to_string())
+    );
+    assert!(model.workbook.tables.contains_key("Sales"));
+}
+
+#[test]
+fn test_rename_column() {
+    let mut model = sales_model();
+    model._set("G1", "=SUM(Sales[Qty])");
+    model._set("G2", "=SUM(Sales[[#Totals],[Price]:[Qty]])");
+    model._set("E3", "=[@Price]*[@Qty]");
+    model.evaluate();
+
+    model
+        .rename_table_column("Sales", "qty", "Units sold")
+        .unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("G1"), "=SUM(Sales[Units sold])");
+    assert_eq!(
+        model._get_formula("G2"),
+        "=SUM(Sales[[#Totals],[Price]:[Units sold]])"
+    );
+    assert_eq!(model._get_formula("E3"), "=[@Price]*[@[Units sold]]");
+    assert_eq!(model._get_text("D2"), "Units sold");
+    assert_eq!(model._get_text("G1"), "14");
+    assert_eq!(model._get_text("E3"), "20");
+
+    assert_eq!(
+        model.rename_table_column("Sales", "Units sold", "price"),
+        Err("Column already exists: 'price'".to_string())
+    );
+    assert_eq!(
+        model.rename_table_column("Sales", "Tax", "VAT"),
+        Err("Column not found: 'Tax'".to_string())
+    );
+
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("G1"), "=SUM(Sales[Qty])");
+    assert_eq!(model._get_formula("E3"), "=[@Price]*[@Qty]");
+    assert_eq!(model._get_text("D2"), "Qty");
+    assert_eq!(model._get_text("G1"), "14");
+}
+
+#[test]
+fn test_rename_column_in_header() {
+    let mut model = sales_model();
+    model._set("G1", "=SUM(Sales[Qty])");
+    model._set("E3", "=[@Price]*[@Qty]");
+    model.evaluate();
+
+    model._set("D2", "Units");
+    model.evaluate();
+    assert_eq!(model.workbook.tables["Sales"].columns[2].name, "Units");
+    assert_eq!(model._get_formula("G1"), "=SUM(Sales[Units])");
+    assert_eq!(model._get_formula("E3"), "=[@Price]*[@Units]");
+    assert_eq!(model._get_text("D2"), "Units");
+    assert_eq!(model._get_text("E3"), "20");
+
+    // A name already in the table gets a number
+    model._set("D2", "price");
+    model.evaluate();
+    assert_eq!(model._get_text("D2"), "price2");
+    assert_eq!(model._get_formula("G1"), "=SUM(Sales[price2])");
+
+    model.undo().unwrap();
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(model.workbook.tables["Sales"].columns[2].name, "Qty");
+    assert_eq!(model._get_formula("G1"), "=SUM(Sales[Qty])");
+    assert_eq!(model._get_text("D2"), "Qty");
+}
+
+#[test]
+fn test_rename_column_shared_formula() {
+    let mut model = sales_model();
+    // A second table with the same columns in H2:I4
+    let rows = [["Price", "Qty"], ["1", "2"], ["3", "4"]];
+    for (index, values) in rows.iter().enumerate() {
+        let row = index + 2;
+        for (column, value) in ["H", "I"].iter().zip(values) {
+            model._set(&format!("{}{}", column, row), value);
+        }
+    }
+    let mut table = model.workbook.tables["Sales"].clone();
+    table.name = "Stock".to_string();
+    table.display_name = "Stock".to_string();
+    table.reference = "H2:J4".to_string();
+    table.columns.remove(0);
+    model.workbook.tables.insert("Stock".to_string(), table);
+    model.reset_parsed_structures();
+
+    // Both cells have the same formula
+    model._set("E3", "=[@Qty]*2");
+    model._set("J3", "=[@Qty]*2");
+    model.evaluate();
+    assert_eq!(model._get_text("E3"), "20");
+    assert_eq!(model._get_text("J3"), "4");
+
+    model.rename_table_column("Sales", "Qty", "Units").unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("E3"), "=[@Units]*2");
+    assert_eq!(model._get_formula("J3"), "=[@Qty]*2");
+    assert_eq!(model._get_text("E3"), "20");
+    assert_eq!(model._get_text("J3"), "4");
+}
+
+#[test]
+fn test_rename_sheet() {
+    let mut model = sales_model();
+    model.new_sheet();
+    model._set("Sheet2!A1", "=SUM(Sales[Price])");
+    model.evaluate();
+
+    model.rename_sheet("Sheet1", "Data").unwrap();
+    model.evaluate();
+    assert_eq!(model.workbook.tables["Sales"].sheet_name, "Data");
+    assert_eq!(model._get_text("Sheet2!A1"), "27");
+}
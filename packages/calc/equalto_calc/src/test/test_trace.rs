@@ -0,0 +1,247 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::calc_result::CellReference;
+use crate::expressions::types::{Area, CellReferenceIndex};
+use crate::test::util::{cell, new_empty_model};
+use crate::trace::TraceArrow;
+
+fn arrow(precedent: (i32, i32, i32, i32), dependent: (i32, i32), depth: usize) -> TraceArrow {
+    let (row, column, width, height) = precedent;
+    TraceArrow {
+        precedent: Area {
+            sheet: 0,
+            row,
+            column,
+            width,
+            height,
+        },
+        dependent: CellReferenceIndex {
+            sheet: 0,
+            row: dependent.0,
+            column: dependent.1,
+        },
+        depth,
+    }
+}
+
+#[test]
+fn test_precedents() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model._set("B1", "=SUM(A1:A2)");
+    model._set("C1", "=B1*2+A1");
+    model._set("D1", "=C1+B1");
+    model.evaluate();
+
+    assert_eq!(
+        model.precedents(cell(1, 4), Some(1)).unwrap(),
+        vec![
+            arrow((1, 3, 1, 1), (1, 4), 1),
+            arrow((1, 2, 1, 1), (1, 4), 1)
+        ]
+    );
+    assert_eq!(
+        model.precedents(cell(1, 4), None).unwrap(),
+        vec![
+            arrow((1, 3, 1, 1), (1, 4), 1),
+            arrow((1, 2, 1, 1), (1, 4), 1),
+            arrow((1, 2, 1, 1), (1, 3), 2),
+            arrow((1, 1, 1, 1), (1, 3), 2),
+            arrow((1, 1, 1, 2), (1, 2), 2),
+        ]
+    );
+    // Values have no precedents
+    assert_eq!(model.precedents(cell(1, 1), None).unwrap(), vec![]);
+    assert_eq!(model.precedents(cell(1, 4), Some(0)).unwrap(), vec![]);
+}
+
+#[test]
+fn test_dependents() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model._set("B1", "=SUM(A1:A2)");
+    model._set("C1", "=B1*2+A1");
+    model._set("D1", "=C1+B1");
+    model.evaluate();
+
+    assert_eq!(
+        model.dependents(cell(1, 1), Some(1)).unwrap(),
+        vec![
+            arrow((1, 1, 1, 1), (1, 2), 1),
+            arrow((1, 1, 1, 1), (1, 3), 1)
+        ]
+    );
+    assert_eq!(
+        model.dependents(cell(2, 1), None).unwrap(),
+        vec![
+            arrow((2, 1, 1, 1), (1, 2), 1),
+            arrow((1, 2, 1, 1), (1, 3), 2),
+            arrow((1, 2, 1, 1), (1, 4), 2),
+            arrow((1, 3, 1, 1), (1, 4), 3),
+        ]
+    );
+    assert_eq!(model.dependents(cell(1, 4), None).unwrap(), vec![]);
+}
+
+#[test]
+fn test_defined_names() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model
+        .new_defined_name("values", None, "Sheet1!$A$1:$A$2")
+        .unwrap();
+    model
+        .new_defined_name("double", None, "Sheet1!$A$1*2")
+        .unwrap();
+    model._set("B1", "=SUM(values)+double");
+    model.evaluate();
+
+    assert_eq!(
+        model.precedents(cell(1, 2), None).unwrap(),
+        vec![
+            arrow((1, 1, 1, 2), (1, 2), 1),
+            arrow((1, 1, 1, 1), (1, 2), 1)
+        ]
+    );
+    assert_eq!(
+        model.dependents(cell(2, 1), None).unwrap(),
+        vec![arrow((2, 1, 1, 1), (1, 2), 1)]
+    );
+}
+
+#[test]
+fn test_indirect_and_offset() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model._set("A3", "3");
+    model._set("B1", "A2");
+    model._set("C1", "=INDIRECT(B1)");
+    model._set("C2", "=SUM(OFFSET(A1,1,0,2,1))");
+    model.evaluate();
+
+    assert_eq!(
+        model.precedents(cell(1, 3), Some(1)).unwrap(),
+        vec![
+            arrow((1, 2, 1, 1), (1, 3), 1),
+            arrow((2, 1, 1, 1), (1, 3), 1)
+        ]
+    );
+    assert_eq!(
+        model.precedents(cell(2, 3), Some(1)).unwrap(),
+        vec![
+            arrow((1, 1, 1, 1), (2, 3), 1),
+            arrow((2, 1, 1, 2), (2, 3), 1)
+        ]
+    );
+    assert_eq!(
+        model.dependents(cell(3, 1), None).unwrap(),
+        vec![arrow((3, 1, 1, 1), (2, 3), 1)]
+    );
+
+    // The references follow the last evaluation
+    model._set("B1", "A3");
+    model.evaluate();
+    assert_eq!(
+        model.precedents(cell(1, 3), Some(1)).unwrap(),
+        vec![
+            arrow((1, 2, 1, 1), (1, 3), 1),
+            arrow((3, 1, 1, 1), (1, 3), 1)
+        ]
+    );
+    assert_eq!(
+        model.dependents(cell(2, 1), None).unwrap(),
+        vec![arrow((2, 1, 1, 1), (2, 3), 1)]
+    );
+}
+
+#[test]
+fn test_spill() {
+    let mut model = new_empty_model();
+    model._set("A1", "=SEQUENCE(3)");
+    model._set("B1", "=A2*2");
+    model._set("C1", "=SUM(A1#)");
+    model.evaluate();
+
+    // The formulas reading a spilled value depend on the formula that spilled it
+    assert_eq!(
+        model.precedents(cell(1, 2), None).unwrap(),
+        vec![arrow((2, 1, 1, 1), (1, 2), 1)]
+    );
+    assert_eq!(
+        model.dependents(cell(1, 1), None).unwrap(),
+        vec![
+            arrow((1, 1, 1, 3), (1, 2), 1),
+            arrow((1, 1, 1, 3), (1, 3), 1)
+        ]
+    );
+}
+
+#[test]
+fn test_circular_references() {
+    let mut model = new_empty_model();
+    model._set("A1", "=B1+1");
+    model._set("B1", "=A1+1");
+    model.evaluate();
+
+    assert_eq!(
+        model.precedents(cell(1, 1), None).unwrap(),
+        vec![
+            arrow((1, 2, 1, 1), (1, 1), 1),
+            arrow((1, 1, 1, 1), (1, 2), 2)
+        ]
+    );
+    assert_eq!(
+        model.dependents(cell(1, 1), None).unwrap(),
+        vec![
+            arrow((1, 1, 1, 1), (1, 2), 1),
+            arrow((1, 2, 1, 1), (1, 1), 2)
+        ]
+    );
+}
+
+#[test]
+fn test_other_sheets() {
+    let mut model = new_empty_model();
+    model.new_sheet();
+    model._set("Sheet2!A1", "5");
+    model._set("A1", "=Sheet2!A1*2");
+    model.evaluate();
+
+    let arrows = model.dependents(
+        CellReference {
+            sheet: 1,
+            row: 1,
+            column: 1,
+        },
+        None,
+    );
+    assert_eq!(
+        arrows.unwrap(),
+        vec![TraceArrow {
+            precedent: Area {
+                sheet: 1,
+                row: 1,
+                column: 1,
+                width: 1,
+                height: 1,
+            },
+            dependent: CellReferenceIndex {
+                sheet: 0,
+                row: 1,
+                column: 1,
+            },
+            depth: 1,
+        }]
+    );
+    let invalid = CellReference {
+        sheet: 5,
+        row: 1,
+        column: 1,
+    };
+    assert!(model.precedents(invalid, None).is_err());
+    assert!(model.dependents(invalid, None).is_err());
+}
@@ -0,0 +1,312 @@
+#![allow(clippy::unwrap_used)]
+
+use crate::test::util::new_empty_model;
+
+#[test]
+fn test_set_user_input() {
+    let mut model = new_empty_model();
+    assert!(!model.can_undo());
+    model._set("A1", "1");
+    model._set("A2", "=A1*2");
+    model._set("A1", "5");
+    model.evaluate();
+    assert_eq!(model._get_text("A2"), "10");
+
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "1");
+    assert_eq!(model._get_text("A2"), "2");
+    assert!(model.can_redo());
+
+    model.undo().unwrap();
+    model.evaluate();
+    assert!(!model._has_formula("A2"));
+    assert_eq!(model._get_text("A2"), "");
+
+    model.redo().unwrap();
+    model.redo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("A2"), "10");
+    assert!(!model.can_redo());
+
+    model.undo().unwrap();
+    model.undo().unwrap();
+    model.undo().unwrap();
+    assert!(!model.can_undo());
+    model.evaluate();
+    assert!(model.is_empty_cell(0, 1, 1).unwrap());
+
+    // Nothing to undo
+    model.undo().unwrap();
+}
+
+#[test]
+fn test_new_change_clears_redo() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A1", "2");
+    model.undo().unwrap();
+    assert!(model.can_redo());
+    model._set("A1", "3");
+    assert!(!model.can_redo());
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "1");
+}
+
+#[test]
+fn test_styles() {
+    let mut model = new_empty_model();
+    model._set("A1", "10");
+    let mut style = model.get_style_for_cell(0, 1, 1);
+    style.font.b = true;
+    model.set_cell_style(0, 1, 1, &style).unwrap();
+    assert!(model.get_style_for_cell(0, 1, 1).font.b);
+
+    model.undo().unwrap();
+    assert!(!model.get_style_for_cell(0, 1, 1).font.b);
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "10");
+
+    model.redo().unwrap();
+    assert!(model.get_style_for_cell(0, 1, 1).font.b);
+}
+
+#[test]
+fn test_rows() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model._set("A3", "3");
+    model._set("B1", "=SUM(A1:A3)");
+    model._set("C1", "=A2");
+    model.evaluate();
+
+    model.delete_rows(0, 2, 1).unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("C1"), "=#REF!");
+    assert_eq!(model._get_text("A2"), "3");
+
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("B1"), "=SUM(A1:A3)");
+    assert_eq!(model._get_formula("C1"), "=A2");
+    assert_eq!(model._get_text("A2"), "2");
+    assert_eq!(model._get_text("A3"), "3");
+    assert_eq!(model._get_text("C1"), "2");
+
+    model.insert_rows(0, 1, 2).unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("B3"), "=SUM(A3:A5)");
+
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("B1"), "=SUM(A1:A3)");
+    assert_eq!(model._get_text("B1"), "6");
+    assert!(model.is_empty_cell(0, 4, 1).unwrap());
+    assert!(model.is_empty_cell(0, 5, 1).unwrap());
+
+    model.redo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("B3"), "=SUM(A3:A5)");
+    assert_eq!(model._get_text("B3"), "6");
+}
+
+#[test]
+fn test_columns() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model._set("B1", "2");
+    model._set("A2", "=A1+B1");
+    model.insert_columns(0, 2, 1).unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("A2"), "=A1+C1");
+
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("A2"), "=A1+B1");
+    assert_eq!(model._get_text("A2"), "3");
+    assert!(model.is_empty_cell(0, 1, 3).unwrap());
+}
+
+#[test]
+fn test_sheets() {
+    let mut model = new_empty_model();
+    model._set("A1", "=Sheet2!A1");
+    model.add_sheet("Sheet2").unwrap();
+    model._set("Sheet2!A1", "42");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "42");
+
+    model.rename_sheet("Sheet2", "Data").unwrap();
+    assert_eq!(model._get_formula("A1"), "=Data!A1");
+    model.undo().unwrap();
+    assert_eq!(model.workbook.get_worksheet_names(), ["Sheet1", "Sheet2"]);
+    assert_eq!(model._get_formula("A1"), "=Sheet2!A1");
+
+    model.delete_sheet(1).unwrap();
+    assert_eq!(model._get_text("A1"), "#REF!");
+    model.undo().unwrap();
+    assert_eq!(model._get_text("A1"), "42");
+
+    // Undo the value in the new sheet and the sheet itself
+    model.undo().unwrap();
+    model.undo().unwrap();
+    assert_eq!(model.workbook.get_worksheet_names(), ["Sheet1"]);
+
+    model.redo().unwrap();
+    model.redo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "42");
+}
+
+#[test]
+fn test_formulas_in_new_sheet() {
+    let mut model = new_empty_model();
+    model.new_sheet();
+    model._set("Sheet2!A1", "=1+1");
+    model.undo().unwrap();
+    model.undo().unwrap();
+    model.redo().unwrap();
+    model.redo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("Sheet2!A1"), "2");
+}
+
+#[test]
+fn test_defined_names() {
+    let mut model = new_empty_model();
+    model._set("A1", "10");
+    model
+        .new_defined_name("Price", None, "=Sheet1!$A$1")
+        .unwrap();
+    model._set("B1", "=Price*2");
+    model
+        .update_defined_name("Price", None, "Cost", None, "=Sheet1!$A$1")
+        .unwrap();
+    assert_eq!(model._get_formula("B1"), "=Cost*2");
+
+    model.undo().unwrap();
+    assert_eq!(model._get_formula("B1"), "=Price*2");
+    assert_eq!(model._get_text("B1"), "20");
+
+    model.undo().unwrap();
+    model.undo().unwrap();
+    model.evaluate();
+    assert!(model.get_defined_name_list().is_empty());
+
+    model.redo().unwrap();
+    model.redo().unwrap();
+    model.redo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("B1"), "=Cost*2");
+    assert_eq!(model._get_text("B1"), "20");
+}
+
+#[test]
+fn test_transactions() {
+    let mut model = new_empty_model();
+    model._set("A1", "1");
+    model.begin_transaction();
+    model._set("A1", "2");
+    model._set("A2", "3");
+    model.set_column_width(0, 1, 200.0).unwrap();
+    model.end_transaction();
+    model.evaluate();
+
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "1");
+    assert!(model.is_empty_cell(0, 2, 1).unwrap());
+    let worksheet = model.workbook.worksheet(0).unwrap();
+    assert!(worksheet.column_width(1).unwrap() != 200.0);
+    assert!(model.can_undo());
+
+    model.redo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "2");
+    assert_eq!(model._get_text("A2"), "3");
+    let worksheet = model.workbook.worksheet(0).unwrap();
+    assert_eq!(worksheet.column_width(1).unwrap(), 200.0);
+}
+
+#[test]
+fn test_sheet_properties() {
+    let mut model = new_empty_model();
+    model.set_row_height(0, 3, 40.0).unwrap();
+    model.set_frozen_rows(0, 2).unwrap();
+    model.set_sheet_color(0, "#FF0000").unwrap();
+
+    model.undo().unwrap();
+    model.undo().unwrap();
+    let worksheet = model.workbook.worksheet(0).unwrap();
+    assert_eq!(worksheet.color, None);
+    assert_eq!(worksheet.frozen_rows, 0);
+    assert_eq!(worksheet.row_height(3).unwrap(), 40.0);
+
+    model.undo().unwrap();
+    assert!(model.workbook.worksheet(0).unwrap().row_height(3).unwrap() != 40.0);
+    assert!(!model.can_undo());
+}
+
+#[test]
+fn test_failed_change_is_not_recorded() {
+    let mut model = new_empty_model();
+    assert!(model.set_sheet_color(0, "red").is_err());
+    assert!(model.delete_rows(0, 1, 0).is_err());
+    assert!(!model.can_undo());
+}
+
+#[test]
+fn test_rows_record_rewritten_formulas() {
+    let mut model = new_empty_model();
+    model.new_sheet();
+    model._set("A1", "1");
+    model._set("A2", "2");
+    model._set("C1", "=A2*10");
+    model._set("Sheet2!A1", "=Sheet1!A2+1");
+    model._set("Sheet2!A2", "=A1*2");
+    model._set("Sheet2!A3", "=Sheet1!A1");
+    model.evaluate();
+
+    model.insert_rows(0, 2, 1).unwrap();
+    // Only the cells that moved and the formulas pointing below the new row
+    let mut cells = model.history.last_changed_cells();
+    cells.sort();
+    assert_eq!(cells, vec![(0, 1, 3), (0, 2, 1), (0, 3, 1), (1, 1, 1)]);
+    model.evaluate();
+    assert_eq!(model._get_formula("C1"), "=A3*10");
+    assert_eq!(model._get_formula("Sheet2!A1"), "=Sheet1!A3+1");
+    assert_eq!(model._get_text("Sheet2!A1"), "3");
+
+    model.undo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("C1"), "=A2*10");
+    assert_eq!(model._get_formula("Sheet2!A1"), "=Sheet1!A2+1");
+    assert_eq!(model._get_formula("Sheet2!A2"), "=A1*2");
+    assert_eq!(model._get_text("Sheet2!A2"), "6");
+    assert_eq!(model._get_text("C1"), "20");
+
+    model.redo().unwrap();
+    model.evaluate();
+    assert_eq!(model._get_formula("C1"), "=A3*10");
+    assert_eq!(model._get_formula("Sheet2!A1"), "=Sheet1!A3+1");
+    assert_eq!(model._get_text("C1"), "20");
+}
+
+#[test]
+fn test_currency() {
+    let mut model = new_empty_model();
+    model.set_currency("EUR").unwrap();
+    assert_eq!(model.locale.currency.symbol, "€");
+
+    model.undo().unwrap();
+    assert_eq!(model.locale.currency.iso, "USD");
+    assert_eq!(model.locale.currency.symbol, "$");
+
+    model.redo().unwrap();
+    assert_eq!(model.locale.currency.iso, "EUR");
+    model._set("A1", "=PMT(8/1200,10,10000)");
+    model.evaluate();
+    assert_eq!(model._get_text("A1"), "-€1,037.03");
+}
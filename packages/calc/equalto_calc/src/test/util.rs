@@ -8,6 +8,15 @@ pub fn new_empty_model() -> Model {
     Model::new_empty("model", "en", "UTC").unwrap()
 }
 
+/// A cell of the first sheet
+pub fn cell(row: i32, column: i32) -> CellReference {
+    CellReference {
+        sheet: 0,
+        row,
+        column,
+    }
+}
+
 impl Model {
     fn _parse_reference(&self, cell: &str) -> CellReference {
         if cell.contains('!') {
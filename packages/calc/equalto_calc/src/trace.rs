@@ -0,0 +1,135 @@
+//! Precedents and dependents of the formulas, as shown by trace arrows.
+//!
+//! The references are the ones in the dependency graph: defined names and tables are resolved
+//! to the cells they refer to, and INDIRECT and OFFSET contribute the references they returned
+//! in the last evaluation.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    calc_result::{CellReference, Range},
+    dependencies::CellKey,
+    expressions::types::{Area, CellReferenceIndex},
+    model::Model,
+};
+
+/// An arrow from a cell or range to a formula that reads it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TraceArrow {
+    pub precedent: Area,
+    pub dependent: CellReferenceIndex,
+    /// 1 for the arrows that start or end in the traced cell, 2 for the next level and so on
+    pub depth: usize,
+}
+
+impl TraceArrow {
+    fn new(precedent: &Range, dependent: CellKey, depth: usize) -> TraceArrow {
+        let (sheet, row, column) = dependent;
+        TraceArrow {
+            precedent: Area {
+                sheet: precedent.left.sheet,
+                row: precedent.left.row,
+                column: precedent.left.column,
+                width: precedent.right.column - precedent.left.column + 1,
+                height: precedent.right.row - precedent.left.row + 1,
+            },
+            dependent: CellReferenceIndex { sheet, row, column },
+            depth,
+        }
+    }
+}
+
+impl Model {
+    /// Returns the arrows to `cell` from the cells and ranges its formula reads, and then
+    /// the arrows to the formulas found in those, up to `depth` levels (all of them if None).
+    /// Every formula is expanded once, so circular references are traced only once.
+    pub fn precedents(
+        &self,
+        cell: CellReference,
+        depth: Option<usize>,
+    ) -> Result<Vec<TraceArrow>, String> {
+        self.workbook.worksheet(cell.sheet)?;
+        let mut arrows = Vec::new();
+        let mut visited = HashSet::from([(cell.sheet, cell.row, cell.column)]);
+        let mut level = vec![cell];
+        let mut current_depth = 1;
+        while !level.is_empty() && depth.map_or(true, |depth| current_depth <= depth) {
+            let mut next_level = Vec::new();
+            for formula in level {
+                let key = (formula.sheet, formula.row, formula.column);
+                let ranges = match self.dependency_graph.get_precedents(key) {
+                    Some(ranges) => ranges,
+                    None => continue,
+                };
+                for range in ranges {
+                    if (range.left.sheet as usize) >= self.workbook.worksheets.len() {
+                        continue;
+                    }
+                    let arrow = TraceArrow::new(&range, key, current_depth);
+                    if !arrows.contains(&arrow) {
+                        arrows.push(arrow);
+                    }
+                    for precedent in self.get_formulas_in_range(&range) {
+                        if visited.insert((precedent.sheet, precedent.row, precedent.column)) {
+                            next_level.push(precedent);
+                        }
+                    }
+                }
+            }
+            level = next_level;
+            current_depth += 1;
+        }
+        Ok(arrows)
+    }
+
+    /// Returns the arrows from `cell` to the formulas that read it, and then the arrows from
+    /// those to the formulas that read them, up to `depth` levels (all of them if None).
+    /// The arrows from a formula that spills start in its whole spill area.
+    pub fn dependents(
+        &self,
+        cell: CellReference,
+        depth: Option<usize>,
+    ) -> Result<Vec<TraceArrow>, String> {
+        self.workbook.worksheet(cell.sheet)?;
+        let mut arrows = Vec::new();
+        let mut visited = HashSet::from([(cell.sheet, cell.row, cell.column)]);
+        let mut level = vec![(cell.sheet, cell.row, cell.column)];
+        let mut current_depth = 1;
+        while !level.is_empty() && depth.map_or(true, |depth| current_depth <= depth) {
+            let mut next_level = Vec::new();
+            for key in level {
+                let (sheet, row, column) = key;
+                let reference = CellReference { sheet, row, column };
+                let area = match self.dependency_graph.get_spill_area(key) {
+                    Some(area) if self.has_spilled(reference, area) => area.clone(),
+                    _ => Range {
+                        left: reference,
+                        right: reference,
+                    },
+                };
+                for dependent in self.dependency_graph.get_dependents(&area) {
+                    if (dependent.0 as usize) >= self.workbook.worksheets.len() {
+                        continue;
+                    }
+                    arrows.push(TraceArrow::new(&area, dependent, current_depth));
+                    if visited.insert(dependent) {
+                        next_level.push(dependent);
+                    }
+                }
+            }
+            level = next_level;
+            current_depth += 1;
+        }
+        Ok(arrows)
+    }
+
+    /// Returns true if the formula in `anchor` spilled its values into `area`
+    fn has_spilled(&self, anchor: CellReference, area: &Range) -> bool {
+        self.workbook.worksheets[anchor.sheet as usize]
+            .cell(area.right.row, area.right.column)
+            .and_then(|cell| cell.get_spill_anchor())
+            == Some((anchor.row, anchor.column))
+    }
+}
@@ -43,11 +43,64 @@ pub struct Metadata {
     pub last_modified: String, //"2020-11-20T16:24:35"
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct WorkbookSettings {
     pub tz: String,
     pub locale: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_default_calculation")]
+    pub calculation: CalculationSettings,
+}
+
+fn is_default_calculation(c: &CalculationSettings) -> bool {
+    c == &CalculationSettings::default()
+}
+
+/// How formulas are recalculated.
+/// With iterative calculation circular references are allowed: the formulas in a cycle are
+/// recomputed until no value changes by more than `max_change` or `max_iterations` is reached.
+// ECMA-376-1:2016 section 18.2.2 (calcPr)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct CalculationSettings {
+    pub iterative: bool,
+    pub max_iterations: i32,
+    pub max_change: f64,
+    #[serde(default)]
+    pub reference_style: ReferenceStyle,
+}
+
+impl Default for CalculationSettings {
+    fn default() -> Self {
+        CalculationSettings {
+            iterative: false,
+            max_iterations: 100,
+            max_change: 0.001,
+            reference_style: ReferenceStyle::A1,
+        }
+    }
+}
+
+/// How users write and read references in formulas.
+/// It only changes how formulas are entered and shown, not how they are stored.
+// ECMA-376-1:2016 section 18.18.67 (ST_RefMode)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ReferenceStyle {
+    /// References like `B3` or `$A$1:$A$10`
+    #[default]
+    A1,
+    /// References like `R[-1]C` or `R1C1:R10C1`, relative to the cell the formula is in
+    R1C1,
+}
+
+impl Display for ReferenceStyle {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ReferenceStyle::A1 => write!(formatter, "A1"),
+            ReferenceStyle::R1C1 => write!(formatter, "R1C1"),
+        }
+    }
 }
+
 /// An internal representation of an EqualTo Workbook
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 #[serde(deny_unknown_fields)]
@@ -62,6 +115,44 @@ pub struct Workbook {
     #[serde(default)]
     #[serde(skip_serializing_if = "hashmap_is_empty")]
     pub tables: HashMap<String, Table>,
+    /// The workbooks referenced in formulas, with the values they had when last read
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub external_links: Vec<ExternalLink>,
+}
+
+/// Another workbook referenced in formulas: `[Rates.xlsx]Sheet1!$B$2`
+// ECMA-376-1:2016 section 18.14.7 (externalBook)
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ExternalLink {
+    /// The workbook as written in formulas, with its path if any: `C:\models\fx.xlsx`
+    pub workbook: String,
+    pub sheets: Vec<ExternalSheet>,
+}
+
+/// The cached values of a sheet of another workbook
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ExternalSheet {
+    pub name: String,
+    pub cells: Vec<ExternalCell>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct ExternalCell {
+    pub row: i32,
+    pub column: i32,
+    pub value: ExternalValue,
+}
+
+/// The value of a cell of another workbook
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum ExternalValue {
+    Empty,
+    Number(f64),
+    String(String),
+    Boolean(bool),
+    Error(Error),
 }
 
 /// A defined name. The `sheet_id` is the sheet index in case the name is local
@@ -71,6 +162,10 @@ pub struct DefinedName {
     pub formula: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sheet_id: Option<u32>,
+    /// Hidden names, like the ones Excel uses to store the Solver settings, are not listed
+    #[serde(default = "default_as_false")]
+    #[serde(skip_serializing_if = "is_false")]
+    pub hidden: bool,
 }
 
 // TODO: Move to worksheet.rs make frozen_rows/columns private and u32
@@ -111,6 +206,14 @@ pub struct Worksheet {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub color: Option<String>,
     pub merge_cells: Vec<String>,
+    /// Ranges of the legacy (Ctrl+Shift+Enter) array formulas in the sheet, like "B1:B3".
+    /// The formula is in the top left cell and each cell of the range holds an element of its value.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub array_formulas: Vec<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub data_tables: Vec<DataTable>,
     pub comments: Vec<Comment>,
     #[serde(default)]
     #[serde(skip_serializing_if = "is_zero")]
@@ -120,6 +223,25 @@ pub struct Worksheet {
     pub frozen_columns: i32,
 }
 
+/// A what-if data table (Data > What-If Analysis > Data Table in Excel).
+/// The cells in `range` hold the values of a formula when the input cells take the values
+/// in the row above and/or the column to the left of the range.
+// ECMA-376-1:2016 section 18.3.1.40
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DataTable {
+    /// The result cells, like "C3:C5"
+    pub range: String,
+    /// The input cell. In a two-variable data table, the row input cell
+    pub r1: String,
+    /// The column input cell of a two-variable data table
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r2: Option<String>,
+    /// True for two-variable data tables
+    pub dt2d: bool,
+    /// True if the input values of a one-variable data table are in a row
+    pub dtr: bool,
+}
+
 /// Internal representation of Excel's sheet_data
 /// It is row first and because of this all of our API's should be row first
 pub type SheetData = HashMap<i32, HashMap<i32, Cell>>;
@@ -199,7 +321,16 @@ pub enum Cell {
         // Error Message: "Not implemented function"
         m: String,
     },
-    // TODO: Array formulas
+    // Values spilled by a dynamic array formula.
+    // The formula is in row `r`, column `c` of the same sheet
+    #[serde(rename = "sb")]
+    SpillBoolean { v: bool, r: i32, c: i32, s: i32 },
+    #[serde(rename = "sn")]
+    SpillNumber { v: f64, r: i32, c: i32, s: i32 },
+    #[serde(rename = "ss")]
+    SpillString { v: String, r: i32, c: i32, s: i32 },
+    #[serde(rename = "se")]
+    SpillError { ei: Error, r: i32, c: i32, s: i32 },
 }
 
 impl Default for Cell {
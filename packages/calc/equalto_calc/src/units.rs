@@ -277,13 +277,22 @@ impl Model {
             Node::StringKind(_) => None,
             Node::WrongReferenceKind { .. } => None,
             Node::WrongRangeKind { .. } => None,
+            Node::ExternalReferenceKind { .. } => None,
+            Node::ExternalRangeKind { .. } => None,
+            Node::SheetSpanReferenceKind { .. } => None,
+            Node::SheetSpanRangeKind { .. } => None,
             Node::OpRangeKind { .. } => None,
+            Node::OpIntersectKind { .. } => None,
+            Node::OpUnionKind(_) => None,
+            Node::TableReferenceKind { .. } => None,
             Node::OpConcatenateKind { .. } => None,
             Node::ErrorKind(_) => None,
             Node::ParseErrorKind { .. } => None,
             Node::EmptyArgKind => None,
             Node::InvalidFunctionKind { .. } => None,
+            Node::CallKind { .. } => None,
             Node::ArrayKind(_) => None,
+            Node::SpillRangeKind(anchor) => self.compute_node_units(anchor, cell),
             Node::VariableKind(_) => None,
             Node::CompareKind { .. } => None,
             Node::OpPowerKind { .. } => None,
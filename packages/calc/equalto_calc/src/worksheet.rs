@@ -1,6 +1,6 @@
 use crate::constants::{self, LAST_COLUMN, LAST_ROW};
 use crate::expressions::types::CellReferenceIndex;
-use crate::expressions::utils::{is_valid_column_number, is_valid_row};
+use crate::expressions::utils::{is_valid_column_number, is_valid_row, parse_reference_a1};
 use crate::{expressions::token::Error, types::*};
 
 use std::collections::HashMap;
@@ -42,7 +42,7 @@ impl Worksheet {
         self.sheet_data.get_mut(&row)?.get_mut(&column)
     }
 
-    fn update_cell(&mut self, row: i32, column: i32, new_cell: Cell) {
+    pub(crate) fn update_cell(&mut self, row: i32, column: i32, new_cell: Cell) {
         match self.sheet_data.get_mut(&row) {
             Some(column_data) => match column_data.get(&column) {
                 Some(_cell) => {
@@ -84,6 +84,20 @@ impl Worksheet {
         0
     }
 
+    /// Returns the last row and column of the legacy array formula in (`row`, `column`), if any
+    pub fn get_array_formula_area(&self, row: i32, column: i32) -> Option<(i32, i32)> {
+        self.array_formulas.iter().find_map(|range| {
+            let (first, last) = range.split_once(':').unwrap_or((range, range));
+            let first = parse_reference_a1(first)?;
+            let last = parse_reference_a1(last)?;
+            if first.row == row && first.column == column {
+                Some((last.row, last.column))
+            } else {
+                None
+            }
+        })
+    }
+
     pub fn get_style(&self, row: i32, column: i32) -> i32 {
         match self.sheet_data.get(&row) {
             Some(column_data) => match column_data.get(&column) {
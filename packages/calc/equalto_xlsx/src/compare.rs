@@ -140,6 +140,54 @@ pub fn compare(model1: &Model, model2: &Model) -> CompareResult<Vec<Diff>> {
                     });
                 }
             }
+            (Cell::SpillNumber { v: value1, .. }, Cell::SpillNumber { v: value2, .. }) => {
+                if !numbers_are_close(*value1, *value2, eps) {
+                    diffs.push(Diff {
+                        sheet_name: ws1[cell.index as usize].clone(),
+                        row,
+                        column,
+                        value1: cell1.clone(),
+                        value2: cell2.clone(),
+                        reason: "Numbers are different".to_string(),
+                    });
+                }
+            }
+            (Cell::SpillString { v: value1, .. }, Cell::SpillString { v: value2, .. }) => {
+                if value1 != value2 {
+                    diffs.push(Diff {
+                        sheet_name: ws1[cell.index as usize].clone(),
+                        row,
+                        column,
+                        value1: cell1.clone(),
+                        value2: cell2.clone(),
+                        reason: "Strings are different".to_string(),
+                    });
+                }
+            }
+            (Cell::SpillBoolean { v: value1, .. }, Cell::SpillBoolean { v: value2, .. }) => {
+                if value1 != value2 {
+                    diffs.push(Diff {
+                        sheet_name: ws1[cell.index as usize].clone(),
+                        row,
+                        column,
+                        value1: cell1.clone(),
+                        value2: cell2.clone(),
+                        reason: "Booleans are different".to_string(),
+                    });
+                }
+            }
+            (Cell::SpillError { ei: index1, .. }, Cell::SpillError { ei: index2, .. }) => {
+                if index1 != index2 {
+                    diffs.push(Diff {
+                        sheet_name: ws1[cell.index as usize].clone(),
+                        row,
+                        column,
+                        value1: cell1.clone(),
+                        value2: cell2.clone(),
+                        reason: "Errors are different".to_string(),
+                    });
+                }
+            }
             (_, _) => {
                 diffs.push(Diff {
                     sheet_name: ws1[cell.index as usize].clone(),
@@ -0,0 +1,204 @@
+//! # External links
+//! Each workbook referenced in formulas has a part with the names of its sheets and the values
+//! of the cells read from it when the file was saved:
+//!
+//! <externalLink>
+//!   <externalBook r:id="rId1">
+//!     <sheetNames><sheetName val="Sheet1"/></sheetNames>
+//!     <sheetDataSet>
+//!       <sheetData sheetId="0">
+//!         <row r="2"><cell r="B2"><v>1.5</v></cell></row>
+//!       </sheetData>
+//!     </sheetDataSet>
+//!   </externalBook>
+//! </externalLink>
+//!
+//! The path of the workbook goes in the relationships of the part, and formulas refer to the
+//! link by its position, starting at 1: `[1]Sheet1!$B$2`.
+
+use std::collections::BTreeMap;
+
+use equalto_calc::{
+    expressions::{
+        ast::{collect_references, walk_mut, Node, VisitorMut},
+        parser::stringify::to_string,
+        types::CellReferenceRC,
+        utils::number_to_column,
+    },
+    model::{Model, ParsedDefinedName},
+    types::{ExternalLink, ExternalSheet, ExternalValue},
+};
+
+use super::{escape::escape_xml, xml_constants::XML_DECLARATION};
+
+/// Returns the links to other workbooks: the ones of the workbook followed by any other workbook
+/// or sheet referenced in formulas
+pub(crate) fn get_external_links(model: &Model) -> Vec<ExternalLink> {
+    let mut external_links = model.workbook.external_links.clone();
+    let mut defined_names: Vec<_> = model.parsed_defined_names.iter().collect();
+    defined_names.sort_by_key(|(key, _)| *key);
+    let defined_names =
+        defined_names
+            .into_iter()
+            .filter_map(|(_, defined_name)| match defined_name {
+                ParsedDefinedName::Formula(node) => Some(node),
+                _ => None,
+            });
+    for node in model.parsed_formulas.iter().flatten().chain(defined_names) {
+        for reference in collect_references(node) {
+            if let Node::ExternalReferenceKind {
+                workbook,
+                sheet_name,
+                ..
+            }
+            | Node::ExternalRangeKind {
+                workbook,
+                sheet_name,
+                ..
+            } = reference
+            {
+                let index = match external_links
+                    .iter()
+                    .position(|link| &link.workbook == workbook)
+                {
+                    Some(index) => index,
+                    None => {
+                        external_links.push(ExternalLink {
+                            workbook: workbook.clone(),
+                            sheets: Vec::new(),
+                        });
+                        external_links.len() - 1
+                    }
+                };
+                let sheets = &mut external_links[index].sheets;
+                if !sheets
+                    .iter()
+                    .any(|sheet| sheet.name.to_lowercase() == sheet_name.to_lowercase())
+                {
+                    sheets.push(ExternalSheet {
+                        name: sheet_name.clone(),
+                        cells: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+    external_links
+}
+
+struct ExternalLinkPositions<'a>(&'a [ExternalLink]);
+
+impl VisitorMut for ExternalLinkPositions<'_> {
+    fn enter(&mut self, node: &mut Node) -> bool {
+        if let Node::ExternalReferenceKind { workbook, .. }
+        | Node::ExternalRangeKind { workbook, .. } = node
+        {
+            if let Some(index) = self.0.iter().position(|link| &link.workbook == workbook) {
+                *workbook = format!("{}", index + 1);
+            }
+        }
+        true
+    }
+}
+
+/// Returns `node` with the workbooks it references replaced by the positions of their links
+pub(crate) fn number_external_links(node: &Node, external_links: &[ExternalLink]) -> Node {
+    let mut node = node.clone();
+    walk_mut(&mut node, &mut ExternalLinkPositions(external_links));
+    node
+}
+
+/// Returns the formula of a defined name with the positions of the links to other workbooks
+/// and the cells of the structured references
+pub(crate) fn number_external_links_in_defined_name(
+    model: &Model,
+    formula: &str,
+    external_links: &[ExternalLink],
+) -> String {
+    if (external_links.is_empty() && model.workbook.tables.is_empty()) || !formula.contains('[') {
+        return formula.to_string();
+    }
+    let context = CellReferenceRC {
+        sheet: model.workbook.worksheets[0].get_name(),
+        row: 1,
+        column: 1,
+    };
+    let mut parser = model.parser.clone();
+    let node = parser.parse(formula, &Some(context.clone()));
+    if matches!(node, Node::ParseErrorKind { .. }) {
+        return formula.to_string();
+    }
+    let node = model.replace_table_references(&node);
+    to_string(&number_external_links(&node, external_links), &context)
+}
+
+pub(crate) fn get_external_link_xml(external_link: &ExternalLink) -> String {
+    let sheet_names: String = external_link
+        .sheets
+        .iter()
+        .map(|sheet| format!("<sheetName val=\"{}\"/>", escape_xml(&sheet.name)))
+        .collect();
+    let mut sheet_data_set = String::new();
+    for (sheet_id, sheet) in external_link.sheets.iter().enumerate() {
+        let mut rows: BTreeMap<i32, Vec<String>> = BTreeMap::new();
+        let mut cells: Vec<_> = sheet.cells.iter().collect();
+        cells.sort_by_key(|cell| (cell.row, cell.column));
+        for cell in cells {
+            let column = match number_to_column(cell.column) {
+                Some(column) => column,
+                None => continue,
+            };
+            let cell_name = format!("{column}{}", cell.row);
+            let cell_str = match &cell.value {
+                ExternalValue::Empty => continue,
+                ExternalValue::Number(value) => {
+                    format!("<cell r=\"{cell_name}\"><v>{value}</v></cell>")
+                }
+                ExternalValue::String(value) => format!(
+                    "<cell r=\"{cell_name}\" t=\"str\"><v>{}</v></cell>",
+                    escape_xml(value)
+                ),
+                ExternalValue::Boolean(value) => format!(
+                    "<cell r=\"{cell_name}\" t=\"b\"><v>{}</v></cell>",
+                    i32::from(*value)
+                ),
+                ExternalValue::Error(error) => {
+                    format!("<cell r=\"{cell_name}\" t=\"e\"><v>{error}</v></cell>")
+                }
+            };
+            rows.entry(cell.row).or_default().push(cell_str);
+        }
+        let rows: String = rows
+            .iter()
+            .map(|(row, cells)| format!("<row r=\"{row}\">{}</row>", cells.join("")))
+            .collect();
+        sheet_data_set.push_str(&format!(
+            "<sheetData sheetId=\"{sheet_id}\">{rows}</sheetData>"
+        ));
+    }
+    format!("{XML_DECLARATION}\n\
+    <externalLink xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\" xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\">\
+      <externalBook r:id=\"rId1\">\
+        <sheetNames>{sheet_names}</sheetNames>\
+        <sheetDataSet>{sheet_data_set}</sheetDataSet>\
+      </externalBook>\
+    </externalLink>")
+}
+
+pub(crate) fn get_external_link_rels_xml(external_link: &ExternalLink) -> String {
+    let workbook = &external_link.workbook;
+    // Absolute paths are written as URIs: file:///C:\models\fx.xlsx
+    let target = if workbook.starts_with('/') {
+        format!("file://{workbook}")
+    } else if workbook.get(1..3) == Some(":\\") {
+        format!("file:///{workbook}")
+    } else {
+        workbook.to_string()
+    };
+    format!(
+        "{XML_DECLARATION}\n<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+        <Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/externalLinkPath\" Target=\"{}\" TargetMode=\"External\"/>\
+        </Relationships>",
+        escape_xml(&target)
+    )
+}
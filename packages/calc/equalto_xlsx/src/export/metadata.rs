@@ -0,0 +1,55 @@
+use equalto_calc::{
+    expressions::{ast::add_implicit_intersections, parser::Node},
+    model::Model,
+};
+
+use super::xml_constants::XML_DECLARATION;
+
+/// Returns true if versions of Excel without dynamic arrays would reduce some range in `node` to
+/// a single cell. Those formulas need to be flagged as dynamic array formulas.
+pub(crate) fn needs_dynamic_array_flag(node: &Node) -> bool {
+    let mut legacy_node = node.clone();
+    add_implicit_intersections(&mut legacy_node);
+    legacy_node != *node
+}
+
+/// Returns true if any formula in the workbook spills its values into neighbouring cells
+/// or needs to be flagged as a dynamic array formula
+pub(crate) fn has_dynamic_arrays(model: &Model) -> bool {
+    let spills = model.workbook.worksheets.iter().any(|worksheet| {
+        worksheet
+            .sheet_data
+            .values()
+            .flat_map(|row_data| row_data.values())
+            .filter_map(|cell| cell.get_spill_anchor())
+            .any(|(row, column)| worksheet.get_array_formula_area(row, column).is_none())
+    });
+    spills
+        || model
+            .parsed_formulas
+            .iter()
+            .flatten()
+            .any(needs_dynamic_array_flag)
+}
+
+/// Cell metadata flagging dynamic array formulas. Cells that refer to it have the attribute cm="1"
+pub(crate) fn get_metadata_xml() -> String {
+    format!(
+        "{XML_DECLARATION}
+<metadata \
+xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\" \
+xmlns:xda=\"http://schemas.microsoft.com/office/spreadsheetml/2017/dynamicarray\">\
+<metadataTypes count=\"1\">\
+<metadataType name=\"XLDAPR\" minSupportedVersion=\"120000\" copy=\"1\" pasteAll=\"1\" pasteValues=\"1\" \
+merge=\"1\" splitFirst=\"1\" rowColShift=\"1\" clearFormats=\"1\" clearComments=\"1\" assign=\"1\" \
+coerce=\"1\" cellMeta=\"1\"/>\
+</metadataTypes>\
+<futureMetadata name=\"XLDAPR\" count=\"1\">\
+<bk><extLst><ext uri=\"{{bdbb8cdc-fa1e-496e-a857-3c3f30c029c3}}\">\
+<xda:dynamicArrayProperties fDynamic=\"1\" fCollapsed=\"0\"/>\
+</ext></extLst></bk>\
+</futureMetadata>\
+<cellMetadata count=\"1\"><bk><rc t=\"1\" v=\"0\"/></bk></cellMetadata>\
+</metadata>"
+    )
+}
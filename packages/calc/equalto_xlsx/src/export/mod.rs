@@ -1,6 +1,8 @@
 mod _rels;
 mod doc_props;
 mod escape;
+mod external_links;
+mod metadata;
 mod shared_strings;
 mod styles;
 mod workbook;
@@ -8,6 +10,7 @@ mod workbook_xml_rels;
 mod worksheets;
 mod xml_constants;
 
+use std::borrow::Cow;
 use std::io::BufWriter;
 use std::{
     fs,
@@ -16,7 +19,7 @@ use std::{
 
 use equalto_calc::expressions::utils::number_to_column;
 use equalto_calc::model::{get_milliseconds_since_epoch, Model};
-use equalto_calc::types::Workbook;
+use equalto_calc::types::{ExternalLink, Workbook};
 
 use self::xml_constants::XML_DECLARATION;
 
@@ -25,7 +28,7 @@ use crate::error::XlsxError;
 #[cfg(test)]
 mod test;
 
-fn get_content_types_xml(workbook: &Workbook) -> String {
+fn get_content_types_xml(model: &Model, external_links: &[ExternalLink]) -> String {
     // A list of all files in the zip
     let mut content = vec![
         r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#.to_string(),
@@ -33,13 +36,19 @@ fn get_content_types_xml(workbook: &Workbook) -> String {
         r#"<Default Extension="xml" ContentType="application/xml"/>"#.to_string(),
         r#"<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#.to_string(),
     ];
-    for worksheet in 0..workbook.worksheets.len() {
+    for worksheet in 0..model.workbook.worksheets.len() {
         let sheet = format!(
             r#"<Override PartName="/xl/worksheets/sheet{}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#,
             worksheet + 1
         );
         content.push(sheet);
     }
+    for link in 0..external_links.len() {
+        content.push(format!(
+            r#"<Override PartName="/xl/externalLinks/externalLink{}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.externalLink+xml"/>"#,
+            link + 1
+        ));
+    }
     // we skip the theme and calcChain
     // r#"<Override PartName="/xl/theme/theme1.xml" ContentType="application/vnd.openxmlformats-officedocument.theme+xml"/>"#,
     // r#"<Override PartName="/xl/calcChain.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.calcChain+xml"/>"#,
@@ -48,8 +57,11 @@ fn get_content_types_xml(workbook: &Workbook) -> String {
         r#"<Override PartName="/xl/sharedStrings.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sharedStrings+xml"/>"#.to_string(),
         r#"<Override PartName="/docProps/core.xml" ContentType="application/vnd.openxmlformats-package.core-properties+xml"/>"#.to_string(),
         r#"<Override PartName="/docProps/app.xml" ContentType="application/vnd.openxmlformats-officedocument.extended-properties+xml"/>"#.to_string(),
-        r#"</Types>"#.to_string(),
     ]);
+    if metadata::has_dynamic_arrays(model) {
+        content.push(r#"<Override PartName="/xl/metadata.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheetMetadata+xml"/>"#.to_string());
+    }
+    content.push(r#"</Types>"#.to_string());
     format!("{XML_DECLARATION}\n{}", content.join(""))
 }
 
@@ -68,6 +80,7 @@ pub fn save_to_xlsx(model: &Model, file_name: &str) -> Result<(), XlsxError> {
 
 pub fn save_xlsx_to_writer<W: Write + Seek>(model: &Model, writer: W) -> Result<W, XlsxError> {
     let workbook = &model.workbook;
+    let external_links = external_links::get_external_links(model);
     let mut zip = zip::ZipWriter::new(writer);
 
     let options =
@@ -75,7 +88,7 @@ pub fn save_xlsx_to_writer<W: Write + Seek>(model: &Model, writer: W) -> Result<
 
     // root folder
     zip.start_file("[Content_Types].xml", options)?;
-    zip.write_all(get_content_types_xml(workbook).as_bytes())?;
+    zip.write_all(get_content_types_xml(model, &external_links).as_bytes())?;
 
     zip.add_directory("docProps", options)?;
     zip.start_file("docProps/app.xml", options)?;
@@ -95,11 +108,30 @@ pub fn save_xlsx_to_writer<W: Write + Seek>(model: &Model, writer: W) -> Result<
     zip.start_file("xl/styles.xml", options)?;
     zip.write_all(styles::get_styles_xml(workbook).as_bytes())?;
     zip.start_file("xl/workbook.xml", options)?;
-    zip.write_all(workbook::get_workbook_xml(workbook).as_bytes())?;
+    zip.write_all(workbook::get_workbook_xml(model, &external_links).as_bytes())?;
+    if metadata::has_dynamic_arrays(model) {
+        zip.start_file("xl/metadata.xml", options)?;
+        zip.write_all(metadata::get_metadata_xml().as_bytes())?;
+    }
 
     zip.add_directory("xl/_rels", options)?;
     zip.start_file("xl/_rels/workbook.xml.rels", options)?;
-    zip.write_all(workbook_xml_rels::get_workbook_xml_rels(workbook).as_bytes())?;
+    zip.write_all(workbook_xml_rels::get_workbook_xml_rels(model, &external_links).as_bytes())?;
+
+    if !external_links.is_empty() {
+        zip.add_directory("xl/externalLinks", options)?;
+        zip.add_directory("xl/externalLinks/_rels", options)?;
+    }
+    for (index, external_link) in external_links.iter().enumerate() {
+        let id = index + 1;
+        zip.start_file(format!("xl/externalLinks/externalLink{id}.xml"), options)?;
+        zip.write_all(external_links::get_external_link_xml(external_link).as_bytes())?;
+        zip.start_file(
+            format!("xl/externalLinks/_rels/externalLink{id}.xml.rels"),
+            options,
+        )?;
+        zip.write_all(external_links::get_external_link_rels_xml(external_link).as_bytes())?;
+    }
 
     zip.add_directory("xl/worksheets", options)?;
     for (sheet_index, worksheet) in workbook.worksheets.iter().enumerate() {
@@ -115,13 +147,25 @@ pub fn save_xlsx_to_writer<W: Write + Seek>(model: &Model, writer: W) -> Result<
         let min_row = dimension.min_row;
         let max_row = dimension.max_row;
         let sheet_dimension_str = &format!("{column_min_str}{min_row}:{column_max_str}{max_row}");
+        let mut parsed_formulas = Cow::Borrowed(&model.parsed_formulas[sheet_index]);
+        if !model.workbook.tables.is_empty() {
+            // The tables are not exported, structured references are written as the cells they
+            // point to
+            parsed_formulas =
+                Cow::Owned(model.replace_table_references_in_sheet(sheet_index as u32));
+        }
+        if !external_links.is_empty() {
+            // Formulas refer to other workbooks by the position of their links
+            parsed_formulas = Cow::Owned(
+                parsed_formulas
+                    .iter()
+                    .map(|node| external_links::number_external_links(node, &external_links))
+                    .collect(),
+            );
+        }
         zip.write_all(
-            worksheets::get_worksheet_xml(
-                worksheet,
-                &model.parsed_formulas[sheet_index],
-                sheet_dimension_str,
-            )
-            .as_bytes(),
+            worksheets::get_worksheet_xml(worksheet, &parsed_formulas, sheet_dimension_str)
+                .as_bytes(),
         )?;
     }
 
@@ -1,8 +1,17 @@
 use std::fs;
 
-use equalto_calc::model::Model;
+use std::sync::Arc;
+
+use equalto_calc::{
+    model::Model,
+    types::{
+        DataTable, ExternalCell, ExternalLink, ExternalSheet, ExternalValue, ReferenceStyle, Table,
+        TableColumn, TableStyleInfo,
+    },
+};
 
 use crate::error::XlsxError;
+use crate::external::FileResolver;
 use crate::{export::save_to_xlsx, import::load_model_from_xlsx};
 
 pub fn new_empty_model() -> Model {
@@ -66,6 +75,307 @@ fn test_formulas() {
     fs::remove_file(temp_file_name).unwrap();
 }
 
+#[test]
+fn test_dynamic_arrays() {
+    let mut model = new_empty_model();
+    model.set_user_input(0, 1, 1, "3".to_string());
+    model.set_user_input(0, 2, 1, "1".to_string());
+    model.set_user_input(0, 3, 1, "2".to_string());
+
+    model.set_user_input(0, 1, 2, "=SORT(A1:A3)".to_string());
+    model.set_user_input(0, 1, 3, "=B1#&\"<\"".to_string());
+    model.set_user_input(0, 1, 4, "=COUNTA(C1#)".to_string());
+
+    model.evaluate();
+    let temp_file_name = "temp_file_test_dynamic_arrays.xlsx";
+    save_to_xlsx(&model, temp_file_name).unwrap();
+
+    let mut model = load_model_from_xlsx(temp_file_name, "en", "UTC").unwrap();
+    assert_eq!(
+        model.cell_formula(0, 1, 2).unwrap(),
+        Some("=SORT(A1:A3)".to_string())
+    );
+    assert_eq!(model.cell_formula(0, 2, 2).unwrap(), None);
+    assert_eq!(model.formatted_cell_value(0, 3, 2).unwrap(), "3");
+    assert_eq!(model.formatted_cell_value(0, 3, 3).unwrap(), "3<");
+    assert_eq!(model.formatted_cell_value(0, 1, 4).unwrap(), "3");
+
+    // The spilled values are replaced when the model is evaluated again
+    model.set_user_input(0, 2, 1, "5".to_string());
+    model.evaluate();
+    assert_eq!(model.formatted_cell_value(0, 3, 2).unwrap(), "5");
+    assert_eq!(model.formatted_cell_value(0, 3, 3).unwrap(), "5<");
+    fs::remove_file(temp_file_name).unwrap();
+}
+
+#[test]
+fn test_ranges_in_operators() {
+    let mut model = new_empty_model();
+    model.set_user_input(0, 1, 1, "1".to_string());
+    model.set_user_input(0, 2, 1, "2".to_string());
+    // Older versions of Excel would reduce A1:A2 to A2
+    model.set_user_input(0, 2, 2, "=SUM(A1:A2*10)".to_string());
+    model.set_user_input(0, 2, 3, "=SINGLE(A1:A2)*10".to_string());
+
+    model.evaluate();
+    let temp_file_name = "temp_file_test_ranges_in_operators.xlsx";
+    save_to_xlsx(&model, temp_file_name).unwrap();
+
+    let mut model = load_model_from_xlsx(temp_file_name, "en", "UTC").unwrap();
+    assert_eq!(
+        model.cell_formula(0, 2, 2).unwrap(),
+        Some("=SUM(A1:A2*10)".to_string())
+    );
+    assert_eq!(
+        model.cell_formula(0, 2, 3).unwrap(),
+        Some("=SINGLE(A1:A2)*10".to_string())
+    );
+    model.evaluate();
+    assert_eq!(model.formatted_cell_value(0, 2, 2).unwrap(), "30");
+    assert_eq!(model.formatted_cell_value(0, 2, 3).unwrap(), "20");
+    fs::remove_file(temp_file_name).unwrap();
+}
+
+#[test]
+fn test_let() {
+    let mut model = new_empty_model();
+    model.set_user_input(0, 1, 1, "3".to_string());
+    model.set_user_input(0, 1, 2, "=LET(x,A1*2,y,x+1,x*y)".to_string());
+    model.evaluate();
+    let temp_file_name = "temp_file_test_let.xlsx";
+    save_to_xlsx(&model, temp_file_name).unwrap();
+
+    // Excel stores the function and the names with prefixes
+    let file = fs::File::open(temp_file_name).unwrap();
+    let mut archive = zip::ZipArchive::new(file).unwrap();
+    let mut sheet_xml = String::new();
+    std::io::Read::read_to_string(
+        &mut archive.by_name("xl/worksheets/sheet1.xml").unwrap(),
+        &mut sheet_xml,
+    )
+    .unwrap();
+    assert!(sheet_xml.contains("_xlfn.LET(_xlpm.x,A1*2,_xlpm.y,_xlpm.x+1,_xlpm.x*_xlpm.y)"));
+
+    let model = load_model_from_xlsx(temp_file_name, "en", "UTC").unwrap();
+    assert_eq!(
+        model.cell_formula(0, 1, 2).unwrap(),
+        Some("=LET(x,A1*2,y,x+1,x*y)".to_string())
+    );
+    assert_eq!(model.formatted_cell_value(0, 1, 2).unwrap(), "42");
+    fs::remove_file(temp_file_name).unwrap();
+}
+
+#[test]
+fn test_lambda() {
+    let mut model = new_empty_model();
+    model.set_user_input(0, 1, 1, "3".to_string());
+    model.set_user_input(0, 1, 2, "=LAMBDA(x,y,x*y)(A1,2)".to_string());
+    model.evaluate();
+    let temp_file_name = "temp_file_test_lambda.xlsx";
+    save_to_xlsx(&model, temp_file_name).unwrap();
+
+    let file = fs::File::open(temp_file_name).unwrap();
+    let mut archive = zip::ZipArchive::new(file).unwrap();
+    let mut sheet_xml = String::new();
+    std::io::Read::read_to_string(
+        &mut archive.by_name("xl/worksheets/sheet1.xml").unwrap(),
+        &mut sheet_xml,
+    )
+    .unwrap();
+    assert!(sheet_xml.contains("_xlfn.LAMBDA(_xlpm.x,_xlpm.y,_xlpm.x*_xlpm.y)(A1,2)"));
+
+    let model = load_model_from_xlsx(temp_file_name, "en", "UTC").unwrap();
+    assert_eq!(
+        model.cell_formula(0, 1, 2).unwrap(),
+        Some("=LAMBDA(x,y,x*y)(A1,2)".to_string())
+    );
+    assert_eq!(model.formatted_cell_value(0, 1, 2).unwrap(), "6");
+    fs::remove_file(temp_file_name).unwrap();
+}
+
+#[test]
+fn test_array_formulas() {
+    let mut model = new_empty_model();
+    model.set_user_input(0, 1, 1, "1".to_string());
+    model.set_user_input(0, 2, 1, "2".to_string());
+    model.set_user_input(0, 3, 1, "3".to_string());
+    model
+        .set_user_array_formula(0, 1, 2, 3, 2, "=A1:A3*2".to_string())
+        .unwrap();
+    model
+        .set_user_array_formula(0, 1, 3, 1, 3, "=SUM(IF(A1:A3>1,A1:A3))".to_string())
+        .unwrap();
+
+    model.evaluate();
+    let temp_file_name = "temp_file_test_array_formulas.xlsx";
+    save_to_xlsx(&model, temp_file_name).unwrap();
+
+    let mut model = load_model_from_xlsx(temp_file_name, "en", "UTC").unwrap();
+    assert_eq!(
+        model.workbook.worksheets[0].array_formulas,
+        vec!["B1:B3".to_string(), "C1".to_string()]
+    );
+    assert_eq!(model.formatted_cell_value(0, 3, 2).unwrap(), "6");
+    assert_eq!(model.formatted_cell_value(0, 1, 3).unwrap(), "5");
+
+    model.set_user_input(0, 3, 1, "5".to_string());
+    model.evaluate();
+    assert_eq!(model.formatted_cell_value(0, 3, 2).unwrap(), "10");
+    assert_eq!(model.formatted_cell_value(0, 1, 3).unwrap(), "7");
+    fs::remove_file(temp_file_name).unwrap();
+}
+
+#[test]
+fn test_data_tables() {
+    let mut model = new_empty_model();
+    model.set_user_input(0, 1, 1, "2".to_string());
+    model.set_user_input(0, 1, 3, "=A1*10".to_string());
+    model.set_user_input(0, 2, 2, "1".to_string());
+    model.set_user_input(0, 3, 2, "5".to_string());
+    model.set_user_input(0, 2, 4, "=SUM(C2:C3)".to_string());
+    model.workbook.worksheets[0].data_tables.push(DataTable {
+        range: "C2:C3".to_string(),
+        r1: "A1".to_string(),
+        r2: None,
+        dt2d: false,
+        dtr: false,
+    });
+
+    model.evaluate();
+    let temp_file_name = "temp_file_test_data_tables.xlsx";
+    save_to_xlsx(&model, temp_file_name).unwrap();
+
+    let mut model = load_model_from_xlsx(temp_file_name, "en", "UTC").unwrap();
+    assert_eq!(model.workbook.worksheets[0].data_tables.len(), 1);
+    assert_eq!(model.formatted_cell_value(0, 3, 3).unwrap(), "50");
+    assert_eq!(model.formatted_cell_value(0, 2, 4).unwrap(), "60");
+
+    model.set_user_input(0, 3, 2, "7".to_string());
+    model.evaluate();
+    assert_eq!(model.formatted_cell_value(0, 3, 3).unwrap(), "70");
+    assert_eq!(model.formatted_cell_value(0, 2, 4).unwrap(), "80");
+    fs::remove_file(temp_file_name).unwrap();
+}
+
+#[test]
+fn test_iterative_calculation() {
+    let mut model = new_empty_model();
+    model.set_iterative_calculation(true, 50, 0.0001).unwrap();
+    model.set_user_input(0, 1, 1, "=MIN(B1+1,5)".to_string());
+    model.set_user_input(0, 1, 2, "=A1".to_string());
+    model.set_user_input(0, 1, 3, "=A1*2".to_string());
+
+    model.evaluate();
+    let temp_file_name = "temp_file_test_iterative_calculation.xlsx";
+    save_to_xlsx(&model, temp_file_name).unwrap();
+
+    let model = load_model_from_xlsx(temp_file_name, "en", "UTC").unwrap();
+    let calculation = &model.workbook.settings.calculation;
+    assert!(calculation.iterative);
+    assert_eq!(calculation.max_iterations, 50);
+    assert_eq!(calculation.max_change, 0.0001);
+    assert_eq!(model.formatted_cell_value(0, 1, 3).unwrap(), "10");
+    fs::remove_file(temp_file_name).unwrap();
+}
+
+#[test]
+fn test_reference_style() {
+    let mut model = new_empty_model();
+    model.set_reference_style(ReferenceStyle::R1C1);
+    model.set_user_input(0, 1, 1, "3".to_string());
+    model.set_user_input(0, 2, 1, "=R[-1]C*2".to_string());
+
+    model.evaluate();
+    let temp_file_name = "temp_file_test_reference_style.xlsx";
+    save_to_xlsx(&model, temp_file_name).unwrap();
+
+    let model = load_model_from_xlsx(temp_file_name, "en", "UTC").unwrap();
+    assert_eq!(model.get_reference_style(), ReferenceStyle::R1C1);
+    assert_eq!(
+        model.cell_formula(0, 2, 1).unwrap(),
+        Some("=R[-1]C*2".to_string())
+    );
+    assert_eq!(model.formatted_cell_value(0, 2, 1).unwrap(), "6");
+    fs::remove_file(temp_file_name).unwrap();
+}
+
+#[test]
+fn test_external_links() {
+    let mut model = new_empty_model();
+    model.set_external_links(vec![ExternalLink {
+        workbook: "C:\\models\\Rates.xlsx".to_string(),
+        sheets: vec![ExternalSheet {
+            name: "Second Sheet".to_string(),
+            cells: vec![
+                ExternalCell {
+                    row: 2,
+                    column: 2,
+                    value: ExternalValue::Number(1.5),
+                },
+                ExternalCell {
+                    row: 3,
+                    column: 2,
+                    value: ExternalValue::String("EUR".to_string()),
+                },
+            ],
+        }],
+    }]);
+    model.set_user_input(
+        0,
+        1,
+        1,
+        "='C:\\models\\[Rates.xlsx]Second Sheet'!$B$2*2".to_string(),
+    );
+    model.set_user_input(0, 2, 1, "=[Other.xlsx]Sheet1!A1:B2".to_string());
+    model
+        .new_defined_name("rate", None, "='C:\\models\\[Rates.xlsx]Second Sheet'!$B$3")
+        .unwrap();
+    model.set_user_input(0, 3, 1, "=rate".to_string());
+    model.evaluate();
+
+    let temp_file_name = "temp_file_test_external_links.xlsx";
+    save_to_xlsx(&model, temp_file_name).unwrap();
+
+    let model = load_model_from_xlsx(temp_file_name, "en", "UTC").unwrap();
+    assert_eq!(
+        model.cell_formula(0, 1, 1).unwrap(),
+        Some("='C:\\models\\[Rates.xlsx]Second Sheet'!$B$2*2".to_string())
+    );
+    assert_eq!(
+        model.cell_formula(0, 2, 1).unwrap(),
+        Some("=[Other.xlsx]Sheet1!A1:B2".to_string())
+    );
+    assert_eq!(model.formatted_cell_value(0, 1, 1).unwrap(), "3");
+    assert_eq!(model.formatted_cell_value(0, 3, 1).unwrap(), "EUR");
+    assert_eq!(model.workbook.external_links.len(), 2);
+    assert_eq!(model.workbook.external_links[1].workbook, "Other.xlsx");
+    fs::remove_file(temp_file_name).unwrap();
+}
+
+#[test]
+fn test_file_resolver() {
+    let mut rates = new_empty_model();
+    rates.set_user_input(0, 2, 2, "=1+1".to_string());
+    rates.evaluate();
+    let rates_file_name = "temp_file_test_file_resolver_rates.xlsx";
+    save_to_xlsx(&rates, rates_file_name).unwrap();
+
+    let mut model = new_empty_model();
+    model.set_user_input(
+        0,
+        1,
+        1,
+        "=[temp_file_test_file_resolver_rates.xlsx]Sheet1!B2*3".to_string(),
+    );
+    model.evaluate();
+    assert_eq!(model.formatted_cell_value(0, 1, 1).unwrap(), "#REF!");
+
+    model.set_external_resolver(Arc::new(FileResolver::new(".", "en", "UTC")));
+    model.evaluate();
+    assert_eq!(model.formatted_cell_value(0, 1, 1).unwrap(), "6");
+    fs::remove_file(rates_file_name).unwrap();
+}
+
 #[test]
 fn test_sheets() {
     let mut model = new_empty_model();
@@ -88,6 +398,105 @@ fn test_sheets() {
     fs::remove_file(temp_file_name).unwrap();
 }
 
+#[test]
+fn test_sheet_spans() {
+    let mut model = new_empty_model();
+    model.add_sheet("Jan").unwrap();
+    model.add_sheet("Feb 2024").unwrap();
+    model.set_user_input(1, 5, 2, "1".to_string());
+    model.set_user_input(2, 5, 2, "2".to_string());
+    model.set_user_input(0, 1, 1, "=SUM('Jan:Feb 2024'!B5)".to_string());
+    model.evaluate();
+
+    let temp_file_name = "temp_file_test_sheet_spans.xlsx";
+    save_to_xlsx(&model, temp_file_name).unwrap();
+
+    let model = load_model_from_xlsx(temp_file_name, "en", "UTC").unwrap();
+    assert_eq!(
+        model.cell_formula(0, 1, 1).unwrap(),
+        Some("=SUM('Jan:Feb 2024'!B5)".to_string())
+    );
+    assert_eq!(model.formatted_cell_value(0, 1, 1).unwrap(), "3");
+    fs::remove_file(temp_file_name).unwrap();
+}
+
+#[test]
+fn test_tables() {
+    let mut model = new_empty_model();
+    for (row, values) in [
+        ["Item", "Price", "Total"],
+        ["Pens", "2", ""],
+        ["Ink", "20", ""],
+    ]
+    .iter()
+    .enumerate()
+    {
+        for (column, value) in values.iter().enumerate() {
+            model.set_user_input(0, row as i32 + 1, column as i32 + 1, value.to_string());
+        }
+    }
+    model.workbook.tables.insert(
+        "Sales".to_string(),
+        Table {
+            name: "Sales".to_string(),
+            display_name: "Sales".to_string(),
+            sheet_name: "Sheet1".to_string(),
+            reference: "A1:C3".to_string(),
+            totals_row_count: 0,
+            header_row_count: 1,
+            header_row_dxf_id: None,
+            data_dxf_id: None,
+            totals_row_dxf_id: None,
+            columns: vec![
+                TableColumn {
+                    id: 1,
+                    name: "Item".to_string(),
+                    ..Default::default()
+                },
+                TableColumn {
+                    id: 2,
+                    name: "Price".to_string(),
+                    ..Default::default()
+                },
+                TableColumn {
+                    id: 3,
+                    name: "Total".to_string(),
+                    ..Default::default()
+                },
+            ],
+            style_info: TableStyleInfo::default(),
+            has_filters: false,
+        },
+    );
+    let mut model = Model::from_json(&model.to_json_str()).unwrap();
+    model.set_user_input(0, 1, 4, "=SUM(Sales[Price])".to_string());
+    model.set_user_input(0, 3, 4, "=Sales[@Price]*2".to_string());
+    model.set_user_input(0, 3, 3, "=[@Price]*3".to_string());
+    model.evaluate();
+
+    let temp_file_name = "temp_file_test_tables.xlsx";
+    save_to_xlsx(&model, temp_file_name).unwrap();
+
+    // The tables are not exported, the formulas use the cells of the tables
+    let model = load_model_from_xlsx(temp_file_name, "en", "UTC").unwrap();
+    assert_eq!(
+        model.cell_formula(0, 1, 4).unwrap(),
+        Some("=SUM(Sheet1!$B$2:$B$3)".to_string())
+    );
+    assert_eq!(model.formatted_cell_value(0, 1, 4).unwrap(), "22");
+    assert_eq!(
+        model.cell_formula(0, 3, 4).unwrap(),
+        Some("=Sheet1!$B3*2".to_string())
+    );
+    assert_eq!(model.formatted_cell_value(0, 3, 4).unwrap(), "40");
+    assert_eq!(
+        model.cell_formula(0, 3, 3).unwrap(),
+        Some("=Sheet1!$B3*3".to_string())
+    );
+    assert_eq!(model.formatted_cell_value(0, 3, 3).unwrap(), "60");
+    fs::remove_file(temp_file_name).unwrap();
+}
+
 #[test]
 fn test_named_styles() {
     let mut model = new_empty_model();
@@ -29,12 +29,16 @@
 
 use std::collections::HashMap;
 
-use equalto_calc::types::{SheetState, Workbook};
+use equalto_calc::model::Model;
+use equalto_calc::types::{CalculationSettings, ExternalLink, ReferenceStyle, SheetState};
 
 use super::escape::escape_xml;
+use super::external_links::number_external_links_in_defined_name;
+use super::workbook_xml_rels::get_first_external_link_id;
 use super::xml_constants::XML_DECLARATION;
 
-pub(crate) fn get_workbook_xml(workbook: &Workbook) -> String {
+pub(crate) fn get_workbook_xml(model: &Model, external_links: &[ExternalLink]) -> String {
+    let workbook = &model.workbook;
     // sheets
     // <sheet name="Sheet1" sheetId="1" r:id="rId1"/>
     let mut sheets_str: Vec<String> = vec![];
@@ -70,22 +74,64 @@ pub(crate) fn get_workbook_xml(workbook: &Workbook) -> String {
         } else {
             "".to_string()
         };
-        let formula = escape_xml(&defined_name.formula);
+        let hidden = if defined_name.hidden {
+            " hidden=\"1\""
+        } else {
+            ""
+        };
+        let formula =
+            number_external_links_in_defined_name(model, &defined_name.formula, external_links);
+        let formula = escape_xml(&formula);
         defined_names_str.push(format!(
-            "<definedName name=\"{name}\"{local_sheet_id}>{formula}</definedName>"
+            "<definedName name=\"{name}\"{local_sheet_id}{hidden}>{formula}</definedName>"
         ))
     }
 
+    // <externalReferences><externalReference r:id="rId4"/></externalReferences>
+    let first_external_link_id = get_first_external_link_id(model);
+    let external_references = if external_links.is_empty() {
+        "".to_string()
+    } else {
+        let references: String = (0..external_links.len())
+            .map(|index| {
+                format!(
+                    "<externalReference r:id=\"rId{}\"/>",
+                    first_external_link_id + index
+                )
+            })
+            .collect();
+        format!("<externalReferences>{references}</externalReferences>")
+    };
     let sheets = sheets_str.join("");
     let defined_names = defined_names_str.join("");
+    // <calcPr iterate="1" iterateCount="100" iterateDelta="0.001" refMode="R1C1"/>
+    let calculation = &workbook.settings.calculation;
+    let mut calc_pr_attributes = String::new();
+    let iteration = CalculationSettings {
+        reference_style: ReferenceStyle::A1,
+        ..calculation.clone()
+    };
+    if iteration != CalculationSettings::default() {
+        calc_pr_attributes.push_str(&format!(
+            " iterate=\"{}\" iterateCount=\"{}\" iterateDelta=\"{}\"",
+            i32::from(calculation.iterative),
+            calculation.max_iterations,
+            calculation.max_change
+        ));
+    }
+    if calculation.reference_style == ReferenceStyle::R1C1 {
+        calc_pr_attributes.push_str(" refMode=\"R1C1\"");
+    }
+    let calc_pr = format!("<calcPr{calc_pr_attributes}/>");
     format!("{XML_DECLARATION}\n\
     <workbook xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\" xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\">\
       <sheets>\
         {sheets}\
       </sheets>\
+      {external_references}\
       <definedNames>\
         {defined_names}\
       </definedNames>\
-      <calcPr/>\
+      {calc_pr}\
     </workbook>")
 }
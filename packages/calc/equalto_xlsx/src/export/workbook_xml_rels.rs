@@ -1,10 +1,18 @@
-use equalto_calc::types::Workbook;
+use equalto_calc::{model::Model, types::ExternalLink};
 
-use super::xml_constants::{XML_DECLARATION, XML_WORKSHEET};
+use super::{
+    metadata::has_dynamic_arrays,
+    xml_constants::{XML_DECLARATION, XML_WORKSHEET},
+};
 
-pub(crate) fn get_workbook_xml_rels(workbook: &Workbook) -> String {
+/// Returns the relationship id of the first link to another workbook, they go after the rest
+pub(crate) fn get_first_external_link_id(model: &Model) -> usize {
+    model.workbook.worksheets.len() + 3 + usize::from(has_dynamic_arrays(model))
+}
+
+pub(crate) fn get_workbook_xml_rels(model: &Model, external_links: &[ExternalLink]) -> String {
     let mut relationships_str: Vec<String> = vec![];
-    let worksheet_count = workbook.worksheets.len() + 1;
+    let worksheet_count = model.workbook.worksheets.len() + 1;
     for id in 1..worksheet_count {
         relationships_str.push(format!(
             "<Relationship Id=\"rId{id}\" Type=\"{XML_WORKSHEET}\" Target=\"worksheets/sheet{id}.xml\"/>"
@@ -18,6 +26,20 @@ pub(crate) fn get_workbook_xml_rels(workbook: &Workbook) -> String {
     relationships_str.push(
         format!("<Relationship Id=\"rId{id}\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/sharedStrings\" Target=\"sharedStrings.xml\"/>")
     );
+    if has_dynamic_arrays(model) {
+        id += 1;
+        relationships_str.push(
+            format!("<Relationship Id=\"rId{id}\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/sheetMetadata\" Target=\"metadata.xml\"/>")
+        );
+    }
+    let first_external_link_id = get_first_external_link_id(model);
+    for index in 0..external_links.len() {
+        let id = first_external_link_id + index;
+        let link = index + 1;
+        relationships_str.push(
+            format!("<Relationship Id=\"rId{id}\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/externalLink\" Target=\"externalLinks/externalLink{link}.xml\"/>")
+        );
+    }
     format!(
         "{XML_DECLARATION}\n<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">{}</Relationships>",
         relationships_str.join("")
@@ -19,6 +19,7 @@ use itertools::Itertools;
 
 use equalto_calc::{
     expressions::{
+        ast::remove_implicit_intersections,
         parser::{stringify::to_excel_string, Node},
         types::CellReferenceRC,
         utils::number_to_column,
@@ -26,7 +27,9 @@ use equalto_calc::{
     types::{Cell, Worksheet},
 };
 
-use super::{escape::escape_xml, xml_constants::XML_DECLARATION};
+use super::{
+    escape::escape_xml, metadata::needs_dynamic_array_flag, xml_constants::XML_DECLARATION,
+};
 
 fn get_cell_style_attribute(s: i32) -> String {
     if s == 0 {
@@ -36,19 +39,118 @@ fn get_cell_style_attribute(s: i32) -> String {
     }
 }
 
+/// Formulas in cells that are neither array nor dynamic array formulas (`is_legacy`) are read by
+/// Excel with implicit intersections, the `SINGLE` functions they imply are left out.
 fn get_formula_attribute(
     sheet_name: String,
     row: i32,
     column: i32,
     parsed_formula: &Node,
+    is_legacy: bool,
 ) -> String {
     let cell_ref = CellReferenceRC {
         sheet: sheet_name,
         row,
         column,
     };
-    let formula = &to_excel_string(parsed_formula, &cell_ref);
-    escape_xml(formula).to_string()
+    let formula = if is_legacy {
+        let mut legacy_formula = parsed_formula.clone();
+        remove_implicit_intersections(&mut legacy_formula);
+        to_excel_string(&legacy_formula, &cell_ref)
+    } else {
+        to_excel_string(parsed_formula, &cell_ref)
+    };
+    escape_xml(&formula).to_string()
+}
+
+/// Returns the last row and column of the area each array formula writes into,
+/// indexed by the row and column of the formula
+fn get_spill_areas(worksheet: &Worksheet) -> HashMap<(i32, i32), (i32, i32)> {
+    let mut spill_areas = HashMap::new();
+    for (row, row_data) in &worksheet.sheet_data {
+        for (column, cell) in row_data {
+            if let Some(anchor) = cell.get_spill_anchor() {
+                let area = spill_areas.entry(anchor).or_insert(anchor);
+                area.0 = area.0.max(*row);
+                area.1 = area.1.max(*column);
+            }
+        }
+    }
+    spill_areas
+}
+
+/// Legacy array formulas are written with the range they fill:
+/// <c r="B1">
+///   <f t="array" ref="B1:B3">A1:A3*2</f>
+///   <v>2</v>
+/// </c>
+/// Dynamic array formulas are array formulas over their spill area flagged with cell metadata:
+/// <c r="A1" cm="1">
+///   <f t="array" ref="A1:A3">_xlfn.SEQUENCE(3)</f>
+///   <v>1</v>
+/// </c>
+/// Formulas that don't spill are flagged too if older versions of Excel would read them
+/// differently (`is_dynamic`).
+/// Returns the attributes of the cell and of the formula
+fn get_array_attributes(
+    worksheet: &Worksheet,
+    spill_areas: &HashMap<(i32, i32), (i32, i32)>,
+    cell_name: &str,
+    row: i32,
+    column: i32,
+    is_dynamic: bool,
+) -> (String, String) {
+    if let Some((last_row, last_column)) = worksheet.get_array_formula_area(row, column) {
+        let range = if (last_row, last_column) == (row, column) {
+            cell_name.to_string()
+        } else {
+            let last_column = number_to_column(last_column).unwrap();
+            format!("{cell_name}:{last_column}{last_row}")
+        };
+        return ("".to_string(), format!(" t=\"array\" ref=\"{range}\""));
+    }
+    match spill_areas.get(&(row, column)) {
+        Some((last_row, last_column)) => {
+            let last_column = number_to_column(*last_column).unwrap();
+            (
+                " cm=\"1\"".to_string(),
+                format!(" t=\"array\" ref=\"{cell_name}:{last_column}{last_row}\""),
+            )
+        }
+        None if is_dynamic => (
+            " cm=\"1\"".to_string(),
+            format!(" t=\"array\" ref=\"{cell_name}\""),
+        ),
+        None => ("".to_string(), "".to_string()),
+    }
+}
+
+/// The first cell of a what-if data table holds the definition of the table:
+/// <c r="C2">
+///   <f t="dataTable" ref="C2:C3" dt2D="0" dtr="0" r1="A1"/>
+///   <v>11</v>
+/// </c>
+/// Returns the formula element of the cell, if it is the first cell of a data table
+fn get_data_table_formula(worksheet: &Worksheet, cell_name: &str) -> String {
+    let table = worksheet
+        .data_tables
+        .iter()
+        .find(|table| table.range.split(':').next() == Some(cell_name));
+    match table {
+        Some(table) => {
+            let dt2d = i32::from(table.dt2d);
+            let dtr = i32::from(table.dtr);
+            let r2 = match &table.r2 {
+                Some(r2) => format!(" r2=\"{r2}\""),
+                None => "".to_string(),
+            };
+            format!(
+                "<f t=\"dataTable\" ref=\"{}\" dt2D=\"{dt2d}\" dtr=\"{dtr}\" r1=\"{}\"{r2}/>",
+                table.range, table.r1
+            )
+        }
+        None => "".to_string(),
+    }
 }
 
 pub(crate) fn get_worksheet_xml(
@@ -76,6 +178,11 @@ pub(crate) fn get_worksheet_xml(
 
     // this is a bit of an overkill. A dictionary of the row styles by row_index
     let mut row_style_dict = HashMap::new();
+    let spill_areas = get_spill_areas(worksheet);
+    let dynamic_formulas: Vec<bool> = parsed_formulas
+        .iter()
+        .map(needs_dynamic_array_flag)
+        .collect();
     for row in &worksheet.rows {
         // {
         //     "height": 13,
@@ -105,8 +212,9 @@ pub(crate) fn get_worksheet_xml(
                     // </c>
                     let b = i32::from(*v);
                     let style = get_cell_style_attribute(*s);
+                    let data_table = get_data_table_formula(worksheet, &cell_name);
                     row_data_str.push(format!(
-                        "<c r=\"{cell_name}\" t=\"b\"{style}><v>{b}</v></c>"
+                        "<c r=\"{cell_name}\" t=\"b\"{style}>{data_table}<v>{b}</v></c>"
                     ));
                 }
                 Cell::NumberCell { v, s } => {
@@ -115,12 +223,16 @@ pub(crate) fn get_worksheet_xml(
                     //     <v>3</v>
                     // </c>
                     let style = get_cell_style_attribute(*s);
-                    row_data_str.push(format!("<c r=\"{cell_name}\"{style}><v>{v}</v></c>"));
+                    let data_table = get_data_table_formula(worksheet, &cell_name);
+                    row_data_str.push(format!(
+                        "<c r=\"{cell_name}\"{style}>{data_table}<v>{v}</v></c>"
+                    ));
                 }
                 Cell::ErrorCell { ei, s } => {
                     let style = get_cell_style_attribute(*s);
+                    let data_table = get_data_table_formula(worksheet, &cell_name);
                     row_data_str.push(format!(
-                        "<c r=\"{cell_name}\" t=\"e\"{style}><v>{ei}</v></c>"
+                        "<c r=\"{cell_name}\" t=\"e\"{style}>{data_table}<v>{ei}</v></c>"
                     ));
                 }
                 Cell::SharedString { si, s } => {
@@ -130,8 +242,9 @@ pub(crate) fn get_worksheet_xml(
                     // </c>
                     // Cell on A1 contains a string (t="s") of style="1". The string is the 6th in the list of shared strings
                     let style = get_cell_style_attribute(*s);
+                    let data_table = get_data_table_formula(worksheet, &cell_name);
                     row_data_str.push(format!(
-                        "<c r=\"{cell_name}\" t=\"s\"{style}><v>{si}</v></c>"
+                        "<c r=\"{cell_name}\" t=\"s\"{style}>{data_table}<v>{si}</v></c>"
                     ));
                 }
                 Cell::CellFormula { f: _, s: _ } => {
@@ -144,16 +257,25 @@ pub(crate) fn get_worksheet_xml(
                     // </c>
                     let style = get_cell_style_attribute(*s);
 
+                    let (cm, array) = get_array_attributes(
+                        worksheet,
+                        &spill_areas,
+                        &cell_name,
+                        *row_index,
+                        *column_index,
+                        dynamic_formulas[*f as usize],
+                    );
                     let formula = get_formula_attribute(
                         worksheet.get_name(),
                         *row_index,
                         *column_index,
                         &parsed_formulas[*f as usize],
+                        cm.is_empty() && array.is_empty(),
                     );
 
                     let b = i32::from(*v);
                     row_data_str.push(format!(
-                        "<c r=\"{cell_name}\" t=\"b\"{style}><f>{formula}</f><v>{b}</v></c>"
+                        "<c r=\"{cell_name}\"{cm} t=\"b\"{style}><f{array}>{formula}</f><v>{b}</v></c>"
                     ));
                 }
                 Cell::CellFormulaNumber { f, v, s } => {
@@ -163,16 +285,25 @@ pub(crate) fn get_worksheet_xml(
                     //   <v>123</v>
                     // </c>
 
+                    let (cm, array) = get_array_attributes(
+                        worksheet,
+                        &spill_areas,
+                        &cell_name,
+                        *row_index,
+                        *column_index,
+                        dynamic_formulas[*f as usize],
+                    );
                     let formula = get_formula_attribute(
                         worksheet.get_name(),
                         *row_index,
                         *column_index,
                         &parsed_formulas[*f as usize],
+                        cm.is_empty() && array.is_empty(),
                     );
                     let style = get_cell_style_attribute(*s);
 
                     row_data_str.push(format!(
-                        "<c r=\"{cell_name}\"{style}><f>{formula}</f><v>{v}</v></c>"
+                        "<c r=\"{cell_name}\"{cm}{style}><f{array}>{formula}</f><v>{v}</v></c>"
                     ));
                 }
                 Cell::CellFormulaString { f, v, s } => {
@@ -180,16 +311,26 @@ pub(crate) fn get_worksheet_xml(
                     //   <f>CONCATENATE(A1, A2)</f>
                     //   <v>Hello world!</v>
                     // </c>
+                    let (cm, array) = get_array_attributes(
+                        worksheet,
+                        &spill_areas,
+                        &cell_name,
+                        *row_index,
+                        *column_index,
+                        dynamic_formulas[*f as usize],
+                    );
                     let formula = get_formula_attribute(
                         worksheet.get_name(),
                         *row_index,
                         *column_index,
                         &parsed_formulas[*f as usize],
+                        cm.is_empty() && array.is_empty(),
                     );
+                    let v = escape_xml(v);
                     let style = get_cell_style_attribute(*s);
 
                     row_data_str.push(format!(
-                        "<c r=\"{cell_name}\" t=\"str\"{style}><f>{formula}</f><v>{v}</v></c>"
+                        "<c r=\"{cell_name}\"{cm} t=\"str\"{style}><f{array}>{formula}</f><v>{v}</v></c>"
                     ));
                 }
                 Cell::CellFormulaError {
@@ -203,15 +344,52 @@ pub(crate) fn get_worksheet_xml(
                     //   <f>A1/A3<f/>
                     //   <v>#DIV/0!</v>
                     // </c>
+                    let (cm, array) = get_array_attributes(
+                        worksheet,
+                        &spill_areas,
+                        &cell_name,
+                        *row_index,
+                        *column_index,
+                        dynamic_formulas[*f as usize],
+                    );
                     let formula = get_formula_attribute(
                         worksheet.get_name(),
                         *row_index,
                         *column_index,
                         &parsed_formulas[*f as usize],
+                        cm.is_empty() && array.is_empty(),
                     );
                     let style = get_cell_style_attribute(*s);
                     row_data_str.push(format!(
-                        "<c r=\"{cell_name}\" t=\"e\"{style}><f>{formula}</f><v>{ei}</v></c>"
+                        "<c r=\"{cell_name}\"{cm} t=\"e\"{style}><f{array}>{formula}</f><v>{ei}</v></c>"
+                    ));
+                }
+                Cell::SpillBoolean { v, s, .. } => {
+                    // Values spilled by a dynamic array formula are stored without formula
+                    let b = i32::from(*v);
+                    let style = get_cell_style_attribute(*s);
+                    row_data_str.push(format!(
+                        "<c r=\"{cell_name}\" t=\"b\"{style}><v>{b}</v></c>"
+                    ));
+                }
+                Cell::SpillNumber { v, s, .. } => {
+                    let style = get_cell_style_attribute(*s);
+                    row_data_str.push(format!("<c r=\"{cell_name}\"{style}><v>{v}</v></c>"));
+                }
+                Cell::SpillString { v, s, .. } => {
+                    // <c r="A2" t="str">
+                    //   <v>Hello world!</v>
+                    // </c>
+                    let v = escape_xml(v);
+                    let style = get_cell_style_attribute(*s);
+                    row_data_str.push(format!(
+                        "<c r=\"{cell_name}\" t=\"str\"{style}><v>{v}</v></c>"
+                    ));
+                }
+                Cell::SpillError { ei, s, .. } => {
+                    let style = get_cell_style_attribute(*s);
+                    row_data_str.push(format!(
+                        "<c r=\"{cell_name}\" t=\"e\"{style}><v>{ei}</v></c>"
                     ));
                 }
             }
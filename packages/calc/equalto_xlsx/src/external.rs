@@ -0,0 +1,74 @@
+//! Resolves references to other workbooks by reading their xlsx files.
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use equalto_xlsx::{external::FileResolver, import::load_model_from_xlsx};
+//!
+//! let mut model = load_model_from_xlsx("consolidation.xlsx", "en", "UTC").unwrap();
+//! model.set_external_resolver(Arc::new(FileResolver::new("subsidiaries", "en", "UTC")));
+//! model.evaluate();
+//! ```
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use equalto_calc::{
+    external::{file_name, ExternalResolver},
+    model::Model,
+    types::ExternalValue,
+};
+
+use crate::import::load_model_from_xlsx_without_support_check;
+
+/// Reads other workbooks from their files, each one the first time it is needed.
+/// Workbooks are looked for at the path in the formula and then in a directory by name.
+pub struct FileResolver {
+    directory: PathBuf,
+    locale: String,
+    tz: String,
+    /// The workbooks read, None if they could not be read
+    models: Mutex<HashMap<String, Option<Model>>>,
+}
+
+impl FileResolver {
+    pub fn new<P: AsRef<Path>>(directory: P, locale: &str, tz: &str) -> FileResolver {
+        FileResolver {
+            directory: directory.as_ref().to_path_buf(),
+            locale: locale.to_string(),
+            tz: tz.to_string(),
+            models: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn load_model(&self, workbook: &str) -> Option<Model> {
+        let path = Path::new(workbook);
+        let path = if path.is_absolute() && path.exists() {
+            path.to_path_buf()
+        } else {
+            self.directory.join(file_name(workbook))
+        };
+        // The values are the ones saved in the file
+        load_model_from_xlsx_without_support_check(&path.to_string_lossy(), &self.locale, &self.tz)
+            .ok()
+    }
+}
+
+impl ExternalResolver for FileResolver {
+    fn cell_value(
+        &self,
+        workbook: &str,
+        sheet: &str,
+        row: i32,
+        column: i32,
+    ) -> Option<ExternalValue> {
+        let mut models = self.models.lock().ok()?;
+        let model = models
+            .entry(workbook.to_string())
+            .or_insert_with(|| self.load_model(workbook));
+        model.as_ref()?.get_external_value(sheet, row, column)
+    }
+}
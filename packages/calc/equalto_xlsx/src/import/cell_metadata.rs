@@ -0,0 +1,97 @@
+use std::{collections::HashSet, io::Read};
+
+use roxmltree::Node;
+
+use crate::error::XlsxError;
+
+use super::util::get_number;
+
+/// Reads the cell metadata of an Excel workbook and returns the indexes of the entries that flag
+/// dynamic array formulas. Cells refer to an entry with its index, starting at 1:
+/// <c r="A1" cm="1">
+///   <f t="array" ref="A1:A3">_xlfn.SEQUENCE(3)</f>
+/// </c>
+/// Each entry of the cell metadata points to a metadata type and to a block of the future
+/// metadata with that name. Dynamic arrays are of type XLDAPR with the fDynamic property:
+/// <metadataTypes count="1"><metadataType name="XLDAPR" .../></metadataTypes>
+/// <futureMetadata name="XLDAPR" count="1">
+///   <bk><extLst><ext uri="..."><xda:dynamicArrayProperties fDynamic="1" fCollapsed="0"/></ext></extLst></bk>
+/// </futureMetadata>
+/// <cellMetadata count="1"><bk><rc t="1" v="0"/></bk></cellMetadata>
+/// See Section 18.9 and [MS-XLSX] 2.6.
+pub(crate) fn read_dynamic_array_metadata<R: Read + std::io::Seek>(
+    archive: &mut zip::read::ZipArchive<R>,
+) -> Result<HashSet<i32>, XlsxError> {
+    match archive.by_name("xl/metadata.xml") {
+        Ok(mut file) => {
+            let mut text = String::new();
+            file.read_to_string(&mut text)?;
+            read_dynamic_array_metadata_from_string(&text)
+        }
+        Err(_e) => Ok(HashSet::new()),
+    }
+}
+
+fn read_dynamic_array_metadata_from_string(text: &str) -> Result<HashSet<i32>, XlsxError> {
+    let doc = roxmltree::Document::parse(text)?;
+    let root = doc.root_element();
+    let type_names: Vec<&str> = root
+        .descendants()
+        .filter(|n| n.has_tag_name("metadataType"))
+        .map(|n| n.attribute("name").unwrap_or(""))
+        .collect();
+    // The blocks of future metadata flagged fDynamic
+    let dynamic_blocks: Vec<bool> = root
+        .children()
+        .filter(|n| n.has_tag_name("futureMetadata") && n.attribute("name") == Some("XLDAPR"))
+        .flat_map(|n| n.children().filter(|bk| bk.has_tag_name("bk")))
+        .map(|bk| {
+            bk.descendants()
+                .filter(|n| n.has_tag_name("dynamicArrayProperties"))
+                .any(|n| matches!(n.attribute("fDynamic"), Some("1" | "true")))
+        })
+        .collect();
+    let mut dynamic_arrays = HashSet::new();
+    let cell_metadata = root.children().find(|n| n.has_tag_name("cellMetadata"));
+    let blocks = cell_metadata
+        .iter()
+        .flat_map(|n| n.children().filter(|bk| bk.has_tag_name("bk")));
+    for (index, bk) in blocks.enumerate() {
+        let is_dynamic = bk
+            .children()
+            .filter(|rc| rc.has_tag_name("rc"))
+            .any(|rc: Node| {
+                let type_index = get_number(rc, "t") - 1;
+                let value_index = get_number(rc, "v");
+                type_index >= 0
+                    && type_names.get(type_index as usize) == Some(&"XLDAPR")
+                    && dynamic_blocks.get(value_index as usize) == Some(&true)
+            });
+        if is_dynamic {
+            dynamic_arrays.insert(index as i32 + 1);
+        }
+    }
+    Ok(dynamic_arrays)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dynamic_array_metadata() {
+        // As written by Excel: the rich values come first and the second cell metadata entry
+        // flags dynamic arrays
+        let text = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<metadata xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:xlrd="http://schemas.microsoft.com/office/spreadsheetml/2017/richdata" xmlns:xda="http://schemas.microsoft.com/office/spreadsheetml/2017/dynamicarray"><metadataTypes count="2"><metadataType name="XLRICHVALUE" minSupportedVersion="120000" copy="1" pasteAll="1" pasteValues="1" merge="1" splitFirst="1" rowColShift="1" clearFormats="1" clearComments="1" assign="1" coerce="1"/><metadataType name="XLDAPR" minSupportedVersion="120000" copy="1" pasteAll="1" pasteValues="1" merge="1" splitFirst="1" rowColShift="1" clearFormats="1" clearComments="1" assign="1" coerce="1" cellMeta="1"/></metadataTypes><futureMetadata name="XLRICHVALUE" count="1"><bk><extLst><ext uri="{3e2802c4-a4d2-4d8b-9148-e3be6c30e623}"><xlrd:rvb i="0"/></ext></extLst></bk></futureMetadata><futureMetadata name="XLDAPR" count="1"><bk><extLst><ext uri="{bdbb8cdc-fa1e-496e-a857-3c3f30c029c3}"><xda:dynamicArrayProperties fDynamic="1" fCollapsed="0"/></ext></extLst></bk></futureMetadata><cellMetadata count="2"><bk><rc t="1" v="0"/></bk><bk><rc t="2" v="0"/></bk></cellMetadata><valueMetadata count="1"><bk><rc t="1" v="0"/></bk></valueMetadata></metadata>"#;
+        let dynamic_arrays = read_dynamic_array_metadata_from_string(text).unwrap();
+        assert_eq!(dynamic_arrays, HashSet::from([2]));
+    }
+
+    #[test]
+    fn test_no_dynamic_arrays() {
+        let text = r#"<metadata xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:xda="http://schemas.microsoft.com/office/spreadsheetml/2017/dynamicarray"><metadataTypes count="1"><metadataType name="XLDAPR"/></metadataTypes><futureMetadata name="XLDAPR" count="1"><bk><extLst><ext uri="{bdbb8cdc-fa1e-496e-a857-3c3f30c029c3}"><xda:dynamicArrayProperties fDynamic="0" fCollapsed="0"/></ext></extLst></bk></futureMetadata><cellMetadata count="1"><bk><rc t="1" v="0"/></bk></cellMetadata></metadata>"#;
+        let dynamic_arrays = read_dynamic_array_metadata_from_string(text).unwrap();
+        assert!(dynamic_arrays.is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+use std::{collections::HashMap, io::Read};
+
+use equalto_calc::{
+    expressions::{
+        ast::{walk_mut, Node, VisitorMut},
+        parser::{stringify::to_string, Parser},
+        token::get_error_by_english_name,
+        types::CellReferenceRC,
+        utils::parse_reference_a1,
+    },
+    types::{DefinedName, ExternalCell, ExternalLink, ExternalSheet, ExternalValue, Table},
+};
+
+use crate::error::XlsxError;
+
+use super::{util::get_attribute, worksheets::Relationship};
+
+// <externalLink>
+//   <externalBook r:id="rId1">
+//     <sheetNames>
+//       <sheetName val="Sheet1"/>
+//     </sheetNames>
+//     <sheetDataSet>
+//       <sheetData sheetId="0">
+//         <row r="2">
+//           <cell r="B2"><v>1.5</v></cell>
+//           <cell r="C2" t="str"><v>EUR</v></cell>
+//         </row>
+//       </sheetData>
+//     </sheetDataSet>
+//   </externalBook>
+// </externalLink>
+
+/// Reads the file of another workbook the external link points to
+fn load_external_link_target<R: Read + std::io::Seek>(
+    archive: &mut zip::read::ZipArchive<R>,
+    path: &str,
+    id: &str,
+) -> Result<String, XlsxError> {
+    // xl/externalLinks/externalLink1.xml => xl/externalLinks/_rels/externalLink1.xml.rels
+    let rels_path = match path.rsplit_once('/') {
+        Some((directory, file_name)) => format!("{directory}/_rels/{file_name}.rels"),
+        None => format!("_rels/{path}.rels"),
+    };
+    let mut file = archive.by_name(&rels_path)?;
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    let doc = roxmltree::Document::parse(&text)?;
+    let relationship = doc
+        .descendants()
+        .find(|n| n.has_tag_name("Relationship") && n.attribute("Id") == Some(id))
+        .ok_or_else(|| XlsxError::Xml(format!("Missing relationship {id} in {rels_path}")))?;
+    let target = get_attribute(&relationship, "Target")?;
+    // file:///C:\models\fx.xlsx or file:///Users/models/fx.xlsx
+    let target = match target.strip_prefix("file:///") {
+        Some(path) if path.get(1..2) == Some(":") => path.to_string(),
+        Some(path) => format!("/{path}"),
+        None => target.to_string(),
+    };
+    Ok(target.replace("%20", " "))
+}
+
+fn load_external_link<R: Read + std::io::Seek>(
+    archive: &mut zip::read::ZipArchive<R>,
+    path: &str,
+) -> Result<ExternalLink, XlsxError> {
+    let mut file = archive.by_name(path)?;
+    let mut text = String::new();
+    file.read_to_string(&mut text)?;
+    drop(file);
+    let doc = roxmltree::Document::parse(&text)?;
+    let book = doc
+        .descendants()
+        .find(|n| n.has_tag_name("externalBook"))
+        .ok_or_else(|| XlsxError::Xml(format!("Unsupported external link in {path}")))?;
+    let id = get_attribute(
+        &book,
+        (
+            "http://schemas.openxmlformats.org/officeDocument/2006/relationships",
+            "id",
+        ),
+    )?;
+    let workbook = load_external_link_target(archive, path, id)?;
+
+    let mut sheets: Vec<ExternalSheet> = book
+        .descendants()
+        .filter(|n| n.has_tag_name("sheetName"))
+        .map(|n| {
+            Ok(ExternalSheet {
+                name: get_attribute(&n, "val")?.to_string(),
+                cells: Vec::new(),
+            })
+        })
+        .collect::<Result<_, XlsxError>>()?;
+
+    for sheet_data in book.descendants().filter(|n| n.has_tag_name("sheetData")) {
+        let sheet_id = get_attribute(&sheet_data, "sheetId")?.parse::<usize>()?;
+        let sheet = sheets
+            .get_mut(sheet_id)
+            .ok_or_else(|| XlsxError::Xml(format!("Invalid sheetId {sheet_id} in {path}")))?;
+        for cell in sheet_data.descendants().filter(|n| n.has_tag_name("cell")) {
+            let reference = get_attribute(&cell, "r")?;
+            let reference = parse_reference_a1(reference)
+                .ok_or_else(|| XlsxError::Xml(format!("Invalid reference {reference}")))?;
+            let text = cell
+                .children()
+                .find(|n| n.has_tag_name("v"))
+                .and_then(|n| n.text())
+                .unwrap_or("");
+            let value = match cell.attribute("t") {
+                Some("b") => ExternalValue::Boolean(text == "1"),
+                Some("e") => match get_error_by_english_name(text) {
+                    Some(error) => ExternalValue::Error(error),
+                    None => return Err(XlsxError::Xml(format!("Invalid error {text}"))),
+                },
+                Some("str") | Some("s") => ExternalValue::String(text.to_string()),
+                _ if text.is_empty() => ExternalValue::Empty,
+                _ => ExternalValue::Number(text.parse::<f64>()?),
+            };
+            sheet.cells.push(ExternalCell {
+                row: reference.row,
+                column: reference.column,
+                value,
+            });
+        }
+    }
+    Ok(ExternalLink { workbook, sheets })
+}
+
+/// Reads the links to other workbooks, in the order of `<externalReferences>`
+pub(super) fn load_external_links<R: Read + std::io::Seek>(
+    archive: &mut zip::read::ZipArchive<R>,
+    rels: &HashMap<String, Relationship>,
+    external_references: &[String],
+) -> Result<Vec<ExternalLink>, XlsxError> {
+    let mut external_links = Vec::new();
+    for id in external_references {
+        let rel = rels
+            .get(id)
+            .ok_or_else(|| XlsxError::Xml(format!("Missing relationship {id}")))?;
+        let path = match rel.target.strip_prefix('/') {
+            Some(p) => p.to_string(),
+            None => format!("xl/{}", rel.target),
+        };
+        external_links.push(load_external_link(archive, &path)?);
+    }
+    Ok(external_links)
+}
+
+/// In xlsx files references to other workbooks give the position of the link: `[1]Sheet1!A1`
+struct ExternalLinkNames<'a>(&'a [ExternalLink]);
+
+impl VisitorMut for ExternalLinkNames<'_> {
+    fn enter(&mut self, node: &mut Node) -> bool {
+        if let Node::ExternalReferenceKind { workbook, .. }
+        | Node::ExternalRangeKind { workbook, .. } = node
+        {
+            let link = workbook
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| index.checked_sub(1))
+                .and_then(|index| self.0.get(index));
+            if let Some(link) = link {
+                *workbook = link.workbook.clone();
+            }
+        }
+        true
+    }
+}
+
+/// Replaces the positions of the links in `node` by the names of the workbooks
+pub(super) fn rename_external_links(node: &mut Node, external_links: &[ExternalLink]) {
+    walk_mut(node, &mut ExternalLinkNames(external_links));
+}
+
+/// Replaces the positions of the links in the formulas of the defined names
+pub(super) fn rename_external_links_in_defined_names(
+    defined_names: &mut [DefinedName],
+    worksheets: &[String],
+    tables: &HashMap<String, Table>,
+    external_links: &[ExternalLink],
+) {
+    let mut parser = Parser::new(worksheets.to_vec(), tables.clone());
+    for defined_name in defined_names {
+        if !defined_name.formula.contains('[') {
+            continue;
+        }
+        // The cell doesn't matter, the formula is written back relative to the same one
+        let context = CellReferenceRC {
+            sheet: worksheets.first().cloned().unwrap_or_default(),
+            row: 1,
+            column: 1,
+        };
+        let mut node = parser.parse(&defined_name.formula, &Some(context.clone()));
+        if matches!(node, Node::ParseErrorKind { .. }) {
+            continue;
+        }
+        rename_external_links(&mut node, external_links);
+        defined_name.formula = to_string(&node, &context);
+    }
+}
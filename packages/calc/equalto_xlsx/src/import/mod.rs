@@ -1,4 +1,6 @@
+mod cell_metadata;
 mod colors;
+mod external_links;
 mod metadata;
 mod shared_strings;
 mod styles;
@@ -25,6 +27,8 @@ use crate::error::XlsxError;
 
 use shared_strings::read_shared_strings;
 
+use cell_metadata::read_dynamic_array_metadata;
+use external_links::{load_external_links, rename_external_links_in_defined_names};
 use metadata::load_metadata;
 use styles::load_styles;
 use util::get_attribute;
@@ -64,16 +68,29 @@ fn load_xlsx_from_reader<R: Read + std::io::Seek>(
     let mut archive = zip::ZipArchive::new(reader)?;
 
     let mut shared_strings = read_shared_strings(&mut archive)?;
-    let workbook = load_workbook(&mut archive)?;
+    let mut workbook = load_workbook(&mut archive)?;
     let rels = load_relationships(&mut archive)?;
+    let external_links = load_external_links(&mut archive, &rels, &workbook.external_references)?;
+    let dynamic_arrays = read_dynamic_array_metadata(&mut archive)?;
     let mut tables = HashMap::new();
     let worksheets = load_sheets(
         &mut archive,
         &rels,
         &workbook,
         &mut tables,
+        &external_links,
+        &dynamic_arrays,
         &mut shared_strings,
     )?;
+    if !external_links.is_empty() {
+        let worksheet_names: Vec<String> = worksheets.iter().map(|w| w.get_name()).collect();
+        rename_external_links_in_defined_names(
+            &mut workbook.defined_names,
+            &worksheet_names,
+            &tables,
+            &external_links,
+        );
+    }
     let styles = load_styles(&mut archive)?;
     let metadata = match load_metadata(&mut archive) {
         Ok(metadata) => metadata,
@@ -98,9 +115,11 @@ fn load_xlsx_from_reader<R: Read + std::io::Seek>(
         settings: WorkbookSettings {
             tz: tz.to_string(),
             locale: locale.to_string(),
+            calculation: workbook.calculation,
         },
         metadata,
         tables,
+        external_links,
     })
 }
 
@@ -1,12 +1,12 @@
 use std::io::Read;
 
-use equalto_calc::types::{DefinedName, SheetState};
+use equalto_calc::types::{CalculationSettings, DefinedName, ReferenceStyle, SheetState};
 use roxmltree::Node;
 
 use crate::error::XlsxError;
 
 use super::{
-    util::get_attribute,
+    util::{get_attribute, get_bool_false},
     worksheets::{Sheet, WorkbookXML},
 };
 
@@ -69,11 +69,45 @@ pub(super) fn load_workbook<R: Read + std::io::Seek>(
             name,
             formula,
             sheet_id,
+            hidden: get_bool_false(node, "hidden"),
         })
     }
+    // Calculation settings
+    // <calcPr calcId="191029" iterate="1" iterateCount="50" iterateDelta="0.0001" refMode="R1C1"/>
+    let mut calculation = CalculationSettings::default();
+    if let Some(node) = doc.descendants().find(|n| n.has_tag_name("calcPr")) {
+        calculation.iterative = get_bool_false(node, "iterate");
+        if let Some(count) = node.attribute("iterateCount") {
+            calculation.max_iterations = count.parse::<i32>()?;
+        }
+        if let Some(delta) = node.attribute("iterateDelta") {
+            calculation.max_change = delta.parse::<f64>()?;
+        }
+        if node.attribute("refMode") == Some("R1C1") {
+            calculation.reference_style = ReferenceStyle::R1C1;
+        }
+    }
+    // Links to other workbooks
+    // <externalReferences><externalReference r:id="rId4"/></externalReferences>
+    let mut external_references = Vec::new();
+    for node in doc
+        .descendants()
+        .filter(|n| n.has_tag_name("externalReference"))
+    {
+        let id = get_attribute(
+            &node,
+            (
+                "http://schemas.openxmlformats.org/officeDocument/2006/relationships",
+                "id",
+            ),
+        )?;
+        external_references.push(id.to_string());
+    }
     // read the relationships file
     Ok(WorkbookXML {
         worksheets: sheets,
         defined_names,
+        calculation,
+        external_references,
     })
 }
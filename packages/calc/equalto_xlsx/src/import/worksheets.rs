@@ -1,13 +1,21 @@
-use std::{collections::HashMap, io::Read, num::ParseIntError};
+use std::{
+    collections::{HashMap, HashSet},
+    io::Read,
+    num::ParseIntError,
+};
 
 use equalto_calc::{
     expressions::{
+        ast::add_implicit_intersections,
         parser::{stringify::to_rc_format, Parser},
         token::{get_error_by_english_name, Error},
         types::CellReferenceRC,
-        utils::column_to_number,
+        utils::{column_to_number, parse_reference_a1},
+    },
+    types::{
+        CalculationSettings, Cell, Col, Comment, DataTable, DefinedName, ExternalLink, Row,
+        SheetData, SheetState, Table, Worksheet,
     },
-    types::{Cell, Col, Comment, DefinedName, Row, SheetData, SheetState, Table, Worksheet},
 };
 use roxmltree::Node;
 use serde::{Deserialize, Serialize};
@@ -16,8 +24,9 @@ use thiserror::Error;
 use crate::error::XlsxError;
 
 use super::{
+    external_links::rename_external_links,
     tables::load_table,
-    util::{get_attribute, get_color, get_number},
+    util::{get_attribute, get_bool_false, get_color, get_number},
 };
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -32,6 +41,9 @@ pub(crate) struct Sheet {
 pub(crate) struct WorkbookXML {
     pub(crate) worksheets: Vec<Sheet>,
     pub(crate) defined_names: Vec<DefinedName>,
+    pub(crate) calculation: CalculationSettings,
+    /// Relationship ids of the links to other workbooks
+    pub(crate) external_references: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -230,16 +242,24 @@ fn parse_reference(s: &str) -> Result<CellReferenceRC, ParseReferenceError> {
     })
 }
 
+/// Converts `formula` to the R1C1 notation. Formulas that are not dynamic array formulas were
+/// written by versions of Excel that reduce ranges to a single cell, that is made explicit.
 fn from_a1_to_rc(
     formula: String,
     worksheets: &[String],
     context: String,
     tables: HashMap<String, Table>,
+    external_links: &[ExternalLink],
+    is_dynamic: bool,
 ) -> Result<String, XlsxError> {
     let mut parser = Parser::new(worksheets.to_owned(), tables);
     let cell_reference =
         parse_reference(&context).map_err(|error| XlsxError::Xml(error.to_string()))?;
-    let t = parser.parse(&formula, &Some(cell_reference));
+    let mut t = parser.parse(&formula, &Some(cell_reference));
+    rename_external_links(&mut t, external_links);
+    if !is_dynamic {
+        add_implicit_intersections(&mut t);
+    }
     Ok(to_rc_format(&t))
 }
 
@@ -442,6 +462,59 @@ fn get_cell_from_excel(
     }
 }
 
+// The cells in the area of an array formula, other than the formula itself,
+// hold the values of the formula
+fn load_spilled_cells(
+    sheet_data: &mut SheetData,
+    spill_areas: &[(i32, i32, String)],
+    shared_strings: &[String],
+) -> Result<(), XlsxError> {
+    for (r, c, area) in spill_areas {
+        let (r, c) = (*r, *c);
+        let last_cell = match area.split_once(':') {
+            Some((_, last_cell)) => last_cell,
+            None => continue,
+        };
+        let last_cell = parse_reference_a1(last_cell)
+            .ok_or_else(|| XlsxError::Xml(format!("Invalid array formula range: {area}")))?;
+        for row in r..=last_cell.row {
+            for column in c..=last_cell.column {
+                if row == r && column == c {
+                    continue;
+                }
+                let cell = match sheet_data
+                    .get_mut(&row)
+                    .and_then(|row_data| row_data.get_mut(&column))
+                {
+                    Some(cell) => cell,
+                    None => continue,
+                };
+                *cell = match cell {
+                    Cell::BooleanCell { v, s } => Cell::SpillBoolean { v: *v, r, c, s: *s },
+                    Cell::NumberCell { v, s } => Cell::SpillNumber { v: *v, r, c, s: *s },
+                    Cell::SharedString { si, s } => Cell::SpillString {
+                        v: shared_strings
+                            .get(*si as usize)
+                            .cloned()
+                            .unwrap_or_default(),
+                        r,
+                        c,
+                        s: *s,
+                    },
+                    Cell::ErrorCell { ei, s } => Cell::SpillError {
+                        ei: ei.clone(),
+                        r,
+                        c,
+                        s: *s,
+                    },
+                    _ => continue,
+                };
+            }
+        }
+    }
+    Ok(())
+}
+
 fn load_sheet_rels<R: Read + std::io::Seek>(
     archive: &mut zip::read::ZipArchive<R>,
     path: &str,
@@ -563,12 +636,15 @@ pub(super) struct SheetSettings {
     pub comments: Vec<Comment>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(super) fn load_sheet<R: Read + std::io::Seek>(
     archive: &mut zip::read::ZipArchive<R>,
     path: &str,
     settings: SheetSettings,
     worksheets: &[String],
     tables: &HashMap<String, Table>,
+    external_links: &[ExternalLink],
+    dynamic_arrays: &HashSet<i32>,
     shared_strings: &mut Vec<String>,
 ) -> Result<Worksheet, XlsxError> {
     let sheet_name = &settings.name;
@@ -614,6 +690,12 @@ pub(super) fn load_sheet<R: Read + std::io::Seek>(
 
     // holds a map from the formula index in Excel to the index in EqualTo
     let mut index_map = HashMap::new();
+    // ranges of the legacy array formulas
+    let mut array_formulas = Vec::new();
+    // what-if data tables
+    let mut data_tables = Vec::new();
+    // anchor row, anchor column and area of all array formulas
+    let mut spill_areas = Vec::new();
     for row in sheet_data_nodes.children() {
         // This is the row number 1-indexed
         let row_index = get_attribute(&row, "r")?.parse::<i32>()?;
@@ -744,8 +826,14 @@ pub(super) fn load_sheet<R: Read + std::io::Seek>(
                                 // It's the mother cell. We do not use the ref attribute in EqualTo
                                 let formula = fs[0].text().unwrap_or("").to_string();
                                 let context = format!("{}!{}", sheet_name, cell_ref);
-                                let formula =
-                                    from_a1_to_rc(formula, worksheets, context, tables.clone())?;
+                                let formula = from_a1_to_rc(
+                                    formula,
+                                    worksheets,
+                                    context,
+                                    tables.clone(),
+                                    external_links,
+                                    false,
+                                )?;
                                 match index_map.get(&si) {
                                     Some(index) => {
                                         // The index for that formula already exists meaning we bumped into a daughter cell first
@@ -789,17 +877,56 @@ pub(super) fn load_sheet<R: Read + std::io::Seek>(
                             }
                         }
                     }
-                    "array" => {
-                        return Err(XlsxError::NotImplemented("array formulas".to_string()));
-                    }
                     "dataTable" => {
-                        return Err(XlsxError::NotImplemented("data table formulas".to_string()));
+                        // The first cell of a data table holds the definition of the whole table.
+                        // The results are loaded as values and recomputed by the model:
+                        // <c r="C2">
+                        //   <f t="dataTable" ref="C2:C3" dt2D="0" dtr="0" r1="A1"/>
+                        //   <v>11</v>
+                        // </c>
+                        data_tables.push(DataTable {
+                            range: get_attribute(&fs[0], "ref")?.to_string(),
+                            r1: get_attribute(&fs[0], "r1")?.to_string(),
+                            r2: fs[0].attribute("r2").map(|r2| r2.to_string()),
+                            dt2d: get_bool_false(fs[0], "dt2D"),
+                            dtr: get_bool_false(fs[0], "dtr"),
+                        });
                     }
-                    "normal" => {
-                        // Its a cell with a simple formula
+                    "normal" | "array" => {
+                        // Its a cell with a simple formula or an array formula.
+                        // The range of an array formula is the area it fills:
+                        // <c r="B1">
+                        //   <f t="array" ref="B1:B3">A1:A3*2</f>
+                        //   <v>2</v>
+                        // </c>
+                        // Dynamic array formulas are flagged with the cell metadata entry (cm)
+                        // of dynamic arrays and the range is the area they spill into:
+                        // <c r="A1" cm="1">
+                        //   <f t="array" ref="A1:A3">_xlfn.SEQUENCE(3)</f>
+                        //   <v>1</v>
+                        // </c>
+                        if formula_type == "array" {
+                            let area = fs[0].attribute("ref").unwrap_or(cell_ref);
+                            let is_dynamic = cell
+                                .attribute("cm")
+                                .and_then(|cm| cm.parse::<i32>().ok())
+                                .map_or(false, |cm| dynamic_arrays.contains(&cm));
+                            if !is_dynamic {
+                                array_formulas.push(area.to_string());
+                            }
+                            spill_areas.push((row_index, column, area.to_string()));
+                        }
                         let formula = fs[0].text().unwrap_or("").to_string();
                         let context = format!("{}!{}", sheet_name, cell_ref);
-                        let formula = from_a1_to_rc(formula, worksheets, context, tables.clone())?;
+                        // Legacy array formulas are evaluated as arrays
+                        let formula = from_a1_to_rc(
+                            formula,
+                            worksheets,
+                            context,
+                            tables.clone(),
+                            external_links,
+                            formula_type == "array",
+                        )?;
 
                         match get_formula_index(&formula, &shared_formulas) {
                             Some(index) => formula_index = index,
@@ -831,6 +958,7 @@ pub(super) fn load_sheet<R: Read + std::io::Seek>(
         }
         sheet_data.insert(row_index, data_row);
     }
+    load_spilled_cells(&mut sheet_data, &spill_areas, shared_strings)?;
 
     let merge_cells = load_merge_cells(ws)?;
 
@@ -859,6 +987,8 @@ pub(super) fn load_sheet<R: Read + std::io::Seek>(
         state: state.to_owned(),
         color,
         merge_cells,
+        array_formulas,
+        data_tables,
         comments: settings.comments,
         frozen_rows,
         frozen_columns,
@@ -870,6 +1000,8 @@ pub(super) fn load_sheets<R: Read + std::io::Seek>(
     rels: &HashMap<String, Relationship>,
     workbook: &WorkbookXML,
     tables: &mut HashMap<String, Table>,
+    external_links: &[ExternalLink],
+    dynamic_arrays: &HashSet<i32>,
     shared_strings: &mut Vec<String>,
 ) -> Result<Vec<Worksheet>, XlsxError> {
     // load comments and tables
@@ -917,6 +1049,8 @@ pub(super) fn load_sheets<R: Read + std::io::Seek>(
                 settings,
                 worksheets,
                 tables,
+                external_links,
+                dynamic_arrays,
                 shared_strings,
             )?);
         }
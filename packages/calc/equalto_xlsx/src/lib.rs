@@ -57,4 +57,5 @@
 pub mod compare;
 pub mod error;
 pub mod export;
+pub mod external;
 pub mod import;
@@ -2,10 +2,11 @@ use std::{env, fs, io};
 use uuid::Uuid;
 
 use equalto_calc::model::Model;
+use equalto_calc::solver::{ConstraintOperator, SolverConstraint, SolverDefinition, SolverGoal};
 use equalto_calc::types::{HorizontalAlignment, VerticalAlignment, Workbook};
 use equalto_xlsx::compare::{test_file, test_load_and_saving};
 use equalto_xlsx::error::XlsxError;
-use equalto_xlsx::export::save_to_xlsx;
+use equalto_xlsx::export::{save_to_xlsx, save_xlsx_to_writer};
 use equalto_xlsx::import::{load_from_excel, load_model_from_xlsx};
 
 // This is a functional test.
@@ -187,6 +188,75 @@ fn test_defined_names_casing() {
     }
 }
 
+#[test]
+fn test_solver_round_trip() {
+    let mut model = Model::new_empty("solver", "en", "UTC").unwrap();
+    model.set_user_input(0, 1, 2, "=A1*2".to_string());
+    let definition = SolverDefinition {
+        objective: "Sheet1!$B$1".to_string(),
+        goal: SolverGoal::Value(10.0),
+        variables: vec!["Sheet1!$A$1".to_string()],
+        constraints: vec![SolverConstraint {
+            cells: "Sheet1!$A$1".to_string(),
+            operator: ConstraintOperator::LessOrEqual,
+            value: "100".to_string(),
+        }],
+        ..Default::default()
+    };
+    model.set_solver(0, &definition).unwrap();
+    let temp_file_name = "temp_file_solver.xlsx";
+    save_to_xlsx(&model, temp_file_name).unwrap();
+    let model = load_model_from_xlsx(temp_file_name, "en", "UTC").unwrap();
+    fs::remove_file(temp_file_name).unwrap();
+    assert!(model
+        .workbook
+        .defined_names
+        .iter()
+        .all(|defined_name| defined_name.hidden));
+    assert_eq!(model.get_solver(0).unwrap(), Some(definition));
+}
+
+// Formulas saved by versions of Excel without dynamic arrays are written back as they were
+#[test]
+fn test_legacy_formulas_round_trip() {
+    let mut model =
+        load_model_from_xlsx("tests/calc_tests/legacy_formulas.xlsx", "en", "UTC").unwrap();
+    assert_eq!(
+        model.cell_formula(0, 1, 2).unwrap(),
+        Some("=SINGLE(A1:A3)+1".to_string())
+    );
+    assert_eq!(
+        model.cell_formula(0, 3, 2).unwrap(),
+        Some("=SUM(A1:A3)".to_string())
+    );
+    // B4 is flagged with cell metadata that is not the dynamic array one
+    assert_eq!(
+        model.workbook.worksheets[0].array_formulas,
+        vec!["C1:C3".to_string(), "B4".to_string()]
+    );
+    model.evaluate();
+
+    let writer = save_xlsx_to_writer(&model, io::Cursor::new(Vec::new())).unwrap();
+    let mut archive = zip::ZipArchive::new(writer).unwrap();
+    let mut sheet = String::new();
+    io::Read::read_to_string(
+        &mut archive.by_name("xl/worksheets/sheet1.xml").unwrap(),
+        &mut sheet,
+    )
+    .unwrap();
+    for cell in [
+        r#"<c r="B1"><f>A1:A3+1</f>"#,
+        r#"<c r="B2"><f>A1:A3*10</f>"#,
+        r#"<c r="B3"><f>SUM(A1:A3)</f>"#,
+        r#"<c r="C1"><f t="array" ref="C1:C3">A1:A3*2</f>"#,
+        r#"<c r="B4"><f t="array" ref="B4">A1:A3+1</f>"#,
+        r#"<c r="A5" cm="1"><f t="array" ref="A5:A7">A1:A3*3</f>"#,
+    ] {
+        assert!(sheet.contains(cell), "{cell} not in {sheet}");
+    }
+    assert!(!sheet.contains("SINGLE"));
+}
+
 #[test]
 fn test_xlsx() {
     let mut entries = fs::read_dir("tests/calc_tests/")
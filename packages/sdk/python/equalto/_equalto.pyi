@@ -16,6 +16,47 @@ class PyCalcModel:
     def new_sheet(self) -> None: ...
     def delete_sheet_by_sheet_id(self, sheet_id: int) -> None: ...
     def rename_sheet(self, sheet: int, new_name: str) -> None: ...
+    def get_defined_names(self) -> list[tuple[str, int | None, str]]: ...
+    def new_defined_name(self, name: str, scope: int | None, formula: str) -> None: ...
+    def update_defined_name(
+        self, name: str, scope: int | None, new_name: str, new_scope: int | None, new_formula: str
+    ) -> None: ...
+    def delete_defined_name(self, name: str, scope: int | None) -> None: ...
+    def undo(self) -> None: ...
+    def redo(self) -> None: ...
+    def can_undo(self) -> bool: ...
+    def can_redo(self) -> bool: ...
+    def begin_transaction(self) -> None: ...
+    def end_transaction(self) -> None: ...
+    def get_r1c1_style(self) -> bool: ...
+    def set_r1c1_style(self, enabled: bool) -> None: ...
+    def set_events_enabled(self, enabled: bool) -> None: ...
+    def take_events(self) -> str: ...
+    def goal_seek(
+        self,
+        target_sheet: int,
+        target_row: int,
+        target_column: int,
+        goal: float,
+        changing_sheet: int,
+        changing_row: int,
+        changing_column: int,
+        options: str,
+    ) -> str: ...
+    def get_solver(self, sheet: int) -> str | None: ...
+    def set_solver(self, sheet: int, definition: str) -> None: ...
+    def delete_solver(self, sheet: int) -> None: ...
+    def solve(self, sheet: int, definition: str, apply: bool) -> str: ...
+    def format_formula(self, sheet: int, row: int, column: int, formula: str, options: str | None) -> str: ...
+    def localize_formula(
+        self, sheet: int, row: int, column: int, formula: str, locale: str, language: str
+    ) -> str: ...
+    def delocalize_formula(
+        self, sheet: int, row: int, column: int, formula: str, locale: str, language: str
+    ) -> str: ...
+    def evaluate_formula_steps(self, sheet: int, row: int, column: int) -> str: ...
+    def get_precedents(self, sheet: int, row: int, column: int, depth: int | None) -> str: ...
+    def get_dependents(self, sheet: int, row: int, column: int, depth: int | None) -> str: ...
     def set_cell_empty(self, sheet: int, row: int, column: int) -> None: ...
     def delete_cell(self, sheet: int, row: int, column: int) -> None: ...
     def get_timezone(self) -> str: ...
@@ -4,7 +4,7 @@ import json
 from datetime import date, datetime, timedelta
 from enum import Enum
 from functools import cached_property
-from typing import TYPE_CHECKING
+from typing import TYPE_CHECKING, Any
 
 from equalto._equalto import number_to_column
 from equalto.exceptions import WorkbookValueError
@@ -136,6 +136,58 @@ class Cell:
         self._model.set_user_input(*self.cell_ref, value)
         self.workbook.evaluate()
 
+    def localized_formula(self, locale: str, language: str) -> str | None:
+        """
+        Get the formula of the cell in `language` and with the separators of `locale`.
+
+        For instance `=SUMME(A1;0,5)` instead of `=SUM(A1,0.5)` with the "de" locale and language.
+        """
+        formula = self.formula
+        if formula is None:
+            return None
+        return self._model.localize_formula(*self.cell_ref, formula, locale, language)
+
+    def set_localized_formula(self, formula: str, locale: str, language: str) -> None:
+        """Update the cell with a formula written in `language` and with the separators of `locale`."""
+        self.formula = self._model.delocalize_formula(*self.cell_ref, formula, locale, language)
+
+    def formatted_formula(self, indent: int = 4, width: int = 80) -> str | None:
+        """
+        Get the formula of the cell split over several lines, with `indent` spaces for each level of nesting.
+
+        Only the parts longer than `width` characters are split. The result is valid input for `formula`.
+        """
+        formula = self.formula
+        if formula is None:
+            return None
+        options = json.dumps({"indent": indent, "width": width})
+        return self._model.format_formula(*self.cell_ref, formula, options)
+
+    def evaluation_steps(self) -> dict[str, Any]:
+        """
+        Evaluate the formula in the cell step by step, like the "Evaluate Formula" dialog in Excel.
+
+        Returns the parse tree of the formula. Each node has the `formula` of the sub-expression, its `value`
+        (None if it was not evaluated) and its `children`. Errors have the cell where they originated.
+        """
+        return json.loads(self._model.evaluate_formula_steps(*self.cell_ref))
+
+    def precedents(self, depth: int | None = None) -> list[dict[str, Any]]:
+        """
+        Get the trace arrows to this cell from the cells and ranges its formula reads, and then the arrows
+        to the formulas found in those, up to `depth` levels (all of them by default).
+
+        Each arrow has a `precedent` area, the `dependent` formula cell and its `depth`.
+        """
+        return json.loads(self._model.get_precedents(*self.cell_ref, depth))
+
+    def dependents(self, depth: int | None = None) -> list[dict[str, Any]]:
+        """
+        Get the trace arrows from this cell to the formulas that read it, and then the arrows from those
+        to the formulas that read them, up to `depth` levels (all of them by default).
+        """
+        return json.loads(self._model.get_dependents(*self.cell_ref, depth))
+
     @property
     def style(self) -> Style:
         return Style(self)
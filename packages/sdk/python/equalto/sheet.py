@@ -1,7 +1,8 @@
 from __future__ import annotations
 
+import json
 from functools import cached_property
-from typing import TYPE_CHECKING, Generator
+from typing import TYPE_CHECKING, Any, Generator
 
 from equalto.cell import Cell
 from equalto.exceptions import CellReferenceError, WorkbookError
@@ -76,6 +77,37 @@ class Sheet:
         self._model.delete_sheet_by_sheet_id(self.sheet_id)
         self._load_sheets_metadata()
 
+    @property
+    def solver(self) -> dict[str, Any] | None:
+        """Get the solver definition stored in the sheet, see `solve`."""
+        definition = self._model.get_solver(self.index)
+        return None if definition is None else json.loads(definition)
+
+    @solver.setter
+    def solver(self, definition: dict[str, Any] | None) -> None:
+        if definition is None:
+            self._model.delete_solver(self.index)
+        else:
+            self._model.set_solver(self.index, json.dumps(definition))
+
+    def solve(self, definition: dict[str, Any] | None = None, *, apply: bool = True) -> dict[str, Any]:
+        """
+        Optimise the `objective` cell changing the `variables` cells within the `constraints`.
+
+        The definition looks like {"objective": "B1", "goal": "Maximize", "variables": ["A1:A3"],
+        "constraints": [{"cells": "C1", "operator": "LessOrEqual", "value": "10"}], "method": "Linear"}.
+        The goal can also be "Minimize" or {"Value": 10}. References without a sheet name refer to this sheet.
+        The definition stored in the sheet is used by default.
+
+        With `apply` the variable cells keep the solution if one satisfying the constraints is found.
+        Returns the `status`, the `objective_value`, the `values` of the variables and the `iterations`.
+        """
+        if definition is None:
+            definition = self.solver
+            if definition is None:
+                raise WorkbookError("The sheet has no solver definition")
+        return json.loads(self._model.solve(self.index, json.dumps(definition), apply))
+
     @cached_property
     def _model(self) -> PyCalcModel:
         return self.workbook_sheets._model  # noqa: WPS437
@@ -1,8 +1,9 @@
 from __future__ import annotations
 
+import json
 import os
 from functools import cached_property
-from typing import TYPE_CHECKING
+from typing import TYPE_CHECKING, Any, Iterator
 from zoneinfo import ZoneInfo
 
 from equalto.exceptions import CellReferenceError, SuppressEvaluationErrors, WorkbookError, WorkbookEvaluationError
@@ -60,6 +61,47 @@ class Workbook:
     def json(self) -> str:
         return self._model.to_json()
 
+    @property
+    def r1c1_style(self) -> bool:
+        """Whether cell formulas are written and read with R1C1 references, i.e. `=R[-1]C*2` instead of `=A1*2`."""
+        return self._model.get_r1c1_style()
+
+    @r1c1_style.setter
+    def r1c1_style(self, enabled: bool) -> None:
+        self._model.set_r1c1_style(enabled)
+
+    def enable_events(self, enabled: bool = True) -> None:
+        """Start or stop recording the changes returned by `events`."""
+        self._model.set_events_enabled(enabled)
+
+    def events(self) -> Iterator[dict[str, Any]]:
+        """
+        Iterate over the changes since the last call: the cells whose content, value or style changed
+        in each evaluation and changes like inserted rows or renamed sheets.
+        """
+        yield from json.loads(self._model.take_events())
+
+    def goal_seek(
+        self,
+        target: str,
+        goal: float,
+        changing: str,
+        *,
+        max_iterations: int = 100,
+        tolerance: float = 0.001,
+        apply: bool = True,
+    ) -> dict[str, Any]:
+        """
+        Change the value of the `changing` cell (i.e. "Sheet1!A1") until the formula in the `target` cell
+        evaluates to `goal`. With `apply` the changing cell keeps the solution if the goal was reached,
+        otherwise the workbook is left unchanged.
+
+        Returns the `solution`, the `target_value` reached, the number of `iterations` and whether it `converged`.
+        """
+        options = {"max_iterations": max_iterations, "tolerance": tolerance, "apply": apply}
+        result = self._model.goal_seek(*self[target].cell_ref, goal, *self[changing].cell_ref, json.dumps(options))
+        return json.loads(result)
+
     def evaluate(self) -> None:
         errors = self._model.evaluate_with_error_check()
         if not errors:
@@ -1,9 +1,14 @@
 use pyo3::exceptions::{PyException, PyValueError};
 use pyo3::{create_exception, prelude::*, wrap_pyfunction};
 
+use equalto_calc::calc_result::CellReference;
+use equalto_calc::expressions::parser::format::FormatOptions;
 use equalto_calc::expressions::utils;
+use equalto_calc::goal_seek::GoalSeekOptions;
 use equalto_calc::model::Model;
+use equalto_calc::solver::SolverDefinition;
 use equalto_calc::types::CellType;
+use equalto_calc::types::ReferenceStyle;
 use equalto_calc::types::Worksheet;
 use equalto_xlsx::error::XlsxError;
 use equalto_xlsx::export::save_to_xlsx;
@@ -133,6 +138,295 @@ impl PyModel {
             .map_err(WorkbookError::new_err)
     }
 
+    pub fn get_defined_names(&self) -> PyResult<Vec<(String, Option<i32>, String)>> {
+        Ok(self
+            .model
+            .get_defined_name_list()
+            .into_iter()
+            .map(|(name, scope, formula)| {
+                (name, scope.map(|sheet| sheet.try_into().unwrap()), formula)
+            })
+            .collect())
+    }
+
+    pub fn new_defined_name(
+        &mut self,
+        name: &str,
+        scope: Option<i32>,
+        formula: &str,
+    ) -> PyResult<()> {
+        self.model
+            .new_defined_name(name, scope.map(|sheet| sheet.try_into().unwrap()), formula)
+            .map_err(WorkbookError::new_err)
+    }
+
+    pub fn update_defined_name(
+        &mut self,
+        name: &str,
+        scope: Option<i32>,
+        new_name: &str,
+        new_scope: Option<i32>,
+        new_formula: &str,
+    ) -> PyResult<()> {
+        self.model
+            .update_defined_name(
+                name,
+                scope.map(|sheet| sheet.try_into().unwrap()),
+                new_name,
+                new_scope.map(|sheet| sheet.try_into().unwrap()),
+                new_formula,
+            )
+            .map_err(WorkbookError::new_err)
+    }
+
+    pub fn delete_defined_name(&mut self, name: &str, scope: Option<i32>) -> PyResult<()> {
+        self.model
+            .delete_defined_name(name, scope.map(|sheet| sheet.try_into().unwrap()))
+            .map_err(WorkbookError::new_err)
+    }
+
+    pub fn undo(&mut self) -> PyResult<()> {
+        self.model.undo().map_err(WorkbookError::new_err)
+    }
+
+    pub fn redo(&mut self) -> PyResult<()> {
+        self.model.redo().map_err(WorkbookError::new_err)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.model.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.model.can_redo()
+    }
+
+    pub fn begin_transaction(&mut self) {
+        self.model.begin_transaction();
+    }
+
+    pub fn end_transaction(&mut self) {
+        self.model.end_transaction();
+    }
+
+    pub fn get_r1c1_style(&self) -> bool {
+        self.model.get_reference_style() == ReferenceStyle::R1C1
+    }
+
+    pub fn set_r1c1_style(&mut self, enabled: bool) {
+        let reference_style = if enabled {
+            ReferenceStyle::R1C1
+        } else {
+            ReferenceStyle::A1
+        };
+        self.model.set_reference_style(reference_style);
+    }
+
+    pub fn set_events_enabled(&mut self, enabled: bool) {
+        self.model.set_events_enabled(enabled);
+    }
+
+    pub fn take_events(&mut self) -> PyResult<String> {
+        serde_json::to_string(&self.model.take_events())
+            .map_err(|_| WorkbookError::new_err("Could not stringify events to JSON."))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn goal_seek(
+        &mut self,
+        target_sheet: i32,
+        target_row: i32,
+        target_column: i32,
+        goal: f64,
+        changing_sheet: i32,
+        changing_row: i32,
+        changing_column: i32,
+        options: &str,
+    ) -> PyResult<String> {
+        let options: GoalSeekOptions = serde_json::from_str(options)
+            .map_err(|_| WorkbookError::new_err("Could not parse the goal seek options."))?;
+        let target = CellReference {
+            sheet: target_sheet.try_into().unwrap(),
+            row: target_row,
+            column: target_column,
+        };
+        let changing = CellReference {
+            sheet: changing_sheet.try_into().unwrap(),
+            row: changing_row,
+            column: changing_column,
+        };
+        let result = self
+            .model
+            .goal_seek(target, goal, changing, &options)
+            .map_err(WorkbookError::new_err)?;
+        serde_json::to_string(&result).map_err(|_| {
+            WorkbookError::new_err("Could not stringify the goal seek result to JSON.")
+        })
+    }
+
+    pub fn get_solver(&self, sheet: i32) -> PyResult<Option<String>> {
+        match self
+            .model
+            .get_solver(sheet.try_into().unwrap())
+            .map_err(WorkbookError::new_err)?
+        {
+            Some(definition) => serde_json::to_string(&definition)
+                .map(Some)
+                .map_err(|_| WorkbookError::new_err("Could not stringify the solver to JSON.")),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_solver(&mut self, sheet: i32, definition: &str) -> PyResult<()> {
+        let definition: SolverDefinition = serde_json::from_str(definition)
+            .map_err(|_| WorkbookError::new_err("Could not parse the solver definition."))?;
+        self.model
+            .set_solver(sheet.try_into().unwrap(), &definition)
+            .map_err(WorkbookError::new_err)
+    }
+
+    pub fn delete_solver(&mut self, sheet: i32) -> PyResult<()> {
+        self.model
+            .delete_solver(sheet.try_into().unwrap())
+            .map_err(WorkbookError::new_err)
+    }
+
+    pub fn solve(&mut self, sheet: i32, definition: &str, apply: bool) -> PyResult<String> {
+        let definition: SolverDefinition = serde_json::from_str(definition)
+            .map_err(|_| WorkbookError::new_err("Could not parse the solver definition."))?;
+        let result = self
+            .model
+            .solve(sheet.try_into().unwrap(), &definition, apply)
+            .map_err(WorkbookError::new_err)?;
+        serde_json::to_string(&result)
+            .map_err(|_| WorkbookError::new_err("Could not stringify the solver result to JSON."))
+    }
+
+    pub fn format_formula(
+        &mut self,
+        sheet: i32,
+        row: i32,
+        column: i32,
+        formula: &str,
+        options: Option<&str>,
+    ) -> PyResult<String> {
+        let options: Option<FormatOptions> = options
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(|_| WorkbookError::new_err("Could not parse the format options."))?;
+        self.model
+            .format_formula(
+                sheet.try_into().unwrap(),
+                row,
+                column,
+                formula,
+                options.as_ref(),
+            )
+            .map_err(WorkbookError::new_err)
+    }
+
+    pub fn localize_formula(
+        &self,
+        sheet: i32,
+        row: i32,
+        column: i32,
+        formula: &str,
+        locale: &str,
+        language: &str,
+    ) -> PyResult<String> {
+        self.model
+            .localize_formula(
+                sheet.try_into().unwrap(),
+                row,
+                column,
+                formula,
+                locale,
+                language,
+            )
+            .map_err(WorkbookError::new_err)
+    }
+
+    pub fn delocalize_formula(
+        &self,
+        sheet: i32,
+        row: i32,
+        column: i32,
+        formula: &str,
+        locale: &str,
+        language: &str,
+    ) -> PyResult<String> {
+        self.model
+            .delocalize_formula(
+                sheet.try_into().unwrap(),
+                row,
+                column,
+                formula,
+                locale,
+                language,
+            )
+            .map_err(WorkbookError::new_err)
+    }
+
+    pub fn evaluate_formula_steps(
+        &mut self,
+        sheet: i32,
+        row: i32,
+        column: i32,
+    ) -> PyResult<String> {
+        let cell = CellReference {
+            sheet: sheet.try_into().unwrap(),
+            row,
+            column,
+        };
+        let steps = self
+            .model
+            .evaluate_formula_steps(cell)
+            .map_err(WorkbookError::new_err)?;
+        serde_json::to_string(&steps).map_err(|_| {
+            WorkbookError::new_err("Could not stringify the evaluation steps to JSON.")
+        })
+    }
+
+    pub fn get_precedents(
+        &self,
+        sheet: i32,
+        row: i32,
+        column: i32,
+        depth: Option<usize>,
+    ) -> PyResult<String> {
+        let cell = CellReference {
+            sheet: sheet.try_into().unwrap(),
+            row,
+            column,
+        };
+        let arrows = self
+            .model
+            .precedents(cell, depth)
+            .map_err(WorkbookError::new_err)?;
+        serde_json::to_string(&arrows)
+            .map_err(|_| WorkbookError::new_err("Could not stringify the precedents to JSON."))
+    }
+
+    pub fn get_dependents(
+        &self,
+        sheet: i32,
+        row: i32,
+        column: i32,
+        depth: Option<usize>,
+    ) -> PyResult<String> {
+        let cell = CellReference {
+            sheet: sheet.try_into().unwrap(),
+            row,
+            column,
+        };
+        let arrows = self
+            .model
+            .dependents(cell, depth)
+            .map_err(WorkbookError::new_err)?;
+        serde_json::to_string(&arrows)
+            .map_err(|_| WorkbookError::new_err("Could not stringify the dependents to JSON."))
+    }
+
     pub fn update_cell_with_text(&mut self, sheet: i32, row: i32, column: i32, value: &str) {
         self.model
             .update_cell_with_text(sheet.try_into().unwrap(), row, column, value);
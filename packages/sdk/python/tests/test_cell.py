@@ -320,5 +320,77 @@ def test_set_naive_datetime(cell: Cell) -> None:
         cell.value = datetime(2023, 1, 9, 12, 30, 15)
 
 
+def test_precedents_and_dependents(empty_workbook: Workbook) -> None:
+    empty_workbook["Sheet1!A1"].value = 1
+    empty_workbook["Sheet1!B1"].formula = "=A1*2"
+    empty_workbook["Sheet1!C1"].formula = "=B1+A1"
+
+    assert empty_workbook["Sheet1!C1"].precedents(depth=1) == [
+        {
+            "precedent": {"sheet": 0, "row": 1, "column": 2, "width": 1, "height": 1},
+            "dependent": {"sheet": 0, "row": 1, "column": 3},
+            "depth": 1,
+        },
+        {
+            "precedent": {"sheet": 0, "row": 1, "column": 1, "width": 1, "height": 1},
+            "dependent": {"sheet": 0, "row": 1, "column": 3},
+            "depth": 1,
+        },
+    ]
+    assert len(empty_workbook["Sheet1!C1"].precedents()) == 3
+
+    dependents = empty_workbook["Sheet1!A1"].dependents()
+    assert [(arrow["dependent"]["column"], arrow["depth"]) for arrow in dependents] == [(2, 1), (3, 1), (3, 2)]
+    assert empty_workbook["Sheet1!C1"].dependents() == []
+
+
+def test_evaluation_steps(empty_workbook: Workbook) -> None:
+    empty_workbook["Sheet1!A1"].value = "hello"
+    empty_workbook["Sheet1!B1"].formula = "=IF(A1=1,A1,A1*2)"
+
+    steps = empty_workbook["Sheet1!B1"].evaluation_steps()
+    assert steps["formula"] == "IF(A1=1,A1,A1*2)"
+    assert steps["value"]["type"] == "Error"
+    assert steps["value"]["error"] == "#VALUE!"
+    assert steps["value"]["origin"] == {"sheet": 0, "row": 1, "column": 2}
+    condition, value_if_true, value_if_false = steps["children"]
+    assert condition["value"] == {"type": "Boolean", "value": False}
+    assert value_if_true["value"]["type"] == "Reference"
+    assert value_if_false["formula"] == "A1*2"
+    assert value_if_false["value"]["type"] == "Error"
+
+    with pytest.raises(WorkbookError, match="The cell does not contain a formula"):
+        empty_workbook["Sheet1!A1"].evaluation_steps()
+
+
+def test_formatted_formula(empty_workbook: Workbook) -> None:
+    cell = empty_workbook["Sheet1!B1"]
+    cell.formula = '=IF(A1>10,"High",IF(A1>5,"Medium","Low"))'
+    assert cell.formatted_formula() == '=IF(A1>10,"High",IF(A1>5,"Medium","Low"))'
+
+    formatted = cell.formatted_formula(indent=2, width=20)
+    assert formatted == '=IF(\n  A1>10,\n  "High",\n  IF(\n    A1>5,\n    "Medium",\n    "Low"\n  )\n)'
+
+    cell.formula = formatted
+    assert cell.formula == '=IF(A1>10,"High",IF(A1>5,"Medium","Low"))'
+
+    assert empty_workbook["Sheet1!A1"].formatted_formula() is None
+
+
+def test_localized_formula(empty_workbook: Workbook) -> None:
+    empty_workbook["Sheet1!A1"].value = 2
+    cell = empty_workbook["Sheet1!B1"]
+    cell.set_localized_formula("=WENN(A1>1;SUMME(A1;0,5);FALSCH)", "de", "de")
+    assert cell.formula == "=IF(A1>1,SUM(A1,0.5),FALSE)"
+    assert cell.value == 2.5
+
+    assert cell.localized_formula("de", "de") == "=WENN(A1>1;SUMME(A1;0,5);FALSCH)"
+    assert cell.localized_formula("es", "es") == "=SI(A1>1;SUMA(A1;0,5);FALSO)"
+    assert empty_workbook["Sheet1!A1"].localized_formula("de", "de") is None
+
+    with pytest.raises(WorkbookError):
+        cell.set_localized_formula("=SUMME(A1,A2)", "de", "de")
+
+
 def _get_tz_cell(tz: str) -> Cell:
     return equalto.new(timezone=ZoneInfo(tz)).sheets[0]["A1"]
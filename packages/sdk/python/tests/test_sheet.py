@@ -276,5 +276,35 @@ def test_cell_instance_reused(empty_workbook: Workbook) -> None:
     assert id(cell) == id(empty_workbook["Sheet1!A1"])
 
 
+def test_solver(sheet: Sheet) -> None:
+    sheet["B1"].formula = "=3*A1+5*A2"
+    sheet["C1"].formula = "=3*A1+2*A2"
+    assert sheet.solver is None
+
+    sheet.solver = {
+        "objective": "B1",
+        "goal": "Maximize",
+        "variables": ["A1:A2"],
+        "constraints": [
+            {"cells": "A1", "operator": "LessOrEqual", "value": "4"},
+            {"cells": "A2", "operator": "LessOrEqual", "value": "6"},
+            {"cells": "C1", "operator": "LessOrEqual", "value": "18"},
+        ],
+        "method": "Linear",
+    }
+    assert sheet.solver is not None
+    assert sheet.solver["objective"] == "Sheet1!$B$1"
+
+    result = sheet.solve()
+    assert result["status"] == "Optimal"
+    assert result["values"] == pytest.approx([2, 6])
+    assert sheet["B1"].value == pytest.approx(36)
+
+    sheet.solver = None
+    assert sheet.solver is None
+    with pytest.raises(WorkbookError, match="The sheet has no solver definition"):
+        sheet.solve()
+
+
 def _get_sheets(workbook: Workbook) -> list[tuple[int, str]]:
     return [(sheet.index, sheet.name) for sheet in workbook.sheets]
@@ -6,7 +6,7 @@ import pytest
 
 import equalto
 from equalto.cell import Cell
-from equalto.exceptions import CellReferenceError, SuppressEvaluationErrors, WorkbookEvaluationError
+from equalto.exceptions import CellReferenceError, SuppressEvaluationErrors, WorkbookError, WorkbookEvaluationError
 from equalto.workbook import Workbook
 
 
@@ -93,3 +93,50 @@ def test_suppress_evaluation_errors(cell: Cell) -> None:
     # the errors are normally raised after leaving the context
     with pytest.raises(WorkbookEvaluationError):
         cell.formula = "=INVALID()"
+
+
+def test_r1c1_style(empty_workbook: Workbook) -> None:
+    assert not empty_workbook.r1c1_style
+    empty_workbook["Sheet1!A1"].value = 2
+    empty_workbook["Sheet1!A2"].formula = "=A1*2"
+
+    empty_workbook.r1c1_style = True
+    assert empty_workbook.r1c1_style
+    assert empty_workbook["Sheet1!A2"].formula == "=R[-1]C*2"
+    empty_workbook["Sheet1!A3"].formula = "=SUM(R1C1:R[-1]C)"
+    assert empty_workbook["Sheet1!A3"].value == 6
+
+    empty_workbook.r1c1_style = False
+    assert empty_workbook["Sheet1!A3"].formula == "=SUM($A$1:A2)"
+
+
+def test_events(empty_workbook: Workbook) -> None:
+    empty_workbook.enable_events()
+    empty_workbook["Sheet1!A1"].value = 2
+    empty_workbook["Sheet1!A2"].formula = "=A1*2"
+
+    assert list(empty_workbook.events()) == [
+        {"type": "CellChanged", "sheet": 0, "row": 1, "column": 1},
+        {"type": "CellChanged", "sheet": 0, "row": 2, "column": 1},
+    ]
+    assert not list(empty_workbook.events())
+
+    empty_workbook["Sheet1!A1"].value = 2
+    assert not list(empty_workbook.events())
+
+
+def test_goal_seek(empty_workbook: Workbook) -> None:
+    empty_workbook["Sheet1!A1"].value = 1
+    empty_workbook["Sheet1!A2"].formula = "=A1*3+4"
+
+    result = empty_workbook.goal_seek("Sheet1!A2", 19, "Sheet1!A1", apply=False)
+    assert result["converged"]
+    assert result["solution"] == pytest.approx(5)
+    assert empty_workbook["Sheet1!A1"].value == 1
+
+    empty_workbook.goal_seek("Sheet1!A2", 19, "Sheet1!A1")
+    assert empty_workbook["Sheet1!A1"].value == pytest.approx(5)
+    assert empty_workbook["Sheet1!A2"].value == pytest.approx(19, abs=0.001)
+
+    with pytest.raises(WorkbookError, match="The target cell must contain a formula"):
+        empty_workbook.goal_seek("Sheet1!A1", 19, "Sheet1!A2")
@@ -1,6 +1,6 @@
 use crate::error::WorkbookError;
 use crate::workbook::Workbook;
-use equalto_calc::{calc_result, cell};
+use equalto_calc::{calc_result, cell, trace::TraceArrow};
 
 pub enum CellReference {
     Text(String), // i.e. Sheet1!A1
@@ -71,6 +71,32 @@ impl Workbook {
         Ok(())
     }
 
+    /// Arrows to the cell from the cells and ranges it reads, `depth` levels deep (all if None)
+    pub fn precedents<C>(
+        &self,
+        cell: C,
+        depth: Option<usize>,
+    ) -> Result<Vec<TraceArrow>, WorkbookError>
+    where
+        C: Into<CellReference>,
+    {
+        let cell = self.parse_cell_reference(cell)?;
+        Ok(self.calc_model.precedents(cell, depth)?)
+    }
+
+    /// Arrows from the cell to the formulas that read it, `depth` levels deep (all if None)
+    pub fn dependents<C>(
+        &self,
+        cell: C,
+        depth: Option<usize>,
+    ) -> Result<Vec<TraceArrow>, WorkbookError>
+    where
+        C: Into<CellReference>,
+    {
+        let cell = self.parse_cell_reference(cell)?;
+        Ok(self.calc_model.dependents(cell, depth)?)
+    }
+
     fn set_empty(&mut self, cell: &calc_result::CellReference) {
         self.calc_model
             .set_cell_empty(cell.sheet, cell.row, cell.column)
@@ -59,4 +59,102 @@ mod tests {
             CellValue::String("Tres".to_string()),
         );
     }
+
+    #[test]
+    fn test_defined_names() {
+        let mut workbook = Workbook::new().unwrap();
+
+        workbook.set_value("Sheet1!A1", 100.0).unwrap();
+        workbook
+            .new_defined_name("Price", None, "=Sheet1!$A$1")
+            .unwrap();
+        workbook.set_formula("Sheet1!A2", "=Price*2").unwrap();
+        assert_eq!(
+            workbook.value("Sheet1!A2").unwrap(),
+            CellValue::Number(200.0),
+        );
+
+        workbook
+            .update_defined_name("Price", None, "Cost", None, "=Sheet1!$A$1/2")
+            .unwrap();
+        assert_eq!(
+            workbook.formula("Sheet1!A2").unwrap(),
+            Some("=Cost*2".to_string()),
+        );
+        assert_eq!(
+            workbook.value("Sheet1!A2").unwrap(),
+            CellValue::Number(100.0),
+        );
+        assert_eq!(
+            workbook.defined_names(),
+            vec![("Cost".to_string(), None, "Sheet1!$A$1/2".to_string())],
+        );
+
+        workbook.delete_defined_name("Cost", None).unwrap();
+        assert!(workbook.defined_names().is_empty());
+        assert!(workbook.delete_defined_name("Cost", None).is_err());
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut workbook = Workbook::new().unwrap();
+
+        workbook.set_value("Sheet1!A1", 1.0).unwrap();
+        workbook.set_formula("Sheet1!A2", "=A1*2").unwrap();
+        workbook.set_value("Sheet1!A1", 5.0).unwrap();
+        assert_eq!(
+            workbook.value("Sheet1!A2").unwrap(),
+            CellValue::Number(10.0),
+        );
+
+        workbook.undo().unwrap();
+        assert_eq!(workbook.value("Sheet1!A2").unwrap(), CellValue::Number(2.0),);
+        workbook.undo().unwrap();
+        assert_eq!(workbook.formula("Sheet1!A2").unwrap(), None);
+
+        workbook.redo().unwrap();
+        workbook.redo().unwrap();
+        assert_eq!(
+            workbook.value("Sheet1!A2").unwrap(),
+            CellValue::Number(10.0),
+        );
+    }
+
+    #[test]
+    fn test_r1c1_style() {
+        let mut workbook = Workbook::new().unwrap();
+
+        workbook.set_value("Sheet1!A1", 3.0).unwrap();
+        workbook.set_r1c1_style(true);
+        assert!(workbook.r1c1_style());
+        workbook.set_formula("Sheet1!A2", "=R[-1]C*2").unwrap();
+        assert_eq!(workbook.value("Sheet1!A2").unwrap(), CellValue::Number(6.0),);
+
+        workbook.set_r1c1_style(false);
+        assert_eq!(
+            workbook.formula("Sheet1!A2").unwrap(),
+            Some("=A1*2".to_string()),
+        );
+    }
+
+    #[test]
+    fn test_precedents_and_dependents() {
+        let mut workbook = Workbook::new().unwrap();
+
+        workbook.set_value("Sheet1!A1", 1.0).unwrap();
+        workbook.set_formula("Sheet1!B1", "=A1*2").unwrap();
+        workbook.set_formula("Sheet1!C1", "=B1+A1").unwrap();
+
+        let arrows = workbook.precedents("Sheet1!C1", None).unwrap();
+        assert_eq!(arrows.len(), 3);
+        assert_eq!(workbook.precedents("Sheet1!C1", Some(1)).unwrap().len(), 2);
+
+        let arrows = workbook.dependents("Sheet1!A1", Some(1)).unwrap();
+        let dependents: Vec<(i32, i32)> = arrows
+            .iter()
+            .map(|arrow| (arrow.dependent.row, arrow.dependent.column))
+            .collect();
+        assert_eq!(dependents, vec![(1, 2), (1, 3)]);
+        assert!(workbook.dependents("Sheet1!C1", None).unwrap().is_empty());
+    }
 }
@@ -1,5 +1,6 @@
 use crate::error::WorkbookError;
 use equalto_calc::model::Model;
+use equalto_calc::types::ReferenceStyle;
 use equalto_xlsx::import::load_from_excel;
 
 pub struct Workbook {
@@ -19,3 +20,74 @@ impl Workbook {
         Ok(Self { calc_model })
     }
 }
+
+impl Workbook {
+    /// Returns the defined names as (name, scope, formula).
+    /// The scope is the sheet index for local names and None for global names.
+    pub fn defined_names(&self) -> Vec<(String, Option<u32>, String)> {
+        self.calc_model.get_defined_name_list()
+    }
+
+    pub fn new_defined_name(
+        &mut self,
+        name: &str,
+        scope: Option<u32>,
+        formula: &str,
+    ) -> Result<(), WorkbookError> {
+        self.calc_model.new_defined_name(name, scope, formula)?;
+        self.calc_model.evaluate_with_error_check()?;
+        Ok(())
+    }
+
+    pub fn update_defined_name(
+        &mut self,
+        name: &str,
+        scope: Option<u32>,
+        new_name: &str,
+        new_scope: Option<u32>,
+        new_formula: &str,
+    ) -> Result<(), WorkbookError> {
+        self.calc_model
+            .update_defined_name(name, scope, new_name, new_scope, new_formula)?;
+        self.calc_model.evaluate_with_error_check()?;
+        Ok(())
+    }
+
+    pub fn delete_defined_name(
+        &mut self,
+        name: &str,
+        scope: Option<u32>,
+    ) -> Result<(), WorkbookError> {
+        self.calc_model.delete_defined_name(name, scope)?;
+        self.calc_model.evaluate_with_error_check()?;
+        Ok(())
+    }
+
+    /// Undoes the last change to the workbook
+    pub fn undo(&mut self) -> Result<(), WorkbookError> {
+        self.calc_model.undo()?;
+        self.calc_model.evaluate_with_error_check()?;
+        Ok(())
+    }
+
+    /// Whether formulas are written and read with R1C1 references: `=R[-1]C*2`
+    pub fn r1c1_style(&self) -> bool {
+        self.calc_model.get_reference_style() == ReferenceStyle::R1C1
+    }
+
+    pub fn set_r1c1_style(&mut self, enabled: bool) {
+        let reference_style = if enabled {
+            ReferenceStyle::R1C1
+        } else {
+            ReferenceStyle::A1
+        };
+        self.calc_model.set_reference_style(reference_style);
+    }
+
+    /// Redoes the last change that was undone
+    pub fn redo(&mut self) -> Result<(), WorkbookError> {
+        self.calc_model.redo()?;
+        self.calc_model.evaluate_with_error_check()?;
+        Ok(())
+    }
+}
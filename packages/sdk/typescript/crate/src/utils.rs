@@ -128,6 +128,9 @@ fn to_json_string(marked_token: &MarkedToken) -> String {
         TokenType::And => json!({
             "type": "AND",
         }),
+        TokenType::Hash => json!({
+            "type": "HASH",
+        }),
         TokenType::Reference {
             sheet,
             row,
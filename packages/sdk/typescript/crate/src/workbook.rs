@@ -4,9 +4,16 @@ use wasm_bindgen::{
 };
 
 use equalto_calc::{
+    calc_result::CellReference,
     cell::CellValue,
-    expressions::types::{Area, CellReferenceIndex},
+    expressions::{
+        parser::format::FormatOptions,
+        types::{Area, CellReferenceIndex},
+    },
+    goal_seek::GoalSeekOptions,
     model::Model,
+    solver::SolverDefinition,
+    types::ReferenceStyle,
     worksheet::NavigationDirection,
 };
 
@@ -115,9 +122,17 @@ pub struct WasmSheetDimension {
     pub max_row: i32,
 }
 
+fn parse_solver_definition(definition: &str) -> Result<SolverDefinition, WorkbookError> {
+    serde_json::from_str(definition)
+        .map_err(|_| "Could not parse the solver definition.".to_string())
+        .map_err(WorkbookError::from)
+}
+
 #[wasm_bindgen]
 pub struct WasmWorkbook {
     model: Model,
+    /// Called with the events of the model after each evaluation
+    event_listener: Option<js_sys::Function>,
 }
 
 #[wasm_bindgen]
@@ -125,7 +140,10 @@ impl WasmWorkbook {
     #[wasm_bindgen(constructor)]
     pub fn new(locale: &str, timezone: &str) -> Result<WasmWorkbook, JsError> {
         let model = Model::new_empty("workbook", locale, timezone).map_err(WorkbookError::from)?;
-        Ok(WasmWorkbook { model })
+        Ok(WasmWorkbook {
+            model,
+            event_listener: None,
+        })
     }
 
     #[wasm_bindgen(js_name=loadFromMemory)]
@@ -137,13 +155,19 @@ impl WasmWorkbook {
     ) -> Result<WasmWorkbook, JsError> {
         let model = load_xlsx_from_memory("workbook", data, locale, timezone)
             .map_err(WorkbookError::from)?;
-        Ok(WasmWorkbook { model })
+        Ok(WasmWorkbook {
+            model,
+            event_listener: None,
+        })
     }
 
     #[wasm_bindgen(js_name=loadFromJson)]
     pub fn load_from_json(workbook_json: &str) -> Result<WasmWorkbook, JsError> {
         let model = Model::from_json(workbook_json).map_err(WorkbookError::from)?;
-        Ok(WasmWorkbook { model })
+        Ok(WasmWorkbook {
+            model,
+            event_listener: None,
+        })
     }
 
     #[wasm_bindgen(js_name=saveToMemory)]
@@ -165,6 +189,34 @@ impl WasmWorkbook {
 
     pub fn evaluate(&mut self) -> Result<(), JsError> {
         self.model.evaluate();
+        self.emit_events()
+    }
+
+    /// Sets a function called after each evaluation with a JSON list of the events since
+    /// the previous one: the cells that changed and changes like inserted rows or renamed sheets.
+    /// Events are only recorded while there is a listener, `null` removes it.
+    #[wasm_bindgen(js_name = "setEventListener")]
+    pub fn set_event_listener(&mut self, listener: Option<js_sys::Function>) {
+        self.model.set_events_enabled(listener.is_some());
+        self.event_listener = listener;
+    }
+
+    fn emit_events(&mut self) -> Result<(), JsError> {
+        let listener = match &self.event_listener {
+            Some(listener) => listener,
+            None => return Ok(()),
+        };
+        let events = self.model.take_events();
+        if events.is_empty() {
+            return Ok(());
+        }
+        let events = serde_json::to_string(&events)
+            .map_err(|_| "Could not stringify events to JSON.".to_string())
+            .map_err(WorkbookError::from)?;
+        listener
+            .call1(&JsValue::NULL, &JsValue::from(events))
+            .map_err(|_| "The event listener failed.".to_string())
+            .map_err(WorkbookError::from)?;
         Ok(())
     }
 
@@ -225,6 +277,328 @@ impl WasmWorkbook {
             .map_err(JsError::from)
     }
 
+    /// Returns a JSON list of [name, scope, formula], where the scope is the sheet index
+    /// for local names and null for global names
+    #[wasm_bindgen(js_name = "getDefinedNames")]
+    pub fn get_defined_names(&self) -> Result<String, JsError> {
+        Ok(serde_json::to_string(&self.model.get_defined_name_list())
+            .map_err(|_| "Could not stringify defined names to JSON.".to_string())
+            .map_err(WorkbookError::from)?)
+    }
+
+    #[wasm_bindgen(js_name = "newDefinedName")]
+    pub fn new_defined_name(
+        &mut self,
+        name: &str,
+        scope: Option<u32>,
+        formula: &str,
+    ) -> Result<(), JsError> {
+        self.model
+            .new_defined_name(name, scope, formula)
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    #[wasm_bindgen(js_name = "updateDefinedName")]
+    pub fn update_defined_name(
+        &mut self,
+        name: &str,
+        scope: Option<u32>,
+        new_name: &str,
+        new_scope: Option<u32>,
+        new_formula: &str,
+    ) -> Result<(), JsError> {
+        self.model
+            .update_defined_name(name, scope, new_name, new_scope, new_formula)
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    #[wasm_bindgen(js_name = "deleteDefinedName")]
+    pub fn delete_defined_name(&mut self, name: &str, scope: Option<u32>) -> Result<(), JsError> {
+        self.model
+            .delete_defined_name(name, scope)
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    /// Undoes the last change or transaction. The workbook needs to be evaluated afterwards.
+    pub fn undo(&mut self) -> Result<(), JsError> {
+        self.model
+            .undo()
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    pub fn redo(&mut self) -> Result<(), JsError> {
+        self.model
+            .redo()
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    #[wasm_bindgen(js_name = "canUndo")]
+    pub fn can_undo(&self) -> bool {
+        self.model.can_undo()
+    }
+
+    #[wasm_bindgen(js_name = "canRedo")]
+    pub fn can_redo(&self) -> bool {
+        self.model.can_redo()
+    }
+
+    /// Groups all the changes until `endTransaction` so that they are undone together
+    #[wasm_bindgen(js_name = "beginTransaction")]
+    pub fn begin_transaction(&mut self) {
+        self.model.begin_transaction();
+    }
+
+    #[wasm_bindgen(js_name = "endTransaction")]
+    pub fn end_transaction(&mut self) {
+        self.model.end_transaction();
+    }
+
+    /// Changes the value of the changing cell until the target cell evaluates to `goal`.
+    /// `options` is a JSON object with `max_iterations`, `tolerance` and `apply`, all optional.
+    /// Returns a JSON object with the `solution`, the `target_value` reached, the number of
+    /// `iterations` and whether it `converged`.
+    #[wasm_bindgen(js_name = "goalSeek")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn goal_seek(
+        &mut self,
+        target_sheet: u32,
+        target_row: i32,
+        target_column: i32,
+        goal: f64,
+        changing_sheet: u32,
+        changing_row: i32,
+        changing_column: i32,
+        options: Option<String>,
+    ) -> Result<String, JsError> {
+        let options: GoalSeekOptions = match options {
+            Some(options) => serde_json::from_str(&options)
+                .map_err(|_| "Could not parse the goal seek options.".to_string())
+                .map_err(WorkbookError::from)?,
+            None => GoalSeekOptions::default(),
+        };
+        let target = CellReference {
+            sheet: target_sheet,
+            row: target_row,
+            column: target_column,
+        };
+        let changing = CellReference {
+            sheet: changing_sheet,
+            row: changing_row,
+            column: changing_column,
+        };
+        let result = self
+            .model
+            .goal_seek(target, goal, changing, &options)
+            .map_err(WorkbookError::from)?;
+        self.emit_events()?;
+        serde_json::to_string(&result)
+            .map_err(|_| "Could not stringify the goal seek result to JSON.".to_string())
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    /// Returns the solver definition stored in the sheet as JSON, or `null`
+    #[wasm_bindgen(js_name = "getSolver")]
+    pub fn get_solver(&self, sheet: u32) -> Result<Option<String>, JsError> {
+        match self.model.get_solver(sheet).map_err(WorkbookError::from)? {
+            Some(definition) => Ok(Some(
+                serde_json::to_string(&definition)
+                    .map_err(|_| "Could not stringify the solver to JSON.".to_string())
+                    .map_err(WorkbookError::from)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    #[wasm_bindgen(js_name = "setSolver")]
+    pub fn set_solver(&mut self, sheet: u32, definition: &str) -> Result<(), JsError> {
+        let definition = parse_solver_definition(definition)?;
+        self.model
+            .set_solver(sheet, &definition)
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    #[wasm_bindgen(js_name = "deleteSolver")]
+    pub fn delete_solver(&mut self, sheet: u32) -> Result<(), JsError> {
+        self.model
+            .delete_solver(sheet)
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    /// Optimises the objective cell of the JSON solver `definition` changing its variable cells
+    /// within the constraints. With `apply` the variable cells keep the solution if one satisfying
+    /// the constraints is found. Returns a JSON object with the `status`, the `objective_value`,
+    /// the `values` of the variables and the number of `iterations`.
+    pub fn solve(&mut self, sheet: u32, definition: &str, apply: bool) -> Result<String, JsError> {
+        let definition = parse_solver_definition(definition)?;
+        let result = self
+            .model
+            .solve(sheet, &definition, apply)
+            .map_err(WorkbookError::from)?;
+        self.emit_events()?;
+        serde_json::to_string(&result)
+            .map_err(|_| "Could not stringify the solver result to JSON.".to_string())
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    /// Whether cell formulas are written and read with R1C1 references: `=R[-1]C*2`
+    #[wasm_bindgen(js_name = "getR1C1Style")]
+    pub fn get_r1c1_style(&self) -> bool {
+        self.model.get_reference_style() == ReferenceStyle::R1C1
+    }
+
+    #[wasm_bindgen(js_name = "setR1C1Style")]
+    pub fn set_r1c1_style(&mut self, enabled: bool) {
+        let reference_style = if enabled {
+            ReferenceStyle::R1C1
+        } else {
+            ReferenceStyle::A1
+        };
+        self.model.set_reference_style(reference_style);
+    }
+
+    /// Lays out `formula` (starting with `=`) over several lines as if it was in the cell.
+    /// `options` is a JSON object with the `indent` and the `width` of the lines.
+    #[wasm_bindgen(js_name = "formatFormula")]
+    pub fn format_formula(
+        &mut self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        formula: &str,
+        options: Option<String>,
+    ) -> Result<String, JsError> {
+        let options: FormatOptions = match options {
+            Some(options) => serde_json::from_str(&options)
+                .map_err(|_| "Could not parse the format options.".to_string())
+                .map_err(WorkbookError::from)?,
+            None => FormatOptions::default(),
+        };
+        self.model
+            .format_formula(sheet, row, column, formula, Some(&options))
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    /// Prints `formula` (starting with `=`) in one line without whitespace
+    #[wasm_bindgen(js_name = "minifyFormula")]
+    pub fn minify_formula(
+        &mut self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        formula: &str,
+    ) -> Result<String, JsError> {
+        self.model
+            .format_formula(sheet, row, column, formula, None)
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    /// Translates `formula` (starting with `=`) to `language` and the separators of `locale`,
+    /// i.e. `=SUM(A1,0.5)` to `=SUMME(A1;0,5)` for 'de'
+    #[wasm_bindgen(js_name = "localizeFormula")]
+    pub fn localize_formula(
+        &self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        formula: &str,
+        locale: &str,
+        language: &str,
+    ) -> Result<String, JsError> {
+        self.model
+            .localize_formula(sheet, row, column, formula, locale, language)
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    /// Translates `formula` (starting with `=`) written in `language` and with the separators
+    /// of `locale` to English, the way formulas are stored
+    #[wasm_bindgen(js_name = "delocalizeFormula")]
+    pub fn delocalize_formula(
+        &self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        formula: &str,
+        locale: &str,
+        language: &str,
+    ) -> Result<String, JsError> {
+        self.model
+            .delocalize_formula(sheet, row, column, formula, locale, language)
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    /// Evaluates the formula in the cell and returns its parse tree as JSON, each node with
+    /// the `formula` of the sub-expression, its `value` (null if not evaluated) and its `children`
+    #[wasm_bindgen(js_name = "evaluateFormulaSteps")]
+    pub fn evaluate_formula_steps(
+        &mut self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+    ) -> Result<String, JsError> {
+        let steps = self
+            .model
+            .evaluate_formula_steps(CellReference { sheet, row, column })
+            .map_err(WorkbookError::from)?;
+        self.emit_events()?;
+        serde_json::to_string(&steps)
+            .map_err(|_| "Could not stringify the evaluation steps to JSON.".to_string())
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    /// Returns the trace arrows to the cell from the cells it reads as JSON,
+    /// `depth` levels deep (all of them if not given)
+    #[wasm_bindgen(js_name = "getPrecedents")]
+    pub fn get_precedents(
+        &self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        depth: Option<usize>,
+    ) -> Result<String, JsError> {
+        let arrows = self
+            .model
+            .precedents(CellReference { sheet, row, column }, depth)
+            .map_err(WorkbookError::from)?;
+        serde_json::to_string(&arrows)
+            .map_err(|_| "Could not stringify the precedents to JSON.".to_string())
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
+    /// Returns the trace arrows from the cell to the formulas that read it as JSON,
+    /// `depth` levels deep (all of them if not given)
+    #[wasm_bindgen(js_name = "getDependents")]
+    pub fn get_dependents(
+        &self,
+        sheet: u32,
+        row: i32,
+        column: i32,
+        depth: Option<usize>,
+    ) -> Result<String, JsError> {
+        let arrows = self
+            .model
+            .dependents(CellReference { sheet, row, column }, depth)
+            .map_err(WorkbookError::from)?;
+        serde_json::to_string(&arrows)
+            .map_err(|_| "Could not stringify the dependents to JSON.".to_string())
+            .map_err(WorkbookError::from)
+            .map_err(JsError::from)
+    }
+
     #[wasm_bindgen(js_name = "getCellValueByIndex")]
     pub fn get_cell_value_by_index(
         &self,
@@ -427,10 +801,7 @@ impl WasmWorkbook {
         width: f64,
     ) -> Result<(), JsError> {
         self.model
-            .workbook
-            .worksheet_mut(sheet_index)
-            .map_err(WorkbookError::from)?
-            .set_column_width(column, width)
+            .set_column_width(sheet_index, column, width)
             .map_err(WorkbookError::from)
             .map_err(JsError::from)
     }
@@ -443,10 +814,7 @@ impl WasmWorkbook {
         height: f64,
     ) -> Result<(), JsError> {
         self.model
-            .workbook
-            .worksheet_mut(sheet_index)
-            .map_err(WorkbookError::from)?
-            .set_row_height(row, height)
+            .set_row_height(sheet_index, row, height)
             .map_err(WorkbookError::from)
             .map_err(JsError::from)
     }
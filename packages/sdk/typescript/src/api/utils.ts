@@ -38,6 +38,7 @@ export type FormulaToken = {
           | 'BANG' // !
           | 'PERCENT' // %
           | 'AND' // &
+          | 'HASH' // #
           | 'EOF';
       }
     | {